@@ -1,12 +1,33 @@
 use analysis::*;
 use analysis::analysis_location::AnalysisLocation::*;
+use analysis::memory_location::MemoryLocation;
 use il;
 use std::collections::{BTreeMap, BTreeSet};
 
 
+/// Returns true if a `Store` at `def_location` may write memory read by a
+/// `Load` at `use_location`. Without memory locations, every store to an
+/// array reaches every load from it.
+fn may_alias(
+    memory_locations: Option<&BTreeMap<AnalysisLocation, MemoryLocation>>,
+    def_location: &AnalysisLocation,
+    use_location: &AnalysisLocation
+) -> bool {
+    let memory_locations = match memory_locations {
+        Some(memory_locations) => memory_locations,
+        None => return true
+    };
+    match (memory_locations.get(def_location), memory_locations.get(use_location)) {
+        (Some(def_memory), Some(use_memory)) => def_memory.may_alias(use_memory),
+        _ => true
+    }
+}
+
+
 pub fn def_use(
     reaching_definitions: &BTreeMap<AnalysisLocation, Reaches>,
-    control_flow_graph: &il::ControlFlowGraph
+    control_flow_graph: &il::ControlFlowGraph,
+    memory_locations: Option<&BTreeMap<AnalysisLocation, MemoryLocation>>
 ) -> Result<BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>>> {
     let mut du = BTreeMap::new();
 
@@ -45,7 +66,10 @@ pub fn def_use(
               if let Some(variable_written) = def_location
                                           .find(control_flow_graph)?
                                           .variable_written() {
-                  if haystack.contains(&variable_written.multi_var_clone()) {
+                  if haystack.contains(&variable_written.multi_var_clone()) &&
+                     may_alias(memory_locations,
+                               &def_location.clone().into(),
+                               this_location) {
                       du.get_mut(&def_location.clone().into())
                         .unwrap()
                         .insert(this_location.clone());
@@ -62,7 +86,8 @@ pub fn def_use(
 
 pub fn use_def(
     reaching_definitions: &BTreeMap<AnalysisLocation, Reaches>,
-    control_flow_graph: &il::ControlFlowGraph
+    control_flow_graph: &il::ControlFlowGraph,
+    memory_locations: Option<&BTreeMap<AnalysisLocation, MemoryLocation>>
 ) -> Result<BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>>> {
     let mut ud = BTreeMap::new();
 
@@ -101,7 +126,10 @@ pub fn use_def(
               if let Some(variable_written) = def_location
                                           .find(control_flow_graph)?
                                           .variable_written() {
-                  if haystack.contains(&variable_written.multi_var_clone()) {
+                  if haystack.contains(&variable_written.multi_var_clone()) &&
+                     may_alias(memory_locations,
+                               &def_location.clone().into(),
+                               this_location) {
                       ud.get_mut(&this_location.clone())
                         .unwrap()
                         .insert(def_location.clone().into());
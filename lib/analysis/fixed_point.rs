@@ -16,6 +16,26 @@ pub trait FixedPointAnalysis<State: Clone + Debug + PartialEq + Eq> {
 
     /// Given two states, join them into one state.
     fn join(&self, state0: State, state1: &State) -> Result<State>;
+
+    /// Widen a state at a location reached through a back edge.
+    ///
+    /// `previous` is the state last held at this location, and `next` is the
+    /// newly computed state. Domains with infinite ascending chains should
+    /// override this to guarantee termination. By default, `next` is returned
+    /// unchanged.
+    fn widen(&self, _previous: &State, next: State) -> Result<State> {
+        Ok(next)
+    }
+
+    /// Narrow a state at a location reached through a back edge, once a
+    /// fixed point has been reached with widening.
+    ///
+    /// `previous` is the widened state held at this location, and `next` is
+    /// the newly computed state. By default, `previous` is kept, and no
+    /// narrowing takes place.
+    fn narrow(&self, previous: &State, _next: State) -> Result<State> {
+        Ok(previous.clone())
+    }
 }
 
 
@@ -27,7 +47,9 @@ pub trait FixedPointAnalysis<State: Clone + Debug + PartialEq + Eq> {
 struct FPA {
     predecessor_locations: BTreeMap<AnalysisLocation, Vec<AnalysisLocation>>,
     successor_locations: BTreeMap<AnalysisLocation, Vec<AnalysisLocation>>,
-    back_edges: BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>>
+    back_edges: BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>>,
+    /// The position of each location in a reverse postorder traversal.
+    order: BTreeMap<AnalysisLocation, usize>
 }
 
 
@@ -193,16 +215,161 @@ impl FPA {
             pruned_sets
         };
 
+        // Order all locations in reverse postorder, starting from the entry of
+        // the graph, then from locations without predecessors, then from
+        // whatever remains unvisited.
+        trace!("ordering locations");
+        let order = {
+            let mut roots = Vec::new();
+            if let Some(entry) = control_flow_graph.entry() {
+                let block = control_flow_graph.block(entry)
+                                              .ok_or("Could not find entry block")?;
+                roots.push(match block.instructions().first() {
+                    Some(ins) => AnalysisLocation::instruction(entry, ins.index()),
+                    None => AnalysisLocation::empty_block(entry)
+                });
+            }
+            for entry in &predecessor_locations {
+                if entry.1.is_empty() {
+                    roots.push(entry.0.clone());
+                }
+            }
+            for al in predecessor_locations.keys() {
+                roots.push(al.clone());
+            }
+
+            let mut visited: BTreeSet<AnalysisLocation> = BTreeSet::new();
+            let mut postorder: Vec<AnalysisLocation> = Vec::new();
+            for root in roots {
+                if visited.contains(&root) {
+                    continue;
+                }
+                visited.insert(root.clone());
+                let mut stack = vec![(root, 0)];
+                while !stack.is_empty() {
+                    let (al, next) = stack.pop().unwrap();
+                    let successor = successor_locations[&al].get(next).cloned();
+                    match successor {
+                        Some(successor) => {
+                            stack.push((al, next + 1));
+                            if !visited.contains(&successor) {
+                                visited.insert(successor.clone());
+                                stack.push((successor, 0));
+                            }
+                        },
+                        None => postorder.push(al)
+                    }
+                }
+            }
+
+            postorder.into_iter()
+                     .rev()
+                     .enumerate()
+                     .map(|(i, al)| (al, i))
+                     .collect::<BTreeMap<AnalysisLocation, usize>>()
+        };
+
         Ok(FPA {
             predecessor_locations: predecessor_locations,
             successor_locations: successor_locations,
-            back_edges: back_edges
+            back_edges: back_edges,
+            order: order
         })
     }
+
+
+    /// Returns true if the flow from `head` to `tail` follows a back edge.
+    fn is_back_edge(&self, head: &AnalysisLocation, tail: &AnalysisLocation) -> bool {
+        self.back_edges[tail].contains(head) && self.order[head] >= self.order[tail]
+    }
+
+
+    /// Returns true if a forward analysis should widen at this location.
+    fn widen_forward(&self, analysis_location: &AnalysisLocation) -> bool {
+        self.predecessor_locations[analysis_location]
+            .iter()
+            .any(|pred| self.is_back_edge(pred, analysis_location))
+    }
+
+
+    /// Returns true if a backward analysis should widen at this location.
+    fn widen_backward(&self, analysis_location: &AnalysisLocation) -> bool {
+        self.successor_locations[analysis_location]
+            .iter()
+            .any(|succ| self.is_back_edge(analysis_location, succ))
+    }
 }
 
 
+/// A worklist of locations, ordered by their position in reverse postorder.
+struct Worklist<'f> {
+    fpa: &'f FPA,
+    locations: BTreeSet<(usize, AnalysisLocation)>
+}
+
 
+impl<'f> Worklist<'f> {
+    fn new(fpa: &'f FPA) -> Worklist<'f> {
+        Worklist {
+            fpa: fpa,
+            locations: BTreeSet::new()
+        }
+    }
+
+    fn push(&mut self, analysis_location: &AnalysisLocation) {
+        let order = self.fpa.order[analysis_location];
+        self.locations.insert((order, analysis_location.clone()));
+    }
+
+    /// Removes the location earliest in reverse postorder.
+    fn pop_first(&mut self) -> Option<AnalysisLocation> {
+        let first = self.locations.iter().next().cloned();
+        first.map(|first| {
+            self.locations.remove(&first);
+            first.1
+        })
+    }
+
+    /// Removes the location latest in reverse postorder.
+    fn pop_last(&mut self) -> Option<AnalysisLocation> {
+        let last = self.locations.iter().next_back().cloned();
+        last.map(|last| {
+            self.locations.remove(&last);
+            last.1
+        })
+    }
+}
+
+
+/// Join the states held at the given locations.
+fn join_states<Analysis, State>(
+    analysis: &Analysis,
+    states: &BTreeMap<AnalysisLocation, State>,
+    analysis_locations: &[AnalysisLocation]
+) -> Result<Option<State>>
+where Analysis: FixedPointAnalysis<State>, State: Clone + Debug + PartialEq + Eq {
+
+    let mut in_state = None;
+    for analysis_location in analysis_locations {
+        if let Some(state) = states.get(analysis_location) {
+            if let Some(in_state_) = in_state {
+                in_state = Some(analysis.join(in_state_, state)?);
+            }
+            else {
+                in_state = Some(state.clone());
+            }
+        }
+    }
+    Ok(in_state)
+}
+
+
+
+/// Run a forward fixed-point analysis over a `ControlFlowGraph`.
+///
+/// Locations are visited in reverse postorder. States at locations reached
+/// through a back edge are widened until a fixed point is reached, and then
+/// narrowed.
 pub fn fixed_point_forward<Analysis, State>(
     analysis: &Analysis,
     control_flow_graph: &il::ControlFlowGraph
@@ -210,80 +377,71 @@ pub fn fixed_point_forward<Analysis, State>(
 where Analysis: FixedPointAnalysis<State>, State: Clone + Debug + PartialEq + Eq {
 
     let mut states: BTreeMap<AnalysisLocation, State> = BTreeMap::new();
-    let mut queue: VecDeque<AnalysisLocation> = VecDeque::new();
 
     let fpa = FPA::new(control_flow_graph)?;
+    let mut queue = Worklist::new(&fpa);
 
-    for al in fpa.back_edges.keys() {
-        queue.push_back(al.clone());
+    for al in fpa.order.keys() {
+        queue.push(al);
     }
 
-    // for every instruction in the queue
-    while !queue.is_empty() {
-        let analysis_location = queue.pop_front().unwrap();
+    // Ascending phase, widening at back edges
+    while let Some(analysis_location) = queue.pop_first() {
+        let in_state = join_states(
+            analysis,
+            &states,
+            &fpa.predecessor_locations[&analysis_location]
+        )?;
 
-        let predlocs = match fpa.predecessor_locations.get(&analysis_location) {
-            Some(predlocs) => predlocs,
-            None => bail!(
-                "failed to get predecessor_locations for {}",
-                analysis_location
-            )
-        };
+        let mut out_state = analysis.trans(&analysis_location, &in_state)?;
 
-        // join states of the last instruction of all predecessors to this block
-        let out_state = {
-            let mut in_state = None;
-            for analysis_location in predlocs {
-                if let Some(state) = states.get(analysis_location) {
-                    if let Some(in_state_) = in_state {
-                        in_state = Some(analysis.join(in_state_, state)?);
-                    }
-                    else {
-                        in_state = Some(state.clone());
-                    }
-                }
+        if let Some(state) = states.get(&analysis_location) {
+            if fpa.widen_forward(&analysis_location) {
+                out_state = analysis.widen(state, out_state)?;
             }
-
-            analysis.trans(&analysis_location, &in_state)?
-        };
-
-        if let Some(in_state) = states.get(&analysis_location) {
-            if out_state == *in_state {
-                // If we have successors reachable via back edges, we add all
-                // successors not reachable by back edges
-                if fpa.back_edges[&analysis_location].is_empty() {
-                    continue;
-                }
-                for successor_location in &fpa.successor_locations[&analysis_location] {
-                    if    !fpa.back_edges[&analysis_location].contains(successor_location)
-                       && !queue.contains(successor_location) {
-                        queue.push_back(successor_location.clone());
-                    }
-                }
+            if out_state == *state {
                 continue;
             }
         }
 
         states.insert(analysis_location.clone(), out_state);
 
-        // If we have successors reachable via back edges, we only add
-        // those successors
-        if !fpa.back_edges[&analysis_location].is_empty() {
-            for successor_location in &fpa.successor_locations[&analysis_location] {
-                if    fpa.back_edges[&analysis_location].contains(successor_location)
-                   && !queue.contains(successor_location) {
-                    queue.push_back(successor_location.clone());
-                }
-            }
+        for successor_location in &fpa.successor_locations[&analysis_location] {
+            queue.push(successor_location);
         }
-        // If we don't have any back edges, we add all successors
-        else {
-            for successor_location in &fpa.successor_locations[&analysis_location] {
-                if !queue.contains(successor_location) {
-                    queue.push_back(successor_location.clone());
-                }
+    }
+
+    // Descending phase, narrowing at back edges. Every other location is
+    // already stable, so we only need to begin from the widened locations.
+    for al in fpa.order.keys() {
+        if fpa.widen_forward(al) {
+            queue.push(al);
+        }
+    }
+
+    while let Some(analysis_location) = queue.pop_first() {
+        let in_state = join_states(
+            analysis,
+            &states,
+            &fpa.predecessor_locations[&analysis_location]
+        )?;
+
+        let mut out_state = analysis.trans(&analysis_location, &in_state)?;
+
+        if let Some(state) = states.get(&analysis_location) {
+            if fpa.widen_forward(&analysis_location) {
+                out_state = analysis.narrow(state, out_state)?;
+            }
+            if out_state == *state {
+                continue;
             }
         }
+
+        states.insert(analysis_location.clone(), out_state);
+
+        for successor_location in &fpa.successor_locations[&analysis_location] {
+            queue.push(successor_location);
+        }
     }
 
     Ok(states)
@@ -291,6 +449,38 @@ where Analysis: FixedPointAnalysis<State>, State: Clone + Debug + PartialEq + Eq
 
 
 
+/// Given the results of `fixed_point_forward`, find the state passed to
+/// `trans` at every location, which is the join of the states of its
+/// predecessors.
+///
+/// Locations with no predecessors, or whose predecessors hold no state, are
+/// not included.
+pub fn forward_states_in<Analysis, State>(
+    analysis: &Analysis,
+    control_flow_graph: &il::ControlFlowGraph,
+    states: &BTreeMap<AnalysisLocation, State>
+) -> Result<BTreeMap<AnalysisLocation, State>>
+where Analysis: FixedPointAnalysis<State>, State: Clone + Debug + PartialEq + Eq {
+
+    let fpa = FPA::new(control_flow_graph)?;
+
+    let mut states_in = BTreeMap::new();
+    for (analysis_location, predecessor_locations) in &fpa.predecessor_locations {
+        if let Some(state) = join_states(analysis, states, predecessor_locations)? {
+            states_in.insert(analysis_location.clone(), state);
+        }
+    }
+
+    Ok(states_in)
+}
+
+
+
+/// Run a backward fixed-point analysis over a `ControlFlowGraph`.
+///
+/// Locations are visited in postorder. States at locations which leave
+/// through a back edge are widened until a fixed point is reached, and then
+/// narrowed.
 pub fn fixed_point_backward<Analysis, State>(
     analysis: &Analysis,
     control_flow_graph: &il::ControlFlowGraph
@@ -298,57 +488,69 @@ pub fn fixed_point_backward<Analysis, State>(
 where Analysis: FixedPointAnalysis<State>, State: Clone + Debug + PartialEq + Eq {
 
     let mut states: BTreeMap<AnalysisLocation, State> = BTreeMap::new();
-    let mut queue: VecDeque<AnalysisLocation> = VecDeque::new();
 
     let fpa = FPA::new(control_flow_graph)?;
+    let mut queue = Worklist::new(&fpa);
 
-    for al in fpa.back_edges.keys() {
-        queue.push_back(al.clone());
+    for al in fpa.order.keys() {
+        queue.push(al);
     }
 
-    // for every instruction in the queue
-    while !queue.is_empty() {
-        let analysis_location = queue.pop_front().unwrap();
+    // Ascending phase, widening at back edges
+    while let Some(analysis_location) = queue.pop_last() {
+        let in_state = join_states(
+            analysis,
+            &states,
+            &fpa.successor_locations[&analysis_location]
+        )?;
 
-        let succlocs = match fpa.successor_locations.get(&analysis_location) {
-            Some(succlocs) => succlocs,
-            None => bail!(
-                "failed to get predecessor_locations for {}",
-                analysis_location
-            )
-        };
+        let mut out_state = analysis.trans(&analysis_location, &in_state)?;
 
-        // join states of the successors to this block
-        let out_state = {
-            let mut in_state = None;
-            for analysis_location in succlocs {
-                if let Some(state) = states.get(analysis_location) {
-                    if let Some(in_state_) = in_state {
-                        in_state = Some(analysis.join(in_state_, state)?);
-                    }
-                    else {
-                        in_state = Some(state.clone());
-                    }
-                }
+        if let Some(state) = states.get(&analysis_location) {
+            if fpa.widen_backward(&analysis_location) {
+                out_state = analysis.widen(state, out_state)?;
             }
+            if out_state == *state {
+                continue;
+            }
+        }
 
-            analysis.trans(&analysis_location, &in_state)?
-        };
+        states.insert(analysis_location.clone(), out_state);
+
+        for predecessor_location in &fpa.predecessor_locations[&analysis_location] {
+            queue.push(predecessor_location);
+        }
+    }
+
+    // Descending phase, narrowing at back edges
+    for al in fpa.order.keys() {
+        if fpa.widen_backward(al) {
+            queue.push(al);
+        }
+    }
+
+    while let Some(analysis_location) = queue.pop_last() {
+        let in_state = join_states(
+            analysis,
+            &states,
+            &fpa.successor_locations[&analysis_location]
+        )?;
+
+        let mut out_state = analysis.trans(&analysis_location, &in_state)?;
 
-        if let Some(in_state) = states.get(&analysis_location) {
-            if out_state == *in_state {
+        if let Some(state) = states.get(&analysis_location) {
+            if fpa.widen_backward(&analysis_location) {
+                out_state = analysis.narrow(state, out_state)?;
+            }
+            if out_state == *state {
                 continue;
             }
         }
 
-        info!("adding predecessors");
-
         states.insert(analysis_location.clone(), out_state);
 
         for predecessor_location in &fpa.predecessor_locations[&analysis_location] {
-            if !queue.contains(predecessor_location) {
-                queue.push_back(predecessor_location.clone());
-            }
+            queue.push(predecessor_location);
         }
     }
 
@@ -1,5 +1,15 @@
 //! A lattice for tracking `il::Constant` values.
-
+//!
+//! Values are held as small sets of constants. Depending on the `Domain`,
+//! sets which grow too large are either abandoned as `LatticeValue::Join`, or
+//! approximated as a `StridedInterval`.
+//!
+//! Pointers into memory with no known address, such as the stack, are held
+//! as offsets into an abstract `Region`, and each `Region` has its own
+//! `LatticeMemory`.
+
+use analysis::analysis_location::InstructionLocation;
+use analysis::strided_interval::StridedInterval;
 use error::*;
 use executor;
 use il;
@@ -10,11 +20,45 @@ use std::fmt;
 use std::ops::BitOr;
 
 
+/// The abstract domain used for values which hold too many constants.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Domain {
+    /// Values holding too many constants become `LatticeValue::Join`.
+    Constants,
+    /// Values holding too many constants become `LatticeValue::Interval`.
+    StridedIntervals
+}
+
+
+/// An abstract region of memory, with an unknown base address.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Region {
+    /// The stack of the function under analysis, based at the value of the
+    /// stack pointer on entry.
+    Stack,
+    /// Memory allocated by the call at this location.
+    Heap(InstructionLocation)
+}
+
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Region::Stack => write!(f, "stack"),
+            Region::Heap(ref location) => write!(f, "heap@{}", location)
+        }
+    }
+}
+
+
 /// A lattice of `il::Constant` values
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum LatticeValue {
     Join, // Top
     Values(BTreeSet<il::Constant>),
+    Interval(StridedInterval),
+    /// Offsets from the base of a `Region`
+    Pointer(Region, StridedInterval),
     Meet // Bottom
 }
 
@@ -41,13 +85,156 @@ impl LatticeValue {
                         }
                         LatticeValue::Values(lhs.bitor(rhs))
                     },
+                    Interval(ref rhs) => match StridedInterval::from_constants(&lhs) {
+                        Some(lhs) => LatticeValue::from_interval(lhs.join(rhs)),
+                        None => other.clone()
+                    },
+                    Pointer(_, _) => Join,
                     Meet => Values(lhs)
                 }
             },
+            Interval(lhs) => {
+                match *other {
+                    Join |
+                    Pointer(_, _) => Join,
+                    Values(ref rhs) => match StridedInterval::from_constants(rhs) {
+                        Some(rhs) => LatticeValue::from_interval(lhs.join(&rhs)),
+                        None => Interval(lhs)
+                    },
+                    Interval(ref rhs) => LatticeValue::from_interval(lhs.join(rhs)),
+                    Meet => Interval(lhs)
+                }
+            },
+            Pointer(region, lhs) => {
+                match *other {
+                    Pointer(ref rhs_region, ref rhs) if *rhs_region == region =>
+                        Pointer(region, lhs.join(rhs)),
+                    Meet => Pointer(region, lhs),
+                    _ => Join
+                }
+            },
             Meet => other.clone()
         }
     }
 
+    /// Widens this `LatticeValue` with the next value computed for the same
+    /// location, by widening both as `StridedInterval`s.
+    pub fn widen(&self, next: &LatticeValue) -> LatticeValue {
+        let bits = match next.bits().or(self.bits()) {
+            Some(bits) => bits,
+            None => return self.clone().join(next)
+        };
+        if *self == *next {
+            return next.clone();
+        }
+        if let (&Pointer(ref region, ref previous), &Pointer(ref next_region, ref next)) =
+               (self, next) {
+            if region == next_region {
+                return Pointer(region.clone(), previous.widen(next));
+            }
+        }
+        match (self.interval(bits), next.interval(bits)) {
+            (Some(previous), Some(next)) =>
+                LatticeValue::from_interval(previous.widen(&next)),
+            _ => self.clone().join(next)
+        }
+    }
+
+    /// Narrows this widened `LatticeValue` with the next value computed for
+    /// the same location.
+    pub fn narrow(&self, next: &LatticeValue) -> LatticeValue {
+        let bits = match next.bits() {
+            Some(bits) => bits,
+            None => return self.clone()
+        };
+        match *self {
+            Join |
+            Interval(_) => match (self.interval(bits), next.interval(bits)) {
+                (Some(previous), Some(next)) =>
+                    LatticeValue::from_interval(previous.narrow(&next)),
+                _ => self.clone()
+            },
+            Pointer(ref region, ref previous) => match *next {
+                Pointer(ref next_region, ref next) if next_region == region =>
+                    Pointer(region.clone(), previous.narrow(next)),
+                _ => self.clone()
+            },
+            Values(_) |
+            Meet => self.clone()
+        }
+    }
+
+    /// Returns the `StridedInterval` holding every value of this
+    /// `LatticeValue`, or `None` if this `LatticeValue` is `Meet`.
+    ///
+    /// `bits` is the bit-width used when this `LatticeValue` is `Join`. The
+    /// address held by a `Pointer` is unknown, so a `Pointer` may be any value.
+    pub fn interval(&self, bits: usize) -> Option<StridedInterval> {
+        match *self {
+            Join => Some(StridedInterval::top(bits)),
+            Pointer(_, _) => Some(StridedInterval::top(bits)),
+            Values(ref values) => StridedInterval::from_constants(values),
+            Interval(ref interval) => Some(interval.clone()),
+            Meet => None
+        }
+    }
+
+    /// Creates a `LatticeValue` from a `StridedInterval`, which is
+    /// `LatticeValue::Join` if the interval holds every value.
+    pub fn from_interval(interval: StridedInterval) -> LatticeValue {
+        if interval.is_top() {
+            Join
+        }
+        else {
+            Interval(interval)
+        }
+    }
+
+    /// Ensures this `LatticeValue` holds no more than `max` constants, by
+    /// approximating it in the given `Domain`.
+    ///
+    /// Intervals which hold no more than `max` constants are turned back into
+    /// `LatticeValue::Values`.
+    pub fn limit(self, max: usize, domain: Domain) -> LatticeValue {
+        match self {
+            Values(values) => {
+                if values.len() <= max {
+                    Values(values)
+                }
+                else if domain == Domain::StridedIntervals {
+                    match StridedInterval::from_constants(&values) {
+                        Some(interval) => LatticeValue::from_interval(interval),
+                        None => Join
+                    }
+                }
+                else {
+                    Join
+                }
+            },
+            Interval(interval) => {
+                if interval.count() <= max as u64 {
+                    Values(interval.constants().into_iter().collect())
+                }
+                else if domain == Domain::StridedIntervals {
+                    Interval(interval)
+                }
+                else {
+                    Join
+                }
+            },
+            Pointer(region, offsets) => {
+                if domain == Domain::Constants && offsets.count() > max as u64 {
+                    Join
+                }
+                else {
+                    Pointer(region, offsets)
+                }
+            },
+            Join => Join,
+            Meet => Meet
+        }
+    }
+
     /// Attempts to figure out the bit-size of this LatticeValue by returning
     /// the bit-size of the first il::Constant value, or None if Join/Meet
     pub fn bits(&self) -> Option<usize> {
@@ -57,7 +244,9 @@ impl LatticeValue {
             Values(ref v) => match v.iter().next() {
                 Some(c) => Some(c.bits()),
                 None => None
-            }
+            },
+            Interval(ref interval) |
+            Pointer(_, ref interval) => Some(interval.bits())
         }
     }
 
@@ -85,6 +274,17 @@ impl LatticeValue {
                     swapped.insert(const_);
                 }
                 Ok(LatticeValue::Values(swapped))
+            },
+            // The bytes of a pointer are unknown, and only ever loaded whole
+            Pointer(_, _) => Ok(self.clone()),
+            // Only intervals of single bytes keep their values when swapped
+            Interval(ref interval) => {
+                if interval.bits() <= 8 {
+                    Ok(self.clone())
+                }
+                else {
+                    Ok(Join)
+                }
             }
         }
     }
@@ -103,6 +303,27 @@ impl Ord for LatticeValue {
                 match *other {
                     Join => Ordering::Greater,
                     Values(ref other_values) => values.cmp(other_values),
+                    Interval(_) |
+                    Pointer(_, _) |
+                    Meet => Ordering::Less
+                }
+            },
+            Interval(ref interval) => {
+                match *other {
+                    Join |
+                    Values(_) => Ordering::Greater,
+                    Interval(ref other_interval) => interval.cmp(other_interval),
+                    Pointer(_, _) |
+                    Meet => Ordering::Less
+                }
+            },
+            Pointer(ref region, ref offsets) => {
+                match *other {
+                    Join |
+                    Values(_) |
+                    Interval(_) => Ordering::Greater,
+                    Pointer(ref other_region, ref other_offsets) =>
+                        (region, offsets).cmp(&(other_region, other_offsets)),
                     Meet => Ordering::Less
                 }
             },
@@ -132,6 +353,8 @@ impl fmt::Display for LatticeValue {
                                      .collect::<Vec<String>>()
                                      .join(", "))
             },
+            Interval(ref interval) => write!(f, "{}", interval),
+            Pointer(ref region, ref offsets) => write!(f, "{}+{}", region, offsets),
             Meet => write!(f, "Meet")
         }
     }
@@ -142,10 +365,14 @@ impl fmt::Display for LatticeValue {
 fn lattice_value_binop<F>(lhs: &LatticeValue, rhs: &LatticeValue, op: F) -> LatticeValue
 where F: Fn(il::Constant, il::Constant) -> Expression {
     match *lhs {
-        Join => LatticeValue::Join,
+        Join |
+        Interval(_) |
+        Pointer(_, _) => LatticeValue::Join,
         Values(ref lhs_) => {
             match *rhs {
-                Join => LatticeValue::Join,
+                Join |
+                Interval(_) |
+                Pointer(_, _) => LatticeValue::Join,
                 Values(ref rhs_) => {
                     let mut sum = BTreeSet::new();
                     for l in lhs_.iter() {
@@ -171,6 +398,8 @@ where F: Fn(il::Constant) -> Expression {
     match *rhs {
         Join |
         Meet => rhs.clone(),
+        Interval(_) |
+        Pointer(_, _) => Join,
         Values(ref rhs_) => {
             let mut sum = BTreeSet::new();
             for r in rhs_ {
@@ -228,6 +457,8 @@ impl LatticeMemoryValue {
         lmv.value = match self.value {
             Join => Join,
             Meet => Meet,
+            Interval(ref interval) => LatticeValue::from_interval(interval.trun(bits)),
+            Pointer(_, _) => Join,
             Values(_) => {
                 lattice_extend_op(&self.value, |rhs: il::Constant| {
                     Expression::trun(bits, rhs.into()).unwrap()
@@ -245,7 +476,9 @@ impl LatticeMemoryValue {
         let mut lmv = LatticeMemoryValue::new(bits);
 
         lmv.value = match self.value {
-            Join => Join,
+            Join |
+            Interval(_) |
+            Pointer(_, _) => Join,
             Meet => Meet,
             Values(_) => {
                 let lv = lattice_value_binop(
@@ -272,11 +505,15 @@ impl LatticeMemoryValue {
         let mut lmv = LatticeMemoryValue::new(self.bits + other.bits());
 
         lmv.value = match self.value {
-            Join => Join,
+            Join |
+            Interval(_) |
+            Pointer(_, _) => Join,
             Meet => Meet,
             Values(_) => {
                 match other.value {
-                    Join => Join,
+                    Join |
+                    Interval(_) |
+                    Pointer(_, _) => Join,
                     Meet => Meet,
                     Values(_) => {
                         // zext both lhs and rhs to the sum bit width
@@ -319,22 +556,48 @@ impl fmt::Display for LatticeMemoryValue {
 
 
 /// A mapping of addresses to values
+///
+/// Addresses given as a `LatticeValue::Pointer` are offsets into the
+/// `LatticeMemory` of that pointer's `Region`.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct LatticeMemory {
-    memory: BTreeMap<u64, LatticeMemoryValue>
+    memory: BTreeMap<u64, LatticeMemoryValue>,
+    regions: BTreeMap<Region, LatticeMemory>,
+    domain: Domain
 }
 
 
 impl LatticeMemory {
     pub fn new() -> LatticeMemory {
+        LatticeMemory::new_with_domain(Domain::Constants)
+    }
+
+    pub fn new_with_domain(domain: Domain) -> LatticeMemory {
         LatticeMemory {
-            memory: BTreeMap::new()
+            memory: BTreeMap::new(),
+            regions: BTreeMap::new(),
+            domain: domain
         }
     }
 
 
+    /// Get the `LatticeMemory` for a `Region`, if anything has been stored
+    /// in that `Region`.
+    pub fn region(&self, region: &Region) -> Option<&LatticeMemory> {
+        self.regions.get(region)
+    }
+
+
     /// Joins two `LatticeMemory`s together
     pub fn join(mut self, other: &LatticeMemory, max: usize) -> LatticeMemory {
+        for (region, other_memory) in &other.regions {
+            let memory = match self.regions.remove(region) {
+                Some(memory) => memory.join(other_memory, max),
+                None => other_memory.clone()
+            };
+            self.regions.insert(region.clone(), memory);
+        }
+
         for entry in &other.memory {
             let address = entry.0.clone();
             let lmv = entry.1;
@@ -386,15 +649,7 @@ impl LatticeMemory {
                         // Join
                         let lv = lmv.value.clone();
                         let lv = lv.join(&forward_lv);
-                        let lv = match lv {
-                            Join => Join,
-                            Meet => Meet,
-                            Values(values) => if values.len() > max {
-                                Join
-                            } else {
-                                Values(values)
-                            }
-                        };
+                        let lv = lv.limit(max, self.domain);
                         lmv = LatticeMemoryValue::new_with_value(lmv.bits(), lv);
 
                         // Add this forward_lmv to the remove set
@@ -444,15 +699,7 @@ impl LatticeMemory {
                         // Join
                         let lv = lmv.value.clone();
                         let lv = lv.join(&join_forward_lv);
-                        let lv = match lv {
-                            Join => Join,
-                            Meet => Meet,
-                            Values(values) => if values.len() > max {
-                                Join
-                            } else {
-                                Values(values)
-                            }
-                        };
+                        let lv = lv.limit(max, self.domain);
                         lmv = LatticeMemoryValue::new_with_value(lmv.bits(), lv);
 
                         // Now we need to extract the portion that comes after
@@ -486,7 +733,7 @@ impl LatticeMemory {
                 let mut insert: Vec<(u64, LatticeMemoryValue)> = Vec::new();
                 let mut remove: Vec<u64> = Vec::new();
 
-                let previous = self.memory.range((address - 32)..address);
+                let previous = self.memory.range(address.saturating_sub(32)..address);
 
                 if let Some(previous) = previous.last() {
                     let pre_addr = previous.0.clone();
@@ -527,7 +774,7 @@ impl LatticeMemory {
 
                         // Combine this with the lmv
                         let lv = lmv.value.clone();
-                        let lv = lv.join(&pre_lv);
+                        let lv = lv.join(&pre_lv).limit(max, self.domain);
                         lmv = LatticeMemoryValue::new_with_value(lmv.bits(), lv);
 
                         (lmv, insert, remove)
@@ -577,22 +824,70 @@ impl LatticeMemory {
         match *address {
             Join |
             Meet => {}, // TODO is this the right thing to do here?
-            Values(ref addresses) => {
-                // If we just create a new LatticeMemory, and join it with ourself,
-                // this is effectively a store.
-                let mut lmv = LatticeMemory::new();
-                for addr in addresses {
-                    lmv.memory.insert(
-                        addr.value() as u64,
-                        LatticeMemoryValue::new_with_value(bits, value.clone())
-                    );
-                }
-                *self = self.clone().join(&lmv, max);
+            Pointer(ref region, ref offsets) => {
+                let domain = self.domain;
+                self.regions
+                    .entry(region.clone())
+                    .or_insert_with(|| LatticeMemory::new_with_domain(domain))
+                    .store(&Interval(offsets.clone()), value, bits, max);
+            },
+            Values(ref addresses) => self.store_addresses(addresses, value, bits, max),
+            Interval(ref interval) => {
+                if interval.count() <= max as u64 {
+                    self.store_addresses(&interval.constants(), value, bits, max);
+                }
+                else {
+                    // Too many addresses to store to individually. Every value
+                    // this store may overlap becomes Join.
+                    let bytes = LatticeMemoryValue::new(bits).bytes() as u64;
+                    let lo = interval.lo();
+                    let hi = interval.hi().saturating_add(bytes);
+                    for (address, lmv) in self.memory.iter_mut() {
+                        if *address < hi && address + (lmv.bytes() as u64) > lo {
+                            lmv.value = Join;
+                        }
+                    }
+                }
             }
         }
     }
 
 
+    /// Helper function to store a value at each of the given addresses.
+    fn store_addresses<'a, I>(
+        &mut self,
+        addresses: I,
+        value: LatticeValue,
+        bits: usize,
+        max: usize
+    ) where I: IntoIterator<Item=&'a il::Constant> {
+        // If we just create a new LatticeMemory, and join it with ourself,
+        // this is effectively a store.
+        let mut lmv = LatticeMemory::new_with_domain(self.domain);
+        for addr in addresses {
+            lmv.memory.insert(
+                addr.value() as u64,
+                LatticeMemoryValue::new_with_value(bits, value.clone())
+            );
+        }
+
+        // A store to exactly one address replaces a value of the same size
+        // held there.
+        if lmv.memory.len() == 1 {
+            let address = *lmv.memory.keys().next().unwrap();
+            let replace = match self.memory.get(&address) {
+                Some(previous) => previous.bits() == bits,
+                None => false
+            };
+            if replace {
+                self.memory.remove(&address);
+            }
+        }
+
+        *self = self.clone().join(&lmv, max);
+    }
+
+
     /// Helper function to store a LatticeMemoryValue at the given address.
     ///
     /// If there are not enough values in memory to create a complete value
@@ -617,7 +912,7 @@ impl LatticeMemory {
         // at a previous address
         if lmv_result.is_none() {
             // Search up to 32 bytes back
-            let previous = self.memory.range((address - 32)..address);
+            let previous = self.memory.range(address.saturating_sub(32)..address);
 
             if let Some(previous) = previous.last() {
                 let addr = previous.0;
@@ -671,29 +966,57 @@ impl LatticeMemory {
 
     /// Load a value from the `LatticeMemory`. The value returned will be
     /// big-endian. If you need a little-endian value, swap it after the load.
+    ///
+    /// `max` is the maximum number of addresses an interval address will be
+    /// split into before all values it may overlap are loaded as Join.
     pub fn load(
         &mut self,
         address: &LatticeValue,
-        bits: usize
+        bits: usize,
+        max: usize
     ) -> Option<LatticeValue> {
-        let mut lv_result: Option<LatticeValue> = None;
-
         match *address {
             Join |
-            Meet => {}, // TODO is this the right thing to do here?
-            Values(ref addresses) => {
-                for addr in addresses {
-                    let address_u64 = addr.value();
-                    let lv = self.load_(address_u64, bits);
-                    if let Some(lv) = lv {
-                        match lv_result {
-                            Some(lvr) => {
-                                lv_result = Some(lvr.join(&lv));
-                            },
-                            None => {
-                                lv_result = Some(lv);
-                            }
-                        }
+            Meet => None, // TODO is this the right thing to do here?
+            Pointer(ref region, ref offsets) => match self.regions.get_mut(region) {
+                Some(memory) => memory.load(&Interval(offsets.clone()), bits, max),
+                None => None
+            },
+            Values(ref addresses) => self.load_addresses(addresses, bits),
+            Interval(ref interval) => {
+                if interval.count() <= max as u64 {
+                    self.load_addresses(&interval.constants(), bits)
+                }
+                else {
+                    let bytes = LatticeMemoryValue::new(bits).bytes() as u64;
+                    let lo = interval.lo();
+                    let hi = interval.hi().saturating_add(bytes);
+                    let overlaps = self.memory.iter().any(|(address, lmv)| {
+                        *address < hi && address + (lmv.bytes() as u64) > lo
+                    });
+                    if overlaps { Some(Join) } else { None }
+                }
+            }
+        }
+    }
+
+
+    /// Helper function to load and join the values at each of the given
+    /// addresses.
+    fn load_addresses<'a, I>(&self, addresses: I, bits: usize) -> Option<LatticeValue>
+    where I: IntoIterator<Item=&'a il::Constant> {
+        let mut lv_result: Option<LatticeValue> = None;
+
+        for addr in addresses {
+            let address_u64 = addr.value();
+            let lv = self.load_(address_u64, bits);
+            if let Some(lv) = lv {
+                match lv_result {
+                    Some(lvr) => {
+                        lv_result = Some(lvr.join(&lv));
+                    },
+                    None => {
+                        lv_result = Some(lv);
                     }
                 }
             }
@@ -701,6 +1024,26 @@ impl LatticeMemory {
 
         lv_result
     }
+
+
+    /// Widens the values of this `LatticeMemory` with the values at the same
+    /// addresses in the next `LatticeMemory` computed for the same location.
+    pub fn widen(&self, next: &LatticeMemory) -> LatticeMemory {
+        let mut memory = next.clone();
+        for (region, region_memory) in &self.regions {
+            if let Some(next_memory) = next.regions.get(region) {
+                memory.regions.insert(region.clone(), region_memory.widen(next_memory));
+            }
+        }
+        for (address, lmv) in &self.memory {
+            if let Some(next_lmv) = memory.memory.get_mut(address) {
+                if next_lmv.bits() == lmv.bits() {
+                    next_lmv.value = lmv.value.widen(&next_lmv.value);
+                }
+            }
+        }
+        memory
+    }
 }
 
 
@@ -711,7 +1054,9 @@ pub struct LatticeAssignments {
     memory: LatticeMemory,
     /// The max number of elements for each LatticeValue before converting it
     /// to Join
-    max: usize
+    max: usize,
+    /// The domain values are approximated in once they exceed `max`
+    domain: Domain
 }
 
 
@@ -721,10 +1066,19 @@ impl LatticeAssignments {
     /// `max` is the maximum number of values a `LatticeValue::Values`s will
     /// hold before being transformed into `LatticeValue::Join`
     pub fn new(max: usize) -> LatticeAssignments {
+        LatticeAssignments::new_with_domain(max, Domain::Constants)
+    }
+
+    /// Creates a new LatticeAssignments
+    ///
+    /// `max` is the maximum number of values a `LatticeValue::Values`s will
+    /// hold before being approximated in the given `Domain`.
+    pub fn new_with_domain(max: usize, domain: Domain) -> LatticeAssignments {
         LatticeAssignments {
             scalars: BTreeMap::new(),
-            memory: LatticeMemory::new(),
-            max: max
+            memory: LatticeMemory::new_with_domain(domain),
+            max: max,
+            domain: domain
         }
     }
 
@@ -738,6 +1092,11 @@ impl LatticeAssignments {
         self.max
     }
 
+    /// Get the `Domain` values are approximated in
+    pub fn domain(&self) -> Domain {
+        self.domain
+    }
+
     pub fn join(mut self, other: &LatticeAssignments) -> LatticeAssignments {
         // for every assignment in the other LatticeAssignment
         for assignment in &other.scalars {
@@ -748,19 +1107,7 @@ impl LatticeAssignments {
             if let Some(lv) = self.scalars.get(scalar) {
                 // Join the two values
                 let lv = lv.clone().join(&lattice_value);
-                // If the join is a vlue (not Meet/Join)
-                lattice_value = match lv {
-                    Join => Join,
-                    Meet => Meet,
-                    Values(values) => {
-                        if values.len() > self.max {
-                            Join
-                        }
-                        else {
-                            Values(values)
-                        }
-                    }
-                }
+                lattice_value = lv.limit(self.max, self.domain);
             }
 
             self.scalars.insert(scalar.clone(), lattice_value);
@@ -771,6 +1118,47 @@ impl LatticeAssignments {
         self
     }
 
+    /// Widens this `LatticeAssignments` with the next `LatticeAssignments`
+    /// computed for the same location.
+    ///
+    /// Widening only takes place in the `StridedIntervals` domain.
+    pub fn widen(&self, next: LatticeAssignments) -> LatticeAssignments {
+        if self.domain != Domain::StridedIntervals {
+            return next;
+        }
+
+        let mut widened = next;
+        for (scalar, lattice_value) in &self.scalars {
+            let lattice_value = match widened.scalars.get(scalar) {
+                Some(next) => lattice_value.widen(next),
+                None => lattice_value.clone()
+            };
+            widened.scalars.insert(scalar.clone(), lattice_value);
+        }
+        widened.memory = self.memory.widen(&widened.memory);
+        widened
+    }
+
+    /// Narrows this widened `LatticeAssignments` with the next
+    /// `LatticeAssignments` computed for the same location.
+    ///
+    /// Only scalars are narrowed, and only in the `StridedIntervals` domain.
+    pub fn narrow(&self, next: LatticeAssignments) -> LatticeAssignments {
+        let mut narrowed = self.clone();
+        if self.domain != Domain::StridedIntervals {
+            return narrowed;
+        }
+
+        for (scalar, lattice_value) in &self.scalars {
+            if let Some(next) = next.scalars.get(scalar) {
+                let lattice_value = lattice_value.narrow(next)
+                                                 .limit(self.max, self.domain);
+                narrowed.scalars.insert(scalar.clone(), lattice_value);
+            }
+        }
+        narrowed
+    }
+
     /// Set the `LatticeValue` for an `il::Scalar`
     pub fn set(&mut self, scalar: il::Scalar, value: LatticeValue) {
         self.scalars.insert(scalar, value);
@@ -790,200 +1178,371 @@ impl LatticeAssignments {
         address: &LatticeValue,
         bits: usize
     ) -> Option<LatticeValue> {
-        self.memory.load(address, bits)
+        self.memory.load(address, bits, self.max)
     }
 
     /// Evaluates an `il::Expression`, using the values in this
     /// `LatticeAssignments` for scalars.
     pub fn eval(&self, expr: &Expression) -> LatticeValue {
-        let lattice_value = self.eval_(expr);
-        if let Values(ref values) = lattice_value {
-            if values.len() > self.max {
-                return LatticeValue::Join;
-            }
-        }
-        lattice_value
+        self.eval_(expr).limit(self.max, self.domain)
     }
 
-    fn eval_(&self, expr: &Expression) -> LatticeValue {
-        match *expr {
-
-            Expression::Scalar(ref scalar) => {
-                match self.scalars.get(scalar) {
-                    Some(lattice_value) => lattice_value.clone(),
-                    None => LatticeValue::Meet
-                }
-            },
-
-            Expression::Constant(ref constant) =>
-                LatticeValue::value(constant.clone()),
-
-            Expression::Add(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::add(lhs.into(), rhs.into()).unwrap()
-                )
-            },
-
-            Expression::Sub(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::sub(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+    /// Evaluates a binary operation where either operand is a `Pointer`,
+    /// returning `None` if neither operand is a `Pointer`.
+    fn pointer_binop(&self, expr: &Expression, lhs: &LatticeValue, rhs: &LatticeValue)
+    -> Option<LatticeValue> {
+        match (lhs, rhs) {
+            (&Pointer(_, _), _) |
+            (_, &Pointer(_, _)) => {},
+            _ => return None
+        }
 
-            Expression::Mul(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::mul(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+        let bits = expr.bits();
+        let numeric = |value: &LatticeValue| match *value {
+            Pointer(_, _) => None,
+            _ => value.interval(bits)
+        };
 
-            Expression::Divu(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::divu(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+        if *lhs == Meet || *rhs == Meet {
+            return Some(Meet);
+        }
 
-            Expression::Modu(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::modu(lhs.into(), rhs.into()).unwrap()
-                )
+        Some(match *expr {
+            Expression::Add(_, _) => match (lhs, rhs) {
+                (&Pointer(ref region, ref offsets), other) |
+                (other, &Pointer(ref region, ref offsets)) => match numeric(other) {
+                    Some(other) => Pointer(region.clone(), offsets.add(&other)),
+                    None => Join
+                },
+                _ => Join
             },
 
-            Expression::Divs(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::divs(lhs.into(), rhs.into()).unwrap()
-                )
+            Expression::Sub(_, _) => match (lhs, rhs) {
+                (&Pointer(ref region, ref offsets), &Pointer(ref rhs_region, ref rhs_offsets)) => {
+                    if region == rhs_region {
+                        LatticeValue::from_interval(offsets.sub(rhs_offsets))
+                    }
+                    else {
+                        Join
+                    }
+                },
+                (&Pointer(ref region, ref offsets), _) => match numeric(rhs) {
+                    Some(rhs) => Pointer(region.clone(), offsets.sub(&rhs)),
+                    None => Join
+                },
+                _ => Join
             },
 
-            Expression::Mods(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::mods(lhs.into(), rhs.into()).unwrap()
-                )
+            // Aligning the stack pointer. We assume the base of the stack is
+            // aligned at least as strictly as this mask.
+            Expression::And(_, _) => match (lhs, rhs) {
+                (&Pointer(Region::Stack, ref offsets), &Values(ref values)) |
+                (&Values(ref values), &Pointer(Region::Stack, ref offsets))
+                if values.len() == 1 => {
+                    let mask = StridedInterval::constant(values.iter().next().unwrap());
+                    Pointer(Region::Stack, offsets.and(&mask))
+                },
+                _ => Join
             },
 
-            Expression::And(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::and(lhs.into(), rhs.into()).unwrap()
-                )
+            Expression::Cmpeq(_, _) |
+            Expression::Cmpneq(_, _) |
+            Expression::Cmpltu(_, _) |
+            Expression::Cmplts(_, _) => match (lhs, rhs) {
+                (&Pointer(ref region, ref lhs_offsets), &Pointer(ref rhs_region, ref rhs_offsets))
+                if region == rhs_region => LatticeValue::from_interval(match *expr {
+                    Expression::Cmpeq(_, _) => lhs_offsets.cmpeq(rhs_offsets),
+                    Expression::Cmpneq(_, _) => lhs_offsets.cmpneq(rhs_offsets),
+                    Expression::Cmpltu(_, _) => lhs_offsets.cmpltu(rhs_offsets),
+                    _ => lhs_offsets.cmplts(rhs_offsets)
+                }),
+                _ => Join
             },
 
-            Expression::Or(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::or(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+            _ => Join
+        })
+    }
 
-            Expression::Xor(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::xor(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+    /// Evaluates a binary operation, with `interval_op` used in place of `op`
+    /// when either operand is not a set of constants in the
+    /// `StridedIntervals` domain.
+    fn binop<F, I>(
+        &self,
+        expr: &Expression,
+        lhs: &Expression,
+        rhs: &Expression,
+        op: F,
+        interval_op: I
+    ) -> LatticeValue
+    where F: Fn(il::Constant, il::Constant) -> Expression,
+          I: Fn(&StridedInterval, &StridedInterval) -> StridedInterval {
+        let lhs_value = self.eval_(lhs);
+        let rhs_value = self.eval_(rhs);
+
+        if let Some(lattice_value) = self.pointer_binop(expr, &lhs_value, &rhs_value) {
+            return lattice_value;
+        }
 
-            Expression::Shl(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::shl(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+        if self.domain == Domain::StridedIntervals {
+            let constants = match (&lhs_value, &rhs_value) {
+                (&Values(ref l), &Values(ref r)) => l.len() * r.len() <= self.max,
+                _ => false
+            };
+            if !constants {
+                return match (lhs_value.interval(lhs.bits()),
+                              rhs_value.interval(rhs.bits())) {
+                    (Some(l), Some(r)) => LatticeValue::from_interval(interval_op(&l, &r)),
+                    _ => Meet
+                };
+            }
+        }
 
-            Expression::Shr(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::shr(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+        lattice_value_binop(&lhs_value, &rhs_value, op)
+    }
 
-            Expression::Cmpeq(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::cmpeq(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+    /// Evaluates an extension or truncation, with `interval_op` used in place
+    /// of `op` when the operand is not a set of constants in the
+    /// `StridedIntervals` domain.
+    fn extend<F, I>(&self, expr: &Expression, rhs: &Expression, op: F, interval_op: I)
+    -> LatticeValue
+    where F: Fn(il::Constant) -> Expression,
+          I: Fn(&StridedInterval) -> StridedInterval {
+        let rhs_value = self.eval_(rhs);
+
+        // Pointers keep their region only when their size does not change
+        if let Pointer(_, _) = rhs_value {
+            return if expr.bits() == rhs.bits() { rhs_value } else { Join };
+        }
 
-            Expression::Cmpneq(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::cmpneq(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+        if self.domain == Domain::StridedIntervals {
+            match rhs_value {
+                Join |
+                Interval(_) => return LatticeValue::from_interval(
+                    interval_op(&rhs_value.interval(rhs.bits()).unwrap())
+                ),
+                Values(_) |
+                Pointer(_, _) |
+                Meet => {}
+            }
+        }
 
-            Expression::Cmplts(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::cmplts(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+        lattice_extend_op(&rhs_value, op)
+    }
 
-            Expression::Cmpltu(ref lhs, ref rhs) => {
-                lattice_value_binop(
-                    &self.eval_(lhs),
-                    &self.eval_(rhs),
-                    |lhs: il::Constant, rhs: il::Constant| 
-                        Expression::cmpltu(lhs.into(), rhs.into()).unwrap()
-                )
-            },
+    fn eval_(&self, expr: &Expression) -> LatticeValue {
+        match *expr {
 
-            Expression::Zext(bits, ref rhs) => {
-                lattice_extend_op(
-                    &self.eval_(rhs),
-                    |rhs: il::Constant| {
-                        Expression::zext(bits, rhs.into()).unwrap()
-                    }
-                )
+            Expression::Scalar(ref scalar) => {
+                match self.scalars.get(scalar) {
+                    Some(lattice_value) => lattice_value.clone(),
+                    None => LatticeValue::Meet
+                }
             },
 
-            Expression::Sext(bits, ref rhs) => {
-                lattice_extend_op(
-                    &self.eval_(rhs),
-                    |rhs: il::Constant| Expression::sext(bits, rhs.into()).unwrap()
-                )
-            },
+            Expression::Constant(ref constant) =>
+                LatticeValue::value(constant.clone()),
 
-            Expression::Trun(bits, ref rhs) => {
-                lattice_extend_op(
-                    &self.eval_(rhs),
-                    |rhs: il::Constant| Expression::trun(bits, rhs.into()).unwrap()
-                )
-            }
+            Expression::Add(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::add(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::add
+            ),
+
+            Expression::Sub(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::sub(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::sub
+            ),
+
+            Expression::Mul(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::mul(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::mul
+            ),
+
+            Expression::Divu(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::divu(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::divu
+            ),
+
+            Expression::Modu(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::modu(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::modu
+            ),
+
+            Expression::Divs(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::divs(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::divs
+            ),
+
+            Expression::Mods(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::mods(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::mods
+            ),
+
+            Expression::And(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::and(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::and
+            ),
+
+            Expression::Or(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::or(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::or
+            ),
+
+            Expression::Xor(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::xor(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::xor
+            ),
+
+            Expression::Shl(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::shl(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::shl
+            ),
+
+            Expression::Shr(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::shr(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::shr
+            ),
+
+            Expression::Cmpeq(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::cmpeq(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::cmpeq
+            ),
+
+            Expression::Cmpneq(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::cmpneq(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::cmpneq
+            ),
+
+            Expression::Cmplts(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::cmplts(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::cmplts
+            ),
+
+            Expression::Cmpltu(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant| 
+                    Expression::cmpltu(lhs.into(), rhs.into()).unwrap(),
+                StridedInterval::cmpltu
+            ),
+
+            Expression::Zext(bits, ref rhs) => self.extend(
+                expr, rhs,
+                |rhs: il::Constant| Expression::zext(bits, rhs.into()).unwrap(),
+                |rhs: &StridedInterval| rhs.zext(bits)
+            ),
+
+            Expression::Sext(bits, ref rhs) => self.extend(
+                expr, rhs,
+                |rhs: il::Constant| Expression::sext(bits, rhs.into()).unwrap(),
+                |rhs: &StridedInterval| rhs.sext(bits)
+            ),
+
+            Expression::Trun(bits, ref rhs) => self.extend(
+                expr, rhs,
+                |rhs: il::Constant| Expression::trun(bits, rhs.into()).unwrap(),
+                |rhs: &StridedInterval| rhs.trun(bits)
+            ),
+
+            // Floating point operations are only evaluated over constants
+            Expression::Fadd(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fadd(lhs.into(), rhs.into()).unwrap(),
+                |lhs: &StridedInterval, _: &StridedInterval|
+                    StridedInterval::top(lhs.bits())
+            ),
+
+            Expression::Fsub(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fsub(lhs.into(), rhs.into()).unwrap(),
+                |lhs: &StridedInterval, _: &StridedInterval|
+                    StridedInterval::top(lhs.bits())
+            ),
+
+            Expression::Fmul(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fmul(lhs.into(), rhs.into()).unwrap(),
+                |lhs: &StridedInterval, _: &StridedInterval|
+                    StridedInterval::top(lhs.bits())
+            ),
+
+            Expression::Fdiv(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fdiv(lhs.into(), rhs.into()).unwrap(),
+                |lhs: &StridedInterval, _: &StridedInterval|
+                    StridedInterval::top(lhs.bits())
+            ),
+
+            Expression::Fcmpeq(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fcmpeq(lhs.into(), rhs.into()).unwrap(),
+                |_: &StridedInterval, _: &StridedInterval| StridedInterval::top(1)
+            ),
+
+            Expression::Fcmplt(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fcmplt(lhs.into(), rhs.into()).unwrap(),
+                |_: &StridedInterval, _: &StridedInterval| StridedInterval::top(1)
+            ),
+
+            Expression::Fcmpuo(ref lhs, ref rhs) => self.binop(
+                expr, lhs, rhs,
+                |lhs: il::Constant, rhs: il::Constant|
+                    Expression::fcmpuo(lhs.into(), rhs.into()).unwrap(),
+                |_: &StridedInterval, _: &StridedInterval| StridedInterval::top(1)
+            ),
+
+            Expression::Fext(bits, ref rhs) => self.extend(
+                expr, rhs,
+                |rhs: il::Constant| Expression::fext(bits, rhs.into()).unwrap(),
+                |_: &StridedInterval| StridedInterval::top(bits)
+            ),
+
+            Expression::Itof(bits, ref rhs) => self.extend(
+                expr, rhs,
+                |rhs: il::Constant| Expression::itof(bits, rhs.into()).unwrap(),
+                |_: &StridedInterval| StridedInterval::top(bits)
+            ),
+
+            Expression::Ftoi(bits, ref rhs) => self.extend(
+                expr, rhs,
+                |rhs: il::Constant| Expression::ftoi(bits, rhs.into()).unwrap(),
+                |_: &StridedInterval| StridedInterval::top(bits)
+            )
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,888 @@
+//! Natural loops, their induction variables, trip counts and summaries.
+//!
+//! A basic induction variable is a scalar whose only definitions in a loop
+//! add a constant to itself, and which are executed exactly once on every
+//! iteration. A derived induction variable is defined once in the loop as a
+//! linear function of a basic induction variable.
+//!
+//! The trip count of a loop is the number of times its back edges are taken.
+//! It is found from exit edges which compare a basic induction variable, with
+//! a step of one, for equality against a loop-invariant bound.
+//!
+//! Loops which only copy, fill or scan memory through basic induction
+//! variables, such as those `rep` prefixed string instructions are lifted
+//! into, are summarized so they can be executed without being unrolled.
+//!
+//! Loops are expected to be in non-SSA form.
+
+use analysis::analysis_location::*;
+use analysis::Analysis;
+use error::*;
+use executor;
+use il;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::usize;
+
+
+/// An induction variable of a loop.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum InductionVariable {
+    /// Incremented by `step` once on every iteration.
+    Basic {
+        step: i64
+    },
+    /// Set to `basic * factor + offset` once on every iteration, where
+    /// `basic` is a basic induction variable read where this variable is
+    /// defined.
+    Derived {
+        basic: il::Scalar,
+        factor: i64,
+        offset: i64
+    }
+}
+
+
+/// The number of times the back edges of a loop are taken, in terms of the
+/// values of scalars on entry to the loop.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum TripCount {
+    /// The loop always iterates this many times.
+    Exact(il::Expression),
+    /// The loop has other exits, and may leave before this many iterations.
+    AtMost(il::Expression),
+    Unknown
+}
+
+
+/// What a simple loop does to memory on each iteration, through the basic
+/// induction variables of the loop.
+///
+/// Every scalar a summarized loop writes, other than its basic induction
+/// variables, is dead once the loop exits.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum LoopSummary {
+    /// Loads `bits` from `source`, and stores them at `destination`, like
+    /// `memcpy`.
+    Copy {
+        destination: il::Scalar,
+        source: il::Scalar,
+        bits: usize
+    },
+    /// Stores `value`, of `bits`, at `destination`, like `memset`.
+    Fill {
+        destination: il::Scalar,
+        value: il::Expression,
+        bits: usize
+    },
+    /// Loads `bits` from `pointer` until the value loaded is `terminator`,
+    /// like `strlen`. The loop exits without advancing past the terminator.
+    Scan {
+        pointer: il::Scalar,
+        bits: usize,
+        terminator: u64
+    }
+}
+
+
+/// A natural loop of a `ControlFlowGraph`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Loop {
+    header: u64,
+    blocks: BTreeSet<u64>,
+    latches: BTreeSet<u64>,
+    exits: Vec<(u64, u64)>,
+    induction_variables: BTreeMap<il::Scalar, InductionVariable>,
+    trip_count: TripCount,
+    summary: Option<LoopSummary>
+}
+
+
+impl Loop {
+    /// The index of the block every iteration begins at.
+    pub fn header(&self) -> u64 {
+        self.header
+    }
+
+    /// The indices of every block in this loop, including the header.
+    pub fn blocks(&self) -> &BTreeSet<u64> {
+        &self.blocks
+    }
+
+    /// The indices of the blocks with a back edge to the header.
+    pub fn latches(&self) -> &BTreeSet<u64> {
+        &self.latches
+    }
+
+    /// The edges, as `(head, tail)`, which leave this loop.
+    pub fn exits(&self) -> &Vec<(u64, u64)> {
+        &self.exits
+    }
+
+    /// The induction variables of this loop.
+    pub fn induction_variables(&self) -> &BTreeMap<il::Scalar, InductionVariable> {
+        &self.induction_variables
+    }
+
+    /// Get the induction variable for a scalar, if it is one.
+    pub fn induction_variable(&self, scalar: &il::Scalar) -> Option<&InductionVariable> {
+        self.induction_variables.get(scalar)
+    }
+
+    /// The number of times this loop iterates.
+    pub fn trip_count(&self) -> &TripCount {
+        &self.trip_count
+    }
+
+    /// A summary of this loop, if it is simple enough to have one. Every
+    /// iteration of a summarized loop takes its one exit edge, or the back
+    /// edge, after its accesses to memory.
+    pub fn summary(&self) -> Option<&LoopSummary> {
+        self.summary.as_ref()
+    }
+}
+
+
+impl fmt::Display for TripCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TripCount::Exact(ref count) => write!(f, "{}", count),
+            TripCount::AtMost(ref count) => write!(f, "<= {}", count),
+            TripCount::Unknown => write!(f, "unknown")
+        }
+    }
+}
+
+
+/// A place in a loop. Positions are indices into the instructions of a block.
+/// A position of `usize::MAX` is the end of the block, where its edges are
+/// taken.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Point {
+    block_index: u64,
+    position: usize
+}
+
+
+impl Point {
+    fn new(block_index: u64, position: usize) -> Point {
+        Point {
+            block_index: block_index,
+            position: position
+        }
+    }
+
+    fn end(block_index: u64) -> Point {
+        Point::new(block_index, usize::MAX)
+    }
+}
+
+
+/// Sign-extend the low `bits` of `value`.
+fn signed(value: u64, bits: usize) -> i64 {
+    if bits == 0 || bits >= 64 {
+        value as i64
+    }
+    else {
+        let shift = 64 - bits;
+        ((value << shift) as i64) >> shift
+    }
+}
+
+
+/// Get the constant an assignment to `scalar` adds to it.
+fn increment(scalar: &il::Scalar, src: &il::Expression) -> Option<i64> {
+    match *src {
+        il::Expression::Add(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (&il::Expression::Scalar(ref s), &il::Expression::Constant(ref c)) |
+            (&il::Expression::Constant(ref c), &il::Expression::Scalar(ref s)) =>
+                if s == scalar { Some(signed(c.value(), c.bits())) } else { None },
+            _ => None
+        },
+        il::Expression::Sub(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (&il::Expression::Scalar(ref s), &il::Expression::Constant(ref c)) =>
+                if s == scalar { Some(signed(c.value(), c.bits()).wrapping_neg()) } else { None },
+            _ => None
+        },
+        _ => None
+    }
+}
+
+
+/// Get the basic induction variable, factor and offset of a linear function
+/// of a basic induction variable.
+fn linear(src: &il::Expression, basics: &BTreeMap<il::Scalar, i64>)
+-> Option<(il::Scalar, i64, i64)> {
+    let (scalar, factor, offset) = match *src {
+        il::Expression::Scalar(ref s) => (s, 1, 0),
+        il::Expression::Add(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (&il::Expression::Scalar(ref s), &il::Expression::Constant(ref c)) |
+            (&il::Expression::Constant(ref c), &il::Expression::Scalar(ref s)) =>
+                (s, 1, signed(c.value(), c.bits())),
+            _ => return None
+        },
+        il::Expression::Sub(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (&il::Expression::Scalar(ref s), &il::Expression::Constant(ref c)) =>
+                (s, 1, signed(c.value(), c.bits()).wrapping_neg()),
+            _ => return None
+        },
+        il::Expression::Mul(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (&il::Expression::Scalar(ref s), &il::Expression::Constant(ref c)) |
+            (&il::Expression::Constant(ref c), &il::Expression::Scalar(ref s)) =>
+                (s, signed(c.value(), c.bits()), 0),
+            _ => return None
+        },
+        il::Expression::Shl(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (&il::Expression::Scalar(ref s), &il::Expression::Constant(ref c)) =>
+                if c.value() < 63 { (s, 1 << c.value(), 0) } else { return None },
+            _ => return None
+        },
+        _ => return None
+    };
+    if basics.contains_key(scalar) {
+        Some((scalar.clone(), factor, offset))
+    }
+    else {
+        None
+    }
+}
+
+
+/// Replace `scalar` with `replacement` everywhere in `expression`.
+fn substitute(expression: &mut il::Expression, scalar: &il::Scalar, replacement: &il::Expression) {
+    let replace = match *expression {
+        il::Expression::Scalar(ref s) => s == scalar,
+        il::Expression::Constant(_) => false,
+        il::Expression::Add(ref mut lhs, ref mut rhs) |
+        il::Expression::Sub(ref mut lhs, ref mut rhs) |
+        il::Expression::Mul(ref mut lhs, ref mut rhs) |
+        il::Expression::Divu(ref mut lhs, ref mut rhs) |
+        il::Expression::Modu(ref mut lhs, ref mut rhs) |
+        il::Expression::Divs(ref mut lhs, ref mut rhs) |
+        il::Expression::Mods(ref mut lhs, ref mut rhs) |
+        il::Expression::And(ref mut lhs, ref mut rhs) |
+        il::Expression::Or(ref mut lhs, ref mut rhs) |
+        il::Expression::Xor(ref mut lhs, ref mut rhs) |
+        il::Expression::Shl(ref mut lhs, ref mut rhs) |
+        il::Expression::Shr(ref mut lhs, ref mut rhs) |
+        il::Expression::Cmpeq(ref mut lhs, ref mut rhs) |
+        il::Expression::Cmpneq(ref mut lhs, ref mut rhs) |
+        il::Expression::Cmplts(ref mut lhs, ref mut rhs) |
+        il::Expression::Cmpltu(ref mut lhs, ref mut rhs) |
+        il::Expression::Fadd(ref mut lhs, ref mut rhs) |
+        il::Expression::Fsub(ref mut lhs, ref mut rhs) |
+        il::Expression::Fmul(ref mut lhs, ref mut rhs) |
+        il::Expression::Fdiv(ref mut lhs, ref mut rhs) |
+        il::Expression::Fcmpeq(ref mut lhs, ref mut rhs) |
+        il::Expression::Fcmplt(ref mut lhs, ref mut rhs) |
+        il::Expression::Fcmpuo(ref mut lhs, ref mut rhs) => {
+            substitute(lhs, scalar, replacement);
+            substitute(rhs, scalar, replacement);
+            false
+        },
+        il::Expression::Zext(_, ref mut src) |
+        il::Expression::Sext(_, ref mut src) |
+        il::Expression::Trun(_, ref mut src) |
+        il::Expression::Fext(_, ref mut src) |
+        il::Expression::Itof(_, ref mut src) |
+        il::Expression::Ftoi(_, ref mut src) => {
+            substitute(src, scalar, replacement);
+            false
+        }
+    };
+    if replace {
+        *expression = replacement.clone();
+    }
+}
+
+
+fn is_comparison(expression: &il::Expression) -> bool {
+    match *expression {
+        il::Expression::Cmpeq(_, _) |
+        il::Expression::Cmpneq(_, _) |
+        il::Expression::Cmplts(_, _) |
+        il::Expression::Cmpltu(_, _) => true,
+        _ => false
+    }
+}
+
+
+/// If `condition` holds exactly when two expressions are equal, return them.
+/// Constants are always returned second, and `x & x` is simplified to `x`.
+fn equality(condition: &il::Expression) -> Option<(il::Expression, il::Expression)> {
+    fn strip(expression: &il::Expression) -> il::Expression {
+        match *expression {
+            il::Expression::And(ref lhs, ref rhs) if lhs == rhs => (**lhs).clone(),
+            _ => expression.clone()
+        }
+    }
+
+    match *condition {
+        il::Expression::Cmpeq(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (comparison, &il::Expression::Constant(ref c)) |
+            (&il::Expression::Constant(ref c), comparison)
+                if is_comparison(comparison) && c.value() == 1 => equality(comparison),
+            (operand, &il::Expression::Constant(ref c)) |
+            (&il::Expression::Constant(ref c), operand) if !is_comparison(operand) =>
+                Some((strip(operand), il::Expression::constant(c.clone()))),
+            (lhs, rhs) if !is_comparison(lhs) && !is_comparison(rhs) =>
+                Some((strip(lhs), strip(rhs))),
+            _ => None
+        },
+        il::Expression::Cmpneq(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+            (comparison, &il::Expression::Constant(ref c)) |
+            (&il::Expression::Constant(ref c), comparison)
+                if is_comparison(comparison) && c.value() == 0 => equality(comparison),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+
+/// The state shared while analyzing one loop.
+struct LoopContext<'a> {
+    control_flow_graph: &'a il::ControlFlowGraph,
+    dominators: &'a BTreeMap<u64, BTreeSet<u64>>,
+    blocks: &'a BTreeSet<u64>,
+    latches: BTreeSet<u64>,
+    /// Blocks of loops nested in this loop
+    inner: BTreeSet<u64>,
+    /// The operations, with their points, which write each scalar in the loop
+    definitions: BTreeMap<il::Scalar, Vec<(Point, &'a il::Operation)>>,
+    /// Every operation in the loop, with its point
+    operations: Vec<(Point, &'a il::Operation)>
+}
+
+
+impl<'a> LoopContext<'a> {
+    fn new(
+        control_flow_graph: &'a il::ControlFlowGraph,
+        dominators: &'a BTreeMap<u64, BTreeSet<u64>>,
+        header: u64,
+        blocks: &'a BTreeSet<u64>,
+        inner: BTreeSet<u64>
+    ) -> Result<LoopContext<'a>> {
+        let latches = control_flow_graph.graph()
+            .edges_in(header)
+            .ok_or("Could not find edges into loop header")?
+            .iter()
+            .map(|edge| edge.head())
+            .filter(|head| blocks.contains(head))
+            .collect::<BTreeSet<u64>>();
+
+        let mut definitions: BTreeMap<il::Scalar, Vec<(Point, &il::Operation)>> =
+            BTreeMap::new();
+        let mut operations = Vec::new();
+        for block_index in blocks {
+            let block = control_flow_graph.block(*block_index)
+                                          .ok_or("Could not find loop block")?;
+            for (position, instruction) in block.instructions().iter().enumerate() {
+                let point = Point::new(*block_index, position);
+                let operation = instruction.operation();
+                operations.push((point, operation));
+                let dst = match *operation {
+                    il::Operation::Assign { ref dst, .. } |
+                    il::Operation::Load { ref dst, .. } => dst.clone(),
+                    il::Operation::Phi { dst: il::MultiVar::Scalar(ref dst), .. } => dst.clone(),
+                    _ => continue
+                };
+                definitions.entry(dst).or_insert(Vec::new()).push((point, operation));
+            }
+        }
+
+        Ok(LoopContext {
+            control_flow_graph: control_flow_graph,
+            dominators: dominators,
+            blocks: blocks,
+            latches: latches,
+            inner: inner,
+            definitions: definitions,
+            operations: operations
+        })
+    }
+
+    /// Returns true if `lhs` is always executed before `rhs` in an iteration.
+    fn precedes(&self, lhs: Point, rhs: Point) -> bool {
+        if lhs.block_index == rhs.block_index {
+            lhs.position < rhs.position
+        }
+        else {
+            self.dominators
+                .get(&rhs.block_index)
+                .map(|doms| doms.contains(&lhs.block_index))
+                .unwrap_or(false)
+        }
+    }
+
+    /// Returns true if the block at `block_index` is executed exactly once on
+    /// every iteration.
+    fn every_iteration(&self, block_index: u64) -> bool {
+        !self.inner.contains(&block_index) &&
+        self.latches.iter().all(|latch| {
+            self.dominators
+                .get(latch)
+                .map(|doms| doms.contains(&block_index))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Returns true if no scalar read by `expression` is written in the loop.
+    fn invariant(&self, expression: &il::Expression) -> bool {
+        expression.collect_scalars()
+                  .into_iter()
+                  .all(|scalar| !self.definitions.contains_key(scalar))
+    }
+
+    fn basic_induction_variables(&self) -> BTreeMap<il::Scalar, i64> {
+        let mut basics = BTreeMap::new();
+        'scalars: for (scalar, definitions) in &self.definitions {
+            let mut step: i64 = 0;
+            for &(point, operation) in definitions {
+                if !self.every_iteration(point.block_index) {
+                    continue 'scalars;
+                }
+                let increment = match *operation {
+                    il::Operation::Assign { ref src, .. } => increment(scalar, src),
+                    _ => None
+                };
+                match increment {
+                    Some(increment) => step = step.wrapping_add(increment),
+                    None => continue 'scalars
+                }
+            }
+            let step = signed(step as u64, scalar.bits());
+            if step != 0 {
+                basics.insert(scalar.clone(), step);
+            }
+        }
+        basics
+    }
+
+    fn derived_induction_variables(&self, basics: &BTreeMap<il::Scalar, i64>)
+    -> BTreeMap<il::Scalar, (il::Scalar, i64, i64)> {
+        let mut derived = BTreeMap::new();
+        for (scalar, definitions) in &self.definitions {
+            if basics.contains_key(scalar) || definitions.len() != 1 {
+                continue;
+            }
+            let (point, operation) = definitions[0];
+            if !self.every_iteration(point.block_index) {
+                continue;
+            }
+            if let il::Operation::Assign { ref src, .. } = *operation {
+                if let Some(linear) = linear(src, basics) {
+                    derived.insert(scalar.clone(), linear);
+                }
+            }
+        }
+        derived
+    }
+
+    /// Replace a scalar in the condition of an exit with its definition in
+    /// the loop, if it is defined by one `Assign` before the exit on every
+    /// iteration. Returns the condition, and the point it is evaluated at.
+    fn resolve(&self, condition: &il::Expression, exit: Point) -> (il::Expression, Point) {
+        let candidates = condition
+            .collect_scalars()
+            .into_iter()
+            .filter(|scalar| self.definitions.contains_key(scalar))
+            .cloned()
+            .collect::<BTreeSet<il::Scalar>>();
+        if candidates.len() != 1 {
+            return (condition.clone(), exit);
+        }
+        let scalar = candidates.into_iter().next().unwrap();
+        let definitions = &self.definitions[&scalar];
+        if definitions.len() != 1 {
+            return (condition.clone(), exit);
+        }
+        let (point, operation) = definitions[0];
+        match *operation {
+            il::Operation::Assign { ref src, .. } if self.precedes(point, exit) => {
+                let mut condition = condition.clone();
+                substitute(&mut condition, &scalar, src);
+                (condition, point)
+            },
+            _ => (condition.clone(), exit)
+        }
+    }
+
+    /// The number of back edges taken before an exit, where the exit is
+    /// taken when a basic induction variable with a step of one reaches a
+    /// loop-invariant bound.
+    fn exit_count(
+        &self,
+        exit: &il::Edge,
+        basics: &BTreeMap<il::Scalar, i64>,
+        entry_values: &BTreeMap<il::Scalar, il::Constant>
+    ) -> Result<Option<il::Expression>> {
+        if !self.every_iteration(exit.head()) {
+            return Ok(None);
+        }
+        let condition = match *exit.condition() {
+            Some(ref condition) => condition,
+            None => return Ok(None)
+        };
+        let (condition, evaluated) = self.resolve(condition, Point::end(exit.head()));
+        let (operand, bound) = match equality(&condition) {
+            Some(equality) => equality,
+            None => return Ok(None)
+        };
+        let (scalar, bound) = match (operand, bound) {
+            (il::Expression::Scalar(scalar), bound) |
+            (bound, il::Expression::Scalar(scalar)) => (scalar, bound),
+            _ => return Ok(None)
+        };
+        let step = match basics.get(&scalar) {
+            Some(step) => *step,
+            None => return Ok(None)
+        };
+        if !self.invariant(&bound) || bound.bits() != scalar.bits() {
+            return Ok(None);
+        }
+
+        let initial = il::Expression::scalar(scalar.clone());
+        let mut count = match (step, bound) {
+            (-1, il::Expression::Constant(ref c)) if c.value() == 0 => initial,
+            (1, bound) => il::Expression::sub(bound, initial)?,
+            (-1, bound) => il::Expression::sub(initial, bound)?,
+            _ => return Ok(None)
+        };
+
+        // the value tested is one step ahead if it was updated first
+        let updated = self.definitions[&scalar]
+            .iter()
+            .any(|&(point, _)| self.precedes(point, evaluated));
+        if updated {
+            count = il::Expression::sub(count, il::expr_const(1, scalar.bits()))?;
+        }
+
+        for (scalar, value) in entry_values {
+            substitute(&mut count, scalar, &il::Expression::constant(value.clone()));
+        }
+        if count.collect_scalars().is_empty() {
+            count = il::Expression::constant(executor::constants_expression(&count)?);
+        }
+
+        Ok(Some(count))
+    }
+
+    /// Returns true if every scalar defined in the loop, other than a basic
+    /// induction variable, is only used in the loop.
+    fn dead_on_exit(&self, analysis: &Analysis, basics: &BTreeMap<il::Scalar, i64>)
+    -> Result<bool> {
+        for &(point, operation) in &self.operations {
+            let written = match *operation {
+                il::Operation::Assign { ref dst, .. } |
+                il::Operation::Load { ref dst, .. } => dst,
+                _ => continue
+            };
+            if basics.contains_key(written) {
+                continue;
+            }
+            let block = self.control_flow_graph
+                            .block(point.block_index)
+                            .ok_or("Could not find loop block")?;
+            let location = AnalysisLocation::instruction(
+                point.block_index,
+                block.instructions()[point.position].index()
+            );
+            let uses = match analysis.def_use().get(&location) {
+                Some(uses) => uses,
+                None => continue
+            };
+            for use_ in uses {
+                let inside = match *use_ {
+                    AnalysisLocation::Instruction(ref il) =>
+                        self.blocks.contains(&il.block_index()),
+                    AnalysisLocation::Edge(ref el) =>
+                        self.blocks.contains(&el.head()) && self.blocks.contains(&el.tail()),
+                    AnalysisLocation::EmptyBlock(ref ebl) =>
+                        self.blocks.contains(&ebl.block_index())
+                };
+                if !inside {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns true if `pointer` is a basic induction variable which steps
+    /// over accesses of `bits`, and is only updated after `access`.
+    fn advances(
+        &self,
+        pointer: &il::Scalar,
+        bits: usize,
+        access: Point,
+        basics: &BTreeMap<il::Scalar, i64>
+    ) -> bool {
+        let step = match basics.get(pointer) {
+            Some(step) => *step,
+            None => return false
+        };
+        if bits % 8 != 0 || step.wrapping_abs() as u64 != (bits / 8) as u64 {
+            return false;
+        }
+        self.definitions[pointer].iter().all(|&(point, _)| self.precedes(access, point))
+    }
+
+    fn summary(
+        &self,
+        analysis: &Analysis,
+        exits: &Vec<&il::Edge>,
+        basics: &BTreeMap<il::Scalar, i64>,
+        trip_count: &TripCount
+    ) -> Result<Option<LoopSummary>> {
+        if exits.len() != 1 {
+            return Ok(None);
+        }
+        let exit = exits[0];
+        let exit_point = Point::end(exit.head());
+        if !self.every_iteration(exit.head()) {
+            return Ok(None);
+        }
+
+        let mut loads = Vec::new();
+        let mut stores = Vec::new();
+        for &(point, operation) in &self.operations {
+            match *operation {
+                il::Operation::Load { ref dst, ref index, .. } =>
+                    loads.push((point, dst, index)),
+                il::Operation::Store { ref index, ref src, .. } =>
+                    stores.push((point, index, src)),
+                il::Operation::Assign { .. } => {},
+                il::Operation::Brc { .. } |
+                il::Operation::Phi { .. } |
+                il::Operation::Raise { .. } => return Ok(None)
+            }
+        }
+
+        // every basic induction variable must be updated after the exit
+        for scalar in basics.keys() {
+            if self.definitions[scalar].iter().any(|&(point, _)| self.precedes(point, exit_point)) {
+                return Ok(None);
+            }
+        }
+
+        if !self.dead_on_exit(analysis, basics)? {
+            return Ok(None);
+        }
+
+        let exact = match *trip_count {
+            TripCount::Exact(_) => true,
+            _ => false
+        };
+
+        let summary = match (loads.len(), stores.len()) {
+            (1, 1) if exact => {
+                let (load_point, dst, load_index) = loads[0];
+                let (store_point, store_index, src) = stores[0];
+                let (source, destination) = match (load_index, store_index) {
+                    (&il::Expression::Scalar(ref source),
+                     &il::Expression::Scalar(ref destination)) => (source, destination),
+                    _ => return Ok(None)
+                };
+                if source == destination ||
+                   *src != il::Expression::scalar(dst.clone()) ||
+                   !self.every_iteration(load_point.block_index) ||
+                   !self.every_iteration(store_point.block_index) ||
+                   self.precedes(load_point, exit_point) ||
+                   !self.precedes(load_point, store_point) ||
+                   !self.advances(source, dst.bits(), load_point, basics) ||
+                   !self.advances(destination, dst.bits(), store_point, basics) ||
+                   basics[source] != basics[destination] {
+                    return Ok(None);
+                }
+                LoopSummary::Copy {
+                    destination: destination.clone(),
+                    source: source.clone(),
+                    bits: dst.bits()
+                }
+            },
+            (0, 1) if exact => {
+                let (store_point, store_index, src) = stores[0];
+                let destination = match *store_index {
+                    il::Expression::Scalar(ref destination) => destination,
+                    _ => return Ok(None)
+                };
+                if !self.invariant(src) ||
+                   !self.every_iteration(store_point.block_index) ||
+                   self.precedes(store_point, exit_point) ||
+                   !self.advances(destination, src.bits(), store_point, basics) {
+                    return Ok(None);
+                }
+                LoopSummary::Fill {
+                    destination: destination.clone(),
+                    value: src.clone(),
+                    bits: src.bits()
+                }
+            },
+            (1, 0) => {
+                let (load_point, dst, load_index) = loads[0];
+                let pointer = match *load_index {
+                    il::Expression::Scalar(ref pointer) => pointer,
+                    _ => return Ok(None)
+                };
+                let condition = match *exit.condition() {
+                    Some(ref condition) => condition,
+                    None => return Ok(None)
+                };
+                let (condition, evaluated) = self.resolve(condition, exit_point);
+                let terminator = match equality(&condition) {
+                    Some((il::Expression::Scalar(ref scalar), il::Expression::Constant(ref c)))
+                        if scalar == dst => c.value(),
+                    _ => return Ok(None)
+                };
+                if !self.every_iteration(load_point.block_index) ||
+                   !self.precedes(load_point, evaluated) ||
+                   !self.advances(pointer, dst.bits(), load_point, basics) {
+                    return Ok(None);
+                }
+                LoopSummary::Scan {
+                    pointer: pointer.clone(),
+                    bits: dst.bits(),
+                    terminator: terminator
+                }
+            },
+            _ => return Ok(None)
+        };
+
+        Ok(Some(summary))
+    }
+}
+
+
+/// Get the scalars which are always given the same constant before a loop is
+/// entered.
+fn entry_values(
+    analysis: &Analysis,
+    header: u64,
+    blocks: &BTreeSet<u64>
+) -> Result<BTreeMap<il::Scalar, il::Constant>> {
+    let control_flow_graph = analysis.control_flow_graph();
+
+    let mut values: BTreeMap<il::Scalar, Option<il::Constant>> = BTreeMap::new();
+
+    for edge in control_flow_graph.edges() {
+        if edge.tail() != header || blocks.contains(&edge.head()) {
+            continue;
+        }
+        let reaches = match analysis.reaching_definitions()
+                                    .get(&AnalysisLocation::edge(edge.head(), edge.tail())) {
+            Some(reaches) => reaches,
+            None => return Ok(BTreeMap::new())
+        };
+        for location in reaches.out() {
+            let instruction = match *location {
+                AnalysisLocation::Instruction(ref il) => il.find(control_flow_graph)?,
+                _ => continue
+            };
+            let (dst, value) = match *instruction.operation() {
+                il::Operation::Assign {
+                    ref dst,
+                    src: il::Expression::Constant(ref constant)
+                } => (dst, Some(constant.clone())),
+                il::Operation::Assign { ref dst, .. } |
+                il::Operation::Load { ref dst, .. } => (dst, None),
+                _ => continue
+            };
+            let agrees = match values.get(dst) {
+                Some(existing) => *existing == value,
+                None => true
+            };
+            values.insert(dst.clone(), if agrees { value } else { None });
+        }
+    }
+
+    Ok(values.into_iter()
+             .filter_map(|(scalar, value)| value.map(|value| (scalar, value)))
+             .collect())
+}
+
+
+/// Find the natural loops of the function an `Analysis` was performed over,
+/// with their induction variables, trip counts and summaries.
+pub fn compute(analysis: &Analysis) -> Result<Vec<Loop>> {
+    let control_flow_graph = analysis.control_flow_graph();
+    let entry = match control_flow_graph.entry() {
+        Some(entry) => entry,
+        None => return Ok(Vec::new())
+    };
+
+    let graph = control_flow_graph.graph();
+    let natural_loops = graph.compute_natural_loops(entry)?;
+    let dominators = graph.compute_dominators(entry)?;
+
+    let mut loops = Vec::new();
+    for (header, blocks) in &natural_loops {
+        let inner = natural_loops
+            .iter()
+            .filter(|&(h, b)| h != header && b.is_subset(blocks))
+            .fold(BTreeSet::new(), |inner, (_, b)| &inner | b);
+
+        let context = LoopContext::new(
+            control_flow_graph,
+            &dominators,
+            *header,
+            blocks,
+            inner
+        )?;
+
+        let exits = control_flow_graph
+            .edges()
+            .into_iter()
+            .filter(|edge| blocks.contains(&edge.head()) && !blocks.contains(&edge.tail()))
+            .collect::<Vec<&il::Edge>>();
+
+        let basics = context.basic_induction_variables();
+        let derived = context.derived_induction_variables(&basics);
+
+        let entry_values = entry_values(analysis, *header, blocks)?;
+
+        let mut counts = Vec::new();
+        for exit in &exits {
+            if let Some(count) = context.exit_count(exit, &basics, &entry_values)? {
+                counts.push(count);
+            }
+        }
+        let trip_count = match (exits.len(), counts.into_iter().next()) {
+            (1, Some(count)) => TripCount::Exact(count),
+            (_, Some(count)) => TripCount::AtMost(count),
+            (_, None) => TripCount::Unknown
+        };
+
+        let summary = context.summary(analysis, &exits, &basics, &trip_count)?;
+
+        let mut induction_variables = BTreeMap::new();
+        for (scalar, step) in &basics {
+            induction_variables.insert(
+                scalar.clone(),
+                InductionVariable::Basic { step: *step }
+            );
+        }
+        for (scalar, (basic, factor, offset)) in derived {
+            induction_variables.insert(
+                scalar,
+                InductionVariable::Derived {
+                    basic: basic,
+                    factor: factor,
+                    offset: offset
+                }
+            );
+        }
+
+        loops.push(Loop {
+            header: *header,
+            blocks: blocks.clone(),
+            latches: context.latches.clone(),
+            exits: exits.iter().map(|edge| (edge.head(), edge.tail())).collect(),
+            induction_variables: induction_variables,
+            trip_count: trip_count,
+            summary: summary
+        });
+    }
+
+    Ok(loops)
+}
@@ -0,0 +1,195 @@
+//! Memory accessed by `Load` and `Store` operations.
+//!
+//! Value-set analysis resolves the address of each access into a region and
+//! a set of offsets. Two accesses may alias when they are in the same region
+//! and the bytes they may touch overlap. A precise access, at a single
+//! offset, overwrites every access whose bytes it covers.
+
+use analysis::lattice::{Domain, LatticeValue, Region};
+use analysis::strided_interval::StridedInterval;
+use analysis::value_set::{self, Endian, Regions};
+use analysis::analysis_location::*;
+use error::*;
+use il;
+use std::collections::BTreeMap;
+use std::fmt;
+
+
+/// The memory accessed by a `Load` or `Store`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MemoryLocation {
+    /// `bytes` bytes at one of `offsets` from the base of `region`. Offsets
+    /// with no region are absolute addresses.
+    Addresses {
+        region: Option<Region>,
+        offsets: StridedInterval,
+        bytes: u64
+    },
+    /// Any memory.
+    Unknown
+}
+
+
+impl MemoryLocation {
+    /// Create the `MemoryLocation` for an access of `bits` at an address with
+    /// the given value.
+    pub fn new(address: &LatticeValue, bits: usize) -> MemoryLocation {
+        let bytes = ((bits + 7) / 8) as u64;
+        let (region, offsets) = match *address {
+            LatticeValue::Pointer(ref region, ref offsets) =>
+                (Some(region.clone()), offsets.clone()),
+            LatticeValue::Values(ref values) => match StridedInterval::from_constants(values) {
+                Some(offsets) => (None, offsets),
+                None => return MemoryLocation::Unknown
+            },
+            LatticeValue::Interval(ref offsets) => (None, offsets.clone()),
+            LatticeValue::Join |
+            LatticeValue::Meet => return MemoryLocation::Unknown
+        };
+        if offsets.is_top() {
+            return MemoryLocation::Unknown;
+        }
+        MemoryLocation::Addresses {
+            region: region,
+            offsets: offsets,
+            bytes: bytes
+        }
+    }
+
+    /// Returns true if this access is of exactly one address.
+    pub fn is_precise(&self) -> bool {
+        match *self {
+            MemoryLocation::Addresses { ref offsets, .. } =>
+                offsets.constant_value().is_some(),
+            MemoryLocation::Unknown => false
+        }
+    }
+
+    /// The first byte, and one past the last byte, this access may touch, or
+    /// `None` if these bytes wrap around the end of the address space.
+    fn range(&self) -> Option<(u128, u128)> {
+        match *self {
+            MemoryLocation::Addresses { ref offsets, bytes, .. } => {
+                let end = offsets.hi() as u128 + bytes as u128;
+                if end > 1u128 << offsets.bits() {
+                    None
+                }
+                else {
+                    Some((offsets.lo() as u128, end))
+                }
+            },
+            MemoryLocation::Unknown => None
+        }
+    }
+
+    fn region(&self) -> Option<&Option<Region>> {
+        match *self {
+            MemoryLocation::Addresses { ref region, .. } => Some(region),
+            MemoryLocation::Unknown => None
+        }
+    }
+
+    /// Returns true if this access, and `other`, may touch the same byte.
+    pub fn may_alias(&self, other: &MemoryLocation) -> bool {
+        match (self.region(), other.region()) {
+            (Some(region), Some(other_region)) => {
+                if region != other_region {
+                    return false;
+                }
+            },
+            _ => return true
+        }
+        match (self.range(), other.range()) {
+            (Some((lo, hi)), Some((other_lo, other_hi))) =>
+                lo < other_hi && other_lo < hi,
+            _ => true
+        }
+    }
+
+    /// Returns true if this access, and `other`, are always of the same
+    /// address.
+    pub fn must_alias(&self, other: &MemoryLocation) -> bool {
+        if !self.is_precise() || !other.is_precise() {
+            return false;
+        }
+        match (self, other) {
+            (&MemoryLocation::Addresses { ref region, ref offsets, .. },
+             &MemoryLocation::Addresses { region: ref other_region,
+                                          offsets: ref other_offsets, .. }) =>
+                region == other_region && offsets.lo() == other_offsets.lo(),
+            _ => false
+        }
+    }
+
+    /// Returns true if this access must touch every byte `other` may touch.
+    pub fn covers(&self, other: &MemoryLocation) -> bool {
+        if !self.is_precise() {
+            return false;
+        }
+        match (self.region(), other.region()) {
+            (Some(region), Some(other_region)) => {
+                if region != other_region {
+                    return false;
+                }
+            },
+            _ => return false
+        }
+        match (self.range(), other.range()) {
+            (Some((lo, hi)), Some((other_lo, other_hi))) =>
+                lo <= other_lo && other_hi <= hi,
+            _ => false
+        }
+    }
+}
+
+
+impl fmt::Display for MemoryLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MemoryLocation::Addresses { ref region, ref offsets, bytes } => match *region {
+                Some(ref region) => write!(f, "{}+{}:{}", region, offsets, bytes),
+                None => write!(f, "{}:{}", offsets, bytes)
+            },
+            MemoryLocation::Unknown => write!(f, "unknown")
+        }
+    }
+}
+
+
+/// Resolve the memory accessed by every `Load` and `Store` in
+/// `control_flow_graph`, using value-set analysis with pointers tracked as
+/// described by `regions`.
+///
+/// Accesses which value-set analysis finds are never reached are not
+/// included.
+pub fn compute(
+    control_flow_graph: &il::ControlFlowGraph,
+    max: usize,
+    endian: Endian,
+    regions: &Regions
+) -> Result<BTreeMap<AnalysisLocation, MemoryLocation>> {
+    let addresses = value_set::addresses(
+        control_flow_graph,
+        max,
+        endian,
+        Domain::StridedIntervals,
+        regions
+    )?;
+
+    let mut memory_locations = BTreeMap::new();
+    for (location, address) in addresses {
+        let bits = match location {
+            AnalysisLocation::Instruction(ref il) => match *il.find(control_flow_graph)?
+                                                               .operation() {
+                il::Operation::Load { ref dst, .. } => dst.bits(),
+                il::Operation::Store { ref src, .. } => src.bits(),
+                _ => continue
+            },
+            _ => continue
+        };
+        let memory_location = MemoryLocation::new(&address, bits);
+        memory_locations.insert(location, memory_location);
+    }
+
+    Ok(memory_locations)
+}
@@ -6,18 +6,50 @@ mod dead_code_elimination;
 mod def_use;
 pub mod fixed_point;
 pub mod lattice;
+mod loops;
+mod memory_location;
+mod points_to;
 mod reaching_definitions;
+pub mod sccp;
+mod signature;
 // mod simplification;
 pub mod ssa;
+mod stack_pointer;
+pub mod strided_interval;
+mod taint;
+mod types;
 mod value_set;
 
 use error::*;
 use il;
 pub use self::analysis_location::*;
 pub use self::lattice::*;
+pub use self::loops::{InductionVariable, Loop, LoopSummary, TripCount};
+pub use self::memory_location::MemoryLocation;
+pub use self::points_to::{program_points_to, PointsTo, ProgramPointsTo};
 pub use self::ssa::*;
 pub use self::reaching_definitions::Reaches;
-pub use self::value_set::Endian;
+pub use self::sccp::{sccp, Sccp};
+pub use self::signature::recover_signatures;
+pub use self::stack_pointer::{
+    StackConventions,
+    StackFrame,
+    StackPointer,
+    StackState,
+    StackValue,
+    StackVariable
+};
+pub use self::strided_interval::StridedInterval;
+pub use self::taint::{
+    taint,
+    TaintConfiguration,
+    TaintFlow,
+    TaintSink,
+    TaintSource,
+    TaintStep
+};
+pub use self::types::recover_types;
+pub use self::value_set::{Endian, Regions};
 use std::collections::{BTreeMap, BTreeSet};
 
 /// `Analysis` holds several types of analysis results.
@@ -31,6 +63,7 @@ pub struct Analysis<'a> {
     reaching_definitions: BTreeMap<AnalysisLocation, Reaches>,
     def_use: BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>>,
     use_def: BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>>,
+    points_to: Option<PointsTo>
 }
 
 
@@ -38,13 +71,43 @@ impl<'a> Analysis<'a> {
     pub fn new(control_flow_graph: &'a il::ControlFlowGraph)
     -> Result<Analysis<'a>> {
         let rd = reaching_definitions::compute(control_flow_graph)?;
-        let du = def_use::def_use(&rd, control_flow_graph)?;
-        let ud = def_use::use_def(&rd, control_flow_graph)?;
+        let du = def_use::def_use(&rd, control_flow_graph, None)?;
+        let ud = def_use::use_def(&rd, control_flow_graph, None)?;
         Ok(Analysis {
             control_flow_graph: control_flow_graph,
             reaching_definitions: rd,
             def_use: du,
-            use_def: ud
+            use_def: ud,
+            points_to: None
+        })
+    }
+
+    /// Create a new `Analysis` where reaching definitions, def use and use def
+    /// chains track memory as well as scalars.
+    ///
+    /// The addresses of loads and stores are resolved with value-set analysis,
+    /// with pointers tracked as described by `regions`. A store only kills
+    /// the stores whose memory it must overwrite, and a load only uses the
+    /// stores whose memory it may read.
+    pub fn new_with_memory(
+        control_flow_graph: &'a il::ControlFlowGraph,
+        max: usize,
+        endian: value_set::Endian,
+        regions: &Regions
+    ) -> Result<Analysis<'a>> {
+        let pt = points_to::compute(control_flow_graph, max, endian, regions)?;
+        let rd = reaching_definitions::compute_memory(
+            control_flow_graph,
+            pt.memory_locations()
+        )?;
+        let du = def_use::def_use(&rd, control_flow_graph, Some(pt.memory_locations()))?;
+        let ud = def_use::use_def(&rd, control_flow_graph, Some(pt.memory_locations()))?;
+        Ok(Analysis {
+            control_flow_graph: control_flow_graph,
+            reaching_definitions: rd,
+            def_use: du,
+            use_def: ud,
+            points_to: Some(pt)
         })
     }
 
@@ -70,6 +133,35 @@ impl<'a> Analysis<'a> {
         &self.def_use
     }
 
+    /// Finds the natural loops of the function, with their induction
+    /// variables, trip counts and summaries.
+    pub fn loops(&self) -> Result<Vec<Loop>> {
+        loops::compute(self)
+    }
+
+    /// The memory accessed by each `Load` and `Store`, if this `Analysis`
+    /// tracks memory.
+    pub fn memory_locations(&self) -> Option<&BTreeMap<AnalysisLocation, MemoryLocation>> {
+        self.points_to.as_ref().map(|points_to| points_to.memory_locations())
+    }
+
+    /// Flow-sensitive points-to information for this `Analysis`, if it tracks
+    /// memory.
+    pub fn points_to(&self) -> Option<&PointsTo> {
+        self.points_to.as_ref()
+    }
+
+    /// Computes flow-insensitive points-to information, with pointers tracked
+    /// as described by `regions`.
+    pub fn points_to_flow_insensitive(
+        &self,
+        max: usize,
+        endian: value_set::Endian,
+        regions: &Regions
+    ) -> Result<PointsTo> {
+        points_to::compute_flow_insensitive(self.control_flow_graph, max, endian, regions)
+    }
+
     /// Use Def chains for this `Analysis`.
     pub fn use_def(&self) -> &BTreeMap<AnalysisLocation, BTreeSet<AnalysisLocation>> {
         &self.use_def
@@ -99,17 +191,73 @@ impl<'a> Analysis<'a> {
         &self.reaching_definitions
     }
 
+    /// Recovers the calling convention, arguments, returns and clobbered
+    /// registers of the function.
+    pub fn signature(&self, conventions: &StackConventions)
+    -> Result<il::Signature> {
+        signature::compute(self.control_flow_graph, conventions)
+    }
+
     // /// Simplifies the IL
     // pub fn simplification(&self) -> Result<il::ControlFlowGraph> {
     //     simplification::simplification(self)
     // }
 
+    /// Tracks the stack pointer relative to its value on entry, and recovers
+    /// the stack frame of the function.
+    pub fn stack_pointer(&self, conventions: &StackConventions)
+    -> Result<StackPointer> {
+        stack_pointer::compute(self.control_flow_graph, conventions)
+    }
+
+    /// Recovers the types of the values and memory of the function.
+    ///
+    /// Stack and global slots are only typed when this `Analysis` tracks
+    /// memory.
+    pub fn types(&self) -> Result<il::FunctionTypes> {
+        types::compute(self)
+    }
+
     /// Returns the result of value set analysis
     ///
     /// `max` is the maximum number of values a `LatticeValue::Values` will
     /// hold before being transformed into a `LatticeValue::Join`
     pub fn value_set(&self, max: usize, endian: value_set::Endian)
     -> Result<BTreeMap<AnalysisLocation, LatticeAssignments>> {
-        value_set::compute(self.control_flow_graph, max, endian)
+        self.value_set_with_domain(max, endian, Domain::Constants)
+    }
+
+    /// Returns the result of value set analysis in the given `Domain`
+    ///
+    /// `max` is the maximum number of values a `LatticeValue::Values` will
+    /// hold before being approximated. With `Domain::Constants`, values are
+    /// transformed into a `LatticeValue::Join`. With
+    /// `Domain::StridedIntervals`, values are transformed into a
+    /// `LatticeValue::Interval`, and loops are widened.
+    pub fn value_set_with_domain(
+        &self,
+        max: usize,
+        endian: value_set::Endian,
+        domain: Domain
+    ) -> Result<BTreeMap<AnalysisLocation, LatticeAssignments>> {
+        value_set::compute(
+            self.control_flow_graph,
+            max,
+            endian,
+            domain,
+            &value_set::Regions::new()
+        )
+    }
+
+    /// Returns the result of value set analysis, with pointers into the
+    /// stack, heap and global memory tracked as described by `regions`.
+    pub fn value_set_regions(
+        &self,
+        max: usize,
+        endian: value_set::Endian,
+        domain: Domain,
+        regions: &Regions
+    ) -> Result<BTreeMap<AnalysisLocation, LatticeAssignments>> {
+        value_set::compute(self.control_flow_graph, max, endian, domain, regions)
     }
 }
\ No newline at end of file
@@ -0,0 +1,238 @@
+//! Points-to analysis over Falcon IL.
+//!
+//! The memory accessed by every `Load` and `Store` is resolved into a
+//! `MemoryLocation` over the regions of value-set analysis. Flow-sensitive
+//! results are computed per function. The whole-program mode is
+//! flow-insensitive, and answers queries between locations in different
+//! functions.
+
+use analysis::analysis_location::*;
+use analysis::lattice::{Domain, Region};
+use analysis::memory_location::{self, MemoryLocation};
+use analysis::value_set::{self, Endian, Regions};
+use error::*;
+use il;
+use std::collections::BTreeMap;
+
+
+/// The memory accessed by the loads and stores of one function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PointsTo {
+    memory_locations: BTreeMap<AnalysisLocation, MemoryLocation>
+}
+
+
+impl PointsTo {
+    /// Get the memory accessed by the `Load` or `Store` at a location.
+    ///
+    /// Returns `None` if there is no load or store at this location, or it is
+    /// never reached.
+    pub fn memory_location(&self, analysis_location: &AnalysisLocation)
+    -> Option<&MemoryLocation> {
+        self.memory_locations.get(analysis_location)
+    }
+
+    /// Get the memory accessed by every reachable `Load` and `Store`.
+    pub fn memory_locations(&self) -> &BTreeMap<AnalysisLocation, MemoryLocation> {
+        &self.memory_locations
+    }
+
+    /// Returns true if the accesses at two locations may touch the same
+    /// byte.
+    pub fn may_alias(&self, lhs: &AnalysisLocation, rhs: &AnalysisLocation) -> bool {
+        match (self.memory_location(lhs), self.memory_location(rhs)) {
+            (Some(lhs), Some(rhs)) => lhs.may_alias(rhs),
+            _ => true
+        }
+    }
+
+    /// Returns true if the accesses at two locations are always of the same
+    /// address.
+    pub fn must_alias(&self, lhs: &AnalysisLocation, rhs: &AnalysisLocation) -> bool {
+        match (self.memory_location(lhs), self.memory_location(rhs)) {
+            (Some(lhs), Some(rhs)) => lhs.must_alias(rhs),
+            _ => false
+        }
+    }
+}
+
+
+/// The memory accessed by the loads and stores of every function in a
+/// program, keyed by function address.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProgramPointsTo {
+    functions: BTreeMap<u64, PointsTo>
+}
+
+
+/// Returns true if an access to the stack may touch the frame of a caller,
+/// at or above the value of the stack pointer on entry.
+fn may_access_caller_frame(memory_location: &MemoryLocation) -> bool {
+    match *memory_location {
+        MemoryLocation::Addresses { region: Some(Region::Stack), ref offsets, .. } =>
+            offsets.lo() < 1 << (offsets.bits() - 1),
+        _ => false
+    }
+}
+
+
+impl ProgramPointsTo {
+    /// Get the `PointsTo` of the function at `address`.
+    pub fn function(&self, address: u64) -> Option<&PointsTo> {
+        self.functions.get(&address)
+    }
+
+    fn memory_location(&self, function: u64, analysis_location: &AnalysisLocation)
+    -> Option<&MemoryLocation> {
+        self.functions
+            .get(&function)
+            .and_then(|points_to| points_to.memory_location(analysis_location))
+    }
+
+    /// Returns true if the access at `lhs` in the function at address
+    /// `lhs_function`, and the access at `rhs` in the function at address
+    /// `rhs_function`, may touch the same byte.
+    ///
+    /// Stack regions of different functions only alias when one access may
+    /// touch the frame of its caller. Heap regions of different functions
+    /// never alias, as they are allocated by different calls.
+    pub fn may_alias(
+        &self,
+        lhs_function: u64,
+        lhs: &AnalysisLocation,
+        rhs_function: u64,
+        rhs: &AnalysisLocation
+    ) -> bool {
+        let (lhs_memory, rhs_memory) = match (self.memory_location(lhs_function, lhs),
+                                              self.memory_location(rhs_function, rhs)) {
+            (Some(lhs_memory), Some(rhs_memory)) => (lhs_memory, rhs_memory),
+            _ => return true
+        };
+        if lhs_function == rhs_function {
+            return lhs_memory.may_alias(rhs_memory);
+        }
+        match (lhs_memory, rhs_memory) {
+            (&MemoryLocation::Addresses { region: None, .. },
+             &MemoryLocation::Addresses { region: None, .. }) =>
+                lhs_memory.may_alias(rhs_memory),
+            (&MemoryLocation::Addresses { region: Some(Region::Stack), .. },
+             &MemoryLocation::Addresses { region: Some(Region::Stack), .. }) =>
+                may_access_caller_frame(lhs_memory) || may_access_caller_frame(rhs_memory),
+            (&MemoryLocation::Addresses { .. }, &MemoryLocation::Addresses { .. }) => false,
+            _ => true
+        }
+    }
+
+    /// Returns true if the access at `lhs` in the function at address
+    /// `lhs_function`, and the access at `rhs` in the function at address
+    /// `rhs_function`, are always of the same address.
+    ///
+    /// Accesses in different functions only must alias when they are of the
+    /// same absolute address.
+    pub fn must_alias(
+        &self,
+        lhs_function: u64,
+        lhs: &AnalysisLocation,
+        rhs_function: u64,
+        rhs: &AnalysisLocation
+    ) -> bool {
+        let (lhs_memory, rhs_memory) = match (self.memory_location(lhs_function, lhs),
+                                              self.memory_location(rhs_function, rhs)) {
+            (Some(lhs_memory), Some(rhs_memory)) => (lhs_memory, rhs_memory),
+            _ => return false
+        };
+        if lhs_function == rhs_function {
+            return lhs_memory.must_alias(rhs_memory);
+        }
+        match *lhs_memory {
+            MemoryLocation::Addresses { region: None, .. } => lhs_memory.must_alias(rhs_memory),
+            _ => false
+        }
+    }
+}
+
+
+/// Compute flow-sensitive points-to information for the function in
+/// `control_flow_graph`, with pointers tracked as described by `regions`.
+pub fn compute(
+    control_flow_graph: &il::ControlFlowGraph,
+    max: usize,
+    endian: Endian,
+    regions: &Regions
+) -> Result<PointsTo> {
+    Ok(PointsTo {
+        memory_locations: memory_location::compute(
+            control_flow_graph,
+            max,
+            endian,
+            regions
+        )?
+    })
+}
+
+
+/// Compute flow-insensitive points-to information for the function in
+/// `control_flow_graph`, with pointers tracked as described by `regions`.
+///
+/// Every access is resolved over one set of values which holds at every
+/// location. This is cheaper, but less precise, than `compute`. Scalars which
+/// are written more than once, such as the stack pointer, hold every value
+/// they are ever given, so results are most precise over functions in SSA
+/// form.
+pub fn compute_flow_insensitive(
+    control_flow_graph: &il::ControlFlowGraph,
+    max: usize,
+    endian: Endian,
+    regions: &Regions
+) -> Result<PointsTo> {
+    let state = value_set::compute_flow_insensitive(
+        control_flow_graph,
+        max,
+        endian,
+        Domain::StridedIntervals,
+        regions
+    )?;
+
+    let mut memory_locations = BTreeMap::new();
+    for block in control_flow_graph.blocks() {
+        for instruction in block.instructions() {
+            let (index, bits) = match *instruction.operation() {
+                il::Operation::Load { ref dst, ref index, .. } => (index, dst.bits()),
+                il::Operation::Store { ref index, ref src, .. } => (index, src.bits()),
+                _ => continue
+            };
+            memory_locations.insert(
+                AnalysisLocation::instruction(block.index(), instruction.index()),
+                MemoryLocation::new(&state.eval(index), bits)
+            );
+        }
+    }
+
+    Ok(PointsTo {
+        memory_locations: memory_locations
+    })
+}
+
+
+/// Compute flow-insensitive points-to information for every function in
+/// `program`.
+pub fn program_points_to(
+    program: &il::Program,
+    max: usize,
+    endian: Endian,
+    regions: &Regions
+) -> Result<ProgramPointsTo> {
+    let mut functions = BTreeMap::new();
+    for function in program.functions() {
+        let points_to = compute_flow_insensitive(
+            function.control_flow_graph(),
+            max,
+            endian.clone(),
+            regions
+        )?;
+        functions.insert(function.address(), points_to);
+    }
+    Ok(ProgramPointsTo {
+        functions: functions
+    })
+}
@@ -1,5 +1,6 @@
 use analysis::fixed_point::*;
 use analysis::analysis_location::AnalysisLocation::*;
+use analysis::memory_location::MemoryLocation;
 use error::*;
 use il;
 use std::cmp::{Ord, Ordering, PartialOrd};
@@ -108,20 +109,54 @@ impl fmt::Display for Reaches {
 
 pub fn compute(control_flow_graph: &il::ControlFlowGraph)
 -> Result<BTreeMap<AnalysisLocation, Reaches>> {
-    let reaching_definitions = ReachingDefinitions::new(control_flow_graph);
+    let reaching_definitions = ReachingDefinitions::new(control_flow_graph, None);
+    reaching_definitions.compute()
+}
+
+
+/// Compute reaching definitions where a `Store` only kills the stores whose
+/// memory it covers, as given by `memory_locations`, instead of every store
+/// to the same array.
+pub fn compute_memory(
+    control_flow_graph: &il::ControlFlowGraph,
+    memory_locations: &BTreeMap<AnalysisLocation, MemoryLocation>
+) -> Result<BTreeMap<AnalysisLocation, Reaches>> {
+    let reaching_definitions = ReachingDefinitions::new(
+        control_flow_graph,
+        Some(memory_locations)
+    );
     reaching_definitions.compute()
 }
 
 
 struct ReachingDefinitions<'a> {
-    control_flow_graph: &'a il::ControlFlowGraph
+    control_flow_graph: &'a il::ControlFlowGraph,
+    memory_locations: Option<&'a BTreeMap<AnalysisLocation, MemoryLocation>>
 }
 
 
 impl<'a> ReachingDefinitions<'a> {
-    pub fn new(control_flow_graph: &'a il::ControlFlowGraph) -> ReachingDefinitions<'a> {
+    pub fn new(
+        control_flow_graph: &'a il::ControlFlowGraph,
+        memory_locations: Option<&'a BTreeMap<AnalysisLocation, MemoryLocation>>
+    ) -> ReachingDefinitions<'a> {
         ReachingDefinitions {
-            control_flow_graph: control_flow_graph
+            control_flow_graph: control_flow_graph,
+            memory_locations: memory_locations
+        }
+    }
+
+    /// Returns true if the definition at `this_location` kills the definition
+    /// of the same variable at `kill_location`.
+    fn kills(&self, this_location: &AnalysisLocation, kill_location: &AnalysisLocation)
+    -> bool {
+        let memory_locations = match self.memory_locations {
+            Some(memory_locations) => memory_locations,
+            None => return true
+        };
+        match (memory_locations.get(this_location), memory_locations.get(kill_location)) {
+            (Some(this_memory), Some(kill_memory)) => this_memory.covers(kill_memory),
+            _ => false
         }
     }
 
@@ -161,8 +196,8 @@ impl<'f> FixedPointAnalysis<Reaches> for ReachingDefinitions<'f> {
             // Instructions..
             Instruction(ref il) => { 
                 // If this instruction writes to a variable
-                if let Some(this_dst) = il.find(self.control_flow_graph)?
-                                          .variable_written() {
+                let this_instruction = il.find(self.control_flow_graph)?;
+                if let Some(this_dst) = this_instruction.variable_written() {
 
                     let mut to_kill = Vec::new();
                     // Evaluate every location that reaches this location as a
@@ -173,8 +208,12 @@ impl<'f> FixedPointAnalysis<Reaches> for ReachingDefinitions<'f> {
                             // If this candidate writes to an instruction
                             if let Some(dst) = il.find(self.control_flow_graph)?
                                                  .variable_written() {
-                                // Do they write to the same variable?
-                                if this_dst.name() == dst.name() {
+                                // Do they write to the same variable? A store
+                                // with known memory locations must also
+                                // cover the memory written by the other.
+                                if this_dst.name() == dst.name() &&
+                                   (!this_instruction.is_store() ||
+                                    self.kills(analysis_location, kill_location)) {
                                     // Add this kill_location to be killed.
                                     to_kill.push(kill_location.clone());
                                 }
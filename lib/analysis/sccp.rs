@@ -0,0 +1,691 @@
+//! Sparse Conditional Constant Propagation over `il::ControlFlowGraph`s in SSA
+//! form.
+//!
+//! This is Wegman and Zadeck's SCCP. Every scalar defined in the graph starts
+//! at `LatticeValue::Meet`, and values are only lowered towards
+//! `LatticeValue::Join` once the location defining them is discovered to be
+//! executable. Scalars which are read, but never written, in the graph are
+//! inputs and start at `LatticeValue::Join`.
+//!
+//! Edges are executable when their head block is executable, and their
+//! condition may be non-zero. `Brc` targets which resolve to a single constant
+//! are recorded, and rewritten to that constant. A phi only joins the operands
+//! flowing in over executable edges.
+//!
+//! Loads are `LatticeValue::Join`, unless a `Store` to an identical index
+//! expression reaches the load along a straight-line path without another
+//! `Store` or a `Raise` in between. Because indices are in SSA form, identical
+//! expressions hold identical values. We also allow this path to cross a
+//! `Brc` which targets the address of the load itself. This recovers the
+//! `call next; pop ebx` thunk used by position independent x86 code.
+
+use analysis::analysis_location::*;
+use analysis::lattice::*;
+use error::*;
+use il;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+
+/// The results of sparse conditional constant propagation.
+#[derive(Clone, Debug)]
+pub struct Sccp {
+    assignments: LatticeAssignments,
+    executable_blocks: BTreeSet<u64>,
+    executable_edges: BTreeSet<EdgeLocation>,
+    brc_targets: BTreeMap<InstructionLocation, u64>,
+    control_flow_graph: il::ControlFlowGraph
+}
+
+
+impl Sccp {
+    /// The `LatticeValue` of every scalar in the graph.
+    pub fn assignments(&self) -> &LatticeAssignments {
+        &self.assignments
+    }
+
+    /// Indices of every block which may be executed.
+    pub fn executable_blocks(&self) -> &BTreeSet<u64> {
+        &self.executable_blocks
+    }
+
+    /// Every edge which may be taken.
+    pub fn executable_edges(&self) -> &BTreeSet<EdgeLocation> {
+        &self.executable_edges
+    }
+
+    /// `Brc` instructions whose target resolved to a single address.
+    pub fn brc_targets(&self) -> &BTreeMap<InstructionLocation, u64> {
+        &self.brc_targets
+    }
+
+    /// The graph rewritten with the results of this analysis.
+    ///
+    /// Blocks and edges which are never executed are removed, scalars with
+    /// constant values are replaced with those constants, and edges whose
+    /// conditions are always true become unconditional.
+    pub fn control_flow_graph(&self) -> &il::ControlFlowGraph {
+        &self.control_flow_graph
+    }
+
+    /// Consume this `Sccp`, and return the rewritten graph.
+    pub fn into_control_flow_graph(self) -> il::ControlFlowGraph {
+        self.control_flow_graph
+    }
+}
+
+
+/// Returns the single constant held by a `LatticeValue`, if there is one.
+fn lattice_constant(lattice_value: &LatticeValue) -> Option<&il::Constant> {
+    match *lattice_value {
+        LatticeValue::Values(ref values) => {
+            if values.len() == 1 {
+                values.iter().next()
+            }
+            else {
+                None
+            }
+        },
+        _ => None
+    }
+}
+
+
+/// Lowers `old` by `new`. SCCP tracks single constants, so anything holding
+/// more or less than one value becomes `LatticeValue::Join`.
+fn lower(old: &LatticeValue, new: &LatticeValue) -> LatticeValue {
+    match old.clone().join(new) {
+        LatticeValue::Values(values) => {
+            if values.len() == 1 {
+                LatticeValue::Values(values)
+            }
+            else {
+                LatticeValue::Join
+            }
+        },
+        lattice_value => lattice_value
+    }
+}
+
+
+/// Returns the scalar held by a `MultiVar`, if it holds a scalar.
+fn multi_var_scalar(multi_var: &il::MultiVar) -> Option<&il::Scalar> {
+    match *multi_var {
+        il::MultiVar::Scalar(ref scalar) => Some(scalar),
+        il::MultiVar::Array(_) => None
+    }
+}
+
+
+struct SccpState<'s> {
+    control_flow_graph: &'s il::ControlFlowGraph,
+    assignments: LatticeAssignments,
+    uses: BTreeMap<il::Scalar, BTreeSet<AnalysisLocation>>,
+    executable_blocks: BTreeSet<u64>,
+    executable_edges: BTreeSet<EdgeLocation>,
+    flow_worklist: VecDeque<EdgeLocation>,
+    ssa_worklist: VecDeque<AnalysisLocation>
+}
+
+
+impl<'s> SccpState<'s> {
+    fn new(control_flow_graph: &'s il::ControlFlowGraph) -> SccpState<'s> {
+        let mut defined: BTreeSet<il::Scalar> = BTreeSet::new();
+        let mut uses: BTreeMap<il::Scalar, BTreeSet<AnalysisLocation>> = BTreeMap::new();
+
+        for block in control_flow_graph.blocks() {
+            for instruction in block.instructions() {
+                let location = AnalysisLocation::instruction(
+                    block.index(),
+                    instruction.index()
+                );
+                if let Some(variable) = instruction.variable_written() {
+                    if let il::MultiVar::Scalar(scalar) = variable.multi_var_clone() {
+                        defined.insert(scalar);
+                    }
+                }
+                for variable in instruction.variables_read() {
+                    if let il::MultiVar::Scalar(scalar) = variable.multi_var_clone() {
+                        uses.entry(scalar)
+                            .or_insert(BTreeSet::new())
+                            .insert(location.clone());
+                    }
+                }
+            }
+        }
+
+        for edge in control_flow_graph.edges() {
+            if let Some(ref condition) = *edge.condition() {
+                let location = AnalysisLocation::edge(edge.head(), edge.tail());
+                for scalar in condition.collect_scalars() {
+                    uses.entry(scalar.clone())
+                        .or_insert(BTreeSet::new())
+                        .insert(location.clone());
+                }
+            }
+        }
+
+        // Scalars which are never written are inputs to the graph, and could
+        // hold any value.
+        let mut assignments = LatticeAssignments::new(1);
+        for scalar in uses.keys() {
+            if !defined.contains(scalar) {
+                assignments.set(scalar.clone(), LatticeValue::Join);
+            }
+        }
+
+        SccpState {
+            control_flow_graph: control_flow_graph,
+            assignments: assignments,
+            uses: uses,
+            executable_blocks: BTreeSet::new(),
+            executable_edges: BTreeSet::new(),
+            flow_worklist: VecDeque::new(),
+            ssa_worklist: VecDeque::new()
+        }
+    }
+
+
+    fn value(&self, scalar: &il::Scalar) -> LatticeValue {
+        match self.assignments.get(scalar) {
+            Some(lattice_value) => lattice_value.clone(),
+            None => LatticeValue::Meet
+        }
+    }
+
+
+    /// Lowers the value of a scalar, and queues its uses if it changed.
+    fn lower_scalar(&mut self, scalar: &il::Scalar, lattice_value: &LatticeValue) {
+        let old = self.value(scalar);
+        let new = lower(&old, lattice_value);
+        if new == old {
+            return;
+        }
+        self.assignments.set(scalar.clone(), new);
+        if let Some(uses) = self.uses.get(scalar) {
+            for location in uses {
+                self.ssa_worklist.push_back(location.clone());
+            }
+        }
+    }
+
+
+    /// Records that `location` must be revisited when `scalar` changes.
+    fn add_use(&mut self, scalar: &il::Scalar, location: &AnalysisLocation) {
+        self.uses.entry(scalar.clone())
+                 .or_insert(BTreeSet::new())
+                 .insert(location.clone());
+    }
+
+
+    /// Marks a block as executable, and visits all of its instructions and
+    /// outgoing edges.
+    fn visit_block(&mut self, block_index: u64) -> Result<()> {
+        if !self.executable_blocks.insert(block_index) {
+            return Ok(());
+        }
+
+        let block = self.control_flow_graph
+                        .block(block_index)
+                        .ok_or("Could not find block")?;
+
+        for instruction in block.instructions() {
+            let location = InstructionLocation::new(block_index, instruction.index());
+            self.visit_instruction(&location)?;
+        }
+
+        for edge in self.control_flow_graph
+                        .graph()
+                        .edges_out(block_index)
+                        .ok_or("Could not find edges for block")? {
+            self.visit_edge(&EdgeLocation::new(edge.head(), edge.tail()))?;
+        }
+
+        Ok(())
+    }
+
+
+    /// Visits the phis of an executable block.
+    fn visit_phis(&mut self, block_index: u64) -> Result<()> {
+        if !self.executable_blocks.contains(&block_index) {
+            return Ok(());
+        }
+
+        let block = self.control_flow_graph
+                        .block(block_index)
+                        .ok_or("Could not find block")?;
+
+        for instruction in block.instructions().iter().filter(|i| i.is_phi()) {
+            let location = InstructionLocation::new(block_index, instruction.index());
+            self.visit_instruction(&location)?;
+        }
+
+        Ok(())
+    }
+
+
+    /// Queues an edge as executable if its condition may be non-zero.
+    fn visit_edge(&mut self, location: &EdgeLocation) -> Result<()> {
+        if self.executable_edges.contains(location) {
+            return Ok(());
+        }
+
+        let edge = location.find(self.control_flow_graph)
+                           .ok_or("Could not find edge")?;
+
+        let executable = match *edge.condition() {
+            None => true,
+            Some(ref condition) => {
+                match self.assignments.eval(condition) {
+                    LatticeValue::Meet => false,
+                    LatticeValue::Join |
+                    LatticeValue::Interval(_) |
+                    LatticeValue::Pointer(_, _) => true,
+                    LatticeValue::Values(ref values) =>
+                        values.is_empty() || values.iter().any(|c| c.value() != 0)
+                }
+            }
+        };
+
+        if executable {
+            self.flow_worklist.push_back(location.clone());
+        }
+
+        Ok(())
+    }
+
+
+    fn visit_instruction(&mut self, location: &InstructionLocation) -> Result<()> {
+        let instruction = location.find(self.control_flow_graph)?;
+
+        match *instruction.operation() {
+            il::Operation::Assign { ref dst, ref src } => {
+                let lattice_value = self.assignments.eval(src);
+                self.lower_scalar(dst, &lattice_value);
+            },
+            il::Operation::Load { ref dst, ref index, .. } => {
+                let lattice_value = match self.forwarded_store(location, index)? {
+                    Some(src) => self.assignments.eval(&src),
+                    None => LatticeValue::Join
+                };
+                self.lower_scalar(dst, &lattice_value);
+            },
+            il::Operation::Phi { ref dst, ref src } => {
+                if let Some(dst) = multi_var_scalar(dst) {
+                    // Only operands flowing in over executable edges count.
+                    let mut lattice_value = LatticeValue::Meet;
+                    for (predecessor, multi_var) in src {
+                        let edge = EdgeLocation::new(*predecessor, location.block_index());
+                        if !self.executable_edges.contains(&edge) {
+                            continue;
+                        }
+                        if let Some(scalar) = multi_var_scalar(multi_var) {
+                            lattice_value = lower(&lattice_value, &self.value(scalar));
+                        }
+                    }
+                    self.lower_scalar(dst, &lattice_value);
+                }
+            },
+            il::Operation::Store { .. } |
+            il::Operation::Brc { .. } |
+            il::Operation::Raise { .. } => {}
+        }
+
+        Ok(())
+    }
+
+
+    /// Searches backwards from a load for a store to the same index.
+    ///
+    /// Every scalar the result depends on is registered as a use of the load,
+    /// so the load will be revisited if those scalars change.
+    fn forwarded_store(&mut self, location: &InstructionLocation, index: &il::Expression)
+    -> Result<Option<il::Expression>> {
+        let load_location: AnalysisLocation = location.clone().into();
+        let load_address = location.find(self.control_flow_graph)?.address();
+
+        let mut visited: BTreeSet<u64> = BTreeSet::new();
+        let mut block_index = location.block_index();
+        let mut until = Some(location.instruction_index());
+
+        loop {
+            visited.insert(block_index);
+
+            let block = self.control_flow_graph
+                            .block(block_index)
+                            .ok_or("Could not find block")?;
+
+            let instructions = block.instructions();
+            let end = match until {
+                Some(instruction_index) =>
+                    instructions.iter()
+                                .position(|i| i.index() == instruction_index)
+                                .ok_or("Could not find instruction")?,
+                None => instructions.len()
+            };
+
+            for instruction in instructions[0..end].iter().rev() {
+                match *instruction.operation() {
+                    il::Operation::Store { index: ref store_index, ref src, .. } => {
+                        if store_index != index {
+                            return Ok(None);
+                        }
+                        for scalar in src.collect_scalars() {
+                            self.add_use(scalar, &load_location);
+                        }
+                        return Ok(Some(src.clone()));
+                    },
+                    il::Operation::Brc { ref target, .. } => {
+                        for scalar in target.collect_scalars() {
+                            self.add_use(scalar, &load_location);
+                        }
+                        let target = self.assignments.eval(target);
+                        let target = lattice_constant(&target).map(|c| c.value());
+                        if load_address.is_none() || target != load_address {
+                            return Ok(None);
+                        }
+                    },
+                    il::Operation::Raise { .. } => return Ok(None),
+                    il::Operation::Assign { .. } |
+                    il::Operation::Load { .. } |
+                    il::Operation::Phi { .. } => {}
+                }
+            }
+
+            let edges_in = self.control_flow_graph
+                               .graph()
+                               .edges_in(block_index)
+                               .ok_or("Could not find edges for block")?;
+
+            if edges_in.len() != 1 || visited.contains(&edges_in[0].head()) {
+                return Ok(None);
+            }
+
+            block_index = edges_in[0].head();
+            until = None;
+        }
+    }
+
+
+    fn solve(&mut self) -> Result<()> {
+        let entry = match self.control_flow_graph.entry() {
+            Some(entry) => entry,
+            None => bail!("no entry vertex set for ControlFlowGraph")
+        };
+
+        self.visit_block(entry)?;
+
+        loop {
+            if let Some(edge_location) = self.flow_worklist.pop_front() {
+                if self.executable_edges.insert(edge_location.clone()) {
+                    // A newly executable edge brings a new operand to the phis
+                    // of a block which may already be executable.
+                    self.visit_phis(edge_location.tail())?;
+                    self.visit_block(edge_location.tail())?;
+                }
+            }
+            else if let Some(location) = self.ssa_worklist.pop_front() {
+                match location {
+                    AnalysisLocation::Instruction(ref location) => {
+                        if self.executable_blocks.contains(&location.block_index()) {
+                            self.visit_instruction(location)?;
+                        }
+                    },
+                    AnalysisLocation::Edge(ref location) => {
+                        if self.executable_blocks.contains(&location.head()) {
+                            self.visit_edge(location)?;
+                        }
+                    },
+                    AnalysisLocation::EmptyBlock(_) => {}
+                }
+            }
+            else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Replaces every sub-expression with a constant value by that constant.
+fn substitute(expression: &il::Expression, assignments: &LatticeAssignments)
+-> Result<il::Expression> {
+    if let Some(constant) = lattice_constant(&assignments.eval(expression)) {
+        return Ok(constant.clone().into());
+    }
+
+    Ok(match *expression {
+        il::Expression::Scalar(_) |
+        il::Expression::Constant(_) => expression.clone(),
+        il::Expression::Add(ref lhs, ref rhs) =>
+            il::Expression::add(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Sub(ref lhs, ref rhs) =>
+            il::Expression::sub(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Mul(ref lhs, ref rhs) =>
+            il::Expression::mul(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Divu(ref lhs, ref rhs) =>
+            il::Expression::divu(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Modu(ref lhs, ref rhs) =>
+            il::Expression::modu(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Divs(ref lhs, ref rhs) =>
+            il::Expression::divs(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Mods(ref lhs, ref rhs) =>
+            il::Expression::mods(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::And(ref lhs, ref rhs) =>
+            il::Expression::and(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Or(ref lhs, ref rhs) =>
+            il::Expression::or(substitute(lhs, assignments)?,
+                               substitute(rhs, assignments)?)?,
+        il::Expression::Xor(ref lhs, ref rhs) =>
+            il::Expression::xor(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Shl(ref lhs, ref rhs) =>
+            il::Expression::shl(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Shr(ref lhs, ref rhs) =>
+            il::Expression::shr(substitute(lhs, assignments)?,
+                                substitute(rhs, assignments)?)?,
+        il::Expression::Cmpeq(ref lhs, ref rhs) =>
+            il::Expression::cmpeq(substitute(lhs, assignments)?,
+                                  substitute(rhs, assignments)?)?,
+        il::Expression::Cmpneq(ref lhs, ref rhs) =>
+            il::Expression::cmpneq(substitute(lhs, assignments)?,
+                                   substitute(rhs, assignments)?)?,
+        il::Expression::Cmplts(ref lhs, ref rhs) =>
+            il::Expression::cmplts(substitute(lhs, assignments)?,
+                                   substitute(rhs, assignments)?)?,
+        il::Expression::Cmpltu(ref lhs, ref rhs) =>
+            il::Expression::cmpltu(substitute(lhs, assignments)?,
+                                   substitute(rhs, assignments)?)?,
+        il::Expression::Zext(bits, ref src) =>
+            il::Expression::zext(bits, substitute(src, assignments)?)?,
+        il::Expression::Sext(bits, ref src) =>
+            il::Expression::sext(bits, substitute(src, assignments)?)?,
+        il::Expression::Trun(bits, ref src) =>
+            il::Expression::trun(bits, substitute(src, assignments)?)?,
+        il::Expression::Fadd(ref lhs, ref rhs) =>
+            il::Expression::fadd(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Fsub(ref lhs, ref rhs) =>
+            il::Expression::fsub(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Fmul(ref lhs, ref rhs) =>
+            il::Expression::fmul(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Fdiv(ref lhs, ref rhs) =>
+            il::Expression::fdiv(substitute(lhs, assignments)?,
+                                 substitute(rhs, assignments)?)?,
+        il::Expression::Fcmpeq(ref lhs, ref rhs) =>
+            il::Expression::fcmpeq(substitute(lhs, assignments)?,
+                                   substitute(rhs, assignments)?)?,
+        il::Expression::Fcmplt(ref lhs, ref rhs) =>
+            il::Expression::fcmplt(substitute(lhs, assignments)?,
+                                   substitute(rhs, assignments)?)?,
+        il::Expression::Fcmpuo(ref lhs, ref rhs) =>
+            il::Expression::fcmpuo(substitute(lhs, assignments)?,
+                                   substitute(rhs, assignments)?)?,
+        il::Expression::Fext(bits, ref src) =>
+            il::Expression::fext(bits, substitute(src, assignments)?)?,
+        il::Expression::Itof(bits, ref src) =>
+            il::Expression::itof(bits, substitute(src, assignments)?)?,
+        il::Expression::Ftoi(bits, ref src) =>
+            il::Expression::ftoi(bits, substitute(src, assignments)?)?
+    })
+}
+
+
+/// Rewrites a single operation with the results of SCCP. Returns `None` if
+/// the operation should be left as is. `predecessors` are the predecessors of
+/// the operation's block with executable edges to it.
+fn rewrite_operation(
+    operation: &il::Operation,
+    predecessors: &BTreeSet<u64>,
+    assignments: &LatticeAssignments
+) -> Result<Option<il::Operation>> {
+    Ok(Some(match *operation {
+        il::Operation::Assign { ref dst, ref src } =>
+            il::Operation::assign(dst.clone(), substitute(src, assignments)?),
+        il::Operation::Store { ref dst, ref index, ref src } =>
+            il::Operation::store(
+                dst.clone(),
+                substitute(index, assignments)?,
+                substitute(src, assignments)?
+            ),
+        il::Operation::Load { ref dst, ref index, ref src } => {
+            let lattice_value = match assignments.get(dst) {
+                Some(lattice_value) => lattice_value.clone(),
+                None => LatticeValue::Join
+            };
+            match lattice_constant(&lattice_value) {
+                Some(constant) =>
+                    il::Operation::assign(dst.clone(), constant.clone().into()),
+                None =>
+                    il::Operation::load(
+                        dst.clone(),
+                        substitute(index, assignments)?,
+                        src.clone()
+                    )
+            }
+        },
+        il::Operation::Brc { ref target, ref condition } =>
+            il::Operation::brc(
+                substitute(target, assignments)?,
+                substitute(condition, assignments)?
+            ),
+        il::Operation::Phi { ref dst, ref src } => {
+            // Operands flowing in over unexecutable edges are dropped, along
+            // with those edges.
+            let src = src.iter()
+                         .filter(|&(predecessor, _)| predecessors.contains(predecessor))
+                         .map(|(predecessor, multi_var)| (*predecessor, multi_var.clone()))
+                         .collect::<BTreeMap<u64, il::MultiVar>>();
+            match (dst, src.len()) {
+                (&il::MultiVar::Scalar(ref dst), 1) => {
+                    let src = src.values()
+                                 .next()
+                                 .and_then(multi_var_scalar)
+                                 .ok_or("Phi of mixed variables")?;
+                    il::Operation::assign(
+                        dst.clone(),
+                        substitute(&src.clone().into(), assignments)?
+                    )
+                },
+                _ => il::Operation::phi(dst.clone(), src)
+            }
+        },
+        il::Operation::Raise { .. } => return Ok(None)
+    }))
+}
+
+
+/// Performs sparse conditional constant propagation over a `ControlFlowGraph`
+/// in SSA form.
+pub fn sccp(control_flow_graph: &il::ControlFlowGraph) -> Result<Sccp> {
+    let (assignments, executable_blocks, executable_edges) = {
+        let mut state = SccpState::new(control_flow_graph);
+        state.solve()?;
+        (state.assignments, state.executable_blocks, state.executable_edges)
+    };
+
+    let mut brc_targets: BTreeMap<InstructionLocation, u64> = BTreeMap::new();
+    let mut rewritten = control_flow_graph.clone();
+
+    for block_index in &executable_blocks {
+        let predecessors = executable_edges.iter()
+                                           .filter(|edge| edge.tail() == *block_index)
+                                           .map(|edge| edge.head())
+                                           .collect::<BTreeSet<u64>>();
+        let block = rewritten.block_mut(*block_index)
+                             .ok_or("Could not find block")?;
+        for instruction in block.instructions_mut() {
+            if let il::Operation::Brc { ref target, .. } = *instruction.operation() {
+                if let Some(constant) = lattice_constant(&assignments.eval(target)) {
+                    brc_targets.insert(
+                        InstructionLocation::new(*block_index, instruction.index()),
+                        constant.value()
+                    );
+                }
+            }
+            let operation = rewrite_operation(instruction.operation(), &predecessors, &assignments)?;
+            if let Some(operation) = operation {
+                *instruction.operation_mut() = operation;
+            }
+        }
+    }
+
+    let mut dead_edges = Vec::new();
+    for edge in rewritten.edges_mut() {
+        let location = EdgeLocation::new(edge.head(), edge.tail());
+        if !executable_edges.contains(&location) {
+            dead_edges.push(location);
+            continue;
+        }
+        let condition = match *edge.condition() {
+            Some(ref condition) => {
+                let always_taken = match lattice_constant(&assignments.eval(condition)) {
+                    Some(constant) => constant.value() != 0,
+                    None => false
+                };
+                if always_taken {
+                    None
+                }
+                else {
+                    Some(substitute(condition, &assignments)?)
+                }
+            },
+            None => None
+        };
+        *edge.condition_mut() = condition;
+    }
+
+    for location in dead_edges {
+        rewritten.remove_edge(location.head(), location.tail())?;
+    }
+
+    let dead_blocks = rewritten.blocks()
+                               .iter()
+                               .map(|block| block.index())
+                               .filter(|index| !executable_blocks.contains(index))
+                               .collect::<Vec<u64>>();
+
+    for block_index in dead_blocks {
+        rewritten.remove_block(block_index)?;
+    }
+
+    Ok(Sccp {
+        assignments: assignments,
+        executable_blocks: executable_blocks,
+        executable_edges: executable_edges,
+        brc_targets: brc_targets,
+        control_flow_graph: rewritten
+    })
+}
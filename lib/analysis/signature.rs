@@ -0,0 +1,293 @@
+//! Recovery of function signatures.
+//!
+//! Arguments are the registers live on entry to a function, and the stack
+//! slots above the return address it accesses. Returns are the registers
+//! written on every path to a return. Registers which are written, but do
+//! not hold their value on entry at every return, are clobbered.
+//!
+//! Signatures are recovered for the calling conventions of functions lifted
+//! by `translator::x86`.
+
+use analysis::analysis_location::AnalysisLocation::*;
+use analysis::fixed_point::*;
+use analysis::stack_pointer::{self, StackConventions};
+use error::*;
+use il;
+use std::collections::{BTreeMap, BTreeSet};
+
+
+/// Scalars read by an expression, ignoring idioms such as `xor eax, eax`
+/// which do not depend on their operands.
+pub(crate) fn scalars_read(expression: &il::Expression) -> Vec<&il::Scalar> {
+    match *expression {
+        il::Expression::Xor(ref lhs, ref rhs) |
+        il::Expression::Sub(ref lhs, ref rhs) if lhs == rhs => Vec::new(),
+        _ => expression.collect_scalars()
+    }
+}
+
+
+/// Backwards liveness of scalars.
+struct Liveness<'l> {
+    control_flow_graph: &'l il::ControlFlowGraph,
+    conventions: &'l StackConventions,
+    returns: BTreeSet<AnalysisLocation>
+}
+
+
+impl<'l> FixedPointAnalysis<BTreeSet<il::Scalar>> for Liveness<'l> {
+    fn trans(
+        &self,
+        analysis_location: &AnalysisLocation,
+        state: &Option<BTreeSet<il::Scalar>>
+    ) -> Result<BTreeSet<il::Scalar>> {
+        let mut live = match *state {
+            Some(ref state) => state.clone(),
+            None => BTreeSet::new()
+        };
+
+        match *analysis_location {
+            Edge(ref el) => {
+                let edge = el.find(self.control_flow_graph)
+                             .ok_or("Could not find edge")?;
+                if let Some(ref condition) = *edge.condition() {
+                    live.extend(condition.collect_scalars().into_iter().cloned());
+                }
+            },
+            Instruction(ref il) => {
+                match *il.find(self.control_flow_graph)?.operation() {
+                    il::Operation::Assign { ref dst, ref src } => {
+                        live.remove(dst);
+                        live.extend(scalars_read(src).into_iter().cloned());
+                    },
+                    il::Operation::Load { ref dst, ref index, .. } => {
+                        live.remove(dst);
+                        live.extend(index.collect_scalars().into_iter().cloned());
+                    },
+                    il::Operation::Store { ref index, ref src, .. } => {
+                        live.extend(index.collect_scalars().into_iter().cloned());
+                        live.extend(src.collect_scalars().into_iter().cloned());
+                    },
+                    il::Operation::Brc { ref target, ref condition } => {
+                        if self.returns.contains(analysis_location) {
+                            // Nothing after a return is part of this function
+                            live.clear();
+                        }
+                        else {
+                            for scalar in self.conventions.clobbered() {
+                                live.remove(scalar);
+                            }
+                        }
+                        live.extend(target.collect_scalars().into_iter().cloned());
+                        live.extend(condition.collect_scalars().into_iter().cloned());
+                    },
+                    il::Operation::Phi { ref dst, ref src } => {
+                        if let il::MultiVar::Scalar(ref dst) = *dst {
+                            live.remove(dst);
+                        }
+                        for src in src.values() {
+                            if let il::MultiVar::Scalar(ref src) = *src {
+                                live.insert(src.clone());
+                            }
+                        }
+                    },
+                    il::Operation::Raise { ref expr } => {
+                        live.extend(expr.collect_scalars().into_iter().cloned());
+                    }
+                }
+            },
+            EmptyBlock(_) => {}
+        }
+
+        Ok(live)
+    }
+
+    fn join(&self, mut state0: BTreeSet<il::Scalar>, state1: &BTreeSet<il::Scalar>)
+    -> Result<BTreeSet<il::Scalar>> {
+        state0.extend(state1.iter().cloned());
+        Ok(state0)
+    }
+}
+
+
+/// Scalars written on every path to a location.
+struct Written<'w> {
+    control_flow_graph: &'w il::ControlFlowGraph,
+    conventions: &'w StackConventions,
+    returns: &'w BTreeSet<AnalysisLocation>
+}
+
+
+impl<'w> FixedPointAnalysis<BTreeSet<il::Scalar>> for Written<'w> {
+    fn trans(
+        &self,
+        analysis_location: &AnalysisLocation,
+        state: &Option<BTreeSet<il::Scalar>>
+    ) -> Result<BTreeSet<il::Scalar>> {
+        let mut written = match *state {
+            Some(ref state) => state.clone(),
+            None => BTreeSet::new()
+        };
+
+        if let Instruction(ref il) = *analysis_location {
+            match *il.find(self.control_flow_graph)?.operation() {
+                il::Operation::Assign { ref dst, .. } |
+                il::Operation::Load { ref dst, .. } => {
+                    written.insert(dst.clone());
+                },
+                il::Operation::Brc { .. } => {
+                    if !self.returns.contains(analysis_location) {
+                        written.extend(self.conventions.clobbered().iter().cloned());
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        Ok(written)
+    }
+
+    fn join(&self, state0: BTreeSet<il::Scalar>, state1: &BTreeSet<il::Scalar>)
+    -> Result<BTreeSet<il::Scalar>> {
+        Ok(state0.intersection(state1).cloned().collect())
+    }
+}
+
+
+/// Recover the signature of the function in `control_flow_graph`.
+///
+/// Registers in `conventions.clobbered()` may carry arguments and return
+/// values. A function with arguments in registers is `Fastcall`, a function
+/// which removes its arguments from the stack is `Stdcall`, and all others
+/// are `Cdecl`.
+pub fn compute(control_flow_graph: &il::ControlFlowGraph, conventions: &StackConventions)
+-> Result<il::Signature> {
+    let stack_pointer = stack_pointer::compute(control_flow_graph, conventions)?;
+    let returns = stack_pointer.returns()
+        .keys()
+        .cloned()
+        .collect::<BTreeSet<AnalysisLocation>>();
+
+    let entry = control_flow_graph.entry()
+        .and_then(|entry| control_flow_graph.block(entry))
+        .map(|block| match block.instructions().first() {
+            Some(instruction) =>
+                AnalysisLocation::instruction(block.index(), instruction.index()),
+            None => AnalysisLocation::empty_block(block.index())
+        })
+        .ok_or("Function has no entry")?;
+
+    // Arguments
+    let liveness = Liveness {
+        control_flow_graph: control_flow_graph,
+        conventions: conventions,
+        returns: returns.clone()
+    };
+    let live: BTreeMap<AnalysisLocation, BTreeSet<il::Scalar>> =
+        fixed_point_backward(&liveness, control_flow_graph)?;
+    let live_in = live.get(&entry).cloned().unwrap_or(BTreeSet::new());
+
+    let mut arguments = conventions.clobbered()
+        .iter()
+        .filter(|scalar| live_in.contains(scalar))
+        .map(|scalar| il::Parameter::Scalar(scalar.clone()))
+        .collect::<Vec<il::Parameter>>();
+    let register_arguments = !arguments.is_empty();
+    for variable in stack_pointer.frame().arguments() {
+        arguments.push(il::Parameter::Stack {
+            offset: variable.offset(),
+            bits: variable.bits()
+        });
+    }
+
+    // Returns
+    let written = Written {
+        control_flow_graph: control_flow_graph,
+        conventions: conventions,
+        returns: &returns
+    };
+    let written: BTreeMap<AnalysisLocation, BTreeSet<il::Scalar>> =
+        fixed_point_forward(&written, control_flow_graph)?;
+    let mut written_at_returns: Option<BTreeSet<il::Scalar>> = None;
+    for location in &returns {
+        let written = written.get(location).cloned().unwrap_or(BTreeSet::new());
+        written_at_returns = Some(match written_at_returns {
+            Some(w) => w.intersection(&written).cloned().collect(),
+            None => written
+        });
+    }
+    let written_at_returns = written_at_returns.unwrap_or(BTreeSet::new());
+    let return_values = conventions.clobbered()
+        .iter()
+        .filter(|scalar| written_at_returns.contains(scalar))
+        .cloned()
+        .collect::<Vec<il::Scalar>>();
+
+    // Clobbered
+    let preserved = stack_pointer.preserved();
+    let mut written_anywhere = BTreeSet::new();
+    for block in control_flow_graph.blocks() {
+        for instruction in block.instructions() {
+            match *instruction.operation() {
+                il::Operation::Assign { ref dst, .. } |
+                il::Operation::Load { ref dst, .. } => {
+                    written_anywhere.insert(dst.clone());
+                },
+                il::Operation::Brc { .. } => {
+                    written_anywhere.extend(conventions.clobbered().iter().cloned());
+                },
+                _ => {}
+            }
+        }
+    }
+    let clobbered = conventions.registers()
+        .iter()
+        .filter(|register| written_anywhere.contains(register)
+                           && !preserved.contains(register)
+                           && !return_values.contains(register))
+        .cloned()
+        .collect::<Vec<il::Scalar>>();
+
+    let stack_cleanup = stack_pointer.cleanup().unwrap_or(0);
+
+    let calling_convention = if register_arguments {
+        il::CallingConvention::Fastcall
+    }
+    else if stack_cleanup > 0 {
+        il::CallingConvention::Stdcall
+    }
+    else {
+        il::CallingConvention::Cdecl
+    };
+
+    Ok(il::Signature::new(
+        calling_convention,
+        arguments,
+        return_values,
+        clobbered,
+        stack_cleanup
+    ))
+}
+
+
+/// Recover the signature of every function in `program`, and store it in
+/// that function.
+///
+/// Signatures are recovered twice, so that the stack cleanup of every
+/// function is known when recovering the signatures of its callers.
+pub fn recover_signatures(program: &mut il::Program, conventions: &StackConventions)
+-> Result<()> {
+    let mut conventions = conventions.clone();
+    for _ in 0..2 {
+        let mut cleanups = Vec::new();
+        for function in program.functions_mut() {
+            let signature = compute(function.control_flow_graph(), &conventions)?;
+            cleanups.push((function.address(), signature.stack_cleanup()));
+            function.set_signature(Some(signature));
+        }
+        for (address, cleanup) in cleanups {
+            conventions.add_callee_cleanup(address, cleanup);
+        }
+    }
+    Ok(())
+}
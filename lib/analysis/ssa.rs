@@ -1,358 +1,587 @@
 //! Applies Static Single Assignment to the `il::ControlFlowGraph`, inserting
 //! intermediate blocks with Phi instructions as required.
+//!
+//! Also provides translation back out of SSA form, and verification of graphs
+//! in SSA form.
 
+use analysis::analysis_location::InstructionLocation;
 use error::*;
-use graph;
 use il;
 use il::Variable;
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet};
 
-pub fn ssa(mut control_flow_graph: il::ControlFlowGraph) -> Result<il::ControlFlowGraph> {
-    if control_flow_graph.entry().is_none() {
-        bail!("no entry vertex set for ControlFlowGraph");
-    }
+/// Returns the next SSA value for the variable `name`.
+fn next_ssa(versions: &mut BTreeMap<String, u32>, name: &str) -> u32 {
+    let version = versions.entry(name.to_string()).or_insert(0);
+    *version += 1;
+    *version
+}
+
+
+/// Applies SSA to a `ControlFlowGraph`.
+///
+/// Every write is given a new SSA value. A block with several predecessors
+/// gets a `Phi` for each variable live on entry to it, with an operand for
+/// each predecessor, and `Phi`s which only ever select one value are then
+/// removed. Variables read before they are written anywhere are inputs to the
+/// graph, and are left without an SSA value.
+///
+/// Values entering the graph have no predecessor to key a `Phi` operand by, so
+/// if the entry block has predecessors a new, empty entry block is inserted
+/// in front of it.
+pub fn ssa(control_flow_graph: il::ControlFlowGraph) -> Result<il::ControlFlowGraph> {
+    let entry = match control_flow_graph.entry() {
+        Some(entry) => entry,
+        None => bail!("no entry vertex set for ControlFlowGraph")
+    };
 
-    control_flow_graph = clear_ssa(control_flow_graph)?;
+    let mut control_flow_graph = clear_ssa(control_flow_graph)?;
 
-    struct CfgAssigner {
-        assignments: BTreeMap<String, u32>
+    if !control_flow_graph.graph().predecessors(entry)?.is_empty() {
+        let block_index = control_flow_graph.new_block()?.index();
+        control_flow_graph.unconditional_edge(block_index, entry)?;
+        control_flow_graph.set_entry(block_index)?;
     }
 
-    impl CfgAssigner {
-        pub fn new() -> CfgAssigner {
-            CfgAssigner { assignments: BTreeMap::new() }
-        }
+    let mut versions: BTreeMap<String, u32> = BTreeMap::new();
+    // A copy of each variable, used to build the operands of phis.
+    let mut variables: BTreeMap<String, il::MultiVar> = BTreeMap::new();
+    // The variables each block reads before writing them.
+    let mut exposed: BTreeMap<u64, BTreeSet<String>> = BTreeMap::new();
+    // The SSA value of the last write to each variable in each block.
+    let mut written: BTreeMap<u64, BTreeMap<String, u32>> = BTreeMap::new();
 
-        pub fn get<S: Into<String>>(&mut self, name: S) -> u32 {
-            let n: String = name.into();
-            if !self.assignments.contains_key(&n) {
-                self.assignments.insert(n.clone(), 0);
+    for block in control_flow_graph.blocks_mut() {
+        let mut block_exposed = BTreeSet::new();
+        let mut block_written = BTreeMap::new();
+        for instruction in block.instructions_mut() {
+            for variable in instruction.variables_read_mut() {
+                match block_written.get(variable.name()) {
+                    Some(ssa) => variable.set_ssa(Some(*ssa)),
+                    None => {
+                        block_exposed.insert(variable.name().to_string());
+                    }
+                }
+                if !variables.contains_key(variable.name()) {
+                    variables.insert(variable.name().to_string(), variable.multi_var_clone());
+                }
+            }
+            if let Some(variable) = instruction.variable_written_mut() {
+                let name = variable.name().to_string();
+                let ssa = next_ssa(&mut versions, &name);
+                variable.set_ssa(Some(ssa));
+                if !variables.contains_key(&name) {
+                    variables.insert(name.clone(), variable.multi_var_clone());
+                }
+                block_written.insert(name, ssa);
             }
-            self.assignments[&n]
         }
+        exposed.insert(block.index(), block_exposed);
+        written.insert(block.index(), block_written);
+    }
 
-        pub fn set<S: Into<String>>(&mut self, name: S) -> u32 {
-            let n: String = name.into();
-            let current_value = self.get(n.clone());
-            self.assignments.insert(n.clone(), current_value + 1);
-            current_value + 1
+    // Edge conditions read variables at the end of their head block.
+    let mut condition_reads: BTreeMap<u64, BTreeSet<String>> = BTreeMap::new();
+    for edge in control_flow_graph.edges() {
+        if let Some(ref condition) = *edge.condition() {
+            for scalar in condition.collect_scalars() {
+                condition_reads.entry(edge.head())
+                               .or_insert(BTreeSet::new())
+                               .insert(scalar.name().to_string());
+                if !variables.contains_key(scalar.name()) {
+                    variables.insert(scalar.name().to_string(), scalar.multi_var_clone());
+                }
+            }
         }
     }
 
-    // Applies SSA internally to a block, leaving ssa unset for any
-    // variables which are not set in the same block.
-    fn ssa_block(block: &mut il::Block, assigner: &mut CfgAssigner) {
-        let mut block_set: BTreeMap<String, u32> = BTreeMap::new();
-
-        for operation in block.instructions_mut() {
-            for variable in operation.variables_read_mut() {
-                if block_set.contains_key(variable.name()) {
-                    let name = variable.name().to_owned();
-                    variable.set_ssa(Some(block_set[&name]));
+    // The variables live on entry to each block.
+    let mut live_in: BTreeMap<u64, BTreeSet<String>> = BTreeMap::new();
+    loop {
+        let mut changed = false;
+        for block in control_flow_graph.blocks() {
+            let block_index = block.index();
+            let mut live = condition_reads.get(&block_index)
+                                          .cloned()
+                                          .unwrap_or(BTreeSet::new());
+            for successor in control_flow_graph.graph().successors(block_index)? {
+                if let Some(successor_live) = live_in.get(&successor.index()) {
+                    live.extend(successor_live.iter().cloned());
                 }
             }
-            if let Some(mut variable) = operation.variable_written_mut() {
-                if variable.ssa().is_none() {
-                    let name = variable.name().to_owned();
-                    variable.set_ssa(Some(assigner.set(name)));
-                }
-                block_set.insert(
-                    variable.name().to_owned(),
-                    variable.ssa().unwrap()
-                );
+            let mut live = live.into_iter()
+                               .filter(|name| !written[&block_index].contains_key(name))
+                               .collect::<BTreeSet<String>>();
+            live.extend(exposed[&block_index].iter().cloned());
+            if live_in.get(&block_index) != Some(&live) {
+                live_in.insert(block_index, live);
+                changed = true;
             }
         }
+        if !changed {
+            break;
+        }
     }
 
-    // Returns the ssa values for all variable assignments on block exit
-    // This should be called after ssa block, and all written variables
-    // should have SSA assignments.
-    fn get_block_ssa_assignments(block: &il::Block) -> BTreeMap<&str, &il::Variable> {
-        let mut assignments: BTreeMap<&str, &il::Variable> = BTreeMap::new();
-
-        for operation in block.instructions() {
-            if let Some(variable) = operation.variable_written() {
-                assignments.insert(
-                    variable.name(),
-                    variable
-                );
+    let mut phis: BTreeMap<(u64, String), u32> = BTreeMap::new();
+    for block in control_flow_graph.blocks() {
+        if control_flow_graph.graph().predecessors(block.index())?.len() > 1 {
+            for name in &live_in[&block.index()] {
+                phis.insert((block.index(), name.clone()), next_ssa(&mut versions, name));
             }
         }
-
-        assignments
     }
 
-    // Returns a vector of all variables without SSA set on exit
-    fn get_block_ssa_unassigned(block: &il::Block) -> Vec<il::MultiVar> {
-        let mut unassigned: Vec<il::MultiVar> = Vec::new();
+    // Finds the SSA value of a variable at the start, or end, of a block.
+    struct Definitions<'d> {
+        control_flow_graph: &'d il::ControlFlowGraph,
+        phis: &'d BTreeMap<(u64, String), u32>,
+        written: &'d BTreeMap<u64, BTreeMap<String, u32>>
+    }
 
-        for operation in block.instructions() {
-            for variable in operation.variables_read() {
-                if variable.ssa().is_none() && !unassigned.contains(&variable.multi_var_clone()) {
-                    unassigned.push(variable.multi_var_clone());
+    impl<'d> Definitions<'d> {
+        fn start(&self, block_index: u64, name: &str) -> Result<Option<u32>> {
+            let mut block_index = block_index;
+            let mut visited = BTreeSet::new();
+            loop {
+                if let Some(ssa) = self.phis.get(&(block_index, name.to_string())) {
+                    return Ok(Some(*ssa));
+                }
+                // Blocks with several predecessors have a phi for every live
+                // variable, so we only walk back through single predecessors
+                // until we reach the entry, or loop back on ourselves.
+                let predecessors = self.control_flow_graph.graph().predecessors(block_index)?;
+                if predecessors.len() != 1 || !visited.insert(block_index) {
+                    return Ok(None);
+                }
+                block_index = predecessors[0].index();
+                if let Some(ssa) = self.written[&block_index].get(name) {
+                    return Ok(Some(*ssa));
                 }
             }
         }
 
-        unassigned
+        fn end(&self, block_index: u64, name: &str) -> Result<Option<u32>> {
+            match self.written[&block_index].get(name) {
+                Some(ssa) => Ok(Some(*ssa)),
+                None => self.start(block_index, name)
+            }
+        }
     }
 
-    fn find_assignments(
-        block: &il::Block,
-        mut unassigned: Vec<il::MultiVar>,
-        mut visited: BTreeSet<u64>,
-        graph: &graph::Graph<il::Block, il::Edge>,
-    ) -> Result<BTreeMap<String, BTreeSet<u32>>> {
+    let (block_starts, operands, conditions) = {
+        let definitions = Definitions {
+            control_flow_graph: &control_flow_graph,
+            phis: &phis,
+            written: &written
+        };
 
-        let mut found: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+        let mut block_starts: BTreeMap<(u64, String), Option<u32>> = BTreeMap::new();
+        for (block_index, names) in &exposed {
+            for name in names {
+                block_starts.insert((*block_index, name.clone()), definitions.start(*block_index, name)?);
+            }
+        }
 
-        // This allows us to specify whether or not to skip the first
-        // block before calling this function, and use it for blocks
-        // and edges
-        if !visited.contains(&block.index()) {
-            let assignments = get_block_ssa_assignments(block);
+        let mut operands: BTreeMap<(u64, String), BTreeMap<u64, Option<u32>>> = BTreeMap::new();
+        for &(block_index, ref name) in phis.keys() {
+            let mut src = BTreeMap::new();
+            for predecessor in control_flow_graph.graph().predecessors(block_index)? {
+                src.insert(predecessor.index(), definitions.end(predecessor.index(), name)?);
+            }
+            operands.insert((block_index, name.clone()), src);
+        }
 
-            let mut found_this_block = Vec::new();
+        let mut conditions: BTreeMap<(u64, String), Option<u32>> = BTreeMap::new();
+        for (block_index, names) in &condition_reads {
+            for name in names {
+                conditions.insert((*block_index, name.clone()), definitions.end(*block_index, name)?);
+            }
+        }
 
-            for una in unassigned.clone() {
-                if assignments.get(una.name()).is_some() {
-                    if !found.contains_key(una.name()) {
-                        found.insert(una.name().to_string(), BTreeSet::new());
-                    }
-                    found.get_mut(una.name())
-                         .unwrap()
-                         .insert(assignments[una.name()].ssa().unwrap());
-                    found_this_block.push(una);
-                }
+        (block_starts, operands, conditions)
+    };
+
+    // Phis which select a single value, other than their own, are replaced by
+    // that value. Removing one phi may leave another with a single value.
+    let mut replaced: BTreeMap<(String, u32), Option<u32>> = BTreeMap::new();
+    let resolve = |replaced: &BTreeMap<(String, u32), Option<u32>>, name: &str, ssa: Option<u32>| {
+        let mut ssa = ssa;
+        while let Some(version) = ssa {
+            match replaced.get(&(name.to_string(), version)) {
+                Some(replacement) => ssa = *replacement,
+                None => break
             }
+        }
+        ssa
+    };
 
-            for ftb in found_this_block {
-                unassigned.iter()
-                          .position(|n| *n == ftb)
-                          .map(|e| unassigned.remove(e));
+    loop {
+        let mut changed = false;
+        for (&(block_index, ref name), ssa) in &phis {
+            if replaced.contains_key(&(name.clone(), *ssa)) {
+                continue;
             }
-            visited.insert(block.index());
+            let values = operands[&(block_index, name.clone())]
+                .values()
+                .map(|operand| resolve(&replaced, name, *operand))
+                .filter(|operand| *operand != Some(*ssa))
+                .collect::<BTreeSet<Option<u32>>>();
+            if values.len() == 1 {
+                let value = values.into_iter().next().unwrap();
+                replaced.insert((name.clone(), *ssa), value);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
         }
+    }
 
-        if !unassigned.is_empty() {
-            for predecessor in graph.predecessors(block.index())? {
-                if visited.contains(&predecessor.index()) {
-                    continue;
-                }
-                let block_found = find_assignments(
-                    predecessor,
-                    unassigned.clone(),
-                    visited.clone(),
-                    graph
-                )?;
-                for bf in block_found {
-                    if !found.contains_key(&bf.0) {
-                        found.insert(bf.0.clone(), BTreeSet::new());
-                    }
-                    for s in bf.1 {
-                        let mut found_set = found.get_mut(&bf.0).unwrap();
-                        found_set.insert(s);
-                    }
+    for block in control_flow_graph.blocks_mut() {
+        let block_index = block.index();
+        for instruction in block.instructions_mut() {
+            for variable in instruction.variables_read_mut() {
+                if variable.ssa().is_none() {
+                    let start = block_starts[&(block_index, variable.name().to_string())];
+                    let ssa = resolve(&replaced, variable.name(), start);
+                    variable.set_ssa(ssa);
                 }
             }
         }
+    }
+
+    for edge in control_flow_graph.edges_mut() {
+        let head = edge.head();
+        if let Some(ref mut condition) = *edge.condition_mut() {
+            for scalar in condition.collect_scalars_mut() {
+                let end = conditions[&(head, scalar.name().to_string())];
+                let ssa = resolve(&replaced, scalar.name(), end);
+                scalar.set_ssa(ssa);
+            }
+        }
+    }
 
-        Ok(found)
+    // Phis are prepended, so we visit each block's phis in reverse.
+    for (&(block_index, ref name), ssa) in phis.iter().rev() {
+        if replaced.contains_key(&(name.clone(), *ssa)) {
+            continue;
+        }
+        let mut dst = variables[name].clone();
+        dst.set_ssa(Some(*ssa));
+        let mut src = BTreeMap::new();
+        for (predecessor, operand) in &operands[&(block_index, name.clone())] {
+            let mut variable = variables[name].clone();
+            variable.set_ssa(resolve(&replaced, name, *operand));
+            src.insert(*predecessor, variable);
+        }
+        control_flow_graph.block_mut(block_index)
+                          .ok_or("Could not find block")?
+                          .prepend_phi(dst, src);
     }
 
-    // keeps track of SSA assignments globally across the ControlFlowGraph
-    let mut assigner = CfgAssigner::new();
+    Ok(control_flow_graph)
+}
+
+
 
-    // We apply SSA to each block
+
+pub fn clear_ssa(mut control_flow_graph: il::ControlFlowGraph)
+-> Result<il::ControlFlowGraph> {
     for block in control_flow_graph.blocks_mut() {
-        ssa_block(block, &mut assigner);
-    }
-
-    // For every block where SSA is not set, we recursively search
-    // backwards for the earliest occurences where the variable was set.
-    // We always make sure the block's predecessors, according to a DAG,
-    // are set before we process this block.
-    // One of three cases will take place:
-    // No occurences were found =>
-    //     We leave this variable alone
-    // One SSA occurence was found =>
-    //     We use the SSA value of this occurence
-    // More than one SSA occurence was found =>
-    //     We create a Phi operation assigning these two a new variable
-    //     with a new SSA, and prepend this Phi to the beginning of the
-    //     block
-
-    let mut queue = VecDeque::new();
-    queue.push_back(control_flow_graph.entry().unwrap());
-
-    let dag = control_flow_graph.graph()
-                                .compute_acyclic(control_flow_graph.entry()
-                                                                   .unwrap())?;
-    let mut ssa_set: BTreeSet<u64> = BTreeSet::new();
-
-    while !queue.is_empty() {
-        let block_index = queue.pop_front().unwrap();
-
-        // ensure all predecessors are set according to DAG
-        let mut predecessors_set = true;
-        for edge in dag.edges_in(block_index).unwrap() {
-            let head = graph::Edge::head(edge);
-            if !ssa_set.contains(&head) {
-                if !queue.contains(&head) {
-                    queue.push_back(head);
-                }
-                predecessors_set = false;
+        let mut phi_indices = Vec::new();
+        for instruction in block.instructions_mut() {
+            if let il::Operation::Phi{..} = *instruction.operation() {
+                phi_indices.push(instruction.index());
+                continue;
+            }
+            for variable in instruction.variables_read_mut() {
+                variable.set_ssa(None);
+            }
+            if let Some(mut variable) = instruction.variable_written_mut() {
+                variable.set_ssa(None);
             }
         }
+        for phi_index in phi_indices {
+            block.remove_instruction(phi_index)?;
+        }
+    }
 
-        if !predecessors_set {
-            queue.push_back(block_index);
-            continue;
+    for edge in control_flow_graph.edges_mut() {
+        let condition = edge.condition_mut();
+        if let Some(ref mut condition) = *condition {
+            for scalar in condition.collect_scalars_mut() {
+                scalar.set_ssa(None);
+            }
         }
+    }
 
-        // all predecessors set
-        ssa_set.insert(block_index);
+    Ok(control_flow_graph)
+}
 
-        let (found, unassigned) = {
-            let block = control_flow_graph
-                .block(block_index)
-                .ok_or("Could not find block")?;
 
-            let unassigned = get_block_ssa_unassigned(block); 
 
-            let mut visited = BTreeSet::new();
-            visited.insert(block.index());
-
-            let found = find_assignments(
-                block,
-                unassigned.clone(),
-                visited,
-                control_flow_graph.graph()
-            )?;
-            (found, unassigned)
-        };
+/// Verifies a `ControlFlowGraph` is in SSA form.
+///
+/// Every variable written must have an SSA value, and must be written exactly
+/// once. Every variable read with an SSA value must be written somewhere in
+/// the graph, and that write must dominate the read. Variables read without an
+/// SSA value are inputs to the graph.
+///
+/// `Phi` operands flow in from predecessors. Each operand must be keyed by a
+/// predecessor of the `Phi`'s block, and its write must dominate the end of
+/// that predecessor.
+pub fn verify_ssa(control_flow_graph: &il::ControlFlowGraph) -> Result<()> {
+    let entry = match control_flow_graph.entry() {
+        Some(entry) => entry,
+        None => bail!("no entry vertex set for ControlFlowGraph")
+    };
 
-        for una in &unassigned {
-            if let Some(assignments) = found.get(una.name()) {
-                if assignments.len() == 1 {
-                    let block = control_flow_graph
-                        .block_mut(block_index)
-                        .ok_or("Could not find block")?;
-                    for ref mut operation in block.instructions_mut() {
-                        for ref mut variable in operation.variables_read_mut() {
-                            if    variable.name() == una.name()
-                               && variable.ssa() == None {
-                                variable.set_ssa(Some(*assignments.iter()
-                                                                  .next()
-                                                                  .unwrap()));
-                            }
-                        }
-                    }
+    let dominators = control_flow_graph.graph().compute_dominators(entry)?;
+
+    // The block index and position within that block of every write.
+    let mut writes: BTreeMap<il::MultiVar, (u64, usize)> = BTreeMap::new();
+
+    for block in control_flow_graph.blocks() {
+        for (position, instruction) in block.instructions().iter().enumerate() {
+            if let Some(variable) = instruction.variable_written() {
+                let location = InstructionLocation::new(block.index(), instruction.index());
+                if variable.ssa().is_none() {
+                    bail!("{} written without an SSA value at {}", variable, location);
                 }
-                else {
-                    let mut dst = una.clone();
-                    dst.set_ssa(Some(assigner.get(una.name())));
-                    let mut src = Vec::new();
-                    for assignment in assignments.iter() {
-                        let mut var = una.clone();
-                        var.set_ssa(Some(*assignment));
-                        src.push(var);
-                    }
-                    control_flow_graph.block_mut(block_index)
-                                      .ok_or("Could not find block")?
-                                      .prepend_phi(
-                                        dst.multi_var_clone(),
-                                        src.iter()
-                                           .map(|v| v.multi_var_clone())
-                                           .collect::<Vec<il::MultiVar>>()
-                                      );
+                if writes.insert(variable.multi_var_clone(), (block.index(), position)).is_some() {
+                    bail!("{} written more than once, again at {}", variable, location);
                 }
             }
         }
+    }
 
-        // add all successors to the queue
-        for edge in control_flow_graph.graph().edges_out(block_index).unwrap() {
-            if !ssa_set.contains(&edge.tail()) && !queue.contains(&edge.tail()) {
-                queue.push_back(edge.tail());
+    // Returns true if the write dominates the given position in a block.
+    // Blocks unreachable from the entry have no dominators, and are ignored.
+    let dominates = |write: &(u64, usize), block_index: u64, position: usize| -> bool {
+        if write.0 == block_index {
+            write.1 < position
+        }
+        else {
+            match dominators.get(&block_index) {
+                Some(block_dominators) => block_dominators.contains(&write.0),
+                None => true
             }
         }
-    }
+    };
 
-    // Every block should now have an ssa assignment in it for all
-    // variable writes. We go back and make sure all reads are good to go.
-    for mut block in control_flow_graph.blocks_mut() {
-        ssa_block(block, &mut assigner);
+    for block in control_flow_graph.blocks() {
+        for (position, instruction) in block.instructions().iter().enumerate() {
+            let location = InstructionLocation::new(block.index(), instruction.index());
+            if let il::Operation::Phi { ref src, .. } = *instruction.operation() {
+                let predecessors = control_flow_graph.graph()
+                                                     .predecessors(block.index())?
+                                                     .iter()
+                                                     .map(|predecessor| predecessor.index())
+                                                     .collect::<BTreeSet<u64>>();
+                for (predecessor, variable) in src {
+                    if !predecessors.contains(predecessor) {
+                        bail!("{} read at {} from 0x{:X}, which is not a predecessor",
+                              variable, location, predecessor);
+                    }
+                    if variable.ssa().is_none() {
+                        continue;
+                    }
+                    let write = match writes.get(variable) {
+                        Some(write) => write,
+                        None => bail!("{} read at {}, but never written", variable, location)
+                    };
+                    let end = control_flow_graph.block(*predecessor)
+                                                .ok_or("Could not find block")?
+                                                .instructions()
+                                                .len();
+                    if !dominates(write, *predecessor, end) {
+                        bail!("write of {} does not dominate the end of 0x{:X} for read at {}",
+                              variable, predecessor, location);
+                    }
+                }
+                continue;
+            }
+            for variable in instruction.variables_read() {
+                if variable.ssa().is_none() {
+                    continue;
+                }
+                let write = match writes.get(&variable.multi_var_clone()) {
+                    Some(write) => write,
+                    None => bail!("{} read at {}, but never written", variable, location)
+                };
+                if !dominates(write, block.index(), position) {
+                    bail!("write of {} does not dominate read at {}", variable, location);
+                }
+            }
+        }
     }
-    
-    let edges = { 
-        control_flow_graph.graph()
-                          .edges()
-                          .iter()
-                          .map(|e| (*e).clone()).collect::<Vec<il::Edge>>()
-    };
 
-    for edge in edges {
-        if edge.condition().is_none() {
-            continue;
+    for edge in control_flow_graph.edges() {
+        if let Some(ref condition) = *edge.condition() {
+            let position = control_flow_graph.block(edge.head())
+                                             .ok_or("Could not find block")?
+                                             .instructions()
+                                             .len();
+            for scalar in condition.collect_scalars() {
+                if scalar.ssa().is_none() {
+                    continue;
+                }
+                let write = match writes.get(&scalar.multi_var_clone()) {
+                    Some(write) => write,
+                    None => bail!("{} read at {}, but never written", scalar, edge)
+                };
+                if !dominates(write, edge.head(), position) {
+                    bail!("write of {} does not dominate read at {}", scalar, edge);
+                }
+            }
         }
+    }
 
-        let unassigned: Vec<il::MultiVar> = edge.condition()
-                                                .clone()
-                                                .unwrap()
-                                                .collect_scalars()
-                                                .iter()
-                                                .map(|v| il::MultiVar::Scalar((*v).clone()))
-                                                .collect();
+    Ok(())
+}
 
-        let found = find_assignments(
-            control_flow_graph.block(edge.head())
-                              .ok_or("Could not find block")?,
-            unassigned,
-            BTreeSet::new(),
-            control_flow_graph.graph()
-        )?;
 
+/// Orders the parallel copies `(dst, src)` so no copy overwrites a value
+/// another copy has yet to read. A cycle of copies, such as a swap, is broken
+/// by saving one value to a temporary.
+fn sequentialize(mut copies: Vec<(il::Scalar, il::Scalar)>) -> Vec<(il::Scalar, il::Scalar)> {
+    copies.retain(|&(ref dst, ref src)| dst != src);
+
+    let mut sequence = Vec::new();
+    while !copies.is_empty() {
+        // A copy is ready once no other copy reads its destination.
+        let ready = copies.iter()
+                          .position(|&(ref dst, _)| copies.iter().all(|&(_, ref src)| src != dst));
+        match ready {
+            Some(position) => sequence.push(copies.remove(position)),
+            None => {
+                // Every destination is still to be read, so the remaining
+                // copies form cycles.
+                let dst = copies[0].0.clone();
+                let temp = il::Scalar::new(
+                    format!("phi_{}_{}", dst.name(), dst.ssa().unwrap_or(0)),
+                    dst.bits()
+                );
+                for copy in copies.iter_mut() {
+                    if copy.1 == dst {
+                        copy.1 = temp.clone();
+                    }
+                }
+                sequence.push((temp, dst));
+            }
+        }
+    }
 
-        let block_index = edge.head();
+    sequence
+}
 
-        // Assign SSA to edges
-        let mut phis_to_add: Vec<(u64, il::MultiVar, Vec<il::MultiVar>)> = Vec::new();
 
+/// Replaces every `Phi` with copies.
+///
+/// Each operand of a `Phi` is copied to the `Phi`'s destination at the end of
+/// the predecessor it flows in from. When that predecessor has several
+/// successors, the edge is split first, so the copies only run along it.
+///
+/// The `Phi`s at the start of a block take place in parallel, so the copies
+/// at the end of each predecessor are sequentialized with `sequentialize`.
+fn phis_to_copies(mut control_flow_graph: il::ControlFlowGraph)
+-> Result<il::ControlFlowGraph> {
+    let phi_blocks = control_flow_graph.blocks()
+                                       .iter()
+                                       .filter(|block| block.instructions().iter().any(|i| i.is_phi()))
+                                       .map(|block| block.index())
+                                       .collect::<Vec<u64>>();
+
+    for block_index in phi_blocks {
+        // The parallel copies for each predecessor.
+        let mut copies: BTreeMap<u64, Vec<(il::Scalar, il::Scalar)>> = BTreeMap::new();
         {
-            let mut edge = control_flow_graph.edge_mut(edge.head(), edge.tail()).unwrap();
-
-            if let Some(ref mut condition) = *edge.condition_mut() {
-                for scalar in condition.collect_scalars_mut() {
-                    if let Some(assignments) = found.get(scalar.name()) {
-                        if assignments.len() == 1 {
-                            scalar.set_ssa(Some(*assignments.iter()
-                                                            .next()
-                                                            .unwrap()));
-                            continue;
-                        }
-                        let mut dst = scalar.clone();
-                        dst.set_ssa(Some(assigner.get(scalar.name())));
-                        scalar.set_ssa(Some(dst.ssa().unwrap()));
-                        let mut src = Vec::new();
-                        for assignment in assignments.iter() {
-                            let mut var = scalar.clone();
-                            var.set_ssa(Some(*assignment));
-                            src.push(var.multi_var_clone());
-                        }
-                        phis_to_add.push((
-                            block_index,
-                            dst.multi_var_clone(),
-                            src
-                        ));
+            let block = control_flow_graph.block_mut(block_index)
+                                          .ok_or("Could not find block")?;
+
+            let phis = block.instructions()
+                            .iter()
+                            .filter_map(|instruction| match *instruction.operation() {
+                                il::Operation::Phi { ref dst, ref src } =>
+                                    Some((instruction.index(), dst.clone(), src.clone())),
+                                _ => None
+                            })
+                            .collect::<Vec<(u64, il::MultiVar, BTreeMap<u64, il::MultiVar>)>>();
+
+            for (instruction_index, dst, src) in phis {
+                block.remove_instruction(instruction_index)?;
+
+                let dst = match dst {
+                    il::MultiVar::Scalar(dst) => dst,
+                    // There is only one version of memory outside of SSA form.
+                    il::MultiVar::Array(_) => continue
+                };
+
+                for (predecessor, src) in src {
+                    match src {
+                        il::MultiVar::Scalar(src) =>
+                            copies.entry(predecessor)
+                                  .or_insert(Vec::new())
+                                  .push((dst.clone(), src)),
+                        il::MultiVar::Array(_) => bail!("Phi to {} mixes arrays and scalars", dst)
                     }
                 }
             }
         }
-        
-        for phi_to_add in phis_to_add {
-            control_flow_graph.block_mut(phi_to_add.0)
-                              .ok_or("Could not find block")?
-                              .phi(phi_to_add.1, phi_to_add.2);
+
+        let predecessors = control_flow_graph.graph()
+                                             .predecessors(block_index)?
+                                             .iter()
+                                             .map(|predecessor| predecessor.index())
+                                             .collect::<Vec<u64>>();
+
+        for predecessor in predecessors {
+            let copies = match copies.remove(&predecessor) {
+                Some(copies) => copies,
+                None => continue
+            };
+
+            let successors = control_flow_graph.graph()
+                                               .edges_out(predecessor)
+                                               .ok_or("Could not find edges")?
+                                               .len();
+
+            let copy_index = if successors > 1 {
+                let condition = control_flow_graph.edge(predecessor, block_index)
+                                                  .ok_or("Could not find edge")?
+                                                  .condition()
+                                                  .clone();
+                control_flow_graph.remove_edge(predecessor, block_index)?;
+                let split_index = control_flow_graph.new_block()?.index();
+                match condition {
+                    Some(condition) =>
+                        control_flow_graph.conditional_edge(predecessor, split_index, condition)?,
+                    None => control_flow_graph.unconditional_edge(predecessor, split_index)?
+                }
+                control_flow_graph.unconditional_edge(split_index, block_index)?;
+                split_index
+            }
+            else {
+                predecessor
+            };
+
+            let block = control_flow_graph.block_mut(copy_index)
+                                          .ok_or("Could not find block")?;
+            // Copies go before a trailing Brc, which may leave the graph.
+            let mut position = match block.instructions().last() {
+                Some(instruction) => if instruction.is_brc() {
+                    block.instructions().len() - 1
+                }
+                else {
+                    block.instructions().len()
+                },
+                None => 0
+            };
+            for (dst, src) in sequentialize(copies) {
+                block.insert_assign(position, dst, src.into());
+                position += 1;
+            }
         }
     }
 
@@ -360,37 +589,313 @@ pub fn ssa(mut control_flow_graph: il::ControlFlowGraph) -> Result<il::ControlFl
 }
 
 
+/// Removes the SSA value from a scalar, or renames it if the scalar's version
+/// could not share a name with the other versions of its variable.
+fn strip_scalar(scalar: &mut il::Scalar, renamed: &BTreeMap<il::Scalar, String>) {
+    match renamed.get(scalar) {
+        Some(name) => *scalar = il::Scalar::new(name.to_string(), scalar.bits()),
+        None => scalar.set_ssa(None)
+    }
+}
 
 
-pub fn clear_ssa(mut control_flow_graph: il::ControlFlowGraph)
+fn strip_expression(expression: &mut il::Expression, renamed: &BTreeMap<il::Scalar, String>) {
+    for scalar in expression.collect_scalars_mut() {
+        strip_scalar(scalar, renamed);
+    }
+}
+
+
+fn strip_operation(operation: &mut il::Operation, renamed: &BTreeMap<il::Scalar, String>) {
+    match *operation {
+        il::Operation::Assign { ref mut dst, ref mut src } => {
+            strip_scalar(dst, renamed);
+            strip_expression(src, renamed);
+        },
+        il::Operation::Store { ref mut dst, ref mut index, ref mut src } => {
+            dst.set_ssa(None);
+            strip_expression(index, renamed);
+            strip_expression(src, renamed);
+        },
+        il::Operation::Load { ref mut dst, ref mut index, ref mut src } => {
+            strip_scalar(dst, renamed);
+            strip_expression(index, renamed);
+            src.set_ssa(None);
+        },
+        il::Operation::Brc { ref mut target, ref mut condition } => {
+            strip_expression(target, renamed);
+            strip_expression(condition, renamed);
+        },
+        il::Operation::Phi { ref mut dst, ref mut src } => {
+            dst.set_ssa(None);
+            for multi_var in src.values_mut() {
+                multi_var.set_ssa(None);
+            }
+        },
+        il::Operation::Raise { ref mut expr } => strip_expression(expr, renamed)
+    }
+}
+
+
+fn scalar_written(instruction: &il::Instruction) -> Option<il::Scalar> {
+    match instruction.variable_written().map(|v| v.multi_var_clone()) {
+        Some(il::MultiVar::Scalar(scalar)) => Some(scalar),
+        _ => None
+    }
+}
+
+
+fn scalars_read(instruction: &il::Instruction) -> Vec<il::Scalar> {
+    instruction.variables_read()
+               .iter()
+               .filter_map(|v| match v.multi_var_clone() {
+                   il::MultiVar::Scalar(scalar) => Some(scalar),
+                   il::MultiVar::Array(_) => None
+               })
+               .collect()
+}
+
+
+/// Strips SSA values from a graph without `Phi`s.
+///
+/// Versions of a variable normally go back to sharing the variable's name.
+/// When a version is written while another version of the same variable is
+/// both available and live, they interfere (the lost copy problem), and one of
+/// them is renamed to `<name>_<ssa>` instead. Inputs to the graph are never
+/// renamed, and versions which reach the end of the graph are preferred.
+fn strip_ssa_versions(mut control_flow_graph: il::ControlFlowGraph, entry: u64)
 -> Result<il::ControlFlowGraph> {
-    for block in control_flow_graph.blocks_mut() {
-        let mut phi_indices = Vec::new();
-        for instruction in block.instructions_mut() {
-            if let il::Operation::Phi{..} = *instruction.operation() {
-                phi_indices.push(instruction.index());
-                continue;
+    let mut inputs: BTreeSet<il::Scalar> = BTreeSet::new();
+    for block in control_flow_graph.blocks() {
+        for instruction in block.instructions() {
+            for scalar in scalars_read(instruction) {
+                if scalar.ssa().is_none() {
+                    inputs.insert(scalar);
+                }
             }
-            for variable in instruction.variables_read_mut() {
-                variable.set_ssa(None);
+        }
+    }
+
+    // Versions available at the end of each block.
+    let mut available_out: BTreeMap<u64, BTreeSet<il::Scalar>> = BTreeMap::new();
+    let mut available_in: BTreeMap<u64, BTreeSet<il::Scalar>> = BTreeMap::new();
+    loop {
+        let mut changed = false;
+        for block in control_flow_graph.blocks() {
+            let mut available = if block.index() == entry {
+                inputs.clone()
             }
-            if let Some(mut variable) = instruction.variable_written_mut() {
-                variable.set_ssa(None);
+            else {
+                BTreeSet::new()
+            };
+            for predecessor in control_flow_graph.graph().predecessors(block.index())? {
+                if let Some(out) = available_out.get(&predecessor.index()) {
+                    available = &available | out;
+                }
+            }
+            available_in.insert(block.index(), available.clone());
+            for instruction in block.instructions() {
+                if let Some(scalar) = scalar_written(instruction) {
+                    available = available.into_iter()
+                                         .filter(|s| s.name() != scalar.name())
+                                         .collect();
+                    available.insert(scalar);
+                }
+            }
+            if available_out.get(&block.index()) != Some(&available) {
+                available_out.insert(block.index(), available);
+                changed = true;
             }
         }
-        for phi_index in phi_indices {
-            block.remove_instruction(phi_index)?;
+        if !changed {
+            break;
         }
     }
 
-    for edge in control_flow_graph.edges_mut() {
-        let condition = edge.condition_mut();
-        if let Some(ref mut condition) = *condition {
-            for scalar in condition.collect_scalars_mut() {
-                scalar.set_ssa(None);
+    // Blocks without successors leave the graph, and every version available
+    // at their end is observable.
+    let mut terminal: BTreeSet<u64> = BTreeSet::new();
+    for block in control_flow_graph.blocks() {
+        if control_flow_graph.graph().edges_out(block.index()).ok_or("Could not find edges")?.is_empty() {
+            terminal.insert(block.index());
+        }
+    }
+
+    let mut observable: BTreeSet<il::Scalar> = BTreeSet::new();
+    for block_index in &terminal {
+        if let Some(out) = available_out.get(block_index) {
+            observable = &observable | out;
+        }
+    }
+
+    // Versions live at the start of each block.
+    let mut live_in: BTreeMap<u64, BTreeSet<il::Scalar>> = BTreeMap::new();
+    let live_out = |block_index: u64, live_in: &BTreeMap<u64, BTreeSet<il::Scalar>>|
+    -> Result<BTreeSet<il::Scalar>> {
+        let mut live = BTreeSet::new();
+        if terminal.contains(&block_index) {
+            if let Some(out) = available_out.get(&block_index) {
+                live = out.clone();
+            }
+        }
+        for edge in control_flow_graph.graph().edges_out(block_index).ok_or("Could not find edges")? {
+            if let Some(tail_live) = live_in.get(&edge.tail()) {
+                live = &live | tail_live;
+            }
+            if let Some(ref condition) = *edge.condition() {
+                for scalar in condition.collect_scalars() {
+                    live.insert(scalar.clone());
+                }
             }
         }
+        Ok(live)
+    };
+
+    loop {
+        let mut changed = false;
+        for block in control_flow_graph.blocks() {
+            let mut live = live_out(block.index(), &live_in)?;
+            for instruction in block.instructions().iter().rev() {
+                if let Some(scalar) = scalar_written(instruction) {
+                    live.remove(&scalar);
+                }
+                for scalar in scalars_read(instruction) {
+                    live.insert(scalar);
+                }
+            }
+            if live_in.get(&block.index()) != Some(&live) {
+                live_in.insert(block.index(), live);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Find versions which interfere with other versions of the same variable.
+    let mut interference: BTreeMap<il::Scalar, BTreeSet<il::Scalar>> = BTreeMap::new();
+    for block in control_flow_graph.blocks() {
+        let instructions = block.instructions();
+
+        let mut live_after = vec![BTreeSet::new(); instructions.len()];
+        let mut live = live_out(block.index(), &live_in)?;
+        for (position, instruction) in instructions.iter().enumerate().rev() {
+            live_after[position] = live.clone();
+            if let Some(scalar) = scalar_written(instruction) {
+                live.remove(&scalar);
+            }
+            for scalar in scalars_read(instruction) {
+                live.insert(scalar);
+            }
+        }
+
+        let mut available = available_in[&block.index()].clone();
+        for (position, instruction) in instructions.iter().enumerate() {
+            if let Some(scalar) = scalar_written(instruction) {
+                for other in available.iter().filter(|s| s.name() == scalar.name()) {
+                    if *other != scalar && live_after[position].contains(other) {
+                        interference.entry(scalar.clone())
+                                    .or_insert(BTreeSet::new())
+                                    .insert(other.clone());
+                        interference.entry(other.clone())
+                                    .or_insert(BTreeSet::new())
+                                    .insert(scalar.clone());
+                    }
+                }
+                available = available.into_iter()
+                                     .filter(|s| s.name() != scalar.name())
+                                     .collect();
+                available.insert(scalar);
+            }
+        }
+    }
+
+    // Greedily keep the original name for as many versions as possible.
+    let mut versions: Vec<&il::Scalar> = interference.keys().collect();
+    versions.sort_by_key(|scalar| (
+        scalar.ssa().is_some(),
+        !observable.contains(scalar),
+        scalar.ssa()
+    ));
+
+    let mut renamed: BTreeMap<il::Scalar, String> = BTreeMap::new();
+    let mut kept: BTreeSet<&il::Scalar> = BTreeSet::new();
+    for version in versions {
+        if interference[version].iter().any(|other| kept.contains(other)) {
+            renamed.insert(
+                version.clone(),
+                format!("{}_{}", version.name(), version.ssa().unwrap_or(0))
+            );
+        }
+        else {
+            kept.insert(version);
+        }
+    }
+
+    // If a renamed version is the only version of its variable to leave the
+    // graph from a block, copy it back to the variable's name.
+    let mut exit_copies: Vec<(u64, il::Scalar, il::Scalar)> = Vec::new();
+    for block_index in &terminal {
+        let available = &available_out[block_index];
+        for version in available {
+            if renamed.contains_key(version)
+               && available.iter().filter(|s| s.name() == version.name()).count() == 1 {
+                exit_copies.push((
+                    *block_index,
+                    il::Scalar::new(version.name(), version.bits()),
+                    version.clone()
+                ));
+            }
+        }
+    }
+
+    for (block_index, dst, src) in exit_copies {
+        let block = control_flow_graph.block_mut(block_index)
+                                      .ok_or("Could not find block")?;
+        let position = match block.instructions().last() {
+            Some(instruction) => if instruction.is_brc() {
+                block.instructions().len() - 1
+            }
+            else {
+                block.instructions().len()
+            },
+            None => 0
+        };
+        block.insert_assign(position, dst, src.into());
+    }
+
+    for block in control_flow_graph.blocks_mut() {
+        for instruction in block.instructions_mut() {
+            strip_operation(instruction.operation_mut(), &renamed);
+        }
+    }
+
+    for edge in control_flow_graph.edges_mut() {
+        if let Some(ref mut condition) = *edge.condition_mut() {
+            strip_expression(condition, &renamed);
+        }
     }
 
     Ok(control_flow_graph)
-}
\ No newline at end of file
+}
+
+
+/// Translates a `ControlFlowGraph` out of SSA form.
+///
+/// Unlike `clear_ssa`, this preserves the semantics of the graph. `Phi`s are
+/// replaced with copies, and versions of a variable which interfere with each
+/// other are given distinct names. The graph is checked with `verify_ssa`
+/// first, and an error is returned if it is not in valid SSA form.
+pub fn out_of_ssa(control_flow_graph: il::ControlFlowGraph)
+-> Result<il::ControlFlowGraph> {
+    let entry = match control_flow_graph.entry() {
+        Some(entry) => entry,
+        None => bail!("no entry vertex set for ControlFlowGraph")
+    };
+
+    verify_ssa(&control_flow_graph)?;
+
+    let control_flow_graph = phis_to_copies(control_flow_graph)?;
+    strip_ssa_versions(control_flow_graph, entry)
+}
@@ -0,0 +1,848 @@
+//! Stack pointer tracking and stack frame recovery.
+//!
+//! Computes the value of the stack pointer relative to its value on entry to a
+//! function at every `AnalysisLocation`. Values loaded from, and stored to,
+//! known offsets on the stack are tracked as well, which allows us to follow
+//! a frame pointer saved on the stack, and to find the return address when it
+//! is popped.
+//!
+//! From these deltas we find the number of bytes a function removes from the
+//! stack when returning (`ret imm16`), returns which leave the stack
+//! unbalanced, and a layout of the local variables and arguments of the
+//! function's stack frame.
+
+use analysis::analysis_location::AnalysisLocation::*;
+use analysis::analysis_location::InstructionLocation;
+use analysis::fixed_point::*;
+use error::*;
+use executor;
+use il;
+use std::collections::BTreeMap;
+use std::fmt;
+
+
+/// The value of a scalar or stack slot, as tracked by stack pointer analysis.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum StackValue {
+    /// The value of the stack pointer on entry, plus this offset.
+    Offset(i64),
+    /// The return address of the function.
+    ReturnAddress,
+    /// A constant value.
+    Constant(il::Constant),
+    /// The value held by a scalar on entry to the function.
+    Entry(il::Scalar),
+    /// A value we know nothing about.
+    Unknown
+}
+
+
+impl StackValue {
+    fn join(&self, other: &StackValue) -> StackValue {
+        if self == other {
+            self.clone()
+        }
+        else {
+            StackValue::Unknown
+        }
+    }
+}
+
+
+impl fmt::Display for StackValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StackValue::Offset(offset) => if offset < 0 {
+                write!(f, "sp-0x{:x}", -offset)
+            }
+            else {
+                write!(f, "sp+0x{:x}", offset)
+            },
+            StackValue::ReturnAddress => write!(f, "ret"),
+            StackValue::Constant(ref constant) => constant.fmt(f),
+            StackValue::Entry(ref scalar) => write!(f, "{}@entry", scalar),
+            StackValue::Unknown => write!(f, "unknown")
+        }
+    }
+}
+
+
+/// The state of the stack at an `AnalysisLocation`.
+///
+/// Scalars and stack slots which are not held in the state are
+/// `StackValue::Unknown`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StackState {
+    scalars: BTreeMap<il::Scalar, StackValue>,
+    /// Stack slots, by offset from the stack pointer on entry, holding the
+    /// size of the slot in bits and its value.
+    slots: BTreeMap<i64, (usize, StackValue)>
+}
+
+
+impl StackState {
+    fn new() -> StackState {
+        StackState {
+            scalars: BTreeMap::new(),
+            slots: BTreeMap::new()
+        }
+    }
+
+    /// Get the value of a scalar.
+    pub fn get(&self, scalar: &il::Scalar) -> &StackValue {
+        self.scalars.get(scalar).unwrap_or(&StackValue::Unknown)
+    }
+
+    /// Get the value of the stack slot of `bits` size at `offset`.
+    pub fn slot(&self, offset: i64, bits: usize) -> &StackValue {
+        match self.slots.get(&offset) {
+            Some(&(slot_bits, ref value)) if slot_bits == bits => value,
+            _ => &StackValue::Unknown
+        }
+    }
+
+    fn set(&mut self, scalar: il::Scalar, value: StackValue) {
+        match value {
+            StackValue::Unknown => self.scalars.remove(&scalar),
+            value => self.scalars.insert(scalar, value)
+        };
+    }
+
+    fn store(&mut self, offset: i64, bits: usize, value: StackValue) {
+        let end = offset + bytes(bits);
+        let overlapping = self.slots
+            .iter()
+            .filter(|&(slot_offset, &(slot_bits, _))|
+                *slot_offset < end && slot_offset + bytes(slot_bits) > offset)
+            .map(|(slot_offset, _)| *slot_offset)
+            .collect::<Vec<i64>>();
+        for slot_offset in overlapping {
+            self.slots.remove(&slot_offset);
+        }
+        if value != StackValue::Unknown {
+            self.slots.insert(offset, (bits, value));
+        }
+    }
+
+    /// Forget all stack slots below `offset`.
+    fn pop(&mut self, offset: i64) {
+        self.slots = self.slots.split_off(&offset);
+    }
+
+    fn join(mut self, other: &StackState) -> StackState {
+        self.scalars = self.scalars
+            .into_iter()
+            .filter_map(|(scalar, value)| {
+                let value = value.join(other.get(&scalar));
+                match value {
+                    StackValue::Unknown => None,
+                    value => Some((scalar, value))
+                }
+            })
+            .collect();
+        self.slots = self.slots
+            .into_iter()
+            .filter(|&(offset, ref slot)| other.slots.get(&offset) == Some(slot))
+            .collect();
+        self
+    }
+
+    /// Evaluate an expression over this state.
+    pub fn eval(&self, expression: &il::Expression) -> StackValue {
+        match *expression {
+            il::Expression::Scalar(ref scalar) => self.get(scalar).clone(),
+            il::Expression::Constant(ref constant) =>
+                StackValue::Constant(constant.clone()),
+            il::Expression::Add(ref lhs, ref rhs) => {
+                match (self.eval(lhs), self.eval(rhs)) {
+                    (StackValue::Offset(offset), StackValue::Constant(c)) |
+                    (StackValue::Constant(c), StackValue::Offset(offset)) =>
+                        StackValue::Offset(offset.wrapping_add(signed(&c))),
+                    (StackValue::Constant(l), StackValue::Constant(r)) =>
+                        constant_op(il::Expression::add(l.into(), r.into())),
+                    _ => StackValue::Unknown
+                }
+            },
+            il::Expression::Sub(ref lhs, ref rhs) => {
+                match (self.eval(lhs), self.eval(rhs)) {
+                    (StackValue::Offset(offset), StackValue::Constant(c)) =>
+                        StackValue::Offset(offset.wrapping_sub(signed(&c))),
+                    (StackValue::Offset(l), StackValue::Offset(r)) =>
+                        StackValue::Constant(il::const_(
+                            l.wrapping_sub(r) as u64 & mask(lhs.bits()),
+                            lhs.bits()
+                        )),
+                    (StackValue::Constant(l), StackValue::Constant(r)) =>
+                        constant_op(il::Expression::sub(l.into(), r.into())),
+                    _ => StackValue::Unknown
+                }
+            },
+            il::Expression::Zext(bits, ref src) |
+            il::Expression::Sext(bits, ref src) |
+            il::Expression::Trun(bits, ref src) if bits == src.bits() =>
+                self.eval(src),
+            _ => {
+                // Any other operation is only known over constants
+                match self.constant_expression(expression) {
+                    Some(expression) => constant_op(Ok(expression)),
+                    None => StackValue::Unknown
+                }
+            }
+        }
+    }
+
+    /// Replace every scalar in `expression` with the constant it holds, or
+    /// return `None` if a scalar does not hold a constant.
+    fn constant_expression(&self, expression: &il::Expression)
+    -> Option<il::Expression> {
+        let mut expression = expression.clone();
+        if self.substitute_constants(&mut expression) {
+            Some(expression)
+        }
+        else {
+            None
+        }
+    }
+
+    fn substitute_constants(&self, expression: &mut il::Expression) -> bool {
+        let constant = match *expression {
+            il::Expression::Scalar(ref scalar) => match *self.get(scalar) {
+                StackValue::Constant(ref constant) => constant.clone(),
+                _ => return false
+            },
+            il::Expression::Constant(_) => return true,
+            il::Expression::Add(ref mut lhs, ref mut rhs) |
+            il::Expression::Sub(ref mut lhs, ref mut rhs) |
+            il::Expression::Mul(ref mut lhs, ref mut rhs) |
+            il::Expression::Divu(ref mut lhs, ref mut rhs) |
+            il::Expression::Modu(ref mut lhs, ref mut rhs) |
+            il::Expression::Divs(ref mut lhs, ref mut rhs) |
+            il::Expression::Mods(ref mut lhs, ref mut rhs) |
+            il::Expression::And(ref mut lhs, ref mut rhs) |
+            il::Expression::Or(ref mut lhs, ref mut rhs) |
+            il::Expression::Xor(ref mut lhs, ref mut rhs) |
+            il::Expression::Shl(ref mut lhs, ref mut rhs) |
+            il::Expression::Shr(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmpeq(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmpneq(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmplts(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmpltu(ref mut lhs, ref mut rhs) |
+            il::Expression::Fadd(ref mut lhs, ref mut rhs) |
+            il::Expression::Fsub(ref mut lhs, ref mut rhs) |
+            il::Expression::Fmul(ref mut lhs, ref mut rhs) |
+            il::Expression::Fdiv(ref mut lhs, ref mut rhs) |
+            il::Expression::Fcmpeq(ref mut lhs, ref mut rhs) |
+            il::Expression::Fcmplt(ref mut lhs, ref mut rhs) |
+            il::Expression::Fcmpuo(ref mut lhs, ref mut rhs) =>
+                return self.substitute_constants(lhs)
+                    && self.substitute_constants(rhs),
+            il::Expression::Zext(_, ref mut src) |
+            il::Expression::Sext(_, ref mut src) |
+            il::Expression::Trun(_, ref mut src) |
+            il::Expression::Fext(_, ref mut src) |
+            il::Expression::Itof(_, ref mut src) |
+            il::Expression::Ftoi(_, ref mut src) =>
+                return self.substitute_constants(src)
+        };
+        *expression = constant.into();
+        true
+    }
+}
+
+
+fn bytes(bits: usize) -> i64 {
+    ((bits + 7) / 8) as i64
+}
+
+
+fn mask(bits: usize) -> u64 {
+    if bits >= 64 { 0xffffffffffffffff } else { (1 << bits) - 1 }
+}
+
+
+/// Sign-extend a constant so it can be added to an offset.
+fn signed(constant: &il::Constant) -> i64 {
+    let bits = constant.bits();
+    if bits < 64 && constant.value() & (1 << (bits - 1)) != 0 {
+        (constant.value() | !mask(bits)) as i64
+    }
+    else {
+        constant.value() as i64
+    }
+}
+
+
+fn constant_op(expression: Result<il::Expression>) -> StackValue {
+    match expression.and_then(|e| executor::constants_expression(&e)) {
+        Ok(constant) => StackValue::Constant(constant),
+        Err(_) => StackValue::Unknown
+    }
+}
+
+
+/// The conventions of an architecture needed to track its stack pointer.
+#[derive(Clone, Debug)]
+pub struct StackConventions {
+    stack_pointer: il::Scalar,
+    return_address_bits: usize,
+    registers: Vec<il::Scalar>,
+    clobbered: Vec<il::Scalar>,
+    callee_cleanup: BTreeMap<u64, u64>
+}
+
+
+impl StackConventions {
+    /// Create new `StackConventions` for a stack pointer, where calls push a
+    /// return address of `return_address_bits` onto the stack.
+    pub fn new(stack_pointer: il::Scalar, return_address_bits: usize)
+    -> StackConventions {
+        StackConventions {
+            stack_pointer: stack_pointer,
+            return_address_bits: return_address_bits,
+            registers: Vec::new(),
+            clobbered: Vec::new(),
+            callee_cleanup: BTreeMap::new()
+        }
+    }
+
+    /// `StackConventions` for functions lifted by `translator::x86`.
+    pub fn x86() -> StackConventions {
+        let mut conventions = StackConventions::new(il::scalar("esp", 32), 32);
+        for register in &["eax", "ebx", "ecx", "edx", "esi", "edi", "ebp"] {
+            conventions.add_register(il::scalar(*register, 32));
+        }
+        conventions.add_clobbered(il::scalar("eax", 32));
+        conventions.add_clobbered(il::scalar("ecx", 32));
+        conventions.add_clobbered(il::scalar("edx", 32));
+        conventions
+    }
+
+    /// Add a register whose value on entry is tracked, to find whether the
+    /// function preserves it.
+    pub fn add_register(&mut self, scalar: il::Scalar) {
+        self.registers.push(scalar);
+    }
+
+    /// Add a scalar which does not survive a call.
+    pub fn add_clobbered(&mut self, scalar: il::Scalar) {
+        self.clobbered.push(scalar);
+    }
+
+    /// The stack pointer.
+    pub fn stack_pointer(&self) -> &il::Scalar {
+        &self.stack_pointer
+    }
+
+    /// Registers whose values on entry are tracked.
+    pub fn registers(&self) -> &Vec<il::Scalar> {
+        &self.registers
+    }
+
+    /// Scalars which do not survive a call.
+    pub fn clobbered(&self) -> &Vec<il::Scalar> {
+        &self.clobbered
+    }
+
+    /// Calls to the function at `address` remove `bytes` bytes from the stack,
+    /// in addition to the return address, when returning.
+    pub fn add_callee_cleanup(&mut self, address: u64, bytes: u64) {
+        self.callee_cleanup.insert(address, bytes);
+    }
+
+    /// The size of a return address in bytes.
+    pub(crate) fn return_address_bytes(&self) -> i64 {
+        bytes(self.return_address_bits)
+    }
+}
+
+
+/// A variable in a stack frame.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StackVariable {
+    name: String,
+    offset: i64,
+    bits: usize
+}
+
+
+impl StackVariable {
+    /// The name of this variable. Local variables are named `var_<n>`, where
+    /// `n` is the distance below the stack pointer on entry, and arguments
+    /// are named `arg_<n>`, where `n` is the distance above the return
+    /// address.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The offset of this variable from the stack pointer on entry.
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// The size of this variable in bits. When accessed with different sizes,
+    /// this is the largest size.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+}
+
+
+impl fmt::Display for StackVariable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.name, self.bits)
+    }
+}
+
+
+/// The layout of a function's stack frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackFrame {
+    return_address_bytes: i64,
+    variables: BTreeMap<i64, StackVariable>,
+    accesses: BTreeMap<AnalysisLocation, i64>
+}
+
+
+impl StackFrame {
+    fn new(return_address_bytes: i64) -> StackFrame {
+        StackFrame {
+            return_address_bytes: return_address_bytes,
+            variables: BTreeMap::new(),
+            accesses: BTreeMap::new()
+        }
+    }
+
+    fn access(&mut self, location: AnalysisLocation, offset: i64, bits: usize) {
+        let name = if offset < 0 {
+            format!("var_{:x}", -offset)
+        }
+        else if offset >= self.return_address_bytes {
+            format!("arg_{:x}", offset - self.return_address_bytes)
+        }
+        else {
+            format!("ret_{:x}", offset)
+        };
+        let variable = self.variables.entry(offset).or_insert(StackVariable {
+            name: name,
+            offset: offset,
+            bits: bits
+        });
+        if bits > variable.bits {
+            variable.bits = bits;
+        }
+        self.accesses.insert(location, offset);
+    }
+
+    /// All variables in this frame, by offset from the stack pointer on entry.
+    pub fn variables(&self) -> &BTreeMap<i64, StackVariable> {
+        &self.variables
+    }
+
+    /// Variables below the return address.
+    pub fn locals(&self) -> Vec<&StackVariable> {
+        self.variables.range(..0).map(|(_, variable)| variable).collect()
+    }
+
+    /// Variables above the return address.
+    pub fn arguments(&self) -> Vec<&StackVariable> {
+        self.variables
+            .range(self.return_address_bytes..)
+            .map(|(_, variable)| variable)
+            .collect()
+    }
+
+    /// The number of bytes used by local variables.
+    pub fn locals_size(&self) -> u64 {
+        match self.variables.range(..0).next() {
+            Some((offset, _)) => (-offset) as u64,
+            None => 0
+        }
+    }
+
+    /// The variable loaded or stored by the instruction at `location`, if
+    /// that instruction accesses the stack frame.
+    pub fn variable(&self, location: &AnalysisLocation) -> Option<&StackVariable> {
+        self.accesses
+            .get(location)
+            .and_then(|offset| self.variables.get(offset))
+    }
+}
+
+
+/// The results of stack pointer analysis over a function.
+#[derive(Clone, Debug)]
+pub struct StackPointer {
+    stack_pointer: il::Scalar,
+    registers: Vec<il::Scalar>,
+    return_address_bytes: i64,
+    states: BTreeMap<AnalysisLocation, StackState>,
+    states_in: BTreeMap<AnalysisLocation, StackState>,
+    returns: BTreeMap<AnalysisLocation, Option<i64>>,
+    frame: StackFrame
+}
+
+
+impl StackPointer {
+    /// The state of the stack after every `AnalysisLocation`.
+    pub fn states(&self) -> &BTreeMap<AnalysisLocation, StackState> {
+        &self.states
+    }
+
+    /// The state of the stack before the instruction at `location`.
+    pub fn state_in(&self, location: &AnalysisLocation) -> Option<&StackState> {
+        self.states_in.get(location)
+    }
+
+    /// The difference between the stack pointer after `location`, and the
+    /// stack pointer on entry to the function, if it is known.
+    pub fn delta(&self, location: &AnalysisLocation) -> Option<i64> {
+        self.states.get(location).and_then(|state| {
+            match *state.get(&self.stack_pointer) {
+                StackValue::Offset(offset) => Some(offset),
+                _ => None
+            }
+        })
+    }
+
+    /// Every branch to the return address, or out of the function once the
+    /// stack pointer is lost, with the stack pointer delta at that branch, if
+    /// known.
+    pub fn returns(&self) -> &BTreeMap<AnalysisLocation, Option<i64>> {
+        &self.returns
+    }
+
+    /// The number of bytes, past the return address, removed from the stack by
+    /// this function when it returns, such as with `ret imm16`.
+    ///
+    /// Returns `None` if the function never returns, or its returns are
+    /// unbalanced.
+    pub fn cleanup(&self) -> Option<u64> {
+        if !self.unbalanced().is_empty() {
+            return None;
+        }
+        self.returns
+            .values()
+            .next()
+            .and_then(|delta| delta.map(|delta| (delta - self.return_address_bytes) as u64))
+    }
+
+    /// Returns where the stack pointer delta is unknown, does not account for
+    /// the return address, or differs from another return.
+    ///
+    /// A branch out of the function where the stack pointer is unknown is
+    /// always an unbalanced return.
+    pub fn unbalanced(&self) -> Vec<&AnalysisLocation> {
+        let mut deltas = self.returns
+            .values()
+            .filter_map(|delta| *delta)
+            .collect::<Vec<i64>>();
+        deltas.sort();
+        deltas.dedup();
+        let consistent = deltas.len() <= 1;
+
+        self.returns
+            .iter()
+            .filter(|&(_, delta)| match *delta {
+                Some(delta) => !consistent || delta < self.return_address_bytes,
+                None => true
+            })
+            .map(|(location, _)| location)
+            .collect()
+    }
+
+    /// Registers which hold their value on entry at every return.
+    ///
+    /// If the function never returns, no registers are preserved.
+    pub fn preserved(&self) -> Vec<&il::Scalar> {
+        if self.returns.is_empty() {
+            return Vec::new();
+        }
+        self.registers
+            .iter()
+            .filter(|register| {
+                self.returns.keys().all(|location| {
+                    self.states.get(location).map(|state| {
+                        *state.get(register) == StackValue::Entry((*register).clone())
+                    }).unwrap_or(false)
+                })
+            })
+            .collect()
+    }
+
+    /// The stack frame of this function.
+    pub fn frame(&self) -> &StackFrame {
+        &self.frame
+    }
+}
+
+
+struct StackPointerAnalysis<'s> {
+    control_flow_graph: &'s il::ControlFlowGraph,
+    conventions: &'s StackConventions,
+    /// The location of the first instruction in the entry block
+    entry: Option<AnalysisLocation>
+}
+
+
+impl<'s> StackPointerAnalysis<'s> {
+    /// The state on entry to the function.
+    fn initial_state(&self) -> StackState {
+        let mut state = StackState::new();
+        for register in &self.conventions.registers {
+            state.set(register.clone(), StackValue::Entry(register.clone()));
+        }
+        state.set(self.conventions.stack_pointer.clone(), StackValue::Offset(0));
+        state.store(0, self.conventions.return_address_bits, StackValue::ReturnAddress);
+        state
+    }
+
+    /// The state before the instruction at `location`, given the results of
+    /// the analysis.
+    fn state_in(
+        &self,
+        location: &InstructionLocation,
+        states: &BTreeMap<AnalysisLocation, StackState>
+    ) -> Result<Option<StackState>> {
+        let block = self.control_flow_graph
+            .block(location.block_index())
+            .ok_or("Could not find block")?;
+
+        let position = block.instructions()
+            .iter()
+            .position(|instruction| instruction.index() == location.instruction_index())
+            .ok_or("Could not find instruction")?;
+
+        let mut state: Option<StackState> = None;
+        if position > 0 {
+            let previous = AnalysisLocation::instruction(
+                block.index(),
+                block.instructions()[position - 1].index()
+            );
+            state = states.get(&previous).cloned();
+        }
+        else {
+            let edges = self.control_flow_graph
+                .graph()
+                .edges_in(block.index())
+                .ok_or("Could not find edges")?;
+            for edge in edges {
+                let edge = AnalysisLocation::edge(edge.head(), edge.tail());
+                if let Some(edge_state) = states.get(&edge) {
+                    state = Some(match state {
+                        Some(state) => state.join(edge_state),
+                        None => edge_state.clone()
+                    });
+                }
+            }
+        }
+
+        if self.entry.as_ref() == Some(&AnalysisLocation::Instruction(location.clone())) {
+            state = Some(match state {
+                Some(state) => self.initial_state().join(&state),
+                None => self.initial_state()
+            });
+        }
+
+        Ok(state)
+    }
+}
+
+
+impl<'s> FixedPointAnalysis<StackState> for StackPointerAnalysis<'s> {
+    fn trans(
+        &self,
+        analysis_location: &AnalysisLocation,
+        state_in: &Option<StackState>
+    ) -> Result<StackState> {
+        let mut state_out = match *state_in {
+            Some(ref state_in) => state_in.clone(),
+            None => StackState::new()
+        };
+
+        if self.entry.as_ref() == Some(analysis_location) {
+            state_out = match *state_in {
+                Some(_) => self.initial_state().join(&state_out),
+                None => self.initial_state()
+            };
+        }
+
+        let il = match *analysis_location {
+            Instruction(ref il) => il,
+            _ => return Ok(state_out)
+        };
+
+        match *il.find(self.control_flow_graph)?.operation() {
+            il::Operation::Assign { ref dst, ref src } => {
+                let value = state_out.eval(src);
+                state_out.set(dst.clone(), value);
+            },
+            il::Operation::Store { ref index, ref src, .. } => {
+                // Stores to anything other than a known offset are assumed
+                // not to alias the stack
+                if let StackValue::Offset(offset) = state_out.eval(index) {
+                    let value = state_out.eval(src);
+                    state_out.store(offset, src.bits(), value);
+                }
+            },
+            il::Operation::Load { ref dst, ref index, .. } => {
+                let value = match state_out.eval(index) {
+                    StackValue::Offset(offset) =>
+                        state_out.slot(offset, dst.bits()).clone(),
+                    _ => StackValue::Unknown
+                };
+                state_out.set(dst.clone(), value);
+            },
+            il::Operation::Brc { ref target, .. } => {
+                let target = state_out.eval(target);
+                if target != StackValue::ReturnAddress {
+                    // A call. The callee pops the return address, and any
+                    // bytes it cleans up, and clobbers the scalars it may.
+                    let stack_pointer = &self.conventions.stack_pointer;
+                    let cleanup = match target {
+                        StackValue::Constant(ref address) =>
+                            self.conventions
+                                .callee_cleanup
+                                .get(&address.value())
+                                .cloned()
+                                .unwrap_or(0),
+                        _ => 0
+                    };
+                    if let StackValue::Offset(offset) = *state_out.get(stack_pointer) {
+                        let offset = offset
+                            + self.conventions.return_address_bytes()
+                            + cleanup as i64;
+                        state_out.set(stack_pointer.clone(), StackValue::Offset(offset));
+                        state_out.pop(offset);
+                    }
+                    for scalar in &self.conventions.clobbered {
+                        state_out.set(scalar.clone(), StackValue::Unknown);
+                    }
+                }
+            },
+            il::Operation::Phi { ref dst, ref src } => {
+                if let il::MultiVar::Scalar(ref dst) = *dst {
+                    let mut value: Option<StackValue> = None;
+                    for src in src.values() {
+                        if let il::MultiVar::Scalar(ref src) = *src {
+                            let src = state_out.get(src);
+                            value = Some(match value {
+                                Some(value) => value.join(src),
+                                None => src.clone()
+                            });
+                        }
+                    }
+                    state_out.set(dst.clone(), value.unwrap_or(StackValue::Unknown));
+                }
+            },
+            il::Operation::Raise { .. } => {}
+        }
+
+        Ok(state_out)
+    }
+
+
+    fn join(&self, state0: StackState, state1: &StackState) -> Result<StackState> {
+        Ok(state0.join(state1))
+    }
+}
+
+
+/// Returns true if the instruction at `location` is the last instruction of a
+/// block with no successors.
+fn leaves_function(
+    control_flow_graph: &il::ControlFlowGraph,
+    location: &InstructionLocation
+) -> Result<bool> {
+    let block = control_flow_graph
+        .block(location.block_index())
+        .ok_or("Could not find block")?;
+    let last = block.instructions()
+        .last()
+        .map(|instruction| instruction.index() == location.instruction_index())
+        .unwrap_or(false);
+    let successors = control_flow_graph
+        .graph()
+        .edges_out(block.index())
+        .ok_or("Could not find edges")?;
+    Ok(last && successors.is_empty())
+}
+
+
+/// Track the stack pointer over a function.
+pub fn compute(
+    control_flow_graph: &il::ControlFlowGraph,
+    conventions: &StackConventions
+) -> Result<StackPointer> {
+    let entry = control_flow_graph.entry()
+        .and_then(|entry| control_flow_graph.block(entry))
+        .map(|block| match block.instructions().first() {
+            Some(instruction) =>
+                AnalysisLocation::instruction(block.index(), instruction.index()),
+            None => AnalysisLocation::empty_block(block.index())
+        });
+
+    let analysis = StackPointerAnalysis {
+        control_flow_graph: control_flow_graph,
+        conventions: conventions,
+        entry: entry
+    };
+
+    let states = fixed_point_forward(&analysis, control_flow_graph)?;
+
+    let mut states_in = BTreeMap::new();
+    let mut returns = BTreeMap::new();
+    let mut frame = StackFrame::new(conventions.return_address_bytes());
+
+    for (location, state_out) in &states {
+        let il = match *location {
+            Instruction(ref il) => il,
+            _ => continue
+        };
+        let state_in = match analysis.state_in(il, &states)? {
+            Some(state_in) => state_in,
+            None => continue
+        };
+        states_in.insert(location.clone(), state_in.clone());
+        let state_in = &states_in[location];
+        match *il.find(control_flow_graph)?.operation() {
+            il::Operation::Store { ref index, ref src, .. } => {
+                if let StackValue::Offset(offset) = state_in.eval(index) {
+                    frame.access(location.clone(), offset, src.bits());
+                }
+            },
+            il::Operation::Load { ref dst, ref index, .. } => {
+                if let StackValue::Offset(offset) = state_in.eval(index) {
+                    frame.access(location.clone(), offset, dst.bits());
+                }
+            },
+            il::Operation::Brc { ref target, .. } => {
+                // Once the stack pointer is lost, we can no longer find the
+                // return address, so we also count branches which leave the
+                // function with an unknown stack pointer as returns
+                let stack_pointer_lost = match *state_in.get(&conventions.stack_pointer) {
+                    StackValue::Offset(_) => false,
+                    _ => leaves_function(control_flow_graph, il)?
+                };
+                if    state_in.eval(target) == StackValue::ReturnAddress
+                   || stack_pointer_lost {
+                    let delta = match *state_out.get(&conventions.stack_pointer) {
+                        StackValue::Offset(offset) => Some(offset),
+                        _ => None
+                    };
+                    returns.insert(location.clone(), delta);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(StackPointer {
+        stack_pointer: conventions.stack_pointer.clone(),
+        registers: conventions.registers.clone(),
+        return_address_bytes: conventions.return_address_bytes(),
+        states: states,
+        states_in: states_in,
+        returns: returns,
+        frame: frame
+    })
+}
@@ -0,0 +1,585 @@
+//! A strided interval abstract domain, as used in value-set analysis.
+//!
+//! A `StridedInterval` `s[lo, hi]` holds every value `lo + i * s` which is
+//! less than or equal to `hi`. Values are unsigned, and intervals never wrap
+//! around. Whenever the result of an operation can not be represented
+//! precisely, an interval is returned which holds every possible result.
+
+use il;
+use std::fmt;
+
+
+/// A set of unsigned values of the form `lo + i * stride`, bounded by `hi`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct StridedInterval {
+    bits: usize,
+    stride: u64,
+    lo: u64,
+    hi: u64
+}
+
+
+pub(crate) fn mask(bits: usize) -> u64 {
+    if bits >= 64 { 0xffffffffffffffff } else { (1 << bits) - 1 }
+}
+
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+
+/// Sets every bit below the highest set bit.
+fn smear(mut value: u64) -> u64 {
+    value |= value >> 1;
+    value |= value >> 2;
+    value |= value >> 4;
+    value |= value >> 8;
+    value |= value >> 16;
+    value |= value >> 32;
+    value
+}
+
+
+impl StridedInterval {
+    /// Create a new `StridedInterval`.
+    ///
+    /// `hi` is rounded down so it falls on the stride, and a `stride` of 0
+    /// is only kept when `lo == hi`.
+    pub fn new(bits: usize, stride: u64, lo: u64, hi: u64) -> StridedInterval {
+        let lo = lo & mask(bits);
+        let hi = hi & mask(bits);
+        let (lo, hi) = if lo > hi { (hi, lo) } else { (lo, hi) };
+
+        if lo == hi {
+            return StridedInterval { bits: bits, stride: 0, lo: lo, hi: hi };
+        }
+
+        let stride = if stride == 0 { 1 } else { stride };
+        let hi = lo + ((hi - lo) / stride) * stride;
+        let stride = if lo == hi { 0 } else { stride };
+
+        StridedInterval {
+            bits: bits,
+            stride: stride,
+            lo: lo,
+            hi: hi
+        }
+    }
+
+    /// Create a `StridedInterval` holding a single value.
+    pub fn constant(constant: &il::Constant) -> StridedInterval {
+        StridedInterval::new(constant.bits(), 0, constant.value(), constant.value())
+    }
+
+    /// Create a `StridedInterval` holding a single value.
+    pub fn value(bits: usize, value: u64) -> StridedInterval {
+        StridedInterval::new(bits, 0, value, value)
+    }
+
+    /// Create a `StridedInterval` holding every value of the given bit-width.
+    pub fn top(bits: usize) -> StridedInterval {
+        StridedInterval::new(bits, 1, 0, mask(bits))
+    }
+
+    /// Create the smallest `StridedInterval` holding all of the given
+    /// constants, or `None` if there are no constants.
+    pub fn from_constants<'c, I>(constants: I) -> Option<StridedInterval>
+    where I: IntoIterator<Item=&'c il::Constant> {
+        let mut result: Option<StridedInterval> = None;
+        for constant in constants {
+            let si = StridedInterval::constant(constant);
+            result = Some(match result {
+                Some(result) => result.join(&si),
+                None => si
+            });
+        }
+        result
+    }
+
+    /// Returns the bit-width of values in this `StridedInterval`.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Returns the stride of this `StridedInterval`.
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    /// Returns the smallest value in this `StridedInterval`.
+    pub fn lo(&self) -> u64 {
+        self.lo
+    }
+
+    /// Returns the largest value in this `StridedInterval`.
+    pub fn hi(&self) -> u64 {
+        self.hi
+    }
+
+    /// Returns the value held by this `StridedInterval`, if it holds exactly
+    /// one value.
+    pub fn constant_value(&self) -> Option<u64> {
+        if self.lo == self.hi { Some(self.lo) } else { None }
+    }
+
+    /// Returns true if this `StridedInterval` holds every value of its
+    /// bit-width.
+    pub fn is_top(&self) -> bool {
+        self.lo == 0 && self.hi == mask(self.bits) && self.stride == 1
+    }
+
+    /// Returns the number of values held in this `StridedInterval`, saturating
+    /// at `u64::max_value()`.
+    pub fn count(&self) -> u64 {
+        if self.stride == 0 {
+            1
+        }
+        else {
+            ((self.hi - self.lo) / self.stride).saturating_add(1)
+        }
+    }
+
+    /// Returns true if `value` is held in this `StridedInterval`.
+    pub fn contains(&self, value: u64) -> bool {
+        if value < self.lo || value > self.hi {
+            false
+        }
+        else if self.stride == 0 {
+            true
+        }
+        else {
+            (value - self.lo) % self.stride == 0
+        }
+    }
+
+    /// Returns every value held in this `StridedInterval` as an `il::Constant`.
+    ///
+    /// Check `count` before calling this.
+    pub fn constants(&self) -> Vec<il::Constant> {
+        let mut constants = Vec::new();
+        let mut value = self.lo;
+        loop {
+            constants.push(il::const_(value, self.bits));
+            if value >= self.hi || self.stride == 0 {
+                break;
+            }
+            value += self.stride;
+        }
+        constants
+    }
+
+    /// The smallest value of this bit-width which shares this interval's
+    /// congruence.
+    fn aligned_lo(&self) -> u64 {
+        if self.stride == 0 { self.lo } else { self.lo % self.stride }
+    }
+
+    /// The largest value of this bit-width which shares this interval's
+    /// congruence.
+    fn aligned_hi(&self) -> u64 {
+        if self.stride == 0 {
+            self.hi
+        }
+        else {
+            self.hi + ((mask(self.bits) - self.hi) / self.stride) * self.stride
+        }
+    }
+
+    /// Converts bounds which may exceed the bit-width to a `StridedInterval`,
+    /// falling back to every value of the bit-width if the values wrap.
+    fn from_u128(bits: usize, stride: u128, lo: u128, hi: u128) -> StridedInterval {
+        let modulus = (mask(bits) as u128) + 1;
+        if hi < modulus {
+            StridedInterval::new(bits, stride as u64, lo as u64, hi as u64)
+        }
+        else if lo >= modulus && hi - lo < modulus && lo / modulus == hi / modulus {
+            let lo = lo % modulus;
+            let hi = hi % modulus;
+            StridedInterval::new(bits, stride as u64, lo as u64, hi as u64)
+        }
+        else if stride < modulus && stride > 1 && modulus % stride == 0 {
+            // The values wrap, but every value remains congruent modulo the
+            // stride.
+            let lo = (lo % stride) as u64;
+            StridedInterval::new(bits, stride as u64, lo, mask(bits))
+        }
+        else {
+            StridedInterval::top(bits)
+        }
+    }
+
+    /// Returns the signed bounds of this interval, if the interval does not
+    /// cross from positive to negative values.
+    fn signed_bounds(&self) -> Option<(i128, i128)> {
+        let sign = 1u64 << (self.bits - 1);
+        let modulus = (mask(self.bits) as i128) + 1;
+        if self.hi < sign {
+            Some((self.lo as i128, self.hi as i128))
+        }
+        else if self.lo >= sign {
+            Some((self.lo as i128 - modulus, self.hi as i128 - modulus))
+        }
+        else {
+            None
+        }
+    }
+
+    fn boolean(value: Option<bool>) -> StridedInterval {
+        match value {
+            Some(true) => StridedInterval::value(1, 1),
+            Some(false) => StridedInterval::value(1, 0),
+            None => StridedInterval::top(1)
+        }
+    }
+
+    /// Join two `StridedInterval`s, returning the smallest `StridedInterval`
+    /// which holds the values of both.
+    pub fn join(&self, other: &StridedInterval) -> StridedInterval {
+        let lo = if self.lo < other.lo { self.lo } else { other.lo };
+        let hi = if self.hi > other.hi { self.hi } else { other.hi };
+        let difference = if self.lo > other.lo {
+            self.lo - other.lo
+        }
+        else {
+            other.lo - self.lo
+        };
+        let stride = gcd(gcd(self.stride, other.stride), difference);
+        let bits = if self.bits > other.bits { self.bits } else { other.bits };
+        StridedInterval::new(bits, stride, lo, hi)
+    }
+
+    /// Widen this `StridedInterval` with the next `StridedInterval` computed
+    /// for the same location.
+    ///
+    /// Any bound which grows is moved to the furthest value of this
+    /// bit-width with the same congruence.
+    pub fn widen(&self, next: &StridedInterval) -> StridedInterval {
+        let joined = self.join(next);
+        let lo = if joined.lo < self.lo { joined.aligned_lo() } else { joined.lo };
+        let hi = if joined.hi > self.hi { joined.aligned_hi() } else { joined.hi };
+        StridedInterval::new(joined.bits, joined.stride, lo, hi)
+    }
+
+    /// Narrow this `StridedInterval` with the next `StridedInterval` computed
+    /// for the same location.
+    ///
+    /// Only bounds which were moved to the furthest value by widening are
+    /// narrowed.
+    pub fn narrow(&self, next: &StridedInterval) -> StridedInterval {
+        if self.is_top() {
+            return next.clone();
+        }
+        let lo = if self.lo == self.aligned_lo() && next.lo > self.lo {
+            next.lo
+        }
+        else {
+            self.lo
+        };
+        let hi = if self.hi == self.aligned_hi() && next.hi < self.hi && next.hi >= lo {
+            next.hi
+        }
+        else {
+            self.hi
+        };
+        let stride = gcd(self.stride, lo - self.lo);
+        StridedInterval::new(self.bits, stride, lo, hi)
+    }
+
+    pub fn add(&self, rhs: &StridedInterval) -> StridedInterval {
+        StridedInterval::from_u128(
+            self.bits,
+            gcd(self.stride, rhs.stride) as u128,
+            self.lo as u128 + rhs.lo as u128,
+            self.hi as u128 + rhs.hi as u128
+        )
+    }
+
+    pub fn sub(&self, rhs: &StridedInterval) -> StridedInterval {
+        // a - b is computed as a + (2^bits - b)
+        let modulus = (mask(self.bits) as u128) + 1;
+        StridedInterval::from_u128(
+            self.bits,
+            gcd(self.stride, rhs.stride) as u128,
+            self.lo as u128 + modulus - rhs.hi as u128,
+            self.hi as u128 + modulus - rhs.lo as u128
+        )
+    }
+
+    pub fn mul(&self, rhs: &StridedInterval) -> StridedInterval {
+        let lo = self.lo as u128 * rhs.lo as u128;
+        let hi = self.hi as u128 * rhs.hi as u128;
+        let stride = gcd(
+            gcd(
+                (self.stride as u128 * rhs.lo as u128) as u64,
+                (rhs.stride as u128 * self.lo as u128) as u64
+            ),
+            (self.stride as u128 * rhs.stride as u128) as u64
+        );
+        if lo == hi {
+            let modulus = (mask(self.bits) as u128) + 1;
+            return StridedInterval::value(self.bits, (lo % modulus) as u64);
+        }
+        if hi > mask(self.bits) as u128 {
+            return StridedInterval::top(self.bits);
+        }
+        StridedInterval::new(self.bits, stride, lo as u64, hi as u64)
+    }
+
+    pub fn divu(&self, rhs: &StridedInterval) -> StridedInterval {
+        if rhs.hi == 0 {
+            return StridedInterval::top(self.bits);
+        }
+        let divisor_lo = if rhs.lo == 0 { 1 } else { rhs.lo };
+        let stride = match rhs.constant_value() {
+            Some(c) if self.stride % c == 0 => self.stride / c,
+            _ => 1
+        };
+        StridedInterval::new(self.bits, stride, self.lo / rhs.hi, self.hi / divisor_lo)
+    }
+
+    pub fn modu(&self, rhs: &StridedInterval) -> StridedInterval {
+        if rhs.hi == 0 {
+            return StridedInterval::top(self.bits);
+        }
+        if let Some(c) = rhs.constant_value() {
+            if self.hi < c {
+                return self.clone();
+            }
+            if self.lo / c == self.hi / c {
+                return StridedInterval::new(self.bits, self.stride, self.lo % c, self.hi % c);
+            }
+            let g = gcd(self.stride, c);
+            return StridedInterval::new(self.bits, g, self.lo % g, c - 1);
+        }
+        let hi = if self.hi < rhs.hi - 1 { self.hi } else { rhs.hi - 1 };
+        StridedInterval::new(self.bits, 1, 0, hi)
+    }
+
+    pub fn divs(&self, rhs: &StridedInterval) -> StridedInterval {
+        let sign = 1u64 << (self.bits - 1);
+        if self.hi < sign && rhs.hi < sign {
+            return self.divu(rhs);
+        }
+        match (self.constant_value(), rhs.constant_value()) {
+            (Some(_), Some(_)) => self.constant_op(rhs, il::Expression::divs),
+            _ => StridedInterval::top(self.bits)
+        }
+    }
+
+    pub fn mods(&self, rhs: &StridedInterval) -> StridedInterval {
+        let sign = 1u64 << (self.bits - 1);
+        if self.hi < sign && rhs.hi < sign {
+            return self.modu(rhs);
+        }
+        match (self.constant_value(), rhs.constant_value()) {
+            (Some(_), Some(_)) => self.constant_op(rhs, il::Expression::mods),
+            _ => StridedInterval::top(self.bits)
+        }
+    }
+
+    /// Apply an operation to two single-value intervals with the executor.
+    fn constant_op<F>(&self, rhs: &StridedInterval, op: F) -> StridedInterval
+    where F: Fn(il::Expression, il::Expression) -> ::error::Result<il::Expression> {
+        let expr = op(
+            il::expr_const(self.lo, self.bits),
+            il::expr_const(rhs.lo, rhs.bits)
+        );
+        match expr.and_then(|expr| ::executor::constants_expression(&expr)) {
+            Ok(constant) => StridedInterval::constant(&constant),
+            Err(_) => StridedInterval::top(self.bits)
+        }
+    }
+
+    pub fn and(&self, rhs: &StridedInterval) -> StridedInterval {
+        if let (Some(l), Some(r)) = (self.constant_value(), rhs.constant_value()) {
+            return StridedInterval::value(self.bits, l & r);
+        }
+        // Masking with 2^n - 1 is a modulo by 2^n
+        for &(value, other) in &[(rhs, self), (self, rhs)] {
+            if let Some(c) = value.constant_value() {
+                if c == mask(self.bits) {
+                    return other.clone();
+                }
+                if c & (c.wrapping_add(1)) == 0 {
+                    return other.modu(&StridedInterval::value(self.bits, c + 1));
+                }
+            }
+        }
+        let hi = if self.hi < rhs.hi { self.hi } else { rhs.hi };
+        StridedInterval::new(self.bits, 1, 0, hi)
+    }
+
+    pub fn or(&self, rhs: &StridedInterval) -> StridedInterval {
+        if let (Some(l), Some(r)) = (self.constant_value(), rhs.constant_value()) {
+            return StridedInterval::value(self.bits, l | r);
+        }
+        let lo = if self.lo > rhs.lo { self.lo } else { rhs.lo };
+        StridedInterval::new(self.bits, 1, lo, smear(self.hi | rhs.hi))
+    }
+
+    pub fn xor(&self, rhs: &StridedInterval) -> StridedInterval {
+        if let (Some(l), Some(r)) = (self.constant_value(), rhs.constant_value()) {
+            return StridedInterval::value(self.bits, l ^ r);
+        }
+        StridedInterval::new(self.bits, 1, 0, smear(self.hi | rhs.hi))
+    }
+
+    pub fn shl(&self, rhs: &StridedInterval) -> StridedInterval {
+        match rhs.constant_value() {
+            Some(shift) => {
+                if shift >= self.bits as u64 {
+                    StridedInterval::value(self.bits, 0)
+                }
+                else {
+                    self.mul(&StridedInterval::value(self.bits, 1 << shift))
+                }
+            },
+            None => StridedInterval::top(self.bits)
+        }
+    }
+
+    pub fn shr(&self, rhs: &StridedInterval) -> StridedInterval {
+        let shr = |value: u64, shift: u64| {
+            if shift >= self.bits as u64 { 0 } else { value >> shift }
+        };
+        match rhs.constant_value() {
+            Some(shift) => {
+                let stride = if shift < 64 && self.stride % (1 << shift) == 0 {
+                    self.stride >> shift
+                }
+                else {
+                    1
+                };
+                StridedInterval::new(
+                    self.bits,
+                    stride,
+                    shr(self.lo, shift),
+                    shr(self.hi, shift)
+                )
+            },
+            None => StridedInterval::new(
+                self.bits,
+                1,
+                shr(self.lo, rhs.hi),
+                shr(self.hi, rhs.lo)
+            )
+        }
+    }
+
+    pub fn cmpeq(&self, rhs: &StridedInterval) -> StridedInterval {
+        if let (Some(l), Some(r)) = (self.constant_value(), rhs.constant_value()) {
+            return StridedInterval::boolean(Some(l == r));
+        }
+        if self.hi < rhs.lo || rhs.hi < self.lo {
+            return StridedInterval::boolean(Some(false));
+        }
+        let g = gcd(self.stride, rhs.stride);
+        let difference = if self.lo > rhs.lo { self.lo - rhs.lo } else { rhs.lo - self.lo };
+        if g != 0 && difference % g != 0 {
+            return StridedInterval::boolean(Some(false));
+        }
+        StridedInterval::boolean(None)
+    }
+
+    pub fn cmpneq(&self, rhs: &StridedInterval) -> StridedInterval {
+        let eq = self.cmpeq(rhs);
+        match eq.constant_value() {
+            Some(value) => StridedInterval::boolean(Some(value == 0)),
+            None => eq
+        }
+    }
+
+    pub fn cmpltu(&self, rhs: &StridedInterval) -> StridedInterval {
+        if self.hi < rhs.lo {
+            StridedInterval::boolean(Some(true))
+        }
+        else if self.lo >= rhs.hi {
+            StridedInterval::boolean(Some(false))
+        }
+        else {
+            StridedInterval::boolean(None)
+        }
+    }
+
+    pub fn cmplts(&self, rhs: &StridedInterval) -> StridedInterval {
+        match (self.signed_bounds(), rhs.signed_bounds()) {
+            (Some((llo, lhi)), Some((rlo, rhi))) => {
+                if lhi < rlo {
+                    StridedInterval::boolean(Some(true))
+                }
+                else if llo >= rhi {
+                    StridedInterval::boolean(Some(false))
+                }
+                else {
+                    StridedInterval::boolean(None)
+                }
+            },
+            _ => StridedInterval::boolean(None)
+        }
+    }
+
+    pub fn zext(&self, bits: usize) -> StridedInterval {
+        if bits < self.bits {
+            return self.trun(bits);
+        }
+        StridedInterval::new(bits, self.stride, self.lo, self.hi)
+    }
+
+    pub fn sext(&self, bits: usize) -> StridedInterval {
+        if bits < self.bits {
+            return self.trun(bits);
+        }
+        let sign = 1u64 << (self.bits - 1);
+        let extension = mask(bits) - mask(self.bits);
+        if self.hi < sign {
+            StridedInterval::new(bits, self.stride, self.lo, self.hi)
+        }
+        else if self.lo >= sign {
+            StridedInterval::new(
+                bits,
+                self.stride,
+                self.lo + extension,
+                self.hi + extension
+            )
+        }
+        else {
+            StridedInterval::new(
+                bits,
+                gcd(self.stride, extension),
+                self.lo,
+                self.hi + extension
+            )
+        }
+    }
+
+    pub fn trun(&self, bits: usize) -> StridedInterval {
+        if bits >= self.bits {
+            return StridedInterval::new(bits, self.stride, self.lo, self.hi);
+        }
+        let m = mask(bits);
+        if self.hi <= m {
+            return StridedInterval::new(bits, self.stride, self.lo, self.hi);
+        }
+        if self.lo >> bits == self.hi >> bits {
+            return StridedInterval::new(bits, self.stride, self.lo & m, self.hi & m);
+        }
+        let g = gcd(self.stride, m + 1);
+        if g == m + 1 {
+            return StridedInterval::value(bits, self.lo & m);
+        }
+        StridedInterval::new(bits, g, self.lo % g, m)
+    }
+}
+
+
+impl fmt::Display for StridedInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[0x{:X}, 0x{:X}]:{}", self.stride, self.lo, self.hi, self.bits)
+    }
+}
@@ -0,0 +1,994 @@
+//! Interprocedural taint analysis.
+//!
+//! Taint is introduced by a `TaintSource`, flows through scalars and memory,
+//! and is reported when it reaches a `TaintSink`.
+//!
+//! Memory is tracked byte by byte, in the manner of `LatticeMemory`, at
+//! addresses on the stack and at constant addresses, as resolved by stack
+//! pointer analysis. Taint stored through an address we can not resolve
+//! taints every load through an address we can not resolve.
+//!
+//! Taint flows through calls by way of a summary of the callee, which holds
+//! the parameters and sources whose taint reaches the scalars it returns, and
+//! the parameters which reach a sink. Summaries are computed for every
+//! function in a `Program` until they no longer change.
+
+use analysis::analysis_location::AnalysisLocation::*;
+use analysis::fixed_point::*;
+use analysis::signature::scalars_read;
+use analysis::stack_pointer::{self, StackConventions, StackPointer, StackState, StackValue};
+use error::*;
+use il;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+
+/// The `read` system call on Linux x86.
+const SYS_READ: u64 = 3;
+
+
+/// The most bytes a source will taint, the same limit `Linux::read` places on
+/// a read.
+const MAX_BUFFER: u64 = 4096;
+
+
+/// Where taint is introduced.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum TaintSource {
+    /// The bytes written to memory by the `read` system call on Linux x86,
+    /// which are the bytes produced by `platform::linux::Linux::read`. The
+    /// buffer is held in `ecx`, and its length in `edx`.
+    SysRead,
+    /// The bytes written to the buffer passed as argument `buffer` to the
+    /// function at `function`, such as `fgets`. If `length` is given, it is
+    /// the argument which holds the length of the buffer.
+    Buffer {
+        function: u64,
+        buffer: usize,
+        length: Option<usize>
+    },
+    /// The value of `scalar` after a call to the function at `function`, such
+    /// as `getchar`.
+    Return {
+        function: u64,
+        scalar: il::Scalar
+    }
+}
+
+
+/// Where tainted values are reported.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum TaintSink {
+    /// The target of a `brc` which is not a constant, including returns.
+    IndirectBranch,
+    /// The address of a `store`.
+    StoreAddress,
+    /// Argument `argument` passed to the function at `function`, such as the
+    /// length passed to `memcpy`.
+    Argument {
+        function: u64,
+        argument: usize
+    },
+    /// The bytes pointed to by argument `argument` passed to the function at
+    /// `function`, such as the command passed to `system`.
+    Buffer {
+        function: u64,
+        argument: usize
+    }
+}
+
+
+impl fmt::Display for TaintSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TaintSink::IndirectBranch => write!(f, "indirect branch"),
+            TaintSink::StoreAddress => write!(f, "store address"),
+            TaintSink::Argument { function, argument } =>
+                write!(f, "argument {} to 0x{:X}", argument, function),
+            TaintSink::Buffer { function, argument } =>
+                write!(f, "buffer argument {} to 0x{:X}", argument, function)
+        }
+    }
+}
+
+
+/// The sources and sinks of taint analysis.
+#[derive(Clone, Debug)]
+pub struct TaintConfiguration {
+    conventions: StackConventions,
+    sources: Vec<TaintSource>,
+    sinks: Vec<TaintSink>
+}
+
+
+impl TaintConfiguration {
+    /// Create a `TaintConfiguration` with no sources or sinks, for functions
+    /// following `conventions`.
+    pub fn new(conventions: StackConventions) -> TaintConfiguration {
+        TaintConfiguration {
+            conventions: conventions,
+            sources: Vec::new(),
+            sinks: Vec::new()
+        }
+    }
+
+    /// A `TaintConfiguration` for Linux x86, which reports bytes from the
+    /// `read` system call reaching an indirect branch or the address of a
+    /// store.
+    pub fn linux_x86() -> TaintConfiguration {
+        let mut configuration = TaintConfiguration::new(StackConventions::x86());
+        configuration.add_source(TaintSource::SysRead);
+        configuration.add_sink(TaintSink::IndirectBranch);
+        configuration.add_sink(TaintSink::StoreAddress);
+        configuration
+    }
+
+    /// Add a source of taint.
+    pub fn add_source(&mut self, source: TaintSource) {
+        self.sources.push(source);
+    }
+
+    /// Add a sink of taint.
+    pub fn add_sink(&mut self, sink: TaintSink) {
+        self.sinks.push(sink);
+    }
+
+    /// The conventions of the functions analyzed.
+    pub fn conventions(&self) -> &StackConventions {
+        &self.conventions
+    }
+
+    /// The sources of taint.
+    pub fn sources(&self) -> &Vec<TaintSource> {
+        &self.sources
+    }
+
+    /// The sinks of taint.
+    pub fn sinks(&self) -> &Vec<TaintSink> {
+        &self.sinks
+    }
+}
+
+
+/// A location in a function along the path of a `TaintFlow`.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct TaintStep {
+    function: u64,
+    location: AnalysisLocation
+}
+
+
+impl TaintStep {
+    /// The address of the function this step is in.
+    pub fn function(&self) -> u64 {
+        self.function
+    }
+
+    /// The location of this step in its function.
+    pub fn location(&self) -> &AnalysisLocation {
+        &self.location
+    }
+}
+
+
+impl fmt::Display for TaintStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:X}:{}", self.function, self.location)
+    }
+}
+
+
+/// A flow of taint from a source to a sink.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaintFlow {
+    sink: TaintSink,
+    path: Vec<TaintStep>
+}
+
+
+impl TaintFlow {
+    /// The kind of sink this flow reaches.
+    pub fn sink(&self) -> &TaintSink {
+        &self.sink
+    }
+
+    /// The def-use path of this flow, from the source to the sink.
+    pub fn path(&self) -> &Vec<TaintStep> {
+        &self.path
+    }
+
+    /// Where the taint of this flow was introduced.
+    pub fn source(&self) -> &TaintStep {
+        &self.path[0]
+    }
+}
+
+
+impl fmt::Display for TaintFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = self.path
+            .iter()
+            .map(|step| format!("{}", step))
+            .collect::<Vec<String>>();
+        write!(f, "{}: {}", self.sink, path.join(" -> "))
+    }
+}
+
+
+/// Where a tainted value came from.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Origin {
+    /// A source at a location in this function.
+    Source(AnalysisLocation),
+    /// The value of a parameter on entry to this function.
+    Parameter(il::Parameter)
+}
+
+
+/// The taint of a value.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Taint {
+    origins: BTreeSet<Origin>,
+    /// The locations which defined this value.
+    definitions: BTreeSet<AnalysisLocation>
+}
+
+
+impl Taint {
+    fn is_clean(&self) -> bool {
+        self.origins.is_empty()
+    }
+
+    fn union(&mut self, other: &Taint) {
+        self.origins.extend(other.origins.iter().cloned());
+        self.definitions.extend(other.definitions.iter().cloned());
+    }
+
+    /// This taint, for a value defined at `location`.
+    fn defined_at(&self, location: &AnalysisLocation) -> Taint {
+        if self.is_clean() {
+            return Taint::default();
+        }
+        let mut definitions = BTreeSet::new();
+        definitions.insert(location.clone());
+        Taint {
+            origins: self.origins.clone(),
+            definitions: definitions
+        }
+    }
+}
+
+
+/// An address in memory, resolved by stack pointer analysis.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Address {
+    /// An offset from the stack pointer on entry to the function.
+    Stack(i64),
+    /// A constant address.
+    Global(u64)
+}
+
+
+impl Address {
+    fn resolve(value: &StackValue) -> Option<Address> {
+        match *value {
+            StackValue::Offset(offset) => Some(Address::Stack(offset)),
+            StackValue::Constant(ref constant) => Some(Address::Global(constant.value())),
+            _ => None
+        }
+    }
+
+    fn add(&self, bytes: u64) -> Address {
+        match *self {
+            Address::Stack(offset) => Address::Stack(offset.wrapping_add(bytes as i64)),
+            Address::Global(address) => Address::Global(address.wrapping_add(bytes))
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct TaintState {
+    scalars: BTreeMap<il::Scalar, Taint>,
+    memory: BTreeMap<Address, Taint>,
+    /// Taint stored through addresses we could not resolve.
+    unknown: Taint
+}
+
+
+impl TaintState {
+    fn new() -> TaintState {
+        TaintState {
+            scalars: BTreeMap::new(),
+            memory: BTreeMap::new(),
+            unknown: Taint::default()
+        }
+    }
+
+    fn scalar(&self, scalar: &il::Scalar) -> Taint {
+        self.scalars.get(scalar).cloned().unwrap_or(Taint::default())
+    }
+
+    fn set(&mut self, scalar: il::Scalar, taint: Taint) {
+        if taint.is_clean() {
+            self.scalars.remove(&scalar);
+        }
+        else {
+            self.scalars.insert(scalar, taint);
+        }
+    }
+
+    fn expression(&self, expression: &il::Expression) -> Taint {
+        let mut taint = Taint::default();
+        for scalar in scalars_read(expression) {
+            taint.union(&self.scalar(scalar));
+        }
+        taint
+    }
+}
+
+
+/// A summary of how taint flows through a function.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct TaintSummary {
+    /// The parameters whose taint reaches each scalar at a return.
+    returns: BTreeMap<il::Scalar, BTreeSet<il::Parameter>>,
+    /// The path from a source to each scalar it taints at a return.
+    sources: BTreeMap<il::Scalar, Vec<TaintStep>>,
+    /// Parameters which reach a sink, with the path from their use to the
+    /// sink.
+    sinks: Vec<(il::Parameter, TaintSink, Vec<TaintStep>)>
+}
+
+
+fn bytes(bits: usize) -> u64 {
+    ((bits + 7) / 8) as u64
+}
+
+
+fn callee(target: &il::Expression) -> Option<u64> {
+    match *target {
+        il::Expression::Constant(ref constant) => Some(constant.value()),
+        _ => None
+    }
+}
+
+
+struct TaintAnalysis<'t> {
+    function: u64,
+    control_flow_graph: &'t il::ControlFlowGraph,
+    configuration: &'t TaintConfiguration,
+    conventions: &'t StackConventions,
+    stack_pointer: &'t StackPointer,
+    summaries: &'t BTreeMap<u64, TaintSummary>,
+    /// The location of the first instruction in the entry block
+    entry: Option<AnalysisLocation>
+}
+
+
+impl<'t> TaintAnalysis<'t> {
+    fn word_bytes(&self) -> u64 {
+        self.conventions.return_address_bytes() as u64
+    }
+
+    /// The state on entry to the function, where every register holds its
+    /// parameter.
+    fn initial_state(&self) -> TaintState {
+        let mut state = TaintState::new();
+        for register in self.conventions.registers() {
+            let mut taint = Taint::default();
+            taint.origins.insert(Origin::Parameter(il::Parameter::Scalar(register.clone())));
+            state.set(register.clone(), taint);
+        }
+        state
+    }
+
+    /// The state before `location`, given the join of its predecessors.
+    fn state_before(&self, location: &AnalysisLocation, state_in: &Option<TaintState>)
+    -> Result<TaintState> {
+        if self.entry.as_ref() == Some(location) {
+            return match *state_in {
+                Some(ref state_in) => self.join(self.initial_state(), state_in),
+                None => Ok(self.initial_state())
+            };
+        }
+        Ok(state_in.clone().unwrap_or(TaintState::new()))
+    }
+
+    /// The taint of the byte at `address`. Stack slots above the return
+    /// address which have not been written hold a parameter.
+    fn cell(&self, state: &TaintState, address: &Address) -> Taint {
+        if let Some(taint) = state.memory.get(address) {
+            return taint.clone();
+        }
+        let word = self.word_bytes() as i64;
+        let mut taint = Taint::default();
+        if let Address::Stack(offset) = *address {
+            if offset >= word {
+                let parameter = il::Parameter::Stack {
+                    offset: offset - offset % word,
+                    bits: (word * 8) as usize
+                };
+                taint.origins.insert(Origin::Parameter(parameter));
+            }
+        }
+        taint
+    }
+
+    fn load(&self, state: &TaintState, address: Option<Address>, bytes: u64) -> Taint {
+        match address {
+            Some(address) => {
+                let mut taint = Taint::default();
+                for i in 0..bytes {
+                    taint.union(&self.cell(state, &address.add(i)));
+                }
+                taint
+            },
+            None => state.unknown.clone()
+        }
+    }
+
+    fn store(&self, state: &mut TaintState, address: Option<Address>, bytes: u64, taint: Taint) {
+        match address {
+            Some(address) => for i in 0..bytes {
+                state.memory.insert(address.add(i), taint.clone());
+            },
+            None => state.unknown.union(&taint)
+        }
+    }
+
+    /// The taint of the bytes of a string, or other buffer, at `pointer`.
+    fn string(&self, state: &TaintState, pointer: &StackValue) -> Taint {
+        match Address::resolve(pointer) {
+            Some(address) => {
+                let mut taint = self.cell(state, &address);
+                for i in 1..MAX_BUFFER {
+                    match state.memory.get(&address.add(i)) {
+                        Some(cell) => taint.union(cell),
+                        None => break
+                    }
+                }
+                taint
+            },
+            None => state.unknown.clone()
+        }
+    }
+
+    fn address(&self, stack: Option<&StackState>, index: &il::Expression) -> Option<Address> {
+        stack.and_then(|stack| Address::resolve(&stack.eval(index)))
+    }
+
+    /// The stack parameter passed as argument `index` to a call.
+    fn stack_argument(&self, index: usize) -> il::Parameter {
+        let word = self.word_bytes();
+        il::Parameter::Stack {
+            offset: (word + index as u64 * word) as i64,
+            bits: (word * 8) as usize
+        }
+    }
+
+    /// The value of argument `index` passed to a call.
+    fn argument(&self, stack: Option<&StackState>, index: usize) -> StackValue {
+        let stack = match stack {
+            Some(stack) => stack,
+            None => return StackValue::Unknown
+        };
+        match (stack.get(self.conventions.stack_pointer()), self.stack_argument(index)) {
+            (&StackValue::Offset(offset), il::Parameter::Stack { offset: argument, bits }) =>
+                stack.slot(offset + argument, bits).clone(),
+            _ => StackValue::Unknown
+        }
+    }
+
+    /// The taint of `parameter` passed to a call.
+    fn parameter(&self, state: &TaintState, stack: Option<&StackState>, parameter: &il::Parameter)
+    -> Taint {
+        match *parameter {
+            il::Parameter::Scalar(ref scalar) => state.scalar(scalar),
+            il::Parameter::Stack { offset, bits } => {
+                let stack_pointer = stack.map(|stack| stack.get(self.conventions.stack_pointer()));
+                match stack_pointer {
+                    Some(&StackValue::Offset(stack_pointer)) => self.load(
+                        state,
+                        Some(Address::Stack(stack_pointer + offset)),
+                        bytes(bits)
+                    ),
+                    _ => state.unknown.clone()
+                }
+            }
+        }
+    }
+
+    /// Taint the buffer at `pointer`, of `length` bytes, with the source at
+    /// `location`.
+    ///
+    /// If the length is unknown, a buffer on the stack is tainted up to the
+    /// return address.
+    fn taint_buffer(
+        &self,
+        location: &AnalysisLocation,
+        state: &mut TaintState,
+        pointer: &StackValue,
+        length: &StackValue
+    ) {
+        let mut taint = Taint::default();
+        taint.origins.insert(Origin::Source(location.clone()));
+        taint.definitions.insert(location.clone());
+
+        let address = Address::resolve(pointer);
+        let length = match (length, address) {
+            (&StackValue::Constant(ref length), _) => Some(length.value()),
+            (_, Some(Address::Stack(offset))) if offset < 0 => Some(-offset as u64),
+            _ => None
+        };
+        match (address, length) {
+            (Some(address), Some(length)) => for i in 0..length.min(MAX_BUFFER) {
+                state.memory.insert(address.add(i), taint.clone());
+            },
+            _ => state.unknown.union(&taint)
+        }
+    }
+
+    /// The callee summary which introduces taint at a call, if no configured
+    /// source does.
+    fn summary_source(&self, callee: Option<u64>) -> Option<&TaintSummary> {
+        let configured = self.configuration.sources.iter().any(|source| match *source {
+            TaintSource::Buffer { function, .. } |
+            TaintSource::Return { function, .. } => Some(function) == callee,
+            TaintSource::SysRead => false
+        });
+        if configured {
+            return None;
+        }
+        callee.and_then(|callee| self.summaries.get(&callee))
+              .and_then(|summary| if summary.sources.is_empty() { None } else { Some(summary) })
+    }
+
+    fn call(
+        &self,
+        location: &AnalysisLocation,
+        stack: Option<&StackState>,
+        target: &il::Expression,
+        state: &mut TaintState
+    ) -> Taint {
+        let callee = callee(target);
+        let source = {
+            let mut taint = Taint::default();
+            taint.origins.insert(Origin::Source(location.clone()));
+            taint
+        };
+
+        // The taint of the scalars the callee returns
+        let mut returned: BTreeMap<il::Scalar, Taint> = BTreeMap::new();
+        let mut defined = Taint::default();
+        if let Some(summary) = callee.and_then(|callee| self.summaries.get(&callee)) {
+            for (scalar, parameters) in &summary.returns {
+                let mut taint = Taint::default();
+                for parameter in parameters {
+                    taint.union(&self.parameter(state, stack, parameter));
+                }
+                returned.insert(scalar.clone(), taint);
+            }
+        }
+        if let Some(summary) = self.summary_source(callee) {
+            for scalar in summary.sources.keys() {
+                returned.entry(scalar.clone()).or_insert(Taint::default()).union(&source);
+            }
+        }
+
+        for source_ in &self.configuration.sources {
+            match *source_ {
+                TaintSource::Buffer { function, buffer, length } if Some(function) == callee => {
+                    let pointer = self.argument(stack, buffer);
+                    let length = length.map(|length| self.argument(stack, length))
+                                       .unwrap_or(StackValue::Unknown);
+                    self.taint_buffer(location, state, &pointer, &length);
+                    defined.union(&source);
+                },
+                TaintSource::Return { function, ref scalar } if Some(function) == callee => {
+                    returned.entry(scalar.clone()).or_insert(Taint::default()).union(&source);
+                },
+                _ => {}
+            }
+        }
+
+        for scalar in self.conventions.clobbered() {
+            state.set(scalar.clone(), Taint::default());
+        }
+        for (scalar, taint) in returned {
+            defined.union(&taint);
+            state.set(scalar, taint.defined_at(location));
+        }
+
+        defined
+    }
+
+    fn system_call(
+        &self,
+        location: &AnalysisLocation,
+        stack: Option<&StackState>,
+        state: &mut TaintState
+    ) -> Taint {
+        let stack = match stack {
+            Some(stack) => stack,
+            None => return Taint::default()
+        };
+        if !self.configuration.sources.contains(&TaintSource::SysRead) {
+            return Taint::default();
+        }
+        match *stack.get(&il::scalar("eax", 32)) {
+            StackValue::Constant(ref constant) if constant.value() == SYS_READ => {},
+            _ => return Taint::default()
+        }
+
+        let pointer = stack.get(&il::scalar("ecx", 32));
+        let length = stack.get(&il::scalar("edx", 32));
+        self.taint_buffer(location, state, pointer, length);
+        state.set(il::scalar("eax", 32), Taint::default());
+
+        let mut taint = Taint::default();
+        taint.origins.insert(Origin::Source(location.clone()));
+        taint
+    }
+
+    /// Apply the instruction at `location` to `state`, and return the taint of
+    /// the values it defines, with the definitions they were computed from.
+    fn transfer(&self, location: &AnalysisLocation, state: &mut TaintState)
+    -> Result<Taint> {
+        let il = match *location {
+            Instruction(ref il) => il,
+            _ => return Ok(Taint::default())
+        };
+        let stack = self.stack_pointer.state_in(location);
+
+        Ok(match *il.find(self.control_flow_graph)?.operation() {
+            il::Operation::Assign { ref dst, ref src } => {
+                let taint = state.expression(src);
+                state.set(dst.clone(), taint.defined_at(location));
+                taint
+            },
+            il::Operation::Load { ref dst, ref index, .. } => {
+                let address = self.address(stack, index);
+                let taint = self.load(state, address, bytes(dst.bits()));
+                state.set(dst.clone(), taint.defined_at(location));
+                taint
+            },
+            il::Operation::Store { ref index, ref src, .. } => {
+                let address = self.address(stack, index);
+                let taint = state.expression(src);
+                self.store(state, address, bytes(src.bits()), taint.defined_at(location));
+                taint
+            },
+            il::Operation::Brc { ref target, .. } => {
+                if self.stack_pointer.returns().contains_key(location) {
+                    Taint::default()
+                }
+                else {
+                    self.call(location, stack, target, state)
+                }
+            },
+            il::Operation::Phi { ref dst, ref src } => {
+                let mut taint = Taint::default();
+                for src in src.values() {
+                    if let il::MultiVar::Scalar(ref src) = *src {
+                        taint.union(&state.scalar(src));
+                    }
+                }
+                if let il::MultiVar::Scalar(ref dst) = *dst {
+                    state.set(dst.clone(), taint.defined_at(location));
+                }
+                taint
+            },
+            il::Operation::Raise { .. } => self.system_call(location, stack, state)
+        })
+    }
+
+    /// The sinks reached at `location`, with the taint reaching them, and the
+    /// path to the sink in a callee.
+    fn sinks(&self, location: &AnalysisLocation, state: &TaintState)
+    -> Result<Vec<(TaintSink, Taint, Vec<TaintStep>)>> {
+        let il = match *location {
+            Instruction(ref il) => il,
+            _ => return Ok(Vec::new())
+        };
+        let stack = self.stack_pointer.state_in(location);
+        let configured = |sink: &TaintSink| self.configuration.sinks.contains(sink);
+
+        let mut sinks = Vec::new();
+        match *il.find(self.control_flow_graph)?.operation() {
+            il::Operation::Store { ref index, .. } => {
+                if configured(&TaintSink::StoreAddress) {
+                    sinks.push((TaintSink::StoreAddress, state.expression(index), Vec::new()));
+                }
+            },
+            il::Operation::Brc { ref target, .. } => {
+                let callee = callee(target);
+                if callee.is_none() && configured(&TaintSink::IndirectBranch) {
+                    sinks.push((TaintSink::IndirectBranch, state.expression(target), Vec::new()));
+                }
+                if !self.stack_pointer.returns().contains_key(location) {
+                    for sink in &self.configuration.sinks {
+                        match *sink {
+                            TaintSink::Argument { function, argument }
+                            if Some(function) == callee => {
+                                let parameter = self.stack_argument(argument);
+                                let taint = self.parameter(state, stack, &parameter);
+                                sinks.push((sink.clone(), taint, Vec::new()));
+                            },
+                            TaintSink::Buffer { function, argument }
+                            if Some(function) == callee => {
+                                let pointer = self.argument(stack, argument);
+                                let taint = self.string(state, &pointer);
+                                sinks.push((sink.clone(), taint, Vec::new()));
+                            },
+                            _ => {}
+                        }
+                    }
+                    if let Some(summary) = callee.and_then(|callee| self.summaries.get(&callee)) {
+                        for &(ref parameter, ref sink, ref path) in &summary.sinks {
+                            let taint = self.parameter(state, stack, parameter);
+                            sinks.push((sink.clone(), taint, path.clone()));
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        sinks.retain(|&(_, ref taint, _)| !taint.is_clean());
+        Ok(sinks)
+    }
+
+    fn step(&self, location: &AnalysisLocation) -> TaintStep {
+        TaintStep {
+            function: self.function,
+            location: location.clone()
+        }
+    }
+
+    /// The def-use path taken by taint from `origin` to reach `end`, where it
+    /// has the taint `taint`.
+    ///
+    /// `definitions` holds the taint of the values defined at each location.
+    /// When `origin` is a source in a callee, the path begins in that callee.
+    fn path(
+        &self,
+        definitions: &BTreeMap<AnalysisLocation, Taint>,
+        taint: &Taint,
+        origin: &Origin,
+        end: &AnalysisLocation
+    ) -> Vec<TaintStep> {
+        let holds = |location: &AnalysisLocation| {
+            definitions.get(location)
+                       .map(|taint| taint.origins.contains(origin))
+                       .unwrap_or(false)
+        };
+
+        // Search backwards from end, remembering where each definition flows
+        let mut next: BTreeMap<AnalysisLocation, AnalysisLocation> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        for definition in &taint.definitions {
+            if holds(definition) && !next.contains_key(definition) {
+                next.insert(definition.clone(), end.clone());
+                queue.push_back(definition.clone());
+            }
+        }
+
+        let mut start = None;
+        while let Some(location) = queue.pop_front() {
+            if *origin == Origin::Source(location.clone()) {
+                start = Some(location);
+                break;
+            }
+            let parents = definitions[&location].definitions
+                .iter()
+                .filter(|parent| holds(parent))
+                .cloned()
+                .collect::<Vec<AnalysisLocation>>();
+            if parents.is_empty() {
+                start = Some(location);
+                break;
+            }
+            for parent in parents {
+                if !next.contains_key(&parent) {
+                    next.insert(parent.clone(), location.clone());
+                    queue.push_back(parent);
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        if let Some(ref start) = start {
+            if let Origin::Source(ref source) = *origin {
+                if source == start {
+                    let callee = match *start {
+                        Instruction(ref il) => match il.find(self.control_flow_graph)
+                                                       .map(|i| i.operation()) {
+                            Ok(&il::Operation::Brc { ref target, .. }) => callee(target),
+                            _ => None
+                        },
+                        _ => None
+                    };
+                    if let Some(summary) = self.summary_source(callee) {
+                        if let Some(callee_path) = summary.sources.values().next() {
+                            path.extend(callee_path.iter().cloned());
+                        }
+                    }
+                }
+            }
+            let mut location = start.clone();
+            while location != *end {
+                path.push(self.step(&location));
+                location = next[&location].clone();
+            }
+        }
+        path.push(self.step(end));
+        path
+    }
+
+    /// Run taint analysis over this function, returning its summary and the
+    /// flows from sources to sinks found in it.
+    fn analyze(&self) -> Result<(TaintSummary, Vec<TaintFlow>)> {
+        let states = fixed_point_forward(self, self.control_flow_graph)?;
+        let states_in = forward_states_in(self, self.control_flow_graph, &states)?;
+
+        let mut definitions = BTreeMap::new();
+        let mut sinks = Vec::new();
+        for location in states.keys() {
+            let mut state = self.state_before(location, &states_in.get(location).cloned())?;
+            for (sink, taint, callee_path) in self.sinks(location, &state)? {
+                sinks.push((location.clone(), sink, taint, callee_path));
+            }
+            let defined = self.transfer(location, &mut state)?;
+            if !defined.is_clean() {
+                definitions.insert(location.clone(), defined);
+            }
+        }
+
+        let mut summary = TaintSummary::default();
+        let mut flows: Vec<TaintFlow> = Vec::new();
+
+        for (location, sink, taint, callee_path) in sinks {
+            for origin in &taint.origins {
+                let mut path = self.path(&definitions, &taint, origin, &location);
+                path.extend(callee_path.iter().cloned());
+                match *origin {
+                    Origin::Source(_) => {
+                        let flow = TaintFlow { sink: sink.clone(), path: path };
+                        if !flows.contains(&flow) {
+                            flows.push(flow);
+                        }
+                    },
+                    Origin::Parameter(ref parameter) => {
+                        let exists = summary.sinks.iter().any(|s| {
+                            s.0 == *parameter && s.1 == sink && s.2.last() == path.last()
+                        });
+                        if !exists {
+                            summary.sinks.push((parameter.clone(), sink.clone(), path));
+                        }
+                    }
+                }
+            }
+        }
+
+        for location in self.stack_pointer.returns().keys() {
+            let state = match states.get(location) {
+                Some(state) => state,
+                None => continue
+            };
+            for scalar in self.conventions.clobbered() {
+                let taint = state.scalar(scalar);
+                for origin in &taint.origins {
+                    match *origin {
+                        Origin::Parameter(ref parameter) => {
+                            summary.returns
+                                   .entry(scalar.clone())
+                                   .or_insert(BTreeSet::new())
+                                   .insert(parameter.clone());
+                        },
+                        Origin::Source(_) => {
+                            if !summary.sources.contains_key(scalar) {
+                                let path = self.path(&definitions, &taint, origin, location);
+                                summary.sources.insert(scalar.clone(), path);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok((summary, flows))
+    }
+}
+
+
+impl<'t> FixedPointAnalysis<TaintState> for TaintAnalysis<'t> {
+    fn trans(
+        &self,
+        analysis_location: &AnalysisLocation,
+        state_in: &Option<TaintState>
+    ) -> Result<TaintState> {
+        let mut state = self.state_before(analysis_location, state_in)?;
+        self.transfer(analysis_location, &mut state)?;
+        Ok(state)
+    }
+
+    fn join(&self, mut state0: TaintState, state1: &TaintState) -> Result<TaintState> {
+        for (scalar, taint) in &state1.scalars {
+            state0.scalars.entry(scalar.clone()).or_insert(Taint::default()).union(taint);
+        }
+        let addresses = state0.memory
+            .keys()
+            .chain(state1.memory.keys())
+            .cloned()
+            .collect::<BTreeSet<Address>>();
+        for address in addresses {
+            let mut taint = self.cell(&state0, &address);
+            taint.union(&self.cell(state1, &address));
+            state0.memory.insert(address, taint);
+        }
+        state0.unknown.union(&state1.unknown);
+        Ok(state0)
+    }
+}
+
+
+/// Find every flow of taint from a source to a sink in `program`.
+///
+/// Summaries of each function are computed until they no longer change, or
+/// once for every function in the program, whichever comes first. The stack
+/// cleanup of functions with a signature is taken from their signature.
+pub fn taint(program: &il::Program, configuration: &TaintConfiguration)
+-> Result<Vec<TaintFlow>> {
+    let mut conventions = configuration.conventions.clone();
+    for function in program.functions() {
+        if let Some(signature) = function.signature() {
+            conventions.add_callee_cleanup(function.address(), signature.stack_cleanup());
+        }
+    }
+
+    let mut stack_pointers = BTreeMap::new();
+    for function in program.functions() {
+        let stack_pointer = stack_pointer::compute(function.control_flow_graph(), &conventions)?;
+        stack_pointers.insert(function.address(), stack_pointer);
+    }
+
+    let analyze = |function: &il::Function, summaries: &BTreeMap<u64, TaintSummary>| {
+        let control_flow_graph = function.control_flow_graph();
+        let entry = control_flow_graph.entry()
+            .and_then(|entry| control_flow_graph.block(entry))
+            .map(|block| match block.instructions().first() {
+                Some(instruction) =>
+                    AnalysisLocation::instruction(block.index(), instruction.index()),
+                None => AnalysisLocation::empty_block(block.index())
+            });
+        TaintAnalysis {
+            function: function.address(),
+            control_flow_graph: control_flow_graph,
+            configuration: configuration,
+            conventions: &conventions,
+            stack_pointer: &stack_pointers[&function.address()],
+            summaries: summaries,
+            entry: entry
+        }.analyze()
+    };
+
+    let mut summaries = BTreeMap::new();
+    for _ in 0..(program.functions().len() + 1) {
+        let mut next = BTreeMap::new();
+        for function in program.functions() {
+            next.insert(function.address(), analyze(function, &summaries)?.0);
+        }
+        if next == summaries {
+            break;
+        }
+        summaries = next;
+    }
+
+    let mut flows = Vec::new();
+    for function in program.functions() {
+        flows.extend(analyze(function, &summaries)?.1);
+    }
+
+    Ok(flows)
+}
@@ -0,0 +1,670 @@
+//! Constraint-based recovery of types.
+//!
+//! Every scalar definition, scalar on entry to the function, and precise
+//! stack or global slot is given a type variable. Constraints are generated
+//! from the way values are used, and solved by unification.
+//!
+//! * Values used as the base of a `Load` or `Store` address are pointers.
+//!   Accesses at a constant offset from a pointer are fields of the `Struct`
+//!   it points to.
+//! * Operands of `Cmplts`, `Divs`, `Mods` and `Sext` are signed integers, and
+//!   operands of `Cmpltu`, `Divu`, `Modu`, `Shr` and `Zext` are unsigned
+//!   integers. Operands of `Itof`, and results of `Ftoi`, are signed
+//!   integers.
+//! * Values assigned, compared for equality, or stored to and loaded from
+//!   the same memory, have the same type.
+//!
+//! Integer constraints are only taken from definitions which are used, so
+//! flags computed, but never read, by lifted instructions do not decide the
+//! signedness of their operands.
+
+use analysis::analysis_location::*;
+use analysis::{Analysis, MemoryLocation, Region, Regions};
+use analysis::value_set::Endian;
+use error::*;
+use il;
+use std::collections::BTreeMap;
+
+
+/// How far definitions are followed when looking for the base of an address.
+const MAX_BASE_DEPTH: usize = 4;
+
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Variable {
+    /// The scalar written at a location
+    Definition(InstructionLocation),
+    /// A scalar on entry to the function
+    Entry(String),
+    /// A stack slot, at an offset from the stack pointer on entry
+    Stack(i64),
+    /// Global memory
+    Global(u64)
+}
+
+
+#[derive(Clone, Debug, Default)]
+struct Class {
+    pointer: bool,
+    integer: bool,
+    signed: bool,
+    unsigned: bool,
+    fields: BTreeMap<i64, usize>
+}
+
+
+/// Type variables, with their constraints solved by unification as they are
+/// added.
+struct Constraints {
+    variables: BTreeMap<Variable, usize>,
+    parents: Vec<usize>,
+    classes: Vec<Class>,
+    bits: Vec<usize>
+}
+
+
+impl Constraints {
+    fn new() -> Constraints {
+        Constraints {
+            variables: BTreeMap::new(),
+            parents: Vec::new(),
+            classes: Vec::new(),
+            bits: Vec::new()
+        }
+    }
+
+    fn fresh(&mut self, bits: usize) -> usize {
+        let id = self.parents.len();
+        self.parents.push(id);
+        self.classes.push(Class::default());
+        self.bits.push(bits);
+        id
+    }
+
+    fn variable(&mut self, variable: Variable, bits: usize) -> usize {
+        if let Some(id) = self.variables.get(&variable) {
+            return *id;
+        }
+        let id = self.fresh(bits);
+        self.variables.insert(variable, id);
+        id
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        while self.parents[id] != id {
+            let grandparent = self.parents[self.parents[id]];
+            self.parents[id] = grandparent;
+            id = grandparent;
+        }
+        id
+    }
+
+    fn class(&mut self, id: usize) -> &mut Class {
+        let root = self.find(id);
+        &mut self.classes[root]
+    }
+
+    fn unify(&mut self, lhs: usize, rhs: usize) {
+        let mut queue = vec![(lhs, rhs)];
+        while let Some((lhs, rhs)) = queue.pop() {
+            let lhs = self.find(lhs);
+            let rhs = self.find(rhs);
+            if lhs == rhs {
+                continue;
+            }
+            self.parents[rhs] = lhs;
+            let class = ::std::mem::replace(&mut self.classes[rhs], Class::default());
+            let root = &mut self.classes[lhs];
+            root.pointer |= class.pointer;
+            root.integer |= class.integer;
+            root.signed |= class.signed;
+            root.unsigned |= class.unsigned;
+            for (offset, field) in class.fields {
+                match root.fields.get(&offset) {
+                    Some(root_field) => queue.push((*root_field, field)),
+                    None => { root.fields.insert(offset, field); }
+                }
+            }
+        }
+    }
+
+    /// The field of the struct pointed to by `base` at `offset`.
+    fn field(&mut self, base: usize, offset: i64, bits: usize) -> usize {
+        if let Some(field) = self.class(base).fields.get(&offset) {
+            return *field;
+        }
+        let field = self.fresh(bits);
+        self.class(base).fields.insert(offset, field);
+        field
+    }
+
+    fn set_signed(&mut self, id: usize) {
+        let class = self.class(id);
+        class.integer = true;
+        class.signed = true;
+    }
+
+    fn set_unsigned(&mut self, id: usize) {
+        let class = self.class(id);
+        class.integer = true;
+        class.unsigned = true;
+    }
+}
+
+
+fn is_constant(expression: &il::Expression) -> bool {
+    match *expression {
+        il::Expression::Constant(_) => true,
+        _ => false
+    }
+}
+
+
+/// Sign extend an offset of `bits`.
+fn signed_offset(offset: u64, bits: usize) -> i64 {
+    if bits >= 64 {
+        offset as i64
+    }
+    else {
+        ((offset << (64 - bits)) as i64) >> (64 - bits)
+    }
+}
+
+
+struct TypeRecovery<'t> {
+    analysis: &'t Analysis<'t>,
+    constraints: Constraints
+}
+
+
+impl<'t> TypeRecovery<'t> {
+    fn control_flow_graph(&self) -> &'t il::ControlFlowGraph {
+        self.analysis.control_flow_graph()
+    }
+
+    /// The definitions of `scalar` which reach `location`.
+    fn definitions(&self, location: &AnalysisLocation, scalar: &il::Scalar)
+    -> Result<Vec<InstructionLocation>> {
+        let mut definitions = Vec::new();
+        let reaches = match self.analysis.reaching_definitions().get(location) {
+            Some(reaches) => reaches,
+            None => return Ok(definitions)
+        };
+        for definition in reaches.in_() {
+            if let AnalysisLocation::Instruction(ref il) = *definition {
+                let written = il.find(self.control_flow_graph())?.variable_written();
+                if let Some(variable) = written {
+                    if variable.multi_var_clone() == il::MultiVar::Scalar(scalar.clone()) {
+                        definitions.push(il.clone());
+                    }
+                }
+            }
+        }
+        Ok(definitions)
+    }
+
+    /// The type variable of `scalar` where it is read at `location`.
+    fn scalar(&mut self, location: &AnalysisLocation, scalar: &il::Scalar)
+    -> Result<usize> {
+        let definitions = self.definitions(location, scalar)?;
+        if definitions.is_empty() {
+            let variable = Variable::Entry(scalar.name().to_string());
+            return Ok(self.constraints.variable(variable, scalar.bits()));
+        }
+        let mut ids = Vec::new();
+        for definition in definitions {
+            ids.push(self.constraints.variable(Variable::Definition(definition), scalar.bits()));
+        }
+        for id in &ids[1..] {
+            self.constraints.unify(ids[0], *id);
+        }
+        Ok(ids[0])
+    }
+
+    /// The type variable of the value of `expression` at `location`.
+    ///
+    /// Integer constraints are only added when `live` is true.
+    fn expression(
+        &mut self,
+        location: &AnalysisLocation,
+        expression: &il::Expression,
+        live: bool
+    ) -> Result<usize> {
+        Ok(match *expression {
+            il::Expression::Scalar(ref scalar) => self.scalar(location, scalar)?,
+            il::Expression::Constant(ref constant) => self.constraints.fresh(constant.bits()),
+            il::Expression::Add(ref lhs, ref rhs) |
+            il::Expression::And(ref lhs, ref rhs) |
+            il::Expression::Or(ref lhs, ref rhs) |
+            il::Expression::Xor(ref lhs, ref rhs) => {
+                self.expression(location, lhs, live)?;
+                self.expression(location, rhs, live)?;
+                self.constraints.fresh(expression.bits())
+            },
+            il::Expression::Sub(ref lhs, ref rhs) => {
+                let lhs_id = self.expression(location, lhs, live)?;
+                let rhs_id = self.expression(location, rhs, live)?;
+                let result = self.constraints.fresh(expression.bits());
+                // The difference of two values, such as pointers, is an
+                // integer.
+                if live && !is_constant(lhs) && !is_constant(rhs) && lhs != rhs {
+                    self.constraints.unify(lhs_id, rhs_id);
+                    self.constraints.class(result).integer = true;
+                }
+                result
+            },
+            il::Expression::Mul(ref lhs, ref rhs) |
+            il::Expression::Shl(ref lhs, ref rhs) => {
+                let lhs = self.expression(location, lhs, live)?;
+                let rhs = self.expression(location, rhs, live)?;
+                let result = self.constraints.fresh(expression.bits());
+                if live {
+                    for id in &[lhs, rhs, result] {
+                        self.constraints.class(*id).integer = true;
+                    }
+                }
+                result
+            },
+            il::Expression::Divu(ref lhs, ref rhs) |
+            il::Expression::Modu(ref lhs, ref rhs) |
+            il::Expression::Shr(ref lhs, ref rhs) => {
+                let lhs = self.expression(location, lhs, live)?;
+                let rhs = self.expression(location, rhs, live)?;
+                let result = self.constraints.fresh(expression.bits());
+                if live {
+                    self.constraints.set_unsigned(lhs);
+                    self.constraints.class(rhs).integer = true;
+                    self.constraints.set_unsigned(result);
+                }
+                result
+            },
+            il::Expression::Divs(ref lhs, ref rhs) |
+            il::Expression::Mods(ref lhs, ref rhs) => {
+                let lhs = self.expression(location, lhs, live)?;
+                let rhs = self.expression(location, rhs, live)?;
+                let result = self.constraints.fresh(expression.bits());
+                if live {
+                    for id in &[lhs, rhs, result] {
+                        self.constraints.set_signed(*id);
+                    }
+                }
+                result
+            },
+            il::Expression::Cmpeq(ref lhs, ref rhs) |
+            il::Expression::Cmpneq(ref lhs, ref rhs) => {
+                let lhs_id = self.expression(location, lhs, live)?;
+                let rhs_id = self.expression(location, rhs, live)?;
+                if !is_constant(lhs) && !is_constant(rhs) {
+                    self.constraints.unify(lhs_id, rhs_id);
+                }
+                self.constraints.fresh(1)
+            },
+            il::Expression::Cmplts(ref lhs, ref rhs) |
+            il::Expression::Cmpltu(ref lhs, ref rhs) => {
+                let lhs_id = self.expression(location, lhs, live)?;
+                let rhs_id = self.expression(location, rhs, live)?;
+                if live {
+                    for id in &[lhs_id, rhs_id] {
+                        match *expression {
+                            il::Expression::Cmplts(_, _) => self.constraints.set_signed(*id),
+                            _ => self.constraints.set_unsigned(*id)
+                        }
+                    }
+                }
+                self.constraints.fresh(1)
+            },
+            il::Expression::Zext(bits, ref src) => {
+                let src = self.expression(location, src, live)?;
+                if live {
+                    self.constraints.set_unsigned(src);
+                }
+                self.constraints.fresh(bits)
+            },
+            il::Expression::Sext(bits, ref src) => {
+                let src = self.expression(location, src, live)?;
+                let result = self.constraints.fresh(bits);
+                if live {
+                    self.constraints.set_signed(src);
+                    self.constraints.set_signed(result);
+                }
+                result
+            },
+            il::Expression::Trun(bits, ref src) |
+            il::Expression::Fext(bits, ref src) => {
+                self.expression(location, src, live)?;
+                self.constraints.fresh(bits)
+            },
+            il::Expression::Fadd(ref lhs, ref rhs) |
+            il::Expression::Fsub(ref lhs, ref rhs) |
+            il::Expression::Fmul(ref lhs, ref rhs) |
+            il::Expression::Fdiv(ref lhs, ref rhs) => {
+                let lhs_id = self.expression(location, lhs, live)?;
+                let rhs_id = self.expression(location, rhs, live)?;
+                let result = self.constraints.fresh(expression.bits());
+                if live && !is_constant(lhs) && !is_constant(rhs) {
+                    self.constraints.unify(lhs_id, rhs_id);
+                    self.constraints.unify(lhs_id, result);
+                }
+                result
+            },
+            il::Expression::Fcmpeq(ref lhs, ref rhs) |
+            il::Expression::Fcmplt(ref lhs, ref rhs) |
+            il::Expression::Fcmpuo(ref lhs, ref rhs) => {
+                let lhs_id = self.expression(location, lhs, live)?;
+                let rhs_id = self.expression(location, rhs, live)?;
+                if !is_constant(lhs) && !is_constant(rhs) {
+                    self.constraints.unify(lhs_id, rhs_id);
+                }
+                self.constraints.fresh(1)
+            },
+            il::Expression::Itof(bits, ref src) => {
+                let src = self.expression(location, src, live)?;
+                if live {
+                    self.constraints.set_signed(src);
+                }
+                self.constraints.fresh(bits)
+            },
+            il::Expression::Ftoi(bits, ref src) => {
+                self.expression(location, src, live)?;
+                let result = self.constraints.fresh(bits);
+                if live {
+                    self.constraints.set_signed(result);
+                }
+                result
+            }
+        })
+    }
+
+    /// Find the pointer an address is based on, and the constant offset of
+    /// the address from it, following definitions of the form
+    /// `scalar = base + offset`.
+    ///
+    /// The offset is `None` if the address adds a variable to the base.
+    fn base(&mut self, location: &AnalysisLocation, index: &il::Expression, depth: usize)
+    -> Result<Option<(usize, Option<i64>)>> {
+        Ok(match *index {
+            il::Expression::Scalar(ref scalar) => {
+                let definitions = self.definitions(location, scalar)?;
+                if definitions.len() == 1 && depth < MAX_BASE_DEPTH {
+                    let definition = &definitions[0];
+                    let src = match *definition.find(self.control_flow_graph())?.operation() {
+                        il::Operation::Assign { ref src, .. } => Some(src.clone()),
+                        _ => None
+                    };
+                    if let Some(src) = src {
+                        if !is_constant(&src) {
+                            let definition_location = definition.clone().into();
+                            if let Some(base) = self.base(&definition_location, &src, depth + 1)? {
+                                return Ok(Some(base));
+                            }
+                        }
+                    }
+                }
+                Some((self.scalar(location, scalar)?, Some(0)))
+            },
+            il::Expression::Add(ref lhs, ref rhs) => match (&**lhs, &**rhs) {
+                (&il::Expression::Constant(ref constant), base) |
+                (base, &il::Expression::Constant(ref constant)) =>
+                    self.base(location, base, depth)?.map(|(base, offset)| {
+                        let constant = signed_offset(constant.value(), constant.bits());
+                        (base, offset.map(|offset| offset.wrapping_add(constant)))
+                    }),
+                (lhs, rhs) => {
+                    // An index into an array. The base is whichever side is
+                    // not scaled.
+                    let base = match *rhs {
+                        il::Expression::Mul(_, _) |
+                        il::Expression::Shl(_, _) => lhs,
+                        _ => rhs
+                    };
+                    self.base(location, base, depth)?.map(|(base, _)| (base, None))
+                }
+            },
+            il::Expression::Sub(ref lhs, ref rhs) => match **rhs {
+                il::Expression::Constant(ref constant) =>
+                    self.base(location, lhs, depth)?.map(|(base, offset)| {
+                        let constant = signed_offset(constant.value(), constant.bits());
+                        (base, offset.map(|offset| offset.wrapping_sub(constant)))
+                    }),
+                _ => None
+            },
+            _ => None
+        })
+    }
+
+    /// Add the constraints for a memory access of `value`, at `index`.
+    fn memory(
+        &mut self,
+        location: &AnalysisLocation,
+        index: &il::Expression,
+        value: usize,
+        bits: usize
+    ) -> Result<()> {
+        let memory_location = self.analysis
+            .memory_locations()
+            .and_then(|memory_locations| memory_locations.get(location))
+            .cloned()
+            .unwrap_or(MemoryLocation::Unknown);
+
+        let stack = match memory_location {
+            MemoryLocation::Addresses { ref region, ref offsets, .. } => {
+                if let Some(offset) = offsets.constant_value() {
+                    let slot = match *region {
+                        Some(Region::Stack) =>
+                            Some(Variable::Stack(signed_offset(offset, offsets.bits()))),
+                        None => Some(Variable::Global(offset)),
+                        Some(Region::Heap(_)) => None
+                    };
+                    if let Some(slot) = slot {
+                        let slot = self.constraints.variable(slot, bits);
+                        self.constraints.unify(slot, value);
+                    }
+                }
+                *region == Some(Region::Stack)
+            },
+            MemoryLocation::Unknown => false
+        };
+
+        self.expression(location, index, true)?;
+        if let Some((base, offset)) = self.base(location, index, 0)? {
+            self.constraints.class(base).pointer = true;
+            // Fields of the stack frame are stack slots
+            if let (false, Some(offset)) = (stack, offset) {
+                let field = self.constraints.field(base, offset, bits);
+                self.constraints.unify(field, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate(&mut self) -> Result<()> {
+        let control_flow_graph = self.control_flow_graph();
+
+        for block in control_flow_graph.blocks() {
+            for instruction in block.instructions() {
+                let il = InstructionLocation::new(block.index(), instruction.index());
+                let location: AnalysisLocation = il.clone().into();
+                let live = self.analysis
+                    .def_use()
+                    .get(&location)
+                    .map(|uses| !uses.is_empty())
+                    .unwrap_or(false);
+
+                match *instruction.operation() {
+                    il::Operation::Assign { ref dst, ref src } => {
+                        let src = self.expression(&location, src, live)?;
+                        let dst = self.constraints.variable(Variable::Definition(il), dst.bits());
+                        self.constraints.unify(dst, src);
+                    },
+                    il::Operation::Store { ref index, ref src, .. } => {
+                        let src_id = self.expression(&location, src, true)?;
+                        self.memory(&location, index, src_id, src.bits())?;
+                    },
+                    il::Operation::Load { ref dst, ref index, .. } => {
+                        let dst_id = self.constraints.variable(
+                            Variable::Definition(il),
+                            dst.bits()
+                        );
+                        self.memory(&location, index, dst_id, dst.bits())?;
+                    },
+                    il::Operation::Brc { ref target, ref condition } => {
+                        self.expression(&location, target, true)?;
+                        self.expression(&location, condition, true)?;
+                    },
+                    il::Operation::Phi { ref dst, ref src } => {
+                        if let il::MultiVar::Scalar(ref dst) = *dst {
+                            let dst = self.constraints.variable(
+                                Variable::Definition(il),
+                                dst.bits()
+                            );
+                            for src in src.values() {
+                                if let il::MultiVar::Scalar(ref src) = *src {
+                                    let src = self.scalar(&location, src)?;
+                                    self.constraints.unify(dst, src);
+                                }
+                            }
+                        }
+                    },
+                    il::Operation::Raise { ref expr } => {
+                        self.expression(&location, expr, true)?;
+                    }
+                }
+            }
+        }
+
+        for edge in control_flow_graph.edges() {
+            if let Some(ref condition) = *edge.condition() {
+                let location = AnalysisLocation::edge(edge.head(), edge.tail());
+                self.expression(&location, condition, true)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Solved constraints, from which `il::Type`s are built.
+struct Solution {
+    constraints: Constraints,
+    structs: BTreeMap<usize, usize>
+}
+
+
+impl Solution {
+    fn new(mut constraints: Constraints) -> Solution {
+        let mut structs = BTreeMap::new();
+        for id in 0..constraints.parents.len() {
+            let root = constraints.find(id);
+            if !constraints.classes[root].fields.is_empty() && !structs.contains_key(&root) {
+                let index = structs.len();
+                structs.insert(root, index);
+            }
+        }
+        Solution {
+            constraints: constraints,
+            structs: structs
+        }
+    }
+
+    fn type_(&mut self, id: usize, bits: usize) -> il::Type {
+        let root = self.constraints.find(id);
+        let class = &self.constraints.classes[root];
+        if class.pointer || !class.fields.is_empty() {
+            il::Type::Pointer {
+                bits: bits,
+                pointee: self.structs.get(&root).cloned()
+            }
+        }
+        else if class.integer {
+            let signedness = match (class.signed, class.unsigned) {
+                (true, false) => il::Signedness::Signed,
+                (false, true) => il::Signedness::Unsigned,
+                _ => il::Signedness::Unknown
+            };
+            il::Type::Integer {
+                bits: bits,
+                signedness: signedness
+            }
+        }
+        else {
+            il::Type::Unknown(bits)
+        }
+    }
+
+    fn types(mut self) -> il::FunctionTypes {
+        let mut types = il::FunctionTypes::new();
+
+        let mut structs = self.structs
+            .iter()
+            .map(|(root, index)| (*index, *root))
+            .collect::<Vec<(usize, usize)>>();
+        structs.sort();
+        for (_, root) in structs {
+            let fields = self.constraints.classes[root].fields.clone();
+            let mut struct_fields = BTreeMap::new();
+            for (offset, field) in fields {
+                let bits = self.constraints.bits[field];
+                struct_fields.insert(offset, self.type_(field, bits));
+            }
+            types.add_struct(il::Struct::new(struct_fields));
+        }
+
+        let variables = self.constraints.variables.clone();
+        for (variable, id) in variables {
+            let bits = self.constraints.bits[id];
+            let type_ = self.type_(id, bits);
+            match variable {
+                Variable::Definition(il) =>
+                    types.set_definition(il.block_index(), il.instruction_index(), type_),
+                Variable::Entry(name) => types.set_argument(name, type_),
+                Variable::Stack(offset) => types.set_stack(offset, type_),
+                Variable::Global(address) => types.set_global(address, type_)
+            }
+        }
+
+        types
+    }
+}
+
+
+/// Recover the types of the values and memory of the function analysed by
+/// `analysis`.
+///
+/// Stack and global slots are only typed when `analysis` tracks memory.
+pub fn compute(analysis: &Analysis) -> Result<il::FunctionTypes> {
+    let mut type_recovery = TypeRecovery {
+        analysis: analysis,
+        constraints: Constraints::new()
+    };
+    type_recovery.generate()?;
+    Ok(Solution::new(type_recovery.constraints).types())
+}
+
+
+/// Recover the types of every function in `program`, and store them in
+/// that function.
+///
+/// Memory is tracked with value-set analysis, with pointers tracked as
+/// described by `regions`.
+pub fn recover_types(
+    program: &mut il::Program,
+    max: usize,
+    endian: Endian,
+    regions: &Regions
+) -> Result<()> {
+    for function in program.functions_mut() {
+        let types = {
+            let analysis = Analysis::new_with_memory(
+                function.control_flow_graph(),
+                max,
+                endian.clone(),
+                regions
+            )?;
+            compute(&analysis)?
+        };
+        function.set_types(Some(types));
+    }
+    Ok(())
+}
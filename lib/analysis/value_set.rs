@@ -1,8 +1,10 @@
 use analysis::analysis_location::AnalysisLocation::*;
 use analysis::fixed_point::*;
 use analysis::lattice::*;
+use analysis::strided_interval::{mask, StridedInterval};
 use error::*;
 use il;
+use loader;
 use std::collections::BTreeMap;
 
 
@@ -43,10 +45,101 @@ impl From<::loader::Endian> for Endian {
 }
 
 
+/// Describes the abstract memory regions created by value-set analysis.
+///
+/// Without any regions, all memory is treated as one flat address space of
+/// constant addresses.
+#[derive(Clone, Debug)]
+pub struct Regions<'m> {
+    stack_pointer: Option<il::Scalar>,
+    memory: Option<&'m loader::memory::Memory>,
+    allocators: BTreeMap<u64, il::Scalar>
+}
+
+
+impl<'m> Regions<'m> {
+    /// Create a new `Regions` with no regions.
+    pub fn new() -> Regions<'m> {
+        Regions {
+            stack_pointer: None,
+            memory: None,
+            allocators: BTreeMap::new()
+        }
+    }
+
+    /// Create a new `Regions` for functions lifted by `translator::x86`, with
+    /// a stack region based at `esp`, and global memory given by a loader.
+    pub fn x86(memory: Option<&'m loader::memory::Memory>) -> Regions<'m> {
+        Regions {
+            stack_pointer: Some(il::scalar("esp", 32)),
+            memory: memory,
+            allocators: BTreeMap::new()
+        }
+    }
+
+    /// Set the scalar which points to the base of the stack region on entry
+    /// to the function.
+    pub fn set_stack_pointer(&mut self, stack_pointer: Option<il::Scalar>) {
+        self.stack_pointer = stack_pointer;
+    }
+
+    /// Set the memory given by a loader. Loads from read-only segments of
+    /// this memory return the contents of these segments, and loads from
+    /// writable segments return `LatticeValue::Join`.
+    pub fn set_memory(&mut self, memory: Option<&'m loader::memory::Memory>) {
+        self.memory = memory;
+    }
+
+    /// Branches to `address` allocate a new heap region per branch, and
+    /// return a pointer to it in `result`.
+    pub fn add_allocator(&mut self, address: u64, result: il::Scalar) {
+        self.allocators.insert(address, result);
+    }
+
+    /// Returns the initial value of the global memory at the given address,
+    /// or `None` if this address is not given by a loader.
+    fn global_load(&self, address: u64, bits: usize, endian: &Endian)
+    -> Option<LatticeValue> {
+        let memory = match self.memory {
+            Some(memory) => memory,
+            None => return None
+        };
+
+        let bytes = if bits & 0x7 != 0 { (bits / 8) + 1 } else { bits / 8 };
+        let segment = match memory.segments().range(..(address + 1)).next_back() {
+            Some((_, segment)) => segment,
+            None => return None
+        };
+        if address + bytes as u64 > segment.address() + segment.len() as u64 {
+            return None;
+        }
+
+        if segment.permissions().contains(loader::memory::WRITE) {
+            return Some(LatticeValue::Join);
+        }
+
+        let offset = (address - segment.address()) as usize;
+        let mut value: u64 = 0;
+        for i in 0..bytes {
+            let byte = segment.bytes()[offset + i] as u64;
+            value |= match *endian {
+                Endian::Big => byte << ((bytes - i - 1) * 8),
+                Endian::Little => byte << (i * 8)
+            };
+        }
+        Some(LatticeValue::value(il::const_(value, bits)))
+    }
+}
+
+
 struct ValueSetAnalysis<'v> {
     control_flow_graph: &'v il::ControlFlowGraph,
     max: usize,
-    endian: Endian
+    endian: Endian,
+    domain: Domain,
+    regions: &'v Regions<'v>,
+    /// The location of the first instruction in the entry block
+    entry: Option<AnalysisLocation>
 }
 
 
@@ -54,31 +147,275 @@ impl<'v> ValueSetAnalysis<'v> {
     pub fn new(
         control_flow_graph: &'v il::ControlFlowGraph,
         max: usize,
-        endian: Endian
+        endian: Endian,
+        domain: Domain,
+        regions: &'v Regions<'v>
     ) -> ValueSetAnalysis<'v> {
+        let entry = control_flow_graph.entry()
+            .and_then(|entry| control_flow_graph.block(entry))
+            .map(|block| match block.instructions().first() {
+                Some(instruction) =>
+                    AnalysisLocation::instruction(block.index(), instruction.index()),
+                None => AnalysisLocation::empty_block(block.index())
+            });
+
         ValueSetAnalysis {
             control_flow_graph: control_flow_graph,
             max: max,
-            endian: endian
+            endian: endian,
+            domain: domain,
+            regions: regions,
+            entry: entry
         }
     }
 
     pub fn control_flow_graph(&self) -> &il::ControlFlowGraph {
         &self.control_flow_graph
     }
+
+    /// The state on entry to the function.
+    fn initial_state(&self) -> LatticeAssignments {
+        let mut state = LatticeAssignments::new_with_domain(self.max, self.domain);
+        if let Some(ref stack_pointer) = self.regions.stack_pointer {
+            state.set(
+                stack_pointer.clone(),
+                LatticeValue::Pointer(
+                    Region::Stack,
+                    StridedInterval::value(stack_pointer.bits(), 0)
+                )
+            );
+        }
+        state
+    }
 }
 
 
 pub fn compute(
     control_flow_graph: &il::ControlFlowGraph,
     max: usize,
-    endian: Endian
+    endian: Endian,
+    domain: Domain,
+    regions: &Regions
 ) -> Result<BTreeMap<AnalysisLocation, LatticeAssignments>> {
-    let value_set_analysis = ValueSetAnalysis::new(control_flow_graph, max, endian);
+    let value_set_analysis = ValueSetAnalysis::new(
+        control_flow_graph,
+        max,
+        endian,
+        domain,
+        regions
+    );
     fixed_point_forward(&value_set_analysis, value_set_analysis.control_flow_graph())
 }
 
 
+/// Compute one set of values which holds at every location of the function,
+/// ignoring the order in which instructions are executed.
+///
+/// Every instruction is applied to the same state, and the results joined
+/// with it, until it no longer changes. Stores never overwrite values held in
+/// memory, and branch conditions do not refine values.
+pub fn compute_flow_insensitive(
+    control_flow_graph: &il::ControlFlowGraph,
+    max: usize,
+    endian: Endian,
+    domain: Domain,
+    regions: &Regions
+) -> Result<LatticeAssignments> {
+    let value_set_analysis = ValueSetAnalysis::new(
+        control_flow_graph,
+        max,
+        endian,
+        domain,
+        regions
+    );
+
+    let mut locations = Vec::new();
+    for block in control_flow_graph.blocks() {
+        for instruction in block.instructions() {
+            locations.push(AnalysisLocation::instruction(block.index(), instruction.index()));
+        }
+    }
+
+    let mut state = value_set_analysis.initial_state();
+    loop {
+        let mut next = state.clone();
+        for location in &locations {
+            let state_out = value_set_analysis.trans(location, &Some(state.clone()))?;
+            next = next.join(&state_out);
+        }
+        let next = state.widen(next);
+        if next == state {
+            return Ok(state);
+        }
+        state = next;
+    }
+}
+
+
+/// Returns the value of the address accessed by every `Load` and `Store`,
+/// evaluated over the value sets before the access.
+///
+/// Accesses which are never reached are not included.
+pub fn addresses(
+    control_flow_graph: &il::ControlFlowGraph,
+    max: usize,
+    endian: Endian,
+    domain: Domain,
+    regions: &Regions
+) -> Result<BTreeMap<AnalysisLocation, LatticeValue>> {
+    let value_set_analysis = ValueSetAnalysis::new(
+        control_flow_graph,
+        max,
+        endian,
+        domain,
+        regions
+    );
+    let states = fixed_point_forward(&value_set_analysis, control_flow_graph)?;
+    let states_in = forward_states_in(&value_set_analysis, control_flow_graph, &states)?;
+
+    let mut addresses = BTreeMap::new();
+    for block in control_flow_graph.blocks() {
+        for instruction in block.instructions() {
+            let index = match *instruction.operation() {
+                il::Operation::Load { ref index, .. } |
+                il::Operation::Store { ref index, .. } => index,
+                _ => continue
+            };
+            let location = AnalysisLocation::instruction(block.index(), instruction.index());
+            let state = match states_in.get(&location) {
+                Some(state) if value_set_analysis.entry.as_ref() == Some(&location) =>
+                    value_set_analysis.initial_state().join(state),
+                Some(state) => state.clone(),
+                None if value_set_analysis.entry.as_ref() == Some(&location) =>
+                    value_set_analysis.initial_state(),
+                None => continue
+            };
+            addresses.insert(location, state.eval(index));
+        }
+    }
+
+    Ok(addresses)
+}
+
+
+/// Restricts the values of scalars in `state` to those for which `condition`
+/// evaluates to `taken`.
+///
+/// Returns false if `condition` can never evaluate to `taken`.
+fn refine(state: &mut LatticeAssignments, condition: &il::Expression, taken: bool)
+-> bool {
+    // Restrict the scalar to values between lo and hi
+    fn restrict(
+        state: &mut LatticeAssignments,
+        scalar: &il::Scalar,
+        lo: Option<u64>,
+        hi: Option<u64>
+    ) -> bool {
+        let interval = match state.eval(&scalar.clone().into()) {
+            // Comparisons say nothing about the offsets of a pointer
+            LatticeValue::Pointer(_, _) => return true,
+            lattice_value => match lattice_value.interval(scalar.bits()) {
+                Some(interval) => interval,
+                None => return true
+            }
+        };
+        let lo = match lo {
+            Some(lo) if lo > interval.lo() => lo,
+            _ => interval.lo()
+        };
+        let hi = match hi {
+            Some(hi) if hi < interval.hi() => hi,
+            _ => interval.hi()
+        };
+        // Move lo up until it falls on the stride of the interval
+        let lo = match interval.stride() {
+            0 => lo,
+            stride => lo + (stride - (lo - interval.lo()) % stride) % stride
+        };
+        if lo > hi || !interval.contains(lo) {
+            return false;
+        }
+        let interval = StridedInterval::new(
+            interval.bits(),
+            interval.stride(),
+            lo,
+            hi
+        );
+        let max = LatticeAssignments::max(state);
+        let domain = state.domain();
+        state.set(
+            scalar.clone(),
+            LatticeValue::from_interval(interval).limit(max, domain)
+        );
+        true
+    }
+
+    match *condition {
+        il::Expression::Cmpeq(ref lhs, ref rhs) => {
+            // (comparison) == 0 is the negation of the comparison
+            if let il::Expression::Constant(ref c) = **rhs {
+                if lhs.bits() == 1 {
+                    return refine(state, lhs, (c.value() == 1) == taken);
+                }
+            }
+            if !taken {
+                return true;
+            }
+            let rhs_interval = match state.eval(rhs).interval(rhs.bits()) {
+                Some(interval) => interval,
+                None => return true
+            };
+            match **lhs {
+                il::Expression::Scalar(ref scalar) => restrict(
+                    state,
+                    scalar,
+                    Some(rhs_interval.lo()),
+                    Some(rhs_interval.hi())
+                ),
+                _ => true
+            }
+        },
+        il::Expression::Cmpneq(ref lhs, ref rhs) => {
+            let condition = il::Expression::Cmpeq(lhs.clone(), rhs.clone());
+            refine(state, &condition, !taken)
+        },
+        il::Expression::Cmpltu(ref lhs, ref rhs) => {
+            let lhs_interval = state.eval(lhs).interval(lhs.bits());
+            let rhs_interval = state.eval(rhs).interval(rhs.bits());
+            let (lhs_interval, rhs_interval) = match (lhs_interval, rhs_interval) {
+                (Some(l), Some(r)) => (l, r),
+                _ => return true
+            };
+            let mut feasible = true;
+            if let il::Expression::Scalar(ref scalar) = **lhs {
+                feasible = if taken {
+                    // lhs < rhs
+                    rhs_interval.hi() > 0
+                    && restrict(state, scalar, None, Some(rhs_interval.hi() - 1))
+                }
+                else {
+                    // lhs >= rhs
+                    restrict(state, scalar, Some(rhs_interval.lo()), None)
+                };
+            }
+            if let il::Expression::Scalar(ref scalar) = **rhs {
+                feasible = feasible && if taken {
+                    // rhs > lhs
+                    lhs_interval.lo() < mask(lhs.bits())
+                    && restrict(state, scalar, Some(lhs_interval.lo() + 1), None)
+                }
+                else {
+                    // rhs <= lhs
+                    restrict(state, scalar, None, Some(lhs_interval.hi()))
+                };
+            }
+            feasible
+        },
+        _ => true
+    }
+}
+
+
 impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
     fn trans(
         &self,
@@ -88,10 +425,35 @@ impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
 
         let mut state_out = match *state_in {
             Some(ref state_in) => state_in.clone(),
-            None => LatticeAssignments::new(self.max)
+            None => LatticeAssignments::new_with_domain(self.max, self.domain)
         };
+
+        if self.entry.as_ref() == Some(analysis_location) {
+            state_out = self.initial_state().join(&state_out);
+        }
         
         Ok(match *analysis_location {
+            Edge(ref el) if self.domain == Domain::StridedIntervals => {
+                let edge = el.find(self.control_flow_graph)
+                             .ok_or("Could not find edge")?;
+                match *edge.condition() {
+                    Some(ref condition) => {
+                        let feasible = match state_out.eval(condition) {
+                            LatticeValue::Values(ref values) =>
+                                values.iter().any(|value| value.value() != 0),
+                            _ => true
+                        };
+                        if feasible && refine(&mut state_out, condition, true) {
+                            state_out
+                        }
+                        else {
+                            // This edge can not be taken
+                            LatticeAssignments::new_with_domain(self.max, self.domain)
+                        }
+                    },
+                    None => state_out
+                }
+            },
             Edge(_) => {
                 /*
                 let edge = el.find(self.control_flow_graph)?;
@@ -139,6 +501,30 @@ impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
                     }
                     il::Operation::Load { ref dst, ref index, .. } => {
                         let index = state_out.eval(index);
+                        let global = match index {
+                            LatticeValue::Values(ref addresses) => {
+                                // Every address must be given by the loader
+                                let mut global = LatticeValue::Meet;
+                                for address in addresses {
+                                    match self.regions.global_load(
+                                        address.value(),
+                                        dst.bits(),
+                                        &self.endian
+                                    ) {
+                                        Some(value) => global = global.join(&value),
+                                        None => {
+                                            global = LatticeValue::Meet;
+                                            break;
+                                        }
+                                    }
+                                }
+                                match global {
+                                    LatticeValue::Meet => None,
+                                    global => Some(global.limit(self.max, self.domain))
+                                }
+                            },
+                            _ => None
+                        };
                         match state_out.load(&index, dst.bits()) {
                             Some(value) => {
                                 if self.endian == Endian::Little {
@@ -154,10 +540,14 @@ impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
                                 }
 
                             }
-                            None => state_out.set(
-                                dst.clone(),
-                                LatticeValue::Meet
-                            )
+                            None => match global {
+                                // Global memory is already in our endianness
+                                Some(value) => state_out.set(dst.clone(), value),
+                                None => state_out.set(
+                                    dst.clone(),
+                                    LatticeValue::Meet
+                                )
+                            }
                         }
                         state_out
                     }
@@ -172,7 +562,7 @@ impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
                             }
                             else {
                                 let mut src_: Vec<il::Scalar> = Vec::new();
-                                for s in src {
+                                for s in src.values() {
                                     if let il::MultiVar::Scalar(ref s) = *s {
                                         src_.push(s.clone());
                                     }
@@ -202,8 +592,25 @@ impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
                             state_out
                         }
                     }
-                    il::Operation::Raise { .. } |
-                    il::Operation::Brc { .. } => {
+                    il::Operation::Brc { ref target, .. } => {
+                        let target = state_out.eval(target);
+                        if let LatticeValue::Values(ref targets) = target {
+                            if targets.len() == 1 {
+                                let target = targets.iter().next().unwrap().value();
+                                if let Some(result) = self.regions.allocators.get(&target) {
+                                    state_out.set(
+                                        result.clone(),
+                                        LatticeValue::Pointer(
+                                            Region::Heap(il.clone()),
+                                            StridedInterval::value(result.bits(), 0)
+                                        )
+                                    );
+                                }
+                            }
+                        }
+                        state_out
+                    }
+                    il::Operation::Raise { .. } => {
                         state_out
                     }
                 }
@@ -220,4 +627,22 @@ impl<'v> FixedPointAnalysis<LatticeAssignments> for ValueSetAnalysis<'v> {
     ) -> Result<LatticeAssignments> {
         Ok(state0.join(state1))
     }
+
+
+    fn widen(
+        &self,
+        previous: &LatticeAssignments,
+        next: LatticeAssignments
+    ) -> Result<LatticeAssignments> {
+        Ok(previous.widen(next))
+    }
+
+
+    fn narrow(
+        &self,
+        previous: &LatticeAssignments,
+        next: LatticeAssignments
+    ) -> Result<LatticeAssignments> {
+        Ok(previous.narrow(next))
+    }
 }
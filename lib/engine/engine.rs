@@ -6,10 +6,13 @@
 //! in return. Each `SymbolicSuccessor` has a type representing how control flow should
 //! behave.
 
+use analysis;
 use engine::memory::SymbolicMemory;
 use error::*;
 use executor;
+use executor::{ShadowTaint, TaintLabel};
 use il;
+use il::Variable;
 use regex;
 use std::collections::{BTreeMap, BTreeSet};
 use std::io::Read;
@@ -18,6 +21,10 @@ use std::process;
 use translator::TranslationMemory;
 
 
+/// The most iterations of a summarized loop `execute_loop` will perform.
+const MAX_LOOP_ITERATIONS: u64 = 0x100000;
+
+
 /// The type of successor from execution of an `Operation` over a `SymbolicEngine`.
 #[derive(Clone)]
 pub enum SuccessorType {
@@ -69,6 +76,13 @@ pub struct SymbolicEngine  {
     scalars: BTreeMap<String, il::Expression>,
     memory: SymbolicMemory,
     assertions: Vec<il::Expression>,
+    // The block control flow arrived from, which selects the operand of a
+    // `Phi`.
+    predecessor: Option<u64>,
+    // The values of scalars before the `Phi`s at the start of the current
+    // block overwrote them, as those `Phi`s read their operands in parallel.
+    phi_overwritten: BTreeMap<String, Option<il::Expression>>,
+    taint: Option<ShadowTaint>,
 }
 
 
@@ -79,6 +93,40 @@ impl SymbolicEngine {
             scalars: BTreeMap::new(),
             memory: memory,
             assertions: Vec::new(),
+            predecessor: None,
+            phi_overwritten: BTreeMap::new(),
+            taint: None,
+        }
+    }
+
+    /// Begin tracking taint over this engine.
+    ///
+    /// While taint is tracked, every scalar and memory cell carries the labels
+    /// of the input bytes which influence it.
+    pub fn enable_taint(&mut self) {
+        if self.taint.is_none() {
+            self.taint = Some(ShadowTaint::new());
+        }
+    }
+
+    /// Get the `ShadowTaint` of this engine, if taint is tracked.
+    pub fn taint(&self) -> Option<&ShadowTaint> {
+        self.taint.as_ref()
+    }
+
+    /// Get a mutable reference to the `ShadowTaint` of this engine, if taint
+    /// is tracked.
+    pub fn taint_mut(&mut self) -> Option<&mut ShadowTaint> {
+        self.taint.as_mut()
+    }
+
+    /// Get the labels of the input bytes which influence the value of an
+    /// expression, without querying the solver. If taint is not tracked, no
+    /// labels are returned.
+    pub fn taint_labels(&self, expression: &il::Expression) -> BTreeSet<TaintLabel> {
+        match self.taint {
+            Some(ref taint) => taint.expression(expression),
+            None => BTreeSet::new()
         }
     }
 
@@ -123,10 +171,15 @@ impl SymbolicEngine {
     }
 
 
-    /// Set a symbolic scalar
+    /// Set a symbolic scalar. If taint is tracked, the scalar is no longer
+    /// tainted.
     pub fn set_scalar<S>(&mut self, name: S, value: il::Expression)
         where S: Into<String> {
-        self.scalars.insert(name.into(), value);
+        let name = name.into();
+        if let Some(ref mut taint) = self.taint {
+            taint.set_scalar(name.clone(), BTreeSet::new());
+        }
+        self.scalars.insert(name, value);
     }
 
 
@@ -195,13 +248,32 @@ impl SymbolicEngine {
     }
 
 
+    /// Set the index of the block control flow arrived from, when entering a
+    /// new block.
+    ///
+    /// A `Phi` selects the operand for this predecessor. The driver of the
+    /// engine must set it before executing a block with `Phi`s.
+    pub fn set_predecessor(&mut self, predecessor: Option<u64>) {
+        self.predecessor = predecessor;
+        self.phi_overwritten.clear();
+    }
+
+    /// Get the index of the block control flow arrived from.
+    pub fn predecessor(&self) -> Option<u64> {
+        self.predecessor
+    }
+
+
     /// Forks the state of the symbolic engine. In future iterations, this will
     /// allow for Copy-On-Write optimizations.
     pub fn fork(&self) -> SymbolicEngine {
         SymbolicEngine {
             scalars: self.scalars.clone(),
             memory: self.memory.clone(),
-            assertions: self.assertions.clone()
+            assertions: self.assertions.clone(),
+            predecessor: self.predecessor,
+            phi_overwritten: self.phi_overwritten.clone(),
+            taint: self.taint.clone()
         }
     }
 
@@ -274,7 +346,34 @@ impl SymbolicEngine {
             il::Expression::Sext(bits, ref src) =>
                 il::Expression::sext(bits, self.symbolize_expression(src)?)?,
             il::Expression::Trun(bits, ref src) =>
-                il::Expression::trun(bits, self.symbolize_expression(src)?)?
+                il::Expression::trun(bits, self.symbolize_expression(src)?)?,
+            il::Expression::Fadd(ref lhs, ref rhs) =>
+                il::Expression::fadd(self.symbolize_expression(lhs)?,
+                                     self.symbolize_expression(rhs)?)?,
+            il::Expression::Fsub(ref lhs, ref rhs) =>
+                il::Expression::fsub(self.symbolize_expression(lhs)?,
+                                     self.symbolize_expression(rhs)?)?,
+            il::Expression::Fmul(ref lhs, ref rhs) =>
+                il::Expression::fmul(self.symbolize_expression(lhs)?,
+                                     self.symbolize_expression(rhs)?)?,
+            il::Expression::Fdiv(ref lhs, ref rhs) =>
+                il::Expression::fdiv(self.symbolize_expression(lhs)?,
+                                     self.symbolize_expression(rhs)?)?,
+            il::Expression::Fcmpeq(ref lhs, ref rhs) =>
+                il::Expression::fcmpeq(self.symbolize_expression(lhs)?,
+                                       self.symbolize_expression(rhs)?)?,
+            il::Expression::Fcmplt(ref lhs, ref rhs) =>
+                il::Expression::fcmplt(self.symbolize_expression(lhs)?,
+                                       self.symbolize_expression(rhs)?)?,
+            il::Expression::Fcmpuo(ref lhs, ref rhs) =>
+                il::Expression::fcmpuo(self.symbolize_expression(lhs)?,
+                                       self.symbolize_expression(rhs)?)?,
+            il::Expression::Fext(bits, ref src) =>
+                il::Expression::fext(bits, self.symbolize_expression(src)?)?,
+            il::Expression::Itof(bits, ref src) =>
+                il::Expression::itof(bits, self.symbolize_expression(src)?)?,
+            il::Expression::Ftoi(bits, ref src) =>
+                il::Expression::ftoi(bits, self.symbolize_expression(src)?)?
         })
     }
 
@@ -289,7 +388,7 @@ impl SymbolicEngine {
 
         let mut solver_lines : Vec<String> = Vec::new();
         solver_lines.push("(set-option :produce-models true)".to_string());
-        solver_lines.push("(set-logic QF_AUFBV)".to_string());
+        solver_lines.push("(set-logic QF_ABVFP)".to_string());
         solver_lines.push("(set-info :smt-lib-version 2.0)".to_string());
 
         let assertions = match assertions {
@@ -333,12 +432,12 @@ impl SymbolicEngine {
         // Assert our assertions
         for assertion in &assertions {
             solver_lines.push(format!("(assert (= #b1 {}))",
-                expr_to_smtlib2(&assertion)));
+                expr_to_smtlib2(&assertion)?));
         }
 
         // Assert this expression
         solver_lines.push(format!("(assert (= EVAL_RESULT {}))",
-            expr_to_smtlib2(expr)));
+            expr_to_smtlib2(expr)?));
         
         solver_lines.push("(check-sat)".to_string());
         solver_lines.push("(get-value (EVAL_RESULT))".to_string());
@@ -368,14 +467,14 @@ impl SymbolicEngine {
 
         let re = regex::Regex::new("EVAL_RESULT #x([0-9a-f]+)")?;
         if let Some(caps) = re.captures(&solver_output) {
-            let value = u64::from_str_radix(&caps[1], 16)?;
-            return Ok(Some(il::const_(value, expr.bits())));
+            let value = u128::from_str_radix(&caps[1], 16)?;
+            return Ok(Some(il::Constant::new_u128(value, expr.bits())));
         }
 
         let re = regex::Regex::new("EVAL_RESULT #b([0-1]+)")?;
         if let Some(caps) = re.captures(&solver_output) {
-            let value = u64::from_str_radix(&caps[1], 2)?;
-            return Ok(Some(il::const_(value, expr.bits())));
+            let value = u128::from_str_radix(&caps[1], 2)?;
+            return Ok(Some(il::Constant::new_u128(value, expr.bits())));
         }
 
         panic!("Couldn't parse EVAL_RESULT in {}", solver_output);
@@ -432,6 +531,18 @@ impl SymbolicEngine {
     }
 
 
+    /// Propagate taint through an operation, if taint is tracked. `address`
+    /// is the concrete address of a `Load` or `Store`.
+    fn propagate_taint(&mut self, operation: &il::Operation, address: Option<u64>)
+        -> Result<()> {
+
+        if let Some(ref mut taint) = self.taint {
+            taint.execute(operation, address)?;
+        }
+        Ok(())
+    }
+
+
     /// Execute an IL operation over the engine, updating state.
     pub fn execute(mut self, operation: &il::Operation)
         -> Result<Vec<SymbolicSuccessor>> {
@@ -439,7 +550,8 @@ impl SymbolicEngine {
         Ok(match *operation {
             il::Operation::Assign { ref dst, ref src } => {
                 let src = self.symbolize_and_eval(src)?;
-                self.set_scalar(dst.name(), src);
+                self.scalars.insert(dst.name().to_string(), src);
+                self.propagate_taint(operation, None)?;
                 vec![SymbolicSuccessor::new(self, SuccessorType::FallThrough)]
             },
             il::Operation::Store { ref index, ref src, .. } => {
@@ -447,6 +559,7 @@ impl SymbolicEngine {
                 let index = self.symbolize_and_concretize(index, None)?;
                 if let Some(index) = index {
                     self.memory.store(index.value(), src)?;
+                    self.propagate_taint(operation, Some(index.value()))?;
                     vec![SymbolicSuccessor::new(self, SuccessorType::FallThrough)]
                 }
                 else {
@@ -460,6 +573,7 @@ impl SymbolicEngine {
                     match value {
                         Some(v) => {
                             self.scalars.insert(dst.name().to_string(), v.clone());
+                            self.propagate_taint(operation, Some(index.value()))?;
                             vec![SymbolicSuccessor::new(self, SuccessorType::FallThrough)]
                         },
                         None => {
@@ -500,8 +614,30 @@ impl SymbolicEngine {
                 }
                 successors
             },
-            il::Operation::Phi { .. } => {
-                panic!("Phi unimplemented");
+            il::Operation::Phi { ref dst, ref src } => {
+                // Memory is not versioned, so array phis have nothing to do.
+                if let il::MultiVar::Scalar(ref dst) = *dst {
+                    let operand = match self.predecessor.and_then(|p| src.get(&p)) {
+                        Some(operand) => operand,
+                        None => bail!("No operand of Phi to {} for predecessor {:?}",
+                                      dst, self.predecessor)
+                    };
+                    let value = match self.phi_overwritten.get(operand.name()) {
+                        Some(value) => value.clone(),
+                        None => self.scalars.get(operand.name()).cloned()
+                    };
+                    let value = match value {
+                        Some(value) => value,
+                        None => bail!("Operand {} of Phi to {} was never written", operand, dst)
+                    };
+                    if !self.phi_overwritten.contains_key(dst.name()) {
+                        let overwritten = self.scalars.get(dst.name()).cloned();
+                        self.phi_overwritten.insert(dst.name().to_string(), overwritten);
+                    }
+                    self.scalars.insert(dst.name().to_string(), value);
+                }
+                self.propagate_taint(operation, None)?;
+                vec![SymbolicSuccessor::new(self, SuccessorType::FallThrough)]
             },
             il::Operation::Raise { ref expr } => {
                 vec![SymbolicSuccessor::new(
@@ -511,6 +647,156 @@ impl SymbolicEngine {
             }
         })
     }
+
+
+    /// Get the concrete value of a pointer which is a basic induction variable
+    /// of `loop_`, and its step.
+    fn loop_pointer(&self, loop_: &analysis::Loop, pointer: &il::Scalar)
+        -> Result<Option<(u64, i64)>> {
+
+        let step = match loop_.induction_variable(pointer) {
+            Some(&analysis::InductionVariable::Basic { step }) => step,
+            _ => bail!("{} is not a basic induction variable", pointer)
+        };
+        match self.symbolize_and_eval(&il::Expression::scalar(pointer.clone()))? {
+            il::Expression::Constant(address) => Ok(Some((address.value(), step))),
+            _ => Ok(None)
+        }
+    }
+
+
+    /// Execute every iteration of a summarized loop at once, instead of one
+    /// iteration at a time.
+    ///
+    /// This engine must be at the header of `loop_`. The engine returned holds
+    /// memory, and the basic induction variables of the loop, as they are when
+    /// the loop exits, and continues at the tail of the loop's exit edge.
+    ///
+    /// Returns `None` if the loop has no summary, or the number of iterations,
+    /// or an address accessed, is not concrete.
+    pub fn execute_loop(&self, loop_: &analysis::Loop) -> Result<Option<SymbolicEngine>> {
+        let summary = match loop_.summary() {
+            Some(summary) => summary,
+            None => return Ok(None)
+        };
+
+        let mut engine = self.fork();
+
+        let iterations = match *summary {
+            analysis::LoopSummary::Scan { ref pointer, bits, terminator } => {
+                let (address, step) = match engine.loop_pointer(loop_, pointer)? {
+                    Some(pointer) => pointer,
+                    None => return Ok(None)
+                };
+                let mut iterations = 0;
+                loop {
+                    if iterations >= MAX_LOOP_ITERATIONS {
+                        return Ok(None);
+                    }
+                    let address = mask(
+                        address.wrapping_add((step as u64).wrapping_mul(iterations)),
+                        pointer.bits()
+                    );
+                    let value = match engine.memory.load(address, bits)? {
+                        Some(value) => value,
+                        None => return Ok(None)
+                    };
+                    if !all_constants(&value) {
+                        return Ok(None);
+                    }
+                    if executor::constants_expression(&value)?.value() == terminator {
+                        break;
+                    }
+                    iterations += 1;
+                }
+                iterations
+            },
+            analysis::LoopSummary::Copy { .. } |
+            analysis::LoopSummary::Fill { .. } => {
+                let count = match *loop_.trip_count() {
+                    analysis::TripCount::Exact(ref count) => engine.symbolize_and_eval(count)?,
+                    _ => return Ok(None)
+                };
+                match count {
+                    il::Expression::Constant(ref count) if count.value() <= MAX_LOOP_ITERATIONS =>
+                        count.value(),
+                    _ => return Ok(None)
+                }
+            }
+        };
+
+        match *summary {
+            analysis::LoopSummary::Copy { ref destination, ref source, bits } => {
+                let (dst, step) = match engine.loop_pointer(loop_, destination)? {
+                    Some(pointer) => pointer,
+                    None => return Ok(None)
+                };
+                let src = match engine.loop_pointer(loop_, source)? {
+                    Some((src, _)) => src,
+                    None => return Ok(None)
+                };
+                for i in 0..iterations {
+                    let offset = (step as u64).wrapping_mul(i);
+                    let src = mask(src.wrapping_add(offset), source.bits());
+                    let dst = mask(dst.wrapping_add(offset), destination.bits());
+                    let value = match engine.memory.load(src, bits)? {
+                        Some(value) => value,
+                        None => return Ok(None)
+                    };
+                    engine.memory.store(dst, value)?;
+                    if let Some(ref mut taint) = engine.taint {
+                        let labels = taint.load(src, bits);
+                        taint.store(dst, bits, &labels);
+                    }
+                }
+            },
+            analysis::LoopSummary::Fill { ref destination, ref value, bits } => {
+                let (dst, step) = match engine.loop_pointer(loop_, destination)? {
+                    Some(pointer) => pointer,
+                    None => return Ok(None)
+                };
+                let labels = engine.taint_labels(value);
+                let value = engine.symbolize_and_eval(value)?;
+                for i in 0..iterations {
+                    let dst = mask(
+                        dst.wrapping_add((step as u64).wrapping_mul(i)),
+                        destination.bits()
+                    );
+                    engine.memory.store(dst, value.clone())?;
+                    if let Some(ref mut taint) = engine.taint {
+                        taint.store(dst, bits, &labels);
+                    }
+                }
+            },
+            analysis::LoopSummary::Scan { .. } => {}
+        }
+
+        // advance the induction variables by every iteration
+        for (scalar, induction_variable) in loop_.induction_variables() {
+            let step = match *induction_variable {
+                analysis::InductionVariable::Basic { step } => step,
+                analysis::InductionVariable::Derived { .. } => continue
+            };
+            let operation = il::Operation::assign(
+                scalar.clone(),
+                il::Expression::add(
+                    il::Expression::scalar(scalar.clone()),
+                    il::expr_const((step as u64).wrapping_mul(iterations), scalar.bits())
+                )?
+            );
+            if let il::Operation::Assign { ref dst, ref src } = operation {
+                let src = match engine.symbolize_and_eval(src)? {
+                    il::Expression::Constant(value) =>
+                        il::Expression::constant(value),
+                    src => src
+                };
+                engine.scalars.insert(dst.name().to_string(), src);
+            }
+            engine.propagate_taint(&operation, None)?;
+        }
+
+        Ok(Some(engine))
+    }
 }
 
 
@@ -522,6 +808,17 @@ impl TranslationMemory for SymbolicEngine {
 
 
 
+/// Truncate an address to `bits`.
+fn mask(address: u64, bits: usize) -> u64 {
+    if bits >= 64 {
+        address
+    }
+    else {
+        address & ((1 << bits) - 1)
+    }
+}
+
+
 /// Return true if an expression is all constants.
 ///
 /// If an expression is all constants, we can evaluate the expression
@@ -545,79 +842,150 @@ pub fn all_constants(expr: &il::Expression) -> bool {
         il::Expression::Cmpeq(ref lhs, ref rhs) |
         il::Expression::Cmpneq(ref lhs, ref rhs) |
         il::Expression::Cmplts(ref lhs, ref rhs) |
-        il::Expression::Cmpltu(ref lhs, ref rhs) =>
+        il::Expression::Cmpltu(ref lhs, ref rhs) |
+        il::Expression::Fadd(ref lhs, ref rhs) |
+        il::Expression::Fsub(ref lhs, ref rhs) |
+        il::Expression::Fmul(ref lhs, ref rhs) |
+        il::Expression::Fdiv(ref lhs, ref rhs) |
+        il::Expression::Fcmpeq(ref lhs, ref rhs) |
+        il::Expression::Fcmplt(ref lhs, ref rhs) |
+        il::Expression::Fcmpuo(ref lhs, ref rhs) =>
             all_constants(lhs) && all_constants(rhs),
         il::Expression::Zext(_, ref src) |
         il::Expression::Sext(_, ref src) |
-        il::Expression::Trun(_, ref src) =>
+        il::Expression::Trun(_, ref src) |
+        il::Expression::Fext(_, ref src) |
+        il::Expression::Itof(_, ref src) |
+        il::Expression::Ftoi(_, ref src) =>
             all_constants(src)
     }
 }
 
 
+/// The `smtlib2` conversion to the FloatingPoint sort with the given bits.
+///
+/// Only single and double precision values have a sort here, other widths are
+/// an error.
+fn to_fp(bits: usize) -> Result<&'static str> {
+    match bits {
+        32 => Ok("(_ to_fp 8 24)"),
+        64 => Ok("(_ to_fp 11 53)"),
+        _ => bail!("no FloatingPoint sort for {}-bit values", bits)
+    }
+}
+
+
+/// Convert a falcon expression, holding the bits of an IEEE 754 value, to an
+/// `smtlib2` FloatingPoint term.
+fn float_to_smtlib2(expr: &il::Expression) -> Result<String> {
+    Ok(format!("({} {})", to_fp(expr.bits())?, expr_to_smtlib2(expr)?))
+}
+
+
 /// Convert a falcon expression to its `smtlib2` equivalent.
-pub fn expr_to_smtlib2(expr: &il::Expression) -> String {
-    match *expr {
+///
+/// Floating point expressions are converted to the FloatingPoint theory, and
+/// their results back to bitvectors with `fp.to_ieee_bv`.
+pub fn expr_to_smtlib2(expr: &il::Expression) -> Result<String> {
+    Ok(match *expr {
         il::Expression::Constant(ref c) => {
             if c.bits() == 1 {
                 format!("#b{}", c.value())
             }
             else {
-                format!("#x{:01$x}", c.value(), c.bits() / 4)
+                format!("#x{:01$x}", c.value_u128(), c.bits() / 4)
             }
         },
         il::Expression::Scalar(ref s) => {
             s.name().to_string()
         }
         il::Expression::Add ( ref lhs, ref rhs ) =>
-            format!("(bvadd {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvadd {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Sub ( ref lhs, ref rhs ) =>
-            format!("(bvsub {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvsub {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Mul ( ref lhs, ref rhs ) =>
-            format!("(bvmul {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvmul {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Divu ( ref lhs, ref rhs ) =>
-            format!("(bvudiv {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvudiv {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Modu ( ref lhs, ref rhs ) =>
-            format!("(bvumod {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvumod {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Divs ( ref lhs, ref rhs ) =>
-            format!("(bvsdiv {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvsdiv {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Mods ( ref lhs, ref rhs ) =>
-            format!("(bvsmod {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvsmod {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::And ( ref lhs, ref rhs ) =>
-            format!("(bvand {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvand {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Or ( ref lhs, ref rhs ) =>
-            format!("(bvor {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvor {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Xor ( ref lhs, ref rhs ) =>
-            format!("(bvxor {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvxor {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Shl ( ref lhs, ref rhs ) =>
-            format!("(bvshl {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvshl {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Shr ( ref lhs, ref rhs ) =>
-            format!("(bvlshr {} {})", expr_to_smtlib2(lhs), expr_to_smtlib2(rhs)),
+            format!("(bvlshr {} {})", expr_to_smtlib2(lhs)?, expr_to_smtlib2(rhs)?),
         il::Expression::Cmpeq ( ref lhs, ref rhs ) =>
             format!("(ite (= {} {}) #b1 #b0)",
-                    expr_to_smtlib2(lhs),
-                    expr_to_smtlib2(rhs)),
+                    expr_to_smtlib2(lhs)?,
+                    expr_to_smtlib2(rhs)?),
         il::Expression::Cmpneq ( ref lhs, ref rhs ) =>
             format!("(ite (!= {} {}) #b1 #b0)",
-                    expr_to_smtlib2(lhs),
-                    expr_to_smtlib2(rhs)),
+                    expr_to_smtlib2(lhs)?,
+                    expr_to_smtlib2(rhs)?),
         il::Expression::Cmplts ( ref lhs, ref rhs ) =>
             format!("(ite (bvslt {} {}) #b1 #b0)",
-                    expr_to_smtlib2(lhs),
-                    expr_to_smtlib2(rhs)),
+                    expr_to_smtlib2(lhs)?,
+                    expr_to_smtlib2(rhs)?),
         il::Expression::Cmpltu ( ref lhs, ref rhs ) =>
             format!("(ite (bvult {} {}) #b1 #b0)",
-                    expr_to_smtlib2(lhs),
-                    expr_to_smtlib2(rhs)),
+                    expr_to_smtlib2(lhs)?,
+                    expr_to_smtlib2(rhs)?),
         il::Expression::Zext ( bits, ref rhs ) =>
             format!("(concat (_ bv0 {}) {})",
                     bits - rhs.bits(),
-                    expr_to_smtlib2(rhs)),
+                    expr_to_smtlib2(rhs)?),
         il::Expression::Sext ( bits, ref rhs ) =>
             format!("((_ sign_extend {}) {})",
                     bits - rhs.bits(),
-                    expr_to_smtlib2(rhs)),
+                    expr_to_smtlib2(rhs)?),
         il::Expression::Trun ( bits, ref rhs ) =>
-            format!("((_ extract {} 0) {})", bits - 1, expr_to_smtlib2(rhs))
-    }
+            format!("((_ extract {} 0) {})", bits - 1, expr_to_smtlib2(rhs)?),
+        il::Expression::Fadd ( ref lhs, ref rhs ) =>
+            format!("(fp.to_ieee_bv (fp.add RNE {} {}))",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fsub ( ref lhs, ref rhs ) =>
+            format!("(fp.to_ieee_bv (fp.sub RNE {} {}))",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fmul ( ref lhs, ref rhs ) =>
+            format!("(fp.to_ieee_bv (fp.mul RNE {} {}))",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fdiv ( ref lhs, ref rhs ) =>
+            format!("(fp.to_ieee_bv (fp.div RNE {} {}))",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fcmpeq ( ref lhs, ref rhs ) =>
+            format!("(ite (fp.eq {} {}) #b1 #b0)",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fcmplt ( ref lhs, ref rhs ) =>
+            format!("(ite (fp.lt {} {}) #b1 #b0)",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fcmpuo ( ref lhs, ref rhs ) =>
+            format!("(ite (or (fp.isNaN {}) (fp.isNaN {})) #b1 #b0)",
+                    float_to_smtlib2(lhs)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Fext ( bits, ref rhs ) =>
+            format!("(fp.to_ieee_bv ({} RNE {}))",
+                    to_fp(bits)?,
+                    float_to_smtlib2(rhs)?),
+        il::Expression::Itof ( bits, ref rhs ) =>
+            format!("(fp.to_ieee_bv ({} RNE {}))",
+                    to_fp(bits)?,
+                    expr_to_smtlib2(rhs)?),
+        il::Expression::Ftoi ( bits, ref rhs ) =>
+            format!("((_ fp.to_sbv {}) RTZ {})", bits, float_to_smtlib2(rhs)?)
+    })
 }
\ No newline at end of file
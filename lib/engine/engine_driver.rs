@@ -13,10 +13,11 @@
 
 use error::*;
 use engine::*;
+use executor::TaintLabel;
 use il;
 use platform::Platform;
 use translator;
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, VecDeque};
 use std::rc::Rc;
 
 /// Takes a program and an address, and returns function, block, and instruction
@@ -131,6 +132,21 @@ impl ProgramLocation {
     /// empty block, leading to another unconditional edge. In this case, we
     /// want to advance past all of these to the next valid `ProgramLocation`.
     pub fn advance(&self, program: &il::Program) -> Vec<ProgramLocation> {
+        self.advance_edges(program)
+            .into_iter()
+            .map(|(location, _)| location)
+            .collect()
+    }
+
+
+    /// Advances the `DriverLocation` as `advance` does, also returning the
+    /// block each location was entered from when advancing crossed an edge
+    /// into a new block.
+    ///
+    /// The predecessor selects the operand of an `il::Operation::Phi` in the
+    /// new block.
+    pub fn advance_edges(&self, program: &il::Program)
+    -> Vec<(ProgramLocation, Option<u64>)> {
         // This is the list of locations which no longer need to be advanced
         let mut final_locations = Vec::new();
 
@@ -152,13 +168,13 @@ impl ProgramLocation {
                             // If there is a successor in the block, advance to the
                             // successor
                             if i + 1 < instructions.len() {
-                                final_locations.push(ProgramLocation::new(
+                                final_locations.push((ProgramLocation::new(
                                     location.function_index,
                                     FunctionLocation::Instruction {
                                         block_index: block_index,
                                         instruction_index: instructions[i + 1].index()
                                     }
-                                ));
+                                ), None));
                                 break;
                             }
                             // There is no successor, let's take a look at outgoing
@@ -172,13 +188,13 @@ impl ProgramLocation {
                                 // If this is a conditional edge, advance to the
                                 // edge
                                 if edge.condition().is_some() {
-                                    final_locations.push(ProgramLocation::new(
+                                    final_locations.push((ProgramLocation::new(
                                         location.function_index,
                                         FunctionLocation::Edge {
                                             head: edge.head(),
                                             tail: edge.tail()
                                         }
-                                    ));
+                                    ), None));
                                 }
                                 // If this is an unconditional edge, push the
                                 // unconditional edge onto the queue
@@ -195,7 +211,7 @@ impl ProgramLocation {
                         } // if instructions[i].index()
                     } // for i in 0..instructions.len()
                 },
-                FunctionLocation::Edge { head: _, tail } => {
+                FunctionLocation::Edge { head, tail } => {
                     // Get the successor block
                     let block = location.function(program).unwrap()
                                         .block(tail).unwrap();
@@ -214,13 +230,13 @@ impl ProgramLocation {
                             // will cause us to hang here. We would prefer to hang somewhere else
                             // so we can eventually stop.
                             if edge.condition().is_some() {
-                                final_locations.push(ProgramLocation::new(
+                                final_locations.push((ProgramLocation::new(
                                     location.function_index,
                                     FunctionLocation::Edge {
                                         head: edge.head(),
                                         tail: edge.tail()
                                     }
-                                ));
+                                ), None));
                             }
                             else {
                                 queue.push_back(ProgramLocation::new(
@@ -235,13 +251,13 @@ impl ProgramLocation {
                     } // if block.instructions().is_empty()
                     // If this block isn't empty, we advance to the first instruction
                     else {
-                        final_locations.push(ProgramLocation::new(
+                        final_locations.push((ProgramLocation::new(
                             location.function_index,
                             FunctionLocation::Instruction {
                                 block_index: block.index(),
                                 instruction_index: block.instructions()[0].index()
                             }
-                        ));
+                        ), Some(head)));
                     }
                 }
             } // match location
@@ -320,27 +336,19 @@ impl<'e, P> EngineDriver<'e, P> {
                             // Get the possible successor locations for the current
                             // location
                             let engine = successor.into_engine();
-                            let locations = self.location.advance(&self.program);
-                            if locations.len() == 1 {
+                            for (location, predecessor) in self.location.advance_edges(&self.program) {
+                                let mut engine = engine.clone();
+                                if predecessor.is_some() {
+                                    engine.set_predecessor(predecessor);
+                                }
                                 new_engine_drivers.push(EngineDriver::new(
                                     self.program.clone(),
-                                    locations[0].clone(),
+                                    location,
                                     engine,
                                     self.arch,
-                                    self.platform.clone(),
+                                    self.platform.clone()
                                 ));
                             }
-                            else {
-                                for location in self.location.advance(&self.program) {
-                                    new_engine_drivers.push(EngineDriver::new(
-                                        self.program.clone(),
-                                        location,
-                                        engine.clone(),
-                                        self.arch,
-                                        self.platform.clone()
-                                    ));
-                                }
-                            }
                         },
                         SuccessorType::Branch(address) => {
                             println!("Branching to 0x{:x}", address);
@@ -382,7 +390,7 @@ impl<'e, P> EngineDriver<'e, P> {
                         },
                         SuccessorType::Raise(expression) => {
                             let platform = Rc::make_mut(&mut self.platform).to_owned();
-                            let locations = self.location.advance(&self.program);
+                            let locations = self.location.advance_edges(&self.program);
                             let engine = successor.clone().into_engine();
                             let results = match platform.raise(&expression, engine) {
                                 Ok(results) => results,
@@ -391,12 +399,16 @@ impl<'e, P> EngineDriver<'e, P> {
                                     continue;
                                 }
                             };
-                            for location in locations {
+                            for (location, predecessor) in locations {
                                 for result in &results {
+                                    let mut engine = result.1.clone();
+                                    if predecessor.is_some() {
+                                        engine.set_predecessor(predecessor);
+                                    }
                                     new_engine_drivers.push(EngineDriver::new(
                                         self.program.clone(),
                                         location.clone(),
-                                        result.1.clone(),
+                                        engine,
                                         self.arch,
                                         Rc::new(result.0.clone())
                                     ));
@@ -415,11 +427,15 @@ impl<'e, P> EngineDriver<'e, P> {
                 match *edge.condition() {
                     None => {
                         if edge.condition().is_none() {
-                            for location in self.location.advance(&self.program) {
+                            for (location, predecessor) in self.location.advance_edges(&self.program) {
+                                let mut engine = self.engine.clone();
+                                if predecessor.is_some() {
+                                    engine.set_predecessor(predecessor);
+                                }
                                 new_engine_drivers.push(EngineDriver::new(
                                     self.program.clone(),
                                     location,
-                                    self.engine.clone(),
+                                    engine,
                                     self.arch,
                                     self.platform.clone()
                                 ));
@@ -432,27 +448,19 @@ impl<'e, P> EngineDriver<'e, P> {
                             println!("Expression sat");
                             let mut engine = self.engine.clone();
                             engine.add_assertion(condition.clone())?;
-                            let locations = self.location.advance(&self.program);
-                            if locations.len() == 1 {
+                            for (location, predecessor) in self.location.advance_edges(&self.program) {
+                                let mut engine = engine.clone();
+                                if predecessor.is_some() {
+                                    engine.set_predecessor(predecessor);
+                                }
                                 new_engine_drivers.push(EngineDriver::new(
                                     self.program.clone(),
-                                    locations[0].clone(),
+                                    location,
                                     engine,
                                     self.arch,
                                     self.platform.clone()
                                 ));
                             }
-                            else {
-                                for location in self.location.advance(&self.program) {
-                                    new_engine_drivers.push(EngineDriver::new(
-                                        self.program.clone(),
-                                        location,
-                                        engine.clone(),
-                                        self.arch,
-                                        self.platform.clone()
-                                    ));
-                                }
-                            }
                         }
                     }
                 }
@@ -485,4 +493,43 @@ impl<'e, P> EngineDriver<'e, P> {
     pub fn engine(&self) -> &SymbolicEngine {
         &self.engine
     }
+
+    /// Return the labels of the input bytes which influence control flow, or
+    /// the address of memory accessed, at the location of this
+    /// `EngineDriver`.
+    ///
+    /// These are the target and condition of a `Brc`, the index of a `Load`
+    /// or `Store`, and the condition of an edge. Taint must be enabled in the
+    /// engine, or no labels are returned.
+    pub fn taint_labels(&self) -> Result<BTreeSet<TaintLabel>> {
+        let function = self.location
+                           .function(&self.program)
+                           .ok_or("Could not find function")?;
+        let mut labels = BTreeSet::new();
+        match self.location.function_location {
+            FunctionLocation::Instruction { block_index, instruction_index } => {
+                let instruction = function.block(block_index)
+                                          .and_then(|block| block.instruction(instruction_index))
+                                          .ok_or("Could not find instruction")?;
+                match *instruction.operation() {
+                    il::Operation::Brc { ref target, ref condition } => {
+                        labels.extend(self.engine.taint_labels(target));
+                        labels.extend(self.engine.taint_labels(condition));
+                    },
+                    il::Operation::Load { ref index, .. } |
+                    il::Operation::Store { ref index, .. } => {
+                        labels.extend(self.engine.taint_labels(index));
+                    },
+                    _ => {}
+                }
+            },
+            FunctionLocation::Edge { head, tail } => {
+                let edge = function.edge(head, tail).ok_or("Could not find edge")?;
+                if let Some(ref condition) = *edge.condition() {
+                    labels.extend(self.engine.taint_labels(condition));
+                }
+            }
+        }
+        Ok(labels)
+    }
 }
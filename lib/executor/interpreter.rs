@@ -0,0 +1,278 @@
+//! A concrete interpreter for Falcon IL.
+//!
+//! `Interpreter` holds a concrete value for every scalar and byte of memory,
+//! and executes operations without a solver. It may optionally carry
+//! `ShadowTaint`, to track which bytes of input influence each value.
+
+use engine::Endian;
+use error::*;
+use executor::{constants_expression, ShadowTaint, TaintLabel};
+use il;
+use il::Variable;
+use std::collections::{BTreeMap, BTreeSet};
+
+
+/// The result of executing an `Operation` over an `Interpreter`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Successor {
+    /// Control flow should continue normally.
+    FallThrough,
+    /// Control flow should branch to the given address.
+    Branch(u64),
+    /// A `Platform` must handle a `Raise` instruction, and then control flow
+    /// should continue normally.
+    Raise(il::Expression)
+}
+
+
+/// A concrete state, over which operations are executed.
+#[derive(Clone, Debug)]
+pub struct Interpreter {
+    scalars: BTreeMap<String, il::Constant>,
+    memory: BTreeMap<u64, u8>,
+    endian: Endian,
+    taint: Option<ShadowTaint>,
+    // The block control flow arrived from, which selects the operand of a
+    // `Phi`.
+    predecessor: Option<u64>,
+    // The values of scalars before the `Phi`s at the start of the current
+    // block overwrote them, as those `Phi`s read their operands in parallel.
+    phi_overwritten: BTreeMap<String, Option<il::Constant>>
+}
+
+
+impl Interpreter {
+    /// Create a new `Interpreter`, with memory of the given endianness.
+    pub fn new(endian: Endian) -> Interpreter {
+        Interpreter {
+            scalars: BTreeMap::new(),
+            memory: BTreeMap::new(),
+            endian: endian,
+            taint: None,
+            predecessor: None,
+            phi_overwritten: BTreeMap::new()
+        }
+    }
+
+    /// Begin tracking taint over this `Interpreter`.
+    pub fn enable_taint(&mut self) {
+        if self.taint.is_none() {
+            self.taint = Some(ShadowTaint::new());
+        }
+    }
+
+    /// Get the `ShadowTaint` of this `Interpreter`, if taint is tracked.
+    pub fn taint(&self) -> Option<&ShadowTaint> {
+        self.taint.as_ref()
+    }
+
+    /// Get a mutable reference to the `ShadowTaint` of this `Interpreter`, if
+    /// taint is tracked.
+    pub fn taint_mut(&mut self) -> Option<&mut ShadowTaint> {
+        self.taint.as_mut()
+    }
+
+    /// Get the labels of the input bytes which influence the value of an
+    /// expression. If taint is not tracked, no labels are returned.
+    pub fn taint_labels(&self, expression: &il::Expression) -> BTreeSet<TaintLabel> {
+        match self.taint {
+            Some(ref taint) => taint.expression(expression),
+            None => BTreeSet::new()
+        }
+    }
+
+    /// Set the index of the block control flow arrived from, when entering a
+    /// new block. A `Phi` selects the operand for this predecessor.
+    pub fn set_predecessor(&mut self, predecessor: Option<u64>) {
+        self.predecessor = predecessor;
+        self.phi_overwritten.clear();
+    }
+
+    /// Get the index of the block control flow arrived from.
+    pub fn predecessor(&self) -> Option<u64> {
+        self.predecessor
+    }
+
+    /// Get the value of a scalar.
+    pub fn scalar(&self, name: &str) -> Option<&il::Constant> {
+        self.scalars.get(name)
+    }
+
+    /// Set the value of a scalar. The scalar is no longer tainted.
+    pub fn set_scalar<S>(&mut self, name: S, value: il::Constant)
+        where S: Into<String> {
+        let name = name.into();
+        if let Some(ref mut taint) = self.taint {
+            taint.set_scalar(name.clone(), BTreeSet::new());
+        }
+        self.scalars.insert(name, value);
+    }
+
+    /// Store a value in memory.
+    ///
+    /// The bit-width of the value must be evenly divisible by 8.
+    pub fn store(&mut self, address: u64, value: &il::Constant) -> Result<()> {
+        if value.bits() % 8 != 0 {
+            bail!("Storing value with bit width not divisible by 8 {}", value.bits());
+        }
+        let bytes = (value.bits() / 8) as u64;
+        for offset in 0..bytes {
+            let shift = match self.endian {
+                Endian::Big => (bytes - offset - 1) * 8,
+                Endian::Little => offset * 8
+            };
+            let byte = if shift < 128 { (value.value_u128() >> shift) as u8 } else { 0 };
+            self.memory.insert(address.wrapping_add(offset), byte);
+        }
+        Ok(())
+    }
+
+    /// Load a value of `bits` from memory.
+    ///
+    /// Returns `None` if any byte of the value was never stored.
+    pub fn load(&self, address: u64, bits: usize) -> Result<Option<il::Constant>> {
+        if bits % 8 != 0 || bits == 0 {
+            bail!("Loading memory with invalid bit width {}", bits);
+        }
+        let bytes = (bits / 8) as u64;
+        let mut value: u128 = 0;
+        for offset in 0..bytes {
+            let byte = match self.memory.get(&address.wrapping_add(offset)) {
+                Some(byte) => *byte as u128,
+                None => return Ok(None)
+            };
+            let shift = match self.endian {
+                Endian::Big => (bytes - offset - 1) * 8,
+                Endian::Little => offset * 8
+            };
+            if shift < 128 {
+                value |= byte << shift;
+            }
+        }
+        Ok(Some(il::Constant::new_u128(value, bits)))
+    }
+
+    fn substitute(&self, expression: &mut il::Expression) -> Result<()> {
+        let value = match *expression {
+            il::Expression::Scalar(ref scalar) => Some(
+                self.scalars
+                    .get(scalar.name())
+                    .cloned()
+                    .ok_or(format!("No value for scalar {}", scalar))?
+            ),
+            il::Expression::Constant(_) => None,
+            il::Expression::Add(ref mut lhs, ref mut rhs) |
+            il::Expression::Sub(ref mut lhs, ref mut rhs) |
+            il::Expression::Mul(ref mut lhs, ref mut rhs) |
+            il::Expression::Divu(ref mut lhs, ref mut rhs) |
+            il::Expression::Modu(ref mut lhs, ref mut rhs) |
+            il::Expression::Divs(ref mut lhs, ref mut rhs) |
+            il::Expression::Mods(ref mut lhs, ref mut rhs) |
+            il::Expression::And(ref mut lhs, ref mut rhs) |
+            il::Expression::Or(ref mut lhs, ref mut rhs) |
+            il::Expression::Xor(ref mut lhs, ref mut rhs) |
+            il::Expression::Shl(ref mut lhs, ref mut rhs) |
+            il::Expression::Shr(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmpeq(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmpneq(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmplts(ref mut lhs, ref mut rhs) |
+            il::Expression::Cmpltu(ref mut lhs, ref mut rhs) |
+            il::Expression::Fadd(ref mut lhs, ref mut rhs) |
+            il::Expression::Fsub(ref mut lhs, ref mut rhs) |
+            il::Expression::Fmul(ref mut lhs, ref mut rhs) |
+            il::Expression::Fdiv(ref mut lhs, ref mut rhs) |
+            il::Expression::Fcmpeq(ref mut lhs, ref mut rhs) |
+            il::Expression::Fcmplt(ref mut lhs, ref mut rhs) |
+            il::Expression::Fcmpuo(ref mut lhs, ref mut rhs) => {
+                self.substitute(lhs)?;
+                self.substitute(rhs)?;
+                None
+            },
+            il::Expression::Zext(_, ref mut src) |
+            il::Expression::Sext(_, ref mut src) |
+            il::Expression::Trun(_, ref mut src) |
+            il::Expression::Fext(_, ref mut src) |
+            il::Expression::Itof(_, ref mut src) |
+            il::Expression::Ftoi(_, ref mut src) => {
+                self.substitute(src)?;
+                None
+            }
+        };
+        if let Some(value) = value {
+            *expression = il::Expression::Constant(value);
+        }
+        Ok(())
+    }
+
+    /// Evaluate an expression over the values of this `Interpreter`.
+    pub fn eval(&self, expression: &il::Expression) -> Result<il::Constant> {
+        let mut expression = expression.clone();
+        self.substitute(&mut expression)?;
+        constants_expression(&expression)
+    }
+
+    /// Execute an IL operation over the interpreter, updating state.
+    pub fn execute(&mut self, operation: &il::Operation) -> Result<Successor> {
+        let mut address = None;
+        let successor = match *operation {
+            il::Operation::Assign { ref dst, ref src } => {
+                let value = self.eval(src)?;
+                self.scalars.insert(dst.name().to_string(), value);
+                Successor::FallThrough
+            },
+            il::Operation::Store { ref index, ref src, .. } => {
+                let index = self.eval(index)?.value();
+                let value = self.eval(src)?;
+                self.store(index, &value)?;
+                address = Some(index);
+                Successor::FallThrough
+            },
+            il::Operation::Load { ref dst, ref index, .. } => {
+                let index = self.eval(index)?.value();
+                let value = self.load(index, dst.bits())?
+                                .ok_or(format!("Load from uninitialized memory 0x{:x}", index))?;
+                self.scalars.insert(dst.name().to_string(), value);
+                address = Some(index);
+                Successor::FallThrough
+            },
+            il::Operation::Brc { ref target, ref condition } => {
+                if self.eval(condition)?.value() == 1 {
+                    Successor::Branch(self.eval(target)?.value())
+                }
+                else {
+                    Successor::FallThrough
+                }
+            },
+            il::Operation::Phi { ref dst, ref src } => {
+                if let il::MultiVar::Scalar(ref dst) = *dst {
+                    let operand = match self.predecessor.and_then(|p| src.get(&p)) {
+                        Some(operand) => operand,
+                        None => bail!("No operand of Phi to {} for predecessor {:?}",
+                                      dst, self.predecessor)
+                    };
+                    let value = match self.phi_overwritten.get(operand.name()) {
+                        Some(value) => value.clone(),
+                        None => self.scalars.get(operand.name()).cloned()
+                    };
+                    let value = match value {
+                        Some(value) => value,
+                        None => bail!("Operand {} of Phi to {} was never written", operand, dst)
+                    };
+                    if !self.phi_overwritten.contains_key(dst.name()) {
+                        let overwritten = self.scalars.get(dst.name()).cloned();
+                        self.phi_overwritten.insert(dst.name().to_string(), overwritten);
+                    }
+                    self.scalars.insert(dst.name().to_string(), value);
+                }
+                Successor::FallThrough
+            },
+            il::Operation::Raise { ref expr } => Successor::Raise(expr.clone())
+        };
+
+        if let Some(ref mut taint) = self.taint {
+            taint.execute(operation, address)?;
+        }
+
+        Ok(successor)
+    }
+}
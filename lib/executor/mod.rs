@@ -3,6 +3,12 @@
 use error::*;
 use il;
 
+pub mod interpreter;
+pub mod taint;
+
+pub use self::interpreter::{Interpreter, Successor};
+pub use self::taint::{ShadowTaint, TaintLabel};
+
 
 /// Swaps the bytes of an expression (swaps endianness)
 pub fn swap_bytes(expr: &il::Expression) -> Result<il::Expression> {
@@ -116,6 +122,46 @@ pub fn swap_bytes(expr: &il::Expression) -> Result<il::Expression> {
 }
 
 
+/// Interpret a 32 or 64-bit `il::Constant` as an IEEE 754 floating point
+/// value.
+fn float_value(constant: &il::Constant) -> Result<f64> {
+    match constant.bits() {
+        32 => Ok(f32::from_bits(constant.value() as u32) as f64),
+        64 => Ok(f64::from_bits(constant.value())),
+        _ => bail!("invalid bit-length {} for floating point", constant.bits())
+    }
+}
+
+
+/// Create an `il::Constant` holding the IEEE 754 encoding of `value` in the
+/// floating point format with the given bits.
+fn float_constant(value: f64, bits: usize) -> Result<il::Constant> {
+    match bits {
+        32 => Ok(il::Constant::new((value as f32).to_bits() as u64, 32)),
+        64 => Ok(il::Constant::new(value.to_bits(), 64)),
+        _ => bail!("invalid bit-length {} for floating point", bits)
+    }
+}
+
+
+/// The value of a constant, interpreted as a signed integer of its bitness.
+fn signed(constant: &il::Constant) -> i128 {
+    let shift = 128 - constant.bits();
+    ((constant.value_u128() << shift) as i128) >> shift
+}
+
+
+/// The amount a constant shifts by, or `None` if it shifts out every bit.
+fn shift_amount(constant: &il::Constant) -> Option<u32> {
+    if constant.value_u128() < 128 {
+        Some(constant.value_u128() as u32)
+    }
+    else {
+        None
+    }
+}
+
+
 /// Takes an `il::Expression` where all terminals are `il::Constants`, and
 /// returns an `il::Constant` with the result of the expression.
 pub fn constants_expression(expr: &il::Expression) -> Result<il::Constant> {
@@ -133,83 +179,87 @@ pub fn constants_expression(expr: &il::Expression) -> Result<il::Constant> {
         il::Expression::Constant(ref constant) => Ok(constant.clone()),
 
         il::Expression::Add(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() + ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128().wrapping_add(ece(rhs)?.value_u128());
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Sub(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value().wrapping_sub(ece(rhs)?.value());
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128().wrapping_sub(ece(rhs)?.value_u128());
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Mul(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() * ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128().wrapping_mul(ece(rhs)?.value_u128());
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Divu(ref lhs, ref rhs) => {
             let rhs = ece(rhs)?;
-            if rhs.value() == 0 {
+            if rhs.value_u128() == 0 {
                 return Err(ErrorKind::Arithmetic.into());
             }
-            let r = ece(lhs)?.value() / rhs.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128() / rhs.value_u128();
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Modu(ref lhs, ref rhs) => {
             let rhs = ece(rhs)?;
-            if rhs.value() == 0 {
+            if rhs.value_u128() == 0 {
                 return Err(ErrorKind::Arithmetic.into());
             }
-            let r = ece(lhs)?.value() % rhs.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128() % rhs.value_u128();
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Divs(ref lhs, ref rhs) => {
             let rhs = ece(rhs)?;
-            if rhs.value() == 0 {
+            if rhs.value_u128() == 0 {
                 return Err(ErrorKind::Arithmetic.into());
             }
-            let r = (ece(lhs)?.value() as i64) / (rhs.value() as i64);
-            Ok(il::Constant::new(r as u64, lhs.bits()))
+            let r = signed(&ece(lhs)?).wrapping_div(signed(&rhs));
+            Ok(il::Constant::new_u128(r as u128, lhs.bits()))
         },
 
         il::Expression::Mods(ref lhs, ref rhs) => {
             let rhs = ece(rhs)?;
-            if rhs.value() == 0 {
+            if rhs.value_u128() == 0 {
                 return Err(ErrorKind::Arithmetic.into());
             }
-            let r = (ece(lhs)?.value() as i64) % (rhs.value() as i64);
-            Ok(il::Constant::new(r as u64, lhs.bits()))
+            let r = signed(&ece(lhs)?).wrapping_rem(signed(&rhs));
+            Ok(il::Constant::new_u128(r as u128, lhs.bits()))
         },
 
         il::Expression::And(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() & ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128() & ece(rhs)?.value_u128();
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Or(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() | ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128() | ece(rhs)?.value_u128();
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Xor(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() ^ ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = ece(lhs)?.value_u128() ^ ece(rhs)?.value_u128();
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Shl(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() << ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = shift_amount(&ece(rhs)?)
+                .and_then(|shift| ece(lhs).ok().map(|lhs| lhs.value_u128() << shift))
+                .unwrap_or(0);
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Shr(ref lhs, ref rhs) => {
-            let r = ece(lhs)?.value() >> ece(rhs)?.value();
-            Ok(il::Constant::new(r, lhs.bits()))
+            let r = shift_amount(&ece(rhs)?)
+                .and_then(|shift| ece(lhs).ok().map(|lhs| lhs.value_u128() >> shift))
+                .unwrap_or(0);
+            Ok(il::Constant::new_u128(r, lhs.bits()))
         },
 
         il::Expression::Cmpeq(ref lhs, ref rhs) => {
-            if ece(lhs)?.value() == ece(rhs)?.value() {
+            if ece(lhs)?.value_u128() == ece(rhs)?.value_u128() {
                 Ok(il::Constant::new(1, 1))
             }
             else {
@@ -218,7 +268,7 @@ pub fn constants_expression(expr: &il::Expression) -> Result<il::Constant> {
         },
 
         il::Expression::Cmpneq(ref lhs, ref rhs) => {
-            if ece(lhs)?.value() != ece(rhs)?.value() {
+            if ece(lhs)?.value_u128() != ece(rhs)?.value_u128() {
                 Ok(il::Constant::new(1, 1))
             }
             else {
@@ -227,7 +277,7 @@ pub fn constants_expression(expr: &il::Expression) -> Result<il::Constant> {
         },
 
         il::Expression::Cmplts(ref lhs, ref rhs) => {
-            if (ece(lhs)?.value() as i64) < (ece(rhs)?.value() as i64) {
+            if signed(&ece(lhs)?) < signed(&ece(rhs)?) {
                 Ok(il::Constant::new(1, 1))
             }
             else {
@@ -236,7 +286,7 @@ pub fn constants_expression(expr: &il::Expression) -> Result<il::Constant> {
         },
 
         il::Expression::Cmpltu(ref lhs, ref rhs) => {
-            if ece(lhs)?.value() < ece(rhs)?.value() {
+            if ece(lhs)?.value_u128() < ece(rhs)?.value_u128() {
                 Ok(il::Constant::new(1, 1))
             }
             else {
@@ -246,17 +296,83 @@ pub fn constants_expression(expr: &il::Expression) -> Result<il::Constant> {
 
         il::Expression::Zext(bits, ref rhs) |
         il::Expression::Trun(bits, ref rhs) => {
-            Ok(il::Constant::new(ece(rhs)?.value(), bits))
+            Ok(il::Constant::new_u128(ece(rhs)?.value_u128(), bits))
         },
 
         il::Expression::Sext(bits, ref rhs) => {
-            let rhs = ece(rhs)?;
-            if rhs.value() >> (rhs.bits() - 1) == 1 {
-                let mask = !((1 << rhs.bits()) - 1);
-                Ok(il::Constant::new(rhs.value() | mask, bits))
+            Ok(il::Constant::new_u128(signed(&ece(rhs)?) as u128, bits))
+        },
+
+        il::Expression::Fadd(ref lhs, ref rhs) => {
+            let r = float_value(&ece(lhs)?)? + float_value(&ece(rhs)?)?;
+            float_constant(r, lhs.bits())
+        },
+
+        il::Expression::Fsub(ref lhs, ref rhs) => {
+            let r = float_value(&ece(lhs)?)? - float_value(&ece(rhs)?)?;
+            float_constant(r, lhs.bits())
+        },
+
+        il::Expression::Fmul(ref lhs, ref rhs) => {
+            let r = float_value(&ece(lhs)?)? * float_value(&ece(rhs)?)?;
+            float_constant(r, lhs.bits())
+        },
+
+        il::Expression::Fdiv(ref lhs, ref rhs) => {
+            let r = float_value(&ece(lhs)?)? / float_value(&ece(rhs)?)?;
+            float_constant(r, lhs.bits())
+        },
+
+        il::Expression::Fcmpeq(ref lhs, ref rhs) => {
+            if float_value(&ece(lhs)?)? == float_value(&ece(rhs)?)? {
+                Ok(il::Constant::new(1, 1))
+            }
+            else {
+                Ok(il::Constant::new(0, 1))
+            }
+        },
+
+        il::Expression::Fcmplt(ref lhs, ref rhs) => {
+            if float_value(&ece(lhs)?)? < float_value(&ece(rhs)?)? {
+                Ok(il::Constant::new(1, 1))
+            }
+            else {
+                Ok(il::Constant::new(0, 1))
+            }
+        },
+
+        il::Expression::Fcmpuo(ref lhs, ref rhs) => {
+            if    float_value(&ece(lhs)?)?.is_nan()
+               || float_value(&ece(rhs)?)?.is_nan() {
+                Ok(il::Constant::new(1, 1))
+            }
+            else {
+                Ok(il::Constant::new(0, 1))
+            }
+        },
+
+        il::Expression::Fext(bits, ref rhs) => {
+            float_constant(float_value(&ece(rhs)?)?, bits)
+        },
+
+        il::Expression::Itof(bits, ref rhs) => {
+            let value = signed(&ece(rhs)?);
+            match bits {
+                32 => float_constant(value as f32 as f64, bits),
+                _ => float_constant(value as f64, bits)
+            }
+        },
+
+        il::Expression::Ftoi(bits, ref rhs) => {
+            // Values which can not be represented become the smallest signed
+            // integer, as the x87 and SSE conversions do.
+            let value = float_value(&ece(rhs)?)?.trunc();
+            let limit = (2.0 as f64).powi(bits as i32 - 1);
+            if value.is_nan() || value < -limit || value >= limit {
+                Ok(il::Constant::new(1 << (bits - 1), bits))
             }
             else {
-                Ok(il::Constant::new(rhs.value(), bits))
+                Ok(il::Constant::new(value as i64 as u64, bits))
             }
         }
     }
@@ -0,0 +1,164 @@
+//! Shadow taint labels for dynamic taint tracking.
+//!
+//! A `TaintLabel` identifies one byte of input. `ShadowTaint` holds the labels
+//! of the input bytes which influence every scalar and memory cell of an
+//! engine, and is updated alongside the engine as operations are executed.
+
+use error::*;
+use il;
+use il::Variable;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+
+/// One byte of input, read from a file descriptor at an offset.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TaintLabel {
+    fd: i32,
+    offset: u64
+}
+
+
+impl TaintLabel {
+    /// Create a new `TaintLabel` for the byte at `offset` in `fd`.
+    pub fn new(fd: i32, offset: u64) -> TaintLabel {
+        TaintLabel {
+            fd: fd,
+            offset: offset
+        }
+    }
+
+    /// Get the file descriptor this byte was read from.
+    pub fn fd(&self) -> i32 {
+        self.fd
+    }
+
+    /// Get the offset of this byte in its file descriptor.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+
+impl fmt::Display for TaintLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fd_{}_{}", self.fd, self.offset)
+    }
+}
+
+
+/// The taint labels of the scalars and memory of an engine.
+///
+/// Memory is labelled byte by byte. A value stored to memory gives every byte
+/// it covers the labels of the whole value.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ShadowTaint {
+    scalars: BTreeMap<String, BTreeSet<TaintLabel>>,
+    memory: BTreeMap<u64, BTreeSet<TaintLabel>>
+}
+
+
+impl ShadowTaint {
+    /// Create a new `ShadowTaint` where nothing is tainted.
+    pub fn new() -> ShadowTaint {
+        ShadowTaint::default()
+    }
+
+    /// Get the labels of a scalar.
+    pub fn scalar(&self, name: &str) -> BTreeSet<TaintLabel> {
+        self.scalars.get(name).cloned().unwrap_or(BTreeSet::new())
+    }
+
+    /// Set the labels of a scalar.
+    pub fn set_scalar<S>(&mut self, name: S, labels: BTreeSet<TaintLabel>)
+        where S: Into<String> {
+        if labels.is_empty() {
+            self.scalars.remove(&name.into());
+        }
+        else {
+            self.scalars.insert(name.into(), labels);
+        }
+    }
+
+    /// Get the labels of the byte at `address`.
+    pub fn byte(&self, address: u64) -> BTreeSet<TaintLabel> {
+        self.memory.get(&address).cloned().unwrap_or(BTreeSet::new())
+    }
+
+    /// Set the labels of the byte at `address`.
+    pub fn set_byte(&mut self, address: u64, labels: BTreeSet<TaintLabel>) {
+        if labels.is_empty() {
+            self.memory.remove(&address);
+        }
+        else {
+            self.memory.insert(address, labels);
+        }
+    }
+
+    /// Get the labels of a value of `bits` loaded from `address`.
+    pub fn load(&self, address: u64, bits: usize) -> BTreeSet<TaintLabel> {
+        let mut labels = BTreeSet::new();
+        for offset in 0..((bits + 7) / 8) as u64 {
+            if let Some(byte) = self.memory.get(&address.wrapping_add(offset)) {
+                labels.extend(byte.iter().cloned());
+            }
+        }
+        labels
+    }
+
+    /// Label every byte of a value of `bits` stored at `address`.
+    pub fn store(&mut self, address: u64, bits: usize, labels: &BTreeSet<TaintLabel>) {
+        for offset in 0..((bits + 7) / 8) as u64 {
+            self.set_byte(address.wrapping_add(offset), labels.clone());
+        }
+    }
+
+    /// Get the labels of the value of an expression.
+    pub fn expression(&self, expression: &il::Expression) -> BTreeSet<TaintLabel> {
+        let mut labels = BTreeSet::new();
+        for scalar in expression.collect_scalars() {
+            if let Some(scalar_labels) = self.scalars.get(scalar.name()) {
+                labels.extend(scalar_labels.iter().cloned());
+            }
+        }
+        labels
+    }
+
+    /// Propagate labels through an operation.
+    ///
+    /// `address` is the concrete address of a `Load` or `Store`. Branches and
+    /// raises do not change labels.
+    pub fn execute(&mut self, operation: &il::Operation, address: Option<u64>)
+        -> Result<()> {
+
+        match *operation {
+            il::Operation::Assign { ref dst, ref src } => {
+                let labels = self.expression(src);
+                self.set_scalar(dst.name(), labels);
+            },
+            il::Operation::Store { ref src, .. } => {
+                let address = address.ok_or("Store without an address")?;
+                let labels = self.expression(src);
+                self.store(address, src.bits(), &labels);
+            },
+            il::Operation::Load { ref dst, .. } => {
+                let address = address.ok_or("Load without an address")?;
+                let labels = self.load(address, dst.bits());
+                self.set_scalar(dst.name(), labels);
+            },
+            il::Operation::Phi { ref dst, ref src } => {
+                if let il::MultiVar::Scalar(ref dst) = *dst {
+                    let mut labels = BTreeSet::new();
+                    for src in src.values() {
+                        labels.extend(self.scalar(src.name()));
+                    }
+                    self.set_scalar(dst.name(), labels);
+                }
+            },
+            il::Operation::Brc { .. } |
+            il::Operation::Raise { .. } => {}
+        }
+
+        Ok(())
+    }
+}
@@ -406,6 +406,49 @@ impl<V, E> Graph<V, E> where V: Vertex, E: Edge {
     }
 
 
+    /// Computes the natural loops of the graph, keyed by loop header
+    ///
+    /// An edge from A to B is a back edge when B dominates A. The natural
+    /// loop of this edge is B, and every vertex which can reach A without
+    /// passing through B. Loops which share a header are merged.
+    pub fn compute_natural_loops(&self, start_index: u64)
+    -> Result<BTreeMap<u64, BTreeSet<u64>>> {
+        let dominators = self.compute_dominators(start_index)?;
+
+        let mut loops: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+
+        for (vertex_index, doms) in &dominators {
+            for edge in &self.edges_out[vertex_index] {
+                let header = edge.tail();
+                if !doms.contains(&header) {
+                    continue;
+                }
+
+                let body = loops.entry(header).or_insert(BTreeSet::new());
+                body.insert(header);
+
+                // walk backwards from the tail of the back edge, stopping at
+                // the header
+                let mut queue = VecDeque::new();
+                queue.push_back(*vertex_index);
+                while let Some(index) = queue.pop_front() {
+                    if body.contains(&index) {
+                        continue;
+                    }
+                    body.insert(index);
+                    for edge in &self.edges_in[&index] {
+                        if dominators.contains_key(&edge.head()) {
+                            queue.push_back(edge.head());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(loops)
+    }
+
+
     /// Computes predecessors for all vertices in the graph
     ///
     /// The resulting sets include all predecessors for each vertex in the
@@ -7,6 +7,7 @@
 //!
 //! To create a `Block`, call `ControlFlowGraph::new_block`.
 
+use std::collections::BTreeMap;
 use std::fmt;
 use il::*;
 
@@ -160,7 +161,7 @@ impl Block {
     }
 
     /// Adds a phi operation to the end of this block.
-    pub fn phi(&mut self, dst: MultiVar, src: Vec<MultiVar>) {
+    pub fn phi(&mut self, dst: MultiVar, src: BTreeMap<u64, MultiVar>) {
         let index = self.new_instruction_index();
         self.push(Instruction::phi(index, dst, src));
     }
@@ -171,8 +172,17 @@ impl Block {
         self.push(Instruction::raise(index, expr));
     }
 
+    /// Inserts an assign operation at the given position in this block.
+    ///
+    /// `position` is an offset into the instructions of this block, not an
+    /// instruction index.
+    pub fn insert_assign(&mut self, position: usize, dst: Scalar, src: Expression) {
+        let index = self.new_instruction_index();
+        self.instructions.insert(position, Instruction::assign(index, dst, src));
+    }
+
     /// Prepends an operation to the beginning of this block
-    pub fn prepend_phi(&mut self, dst: MultiVar, src: Vec<MultiVar>) {
+    pub fn prepend_phi(&mut self, dst: MultiVar, src: BTreeMap<u64, MultiVar>) {
         let index = self.new_instruction_index();
         let phi = Instruction::phi(index, dst, src);
         self.instructions.insert(0, phi);
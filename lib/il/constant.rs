@@ -1,6 +1,7 @@
 //! A `Constant` holds a single value.
 //!
-//! Currently, only constant values upto 64-bits are supported.
+//! Constant values of up to 128 bits are supported. Wider constants, such as
+//! the value of a vector register, are created with `Constant::new_u128`.
 
 use std::fmt;
 use il::*;
@@ -9,7 +10,7 @@ use il::*;
 /// A constant value for Falcon IL
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Constant {
-    value: u64,
+    value: u128,
     bits: usize
 }
 
@@ -17,17 +18,29 @@ pub struct Constant {
 impl Constant {
     /// Create a new `Constant` with the given value and bitness.
     pub fn new(value: u64, bits: usize) -> Constant {
+        Constant::new_u128(value as u128, bits)
+    }
+
+    /// Create a new `Constant` of up to 128 bits with the given value and
+    /// bitness.
+    ///
+    /// Bits of `value` above `bits` are discarded, so constants which are
+    /// equal in `bits` bits compare equal.
+    pub fn new_u128(value: u128, bits: usize) -> Constant {
+        let value = if bits >= 128 { value } else { value & ((1 << bits) - 1) };
         Constant { value: value, bits: bits }
     }
 
     /// Get the value of this `Constant`.
+    ///
+    /// Only the low 64 bits are returned for constants wider than 64 bits.
     pub fn value(&self) -> u64 {
-        if self.bits == 64 {
-            self.value
-        }
-        else {
-            self.value & ((1 << self.bits) - 1)
-        }
+        self.value as u64
+    }
+
+    /// Get the full value of this `Constant`.
+    pub fn value_u128(&self) -> u128 {
+        self.value
     }
 
     /// Get the number of bits for this `Constant`.
@@ -39,7 +52,7 @@ impl Constant {
 
 impl fmt::Display for Constant {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "0x{:X}:{}", self.value(), self.bits)
+        write!(f, "0x{:X}:{}", self.value_u128(), self.bits)
     }
 }
 
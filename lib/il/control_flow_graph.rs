@@ -245,6 +245,28 @@ impl ControlFlowGraph {
     }
 
 
+    /// Removes a `Block`, and every `Edge` to or from that `Block`.
+    ///
+    /// If the `Block` is the entry or exit of this `ControlFlowGraph`, the
+    /// entry or exit is unset.
+    pub fn remove_block(&mut self, index: u64) -> Result<()> {
+        self.graph.remove_vertex(index)?;
+        if self.entry == Some(index) {
+            self.entry = None;
+        }
+        if self.exit == Some(index) {
+            self.exit = None;
+        }
+        Ok(())
+    }
+
+
+    /// Removes an `Edge` by its head and tail `Block` indices.
+    pub fn remove_edge(&mut self, head: u64, tail: u64) -> Result<()> {
+        self.graph.remove_edge(head, tail)
+    }
+
+
     /// Creates an unconditional edge from one block to another block
     pub fn unconditional_edge(&mut self, head: u64, tail: u64) -> Result<()> {
         let edge = Edge::new(head, tail, None);
@@ -20,6 +20,15 @@
 //!
 //! ## Extension/Truncation
 //! `zext`, `sext`, `trun`
+//!
+//! ## Floating Point
+//! Floating point expressions interpret their operands as IEEE 754 binary32
+//! or binary64 values, and so their operands must be 32 or 64 bits.
+//! Floating point results round to nearest, ties to even, and conversions
+//! to integers truncate towards zero.
+//!
+//! `fadd`, `fsub`, `fmul`, `fdiv`, `fcmpeq`, `fcmplt`, `fcmpuo`, `fext`,
+//! `itof`, `ftoi`
 
 use std::fmt;
 
@@ -52,6 +61,19 @@ pub enum Expression {
     Zext(usize, Box<Expression>),
     Sext(usize, Box<Expression>),
     Trun(usize, Box<Expression>),
+
+    Fadd(Box<Expression>, Box<Expression>),
+    Fsub(Box<Expression>, Box<Expression>),
+    Fmul(Box<Expression>, Box<Expression>),
+    Fdiv(Box<Expression>, Box<Expression>),
+
+    Fcmpeq(Box<Expression>, Box<Expression>),
+    Fcmplt(Box<Expression>, Box<Expression>),
+    Fcmpuo(Box<Expression>, Box<Expression>),
+
+    Fext(usize, Box<Expression>),
+    Itof(usize, Box<Expression>),
+    Ftoi(usize, Box<Expression>),
 }
 
 
@@ -72,14 +94,24 @@ impl Expression {
             Expression::Or(ref lhs, _) |
             Expression::Xor(ref lhs, _) |
             Expression::Shl(ref lhs, _) |
-            Expression::Shr(ref lhs, _) => lhs.bits(),
+            Expression::Shr(ref lhs, _) |
+            Expression::Fadd(ref lhs, _) |
+            Expression::Fsub(ref lhs, _) |
+            Expression::Fmul(ref lhs, _) |
+            Expression::Fdiv(ref lhs, _) => lhs.bits(),
             Expression::Cmpeq(_, _) |
             Expression::Cmpneq(_, _) |
             Expression::Cmplts(_, _) |
-            Expression::Cmpltu(_, _) => 1,
+            Expression::Cmpltu(_, _) |
+            Expression::Fcmpeq(_, _) |
+            Expression::Fcmplt(_, _) |
+            Expression::Fcmpuo(_, _) => 1,
             Expression::Zext(bits, _) |
             Expression::Sext(bits, _) |
-            Expression::Trun(bits, _) => bits
+            Expression::Trun(bits, _) |
+            Expression::Fext(bits, _) |
+            Expression::Itof(bits, _) |
+            Expression::Ftoi(bits, _) => bits
         }
     }
 
@@ -97,6 +129,23 @@ impl Expression {
         }
     }
 
+    /// Returns true if `bits` is the width of a supported floating point
+    /// format.
+    fn is_float_sort(bits: usize) -> bool {
+        bits == 32 || bits == 64
+    }
+
+    /// Ensures lhs and rhs are both floating point values of the same format.
+    fn ensure_float_sort(lhs: &Expression, rhs: &Expression) -> Result<()> {
+        if    lhs.bits() != rhs.bits()
+           || !Expression::is_float_sort(lhs.bits()) {
+            Err(ErrorKind::Sort.into())
+        }
+        else {
+            Ok(())
+        }
+    }
+
     /// Returns all `Scalars` used in this `Expression`
     pub fn collect_scalars(&self) -> Vec<&Scalar> {
         let mut scalars: Vec<&Scalar> = Vec::new();
@@ -120,13 +169,23 @@ impl Expression {
             Expression::Cmpeq(ref lhs, ref rhs) |
             Expression::Cmpneq(ref lhs, ref rhs) |
             Expression::Cmplts(ref lhs, ref rhs) |
-            Expression::Cmpltu(ref lhs, ref rhs) => {
+            Expression::Cmpltu(ref lhs, ref rhs) |
+            Expression::Fadd(ref lhs, ref rhs) |
+            Expression::Fsub(ref lhs, ref rhs) |
+            Expression::Fmul(ref lhs, ref rhs) |
+            Expression::Fdiv(ref lhs, ref rhs) |
+            Expression::Fcmpeq(ref lhs, ref rhs) |
+            Expression::Fcmplt(ref lhs, ref rhs) |
+            Expression::Fcmpuo(ref lhs, ref rhs) => {
                 scalars.append(&mut lhs.collect_scalars());
                 scalars.append(&mut rhs.collect_scalars());
             },
             Expression::Zext(_, ref rhs) |
             Expression::Sext(_, ref rhs) |
-            Expression::Trun(_, ref rhs) => {
+            Expression::Trun(_, ref rhs) |
+            Expression::Fext(_, ref rhs) |
+            Expression::Itof(_, ref rhs) |
+            Expression::Ftoi(_, ref rhs) => {
                 scalars.append(&mut rhs.collect_scalars());
             }
         }
@@ -156,13 +215,23 @@ impl Expression {
             Expression::Cmpeq(ref mut lhs, ref mut rhs) |
             Expression::Cmpneq(ref mut lhs, ref mut rhs) |
             Expression::Cmplts(ref mut lhs, ref mut rhs) |
-            Expression::Cmpltu(ref mut lhs, ref mut rhs) => {
+            Expression::Cmpltu(ref mut lhs, ref mut rhs) |
+            Expression::Fadd(ref mut lhs, ref mut rhs) |
+            Expression::Fsub(ref mut lhs, ref mut rhs) |
+            Expression::Fmul(ref mut lhs, ref mut rhs) |
+            Expression::Fdiv(ref mut lhs, ref mut rhs) |
+            Expression::Fcmpeq(ref mut lhs, ref mut rhs) |
+            Expression::Fcmplt(ref mut lhs, ref mut rhs) |
+            Expression::Fcmpuo(ref mut lhs, ref mut rhs) => {
                 scalars.append(&mut lhs.collect_scalars_mut());
                 scalars.append(&mut rhs.collect_scalars_mut());
             },
             Expression::Zext(_, ref mut rhs) |
             Expression::Sext(_, ref mut rhs) |
-            Expression::Trun(_, ref mut rhs) => {
+            Expression::Trun(_, ref mut rhs) |
+            Expression::Fext(_, ref mut rhs) |
+            Expression::Itof(_, ref mut rhs) |
+            Expression::Ftoi(_, ref mut rhs) => {
                 scalars.append(&mut rhs.collect_scalars_mut());
             }
         }
@@ -338,6 +407,99 @@ impl Expression {
         }
         Ok(Expression::Trun(bits, Box::new(src)))
     }
+
+    /// Create a floating point addition `Expression`.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fadd(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fadd(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create a floating point subtraction `Expression`.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fsub(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fsub(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create a floating point multiplication `Expression`.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fmul(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fmul(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create a floating point division `Expression`.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fdiv(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fdiv(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create a floating point equals comparison `Expression`. This is false
+    /// if either operand is NaN.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fcmpeq(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fcmpeq(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create a floating point less-than comparison `Expression`. This is
+    /// false if either operand is NaN.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fcmplt(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fcmplt(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create a floating point unordered comparison `Expression`, which is
+    /// true if either operand is NaN.
+    /// # Error
+    /// The sort of the lhs and the rhs are not the same floating point format.
+    pub fn fcmpuo(lhs: Expression, rhs: Expression) -> Result<Expression> {
+        try!(Expression::ensure_float_sort(&lhs, &rhs));
+        Ok(Expression::Fcmpuo(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Create an expression to convert the floating point value src to the
+    /// floating point format with the number of bits given.
+    /// # Error
+    /// src or bits is not a floating point format.
+    pub fn fext(bits: usize, src: Expression) -> Result<Expression> {
+        if    !Expression::is_float_sort(bits)
+           || !Expression::is_float_sort(src.bits()) {
+            return Err(ErrorKind::Sort.into());
+        }
+        Ok(Expression::Fext(bits, Box::new(src)))
+    }
+
+    /// Create an expression to convert the signed integer src to a floating
+    /// point value with the number of bits given.
+    /// # Error
+    /// bits is not a floating point format, or src has no bits.
+    pub fn itof(bits: usize, src: Expression) -> Result<Expression> {
+        if !Expression::is_float_sort(bits) || src.bits() == 0 {
+            return Err(ErrorKind::Sort.into());
+        }
+        Ok(Expression::Itof(bits, Box::new(src)))
+    }
+
+    /// Create an expression to convert the floating point value src to a
+    /// signed integer with the number of bits given, truncating towards zero.
+    /// # Error
+    /// src is not a floating point format, or bits is 0.
+    pub fn ftoi(bits: usize, src: Expression) -> Result<Expression> {
+        if !Expression::is_float_sort(src.bits()) || bits == 0 {
+            return Err(ErrorKind::Sort.into());
+        }
+        Ok(Expression::Ftoi(bits, Box::new(src)))
+    }
 }
 
 
@@ -384,6 +546,26 @@ impl fmt::Display for Expression {
                 write!(f, "sext.{}({})", bits, src),
             Expression::Trun(ref bits, ref src) =>
                 write!(f, "trun.{}({})", bits, src),
+            Expression::Fadd(ref lhs, ref rhs) =>
+                write!(f, "({} +. {})", lhs, rhs),
+            Expression::Fsub(ref lhs, ref rhs) =>
+                write!(f, "({} -. {})", lhs, rhs),
+            Expression::Fmul(ref lhs, ref rhs) =>
+                write!(f, "({} *. {})", lhs, rhs),
+            Expression::Fdiv(ref lhs, ref rhs) =>
+                write!(f, "({} /. {})", lhs, rhs),
+            Expression::Fcmpeq(ref lhs, ref rhs) =>
+                write!(f, "({} ==. {})", lhs, rhs),
+            Expression::Fcmplt(ref lhs, ref rhs) =>
+                write!(f, "({} <. {})", lhs, rhs),
+            Expression::Fcmpuo(ref lhs, ref rhs) =>
+                write!(f, "({} uo. {})", lhs, rhs),
+            Expression::Fext(ref bits, ref src) =>
+                write!(f, "fext.{}({})", bits, src),
+            Expression::Itof(ref bits, ref src) =>
+                write!(f, "itof.{}({})", bits, src),
+            Expression::Ftoi(ref bits, ref src) =>
+                write!(f, "ftoi.{}({})", bits, src),
         }
     }
 }
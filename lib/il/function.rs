@@ -16,7 +16,13 @@ pub struct Function {
     // The name of the function
     name: Option<String>,
     // Functions which belong to Programs have indices
-    index: Option<u64>
+    index: Option<u64>,
+    // The recovered signature of this function
+    #[serde(default)]
+    signature: Option<Signature>,
+    // The recovered types of this function
+    #[serde(default)]
+    types: Option<FunctionTypes>
 }
 
 
@@ -31,7 +37,9 @@ impl Function {
             address: address,
             control_flow_graph: control_flow_graph,
             name: None,
-            index: None
+            index: None,
+            signature: None,
+            types: None
         }
     }
 
@@ -80,6 +88,27 @@ impl Function {
         self.name = name;
     }
 
+    /// Return the `Signature` of this `Function`, if one has been recovered.
+    pub fn signature(&self) -> Option<&Signature> {
+        self.signature.as_ref()
+    }
+
+    /// Set this `Function`'s `Signature`.
+    pub fn set_signature(&mut self, signature: Option<Signature>) {
+        self.signature = signature;
+    }
+
+    /// Return the `FunctionTypes` of this `Function`, if types have been
+    /// recovered.
+    pub fn types(&self) -> Option<&FunctionTypes> {
+        self.types.as_ref()
+    }
+
+    /// Set this `Function`'s `FunctionTypes`.
+    pub fn set_types(&mut self, types: Option<FunctionTypes>) {
+        self.types = types;
+    }
+
     /// Return the index of this `Function`. A `Function` will have an index if
     /// it is added to a `Program`.
     pub fn index(&self) -> Option<u64> {
@@ -6,6 +6,7 @@
 //! over a `Block`, such as `Block::assign`.
 
 use il::*;
+use std::collections::BTreeMap;
 use std::fmt;
 
 /// An `Instruction` represents location, and non-semantical information about
@@ -64,7 +65,7 @@ impl Instruction {
     }
 
 
-    pub(crate) fn phi(index: u64, dst: MultiVar, src: Vec<MultiVar>)
+    pub(crate) fn phi(index: u64, dst: MultiVar, src: BTreeMap<u64, MultiVar>)
     -> Instruction {
 
         Instruction::new(index, Operation::phi(dst, src))
@@ -12,6 +12,8 @@ pub mod function;
 pub mod instruction;
 pub mod operation;
 pub mod scalar;
+pub mod signature;
+pub mod types;
 pub mod program;
 pub mod variable;
 
@@ -24,6 +26,8 @@ pub use self::function::*;
 pub use self::instruction::*;
 pub use self::operation::*;
 pub use self::scalar::*;
+pub use self::signature::*;
+pub use self::types::*;
 pub use self::program::*;
 pub use self::variable::*;
 
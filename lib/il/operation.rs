@@ -1,8 +1,9 @@
 //! An `Operation` applies semantics to `Array` and `Scalar` with `Expression`, or emits
 //! `Raise`.
 
-use std::fmt;
 use il::*;
+use std::collections::BTreeMap;
+use std::fmt;
 
 /// An IL Operation updates some state.
 #[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
@@ -29,10 +30,11 @@ pub enum Operation {
         target: Expression,
         condition: Expression
     },
-    /// Phi operation for SSA
+    /// Phi operation for SSA. Each operand is keyed by the index of the
+    /// predecessor block it flows in from.
     Phi {
         dst: MultiVar,
-        src: Vec<MultiVar>
+        src: BTreeMap<u64, MultiVar>
     },
     /// Raise operation for handling things such as system calls
     Raise {
@@ -66,7 +68,7 @@ impl Operation {
     }
 
     /// Create a new `Operation::Phi`.
-    pub fn phi(dst: MultiVar, src: Vec<MultiVar>) -> Operation {
+    pub fn phi(dst: MultiVar, src: BTreeMap<u64, MultiVar>) -> Operation {
         Operation::Phi { dst: dst, src: src }
     }
 
@@ -101,7 +103,7 @@ impl Operation {
                 read.append(&mut collect_scalars(condition));
             },
             Operation::Phi { ref src, .. } => {
-                for multi_var in src {
+                for multi_var in src.values() {
                     read.push(multi_var);
                 }
             },
@@ -141,7 +143,7 @@ impl Operation {
                 read.append(&mut collect_scalars_mut(condition));
             },
             Operation::Phi { ref mut src, .. } => {
-                for multi_var in src {
+                for multi_var in src.values_mut() {
                     read.push(multi_var);
                 }
             },
@@ -195,7 +197,7 @@ impl fmt::Display for Operation {
             Operation::Phi { ref dst, ref src } => 
                 write!(f, "phi {} <- {{{}}}", dst,
                     src.iter()
-                       .map(|v| format!("{}", v))
+                       .map(|(predecessor, v)| format!("0x{:X}: {}", predecessor, v))
                        .collect::<Vec<String>>()
                        .join(", ")),
             Operation::Raise { ref expr } => 
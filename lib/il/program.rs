@@ -40,6 +40,12 @@ impl Program {
     }
 
 
+    /// Get mutable references to all `Function` for this `Program`.
+    pub fn functions_mut(&mut self) -> Vec<&mut Function> {
+        self.functions.values_mut().collect::<Vec<&mut Function>>()
+    }
+
+
     /// Get a `Function` by its index.
     ///
     /// A `Function` index is assigned by `Program` and is not the address where the `Function`
@@ -0,0 +1,137 @@
+//! A `Signature` summarizes how a `Function` is called.
+
+use il::*;
+use std::fmt;
+
+
+/// The calling conventions recognized when recovering a `Signature`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum CallingConvention {
+    /// Arguments on the stack, removed by the caller.
+    Cdecl,
+    /// Arguments on the stack, removed by the callee.
+    Stdcall,
+    /// The first arguments in registers, the rest as with `Stdcall`.
+    Fastcall
+}
+
+
+impl fmt::Display for CallingConvention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CallingConvention::Cdecl => write!(f, "cdecl"),
+            CallingConvention::Stdcall => write!(f, "stdcall"),
+            CallingConvention::Fastcall => write!(f, "fastcall")
+        }
+    }
+}
+
+
+/// An argument passed to a `Function`.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Parameter {
+    /// An argument passed in a scalar.
+    Scalar(Scalar),
+    /// An argument passed on the stack, at an offset from the stack pointer on
+    /// entry to the function.
+    Stack {
+        offset: i64,
+        bits: usize
+    }
+}
+
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Parameter::Scalar(ref scalar) => scalar.fmt(f),
+            Parameter::Stack { offset, bits } => write!(f, "[sp+0x{:x}]:{}", offset, bits)
+        }
+    }
+}
+
+
+/// A summary of the arguments, returns and side effects of a `Function`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Signature {
+    calling_convention: CallingConvention,
+    arguments: Vec<Parameter>,
+    returns: Vec<Scalar>,
+    clobbered: Vec<Scalar>,
+    stack_cleanup: u64
+}
+
+
+impl Signature {
+    /// Create a new `Signature`.
+    ///
+    /// # Parameters
+    /// * `calling_convention` - The calling convention of the function.
+    /// * `arguments` - Scalars and stack slots read before they are written.
+    /// * `returns` - Scalars written on every path to a return.
+    /// * `clobbered` - Other scalars which are not preserved by the function.
+    /// * `stack_cleanup` - Bytes removed from the stack by the function when it
+    ///   returns, besides the return address.
+    pub fn new(
+        calling_convention: CallingConvention,
+        arguments: Vec<Parameter>,
+        returns: Vec<Scalar>,
+        clobbered: Vec<Scalar>,
+        stack_cleanup: u64
+    ) -> Signature {
+        Signature {
+            calling_convention: calling_convention,
+            arguments: arguments,
+            returns: returns,
+            clobbered: clobbered,
+            stack_cleanup: stack_cleanup
+        }
+    }
+
+    /// Get the calling convention of this `Signature`.
+    pub fn calling_convention(&self) -> CallingConvention {
+        self.calling_convention
+    }
+
+    /// Get the arguments of this `Signature`.
+    pub fn arguments(&self) -> &Vec<Parameter> {
+        &self.arguments
+    }
+
+    /// Get the scalars returned by this `Signature`.
+    pub fn returns(&self) -> &Vec<Scalar> {
+        &self.returns
+    }
+
+    /// Get the scalars clobbered by this `Signature`, which are not returned.
+    pub fn clobbered(&self) -> &Vec<Scalar> {
+        &self.clobbered
+    }
+
+    /// Get the number of bytes removed from the stack by the function when it
+    /// returns, besides the return address.
+    pub fn stack_cleanup(&self) -> u64 {
+        self.stack_cleanup
+    }
+}
+
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arguments = self.arguments
+            .iter()
+            .map(|argument| format!("{}", argument))
+            .collect::<Vec<String>>();
+        let returns = self.returns
+            .iter()
+            .map(|scalar| format!("{}", scalar))
+            .collect::<Vec<String>>();
+        write!(
+            f,
+            "{} ({}) -> ({})",
+            self.calling_convention,
+            arguments.join(", "),
+            returns.join(", ")
+        )
+    }
+}
@@ -0,0 +1,206 @@
+//! Types recovered for the values and memory of a `Function`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+
+/// Whether an integer is signed.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Signedness {
+    Signed,
+    Unsigned,
+    /// The value is never used in a way which depends on its sign, or is
+    /// used as both.
+    Unknown
+}
+
+
+/// The type of a value.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Type {
+    /// A value of `bits` which is never used as an integer or a pointer.
+    Unknown(usize),
+    /// An integer of `bits`.
+    Integer {
+        bits: usize,
+        signedness: Signedness
+    },
+    /// A pointer of `bits`. If fields are accessed through this pointer,
+    /// `pointee` is the index of a `Struct` in the `FunctionTypes` which
+    /// holds it.
+    Pointer {
+        bits: usize,
+        pointee: Option<usize>
+    }
+}
+
+
+impl Type {
+    /// Get the bit-width of a value of this `Type`.
+    pub fn bits(&self) -> usize {
+        match *self {
+            Type::Unknown(bits) |
+            Type::Integer { bits, .. } |
+            Type::Pointer { bits, .. } => bits
+        }
+    }
+
+    /// Returns true if this `Type` is a pointer.
+    pub fn is_pointer(&self) -> bool {
+        match *self {
+            Type::Pointer { .. } => true,
+            _ => false
+        }
+    }
+}
+
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Unknown(bits) => write!(f, "u{}", bits),
+            Type::Integer { bits, signedness } => match signedness {
+                Signedness::Signed => write!(f, "int{}", bits),
+                Signedness::Unsigned => write!(f, "uint{}", bits),
+                Signedness::Unknown => write!(f, "i{}", bits)
+            },
+            Type::Pointer { pointee, .. } => match pointee {
+                Some(pointee) => write!(f, "struct_{}*", pointee),
+                None => write!(f, "void*")
+            }
+        }
+    }
+}
+
+
+/// A structure, accessed through a pointer, with fields at offsets from that
+/// pointer.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Struct {
+    fields: BTreeMap<i64, Type>
+}
+
+
+impl Struct {
+    /// Create a new `Struct` with the given fields.
+    pub fn new(fields: BTreeMap<i64, Type>) -> Struct {
+        Struct {
+            fields: fields
+        }
+    }
+
+    /// Get the fields of this `Struct`, keyed by offset.
+    pub fn fields(&self) -> &BTreeMap<i64, Type> {
+        &self.fields
+    }
+
+    /// Get the field at an offset.
+    pub fn field(&self, offset: i64) -> Option<&Type> {
+        self.fields.get(&offset)
+    }
+}
+
+
+impl fmt::Display for Struct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = self.fields
+            .iter()
+            .map(|(offset, type_)| format!("0x{:x}: {}", offset, type_))
+            .collect::<Vec<String>>();
+        write!(f, "{{ {} }}", fields.join(", "))
+    }
+}
+
+
+/// The types recovered for a `Function`.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FunctionTypes {
+    definitions: BTreeMap<u64, BTreeMap<u64, Type>>,
+    arguments: BTreeMap<String, Type>,
+    stack: BTreeMap<i64, Type>,
+    globals: BTreeMap<u64, Type>,
+    structs: Vec<Struct>
+}
+
+
+impl FunctionTypes {
+    /// Create a new, empty, `FunctionTypes`.
+    pub fn new() -> FunctionTypes {
+        FunctionTypes::default()
+    }
+
+    /// Get the type of the scalar written by the instruction at
+    /// `instruction_index` in the block at `block_index`.
+    pub fn definition(&self, block_index: u64, instruction_index: u64) -> Option<&Type> {
+        self.definitions
+            .get(&block_index)
+            .and_then(|instructions| instructions.get(&instruction_index))
+    }
+
+    /// Set the type of the scalar written by an instruction.
+    pub fn set_definition(&mut self, block_index: u64, instruction_index: u64, type_: Type) {
+        self.definitions
+            .entry(block_index)
+            .or_insert(BTreeMap::new())
+            .insert(instruction_index, type_);
+    }
+
+    /// Get the type of a scalar on entry to the function.
+    pub fn argument(&self, name: &str) -> Option<&Type> {
+        self.arguments.get(name)
+    }
+
+    /// Set the type of a scalar on entry to the function.
+    pub fn set_argument<S>(&mut self, name: S, type_: Type) where S: Into<String> {
+        self.arguments.insert(name.into(), type_);
+    }
+
+    /// Get the type of the stack slot at an offset from the stack pointer on
+    /// entry to the function.
+    pub fn stack(&self, offset: i64) -> Option<&Type> {
+        self.stack.get(&offset)
+    }
+
+    /// Get the types of every stack slot, keyed by offset.
+    pub fn stack_slots(&self) -> &BTreeMap<i64, Type> {
+        &self.stack
+    }
+
+    /// Set the type of a stack slot.
+    pub fn set_stack(&mut self, offset: i64, type_: Type) {
+        self.stack.insert(offset, type_);
+    }
+
+    /// Get the type of the global memory at an address.
+    pub fn global(&self, address: u64) -> Option<&Type> {
+        self.globals.get(&address)
+    }
+
+    /// Get the types of every global accessed by the function, keyed by
+    /// address.
+    pub fn globals(&self) -> &BTreeMap<u64, Type> {
+        &self.globals
+    }
+
+    /// Set the type of the global memory at an address.
+    pub fn set_global(&mut self, address: u64, type_: Type) {
+        self.globals.insert(address, type_);
+    }
+
+    /// Get a `Struct` by index.
+    pub fn struct_(&self, index: usize) -> Option<&Struct> {
+        self.structs.get(index)
+    }
+
+    /// Get every `Struct`. A `Type::Pointer` refers to a `Struct` by its index
+    /// here.
+    pub fn structs(&self) -> &Vec<Struct> {
+        &self.structs
+    }
+
+    /// Add a `Struct`, returning its index.
+    pub fn add_struct(&mut self, struct_: Struct) -> usize {
+        self.structs.push(struct_);
+        self.structs.len() - 1
+    }
+}
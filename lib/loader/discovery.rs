@@ -0,0 +1,189 @@
+//! Recursive-descent discovery of functions.
+//!
+//! Starting from the function entries given by a `Loader`, its program entry,
+//! its unwind information, and common function prologues found in executable
+//! memory, we lift every function and follow the targets of calls made in
+//! those functions until no new functions are found.
+//!
+//! Jumps to the entry of another function are tail calls. Once all functions
+//! have been found, functions which have absorbed the code of another
+//! function through a tail call are lifted again, with this jump split off.
+
+use error::*;
+use il;
+use loader::*;
+use loader::memory::EXECUTE;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+
+/// Common byte sequences which begin functions on an `Architecture`.
+fn prologues(architecture: &Architecture) -> Vec<&'static [u8]> {
+    match *architecture {
+        Architecture::AArch64 => vec![
+            // stp x29, x30, [sp, #-16]!
+            &[0xfd, 0x7b, 0xbf, 0xa9],
+            // stp x29, x30, [sp, #-32]!
+            &[0xfd, 0x7b, 0xbe, 0xa9]
+        ],
+        Architecture::Amd64 => vec![
+            // push rbp; mov rbp, rsp
+            &[0x55, 0x48, 0x89, 0xe5],
+            &[0x55, 0x48, 0x8b, 0xec]
+        ],
+        Architecture::Arm => vec![
+            // push {fp, lr}
+            &[0x00, 0x48, 0x2d, 0xe9],
+            // push {r4, lr}
+            &[0x10, 0x40, 0x2d, 0xe9]
+        ],
+        Architecture::Hsvm => vec![
+            // push rbp (0x42, rd = rbp); mov rbp, rsp (0x51, rd = rbp,
+            // rs = rsp), see `translator::hsvm` for these encodings
+            &[0x42, 0x08, 0x00, 0x00, 0x51, 0x08, 0x09, 0x00]
+        ],
+        Architecture::Mips => vec![
+            // addiu sp, sp, -32
+            &[0x27, 0xbd, 0xff, 0xe0],
+            // addiu sp, sp, -24
+            &[0x27, 0xbd, 0xff, 0xe8]
+        ],
+        Architecture::Mipsel => vec![
+            // addiu sp, sp, -32
+            &[0xe0, 0xff, 0xbd, 0x27],
+            // addiu sp, sp, -24
+            &[0xe8, 0xff, 0xbd, 0x27]
+        ],
+        Architecture::X86 => vec![
+            // push ebp; mov ebp, esp
+            &[0x55, 0x89, 0xe5],
+            &[0x55, 0x8b, 0xec]
+        ]
+    }
+}
+
+
+/// Scan executable memory for function prologues.
+pub fn scan_prologues(memory: &memory::Memory, architecture: &Architecture)
+-> Vec<u64> {
+    let prologues = prologues(architecture);
+    let mut addresses = Vec::new();
+    for (address, segment) in memory.segments() {
+        if !segment.permissions().contains(EXECUTE) {
+            continue;
+        }
+        let bytes = segment.bytes();
+        for offset in 0..bytes.len() {
+            if prologues.iter().any(|prologue| bytes[offset..].starts_with(prologue)) {
+                addresses.push(address + offset as u64);
+            }
+        }
+    }
+    addresses
+}
+
+
+/// The targets of all direct calls made by a function.
+pub fn call_targets(function: &il::Function) -> Vec<u64> {
+    let mut targets = Vec::new();
+    for block in function.control_flow_graph().blocks() {
+        for instruction in block.instructions() {
+            if let il::Operation::Brc { ref target, .. } = *instruction.operation() {
+                if let il::Expression::Constant(ref constant) = *target {
+                    targets.push(constant.value());
+                }
+            }
+        }
+    }
+    targets
+}
+
+
+fn is_executable(memory: &memory::Memory, address: u64) -> bool {
+    match memory.segments().range(..(address + 1)).next_back() {
+        Some((segment_address, segment)) =>
+               address < segment_address + segment.len() as u64
+            && segment.permissions().contains(EXECUTE),
+        None => false
+    }
+}
+
+
+/// Discover and lift all functions reachable from the function entries,
+/// program entry and unwind information given by `loader`, and from
+/// prologues in its executable memory.
+///
+/// Function entries given by the loader must lift successfully. Functions
+/// found any other way are discarded if they can not be lifted.
+pub fn discover<L: Loader>(loader: &L) -> Result<Vec<(FunctionEntry, il::Function)>> {
+    let translator = loader.translator()?;
+    let memory = loader.memory()?;
+
+    let mut names: BTreeMap<u64, FunctionEntry> = BTreeMap::new();
+    for function_entry in loader.function_entries()? {
+        names.entry(function_entry.address()).or_insert(function_entry);
+    }
+    let required = names.keys().cloned().collect::<BTreeSet<u64>>();
+
+    let mut entries = required.clone();
+    entries.insert(loader.program_entry());
+    for function_entry in loader.unwind_function_entries()? {
+        entries.insert(function_entry.address());
+    }
+    entries.extend(scan_prologues(&memory, &loader.architecture()?));
+
+    let mut functions: BTreeMap<u64, il::Function> = BTreeMap::new();
+    let mut queue = entries.iter().cloned().collect::<VecDeque<u64>>();
+
+    while let Some(address) = queue.pop_front() {
+        if functions.contains_key(&address) || !entries.contains(&address) {
+            continue;
+        }
+        let function = match translator.translate_function_with_entries(
+            &memory,
+            address,
+            &entries
+        ) {
+            Ok(function) => function,
+            Err(e) => {
+                if required.contains(&address) {
+                    return Err(e);
+                }
+                trace!("discarding function at {:X}: {}", address, e);
+                entries.remove(&address);
+                continue;
+            }
+        };
+        for target in call_targets(&function) {
+            if !entries.contains(&target) && is_executable(&memory, target) {
+                entries.insert(target);
+                queue.push_back(target);
+            }
+        }
+        functions.insert(address, function);
+    }
+
+    // Split tail calls into functions found after their callers were lifted
+    let mut discovered = Vec::new();
+    for (address, mut function) in functions {
+        let absorbs_entry = function.control_flow_graph()
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions().iter())
+            .filter_map(|instruction| instruction.address())
+            .any(|instruction_address| instruction_address != address
+                                       && entries.contains(&instruction_address));
+        if absorbs_entry {
+            function = translator.translate_function_with_entries(
+                &memory,
+                address,
+                &entries
+            )?;
+        }
+        let function_entry = names.remove(&address)
+                                  .unwrap_or(FunctionEntry::new(address, None));
+        function.set_name(Some(function_entry.name().to_string()));
+        discovered.push((function_entry, function));
+    }
+
+    Ok(discovered)
+}
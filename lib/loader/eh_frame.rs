@@ -0,0 +1,245 @@
+//! Parsing of `.eh_frame` sections for function entries.
+//!
+//! Every Frame Description Entry (FDE) in `.eh_frame` covers a range of code,
+//! which begins at the entry of a function. We only decode as much of each
+//! Common Information Entry (CIE) as is needed to find the encoding of these
+//! addresses.
+
+use error::*;
+use loader::Endian;
+use std::collections::BTreeMap;
+
+
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_ULEB128: u8 = 0x01;
+const DW_EH_PE_UDATA2: u8 = 0x02;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_UDATA8: u8 = 0x04;
+const DW_EH_PE_SLEB128: u8 = 0x09;
+const DW_EH_PE_SDATA2: u8 = 0x0a;
+const DW_EH_PE_SDATA4: u8 = 0x0b;
+const DW_EH_PE_SDATA8: u8 = 0x0c;
+const DW_EH_PE_PCREL: u8 = 0x10;
+
+
+/// A reader over the bytes of an `.eh_frame` section, which holds values in
+/// the endianness of its binary.
+struct Reader<'r> {
+    bytes: &'r [u8],
+    offset: usize,
+    endian: &'r Endian
+}
+
+
+impl<'r> Reader<'r> {
+    fn u8(&mut self) -> Result<u8> {
+        let byte = *self.bytes.get(self.offset).ok_or("Truncated .eh_frame")?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn unsigned(&mut self, bytes: usize) -> Result<u64> {
+        let mut value = 0;
+        for i in 0..bytes {
+            let byte = self.u8()? as u64;
+            value = match *self.endian {
+                Endian::Big => (value << 8) | byte,
+                Endian::Little => value | (byte << (i * 8))
+            };
+        }
+        Ok(value)
+    }
+
+    fn signed(&mut self, bytes: usize) -> Result<u64> {
+        let value = self.unsigned(bytes)?;
+        let bits = bytes * 8;
+        if bits < 64 && value & (1 << (bits - 1)) != 0 {
+            Ok(value | !((1 << bits) - 1))
+        }
+        else {
+            Ok(value)
+        }
+    }
+
+    fn uleb128(&mut self) -> Result<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> Result<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= !0 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>> {
+        let mut string = Vec::new();
+        loop {
+            match self.u8()? {
+                0 => return Ok(string),
+                byte => string.push(byte)
+            }
+        }
+    }
+
+    /// Read a pointer encoded with `encoding`, where the section is loaded at
+    /// `address`.
+    fn pointer(&mut self, encoding: u8, address: u64, address_bytes: usize)
+    -> Result<u64> {
+        let field_address = address.wrapping_add(self.offset as u64);
+        let value = match encoding & 0x0f {
+            DW_EH_PE_ABSPTR => self.unsigned(address_bytes)?,
+            DW_EH_PE_ULEB128 => self.uleb128()?,
+            DW_EH_PE_UDATA2 => self.unsigned(2)?,
+            DW_EH_PE_UDATA4 => self.unsigned(4)?,
+            DW_EH_PE_UDATA8 => self.unsigned(8)?,
+            DW_EH_PE_SLEB128 => self.sleb128()?,
+            DW_EH_PE_SDATA2 => self.signed(2)?,
+            DW_EH_PE_SDATA4 => self.signed(4)?,
+            DW_EH_PE_SDATA8 => self.signed(8)?,
+            _ => bail!("Unsupported pointer encoding 0x{:x}", encoding)
+        };
+        let value = match encoding & 0x70 {
+            0 => value,
+            DW_EH_PE_PCREL => field_address.wrapping_add(value),
+            _ => bail!("Unsupported pointer application 0x{:x}", encoding)
+        };
+        Ok(if address_bytes < 8 {
+            value & ((1 << (address_bytes * 8)) - 1)
+        }
+        else {
+            value
+        })
+    }
+}
+
+
+/// Parse the CIE at `offset`, and return the encoding of the addresses in the
+/// FDEs which use it.
+fn cie_encoding(
+    bytes: &[u8],
+    offset: usize,
+    address: u64,
+    address_bytes: usize,
+    endian: &Endian
+) -> Result<u8> {
+    let mut reader = Reader { bytes: bytes, offset: offset, endian: endian };
+    let length = reader.unsigned(4)?;
+    if length == 0xffffffff {
+        reader.unsigned(8)?;
+    }
+    let id = reader.unsigned(if length == 0xffffffff { 8 } else { 4 })?;
+    if id != 0 {
+        bail!("Expected CIE in .eh_frame at 0x{:x}", offset);
+    }
+
+    let version = reader.u8()?;
+    let augmentation = reader.string()?;
+    if augmentation.starts_with(b"eh") {
+        reader.unsigned(address_bytes)?;
+    }
+    // code alignment, data alignment and return address register
+    reader.uleb128()?;
+    reader.sleb128()?;
+    if version == 1 {
+        reader.u8()?;
+    }
+    else {
+        reader.uleb128()?;
+    }
+
+    if augmentation.first() != Some(&b'z') {
+        return Ok(DW_EH_PE_ABSPTR);
+    }
+    reader.uleb128()?;
+    for character in &augmentation[1..] {
+        match *character {
+            b'L' => { reader.u8()?; },
+            b'P' => {
+                let encoding = reader.u8()?;
+                reader.pointer(encoding & 0x7f, address, address_bytes)?;
+            },
+            b'R' => return Ok(reader.u8()?),
+            _ => {}
+        }
+    }
+
+    Ok(DW_EH_PE_ABSPTR)
+}
+
+
+/// Returns the first address covered by every FDE in the `.eh_frame` section
+/// held in `bytes`, which is loaded at `address`, in a binary with the given
+/// endianness.
+pub fn function_addresses(
+    bytes: &[u8],
+    address: u64,
+    address_bytes: usize,
+    endian: &Endian
+) -> Result<Vec<u64>> {
+    let mut addresses = Vec::new();
+    let mut encodings: BTreeMap<usize, u8> = BTreeMap::new();
+
+    let mut offset = 0;
+    while offset + 4 <= bytes.len() {
+        let mut reader = Reader { bytes: bytes, offset: offset, endian: endian };
+        let mut length = reader.unsigned(4)?;
+        if length == 0 {
+            break;
+        }
+        let id_bytes = if length == 0xffffffff {
+            length = reader.unsigned(8)?;
+            8
+        }
+        else {
+            4
+        };
+        let id_offset = reader.offset;
+        let next = id_offset + length as usize;
+
+        let id = reader.unsigned(id_bytes)?;
+        if id != 0 {
+            // An FDE, with a pointer back to its CIE
+            let cie = id_offset.checked_sub(id as usize)
+                               .ok_or("Invalid CIE pointer in .eh_frame")?;
+            let encoding = match encodings.get(&cie) {
+                Some(encoding) => *encoding,
+                None => cie_encoding(bytes, cie, address, address_bytes, endian)?
+            };
+            encodings.insert(cie, encoding);
+            if encoding != DW_EH_PE_OMIT {
+                let pc_begin = reader.pointer(encoding & 0x7f, address, address_bytes)?;
+                if pc_begin != 0 {
+                    addresses.push(pc_begin);
+                }
+            }
+        }
+
+        offset = next;
+    }
+
+    Ok(addresses)
+}
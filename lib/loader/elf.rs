@@ -28,6 +28,40 @@ const DEFAULT_LIB_BASE: u64 = 0x8000_0000;
 /// The step in address between where we will load libraries.
 const LIB_BASE_STEP: u64    = 0x0400_0000;
 
+// ARM, AArch64 and MIPS relocation types, which goblin does not provide
+const R_ARM_ABS32: u32 = 2;
+const R_ARM_COPY: u32 = 20;
+const R_ARM_GLOB_DAT: u32 = 21;
+const R_ARM_JUMP_SLOT: u32 = 22;
+const R_ARM_RELATIVE: u32 = 23;
+const R_AARCH64_ABS64: u32 = 257;
+const R_AARCH64_COPY: u32 = 1024;
+const R_AARCH64_GLOB_DAT: u32 = 1025;
+const R_AARCH64_JUMP_SLOT: u32 = 1026;
+const R_AARCH64_RELATIVE: u32 = 1027;
+const R_MIPS_NONE: u32 = 0;
+const R_MIPS_REL32: u32 = 3;
+const R_MIPS_COPY: u32 = 126;
+const R_MIPS_JUMP_SLOT: u32 = 127;
+
+
+/// Get a `u32` of the given endianness from memory.
+fn get_u32(memory: &Memory, address: u64, endian: &Endian) -> Option<u32> {
+    match *endian {
+        Endian::Big => memory.get_u32_be(address),
+        Endian::Little => memory.get_u32_le(address)
+    }
+}
+
+
+/// Set a `u32` of the given endianness in memory.
+fn set_u32(memory: &mut Memory, address: u64, value: u32, endian: &Endian) -> Result<()> {
+    match *endian {
+        Endian::Big => memory.set_u32_be(address, value),
+        Endian::Little => memory.set_u32_le(address, value)
+    }
+}
+
 
 /// Loads and links multiple ELFs together
 #[derive(Clone, Debug)]
@@ -133,6 +167,7 @@ impl ElfLinker {
         let ref elf = self.loaded[&filename];
         let dynsyms = elf.elf().dynsyms;
         let dynstrtab = elf.elf().dynstrtab;
+        let architecture = elf.architecture()?;
         for reloc in elf.elf()
                         .dynrelas
                         .iter()
@@ -142,6 +177,201 @@ impl ElfLinker {
                                   .chain(elf.elf()
                                             .pltrelocs
                                             .iter())) {
+            if let Architecture::Amd64 = architecture {
+                let address = reloc.r_offset as u64 + elf.base_address();
+                match reloc.r_type {
+                    goblin::elf::reloc::R_X86_64_64 => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_X86_64_64 {}:0x{:x}:{}", filename, reloc.r_offset, sym_name);
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => v.wrapping_add(reloc.r_addend as u64),
+                            None => bail!("Could not resolve symbol {}", sym_name)
+                        };
+                        self.memory.set_u64_le(address, value)?;
+                    },
+                    goblin::elf::reloc::R_X86_64_COPY => {
+                        bail!("R_X86_64_COPY");
+                    },
+                    goblin::elf::reloc::R_X86_64_GLOB_DAT |
+                    goblin::elf::reloc::R_X86_64_JUMP_SLOT => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_X86_64_GLOB_DAT/JUMP_SLOT {}:0x{:x}:{}",
+                            filename,
+                            reloc.r_offset,
+                            sym_name);
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => v.to_owned(),
+                            None => {
+                                warn!("Could not resolve symbol {}", sym_name);
+                                continue
+                            }
+                        };
+                        self.memory.set_u64_le(address, value)?;
+                    },
+                    goblin::elf::reloc::R_X86_64_RELATIVE => {
+                        trace!("R_X86_64_RELATIVE {}:{:x}", filename, reloc.r_offset);
+                        let value = elf.base_address().wrapping_add(reloc.r_addend as u64);
+                        self.memory.set_u64_le(address, value)?;
+                    },
+                    goblin::elf::reloc::R_X86_64_TPOFF64 => {
+                        warn!("Ignoring R_X86_64_TPOFF64 Relocation");
+                    },
+                    goblin::elf::reloc::R_X86_64_IRELATIVE => {
+                        warn!("R_X86_64_IRELATIVE {}:0x{:x} going unprocessed", filename, reloc.r_offset);
+                    },
+                    _ => bail!("unhandled relocation type {}", reloc.r_type)
+                }
+                continue;
+            }
+            if let Architecture::AArch64 = architecture {
+                let address = reloc.r_offset as u64 + elf.base_address();
+                match reloc.r_type {
+                    R_AARCH64_ABS64 => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_AARCH64_ABS64 {}:0x{:x}:{}", filename, reloc.r_offset, sym_name);
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => v.wrapping_add(reloc.r_addend as u64),
+                            None => bail!("Could not resolve symbol {}", sym_name)
+                        };
+                        self.memory.set_u64_le(address, value)?;
+                    },
+                    R_AARCH64_COPY => {
+                        bail!("R_AARCH64_COPY");
+                    },
+                    R_AARCH64_GLOB_DAT |
+                    R_AARCH64_JUMP_SLOT => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_AARCH64_GLOB_DAT/JUMP_SLOT {}:0x{:x}:{}",
+                            filename,
+                            reloc.r_offset,
+                            sym_name);
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => v.to_owned(),
+                            None => {
+                                warn!("Could not resolve symbol {}", sym_name);
+                                continue
+                            }
+                        };
+                        self.memory.set_u64_le(address, value)?;
+                    },
+                    R_AARCH64_RELATIVE => {
+                        trace!("R_AARCH64_RELATIVE {}:{:x}", filename, reloc.r_offset);
+                        let value = elf.base_address().wrapping_add(reloc.r_addend as u64);
+                        self.memory.set_u64_le(address, value)?;
+                    },
+                    _ => bail!("unhandled relocation type {}", reloc.r_type)
+                }
+                continue;
+            }
+            if let Architecture::Arm = architecture {
+                // ARM uses REL relocations, where the addend is the value
+                // already at the address relocated
+                let address = reloc.r_offset as u64 + elf.base_address();
+                match reloc.r_type {
+                    R_ARM_ABS32 => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_ARM_ABS32 {}:0x{:x}:{}", filename, reloc.r_offset, sym_name);
+                        let addend = self.memory.get_u32_le(address)
+                            .ok_or(format!("Invalid address for R_ARM_ABS32 {}:{:x}", filename, reloc.r_offset))?;
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => (*v as u32).wrapping_add(addend),
+                            None => bail!("Could not resolve symbol {}", sym_name)
+                        };
+                        self.memory.set_u32_le(address, value)?;
+                    },
+                    R_ARM_COPY => {
+                        bail!("R_ARM_COPY");
+                    },
+                    R_ARM_GLOB_DAT |
+                    R_ARM_JUMP_SLOT => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_ARM_GLOB_DAT/JUMP_SLOT {}:0x{:x}:{}",
+                            filename,
+                            reloc.r_offset,
+                            sym_name);
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => v.to_owned() as u32,
+                            None => {
+                                warn!("Could not resolve symbol {}", sym_name);
+                                continue
+                            }
+                        };
+                        self.memory.set_u32_le(address, value)?;
+                    },
+                    R_ARM_RELATIVE => {
+                        trace!("R_ARM_RELATIVE {}:{:x}", filename, reloc.r_offset);
+                        let value = match self.memory.get_u32_le(address) {
+                            Some(value) => (elf.base_address() as u32).wrapping_add(value),
+                            None => bail!("Invalid address for R_ARM_RELATIVE {}:{:x}",
+                                          filename,
+                                          reloc.r_offset)
+                        };
+                        self.memory.set_u32_le(address, value)?;
+                    },
+                    _ => bail!("unhandled relocation type {}", reloc.r_type)
+                }
+                continue;
+            }
+            let is_mips = match architecture {
+                Architecture::Mips | Architecture::Mipsel => true,
+                _ => false
+            };
+            if is_mips {
+                // MIPS uses REL relocations, where the addend is the value
+                // already at the address relocated
+                let endian = architecture.endian();
+                let address = reloc.r_offset as u64 + elf.base_address();
+                match reloc.r_type {
+                    R_MIPS_NONE => {},
+                    R_MIPS_REL32 => {
+                        let addend = get_u32(&self.memory, address, &endian)
+                            .ok_or(format!("Invalid address for R_MIPS_REL32 {}:{:x}", filename, reloc.r_offset))?;
+                        // relocations without a symbol are relative to the
+                        // base address
+                        let value = if reloc.r_sym == 0 {
+                            trace!("R_MIPS_REL32 {}:{:x}", filename, reloc.r_offset);
+                            (elf.base_address() as u32).wrapping_add(addend)
+                        }
+                        else {
+                            let ref sym = dynsyms[reloc.r_sym];
+                            let sym_name = dynstrtab.get(sym.st_name);
+                            trace!("R_MIPS_REL32 {}:0x{:x}:{}", filename, reloc.r_offset, sym_name);
+                            match self.symbols.get(sym_name) {
+                                Some(v) => (*v as u32).wrapping_add(addend),
+                                None => bail!("Could not resolve symbol {}", sym_name)
+                            }
+                        };
+                        set_u32(&mut self.memory, address, value, &endian)?;
+                    },
+                    R_MIPS_COPY => {
+                        bail!("R_MIPS_COPY");
+                    },
+                    R_MIPS_JUMP_SLOT => {
+                        let ref sym = dynsyms[reloc.r_sym];
+                        let sym_name = dynstrtab.get(sym.st_name);
+                        trace!("R_MIPS_JUMP_SLOT {}:0x{:x}:{}",
+                            filename,
+                            reloc.r_offset,
+                            sym_name);
+                        let value = match self.symbols.get(sym_name) {
+                            Some(v) => v.to_owned() as u32,
+                            None => {
+                                warn!("Could not resolve symbol {}", sym_name);
+                                continue
+                            }
+                        };
+                        set_u32(&mut self.memory, address, value, &endian)?;
+                    },
+                    _ => bail!("unhandled relocation type {}", reloc.r_type)
+                }
+                continue;
+            }
             match reloc.r_type {
                 goblin::elf::reloc::R_386_32 => {
                     let ref sym = dynsyms[reloc.r_sym];
@@ -257,6 +487,14 @@ impl Loader for ElfLinker {
         Ok(function_entries)
     }
 
+    fn unwind_function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        let mut function_entries = Vec::new();
+        for loaded in &self.loaded {
+            function_entries.append(&mut loaded.1.unwind_function_entries()?);
+        }
+        Ok(function_entries)
+    }
+
     // TODO Just maybe a bit too much unwrapping here.
     fn program_entry(&self) -> u64 {
         let filename = self.filename
@@ -540,6 +778,35 @@ impl Loader for Elf {
     }
 
 
+    fn unwind_function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        let elf = self.elf();
+
+        let address_bytes = if elf.is_64 { 8 } else { 4 };
+        let endian = if elf.little_endian { Endian::Little } else { Endian::Big };
+
+        let mut function_entries = Vec::new();
+        for section_header in &elf.section_headers {
+            if elf.shdr_strtab.get(section_header.sh_name) != ".eh_frame" {
+                continue;
+            }
+            let start = section_header.sh_offset as usize;
+            let end = start + section_header.sh_size as usize;
+            let bytes = self.bytes.get(start..end).ok_or("Malformed Elf")?;
+            let addresses = eh_frame::function_addresses(
+                bytes,
+                section_header.sh_addr + self.base_address,
+                address_bytes,
+                &endian
+            )?;
+            for address in addresses {
+                function_entries.push(FunctionEntry::new(address, None));
+            }
+        }
+
+        Ok(function_entries)
+    }
+
+
     fn program_entry(&self) -> u64 {
         self.elf().header.e_entry
     }
@@ -551,6 +818,23 @@ impl Loader for Elf {
         if elf.header.e_machine == goblin::elf::header::EM_386 {
             Ok(Architecture::X86)
         }
+        else if elf.header.e_machine == goblin::elf::header::EM_X86_64 {
+            Ok(Architecture::Amd64)
+        }
+        else if elf.header.e_machine == goblin::elf::header::EM_AARCH64 {
+            Ok(Architecture::AArch64)
+        }
+        else if elf.header.e_machine == goblin::elf::header::EM_ARM {
+            Ok(Architecture::Arm)
+        }
+        else if elf.header.e_machine == goblin::elf::header::EM_MIPS {
+            if elf.header.e_ident[goblin::elf::header::EI_DATA] == goblin::elf::header::ELFDATA2MSB {
+                Ok(Architecture::Mips)
+            }
+            else {
+                Ok(Architecture::Mipsel)
+            }
+        }
         else {
             Err("Unsupported Arcthiecture".into())
         }
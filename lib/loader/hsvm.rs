@@ -0,0 +1,71 @@
+//! Load a raw HSVM program image.
+//!
+//! An HSVM program is a flat image of its 64KiB of memory. The image is
+//! loaded at address 0, where execution begins.
+
+use error::*;
+use loader::*;
+use loader::memory::*;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+
+/// The size of HSVM memory in bytes.
+const MEMORY_SIZE: usize = 0x10000;
+
+
+/// A raw HSVM program image.
+#[derive(Clone, Debug)]
+pub struct Hsvm {
+    memory: Memory
+}
+
+
+impl Hsvm {
+    /// Create a new `Hsvm` loader from the bytes of a program image.
+    pub fn new(bytes: Vec<u8>) -> Result<Hsvm> {
+        if bytes.len() > MEMORY_SIZE {
+            bail!("HSVM image of 0x{:x} bytes is larger than memory", bytes.len());
+        }
+
+        let mut memory = Memory::new();
+        memory.add_segment(MemorySegment::new(0, bytes, ALL));
+
+        Ok(Hsvm {
+            memory: memory
+        })
+    }
+
+
+    /// Create a new `Hsvm` loader from the given file.
+    pub fn from_file(filename: &Path) -> Result<Hsvm> {
+        let mut file = File::open(filename)?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        Hsvm::new(buf)
+    }
+}
+
+
+
+impl Loader for Hsvm {
+    fn memory(&self) -> Result<Memory> {
+        Ok(self.memory.clone())
+    }
+
+
+    fn function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        Ok(vec![FunctionEntry::new(0, Some("entry".to_string()))])
+    }
+
+
+    fn program_entry(&self) -> u64 {
+        0
+    }
+
+
+    fn architecture(&self) -> Result<Architecture> {
+        Ok(Architecture::Hsvm)
+    }
+}
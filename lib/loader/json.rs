@@ -37,6 +37,24 @@ impl Json {
                 if architecture == "x86" {
                     Architecture::X86
                 }
+                else if architecture == "amd64" {
+                    Architecture::Amd64
+                }
+                else if architecture == "arm" {
+                    Architecture::Arm
+                }
+                else if architecture == "aarch64" {
+                    Architecture::AArch64
+                }
+                else if architecture == "hsvm" {
+                    Architecture::Hsvm
+                }
+                else if architecture == "mips" {
+                    Architecture::Mips
+                }
+                else if architecture == "mipsel" {
+                    Architecture::Mipsel
+                }
                 else {
                     bail!("unsupported architecture {}", root["arch"])
                 }
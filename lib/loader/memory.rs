@@ -199,6 +199,49 @@ impl Memory {
         Ok(())
     }
 
+    /// Get a big-endian `u32` from the given address.
+    pub fn get_u32_be(&self, address: u64) -> Option<u32> {
+        let mut result: u32 = 0;
+        for i in 0..4 {
+            match self.get_u8(address + i as u64) {
+                None => return None,
+                Some(u) => result |= (u as u32) << (((3 - i) * 8) as u32)
+            }
+        }
+        Some(result)
+    }
+
+    /// Set a big-endian `u32` at the given address.
+    pub fn set_u32_be(&mut self, address: u64, value: u32) -> Result<()> {
+        for i in 0..4 {
+            let value_u8: u8 = (value >> ((3 - i) * 8)) as u8;
+            self.set_u8(address + i as u64, value_u8)?;
+        }
+        Ok(())
+    }
+
+    /// Get a little-endian `u64` from the given address.
+    pub fn get_u64_le(&self, address: u64) -> Option<u64> {
+        let mut result: u64 = 0;
+        for i in 0..8 {
+            match self.get_u8(address + i as u64) {
+                None => return None,
+                Some(u) => result |= (u as u64) << ((i * 8) as u64)
+            }
+        }
+        Some(result)
+    }
+
+    /// Set a little-endian `u64` at the given address.
+    pub fn set_u64_le(&mut self, address: u64, mut value: u64) -> Result<()> {
+        for i in address..(address + 8 as u64) {
+            let value_u8: u8 = (value & 0xff) as u8;
+            value >>= 8;
+            self.set_u8(i, value_u8)?;
+        }
+        Ok(())
+    }
+
     /// Get a null-terminated string beginning at the given address
     pub fn get_str(&self, address: u64) -> Option<String> {
         if let Some(buf) = self.get(address) {
@@ -1,6 +1,9 @@
 //! Loading executable binaries into Falcon
 
+pub mod discovery;
+pub mod eh_frame;
 pub mod elf;
+pub mod hsvm;
 pub mod json;
 pub mod memory;
 
@@ -12,6 +15,12 @@ use std::fmt;
 /// An enum of architectures supported by the loader.
 #[derive(Clone, Debug)]
 pub enum Architecture {
+    AArch64,
+    Amd64,
+    Arm,
+    Hsvm,
+    Mips,
+    Mipsel,
     X86
 }
 
@@ -20,7 +29,13 @@ impl Architecture {
     /// Get the endiannes of an `Architecture`
     pub fn endian(&self) -> Endian {
         match *self {
-            Architecture::X86 => Endian::Little
+            Architecture::AArch64 |
+            Architecture::Amd64 |
+            Architecture::Arm |
+            Architecture::Mipsel |
+            Architecture::X86 => Endian::Little,
+            Architecture::Hsvm |
+            Architecture::Mips => Endian::Big
         }
     }
 }
@@ -98,6 +113,21 @@ pub trait Loader: ::std::fmt::Debug + Clone {
     /// Get addresses for known function entries
     fn function_entries(&self) -> Result<Vec<FunctionEntry>>;
 
+    /// Get addresses for function entries given by unwind information, such as
+    /// `.eh_frame`. By default, there are none.
+    fn unwind_function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        Ok(Vec::new())
+    }
+
+    /// Get addresses for all function entries found by recursive-descent
+    /// disassembly, as described in `loader::discovery`.
+    fn discover_function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        Ok(discovery::discover(self)?
+            .into_iter()
+            .map(|(function_entry, _)| function_entry)
+            .collect())
+    }
+
     /// The address program execution should begin at
     fn program_entry(&self) -> u64;
 
@@ -108,6 +138,12 @@ pub trait Loader: ::std::fmt::Debug + Clone {
     fn translator(&self) -> Result<Box<translator::Arch>> {
         match self.architecture() {
             Ok(arch) => match arch {
+                Architecture::AArch64 => Ok(Box::new(translator::aarch64::AArch64::new())),
+                Architecture::Amd64 => Ok(Box::new(translator::x86::Amd64::new())),
+                Architecture::Arm => Ok(Box::new(translator::arm::Arm::new())),
+                Architecture::Hsvm => Ok(Box::new(translator::hsvm::Hsvm::new())),
+                Architecture::Mips => Ok(Box::new(translator::mips::Mips::new())),
+                Architecture::Mipsel => Ok(Box::new(translator::mips::Mipsel::new())),
                 Architecture::X86 => Ok(Box::new(translator::x86::X86::new()))
             },
             Err(_) => bail!("Unsupported Architecture")
@@ -141,4 +177,20 @@ pub trait Loader: ::std::fmt::Debug + Clone {
 
         Ok(program)
     }
+
+    /// Lift executable into an il::Program, using `loader::discovery` to find
+    /// functions.
+    ///
+    /// All functions found by `discover_function_entries` are lifted, which
+    /// allows stripped binaries to be lifted.
+    fn to_program_discovered(&self) -> Result<il::Program> {
+        let mut program = il::Program::new();
+
+        for (function_entry, function) in discovery::discover(self)? {
+            trace!("adding function at {:X}", function_entry.address());
+            program.add_function(function);
+        }
+
+        Ok(program)
+    }
 }
\ No newline at end of file
@@ -38,6 +38,11 @@ impl Linux {
         fd
     }
 
+    /// Get the offset of the next byte to be read from a file descriptor.
+    pub fn offset(&self, fd: i32) -> Option<u64> {
+        self.files.get(&fd).map(|file| file.file_descriptor.offset)
+    }
+
     /// Read from an open file descriptor.
     pub fn read(&mut self, fd: i32, mut length: u64) -> (i32, Vec<il::Scalar>) {
         if let Some(file) = self.files.get_mut(&fd) {
@@ -1,11 +1,12 @@
 //! An x86 (i386) specific model for Linux.
 
 use engine::engine::*;
-use executor;
+use executor::{self, TaintLabel};
 use error::*;
 use il;
 use platform::linux;
 use platform::Platform;
+use std::collections::{BTreeMap, BTreeSet};
 
 
 
@@ -13,6 +14,14 @@ const SYS_READ:  u32 = 3;
 // const SYS_WRITE: u32 = 4;
 // const SYS_OPEN:  u32 = 5;
 // const SYS_CLOSE: u32 = 6;
+const SYS_SET_THREAD_AREA: u32 = 243;
+
+const EINVAL: u32 = 22;
+const ESRCH: u32 = 3;
+
+// The GDT entries available for thread local storage
+const GDT_ENTRY_TLS_MIN: u32 = 6;
+const GDT_ENTRY_TLS_ENTRIES: u32 = 3;
 
 const STACK_ADDRESS: u64 = 0xb000_0000;
 const STACK_SIZE: u64 = 0x0001_0000;
@@ -23,6 +32,15 @@ const FS_SIZE: u64 = 0x0000_8000;
 const GS_BASE: u64 = 0xbf00_8000;
 const GS_SIZE: u64 = 0x0000_8000;
 
+// cpuid answers as a family 6 Intel processor with the features we lift:
+// fpu, tsc, cx8, cmov, sse and sse2 in edx, and movbe and popcnt in ecx.
+const CPUID_SIGNATURE: u32 = 0x0000_06fb;
+const CPUID_FEATURES_ECX: u32 = 0x00c0_0000;
+const CPUID_FEATURES_EDX: u32 = 0x0600_8111;
+
+// How far the time-stamp counter advances each time it is read
+const TSC_INCREMENT: u64 = 0x1000;
+
 const KERNEL_VSYSCALL_BYTES: &'static [u8] = &[0x0f, 0x34, 0xc3];
 const KERNEL_VSYSCALL_ADDRESS: u64 = 0xbfff_0000;
 
@@ -30,7 +48,11 @@ const KERNEL_VSYSCALL_ADDRESS: u64 = 0xbfff_0000;
 /// An x86 (i386) specific model for Linux.
 #[derive(Clone)]
 pub struct LinuxX86 {
-    linux: linux::Linux
+    linux: linux::Linux,
+    // GDT entries set by set_thread_area, and their base addresses
+    thread_areas: BTreeMap<u32, u64>,
+    // The time-stamp counter read by rdtsc
+    tsc: u64
 }
 
 
@@ -38,7 +60,9 @@ impl LinuxX86 {
     /// Create a new `LinuxX86`.
     pub fn new() -> LinuxX86 {
         LinuxX86 {
-            linux: linux::Linux::new()
+            linux: linux::Linux::new(),
+            thread_areas: BTreeMap::new(),
+            tsc: 0
         }
     }
 
@@ -68,6 +92,71 @@ impl LinuxX86 {
     }
 
 
+    fn load_u32(&self, engine: &SymbolicEngine, address: u64) -> Result<u32> {
+        let value = match engine.memory().load(address, 32)? {
+            Some(value) => value,
+            None => bail!("Could not load value at 0x{:x}", address)
+        };
+
+        match engine.symbolize_and_concretize(&value, None)? {
+            Some(value) => Ok(value.value() as u32),
+            None => bail!("Could not get concrete value at 0x{:x}", address)
+        }
+    }
+
+
+    /// Sets the base of `segment`, fs or gs, after it is loaded with a
+    /// selector for a GDT entry set by set_thread_area. Other selectors leave
+    /// the base as it was.
+    fn load_segment(self, mut engine: SymbolicEngine, segment: &str) -> Result<Vec<(LinuxX86, SymbolicEngine)>> {
+        let selector = match engine.get_scalar_only_concrete(segment)? {
+            Some(selector) => selector.value(),
+            None => bail!("Could not get concrete {}", segment)
+        };
+
+        // The low three bits of a selector are its privilege level and table
+        if let Some(base_address) = self.thread_areas.get(&((selector >> 3) as u32)) {
+            engine.set_scalar(format!("{}_base", segment), il::expr_const(*base_address, 32));
+        }
+
+        Ok(vec![(self, engine)])
+    }
+
+
+    /// Answers a cpuid for the leaf in eax.
+    fn cpuid(self, mut engine: SymbolicEngine) -> Result<Vec<(LinuxX86, SymbolicEngine)>> {
+        let leaf = match engine.get_scalar_only_concrete("eax")? {
+            Some(eax) => eax.value() as u32,
+            None => bail!("Could not get concrete eax")
+        };
+
+        let (eax, ebx, ecx, edx) = match leaf {
+            // The highest leaf we answer, and "GenuineIntel"
+            0 => (1, 0x756e_6547, 0x6c65_746e, 0x4965_6e69),
+            1 => (CPUID_SIGNATURE, 0, CPUID_FEATURES_ECX, CPUID_FEATURES_EDX),
+            _ => (0, 0, 0, 0)
+        };
+
+        engine.set_scalar("eax", il::expr_const(eax as u64, 32));
+        engine.set_scalar("ebx", il::expr_const(ebx as u64, 32));
+        engine.set_scalar("ecx", il::expr_const(ecx as u64, 32));
+        engine.set_scalar("edx", il::expr_const(edx as u64, 32));
+
+        Ok(vec![(self, engine)])
+    }
+
+
+    /// Answers an rdtsc with a counter which advances each time it is read.
+    fn rdtsc(mut self, mut engine: SymbolicEngine) -> Result<Vec<(LinuxX86, SymbolicEngine)>> {
+        self.tsc += TSC_INCREMENT;
+
+        engine.set_scalar("eax", il::expr_const(self.tsc & 0xffff_ffff, 32));
+        engine.set_scalar("edx", il::expr_const(self.tsc >> 32, 32));
+
+        Ok(vec![(self, engine)])
+    }
+
+
     fn initialize_stack(&self, engine: &mut SymbolicEngine)
         -> Result<()> {
 
@@ -170,6 +259,20 @@ impl LinuxX86 {
 
         engine.set_scalar("DF", il::expr_const(0, 1));
 
+        // Interrupts are enabled in user mode, and the ID flag starts clear.
+        engine.set_scalar("IF", il::expr_const(1, 1));
+        engine.set_scalar("ID", il::expr_const(0, 1));
+
+        // The x87 state after finit: an empty register stack, all exceptions
+        // masked, double precision and round to nearest.
+        engine.set_scalar("fpu_cw", il::expr_const(0x37f, 16));
+        engine.set_scalar("TOP", il::expr_const(0, 3));
+
+        // The SSE registers are clear at process start.
+        for i in 0..8 {
+            engine.set_scalar(format!("xmm{}", i), il::expr_const(0, 128));
+        }
+
         /* SVR4/i386 ABI (pages 3-31, 3-32) says that when the program
         starts %edx contains a pointer to a function
         which might be registered using atexit.
@@ -211,8 +314,13 @@ impl Platform<LinuxX86> for LinuxX86 {
     -> Result<Vec<(LinuxX86, SymbolicEngine)>> {
 
         match *expression {
-            il::Expression::Scalar(ref scalar) => if scalar.name() != "sysenter" {
-                bail!("Not a sysenter raise for LinuxX86")
+            il::Expression::Scalar(ref scalar) => match scalar.name() {
+                "cpuid" => return self.cpuid(engine),
+                "load_fs" => return self.load_segment(engine, "fs"),
+                "load_gs" => return self.load_segment(engine, "gs"),
+                "rdtsc" => return self.rdtsc(engine),
+                "sysenter" => {},
+                _ => bail!("Unhandled raise {} for LinuxX86", scalar.name())
             },
             _ => bail!("Raise not a scalar for LinuxX86")
         }
@@ -259,16 +367,68 @@ impl Platform<LinuxX86> for LinuxX86 {
                 }
 
                 // Get variables for the data we're about to read
-                let (result, read) = self.linux.read(fd.value() as i32, length.value());
+                let fd = fd.value() as i32;
+                let offset = self.linux.offset(fd);
+                let (result, read) = self.linux.read(fd, length.value());
 
                 for i in 0..read.len() as u64 {
                     engine.memory_mut().store(address.value() + i, read[i as usize].to_owned().into())?;
                 }
 
+                // Label each byte read with its file descriptor and offset
+                if let (Some(taint), Some(offset)) = (engine.taint_mut(), offset) {
+                    for i in 0..read.len() as u64 {
+                        let mut labels = BTreeSet::new();
+                        labels.insert(TaintLabel::new(fd, offset + i));
+                        taint.set_byte(address.value() + i, labels);
+                    }
+                }
+
                 engine.set_scalar("eax", il::expr_const(result as u64, 32));
 
                 Ok(vec![(self, engine)])
             }
+            SYS_SET_THREAD_AREA => {
+                info!("SYS_SET_THREAD_AREA");
+                // ebx points to a struct user_desc, which begins with the
+                // entry number and base address of the segment
+                let u_info = match engine.get_scalar_only_concrete("ebx")? {
+                    Some(ebx) => ebx.value(),
+                    None => bail!("Could not get concrete ebx")
+                };
+
+                let entry_number = self.load_u32(&engine, u_info)?;
+                let base_address = self.load_u32(&engine, u_info + 4)? as u64;
+
+                // An entry number of -1 asks for a free entry
+                let entry_number = if entry_number == 0xffff_ffff {
+                    (GDT_ENTRY_TLS_MIN..(GDT_ENTRY_TLS_MIN + GDT_ENTRY_TLS_ENTRIES))
+                        .find(|entry| !self.thread_areas.contains_key(entry))
+                }
+                else if entry_number >= GDT_ENTRY_TLS_MIN &&
+                        entry_number < GDT_ENTRY_TLS_MIN + GDT_ENTRY_TLS_ENTRIES {
+                    Some(entry_number)
+                }
+                else {
+                    engine.set_scalar("eax", il::expr_const(EINVAL.wrapping_neg() as u64, 32));
+                    return Ok(vec![(self, engine)]);
+                };
+
+                let entry_number = match entry_number {
+                    Some(entry_number) => entry_number,
+                    None => {
+                        engine.set_scalar("eax", il::expr_const(ESRCH.wrapping_neg() as u64, 32));
+                        return Ok(vec![(self, engine)]);
+                    }
+                };
+
+                self.thread_areas.insert(entry_number, base_address);
+                engine.memory_mut().store(u_info, il::expr_const(entry_number as u64, 32))?;
+
+                engine.set_scalar("eax", il::expr_const(0, 32));
+
+                Ok(vec![(self, engine)])
+            }
             _ => bail!("Unhandled system call {}", eax.value())
         }
     }
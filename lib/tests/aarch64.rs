@@ -0,0 +1,147 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use tests::{load, run, value};
+#[cfg(test)]use translator::aarch64::AArch64;
+
+
+#[cfg(test)]
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new(Endian::Little);
+    for i in 0..5 {
+        interpreter.set_scalar(format!("x{}", i), il::const_(0x1111_1111_0000_0000 * (i + 1), 64));
+    }
+    interpreter.set_scalar("sp", il::const_(0x8000, 64));
+    for &flag in &["N", "Z", "C", "V"] {
+        interpreter.set_scalar(flag, il::const_(0, 1));
+    }
+    interpreter
+}
+
+
+#[test]
+fn w_register_test() {
+    // add w0, w1, w2 writes the sum of the low halves and zeroes the top
+    let mut interpreter = interpreter();
+    interpreter.set_scalar("x1", il::const_(0xffff_ffff_ffff_fffe, 64));
+    interpreter.set_scalar("x2", il::const_(0x1234_5678_0000_0003, 64));
+    run(&AArch64::new(), &[0x20, 0x00, 0x02, 0x0b], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "x0"), 1);
+
+    // mov w0, w1
+    run(&AArch64::new(), &[0xe0, 0x03, 0x01, 0x2a], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "x0"), 0xffff_fffe);
+
+    // ldr w0, [x1] zero extends the word loaded
+    interpreter.set_scalar("x1", il::const_(0x4000, 64));
+    interpreter.store(0x4000, &il::const_(0xffff_ffff_ffff_ffff, 64)).unwrap();
+    run(&AArch64::new(), &[0x20, 0x00, 0x40, 0xb9], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "x0"), 0xffff_ffff);
+
+    // add x0, x1, x2 keeps all 64 bits
+    run(&AArch64::new(), &[0x20, 0x00, 0x02, 0x8b], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "x0"), 0x1234_5678_0000_4003);
+}
+
+
+#[test]
+fn pair_test() {
+    // stp x1, x2, [sp, #-16]! stores below sp, then writes back
+    let mut interpreter = interpreter();
+    run(&AArch64::new(), &[0xe1, 0x0b, 0xbf, 0xa9], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "sp"), 0x7ff0);
+    assert_eq!(load(&interpreter, 0x7ff0, 64), 0x2222_2222_0000_0000);
+    assert_eq!(load(&interpreter, 0x7ff8, 64), 0x3333_3333_0000_0000);
+
+    // ldp x3, x4, [sp], #16 loads at sp, then writes back
+    run(&AArch64::new(), &[0xe3, 0x13, 0xc1, 0xa8], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "sp"), 0x8000);
+    assert_eq!(value(&interpreter, "x3"), 0x2222_2222_0000_0000);
+    assert_eq!(value(&interpreter, "x4"), 0x3333_3333_0000_0000);
+
+    // stp x1, x2, [sp, #16] leaves sp alone
+    run(&AArch64::new(), &[0xe1, 0x0b, 0x01, 0xa9], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "sp"), 0x8000);
+    assert_eq!(load(&interpreter, 0x8010, 64), 0x2222_2222_0000_0000);
+    assert_eq!(load(&interpreter, 0x8018, 64), 0x3333_3333_0000_0000);
+}
+
+
+#[test]
+fn conditional_test() {
+    for &(x0, x1, x2) in &[(5, 5, 7), (5, 5, 5), (5, 6, 6)] {
+        let mut interpreter = interpreter();
+        interpreter.set_scalar("x0", il::const_(x0, 64));
+        interpreter.set_scalar("x1", il::const_(x1, 64));
+        interpreter.set_scalar("x2", il::const_(x2, 64));
+
+        // cmp x0, x1; ccmp x0, x2, #4, eq
+        run(&AArch64::new(), &[0x1f, 0x00, 0x01, 0xeb, 0x04, 0x00, 0x42, 0xfa], 0x1000, &mut interpreter);
+
+        // When x0 == x1 the flags come from comparing x0 with x2, and
+        // otherwise are set to nzcv, Z only
+        let z = if x0 == x1 { x0 == x2 } else { true };
+        assert_eq!(value(&interpreter, "Z"), z as u64);
+        assert_eq!(value(&interpreter, "C"), (x0 == x1 && x0 >= x2) as u64);
+
+        // csel x3, x1, x2, eq
+        run(&AArch64::new(), &[0x23, 0x00, 0x82, 0x9a], 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "x3"), if z { x1 } else { x2 });
+    }
+}
+
+
+#[test]
+fn compare_branch_test() {
+    // cbz x0, 0x1008
+    let bytes = [0x40, 0x00, 0x00, 0xb4];
+    let mut interpreter = interpreter();
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1004]);
+    interpreter.set_scalar("x0", il::const_(0, 64));
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+
+    // cbz w0, 0x1008 only tests the low half
+    let bytes = [0x40, 0x00, 0x00, 0x34];
+    interpreter.set_scalar("x0", il::const_(0xffff_ffff_0000_0000, 64));
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+
+    // tbz w0, #3, 0x1008
+    let bytes = [0x40, 0x00, 0x18, 0x36];
+    interpreter.set_scalar("x0", il::const_(8, 64));
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1004]);
+    interpreter.set_scalar("x0", il::const_(7, 64));
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+
+    // tbnz x0, #40, 0x1008
+    let bytes = [0x40, 0x00, 0x40, 0xb7];
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1004]);
+    interpreter.set_scalar("x0", il::const_(1 << 40, 64));
+    assert_eq!(run(&AArch64::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+}
+
+
+#[test]
+fn division_test() {
+    // sdiv x0, x1, x2; udiv x0, x1, x2; sdiv w0, w1, w2
+    let sdiv = [0x20, 0x0c, 0xc2, 0x9a];
+    let udiv = [0x20, 0x08, 0xc2, 0x9a];
+    let sdiv_w = [0x20, 0x0c, 0xc2, 0x1a];
+
+    let cases = [
+        (&sdiv, 0xffff_ffff_ffff_fff9, 2, 0xffff_ffff_ffff_fffd),
+        (&sdiv, 0x8000_0000_0000_0000, 0xffff_ffff_ffff_ffff, 0x8000_0000_0000_0000),
+        (&sdiv, 7, 0, 0),
+        (&udiv, 0xffff_ffff_ffff_fff9, 2, 0x7fff_ffff_ffff_fffc),
+        (&udiv, 7, 0, 0),
+        (&sdiv_w, 0xffff_fff9, 2, 0xffff_fffd),
+        (&sdiv_w, 7, 0xffff_ffff_0000_0000, 0)
+    ];
+
+    for &(bytes, lhs, rhs, quotient) in cases.iter() {
+        let mut interpreter = interpreter();
+        interpreter.set_scalar("x1", il::const_(lhs, 64));
+        interpreter.set_scalar("x2", il::const_(rhs, 64));
+        run(&AArch64::new(), bytes, 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "x0"), quotient, "0x{:x} / 0x{:x}", lhs, rhs);
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use tests::{load, run, value};
+#[cfg(test)]use translator::x86::Amd64;
+
+
+#[cfg(test)]
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new(Endian::Little);
+    for &name in &["rax", "rbx", "rcx", "rdx"] {
+        interpreter.set_scalar(name, il::const_(0xffff_ffff_ffff_ffff, 64));
+    }
+    interpreter.set_scalar("rsp", il::const_(0x8000, 64));
+    for &flag in &["CF", "PF", "AF", "ZF", "SF", "IF", "DF", "OF", "ID"] {
+        interpreter.set_scalar(flag, il::const_(0, 1));
+    }
+    interpreter
+}
+
+
+#[test]
+fn rip_relative_test() {
+    // mov rax, [rip + 0x10], reading 0x1017
+    let mut interpreter = interpreter();
+    interpreter.store(0x1017, &il::const_(0x1122_3344_5566_7788, 64)).unwrap();
+    run(&Amd64::new(), &[0x48, 0x8b, 0x05, 0x10, 0x00, 0x00, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 0x1122_3344_5566_7788);
+
+    // lea rbx, [rip - 0x10]
+    let mut interpreter = self::interpreter();
+    run(&Amd64::new(), &[0x48, 0x8d, 0x1d, 0xf0, 0xff, 0xff, 0xff], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rbx"), 0x0ff7);
+}
+
+
+#[test]
+fn zero_extension_test() {
+    // mov eax, 1
+    let mut interpreter = interpreter();
+    run(&Amd64::new(), &[0xb8, 0x01, 0x00, 0x00, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 1);
+
+    // add eax, ebx
+    let mut interpreter = self::interpreter();
+    run(&Amd64::new(), &[0x01, 0xd8], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 0xffff_fffe);
+
+    // mov ax, 1 leaves the upper bits alone
+    let mut interpreter = self::interpreter();
+    run(&Amd64::new(), &[0x66, 0xb8, 0x01, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 0xffff_ffff_ffff_0001);
+
+    // cmove eax, ebx zero extends eax whether or not it moves
+    for &zf in &[0, 1] {
+        let mut interpreter = self::interpreter();
+        interpreter.set_scalar("rax", il::const_(0xaaaa_aaaa_0000_0001, 64));
+        interpreter.set_scalar("rbx", il::const_(0xbbbb_bbbb_0000_0002, 64));
+        interpreter.set_scalar("ZF", il::const_(zf, 1));
+        run(&Amd64::new(), &[0x0f, 0x44, 0xc3], 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "rax"), if zf == 1 { 2 } else { 1 });
+    }
+
+    // cmove rax, rbx leaves rax alone if it does not move
+    let mut interpreter = self::interpreter();
+    interpreter.set_scalar("rax", il::const_(0xaaaa_aaaa_0000_0001, 64));
+    run(&Amd64::new(), &[0x48, 0x0f, 0x44, 0xc3], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 0xaaaa_aaaa_0000_0001);
+}
+
+
+#[test]
+fn push_pop_test() {
+    // push rbx; pop rcx
+    let mut interpreter = interpreter();
+    interpreter.set_scalar("rbx", il::const_(0x1122_3344_5566_7788, 64));
+    run(&Amd64::new(), &[0x53], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rsp"), 0x7ff8);
+    assert_eq!(load(&interpreter, 0x7ff8, 64), 0x1122_3344_5566_7788);
+    run(&Amd64::new(), &[0x59], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rsp"), 0x8000);
+    assert_eq!(value(&interpreter, "rcx"), 0x1122_3344_5566_7788);
+
+    // push -1 pushes a sign extended 64-bit immediate
+    let mut interpreter = self::interpreter();
+    run(&Amd64::new(), &[0x6a, 0xff], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rsp"), 0x7ff8);
+    assert_eq!(load(&interpreter, 0x7ff8, 64), 0xffff_ffff_ffff_ffff);
+
+    // push bx pushes 16 bits
+    let mut interpreter = self::interpreter();
+    run(&Amd64::new(), &[0x66, 0x53], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rsp"), 0x7ffe);
+    assert_eq!(load(&interpreter, 0x7ffe, 16), 0xffff);
+}
+
+
+#[test]
+fn sign_extension_test() {
+    // cqo
+    let mut interpreter = interpreter();
+    interpreter.set_scalar("rax", il::const_(0x8000_0000_0000_0000, 64));
+    interpreter.set_scalar("rdx", il::const_(0, 64));
+    run(&Amd64::new(), &[0x48, 0x99], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rdx"), 0xffff_ffff_ffff_ffff);
+
+    interpreter.set_scalar("rax", il::const_(0x7fff_ffff_ffff_ffff, 64));
+    run(&Amd64::new(), &[0x48, 0x99], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rdx"), 0);
+
+    // cdqe
+    interpreter.set_scalar("rax", il::const_(0x1234_5678_8000_0000, 64));
+    run(&Amd64::new(), &[0x48, 0x98], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 0xffff_ffff_8000_0000);
+
+    interpreter.set_scalar("rax", il::const_(0x1234_5678_7fff_ffff, 64));
+    run(&Amd64::new(), &[0x48, 0x98], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rax"), 0x7fff_ffff);
+}
+
+
+#[test]
+fn syscall_test() {
+    let mut interpreter = interpreter();
+    interpreter.set_scalar("CF", il::const_(1, 1));
+    interpreter.set_scalar("ZF", il::const_(1, 1));
+    run(&Amd64::new(), &[0x0f, 0x05], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "rcx"), 0x1002);
+    assert_eq!(value(&interpreter, "r11"), 0x43);
+}
@@ -0,0 +1,185 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use tests::{load, run, value};
+#[cfg(test)]use translator::Arch;
+#[cfg(test)]use translator::arm::Arm;
+
+
+#[cfg(test)]
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new(Endian::Little);
+    for (i, &name) in ["r0", "r1", "r2", "r3"].iter().enumerate() {
+        interpreter.set_scalar(name, il::const_(0x100 * (i as u64 + 1), 32));
+    }
+    interpreter.set_scalar("sp", il::const_(0x8000, 32));
+    for &flag in &["N", "Z", "C", "V"] {
+        interpreter.set_scalar(flag, il::const_(0, 1));
+    }
+    interpreter
+}
+
+
+#[test]
+fn conditional_execution_test() {
+    for &z in &[0, 1] {
+        // addeq r0, r1, r2
+        let mut interpreter = interpreter();
+        interpreter.set_scalar("Z", il::const_(z, 1));
+        run(&Arm::new(), &[0x02, 0x00, 0x81, 0x00], 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "r0"), if z == 1 { 0x500 } else { 0x100 });
+
+        // movne r0, #1
+        let mut interpreter = self::interpreter();
+        interpreter.set_scalar("Z", il::const_(z, 1));
+        run(&Arm::new(), &[0x01, 0x00, 0xa0, 0x13], 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "r0"), if z == 1 { 0x100 } else { 1 });
+    }
+
+    // In Thumb, ite eq; moveq r0, #1; movne r0, #2, where the moves do not
+    // set flags inside the it block
+    for &z in &[0, 1] {
+        let mut interpreter = interpreter();
+        interpreter.set_scalar("Z", il::const_(z, 1));
+        run(&Arm::new(), &[0x0c, 0xbf, 0x01, 0x20, 0x02, 0x20], 0x2001, &mut interpreter);
+        assert_eq!(value(&interpreter, "r0"), if z == 1 { 1 } else { 2 });
+        assert_eq!(value(&interpreter, "Z"), z);
+    }
+
+    // movs r0, #0 after the it block sets flags again
+    let mut interpreter = interpreter();
+    run(&Arm::new(), &[0x08, 0xbf, 0x01, 0x20, 0x00, 0x20], 0x2001, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0);
+    assert_eq!(value(&interpreter, "Z"), 1);
+}
+
+
+#[test]
+fn barrel_shifter_test() {
+    // add r0, r1, r2, lsl #2
+    let mut interpreter = interpreter();
+    run(&Arm::new(), &[0x02, 0x01, 0x81, 0xe0], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0x200 + (0x300 << 2));
+
+    // add r0, r1, r2, lsl r3, shifting by the low byte of r3
+    let mut interpreter = self::interpreter();
+    interpreter.set_scalar("r3", il::const_(0x104, 32));
+    run(&Arm::new(), &[0x12, 0x03, 0x81, 0xe0], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0x200 + (0x300 << 4));
+
+    // movs r0, r1, lsr #1 sets C to the last bit shifted out
+    let mut interpreter = self::interpreter();
+    interpreter.set_scalar("r1", il::const_(0x8000_0003, 32));
+    run(&Arm::new(), &[0xa1, 0x00, 0xb0, 0xe1], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0x4000_0001);
+    assert_eq!(value(&interpreter, "C"), 1);
+    assert_eq!(value(&interpreter, "N"), 0);
+
+    // mov r0, r1, asr #31
+    run(&Arm::new(), &[0xc1, 0x0f, 0xa0, 0xe1], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0xffff_ffff);
+
+    // mov r0, r1, ror #8
+    run(&Arm::new(), &[0x61, 0x04, 0xa0, 0xe1], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0x0380_0000);
+}
+
+
+#[test]
+fn load_store_multiple_test() {
+    // push {r1, r2}, as stmdb sp!, {r1, r2}
+    let mut interpreter = interpreter();
+    run(&Arm::new(), &[0x06, 0x00, 0x2d, 0xe9], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "sp"), 0x7ff8);
+    assert_eq!(load(&interpreter, 0x7ff8, 32), 0x200);
+    assert_eq!(load(&interpreter, 0x7ffc, 32), 0x300);
+
+    // ldmia r0!, {r1, r2}
+    interpreter.set_scalar("r0", il::const_(0x7ff8, 32));
+    interpreter.set_scalar("r1", il::const_(0, 32));
+    interpreter.set_scalar("r2", il::const_(0, 32));
+    run(&Arm::new(), &[0x06, 0x00, 0xb0, 0xe8], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0x8000);
+    assert_eq!(value(&interpreter, "r1"), 0x200);
+    assert_eq!(value(&interpreter, "r2"), 0x300);
+
+    // ldmia r0, {r1, r2} leaves r0 alone
+    interpreter.set_scalar("r0", il::const_(0x7ff8, 32));
+    run(&Arm::new(), &[0x06, 0x00, 0x90, 0xe8], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0x7ff8);
+
+    // pop {r4, pc} branches to the value popped
+    let mut interpreter = self::interpreter();
+    interpreter.set_scalar("sp", il::const_(0x7ff8, 32));
+    interpreter.store(0x7ff8, &il::const_(0x44, 32)).unwrap();
+    interpreter.store(0x7ffc, &il::const_(0x3001, 32)).unwrap();
+    let target = run(&Arm::new(), &[0x10, 0x80, 0xbd, 0xe8], 0x1000, &mut interpreter);
+    assert_eq!(target, vec![0x3001]);
+    assert_eq!(value(&interpreter, "r4"), 0x44);
+    assert_eq!(value(&interpreter, "sp"), 0x8000);
+}
+
+
+#[test]
+fn pc_relative_test() {
+    // ldr r0, [pc, #8] at 0x1000 reads 0x1000 + 8 + 8
+    let mut interpreter = interpreter();
+    interpreter.store(0x1010, &il::const_(0xdead_beef, 32)).unwrap();
+    run(&Arm::new(), &[0x08, 0x00, 0x9f, 0xe5], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0xdead_beef);
+
+    // ldr r0, [pc, #4] in Thumb at 0x2002 reads from the aligned pc, 0x2004,
+    // plus 4
+    let mut interpreter = self::interpreter();
+    interpreter.store(0x2008, &il::const_(0xcafe_f00d, 32)).unwrap();
+    run(&Arm::new(), &[0x01, 0x48], 0x2003, &mut interpreter);
+    assert_eq!(value(&interpreter, "r0"), 0xcafe_f00d);
+}
+
+
+#[test]
+fn thumb_successors_test() {
+    // movs r0, #1; b.n to 0x200a
+    let result = Arm::new().translate_block(&[0x01, 0x20, 0x02, 0xe0], 0x2001).unwrap();
+    let successors = result.successors().iter().map(|s| s.0).collect::<Vec<u64>>();
+    assert_eq!(successors, vec![0x200b]);
+
+    // beq.n to 0x2006, falling through to 0x2002
+    let result = Arm::new().translate_block(&[0x01, 0xd0], 0x2001).unwrap();
+    let successors = result.successors().iter().map(|s| s.0).collect::<Vec<u64>>();
+    assert_eq!(successors, vec![0x2003, 0x2007]);
+
+    // The same branch in ARM mode, beq to 0x100c
+    let result = Arm::new().translate_block(&[0x01, 0x00, 0x00, 0x0a], 0x1000).unwrap();
+    let successors = result.successors().iter().map(|s| s.0).collect::<Vec<u64>>();
+    assert_eq!(successors, vec![0x1004, 0x100c]);
+
+    // mov pc, r0 in Thumb stays in Thumb
+    let mut interpreter = interpreter();
+    interpreter.set_scalar("r0", il::const_(0x3000, 32));
+    assert_eq!(run(&Arm::new(), &[0x87, 0x46], 0x2001, &mut interpreter), vec![0x3001]);
+}
+
+
+#[test]
+fn division_test() {
+    // sdiv r0, r1, r2; udiv r0, r1, r2
+    let sdiv = [0x11, 0xf2, 0x10, 0xe7];
+    let udiv = [0x11, 0xf2, 0x30, 0xe7];
+
+    let cases = [
+        (&sdiv, 0xffff_fff9, 2, 0xffff_fffd),
+        (&sdiv, 0x8000_0000, 0xffff_ffff, 0x8000_0000),
+        (&sdiv, 7, 0, 0),
+        (&udiv, 0xffff_fff9, 2, 0x7fff_fffc),
+        (&udiv, 7, 0, 0)
+    ];
+
+    for &(bytes, lhs, rhs, quotient) in cases.iter() {
+        let mut interpreter = interpreter();
+        interpreter.set_scalar("r1", il::const_(lhs, 32));
+        interpreter.set_scalar("r2", il::const_(rhs, 32));
+        run(&Arm::new(), bytes, 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "r0"), quotient, "0x{:x} / 0x{:x}", lhs, rhs);
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]use error::*;
+#[cfg(test)]use il;
+#[cfg(test)]use loader::*;
+#[cfg(test)]use loader::elf::Elf;
+#[cfg(test)]use std::collections::BTreeSet;
+#[cfg(test)]use std::path::Path;
+#[cfg(test)]use translator::Arch;
+#[cfg(test)]use translator::x86::X86;
+
+
+/// The Palindrome test binary with its symbols hidden, as if it were stripped.
+#[cfg(test)]
+#[derive(Clone, Debug)]
+struct Stripped(Elf);
+
+
+#[cfg(test)]
+impl Loader for Stripped {
+    fn memory(&self) -> Result<memory::Memory> {
+        self.0.memory()
+    }
+
+    fn function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        Ok(Vec::new())
+    }
+
+    fn unwind_function_entries(&self) -> Result<Vec<FunctionEntry>> {
+        self.0.unwind_function_entries()
+    }
+
+    fn program_entry(&self) -> u64 {
+        self.0.program_entry()
+    }
+
+    fn architecture(&self) -> Result<Architecture> {
+        self.0.architecture()
+    }
+}
+
+
+#[cfg(test)]
+fn palindrome() -> Elf {
+    Elf::from_file(Path::new("test_binaries/Palindrome/Palindrome")).unwrap()
+}
+
+
+#[cfg(test)]
+fn instruction_addresses(function: &il::Function) -> BTreeSet<u64> {
+    function.control_flow_graph()
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions().iter())
+            .filter_map(|instruction| instruction.address())
+            .collect()
+}
+
+
+#[test]
+fn discovery_test() {
+    let stripped = Stripped(palindrome());
+
+    let entries = stripped.discover_function_entries()
+                          .unwrap()
+                          .into_iter()
+                          .map(|function_entry| function_entry.address())
+                          .collect::<BTreeSet<u64>>();
+
+    // _start, transmit_all, receive_delim, main, check, __libc_csu_init and
+    // __libc_csu_fini
+    for address in &[0x8048600, 0x80486fb, 0x804877b, 0x804880f, 0x80488ca, 0x80489d0, 0x8048a30] {
+        assert!(entries.contains(address), "0x{:x} not discovered", address);
+    }
+
+    let program = stripped.to_program_discovered().unwrap();
+    assert!(program.function_by_address(0x804880f).is_some());
+
+    // Without discovery, there are no symbols to lift
+    assert!(stripped.to_program().unwrap().functions().is_empty());
+}
+
+
+#[test]
+fn tail_call_test() {
+    let memory = palindrome().memory().unwrap();
+    let x86 = X86::new();
+
+    // frame_dummy ends with a jmp to register_tm_clones
+    let frame_dummy = 0x80486d0;
+    let register_tm_clones = 0x8048670;
+
+    let function = x86.translate_function(&memory, frame_dummy).unwrap();
+    assert!(instruction_addresses(&function).contains(&register_tm_clones));
+
+    let mut function_entries = BTreeSet::new();
+    function_entries.insert(register_tm_clones);
+    let function = x86.translate_function_with_entries(&memory, frame_dummy, &function_entries)
+                      .unwrap();
+
+    assert!(instruction_addresses(&function).iter()
+                                            .all(|address| *address >= frame_dummy));
+
+    let tail_calls = function.control_flow_graph()
+        .blocks()
+        .iter()
+        .flat_map(|block| block.instructions().iter())
+        .filter(|instruction| match *instruction.operation() {
+            il::Operation::Brc { ref target, .. } =>
+                *target == il::expr_const(register_tm_clones, 32),
+            _ => false
+        })
+        .count();
+    assert_eq!(tail_calls, 2);
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]use engine::*;
+#[cfg(test)]use executor::*;
+#[cfg(test)]use il;
+#[cfg(test)]use std::collections::BTreeSet;
+
+
+#[cfg(test)]
+fn labels(fd: i32, offsets: &[u64]) -> BTreeSet<TaintLabel> {
+    offsets.iter().map(|offset| TaintLabel::new(fd, *offset)).collect()
+}
+
+
+#[test]
+fn interpreter_taint_test() {
+    let mut interpreter = Interpreter::new(Endian::Little);
+    interpreter.enable_taint();
+
+    // Four bytes of input read from fd 0 at 0x100
+    interpreter.store(0x100, &il::const_(0x11223344, 32)).unwrap();
+    for offset in 0..4 {
+        interpreter.taint_mut().unwrap().set_byte(0x100 + offset, labels(0, &[offset]));
+    }
+    interpreter.set_scalar("ecx", il::const_(0x200, 32));
+
+    let mut block = il::Block::new(0);
+    block.load(il::scalar("eax", 32), il::expr_const(0x100, 32), il::array("mem", 1 << 32));
+    block.assign(
+        il::scalar("ebx", 32),
+        il::Expression::add(il::expr_scalar("eax", 32), il::expr_const(1, 32)).unwrap()
+    );
+    block.store(il::array("mem", 1 << 32), il::expr_scalar("ecx", 32), il::expr_scalar("ebx", 32));
+    block.assign(il::scalar("eax", 32), il::expr_const(0, 32));
+    block.brc(il::expr_scalar("ebx", 32), il::expr_const(1, 1));
+
+    let mut successors = Vec::new();
+    for instruction in block.instructions() {
+        successors.push(interpreter.execute(instruction.operation()).unwrap());
+    }
+
+    assert_eq!(successors.last(), Some(&Successor::Branch(0x11223345)));
+
+    let taint = interpreter.taint().unwrap();
+    assert_eq!(taint.scalar("ebx"), labels(0, &[0, 1, 2, 3]));
+    assert_eq!(taint.load(0x200, 32), labels(0, &[0, 1, 2, 3]));
+    assert!(taint.scalar("eax").is_empty());
+    assert!(interpreter.taint_labels(&il::expr_scalar("ecx", 32)).is_empty());
+}
+
+
+#[test]
+fn engine_taint_test() {
+    let mut engine = SymbolicEngine::new(SymbolicMemory::new(Endian::Little));
+    engine.enable_taint();
+
+    engine.memory_mut().store(0x100, il::expr_const(0x41, 8)).unwrap();
+    engine.taint_mut().unwrap().set_byte(0x100, labels(3, &[7]));
+
+    let mut block = il::Block::new(0);
+    block.load(il::scalar("eax", 8), il::expr_const(0x100, 32), il::array("mem", 1 << 32));
+    block.assign(il::scalar("edx", 32), il::Expression::zext(32, il::expr_scalar("eax", 8)).unwrap());
+
+    for instruction in block.instructions() {
+        let mut successors = engine.execute(instruction.operation()).unwrap();
+        assert_eq!(successors.len(), 1);
+        engine = successors.pop().unwrap().into_engine();
+    }
+
+    assert_eq!(engine.taint_labels(&il::expr_scalar("edx", 32)), labels(3, &[7]));
+
+    engine.set_scalar("edx", il::expr_const(0, 32));
+    assert!(engine.taint_labels(&il::expr_scalar("edx", 32)).is_empty());
+}
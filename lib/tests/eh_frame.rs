@@ -0,0 +1,60 @@
+#[cfg(test)]use loader::Endian;
+#[cfg(test)]use loader::eh_frame;
+
+
+#[cfg(test)]
+fn push_u32(bytes: &mut Vec<u8>, value: u32, endian: &Endian) {
+    for i in 0..4 {
+        let shift = match *endian {
+            Endian::Big => (3 - i) * 8,
+            Endian::Little => i * 8
+        };
+        bytes.push((value >> shift) as u8);
+    }
+}
+
+
+// An .eh_frame loaded at 0x8000 with FDEs for functions at 0x1000 and 0x2000
+#[cfg(test)]
+fn eh_frame_bytes(endian: &Endian) -> Vec<u8> {
+    let address = 0x8000;
+    let mut bytes = Vec::new();
+
+    // CIE, "zR" with pc-relative, signed 4-byte addresses
+    push_u32(&mut bytes, 16, endian);
+    push_u32(&mut bytes, 0, endian);
+    bytes.extend_from_slice(&[1, b'z', b'R', 0, 1, 0x7c, 8, 1, 0x1b, 0, 0, 0]);
+
+    for &function in &[0x1000u64, 0x2000] {
+        let id_offset = bytes.len() + 4;
+        push_u32(&mut bytes, 16, endian);
+        push_u32(&mut bytes, id_offset as u32, endian);
+        let field = address + bytes.len() as u64;
+        push_u32(&mut bytes, function.wrapping_sub(field) as u32, endian);
+        push_u32(&mut bytes, 0x10, endian);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+    }
+
+    push_u32(&mut bytes, 0, endian);
+
+    bytes
+}
+
+
+#[test]
+fn eh_frame_test() {
+    for endian in &[Endian::Little, Endian::Big] {
+        let bytes = eh_frame_bytes(endian);
+        assert_eq!(
+            eh_frame::function_addresses(&bytes, 0x8000, 4, endian).unwrap(),
+            vec![0x1000, 0x2000]
+        );
+    }
+
+    // Read with the wrong endianness, the CIE lengths are nonsense
+    let bytes = eh_frame_bytes(&Endian::Big);
+    assert_ne!(
+        eh_frame::function_addresses(&bytes, 0x8000, 4, &Endian::Little).ok(),
+        Some(vec![0x1000, 0x2000])
+    );
+}
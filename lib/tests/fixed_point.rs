@@ -0,0 +1,157 @@
+#[cfg(test)]use analysis::fixed_point::*;
+#[cfg(test)]use error::*;
+#[cfg(test)]use il;
+
+
+/// An interval over the single scalar `i`, `None` when `i` is unconstrained
+/// from above.
+#[cfg(test)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Interval {
+    lo: u64,
+    hi: Option<u64>
+}
+
+
+#[cfg(test)]
+struct IntervalAnalysis<'i> {
+    control_flow_graph: &'i il::ControlFlowGraph
+}
+
+
+#[cfg(test)]
+impl<'i> FixedPointAnalysis<Interval> for IntervalAnalysis<'i> {
+    fn trans(
+        &self,
+        analysis_location: &AnalysisLocation,
+        state: &Option<Interval>
+    ) -> Result<Interval> {
+        let state = match *state {
+            Some(ref state) => state.clone(),
+            None => Interval { lo: 0, hi: None }
+        };
+
+        match *analysis_location {
+            AnalysisLocation::Instruction(ref il) => {
+                let instruction = il.find(self.control_flow_graph)?;
+                match *instruction.operation() {
+                    il::Operation::Assign { ref dst, ref src } if dst.name() == "i" =>
+                        match *src {
+                            il::Expression::Constant(ref c) => Ok(Interval {
+                                lo: c.value(),
+                                hi: Some(c.value())
+                            }),
+                            _ => Ok(Interval {
+                                lo: state.lo + 1,
+                                hi: state.hi.map(|hi| hi + 1)
+                            })
+                        },
+                    _ => Ok(state)
+                }
+            },
+            AnalysisLocation::Edge(ref el) => {
+                let edge = el.find(self.control_flow_graph)
+                             .ok_or("Could not find edge")?;
+                match *edge.condition() {
+                    // i < 10
+                    Some(il::Expression::Cmpltu(_, _)) => Ok(Interval {
+                        lo: state.lo,
+                        hi: Some(state.hi.map_or(9, |hi| if hi > 9 { 9 } else { hi }))
+                    }),
+                    // (i < 10) == 0
+                    Some(_) => Ok(Interval {
+                        lo: if state.lo < 10 { 10 } else { state.lo },
+                        hi: state.hi
+                    }),
+                    None => Ok(state)
+                }
+            },
+            AnalysisLocation::EmptyBlock(_) => Ok(state)
+        }
+    }
+
+    fn join(&self, state0: Interval, state1: &Interval) -> Result<Interval> {
+        Ok(Interval {
+            lo: if state0.lo < state1.lo { state0.lo } else { state1.lo },
+            hi: match (state0.hi, state1.hi) {
+                (Some(hi0), Some(hi1)) => Some(if hi0 > hi1 { hi0 } else { hi1 }),
+                _ => None
+            }
+        })
+    }
+
+    fn widen(&self, previous: &Interval, next: Interval) -> Result<Interval> {
+        Ok(Interval {
+            lo: next.lo,
+            hi: if next.hi == previous.hi { next.hi } else { None }
+        })
+    }
+
+    fn narrow(&self, previous: &Interval, next: Interval) -> Result<Interval> {
+        Ok(Interval {
+            lo: previous.lo,
+            hi: if previous.hi.is_none() { next.hi } else { previous.hi }
+        })
+    }
+}
+
+
+#[test]
+fn fixed_point_widen_narrow_test() {
+    // i = 0
+    // while i < 10:
+    //     i = i + 1
+    // r = i
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let head_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(il::scalar("i", 32), il::expr_const(0, 32));
+        block.index()
+    };
+
+    let header_index = control_flow_graph.new_block().unwrap().index();
+
+    let body_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(
+            il::scalar("i", 32),
+            il::Expression::add(il::expr_scalar("i", 32), il::expr_const(1, 32)).unwrap()
+        );
+        block.index()
+    };
+
+    let (tail_index, r_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(il::scalar("r", 32), il::expr_scalar("i", 32));
+        (block.index(), block.instructions()[0].index())
+    };
+
+    let condition = il::Expression::cmpltu(
+        il::expr_scalar("i", 32),
+        il::expr_const(10, 32)
+    ).unwrap();
+
+    control_flow_graph.unconditional_edge(head_index, header_index).unwrap();
+    control_flow_graph.conditional_edge(header_index, body_index, condition.clone())
+                      .unwrap();
+    control_flow_graph.conditional_edge(
+        header_index,
+        tail_index,
+        il::Expression::cmpeq(condition, il::expr_const(0, 1)).unwrap()
+    ).unwrap();
+    control_flow_graph.unconditional_edge(body_index, header_index).unwrap();
+
+    control_flow_graph.set_entry(head_index).unwrap();
+
+    let interval_analysis = IntervalAnalysis {
+        control_flow_graph: &control_flow_graph
+    };
+    let states = fixed_point_forward(&interval_analysis, &control_flow_graph).unwrap();
+
+    let header = AnalysisLocation::empty_block(header_index);
+    assert_eq!(states[&header], Interval { lo: 0, hi: Some(10) });
+
+    let r = AnalysisLocation::instruction(tail_index, r_index);
+    assert_eq!(states[&r], Interval { lo: 10, hi: Some(10) });
+}
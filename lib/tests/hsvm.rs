@@ -0,0 +1,57 @@
+#[cfg(test)]use engine;
+#[cfg(test)]use executor::*;
+#[cfg(test)]use il;
+#[cfg(test)]use loader::Loader;
+#[cfg(test)]use loader::hsvm::Hsvm;
+
+
+#[test]
+fn hsvm_test() {
+    let program: Vec<u8> = vec![
+        0x52, 0x00, 0x00, 0x05, // 0x00: mov r0, 5
+        0x52, 0x01, 0x00, 0x00, // 0x04: mov r1, 0
+        0x10, 0x01, 0x01, 0x00, // 0x08: add r1, r1, r0
+        0x13, 0x00, 0x00, 0x01, // 0x0c: sub r0, 1
+        0x54, 0x00, 0x00, 0x00, // 0x10: cmp r0, 0
+        0x22, 0x00, 0xff, 0xf0, // 0x14: jne 0x08
+        0x34, 0x01, 0x01, 0x00, // 0x18: stor 0x100, r1
+        0x30, 0x02, 0x01, 0x00, // 0x1c: load r2, 0x100
+        0x60, 0x00, 0x00, 0x00  // 0x20: hlt
+    ];
+
+    let loader = Hsvm::new(program).unwrap();
+    let function = loader.function(loader.program_entry()).unwrap();
+    let control_flow_graph = function.control_flow_graph();
+
+    // Interpret the lifted function until it halts
+    let mut interpreter = Interpreter::new(engine::Endian::Big);
+    let mut block_index = control_flow_graph.entry().unwrap();
+    let mut steps = 0;
+    'blocks: loop {
+        for instruction in control_flow_graph.block(block_index).unwrap().instructions() {
+            if let Successor::Raise(expr) = interpreter.execute(instruction.operation()).unwrap() {
+                assert_eq!(expr, il::expr_scalar("hlt", 1));
+                break 'blocks;
+            }
+        }
+
+        block_index = control_flow_graph.edges()
+            .into_iter()
+            .find(|edge| {
+                edge.head() == block_index && match *edge.condition() {
+                    Some(ref condition) => interpreter.eval(condition).unwrap().value() == 1,
+                    None => true
+                }
+            })
+            .unwrap()
+            .tail();
+
+        steps += 1;
+        assert!(steps < 1000);
+    }
+
+    assert_eq!(interpreter.scalar("r0"), Some(&il::const_(0, 16)));
+    assert_eq!(interpreter.scalar("r1"), Some(&il::const_(15, 16)));
+    assert_eq!(interpreter.scalar("r2"), Some(&il::const_(15, 16)));
+    assert_eq!(interpreter.load(0x100, 16).unwrap(), Some(il::const_(15, 16)));
+}
@@ -0,0 +1,233 @@
+#[cfg(test)]use error::*;
+#[cfg(test)]use il;
+#[cfg(test)]use std::collections::BTreeMap;
+#[cfg(test)]use translator::*;
+#[cfg(test)]use translator::x86::X86;
+
+
+/// Translates hand-written blocks in place of native instructions.
+#[cfg(test)]
+struct JumpTableArch;
+
+
+#[cfg(test)]
+impl Arch for JumpTableArch {
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+
+    fn address_bits(&self) -> usize {
+        32
+    }
+
+    fn translate_block(&self, _: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        let mut control_flow_graph = il::ControlFlowGraph::new();
+        let mut successors = Vec::new();
+
+        let block_index = {
+            let block = control_flow_graph.new_block()?;
+            match address {
+                // cmp eax, 3
+                // ja 0x200
+                0x100 => {
+                    let result = il::Expression::sub(
+                        il::expr_scalar("eax", 32),
+                        il::expr_const(3, 32)
+                    )?;
+                    block.assign(
+                        il::scalar("ZF", 1),
+                        il::Expression::cmpeq(result.clone(), il::expr_const(0, 32))?
+                    );
+                    block.assign(
+                        il::scalar("CF", 1),
+                        il::Expression::cmpltu(il::expr_scalar("eax", 32), result)?
+                    );
+                    let above = il::Expression::and(
+                        il::Expression::cmpeq(il::expr_scalar("CF", 1), il::expr_const(0, 1))?,
+                        il::Expression::cmpeq(il::expr_scalar("ZF", 1), il::expr_const(0, 1))?
+                    )?;
+                    successors.push((
+                        0x110,
+                        Some(il::Expression::cmpeq(above.clone(), il::expr_const(0, 1))?)
+                    ));
+                    successors.push((0x200, Some(above)));
+                },
+                // jmp [eax*4 + 0x1000]
+                0x110 => {
+                    let index = il::Expression::add(
+                        il::Expression::shl(il::expr_scalar("eax", 32), il::expr_const(2, 32))?,
+                        il::expr_const(0x1000, 32)
+                    )?;
+                    let temp = block.temp(32);
+                    block.load(temp.clone(), index, il::array("mem", 1 << 32));
+                    block.brc(temp.into(), il::expr_const(1, 1));
+                },
+                _ => block.assign(il::scalar("eax", 32), il::expr_const(address, 32))
+            }
+            block.index()
+        };
+
+        control_flow_graph.set_entry(block_index)?;
+        control_flow_graph.set_exit(block_index)?;
+        control_flow_graph.set_address(Some(address));
+
+        Ok(BlockTranslationResult::new(control_flow_graph, address, 1, successors))
+    }
+}
+
+
+#[cfg(test)]
+struct JumpTableMemory {
+    bytes: BTreeMap<u64, u8>
+}
+
+
+#[cfg(test)]
+impl TranslationMemory for JumpTableMemory {
+    fn get_u8(&self, address: u64) -> Option<u8> {
+        self.bytes.get(&address).cloned()
+    }
+}
+
+
+#[test]
+fn jump_table_test() {
+    let targets = vec![0x300, 0x310, 0x320, 0x330];
+
+    let mut bytes = BTreeMap::new();
+    for address in vec![0x100, 0x110, 0x200].iter().chain(targets.iter()) {
+        bytes.insert(*address, 0x90);
+    }
+    for (i, target) in targets.iter().enumerate() {
+        for j in 0..4 {
+            bytes.insert(0x1000 + (i * 4 + j) as u64, (target >> (j * 8)) as u8);
+        }
+    }
+    // The entry past the end of the table must not be followed
+    bytes.insert(0x1010, 0x90);
+
+    let function = JumpTableArch.translate_function(
+        &JumpTableMemory { bytes: bytes },
+        0x100
+    ).unwrap();
+    let control_flow_graph = function.control_flow_graph();
+
+    let mut addresses = control_flow_graph.blocks()
+        .iter()
+        .flat_map(|block| block.instructions().iter())
+        .filter_map(|instruction| instruction.address())
+        .collect::<Vec<u64>>();
+    addresses.sort();
+    addresses.dedup();
+    assert_eq!(addresses, vec![0x100, 0x110, 0x200, 0x300, 0x310, 0x320, 0x330]);
+
+    // The indirect branch is replaced by edges to every target
+    assert!(control_flow_graph.blocks()
+        .iter()
+        .flat_map(|block| block.instructions().iter())
+        .all(|instruction| !instruction.is_brc()));
+    assert_eq!(control_flow_graph.edges().len(), 6);
+}
+
+
+/// Translates the x86 function at 0x1000 in `code`, with a table of
+/// `targets` at 0x2000. Every target, and the default at 0x1040, returns.
+/// The entry past the end of the table points to 0x1050, which also returns.
+#[cfg(test)]
+fn x86_switch(code: &[u8], targets: &[u64]) -> il::Function {
+    let mut bytes = BTreeMap::new();
+    for (i, byte) in code.iter().enumerate() {
+        bytes.insert(0x1000 + i as u64, *byte);
+    }
+    for address in targets.iter().chain([0x1040, 0x1050].iter()) {
+        bytes.insert(*address, 0xc3);
+    }
+    for (i, target) in targets.iter().chain([0x1050].iter()).enumerate() {
+        for j in 0..4 {
+            bytes.insert(0x2000 + (i * 4 + j) as u64, (target >> (j * 8)) as u8);
+        }
+    }
+
+    X86::new().translate_function(&JumpTableMemory { bytes: bytes }, 0x1000).unwrap()
+}
+
+
+/// The addresses of the instructions in `function`. Conditional branches and
+/// resolved jumps leave no instructions behind.
+#[cfg(test)]
+fn addresses(function: &il::Function) -> Vec<u64> {
+    let mut addresses = function.control_flow_graph()
+        .blocks()
+        .iter()
+        .flat_map(|block| block.instructions().iter())
+        .filter_map(|instruction| instruction.address())
+        .collect::<Vec<u64>>();
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+
+#[test]
+fn x86_jump_table_test() {
+    // cmp eax, 3
+    // ja 0x1040
+    // jmp dword ptr [eax*4 + 0x2000]
+    let function = x86_switch(&[
+        0x83, 0xf8, 0x03,
+        0x77, 0x3b,
+        0xff, 0x24, 0x85, 0x00, 0x20, 0x00, 0x00
+    ], &[0x1010, 0x1018, 0x1020, 0x1028]);
+
+    assert_eq!(
+        addresses(&function),
+        vec![0x1000, 0x1005, 0x1010, 0x1018, 0x1020, 0x1028, 0x1040]
+    );
+}
+
+
+#[test]
+fn x86_stack_index_jump_table_test() {
+    // A switch at -O0 reloads the index from the stack before the jump.
+    // mov eax, dword ptr [ebp - 8]
+    // cmp eax, 3
+    // ja 0x1040
+    // mov eax, dword ptr [ebp - 8]
+    // mov eax, dword ptr [eax*4 + 0x2000]
+    // jmp eax
+    let function = x86_switch(&[
+        0x8b, 0x45, 0xf8,
+        0x83, 0xf8, 0x03,
+        0x77, 0x38,
+        0x8b, 0x45, 0xf8,
+        0x8b, 0x04, 0x85, 0x00, 0x20, 0x00, 0x00,
+        0xff, 0xe0
+    ], &[0x1020, 0x1028, 0x1030, 0x1038]);
+
+    assert_eq!(
+        addresses(&function),
+        vec![0x1000, 0x1003, 0x1008, 0x100b, 0x1020, 0x1028, 0x1030, 0x1038, 0x1040]
+    );
+}
+
+
+#[test]
+fn x86_signed_jump_table_test() {
+    // A signed guard holds for negative indices, which the table does not
+    // cover, so the jump is left unresolved.
+    // cmp eax, 3
+    // jg 0x1040
+    // jmp dword ptr [eax*4 + 0x2000]
+    let function = x86_switch(&[
+        0x83, 0xf8, 0x03,
+        0x7f, 0x3b,
+        0xff, 0x24, 0x85, 0x00, 0x20, 0x00, 0x00
+    ], &[0x1010, 0x1018, 0x1020, 0x1028]);
+
+    assert_eq!(addresses(&function), vec![0x1000, 0x1005, 0x1040]);
+    assert!(function.control_flow_graph()
+        .blocks()
+        .iter()
+        .flat_map(|block| block.instructions().iter())
+        .any(|instruction| instruction.is_brc() && instruction.address() == Some(0x1005)));
+}
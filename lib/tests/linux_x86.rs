@@ -0,0 +1,119 @@
+#[cfg(test)]use engine::*;
+#[cfg(test)]use executor;
+#[cfg(test)]use il;
+#[cfg(test)]use platform::*;
+#[cfg(test)]use platform::linux_x86::LinuxX86;
+#[cfg(test)]use tests::interpret;
+#[cfg(test)]use translator::Arch;
+#[cfg(test)]use translator::x86::X86;
+
+
+#[cfg(test)]
+fn load_u32(engine: &SymbolicEngine, address: u64) -> u64 {
+    let value = engine.memory().load(address, 32).unwrap().unwrap();
+    executor::constants_expression(&value).unwrap().value()
+}
+
+
+#[cfg(test)]
+fn set_thread_area(platform: LinuxX86, mut engine: SymbolicEngine, entry_number: u32)
+    -> (LinuxX86, SymbolicEngine) {
+
+    // struct user_desc at 0x1000, with a base address of 0x2000
+    engine.memory_mut().store(0x1000, il::expr_const(entry_number as u64, 32)).unwrap();
+    engine.memory_mut().store(0x1004, il::expr_const(0x2000, 32)).unwrap();
+    engine.set_scalar("ebx", il::expr_const(0x1000, 32));
+    engine.set_scalar("eax", il::expr_const(243, 32));
+
+    let mut results = platform.raise(&il::expr_scalar("sysenter", 1), engine).unwrap();
+    assert_eq!(results.len(), 1);
+    results.pop().unwrap()
+}
+
+
+#[test]
+fn set_thread_area_test() {
+    let engine = SymbolicEngine::new(SymbolicMemory::new(Endian::Little));
+
+    // A free entry is chosen and written back, but gs is left alone until it
+    // is loaded
+    let (platform, engine) = set_thread_area(LinuxX86::new(), engine, 0xffff_ffff);
+    assert_eq!(engine.get_scalar("eax"), Some(&il::expr_const(0, 32)));
+    assert_eq!(load_u32(&engine, 0x1000), 6);
+    assert_eq!(engine.get_scalar("gs_base"), None);
+
+    // The next free entry follows the first
+    let (platform, mut engine) = set_thread_area(platform, engine, 0xffff_ffff);
+    assert_eq!(load_u32(&engine, 0x1000), 7);
+
+    // Loading gs with the selector of entry 6 sets its base, while fs loaded
+    // with a selector for an entry never set keeps its base
+    engine.set_scalar("gs", il::expr_const((6 << 3) | 3, 16));
+    engine.set_scalar("fs", il::expr_const((8 << 3) | 3, 16));
+    engine.set_scalar("fs_base", il::expr_const(0x5000, 32));
+    let (platform, engine) = platform.raise(&il::expr_scalar("load_gs", 1), engine).unwrap().pop().unwrap();
+    let (platform, engine) = platform.raise(&il::expr_scalar("load_fs", 1), engine).unwrap().pop().unwrap();
+    assert_eq!(engine.get_scalar("gs_base"), Some(&il::expr_const(0x2000, 32)));
+    assert_eq!(engine.get_scalar("fs_base"), Some(&il::expr_const(0x5000, 32)));
+
+    // Entries outside of the thread local storage entries are rejected
+    let (_, engine) = set_thread_area(platform, engine, 2);
+    assert_eq!(engine.get_scalar("eax"), Some(&il::expr_const(22u32.wrapping_neg() as u64, 32)));
+}
+
+
+#[test]
+fn cpuid_rdtsc_test() {
+    let mut engine = SymbolicEngine::new(SymbolicMemory::new(Endian::Little));
+
+    // Leaf 0 gives the vendor in ebx, edx and ecx
+    engine.set_scalar("eax", il::expr_const(0, 32));
+    let (platform, engine) = LinuxX86::new().raise(&il::expr_scalar("cpuid", 1), engine).unwrap().pop().unwrap();
+    assert_eq!(engine.get_scalar("ebx"), Some(&il::expr_const(0x756e_6547, 32)));
+    assert_eq!(engine.get_scalar("edx"), Some(&il::expr_const(0x4965_6e69, 32)));
+    assert_eq!(engine.get_scalar("ecx"), Some(&il::expr_const(0x6c65_746e, 32)));
+
+    // Each read of the time-stamp counter is later than the last
+    let (platform, engine) = platform.raise(&il::expr_scalar("rdtsc", 1), engine).unwrap().pop().unwrap();
+    let first = engine.get_scalar("eax").unwrap().clone();
+    let (_, engine) = platform.raise(&il::expr_scalar("rdtsc", 1), engine).unwrap().pop().unwrap();
+    let second = engine.get_scalar("eax").unwrap().clone();
+    assert_eq!(engine.get_scalar("edx"), Some(&il::expr_const(0, 32)));
+    let later = il::Expression::cmpltu(first, second).unwrap();
+    assert_eq!(executor::constants_expression(&later).unwrap().value(), 1);
+}
+
+
+#[test]
+fn segment_test() {
+    let mut interpreter = executor::Interpreter::new(Endian::Little);
+    interpreter.set_scalar("fs_base", il::const_(0x3000, 32));
+    interpreter.set_scalar("gs_base", il::const_(0x2000, 32));
+    interpreter.store(0x2014, &il::const_(0x1111_1111, 32)).unwrap();
+    interpreter.store(0x3014, &il::const_(0x2222_2222, 32)).unwrap();
+    interpreter.store(0x0014, &il::const_(0x3333_3333, 32)).unwrap();
+
+    // mov eax, gs:[0x14]; mov ebx, fs:[0x14]; mov ecx, [0x14]
+    let bytes = [
+        0x65, 0xa1, 0x14, 0x00, 0x00, 0x00,
+        0x64, 0x8b, 0x1d, 0x14, 0x00, 0x00, 0x00,
+        0x8b, 0x0d, 0x14, 0x00, 0x00, 0x00
+    ];
+    let translation = X86::new().translate_block(&bytes, 0x1000).unwrap();
+    interpret(translation.control_flow_graph(), &mut interpreter).unwrap();
+    assert_eq!(interpreter.scalar("eax").unwrap().value(), 0x1111_1111);
+    assert_eq!(interpreter.scalar("ebx").unwrap().value(), 0x2222_2222);
+    assert_eq!(interpreter.scalar("ecx").unwrap().value(), 0x3333_3333);
+
+    // mov gs, ax raises load_gs after writing gs
+    let translation = X86::new().translate_block(&[0x8e, 0xe8], 0x1000).unwrap();
+    let raises = translation.control_flow_graph().blocks()
+        .iter()
+        .flat_map(|block| block.instructions())
+        .filter_map(|instruction| match *instruction.operation() {
+            il::Operation::Raise { ref expr } => Some(expr.clone()),
+            _ => None
+        })
+        .collect::<Vec<il::Expression>>();
+    assert_eq!(raises, vec![il::expr_scalar("load_gs", 1)]);
+}
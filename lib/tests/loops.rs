@@ -0,0 +1,122 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use engine::{self, SymbolicEngine, SymbolicMemory};
+#[cfg(test)]use il;
+
+
+/// Build the loop `rep movsb` is lifted into, with `initialize` run before
+/// the loop.
+#[cfg(test)]
+fn rep_movsb<F>(initialize: F) -> il::ControlFlowGraph where F: Fn(&mut il::Block) {
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let entry_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        initialize(block);
+        block.index()
+    };
+
+    let head_index = control_flow_graph.new_block().unwrap().index();
+
+    let movs_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        let temp = block.temp(8);
+        block.load(temp.clone(), il::expr_scalar("esi", 32), il::array("mem", 1 << 32));
+        block.store(il::array("mem", 1 << 32), il::expr_scalar("edi", 32), il::Expression::scalar(temp));
+        block.assign(
+            il::scalar("esi", 32),
+            il::Expression::add(il::expr_scalar("esi", 32), il::expr_const(1, 32)).unwrap()
+        );
+        block.assign(
+            il::scalar("edi", 32),
+            il::Expression::add(il::expr_scalar("edi", 32), il::expr_const(1, 32)).unwrap()
+        );
+        block.index()
+    };
+
+    let loop_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(
+            il::scalar("ecx", 32),
+            il::Expression::sub(il::expr_scalar("ecx", 32), il::expr_const(1, 32)).unwrap()
+        );
+        block.index()
+    };
+
+    let terminating_index = control_flow_graph.new_block().unwrap().index();
+
+    control_flow_graph.unconditional_edge(entry_index, head_index).unwrap();
+    control_flow_graph.conditional_edge(
+        head_index,
+        movs_index,
+        il::Expression::cmpneq(il::expr_scalar("ecx", 32), il::expr_const(0, 32)).unwrap()
+    ).unwrap();
+    control_flow_graph.conditional_edge(
+        head_index,
+        terminating_index,
+        il::Expression::cmpeq(il::expr_scalar("ecx", 32), il::expr_const(0, 32)).unwrap()
+    ).unwrap();
+    control_flow_graph.unconditional_edge(movs_index, loop_index).unwrap();
+    control_flow_graph.unconditional_edge(loop_index, head_index).unwrap();
+
+    control_flow_graph.set_entry(entry_index).unwrap();
+    control_flow_graph.set_exit(terminating_index).unwrap();
+
+    control_flow_graph
+}
+
+
+#[test]
+fn loops_test() {
+    let control_flow_graph = rep_movsb(|block| block.assign(il::scalar("eax", 32), il::expr_const(0, 32)));
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let loops = analysis.loops().unwrap();
+    assert_eq!(loops.len(), 1);
+
+    let loop_ = &loops[0];
+    assert_eq!(loop_.header(), 1);
+    assert_eq!(loop_.blocks().iter().cloned().collect::<Vec<u64>>(), vec![1, 2, 3]);
+    assert_eq!(loop_.latches().iter().cloned().collect::<Vec<u64>>(), vec![3]);
+    assert_eq!(loop_.exits(), &vec![(1, 4)]);
+
+    assert_eq!(
+        loop_.induction_variable(&il::scalar("ecx", 32)),
+        Some(&InductionVariable::Basic { step: -1 })
+    );
+    assert_eq!(
+        loop_.induction_variable(&il::scalar("esi", 32)),
+        Some(&InductionVariable::Basic { step: 1 })
+    );
+    assert_eq!(loop_.trip_count(), &TripCount::Exact(il::expr_scalar("ecx", 32)));
+    assert_eq!(
+        loop_.summary(),
+        Some(&LoopSummary::Copy {
+            destination: il::scalar("edi", 32),
+            source: il::scalar("esi", 32),
+            bits: 8
+        })
+    );
+
+    // A count set before the loop gives a constant trip count
+    let control_flow_graph = rep_movsb(|block| block.assign(il::scalar("ecx", 32), il::expr_const(3, 32)));
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let loops = analysis.loops().unwrap();
+    assert_eq!(loops[0].trip_count(), &TripCount::Exact(il::expr_const(3, 32)));
+
+    // The engine copies every byte without unrolling the loop
+    let mut engine = SymbolicEngine::new(SymbolicMemory::new(engine::Endian::Little));
+    for (i, byte) in b"abc".iter().enumerate() {
+        engine.memory_mut().store(0x100 + i as u64, il::expr_const(*byte as u64, 8)).unwrap();
+    }
+    engine.set_scalar("esi", il::expr_const(0x100, 32));
+    engine.set_scalar("edi", il::expr_const(0x200, 32));
+    engine.set_scalar("ecx", il::expr_const(3, 32));
+
+    let engine = engine.execute_loop(&loops[0]).unwrap().unwrap();
+    for (i, byte) in b"abc".iter().enumerate() {
+        assert_eq!(engine.load_only_concrete(0x200 + i as u64).unwrap(), Some(*byte));
+    }
+    assert_eq!(engine.get_scalar("esi"), Some(&il::expr_const(0x103, 32)));
+    assert_eq!(engine.get_scalar("edi"), Some(&il::expr_const(0x203, 32)));
+    assert_eq!(engine.get_scalar("ecx"), Some(&il::expr_const(0, 32)));
+}
@@ -0,0 +1,95 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+
+
+#[cfg(test)]
+fn esp_plus(offset: u64) -> il::Expression {
+    il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(offset, 32)).unwrap()
+}
+
+
+#[test]
+fn memory_def_use_test() {
+    // esp = esp - 8
+    // [esp] = eax
+    // [esp + 4] = ebx
+    // ecx = [esp]
+    // [esp] = 1
+    // edx = [esp]
+    // esi = [esp + 2]
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let (block_index, indices) = {
+        let block = control_flow_graph.new_block().unwrap();
+        let mut indices = Vec::new();
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(8, 32)).unwrap()
+        );
+        block.store(il::array("mem", 1 << 32), esp_plus(0), il::expr_scalar("eax", 32));
+        indices.push(block.instructions().last().unwrap().index());
+        block.store(il::array("mem", 1 << 32), esp_plus(4), il::expr_scalar("ebx", 32));
+        indices.push(block.instructions().last().unwrap().index());
+        block.load(il::scalar("ecx", 32), esp_plus(0), il::array("mem", 1 << 32));
+        indices.push(block.instructions().last().unwrap().index());
+        block.store(il::array("mem", 1 << 32), esp_plus(0), il::expr_const(1, 32));
+        indices.push(block.instructions().last().unwrap().index());
+        block.load(il::scalar("edx", 32), esp_plus(0), il::array("mem", 1 << 32));
+        indices.push(block.instructions().last().unwrap().index());
+        block.load(il::scalar("esi", 32), esp_plus(2), il::array("mem", 1 << 32));
+        indices.push(block.instructions().last().unwrap().index());
+        (block.index(), indices)
+    };
+
+    control_flow_graph.set_entry(block_index).unwrap();
+    control_flow_graph.set_exit(block_index).unwrap();
+
+    let location = |i: usize| AnalysisLocation::instruction(block_index, indices[i]);
+    let locations = |is: &[usize]| is.iter()
+                                     .map(|i| location(*i))
+                                     .collect::<Vec<AnalysisLocation>>();
+    // The stores used by a load, ignoring the definition of esp
+    let stores = |analysis: &Analysis, i: usize| analysis.use_def()[&location(i)]
+        .iter()
+        .filter(|location| **location != AnalysisLocation::instruction(block_index, 0))
+        .cloned()
+        .collect::<Vec<AnalysisLocation>>();
+
+    let analysis = Analysis::new_with_memory(
+        &control_flow_graph,
+        32,
+        Endian::Little,
+        &Regions::x86(None)
+    ).unwrap();
+
+    let memory_locations = analysis.memory_locations().unwrap();
+    assert!(memory_locations[&location(0)].is_precise());
+    assert!(memory_locations[&location(3)].covers(&memory_locations[&location(0)]));
+    assert!(!memory_locations[&location(1)].may_alias(&memory_locations[&location(0)]));
+
+    // Both stores reach the first load, but it only reads the first
+    assert_eq!(
+        stores(&analysis, 2),
+        locations(&[0])
+    );
+
+    // The store of 1 kills the store of eax, but not the store of ebx
+    assert_eq!(
+        stores(&analysis, 4),
+        locations(&[3])
+    );
+    assert_eq!(
+        stores(&analysis, 5),
+        locations(&[1, 3])
+    );
+    assert!(analysis.def_use()[&location(0)].contains(&location(2)));
+    assert!(!analysis.def_use()[&location(1)].contains(&location(2)));
+
+    // Without memory, only the last store to mem reaches each load
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    assert!(analysis.memory_locations().is_none());
+    assert_eq!(
+        stores(&analysis, 2),
+        locations(&[1])
+    );
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use tests::{run, value};
+#[cfg(test)]use translator::Arch;
+#[cfg(test)]use translator::mips::{Mips, Mipsel};
+
+
+#[cfg(test)]
+fn interpreter(a0: u64, a1: u64) -> Interpreter {
+    let mut interpreter = Interpreter::new(Endian::Big);
+    interpreter.set_scalar("a0", il::const_(a0, 32));
+    interpreter.set_scalar("a1", il::const_(a1, 32));
+    interpreter.set_scalar("v0", il::const_(0, 32));
+    interpreter
+}
+
+
+#[test]
+fn delay_slot_test() {
+    // beq a0, a1, 0x1010; addiu v0, zero, 1
+    let bytes = [0x10, 0x85, 0x00, 0x03, 0x24, 0x02, 0x00, 0x01];
+    assert_eq!(Mips::new().translate_block(&bytes, 0x1000).unwrap().length(), 8);
+
+    // The delay slot executes whether or not the branch is taken
+    let mut interpreter = interpreter(1, 1);
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x1010]);
+    assert_eq!(value(&interpreter, "v0"), 1);
+
+    let mut interpreter = self::interpreter(1, 2);
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+    assert_eq!(value(&interpreter, "v0"), 1);
+}
+
+
+#[test]
+fn branch_likely_test() {
+    // beql a0, a1, 0x1010; addiu v0, zero, 1
+    let bytes = [0x50, 0x85, 0x00, 0x03, 0x24, 0x02, 0x00, 0x01];
+
+    // The delay slot only executes when the branch is taken
+    let mut interpreter = interpreter(1, 1);
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x1010]);
+    assert_eq!(value(&interpreter, "v0"), 1);
+
+    let mut interpreter = self::interpreter(1, 2);
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+    assert_eq!(value(&interpreter, "v0"), 0);
+}
+
+
+#[test]
+fn delay_slot_overwrite_test() {
+    // beq a0, a1, 0x1010; addiu a0, a0, 1
+    let bytes = [0x10, 0x85, 0x00, 0x03, 0x24, 0x84, 0x00, 0x01];
+
+    // The branch compares a0 as it was before the delay slot
+    let mut interpreter = interpreter(1, 1);
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x1010]);
+    assert_eq!(value(&interpreter, "a0"), 2);
+
+    let mut interpreter = self::interpreter(1, 2);
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x1008]);
+
+    // jr t9; move t9, zero jumps to t9 as it was before the delay slot
+    let bytes = [0x03, 0x20, 0x00, 0x08, 0x00, 0x00, 0xc8, 0x21];
+    let mut interpreter = self::interpreter(0, 0);
+    interpreter.set_scalar("t9", il::const_(0x4000, 32));
+    assert_eq!(run(&Mips::new(), &bytes, 0x1000, &mut interpreter), vec![0x4000]);
+    assert_eq!(value(&interpreter, "t9"), 0);
+}
+
+
+#[test]
+fn truncated_delay_slot_test() {
+    // addiu v0, zero, 1; beq a0, a1, 0x1014, with the delay slot missing.
+    // The branch begins the next block.
+    let bytes = [0x24, 0x02, 0x00, 0x01, 0x10, 0x85, 0x00, 0x03];
+    let result = Mips::new().translate_block(&bytes, 0x1000).unwrap();
+    assert_eq!(result.length(), 4);
+    let successors = result.successors().iter().map(|s| s.0).collect::<Vec<u64>>();
+    assert_eq!(successors, vec![0x1004]);
+
+    // A block which is only the branch cannot be translated
+    assert!(Mips::new().translate_block(&[0x10, 0x85, 0x00, 0x03], 0x1000).is_err());
+}
+
+
+#[test]
+fn unaligned_test() {
+    // lwl t0, 0(a0); lwr t0, 3(a0)
+    let bytes = [0x88, 0x88, 0x00, 0x00, 0x98, 0x88, 0x00, 0x03];
+
+    let mut interpreter = interpreter(0x2001, 0);
+    interpreter.set_scalar("t0", il::const_(0, 32));
+    for (i, &byte) in [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77].iter().enumerate() {
+        interpreter.store(0x2000 + i as u64, &il::const_(byte, 8)).unwrap();
+    }
+    run(&Mips::new(), &bytes, 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "t0"), 0x1122_3344);
+
+    // On little endian targets, lwl t0, 3(a0); lwr t0, 0(a0) loads the same
+    // unaligned word
+    let bytes = [0x03, 0x00, 0x88, 0x88, 0x00, 0x00, 0x88, 0x98];
+
+    let mut interpreter = Interpreter::new(Endian::Little);
+    interpreter.set_scalar("a0", il::const_(0x2001, 32));
+    interpreter.set_scalar("t0", il::const_(0, 32));
+    for (i, &byte) in [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77].iter().enumerate() {
+        interpreter.store(0x2000 + i as u64, &il::const_(byte, 8)).unwrap();
+    }
+    run(&Mipsel::new(), &bytes, 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "t0"), 0x4433_2211);
+}
@@ -1,10 +1,165 @@
+#[cfg(test)] use error::*;
 #[cfg(test)] use il;
 #[cfg(test)] use executor;
+#[cfg(test)] use executor::{Interpreter, Successor};
+#[cfg(test)] use translator::Arch;
 
+mod aarch64;
+mod amd64;
+mod arm;
+mod discovery;
+mod dynamic_taint;
+mod eh_frame;
+mod fixed_point;
+mod hsvm;
+mod jump_table;
+mod linux_x86;
+mod loops;
+mod memory_def_use;
+mod mips;
+mod points_to;
+mod sccp;
+mod signature;
 mod simple_0;
+mod ssa;
+mod sse;
+mod stack_pointer;
+mod taint;
+mod types;
+mod value_set;
+mod x86_misc;
+mod x86_strings;
+mod x87;
+
+
+/// Runs a lifted `ControlFlowGraph` over `interpreter`, following the edges
+/// whose conditions hold until leaving the graph.
+///
+/// Returns the target of a `brc` which is taken, which also leaves the graph.
+#[cfg(test)]
+pub fn interpret(control_flow_graph: &il::ControlFlowGraph, interpreter: &mut Interpreter)
+-> Result<Option<u64>> {
+    let mut index = control_flow_graph.entry().ok_or("No entry")?;
+    loop {
+        for instruction in control_flow_graph.block(index).ok_or("No block")?.instructions() {
+            if let Successor::Branch(target) = interpreter.execute(instruction.operation())? {
+                return Ok(Some(target));
+            }
+        }
+        let mut next = None;
+        for edge in control_flow_graph.graph().edges_out(index).ok_or("No edges")? {
+            let taken = match *edge.condition() {
+                Some(ref condition) => interpreter.eval(condition)?.value() == 1,
+                None => true
+            };
+            if taken {
+                next = Some(edge.tail());
+            }
+        }
+        match next {
+            Some(next) => index = next,
+            None => return Ok(None)
+        }
+    }
+}
+
+
+/// Lifts the block in `bytes` at `address` with `arch`, and runs it over
+/// `interpreter`.
+///
+/// Returns the target of a `brc` out of the block, or otherwise the
+/// successors of the block whose conditions hold.
+#[cfg(test)]
+pub fn run(arch: &Arch, bytes: &[u8], address: u64, interpreter: &mut Interpreter) -> Vec<u64> {
+    let result = arch.translate_block(bytes, address).unwrap();
+    if let Some(target) = interpret(result.control_flow_graph(), interpreter).unwrap() {
+        return vec![target];
+    }
+    result.successors()
+        .iter()
+        .filter(|&&(_, ref condition)| match *condition {
+            Some(ref condition) => interpreter.eval(condition).unwrap().value() == 1,
+            None => true
+        })
+        .map(|&(address, _)| address)
+        .collect()
+}
+
+
+/// The value of the scalar `name` in `interpreter`.
+#[cfg(test)]
+pub fn value(interpreter: &Interpreter, name: &str) -> u64 {
+    interpreter.scalar(name).unwrap().value()
+}
+
+
+/// The value of the scalar `name` in `interpreter`, for scalars wider than
+/// 64 bits.
+#[cfg(test)]
+pub fn value_u128(interpreter: &Interpreter, name: &str) -> u128 {
+    interpreter.scalar(name).unwrap().value_u128()
+}
+
+
+/// Loads `bits` bits from `address` in `interpreter`.
+#[cfg(test)]
+pub fn load(interpreter: &Interpreter, address: u64, bits: usize) -> u64 {
+    interpreter.load(address, bits).unwrap().unwrap().value()
+}
+
+
+/// Loads `bits` bits from `address` in `interpreter`, for values wider than
+/// 64 bits.
+#[cfg(test)]
+pub fn load_u128(interpreter: &Interpreter, address: u64, bits: usize) -> u128 {
+    interpreter.load(address, bits).unwrap().unwrap().value_u128()
+}
+
 
 #[test]
 fn il_constants () {
     let expr = il::Expression::add(il::expr_const(10, 32), il::expr_const(20, 32)).unwrap();
     assert_eq!(executor::constants_expression(&expr).unwrap().value(), 30);
-}
\ No newline at end of file
+}
+
+#[test]
+fn il_float_constants () {
+    let one_and_half = il::expr_const(1.5f64.to_bits(), 64);
+    let two_and_quarter = il::expr_const(2.25f64.to_bits(), 64);
+
+    let expr = il::Expression::fadd(one_and_half.clone(), two_and_quarter.clone()).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), 3.75f64.to_bits());
+
+    let expr = il::Expression::fcmplt(one_and_half.clone(), two_and_quarter).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), 1);
+
+    let expr = il::Expression::fext(32, one_and_half).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), 1.5f32.to_bits() as u64);
+
+    let expr = il::Expression::ftoi(32, il::expr_const((-2.75f64).to_bits(), 64)).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), (-2i32) as u32 as u64);
+
+    let expr = il::Expression::itof(64, il::expr_const(-3i32 as u32 as u64, 32)).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), (-3.0f64).to_bits());
+}
+
+#[test]
+fn il_wide_constants () {
+    let wide = il::Expression::constant(il::Constant::new_u128(1 << 100, 128));
+
+    let expr = il::Expression::shl(wide.clone(), il::expr_const(27, 128)).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value_u128(), 1 << 127);
+
+    let expr = il::Expression::shl(wide.clone(), il::expr_const(28, 128)).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value_u128(), 0);
+
+    let expr = il::Expression::sub(il::expr_const(0, 128), wide.clone()).unwrap();
+    let expr = il::Expression::cmplts(expr, wide.clone()).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), 1);
+
+    let expr = il::Expression::trun(64, il::Expression::shr(wide, il::expr_const(40, 128)).unwrap()).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value(), 1 << 60);
+
+    let expr = il::Expression::sext(128, il::expr_const(0x80, 8)).unwrap();
+    assert_eq!(executor::constants_expression(&expr).unwrap().value_u128(), !0x7f);
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+
+
+#[cfg(test)]
+fn esp_plus(offset: u64) -> il::Expression {
+    il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(offset, 32)).unwrap()
+}
+
+
+#[cfg(test)]
+fn function(address: u64, f: &Fn(&mut il::Block)) -> (il::Function, u64) {
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+    let index = {
+        let block = control_flow_graph.new_block().unwrap();
+        f(block);
+        block.index()
+    };
+    control_flow_graph.set_entry(index).unwrap();
+    control_flow_graph.set_exit(index).unwrap();
+    (il::Function::new(address, control_flow_graph), index)
+}
+
+
+#[test]
+fn points_to_test() {
+    // 0x1000:
+    //   esp = esp - 8
+    //   [esp] = eax
+    //   [esp + 4] = ebx
+    //   [0x8000] = eax
+    //   ecx = [esp + 8]
+    let (a, a_index) = function(0x1000, &|block| {
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(8, 32)).unwrap()
+        );
+        block.store(il::array("mem", 1 << 32), esp_plus(0), il::expr_scalar("eax", 32));
+        block.store(il::array("mem", 1 << 32), esp_plus(4), il::expr_scalar("ebx", 32));
+        block.store(il::array("mem", 1 << 32), il::expr_const(0x8000, 32), il::expr_scalar("eax", 32));
+        block.load(il::scalar("ecx", 32), esp_plus(8), il::array("mem", 1 << 32));
+    });
+
+    // 0x2000:
+    //   [0x8000] = 1
+    //   eax = [0x8004]
+    //   esp = esp - 4
+    //   [esp] = 0
+    let (b, b_index) = function(0x2000, &|block| {
+        block.store(il::array("mem", 1 << 32), il::expr_const(0x8000, 32), il::expr_const(1, 32));
+        block.load(il::scalar("eax", 32), il::expr_const(0x8004, 32), il::array("mem", 1 << 32));
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap()
+        );
+        block.store(il::array("mem", 1 << 32), il::expr_scalar("esp", 32), il::expr_const(0, 32));
+    });
+
+    let a_location = |index: u64| AnalysisLocation::instruction(a_index, index);
+    let b_location = |index: u64| AnalysisLocation::instruction(b_index, index);
+
+    // Flow-sensitive, within one function
+    let regions = Regions::x86(None);
+    let analysis = Analysis::new_with_memory(
+        a.control_flow_graph(),
+        32,
+        Endian::Little,
+        &regions
+    ).unwrap();
+    let points_to = analysis.points_to().unwrap();
+    assert!(points_to.must_alias(&a_location(1), &a_location(1)));
+    assert!(!points_to.may_alias(&a_location(1), &a_location(2)));
+    assert!(!points_to.may_alias(&a_location(1), &a_location(3)));
+    assert!(!points_to.may_alias(&a_location(2), &a_location(4)));
+
+    // Flow-insensitive, over the whole program
+    let mut program = il::Program::new();
+    program.add_function(a);
+    program.add_function(b);
+    let program_points_to = program_points_to(&program, 32, Endian::Little, &regions).unwrap();
+
+    assert!(program_points_to.must_alias(0x1000, &a_location(3), 0x2000, &b_location(0)));
+    assert!(!program_points_to.may_alias(0x1000, &a_location(3), 0x2000, &b_location(1)));
+
+    // The stack of 0x2000 is below the arguments read by 0x1000
+    assert!(program_points_to.may_alias(0x1000, &a_location(4), 0x2000, &b_location(3)));
+    assert!(!program_points_to.must_alias(0x1000, &a_location(4), 0x2000, &b_location(3)));
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]use analysis;
+#[cfg(test)]use error::*;
+#[cfg(test)]use il;
+
+
+#[cfg(test)]
+fn sccp_graph() -> Result<il::ControlFlowGraph> {
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let head_index = {
+        let block = control_flow_graph.new_block()?;
+        block.assign(il::scalar("a", 32), il::expr_const(1, 32));
+        block.assign(
+            il::scalar("b", 32),
+            il::Expression::add(il::expr_scalar("a", 32), il::expr_const(2, 32))?
+        );
+        block.index()
+    };
+
+    let true_index = {
+        let block = control_flow_graph.new_block()?;
+        block.assign(il::scalar("c", 32), il::expr_const(0x10, 32));
+        block.index()
+    };
+
+    let false_index = {
+        let block = control_flow_graph.new_block()?;
+        block.assign(il::scalar("c", 32), il::expr_scalar("input", 32));
+        block.index()
+    };
+
+    let tail_index = {
+        let block = control_flow_graph.new_block()?;
+        block.brc(il::expr_scalar("c", 32), il::expr_const(1, 1));
+        block.index()
+    };
+
+    let condition = il::Expression::cmpeq(il::expr_scalar("b", 32), il::expr_const(3, 32))?;
+    let not_condition = il::Expression::cmpneq(il::expr_scalar("b", 32), il::expr_const(3, 32))?;
+    control_flow_graph.conditional_edge(head_index, true_index, condition)?;
+    control_flow_graph.conditional_edge(head_index, false_index, not_condition)?;
+    control_flow_graph.unconditional_edge(true_index, tail_index)?;
+    control_flow_graph.unconditional_edge(false_index, tail_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
+
+    analysis::ssa(control_flow_graph)
+}
+
+
+#[test]
+fn sccp_test() {
+    let control_flow_graph = sccp_graph().unwrap();
+    let sccp = analysis::sccp(&control_flow_graph).unwrap();
+
+    assert!(sccp.executable_blocks().contains(&1));
+    assert!(!sccp.executable_blocks().contains(&2));
+    assert!(sccp.control_flow_graph().block(2).is_none());
+    assert!(sccp.control_flow_graph().edge(0, 1).unwrap().condition().is_none());
+
+    let targets = sccp.brc_targets().values().collect::<Vec<&u64>>();
+    assert_eq!(targets, vec![&0x10]);
+}
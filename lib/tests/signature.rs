@@ -0,0 +1,69 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+
+
+#[test]
+fn signature_test() {
+    // push ebx
+    // mov ebx, ecx
+    // xor edx, edx
+    // mov eax, [esp + 8]
+    // add eax, ebx
+    // pop ebx
+    // ret 4
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+    let mem = il::array("mem", 1 << 32);
+
+    let block_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap()
+        );
+        block.store(mem.clone(), il::expr_scalar("esp", 32), il::expr_scalar("ebx", 32));
+        block.assign(il::scalar("ebx", 32), il::expr_scalar("ecx", 32));
+        block.assign(
+            il::scalar("edx", 32),
+            il::Expression::xor(il::expr_scalar("edx", 32), il::expr_scalar("edx", 32)).unwrap()
+        );
+        block.load(
+            il::scalar("eax", 32),
+            il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(8, 32)).unwrap(),
+            mem.clone()
+        );
+        block.assign(
+            il::scalar("eax", 32),
+            il::Expression::add(il::expr_scalar("eax", 32), il::expr_scalar("ebx", 32)).unwrap()
+        );
+        block.load(il::scalar("ebx", 32), il::expr_scalar("esp", 32), mem.clone());
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap()
+        );
+        block.load(il::scalar("eip", 32), il::expr_scalar("esp", 32), mem.clone());
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(8, 32)).unwrap()
+        );
+        block.brc(il::expr_scalar("eip", 32), il::expr_const(1, 1));
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index).unwrap();
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let signature = analysis.signature(&StackConventions::x86()).unwrap();
+
+    assert_eq!(signature.calling_convention(), il::CallingConvention::Fastcall);
+    assert_eq!(signature.arguments(), &vec![
+        il::Parameter::Scalar(il::scalar("ecx", 32)),
+        il::Parameter::Stack { offset: 4, bits: 32 }
+    ]);
+    // edx is written on every path, and may return the high half of a value
+    assert_eq!(signature.returns(), &vec![
+        il::scalar("eax", 32),
+        il::scalar("edx", 32)
+    ]);
+    assert!(signature.clobbered().is_empty());
+    assert_eq!(signature.stack_cleanup(), 4);
+}
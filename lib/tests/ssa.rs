@@ -0,0 +1,228 @@
+#[cfg(test)]use analysis;
+#[cfg(test)]use engine;
+#[cfg(test)]use engine::*;
+#[cfg(test)]use error::*;
+#[cfg(test)]use il;
+#[cfg(test)]use il::Variable;
+
+
+#[cfg(test)]
+fn ssa_scalar(name: &str, ssa: u32) -> il::Scalar {
+    let mut scalar = il::scalar(name, 32);
+    scalar.set_ssa(Some(ssa));
+    scalar
+}
+
+
+// Two phis in a loop header which swap a and b on every iteration.
+#[cfg(test)]
+fn swap_graph() -> Result<il::ControlFlowGraph> {
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let entry_index = {
+        let block = control_flow_graph.new_block()?;
+        block.assign(ssa_scalar("a", 1), il::expr_const(1, 32));
+        block.assign(ssa_scalar("b", 1), il::expr_const(2, 32));
+        block.assign(ssa_scalar("i", 1), il::expr_const(0, 32));
+        block.index()
+    };
+
+    let header_index = {
+        let block = control_flow_graph.new_block()?;
+        block.phi(ssa_scalar("a", 2).multi_var_clone(),
+                  vec![(0, ssa_scalar("a", 1).multi_var_clone()),
+                       (2, ssa_scalar("b", 2).multi_var_clone())].into_iter().collect());
+        block.phi(ssa_scalar("b", 2).multi_var_clone(),
+                  vec![(0, ssa_scalar("b", 1).multi_var_clone()),
+                       (2, ssa_scalar("a", 2).multi_var_clone())].into_iter().collect());
+        block.phi(ssa_scalar("i", 2).multi_var_clone(),
+                  vec![(0, ssa_scalar("i", 1).multi_var_clone()),
+                       (2, ssa_scalar("i", 3).multi_var_clone())].into_iter().collect());
+        block.index()
+    };
+
+    let body_index = {
+        let block = control_flow_graph.new_block()?;
+        block.assign(
+            ssa_scalar("i", 3),
+            il::Expression::add(ssa_scalar("i", 2).into(), il::expr_const(1, 32))?
+        );
+        block.index()
+    };
+
+    let exit_index = control_flow_graph.new_block()?.index();
+
+    let condition = il::Expression::cmpltu(ssa_scalar("i", 2).into(), il::expr_const(3, 32))?;
+    let not_condition = il::Expression::cmpeq(condition.clone(), il::expr_const(0, 1))?;
+
+    control_flow_graph.unconditional_edge(entry_index, header_index)?;
+    control_flow_graph.conditional_edge(header_index, body_index, condition)?;
+    control_flow_graph.conditional_edge(header_index, exit_index, not_condition)?;
+    control_flow_graph.unconditional_edge(body_index, header_index)?;
+
+    control_flow_graph.set_entry(entry_index)?;
+    control_flow_graph.set_exit(exit_index)?;
+
+    Ok(control_flow_graph)
+}
+
+
+// E: x1 = 1; y1 = 2; P1 and P2 both lead to J, which selects x1 from P1
+// and y1 from P2. Placing each copy after its operand's definition would
+// leave z = 2 on both paths.
+#[cfg(test)]
+fn join_graph(take_p1: bool) -> Result<il::ControlFlowGraph> {
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let e_index = {
+        let block = control_flow_graph.new_block()?;
+        block.assign(ssa_scalar("x", 1), il::expr_const(1, 32));
+        block.assign(ssa_scalar("y", 1), il::expr_const(2, 32));
+        block.assign(ssa_scalar("c", 1), il::expr_const(take_p1 as u64, 32));
+        block.index()
+    };
+
+    let p1_index = control_flow_graph.new_block()?.index();
+    let p2_index = control_flow_graph.new_block()?.index();
+
+    let j_index = {
+        let block = control_flow_graph.new_block()?;
+        block.phi(ssa_scalar("z", 1).multi_var_clone(),
+                  vec![(p1_index, ssa_scalar("x", 1).multi_var_clone()),
+                       (p2_index, ssa_scalar("y", 1).multi_var_clone())].into_iter().collect());
+        block.index()
+    };
+
+    let condition = il::Expression::cmpneq(ssa_scalar("c", 1).into(), il::expr_const(0, 32))?;
+    let not_condition = il::Expression::cmpeq(ssa_scalar("c", 1).into(), il::expr_const(0, 32))?;
+
+    control_flow_graph.conditional_edge(e_index, p1_index, condition)?;
+    control_flow_graph.conditional_edge(e_index, p2_index, not_condition)?;
+    control_flow_graph.unconditional_edge(p1_index, j_index)?;
+    control_flow_graph.unconditional_edge(p2_index, j_index)?;
+
+    control_flow_graph.set_entry(e_index)?;
+    control_flow_graph.set_exit(j_index)?;
+
+    Ok(control_flow_graph)
+}
+
+
+#[cfg(test)]
+fn run(control_flow_graph: &il::ControlFlowGraph) -> Result<SymbolicEngine> {
+    let mut engine = SymbolicEngine::new(SymbolicMemory::new(engine::Endian::Little));
+    let mut block_index = control_flow_graph.entry().unwrap();
+
+    for _ in 0..64 {
+        for instruction in control_flow_graph.block(block_index).unwrap().instructions() {
+            engine = engine.execute(instruction.operation())?.remove(0).into_engine();
+        }
+
+        let mut next = None;
+        for edge in control_flow_graph.graph().edges_out(block_index).unwrap() {
+            let taken = match *edge.condition() {
+                Some(ref condition) =>
+                    engine.symbolize_and_eval(condition)? == il::expr_const(1, 1),
+                None => true
+            };
+            if taken {
+                next = Some(edge.tail());
+            }
+        }
+
+        match next {
+            Some(next) => {
+                engine.set_predecessor(Some(block_index));
+                block_index = next;
+            },
+            None => return Ok(engine)
+        }
+    }
+
+    bail!("graph did not terminate")
+}
+
+
+#[test]
+fn phi_test() {
+    // The engine selects phi operands by the block it arrived from, and reads
+    // all of a block's phi operands before any phi writes.
+    let engine = run(&swap_graph().unwrap()).unwrap();
+    assert_eq!(engine.get_scalar("a"), Some(&il::expr_const(2, 32)));
+    assert_eq!(engine.get_scalar("b"), Some(&il::expr_const(1, 32)));
+
+    let engine = run(&join_graph(true).unwrap()).unwrap();
+    assert_eq!(engine.get_scalar("z"), Some(&il::expr_const(1, 32)));
+
+    let engine = run(&join_graph(false).unwrap()).unwrap();
+    assert_eq!(engine.get_scalar("z"), Some(&il::expr_const(2, 32)));
+
+    // Without a predecessor there is no operand to select.
+    let engine = SymbolicEngine::new(SymbolicMemory::new(engine::Endian::Little));
+    let phi = join_graph(true).unwrap().block(3).unwrap().instructions()[0].clone();
+    assert!(engine.execute(phi.operation()).is_err());
+}
+
+
+#[test]
+fn out_of_ssa_join_test() {
+    for &(take_p1, z) in &[(true, 1), (false, 2)] {
+        let control_flow_graph = join_graph(take_p1).unwrap();
+        analysis::verify_ssa(&control_flow_graph).unwrap();
+
+        let control_flow_graph = analysis::out_of_ssa(control_flow_graph).unwrap();
+        let engine = run(&control_flow_graph).unwrap();
+        assert_eq!(engine.get_scalar("z"), Some(&il::expr_const(z, 32)));
+    }
+}
+
+
+#[test]
+fn out_of_ssa_swap_test() {
+    let control_flow_graph = swap_graph().unwrap();
+    analysis::verify_ssa(&control_flow_graph).unwrap();
+
+    let control_flow_graph = analysis::out_of_ssa(control_flow_graph).unwrap();
+    for block in control_flow_graph.blocks() {
+        assert!(block.instructions().iter().all(|i| !i.is_phi()));
+    }
+
+    let engine = run(&control_flow_graph).unwrap();
+    assert_eq!(engine.get_scalar("a"), Some(&il::expr_const(2, 32)));
+    assert_eq!(engine.get_scalar("b"), Some(&il::expr_const(1, 32)));
+}
+
+
+#[test]
+fn ssa_test() {
+    // Build SSA for the swap loop without SSA values, and run it directly.
+    let mut control_flow_graph = analysis::clear_ssa(swap_graph().unwrap()).unwrap();
+    let temp = il::scalar("t", 32);
+    {
+        let body = control_flow_graph.block_mut(2).unwrap();
+        body.assign(temp.clone(), il::expr_scalar("a", 32));
+        body.assign(il::scalar("a", 32), il::expr_scalar("b", 32));
+        body.assign(il::scalar("b", 32), temp.into());
+    }
+
+    let control_flow_graph = analysis::ssa(control_flow_graph).unwrap();
+    analysis::verify_ssa(&control_flow_graph).unwrap();
+
+    let engine = run(&control_flow_graph).unwrap();
+    assert_eq!(engine.get_scalar("a"), Some(&il::expr_const(2, 32)));
+    assert_eq!(engine.get_scalar("b"), Some(&il::expr_const(1, 32)));
+
+    let engine = run(&analysis::out_of_ssa(control_flow_graph).unwrap()).unwrap();
+    assert_eq!(engine.get_scalar("a"), Some(&il::expr_const(2, 32)));
+    assert_eq!(engine.get_scalar("b"), Some(&il::expr_const(1, 32)));
+}
+
+
+#[test]
+fn verify_ssa_test() {
+    let mut control_flow_graph = swap_graph().unwrap();
+    control_flow_graph.block_mut(2)
+                      .unwrap()
+                      .assign(ssa_scalar("a", 1), il::expr_const(3, 32));
+    assert!(analysis::verify_ssa(&control_flow_graph).is_err());
+}
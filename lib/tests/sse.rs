@@ -0,0 +1,125 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use tests::{load_u128, run, value, value_u128};
+#[cfg(test)]use translator::x86::X86;
+
+
+#[cfg(test)]
+const A: u128 = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+
+
+#[cfg(test)]
+const B: u128 = 0xff0e_ff0c_ff0a_ff08_ff06_ff04_ff02_ff00;
+
+
+#[cfg(test)]
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new(Endian::Little);
+    interpreter.set_scalar("eax", il::const_(0x2000, 32));
+    interpreter.set_scalar("ebx", il::const_(0x3000, 32));
+    interpreter.set_scalar("ecx", il::const_(0xffff_ffff, 32));
+    interpreter.set_scalar("edx", il::const_(0xffff_ffff, 32));
+    interpreter.set_scalar("xmm0", il::Constant::new_u128(A, 128));
+    interpreter.set_scalar("xmm1", il::Constant::new_u128(B, 128));
+    interpreter.store(0x2000, &il::Constant::new_u128(B, 128)).unwrap();
+    interpreter
+}
+
+
+#[test]
+fn movdqa_test() {
+    // movdqa xmm0, xmmword ptr [eax]
+    let mut interpreter = interpreter();
+    interpreter.set_scalar("xmm1", il::Constant::new_u128(0, 128));
+    run(&X86::new(), &[0x66, 0x0f, 0x6f, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), B);
+
+    // movdqa xmmword ptr [ebx], xmm0; movdqa xmm1, xmm0
+    run(&X86::new(), &[0x66, 0x0f, 0x7f, 0x03, 0x66, 0x0f, 0x6f, 0xc8], 0x1000, &mut interpreter);
+    assert_eq!(load_u128(&interpreter, 0x3000, 128), B);
+    assert_eq!(value_u128(&interpreter, "xmm1"), B);
+}
+
+
+#[test]
+fn pcmpeqb_test() {
+    // The even bytes of A and B are equal
+    let equal = 0x00ff_00ff_00ff_00ff_00ff_00ff_00ff_00ff;
+
+    // pcmpeqb xmm0, xmm1
+    let mut interpreter = interpreter();
+    run(&X86::new(), &[0x66, 0x0f, 0x74, 0xc1], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), equal);
+
+    // pcmpeqb xmm0, xmmword ptr [eax]
+    let mut interpreter = self::interpreter();
+    run(&X86::new(), &[0x66, 0x0f, 0x74, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), equal);
+}
+
+
+#[test]
+fn pmovmskb_test() {
+    // pmovmskb ecx, xmm1 gathers the top bit of each byte, clearing the
+    // rest of ecx
+    let mut interpreter = interpreter();
+    run(&X86::new(), &[0x66, 0x0f, 0xd7, 0xc9], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "ecx"), 0xaaaa);
+
+    // pmovmskb ecx, xmm0
+    run(&X86::new(), &[0x66, 0x0f, 0xd7, 0xc8], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "ecx"), 0);
+
+    // The strlen idiom: pxor xmm1, xmm1; pcmpeqb xmm1, xmmword ptr [eax];
+    // pmovmskb ecx, xmm1 finds the zero bytes at eax
+    let mut interpreter = self::interpreter();
+    interpreter.store(0x2000, &il::Constant::new_u128(A, 128)).unwrap();
+    run(&X86::new(), &[
+        0x66, 0x0f, 0xef, 0xc9,
+        0x66, 0x0f, 0x74, 0x08,
+        0x66, 0x0f, 0xd7, 0xc9
+    ], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "ecx"), 1);
+}
+
+
+#[test]
+fn pxor_test() {
+    // pxor xmm0, xmm1
+    let mut interpreter = interpreter();
+    run(&X86::new(), &[0x66, 0x0f, 0xef, 0xc1], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), A ^ B);
+
+    // pxor xmm1, xmm1
+    run(&X86::new(), &[0x66, 0x0f, 0xef, 0xc9], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm1"), 0);
+}
+
+
+#[test]
+fn movd_movq_test() {
+    // movd xmm0, ecx zeroes the rest of xmm0
+    let mut interpreter = interpreter();
+    run(&X86::new(), &[0x66, 0x0f, 0x6e, 0xc1], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), 0xffff_ffff);
+
+    // movd edx, xmm1
+    run(&X86::new(), &[0x66, 0x0f, 0x7e, 0xca], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "edx"), 0xff02_ff00);
+
+    // movq xmm0, qword ptr [eax] zeroes the top of xmm0
+    let mut interpreter = self::interpreter();
+    run(&X86::new(), &[0xf3, 0x0f, 0x7e, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), B & 0xffff_ffff_ffff_ffff);
+
+    // movq xmm0, xmm1
+    interpreter.set_scalar("xmm0", il::Constant::new_u128(A, 128));
+    run(&X86::new(), &[0xf3, 0x0f, 0x7e, 0xc1], 0x1000, &mut interpreter);
+    assert_eq!(value_u128(&interpreter, "xmm0"), B & 0xffff_ffff_ffff_ffff);
+
+    // movq qword ptr [ebx], xmm1 only writes 8 bytes
+    interpreter.store(0x3000, &il::Constant::new_u128(A, 128)).unwrap();
+    run(&X86::new(), &[0x66, 0x0f, 0xd6, 0x0b], 0x1000, &mut interpreter);
+    assert_eq!(load_u128(&interpreter, 0x3000, 128), (A & !0xffff_ffff_ffff_ffff) | (B & 0xffff_ffff_ffff_ffff));
+}
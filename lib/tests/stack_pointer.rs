@@ -0,0 +1,151 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+
+
+#[cfg(test)]
+fn push(block: &mut il::Block, value: il::Expression) {
+    block.assign(
+        il::scalar("esp", 32),
+        il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap()
+    );
+    block.store(il::array("mem", 1 << 32), il::expr_scalar("esp", 32), value);
+}
+
+
+#[cfg(test)]
+fn pop(block: &mut il::Block, dst: il::Scalar) {
+    block.load(dst, il::expr_scalar("esp", 32), il::array("mem", 1 << 32));
+    block.assign(
+        il::scalar("esp", 32),
+        il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap()
+    );
+}
+
+
+#[test]
+fn stack_pointer_test() {
+    // push ebp
+    // mov ebp, esp
+    // sub esp, 8
+    // mov [ebp - 4], 7
+    // mov eax, [ebp + 8]
+    // push eax
+    // call 0x1234 (stdcall, one argument)
+    // leave
+    // ret 8
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+    let mem = il::array("mem", 1 << 32);
+
+    let (block_index, local_index, argument_index, return_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        push(block, il::expr_scalar("ebp", 32));
+        block.assign(il::scalar("ebp", 32), il::expr_scalar("esp", 32));
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(8, 32)).unwrap()
+        );
+        block.store(
+            mem.clone(),
+            il::Expression::sub(il::expr_scalar("ebp", 32), il::expr_const(4, 32)).unwrap(),
+            il::expr_const(7, 32)
+        );
+        let local_index = block.instructions().last().unwrap().index();
+        block.load(
+            il::scalar("eax", 32),
+            il::Expression::add(il::expr_scalar("ebp", 32), il::expr_const(8, 32)).unwrap(),
+            mem.clone()
+        );
+        let argument_index = block.instructions().last().unwrap().index();
+        push(block, il::expr_scalar("eax", 32));
+        push(block, il::expr_const(0x2000, 32));
+        block.brc(il::expr_const(0x1234, 32), il::expr_const(1, 1));
+        block.assign(il::scalar("esp", 32), il::expr_scalar("ebp", 32));
+        pop(block, il::scalar("ebp", 32));
+        pop(block, il::scalar("eip", 32));
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(8, 32)).unwrap()
+        );
+        block.brc(il::expr_scalar("eip", 32), il::expr_const(1, 1));
+        let return_index = block.instructions().last().unwrap().index();
+        (block.index(), local_index, argument_index, return_index)
+    };
+
+    control_flow_graph.set_entry(block_index).unwrap();
+
+    let mut conventions = StackConventions::x86();
+    conventions.add_callee_cleanup(0x1234, 4);
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let stack_pointer = analysis.stack_pointer(&conventions).unwrap();
+
+    let local = AnalysisLocation::instruction(block_index, local_index);
+    let argument = AnalysisLocation::instruction(block_index, argument_index);
+    let ret = AnalysisLocation::instruction(block_index, return_index);
+
+    assert_eq!(stack_pointer.delta(&local), Some(-12));
+    assert_eq!(stack_pointer.delta(&ret), Some(12));
+    assert!(stack_pointer.unbalanced().is_empty());
+    assert_eq!(stack_pointer.cleanup(), Some(8));
+
+    let frame = stack_pointer.frame();
+    assert_eq!(frame.variable(&local).unwrap().name(), "var_8");
+    assert_eq!(frame.variable(&argument).unwrap().name(), "arg_0");
+    assert_eq!(frame.arguments().len(), 1);
+    assert_eq!(frame.locals_size(), 20);
+
+    // Without knowing the callee cleans up its argument, leave still restores
+    // the stack pointer from the frame pointer
+    let stack_pointer = analysis.stack_pointer(&StackConventions::x86()).unwrap();
+    assert_eq!(stack_pointer.cleanup(), Some(8));
+}
+
+
+#[test]
+fn stack_pointer_unbalanced_test() {
+    //     test eax, eax
+    //     jz done
+    //     push eax
+    // done:
+    //     ret
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let head_index = control_flow_graph.new_block().unwrap().index();
+
+    let push_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        push(block, il::expr_scalar("eax", 32));
+        block.index()
+    };
+
+    let (done_index, return_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        pop(block, il::scalar("eip", 32));
+        block.brc(il::expr_scalar("eip", 32), il::expr_const(1, 1));
+        (block.index(), block.instructions().last().unwrap().index())
+    };
+
+    let condition = il::Expression::cmpeq(
+        il::expr_scalar("eax", 32),
+        il::expr_const(0, 32)
+    ).unwrap();
+
+    control_flow_graph.conditional_edge(head_index, done_index, condition.clone())
+                      .unwrap();
+    control_flow_graph.conditional_edge(
+        head_index,
+        push_index,
+        il::Expression::cmpeq(condition, il::expr_const(0, 1)).unwrap()
+    ).unwrap();
+    control_flow_graph.unconditional_edge(push_index, done_index).unwrap();
+
+    control_flow_graph.set_entry(head_index).unwrap();
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let stack_pointer = analysis.stack_pointer(&StackConventions::x86()).unwrap();
+
+    let ret = AnalysisLocation::instruction(done_index, return_index);
+    assert_eq!(stack_pointer.delta(&ret), None);
+    assert_eq!(stack_pointer.unbalanced(), vec![&ret]);
+    assert_eq!(stack_pointer.cleanup(), None);
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+
+
+#[cfg(test)]
+fn esp_add(block: &mut il::Block, value: i64) {
+    block.assign(
+        il::scalar("esp", 32),
+        il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(value as u64 & 0xffffffff, 32)).unwrap()
+    );
+}
+
+
+#[cfg(test)]
+fn call(block: &mut il::Block, target: u64) {
+    esp_add(block, -4);
+    block.store(il::array("mem", 1 << 32), il::expr_scalar("esp", 32), il::expr_const(0, 32));
+    block.brc(il::expr_const(target, 32), il::expr_const(1, 1));
+}
+
+
+#[cfg(test)]
+fn ret(block: &mut il::Block) {
+    block.load(il::scalar("eip", 32), il::expr_scalar("esp", 32), il::array("mem", 1 << 32));
+    esp_add(block, 4);
+    block.brc(il::expr_scalar("eip", 32), il::expr_const(1, 1));
+}
+
+
+#[cfg(test)]
+fn function(address: u64, f: &Fn(&mut il::Block)) -> il::Function {
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+    let index = {
+        let block = control_flow_graph.new_block().unwrap();
+        f(block);
+        block.index()
+    };
+    control_flow_graph.set_entry(index).unwrap();
+    control_flow_graph.set_exit(index).unwrap();
+    il::Function::new(address, control_flow_graph)
+}
+
+
+#[test]
+fn taint_test() {
+    let mem = il::array("mem", 1 << 32);
+
+    // read(0, buf, 16) into a buffer on the stack, and return its first word
+    let reader = function(0x1000, &|block| {
+        esp_add(block, -16);
+        block.assign(il::scalar("eax", 32), il::expr_const(3, 32));
+        block.assign(il::scalar("ebx", 32), il::expr_const(0, 32));
+        block.assign(il::scalar("ecx", 32), il::expr_scalar("esp", 32));
+        block.assign(il::scalar("edx", 32), il::expr_const(16, 32));
+        block.raise(il::expr_scalar("sysenter", 1));
+        block.load(il::scalar("eax", 32), il::expr_scalar("esp", 32), mem.clone());
+        esp_add(block, 16);
+        ret(block);
+    });
+
+    // Store zero to the address passed as the first argument
+    let writer = function(0x3000, &|block| {
+        block.load(
+            il::scalar("ecx", 32),
+            il::Expression::add(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap(),
+            mem.clone()
+        );
+        block.store(mem.clone(), il::expr_scalar("ecx", 32), il::expr_const(0, 32));
+        ret(block);
+    });
+
+    let main = function(0x2000, &|block| {
+        call(block, 0x1000);
+        esp_add(block, -4);
+        block.store(mem.clone(), il::expr_scalar("esp", 32), il::expr_scalar("eax", 32));
+        call(block, 0x3000);
+        esp_add(block, 4);
+        ret(block);
+    });
+
+    let mut program = il::Program::new();
+    program.add_function(reader);
+    program.add_function(writer);
+    program.add_function(main);
+
+    let flows = taint(&program, &TaintConfiguration::linux_x86()).unwrap();
+
+    assert_eq!(flows.len(), 1);
+    let flow = &flows[0];
+    assert_eq!(*flow.sink(), TaintSink::StoreAddress);
+    assert_eq!(flow.source().function(), 0x1000);
+    assert_eq!(flow.path().last().unwrap().function(), 0x3000);
+    assert!(flow.path().iter().any(|step| step.function() == 0x2000));
+}
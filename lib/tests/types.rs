@@ -0,0 +1,73 @@
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+
+
+#[cfg(test)]
+fn plus(scalar: &str, offset: u64) -> il::Expression {
+    il::Expression::add(il::expr_scalar(scalar, 32), il::expr_const(offset, 32)).unwrap()
+}
+
+
+#[test]
+fn types_test() {
+    // eax = [esp + 4]
+    // ecx = [eax + 8]
+    // edx = [eax + 4]
+    // esi = divs(ecx, 3)
+    // [eax] = esi
+    // edi = divu(edx, 2)
+    // [eax + 12] = edi
+    // eip = [esp]
+    // esp = esp + 4
+    // brc eip
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let (block_index, load_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.load(il::scalar("eax", 32), plus("esp", 4), il::array("mem", 1 << 32));
+        let load_index = block.instructions().last().unwrap().index();
+        block.load(il::scalar("ecx", 32), plus("eax", 8), il::array("mem", 1 << 32));
+        block.load(il::scalar("edx", 32), plus("eax", 4), il::array("mem", 1 << 32));
+        block.assign(
+            il::scalar("esi", 32),
+            il::Expression::divs(il::expr_scalar("ecx", 32), il::expr_const(3, 32)).unwrap()
+        );
+        block.store(il::array("mem", 1 << 32), il::expr_scalar("eax", 32), il::expr_scalar("esi", 32));
+        block.assign(
+            il::scalar("edi", 32),
+            il::Expression::divu(il::expr_scalar("edx", 32), il::expr_const(2, 32)).unwrap()
+        );
+        block.store(il::array("mem", 1 << 32), plus("eax", 12), il::expr_scalar("edi", 32));
+        block.load(il::scalar("eip", 32), il::expr_scalar("esp", 32), il::array("mem", 1 << 32));
+        block.assign(il::scalar("esp", 32), plus("esp", 4));
+        block.brc(il::expr_scalar("eip", 32), il::expr_const(1, 1));
+        (block.index(), load_index)
+    };
+
+    control_flow_graph.set_entry(block_index).unwrap();
+    control_flow_graph.set_exit(block_index).unwrap();
+
+    let analysis = Analysis::new_with_memory(
+        &control_flow_graph,
+        32,
+        Endian::Little,
+        &Regions::x86(None)
+    ).unwrap();
+    let types = analysis.types().unwrap();
+
+    // The argument is a pointer to a struct
+    let pointer = il::Type::Pointer { bits: 32, pointee: Some(0) };
+    assert_eq!(types.stack(4), Some(&pointer));
+    assert_eq!(types.definition(block_index, load_index), Some(&pointer));
+    assert_eq!(types.argument("esp"), Some(&il::Type::Pointer { bits: 32, pointee: None }));
+    assert_eq!(types.stack(0), Some(&il::Type::Unknown(32)));
+
+    let signed = il::Type::Integer { bits: 32, signedness: il::Signedness::Signed };
+    let unsigned = il::Type::Integer { bits: 32, signedness: il::Signedness::Unsigned };
+    let fields = types.struct_(0).unwrap().fields();
+    assert_eq!(fields.len(), 4);
+    assert_eq!(fields[&0], signed);
+    assert_eq!(fields[&4], unsigned);
+    assert_eq!(fields[&8], signed);
+    assert_eq!(fields[&12], unsigned);
+}
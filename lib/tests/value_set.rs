@@ -0,0 +1,266 @@
+#[cfg(test)]use analysis;
+#[cfg(test)]use analysis::*;
+#[cfg(test)]use il;
+#[cfg(test)]use loader;
+
+
+#[test]
+fn strided_interval_test() {
+    let a = StridedInterval::new(32, 4, 0x1000, 0x1010);
+    let b = StridedInterval::new(32, 8, 4, 12);
+
+    assert_eq!(a.add(&b), StridedInterval::new(32, 4, 0x1004, 0x101c));
+    assert_eq!(a.sub(&b), StridedInterval::new(32, 4, 0xff4, 0x100c));
+    assert_eq!(a.join(&b), StridedInterval::new(32, 4, 4, 0x1010));
+    assert_eq!(a.cmpeq(&b).constant_value(), Some(0));
+    assert_eq!(b.cmpltu(&a).constant_value(), Some(1));
+
+    // -4..-1 sign extends to the top of the 64-bit range
+    let negative = StridedInterval::new(8, 1, 0xfc, 0xff);
+    assert_eq!(
+        negative.sext(64),
+        StridedInterval::new(64, 1, 0xfffffffffffffffc, 0xffffffffffffffff)
+    );
+    assert_eq!(negative.cmplts(&StridedInterval::value(8, 0)).constant_value(), Some(1));
+    assert_eq!(negative.cmpltu(&StridedInterval::value(8, 0)).constant_value(), Some(0));
+
+    // Wrapping loses everything but the stride
+    let wrapped = StridedInterval::new(8, 4, 0xf0, 0xfc).add(&StridedInterval::value(8, 8));
+    assert_eq!(wrapped, StridedInterval::new(8, 4, 0, 0xfc));
+}
+
+
+#[test]
+fn value_set_loop_test() {
+    // i = 0
+    // while i < 100:
+    //     [0x1000 + i] = i
+    //     i = i + 4
+    // r = i
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let head_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(il::scalar("i", 32), il::expr_const(0, 32));
+        block.index()
+    };
+
+    let header_index = control_flow_graph.new_block().unwrap().index();
+
+    let (body_index, store_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.store(
+            il::array("mem", 1 << 32),
+            il::Expression::add(il::expr_const(0x1000, 32), il::expr_scalar("i", 32)).unwrap(),
+            il::expr_scalar("i", 32)
+        );
+        block.assign(
+            il::scalar("i", 32),
+            il::Expression::add(il::expr_scalar("i", 32), il::expr_const(4, 32)).unwrap()
+        );
+        (block.index(), block.instructions()[0].index())
+    };
+
+    let (tail_index, r_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(il::scalar("r", 32), il::expr_scalar("i", 32));
+        (block.index(), block.instructions()[0].index())
+    };
+
+    let condition = il::Expression::cmpltu(
+        il::expr_scalar("i", 32),
+        il::expr_const(100, 32)
+    ).unwrap();
+
+    control_flow_graph.unconditional_edge(head_index, header_index).unwrap();
+    control_flow_graph.conditional_edge(header_index, body_index, condition.clone())
+                      .unwrap();
+    control_flow_graph.conditional_edge(
+        header_index,
+        tail_index,
+        il::Expression::cmpeq(condition, il::expr_const(0, 1)).unwrap()
+    ).unwrap();
+    control_flow_graph.unconditional_edge(body_index, header_index).unwrap();
+
+    control_flow_graph.set_entry(head_index).unwrap();
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let value_set = analysis.value_set_with_domain(
+        4,
+        analysis::Endian::Little,
+        Domain::StridedIntervals
+    ).unwrap();
+
+    // i is a multiple of 4 in the loop body
+    let store = AnalysisLocation::instruction(body_index, store_index);
+    assert_eq!(
+        value_set[&store].get(&il::scalar("i", 32)),
+        Some(&LatticeValue::Interval(StridedInterval::new(32, 4, 0, 96)))
+    );
+
+    // and exactly 100 once the loop exits
+    let r = AnalysisLocation::instruction(tail_index, r_index);
+    assert_eq!(
+        value_set[&r].get(&il::scalar("r", 32)),
+        Some(&LatticeValue::value(il::const_(100, 32)))
+    );
+
+    // Without intervals, i is lost to Join
+    let value_set = analysis.value_set(4, analysis::Endian::Little).unwrap();
+    assert_eq!(
+        value_set[&r].get(&il::scalar("r", 32)),
+        Some(&LatticeValue::Join)
+    );
+}
+
+
+#[test]
+fn value_set_unsigned_compare_test() {
+    // x = rsp + rsp
+    // y = 0x10
+    // if x < y:
+    //     r = 1
+    // x is unknown, as adding two pointers gives Join
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+
+    let head_index = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(
+            il::scalar("x", 64),
+            il::Expression::add(il::expr_scalar("rsp", 64), il::expr_scalar("rsp", 64)).unwrap()
+        );
+        block.assign(il::scalar("y", 64), il::expr_const(0x10, 64));
+        block.index()
+    };
+
+    let (then_index, r_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(il::scalar("r", 64), il::expr_const(1, 64));
+        (block.index(), block.instructions()[0].index())
+    };
+
+    let condition = il::Expression::cmpltu(
+        il::expr_scalar("x", 64),
+        il::expr_scalar("y", 64)
+    ).unwrap();
+    control_flow_graph.conditional_edge(head_index, then_index, condition).unwrap();
+
+    control_flow_graph.set_entry(head_index).unwrap();
+
+    let mut regions = Regions::new();
+    regions.set_stack_pointer(Some(il::scalar("rsp", 64)));
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let value_set = analysis.value_set_regions(
+        4,
+        analysis::Endian::Little,
+        Domain::StridedIntervals,
+        &regions
+    ).unwrap();
+
+    // The taken edge is feasible, and x is below 0x10 after it
+    let state = &value_set[&AnalysisLocation::instruction(then_index, r_index)];
+    assert_eq!(
+        state.get(&il::scalar("r", 64)),
+        Some(&LatticeValue::value(il::const_(1, 64)))
+    );
+    assert_eq!(
+        state.get(&il::scalar("x", 64)),
+        Some(&LatticeValue::Interval(StridedInterval::new(64, 1, 0, 0xf)))
+    );
+}
+
+
+#[test]
+fn value_set_regions_test() {
+    // push ebp
+    // mov ebp, esp
+    // mov [ebp - 4], 7
+    // call malloc
+    // mov [eax + 8], 5
+    // mov ecx, [eax + 8]
+    // mov edx, [ebp - 4]
+    // mov ebx, [0x1000]
+    let mut control_flow_graph = il::ControlFlowGraph::new();
+    let mem = il::array("mem", 1 << 32);
+
+    let (block_index, last_index) = {
+        let block = control_flow_graph.new_block().unwrap();
+        block.assign(
+            il::scalar("esp", 32),
+            il::Expression::sub(il::expr_scalar("esp", 32), il::expr_const(4, 32)).unwrap()
+        );
+        block.store(mem.clone(), il::expr_scalar("esp", 32), il::expr_scalar("ebp", 32));
+        block.assign(il::scalar("ebp", 32), il::expr_scalar("esp", 32));
+        block.store(
+            mem.clone(),
+            il::Expression::sub(il::expr_scalar("ebp", 32), il::expr_const(4, 32)).unwrap(),
+            il::expr_const(7, 32)
+        );
+        block.brc(il::expr_const(0x8000, 32), il::expr_const(1, 1));
+        block.store(
+            mem.clone(),
+            il::Expression::add(il::expr_scalar("eax", 32), il::expr_const(8, 32)).unwrap(),
+            il::expr_const(5, 32)
+        );
+        block.load(
+            il::scalar("ecx", 32),
+            il::Expression::add(il::expr_scalar("eax", 32), il::expr_const(8, 32)).unwrap(),
+            mem.clone()
+        );
+        block.load(
+            il::scalar("edx", 32),
+            il::Expression::sub(il::expr_scalar("ebp", 32), il::expr_const(4, 32)).unwrap(),
+            mem.clone()
+        );
+        block.load(il::scalar("ebx", 32), il::expr_const(0x1000, 32), mem.clone());
+        block.assign(il::scalar("result", 32), il::expr_const(0, 32));
+        (block.index(), block.instructions().last().unwrap().index())
+    };
+
+    control_flow_graph.set_entry(block_index).unwrap();
+
+    let mut memory = loader::memory::Memory::new();
+    memory.add_segment(loader::memory::MemorySegment::new(
+        0x1000,
+        vec![0x78, 0x56, 0x34, 0x12],
+        loader::memory::READ
+    ));
+
+    let mut regions = Regions::x86(Some(&memory));
+    regions.add_allocator(0x8000, il::scalar("eax", 32));
+
+    let analysis = Analysis::new(&control_flow_graph).unwrap();
+    let value_set = analysis.value_set_regions(
+        4,
+        analysis::Endian::Little,
+        Domain::StridedIntervals,
+        &regions
+    ).unwrap();
+
+    let state = &value_set[&AnalysisLocation::instruction(block_index, last_index)];
+
+    assert_eq!(
+        state.get(&il::scalar("ebp", 32)),
+        Some(&LatticeValue::Pointer(
+            Region::Stack,
+            StridedInterval::value(32, 0xfffffffc)
+        ))
+    );
+    match state.get(&il::scalar("eax", 32)) {
+        Some(&LatticeValue::Pointer(Region::Heap(_), _)) => {},
+        eax => panic!("eax should point to the heap, found {:?}", eax)
+    }
+    assert_eq!(
+        state.get(&il::scalar("ecx", 32)),
+        Some(&LatticeValue::value(il::const_(5, 32)))
+    );
+    assert_eq!(
+        state.get(&il::scalar("edx", 32)),
+        Some(&LatticeValue::value(il::const_(7, 32)))
+    );
+    assert_eq!(
+        state.get(&il::scalar("ebx", 32)),
+        Some(&LatticeValue::value(il::const_(0x12345678, 32)))
+    );
+}
@@ -0,0 +1,548 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use std::collections::BTreeMap;
+#[cfg(test)]use tests::{load, run, value};
+#[cfg(test)]use translator::x86::X86;
+
+
+#[cfg(test)]
+const REGISTERS: [&'static str; 8] = ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi"];
+
+
+#[cfg(test)]
+const FLAGS: [&'static str; 6] = ["CF", "PF", "AF", "ZF", "SF", "OF"];
+
+
+/// The registers, flags and memory these instructions read and write.
+#[cfg(test)]
+#[derive(Clone)]
+struct State {
+    registers: BTreeMap<&'static str, u32>,
+    flags: BTreeMap<&'static str, bool>,
+    memory: BTreeMap<u64, u8>
+}
+
+
+#[cfg(test)]
+impl State {
+    fn new() -> State {
+        let mut registers = BTreeMap::new();
+        for (i, &name) in REGISTERS.iter().enumerate() {
+            registers.insert(name, 0x1010_1010 * (i as u32 + 1));
+        }
+        registers.insert("ebx", 0x2000);
+        registers.insert("esp", 0x7000);
+        registers.insert("ebp", 0x7800);
+        registers.insert("esi", 0x2100);
+
+        let mut memory = BTreeMap::new();
+        for address in (0x2000..0x2200).chain(0x6f00..0x7100) {
+            memory.insert(address, (address * 7) as u8);
+        }
+
+        State {
+            registers: registers,
+            flags: FLAGS.iter().map(|&flag| (flag, false)).collect(),
+            memory: memory
+        }
+    }
+
+    fn reg(&self, name: &str) -> u32 {
+        self.registers[name]
+    }
+
+    fn set_reg(&mut self, name: &'static str, value: u32) {
+        self.registers.insert(name, value);
+    }
+
+    fn al(&self) -> u32 {
+        self.reg("eax") & 0xff
+    }
+
+    fn ah(&self) -> u32 {
+        (self.reg("eax") >> 8) & 0xff
+    }
+
+    fn set_ax(&mut self, al: u32, ah: u32) {
+        let eax = (self.reg("eax") & 0xffff_0000) | ((ah & 0xff) << 8) | (al & 0xff);
+        self.set_reg("eax", eax);
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags[name]
+    }
+
+    fn set_flag(&mut self, name: &'static str, value: bool) {
+        self.flags.insert(name, value);
+    }
+
+    /// Sets SF, ZF and PF from a result of `bits` bits.
+    fn set_szp(&mut self, result: u32, bits: u32) {
+        let mask = ((1u64 << bits) - 1) as u32;
+        self.set_flag("SF", (result >> (bits - 1)) & 1 == 1);
+        self.set_flag("ZF", result & mask == 0);
+        self.set_flag("PF", (result & 0xff).count_ones() % 2 == 0);
+    }
+
+    fn load(&self, address: u64, bytes: u64) -> u64 {
+        (0..bytes).fold(0, |value, i| value | (self.memory[&(address + i)] as u64) << (i * 8))
+    }
+
+    fn store(&mut self, address: u64, bytes: u64, value: u64) {
+        for i in 0..bytes {
+            self.memory.insert(address + i, (value >> (i * 8)) as u8);
+        }
+    }
+
+    fn push(&mut self, value: u32) {
+        let esp = self.reg("esp") - 4;
+        self.set_reg("esp", esp);
+        self.store(esp as u64, 4, value as u64);
+    }
+
+    fn pop(&mut self) -> u32 {
+        let esp = self.reg("esp");
+        self.set_reg("esp", esp + 4);
+        self.load(esp as u64, 4) as u32
+    }
+
+    fn interpreter(&self) -> Interpreter {
+        let mut interpreter = Interpreter::new(Endian::Little);
+        for (&name, &value) in &self.registers {
+            interpreter.set_scalar(name, il::const_(value as u64, 32));
+        }
+        for (&name, &value) in &self.flags {
+            interpreter.set_scalar(name, il::const_(value as u64, 1));
+        }
+        interpreter.set_scalar("DF", il::const_(0, 1));
+        for (&address, &byte) in &self.memory {
+            interpreter.store(address, &il::const_(byte as u64, 8)).unwrap();
+        }
+        interpreter
+    }
+
+    /// Lifts the instruction in `bytes` and interprets it over this state.
+    fn lift(&self, bytes: &[u8]) -> State {
+        let mut interpreter = self.interpreter();
+        run(&X86::new(), bytes, 0x1000, &mut interpreter);
+
+        let mut state = self.clone();
+        for &name in REGISTERS.iter() {
+            state.set_reg(name, value(&interpreter, name) as u32);
+        }
+        for &name in FLAGS.iter() {
+            state.set_flag(name, value(&interpreter, name) == 1);
+        }
+        for address in self.memory.keys() {
+            state.memory.insert(*address, load(&interpreter, *address, 8) as u8);
+        }
+        state
+    }
+}
+
+
+/// Checks the lifted semantics of `bytes` against `reference` from `state`,
+/// ignoring the flags in `undefined`.
+#[cfg(test)]
+fn check<F>(bytes: &[u8], state: &State, undefined: &[&'static str], reference: F)
+where F: Fn(&mut State) {
+    let lifted = state.lift(bytes);
+    let mut expected = state.clone();
+    reference(&mut expected);
+    for &flag in undefined {
+        expected.set_flag(flag, lifted.flag(flag));
+    }
+    assert_eq!(lifted.registers, expected.registers, "{:02x?} from {:x?} {:?}", bytes, state.registers, state.flags);
+    assert_eq!(lifted.flags, expected.flags, "{:02x?} from {:x?} {:?}", bytes, state.registers, state.flags);
+    for (address, byte) in &expected.memory {
+        assert_eq!(lifted.memory[address], *byte, "{:02x?} at {:x} from {:x?}", bytes, address, state.registers);
+    }
+}
+
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug)]
+enum BitOp { Bt, Btc, Btr, Bts }
+
+
+#[cfg(test)]
+impl BitOp {
+    /// The opcode of `op r/m32, r32`, and the reg field of `op r/m32, imm8`
+    fn encoding(&self) -> (u8, u8) {
+        match *self {
+            BitOp::Bt => (0xa3, 4),
+            BitOp::Bts => (0xab, 5),
+            BitOp::Btr => (0xb3, 6),
+            BitOp::Btc => (0xbb, 7)
+        }
+    }
+
+    fn apply(&self, value: u64, bit: u64) -> u64 {
+        match *self {
+            BitOp::Bt => value,
+            BitOp::Btc => value ^ (1 << bit),
+            BitOp::Btr => value & !(1 << bit),
+            BitOp::Bts => value | (1 << bit)
+        }
+    }
+}
+
+
+#[test]
+fn bit_test_test() {
+    let ops = [BitOp::Bt, BitOp::Btc, BitOp::Btr, BitOp::Bts];
+    let offsets: [i32; 8] = [0, 5, 17, 31, 37, 100, -1, -33];
+
+    for &op in ops.iter() {
+        let (opcode, reg) = op.encoding();
+
+        for &offset in offsets.iter() {
+            let mut state = State::new();
+            state.set_reg("eax", 0x8421_0f0f);
+            state.set_reg("ecx", offset as u32);
+
+            // op eax, ecx and op eax, imm8 take the offset modulo 32
+            let apply = |state: &mut State| {
+                let bit = offset as u64 & 31;
+                let eax = state.reg("eax") as u64;
+                state.set_flag("CF", (eax >> bit) & 1 == 1);
+                state.set_reg("eax", op.apply(eax, bit) as u32);
+            };
+            check(&[0x0f, opcode, 0xc8], &state, &["PF", "AF", "ZF", "SF", "OF"], &apply);
+            check(&[0x0f, 0xba, 0xe0 | (reg << 3), offset as u8], &state, &["PF", "AF", "ZF", "SF", "OF"], &apply);
+
+            // op dword ptr [ebx + 0x100], ecx addresses the bit string at
+            // ebx + 0x100, reaching outside of the dword
+            check(&[0x0f, opcode, 0x8b, 0x00, 0x01, 0x00, 0x00], &state, &["PF", "AF", "ZF", "SF", "OF"], |state| {
+                let address = (0x2100 + (offset as i64 >> 3)) as u64;
+                let bit = offset as u64 & 7;
+                let byte = state.load(address, 1);
+                state.set_flag("CF", (byte >> bit) & 1 == 1);
+                state.store(address, 1, op.apply(byte, bit));
+            });
+
+            // op dword ptr [ebx], imm8 stays in the dword
+            check(&[0x0f, 0xba, 0x23 | (reg << 3), offset as u8], &state, &["PF", "AF", "ZF", "SF", "OF"], |state| {
+                let bit = offset as u64 & 31;
+                let value = state.load(0x2000, 4);
+                state.set_flag("CF", (value >> bit) & 1 == 1);
+                state.store(0x2000, 4, op.apply(value, bit));
+            });
+        }
+    }
+}
+
+
+#[test]
+fn xlat_lahf_sahf_test() {
+    for &al in &[0x00, 0x7f, 0x80, 0xff] {
+        let mut state = State::new();
+        state.set_ax(al, 0x55);
+
+        // xlatb
+        check(&[0xd7], &state, &[], |state| {
+            let al = state.load(0x2000 + state.al() as u64, 1) as u32;
+            let ah = state.ah();
+            state.set_ax(al, ah);
+        });
+    }
+
+    for flags in 0..32 {
+        let mut state = State::new();
+        for (i, &flag) in ["CF", "PF", "AF", "ZF", "SF"].iter().enumerate() {
+            state.set_flag(flag, (flags >> i) & 1 == 1);
+        }
+
+        // lahf
+        check(&[0x9f], &state, &[], |state| {
+            let ah = (state.flag("SF") as u32) << 7
+                | (state.flag("ZF") as u32) << 6
+                | (state.flag("AF") as u32) << 4
+                | (state.flag("PF") as u32) << 2
+                | 2
+                | state.flag("CF") as u32;
+            let al = state.al();
+            state.set_ax(al, ah);
+        });
+
+        // sahf, from every combination of the flag bits of ah
+        let mut state = State::new();
+        let ah = (flags & 1) | ((flags & 2) << 1) | ((flags & 4) << 2) | ((flags & 8) << 3) | ((flags & 16) << 3);
+        state.set_ax(0x12, ah | 0x2a);
+        state.set_flag("OF", true);
+        check(&[0x9e], &state, &[], |state| {
+            let ah = state.ah();
+            state.set_flag("CF", ah & 1 == 1);
+            state.set_flag("PF", (ah >> 2) & 1 == 1);
+            state.set_flag("AF", (ah >> 4) & 1 == 1);
+            state.set_flag("ZF", (ah >> 6) & 1 == 1);
+            state.set_flag("SF", (ah >> 7) & 1 == 1);
+        });
+    }
+}
+
+
+#[test]
+fn pushad_popad_enter_test() {
+    let state = State::new();
+
+    // pushad
+    check(&[0x60], &state, &[], |state| {
+        let esp = state.reg("esp");
+        for &name in REGISTERS.iter() {
+            let value = if name == "esp" { esp } else { state.reg(name) };
+            state.push(value);
+        }
+    });
+
+    // popad skips the esp it popped
+    check(&[0x61], &state, &[], |state| {
+        for &name in REGISTERS.iter().rev() {
+            let value = state.pop();
+            if name != "esp" {
+                state.set_reg(name, value);
+            }
+        }
+    });
+
+    // enter 0x10, 0
+    check(&[0xc8, 0x10, 0x00, 0x00], &state, &[], |state| {
+        let ebp = state.reg("ebp");
+        state.push(ebp);
+        let frame = state.reg("esp");
+        state.set_reg("ebp", frame);
+        state.set_reg("esp", frame - 0x10);
+    });
+
+    // enter 8, 1 also pushes the new frame pointer
+    check(&[0xc8, 0x08, 0x00, 0x01], &state, &[], |state| {
+        let ebp = state.reg("ebp");
+        state.push(ebp);
+        let frame = state.reg("esp");
+        state.push(frame);
+        state.set_reg("ebp", frame);
+        let esp = state.reg("esp");
+        state.set_reg("esp", esp - 8);
+    });
+}
+
+
+/// Rotates the low `bits` of eax through CF by `count`, left if `left`.
+#[cfg(test)]
+fn rotate_carry(state: &mut State, bits: u32, count: u32, left: bool) {
+    let mask = ((1u64 << bits) - 1) as u32;
+    let count = (count & 0x1f) % (bits + 1);
+    if count == 0 {
+        return;
+    }
+
+    let eax = state.reg("eax");
+    let mut value = eax & mask;
+    let mut cf = state.flag("CF");
+    let msb = |value: u32| (value >> (bits - 1)) & 1 == 1;
+
+    if left {
+        for _ in 0..count {
+            let carry = msb(value);
+            value = ((value << 1) | cf as u32) & mask;
+            cf = carry;
+        }
+        state.set_flag("OF", msb(value) != cf);
+    }
+    else {
+        state.set_flag("OF", msb(value) != cf);
+        for _ in 0..count {
+            let carry = value & 1 == 1;
+            value = (value >> 1) | ((cf as u32) << (bits - 1));
+            cf = carry;
+        }
+    }
+
+    state.set_flag("CF", cf);
+    state.set_reg("eax", (eax & !mask) | value);
+}
+
+
+#[test]
+fn rotate_carry_test() {
+    for &eax in &[0x0000_0001, 0x8000_0000, 0x1234_5678, 0xffff_ffff] {
+        for &cf in &[false, true] {
+            for &cl in &[0, 1, 4, 9, 10, 31, 33] {
+                let mut state = State::new();
+                state.set_reg("eax", eax);
+                state.set_reg("ecx", cl);
+                state.set_flag("CF", cf);
+
+                // OF is only defined for rotates by 1
+                let undefined: &[&'static str] = if cl & 0x1f == 1 { &[] } else { &["OF"] };
+                let undefined_8: &[&'static str] = if (cl & 0x1f) % 9 == 1 { &[] } else { &["OF"] };
+
+                // rcl eax, cl; rcr eax, cl
+                check(&[0xd3, 0xd0], &state, undefined, |state| rotate_carry(state, 32, cl, true));
+                check(&[0xd3, 0xd8], &state, undefined, |state| rotate_carry(state, 32, cl, false));
+
+                // rcl al, cl; rcr al, cl rotate through 9 bits
+                check(&[0xd2, 0xd0], &state, undefined_8, |state| rotate_carry(state, 8, cl, true));
+                check(&[0xd2, 0xd8], &state, undefined_8, |state| rotate_carry(state, 8, cl, false));
+
+                // rcl al, 1; rcr eax, 1
+                check(&[0xd0, 0xd0], &state, &[], |state| rotate_carry(state, 8, 1, true));
+                check(&[0xd1, 0xd8], &state, &[], |state| rotate_carry(state, 32, 1, false));
+            }
+        }
+    }
+}
+
+
+#[test]
+fn movbe_bit_count_test() {
+    for &value in &[0, 1, 0x8000_0000, 0x0001_0000, 0x00f0_0f00, 0xffff_ffff] {
+        let mut state = State::new();
+        state.set_reg("ecx", value);
+        state.store(0x2000, 4, value as u64);
+        for &flag in FLAGS.iter() {
+            state.set_flag(flag, true);
+        }
+
+        // movbe eax, dword ptr [ebx]; movbe dword ptr [ebx], ecx
+        check(&[0x0f, 0x38, 0xf0, 0x03], &state, &[], |state| {
+            state.set_reg("eax", value.swap_bytes());
+        });
+        check(&[0x0f, 0x38, 0xf1, 0x0b], &state, &[], |state| {
+            state.store(0x2000, 4, value.swap_bytes() as u64);
+        });
+
+        // popcnt eax, ecx clears every flag but ZF
+        check(&[0xf3, 0x0f, 0xb8, 0xc1], &state, &[], |state| {
+            state.set_reg("eax", value.count_ones());
+            for &flag in FLAGS.iter() {
+                state.set_flag(flag, false);
+            }
+            state.set_flag("ZF", value == 0);
+        });
+
+        // lzcnt eax, ecx; tzcnt eax, ecx set CF when the source is zero
+        check(&[0xf3, 0x0f, 0xbd, 0xc1], &state, &["PF", "AF", "SF", "OF"], |state| {
+            state.set_reg("eax", value.leading_zeros());
+            state.set_flag("CF", value == 0);
+            state.set_flag("ZF", value.leading_zeros() == 0);
+        });
+        check(&[0xf3, 0x0f, 0xbc, 0xc1], &state, &["PF", "AF", "SF", "OF"], |state| {
+            state.set_reg("eax", value.trailing_zeros());
+            state.set_flag("CF", value == 0);
+            state.set_flag("ZF", value.trailing_zeros() == 0);
+        });
+    }
+}
+
+
+#[test]
+fn cmpxchg8b_test() {
+    for &equal in &[false, true] {
+        let mut state = State::new();
+        let memory = state.load(0x2100, 8);
+        let edx_eax = if equal { memory } else { memory ^ 0x1_0000_0000 };
+        state.set_reg("eax", edx_eax as u32);
+        state.set_reg("edx", (edx_eax >> 32) as u32);
+
+        // cmpxchg8b qword ptr [esi]
+        check(&[0x0f, 0xc7, 0x0e], &state, &[], |state| {
+            state.set_flag("ZF", equal);
+            if equal {
+                let ecx_ebx = (state.reg("ecx") as u64) << 32 | state.reg("ebx") as u64;
+                state.store(0x2100, 8, ecx_ebx);
+            }
+            else {
+                state.set_reg("eax", memory as u32);
+                state.set_reg("edx", (memory >> 32) as u32);
+            }
+        });
+    }
+}
+
+
+#[test]
+fn bcd_test() {
+    let values = [0x00, 0x06, 0x09, 0x0a, 0x0f, 0x19, 0x45, 0x99, 0x9a, 0xa0, 0xf0, 0xf5];
+
+    for &al in values.iter() {
+        for &(cf, af) in &[(false, false), (false, true), (true, false), (true, true)] {
+            let mut state = State::new();
+            state.set_ax(al, 0x07);
+            state.set_flag("CF", cf);
+            state.set_flag("AF", af);
+
+            // daa, whose CF only depends on the high digit adjustment
+            check(&[0x27], &state, &["OF"], |state| {
+                let mut result = al;
+                let low = al & 0xf > 9 || af;
+                if low {
+                    result += 6;
+                }
+                let high = al > 0x99 || cf;
+                if high {
+                    result += 0x60;
+                }
+                state.set_flag("AF", low);
+                state.set_flag("CF", high);
+                state.set_ax(result, 0x07);
+                state.set_szp(result & 0xff, 8);
+            });
+
+            // das
+            check(&[0x2f], &state, &["OF"], |state| {
+                let mut result = al;
+                let mut carry = false;
+                if al & 0xf > 9 || af {
+                    carry = cf || result < 6;
+                    result = result.wrapping_sub(6);
+                    state.set_flag("AF", true);
+                }
+                else {
+                    state.set_flag("AF", false);
+                }
+                if al > 0x99 || cf {
+                    result = result.wrapping_sub(0x60);
+                    carry = true;
+                }
+                state.set_flag("CF", carry);
+                state.set_ax(result, 0x07);
+                state.set_szp(result & 0xff, 8);
+            });
+
+            // aaa
+            check(&[0x37], &state, &["PF", "ZF", "SF", "OF"], |state| {
+                let adjust = al & 0xf > 9 || af;
+                let (al, ah) = if adjust { (al + 6, 0x08) } else { (al, 0x07) };
+                state.set_ax(al & 0xf, ah);
+                state.set_flag("AF", adjust);
+                state.set_flag("CF", adjust);
+            });
+
+            // aas, which borrows from ah when al is below 6
+            if al >= 6 {
+                check(&[0x3f], &state, &["PF", "ZF", "SF", "OF"], |state| {
+                    let adjust = al & 0xf > 9 || af;
+                    let (al, ah) = if adjust { (al - 6, 0x06) } else { (al, 0x07) };
+                    state.set_ax(al & 0xf, ah);
+                    state.set_flag("AF", adjust);
+                    state.set_flag("CF", adjust);
+                });
+            }
+
+            // aam 10
+            check(&[0xd4, 0x0a], &state, &["CF", "AF", "OF"], |state| {
+                state.set_ax(al % 10, al / 10);
+                state.set_szp(al % 10, 8);
+            });
+
+            // aad 10
+            check(&[0xd5, 0x0a], &state, &["CF", "AF", "OF"], |state| {
+                let result = (al + 0x07 * 10) & 0xff;
+                state.set_ax(result, 0);
+                state.set_szp(result, 8);
+            });
+        }
+    }
+}
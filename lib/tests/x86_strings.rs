@@ -0,0 +1,179 @@
+#[cfg(test)]use engine::Endian;
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use std::collections::BTreeMap;
+#[cfg(test)]use tests::{load, run, value};
+#[cfg(test)]use translator::x86::X86;
+
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StringOp { Cmps, Lods, Movs, Scas, Stos }
+
+
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Prefix { None, Rep, Repne }
+
+
+/// The state a string instruction reads and writes.
+#[cfg(test)]
+#[derive(Clone, Debug)]
+struct State {
+    eax: u64,
+    ecx: u64,
+    esi: u64,
+    edi: u64,
+    df: bool,
+    zf: bool,
+    memory: BTreeMap<u64, u8>
+}
+
+
+#[cfg(test)]
+impl State {
+    fn new(ecx: u64, df: bool) -> State {
+        let mut memory = BTreeMap::new();
+        for address in 0x0f00..0x1100 {
+            memory.insert(address, address as u8);
+        }
+        // The destination matches the source for a few bytes after 0x2010
+        for address in 0x1f00..0x2100 {
+            memory.insert(address, if address < 0x2014 { address as u8 } else { 0xff });
+        }
+
+        State {
+            eax: 0x1514_1312,
+            ecx: ecx,
+            esi: 0x1010,
+            edi: 0x2010,
+            df: df,
+            zf: false,
+            memory: memory
+        }
+    }
+
+    fn load(&self, address: u64, bytes: u64) -> u64 {
+        (0..bytes).fold(0, |value, i| value | (self.memory[&(address + i)] as u64) << (i * 8))
+    }
+
+    fn store(&mut self, address: u64, bytes: u64, value: u64) {
+        for i in 0..bytes {
+            self.memory.insert(address + i, (value >> (i * 8)) as u8);
+        }
+    }
+
+    /// Executes one iteration of the string instruction.
+    fn step(&mut self, op: StringOp, bytes: u64) {
+        let mask = if bytes == 8 { !0 } else { (1 << (bytes * 8)) - 1 };
+        let step = if self.df { bytes.wrapping_neg() } else { bytes };
+        let (esi, edi) = (self.esi, self.edi);
+        match op {
+            StringOp::Cmps => self.zf = self.load(esi, bytes) == self.load(edi, bytes),
+            StringOp::Lods => self.eax = (self.eax & !mask) | self.load(esi, bytes),
+            StringOp::Movs => {
+                let value = self.load(esi, bytes);
+                self.store(edi, bytes, value);
+            },
+            StringOp::Scas => self.zf = self.eax & mask == self.load(edi, bytes),
+            StringOp::Stos => {
+                let value = self.eax & mask;
+                self.store(edi, bytes, value);
+            }
+        }
+        match op {
+            StringOp::Cmps | StringOp::Movs => {
+                self.esi = self.esi.wrapping_add(step) & 0xffff_ffff;
+                self.edi = self.edi.wrapping_add(step) & 0xffff_ffff;
+            },
+            StringOp::Lods => self.esi = self.esi.wrapping_add(step) & 0xffff_ffff,
+            StringOp::Scas | StringOp::Stos => self.edi = self.edi.wrapping_add(step) & 0xffff_ffff
+        }
+    }
+
+    /// Executes the string instruction as the reference for the lifter.
+    fn execute(&mut self, op: StringOp, bytes: u64, prefix: Prefix) {
+        if prefix == Prefix::None {
+            return self.step(op, bytes);
+        }
+        while self.ecx != 0 {
+            self.step(op, bytes);
+            self.ecx -= 1;
+            if op == StringOp::Cmps || op == StringOp::Scas {
+                if (prefix == Prefix::Rep && !self.zf) || (prefix == Prefix::Repne && self.zf) {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn interpreter(&self) -> Interpreter {
+        let mut interpreter = Interpreter::new(Endian::Little);
+        interpreter.set_scalar("eax", il::const_(self.eax, 32));
+        interpreter.set_scalar("ecx", il::const_(self.ecx, 32));
+        interpreter.set_scalar("esi", il::const_(self.esi, 32));
+        interpreter.set_scalar("edi", il::const_(self.edi, 32));
+        interpreter.set_scalar("DF", il::const_(self.df as u64, 1));
+        interpreter.set_scalar("ZF", il::const_(self.zf as u64, 1));
+        for (address, byte) in self.memory.iter() {
+            interpreter.store(*address, &il::const_(*byte as u64, 8)).unwrap();
+        }
+        interpreter
+    }
+}
+
+
+#[test]
+fn x86_strings_test() {
+    let ops = [
+        (StringOp::Cmps, 0xa6),
+        (StringOp::Lods, 0xac),
+        (StringOp::Movs, 0xa4),
+        (StringOp::Scas, 0xae),
+        (StringOp::Stos, 0xaa)
+    ];
+
+    for &(op, opcode) in ops.iter() {
+        for &bytes in [1, 2, 4].iter() {
+            for &df in [false, true].iter() {
+                for &prefix in [Prefix::None, Prefix::Rep, Prefix::Repne].iter() {
+                    if prefix == Prefix::Repne && op != StringOp::Cmps && op != StringOp::Scas {
+                        continue;
+                    }
+                    for &ecx in [0, 1, 8].iter() {
+                        let mut encoding = Vec::new();
+                        match prefix {
+                            Prefix::None => {},
+                            Prefix::Rep => encoding.push(0xf3),
+                            Prefix::Repne => encoding.push(0xf2)
+                        }
+                        if bytes == 2 {
+                            encoding.push(0x66);
+                        }
+                        encoding.push(if bytes == 1 { opcode } else { opcode + 1 });
+
+                        let mut expected = State::new(ecx, df);
+                        let mut interpreter = expected.interpreter();
+
+                        run(&X86::new(), &encoding, 0x1_0000, &mut interpreter);
+                        expected.execute(op, bytes, prefix);
+
+                        let case = format!("{:?} {} bytes, df={}, {:?}, ecx={}", op, bytes, df, prefix, ecx);
+                        assert_eq!(value(&interpreter, "eax"), expected.eax, "eax: {}", case);
+                        assert_eq!(value(&interpreter, "ecx"), expected.ecx, "ecx: {}", case);
+                        assert_eq!(value(&interpreter, "esi"), expected.esi, "esi: {}", case);
+                        assert_eq!(value(&interpreter, "edi"), expected.edi, "edi: {}", case);
+                        assert_eq!(value(&interpreter, "ZF"), expected.zf as u64, "ZF: {}", case);
+                        for (address, byte) in expected.memory.iter() {
+                            assert_eq!(
+                                load(&interpreter, *address, 8),
+                                *byte as u64,
+                                "memory 0x{:x}: {}", address, case
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
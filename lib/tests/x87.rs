@@ -0,0 +1,141 @@
+#[cfg(test)]use engine::{Endian, expr_to_smtlib2};
+#[cfg(test)]use executor::Interpreter;
+#[cfg(test)]use il;
+#[cfg(test)]use tests::{load, run, value};
+#[cfg(test)]use translator::x86::X86;
+
+
+#[cfg(test)]
+fn interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new(Endian::Little);
+    interpreter.set_scalar("eax", il::const_(0x2000, 32));
+    interpreter.set_scalar("ebx", il::const_(0x3000, 32));
+    for i in 0..8 {
+        interpreter.set_scalar(format!("st{}", i), il::const_(0, 64));
+    }
+    interpreter.set_scalar("TOP", il::const_(0, 3));
+    for &flag in &["CF", "PF", "ZF", "SF", "OF"] {
+        interpreter.set_scalar(flag, il::const_(0, 1));
+    }
+    interpreter
+}
+
+
+#[cfg(test)]
+fn store_f64(interpreter: &mut Interpreter, address: u64, value: f64) {
+    interpreter.store(address, &il::const_(value.to_bits(), 64)).unwrap();
+}
+
+
+#[test]
+fn extended_test() {
+    // 1.5, -3.25 and 0 as 80-bit extended precision significands and
+    // sign/exponents
+    let values = [
+        (1.5f64, 0xc000_0000_0000_0000, 0x3fff),
+        (-3.25f64, 0xd000_0000_0000_0000, 0xc000),
+        (0.0f64, 0, 0)
+    ];
+
+    for &(float, significand, sign_exponent) in values.iter() {
+        let mut interpreter = interpreter();
+        interpreter.store(0x2000, &il::const_(significand, 64)).unwrap();
+        interpreter.store(0x2008, &il::const_(sign_exponent, 16)).unwrap();
+
+        // fld tbyte ptr [eax]
+        run(&X86::new(), &[0xdb, 0x28], 0x1000, &mut interpreter);
+        assert_eq!(value(&interpreter, "st0"), float.to_bits());
+        assert_eq!(value(&interpreter, "TOP"), 7);
+
+        // fst qword ptr [ebx]; fstp tbyte ptr [ebx + 0x10]
+        run(&X86::new(), &[0xdd, 0x13, 0xdb, 0x7b, 0x10], 0x1000, &mut interpreter);
+        assert_eq!(load(&interpreter, 0x3000, 64), float.to_bits());
+        assert_eq!(load(&interpreter, 0x3010, 64), significand);
+        assert_eq!(load(&interpreter, 0x3018, 16), sign_exponent);
+        assert_eq!(value(&interpreter, "TOP"), 0);
+    }
+}
+
+
+#[test]
+fn arithmetic_test() {
+    let mut interpreter = interpreter();
+    store_f64(&mut interpreter, 0x2000, 2.5);
+
+    // fld1 pushes onto the stack, wrapping TOP around to 7
+    run(&X86::new(), &[0xd9, 0xe8], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "TOP"), 7);
+
+    // fld qword ptr [eax]
+    run(&X86::new(), &[0xdd, 0x00], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "TOP"), 6);
+    assert_eq!(value(&interpreter, "st0"), 2.5f64.to_bits());
+    assert_eq!(value(&interpreter, "st1"), 1.0f64.to_bits());
+
+    // faddp st(1), st(0)
+    run(&X86::new(), &[0xde, 0xc1], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "TOP"), 7);
+    assert_eq!(value(&interpreter, "st0"), 3.5f64.to_bits());
+
+    // fmul st(0), st(0); fmul qword ptr [eax]
+    run(&X86::new(), &[0xd8, 0xc8, 0xdc, 0x08], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "st0"), 30.625f64.to_bits());
+
+    // fstp qword ptr [ebx] pops, wrapping TOP around to 0
+    run(&X86::new(), &[0xdd, 0x1b], 0x1000, &mut interpreter);
+    assert_eq!(value(&interpreter, "TOP"), 0);
+    assert_eq!(load(&interpreter, 0x3000, 64), 30.625f64.to_bits());
+}
+
+
+#[test]
+fn fucomi_test() {
+    let nan = ::std::f64::NAN;
+    // st0, st1, and the ZF, PF and CF fucomi sets
+    let cases = [
+        (1.0, 2.0, (0, 0, 1)),
+        (2.0, 2.0, (1, 0, 0)),
+        (3.0, 2.0, (0, 0, 0)),
+        (nan, 2.0, (1, 1, 1)),
+        (2.0, nan, (1, 1, 1))
+    ];
+
+    for &(st0, st1, (zf, pf, cf)) in cases.iter() {
+        let mut interpreter = interpreter();
+        interpreter.set_scalar("st0", il::const_(st0.to_bits(), 64));
+        interpreter.set_scalar("st1", il::const_(st1.to_bits(), 64));
+        interpreter.set_scalar("TOP", il::const_(6, 3));
+        interpreter.set_scalar("OF", il::const_(1, 1));
+
+        // fucomi st(0), st(1)
+        run(&X86::new(), &[0xdb, 0xe9], 0x1000, &mut interpreter);
+        let flags = (value(&interpreter, "ZF"), value(&interpreter, "PF"), value(&interpreter, "CF"));
+        assert_eq!(flags, (zf, pf, cf), "{} {}", st0, st1);
+        assert_eq!(value(&interpreter, "OF"), 0);
+        assert_eq!(value(&interpreter, "TOP"), 6);
+
+        // fucomip st(0), st(1) also pops
+        run(&X86::new(), &[0xdf, 0xe9], 0x1000, &mut interpreter);
+        let flags = (value(&interpreter, "ZF"), value(&interpreter, "PF"), value(&interpreter, "CF"));
+        assert_eq!(flags, (zf, pf, cf), "{} {}", st0, st1);
+        assert_eq!(value(&interpreter, "TOP"), 7);
+        assert_eq!(value(&interpreter, "st0"), st1.to_bits());
+    }
+}
+
+
+#[test]
+fn smtlib2_test() {
+    let float = |bits| il::expr_scalar("x", bits);
+    for &bits in &[32, 64] {
+        let expression = il::Expression::fadd(float(bits), float(bits)).unwrap();
+        assert!(expr_to_smtlib2(&expression).is_ok());
+    }
+
+    // There is no FloatingPoint sort for 80-bit values. The constructors
+    // refuse them, so build the expressions directly.
+    let expression = il::Expression::Fadd(Box::new(float(80)), Box::new(float(80)));
+    assert!(expr_to_smtlib2(&expression).is_err());
+    let expression = il::Expression::Fext(80, Box::new(float(64)));
+    assert!(expr_to_smtlib2(&expression).is_err());
+}
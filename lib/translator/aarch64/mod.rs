@@ -0,0 +1,271 @@
+//! Capstone-based translator for AArch64, covering the integer instruction
+//! set.
+//!
+//! W registers are lifted as the low 32 bits of the 64-bit X register
+//! scalars, and the zero registers as constants.
+
+use capstone_rust::{capstone, capstone_sys};
+use error::*;
+use il::*;
+use translator::{Arch, BlockTranslationResult, Endian};
+
+
+mod semantics;
+
+/// The AArch64 translator.
+pub struct AArch64;
+
+
+impl AArch64 {
+    pub fn new() -> AArch64 {
+        AArch64
+    }
+}
+
+
+impl Arch for AArch64 {
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+
+    fn address_bits(&self) -> usize {
+        64
+    }
+
+    fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        let cs = match capstone::Capstone::new(capstone::cs_arch::CS_ARCH_ARM64, capstone::cs_mode::CS_MODE_ARM) {
+            Ok(cs) => cs,
+            Err(_) => return Err("Capstone Error".into())
+        };
+
+        cs.option(capstone::cs_opt_type::CS_OPT_DETAIL, capstone::cs_opt_value::CS_OPT_ON).unwrap();
+
+        // our graph for the block which we will build iteratively with each instruction
+        let mut block_graph = ControlFlowGraph::new();
+
+        // the length of this block in bytes
+        let mut length: usize = 0;
+
+        let mut successors = Vec::new();
+
+        let mut offset: usize = 0;
+
+        loop {
+            let disassembly_range = (offset)..bytes.len();
+            let disassembly_bytes = bytes.get(disassembly_range).unwrap();
+            let instructions = match cs.disasm(disassembly_bytes, address + offset as u64, 1) {
+                Ok(instructions) => instructions,
+                Err(e) => match e.code() {
+                    capstone_sys::cs_err::CS_ERR_OK => {
+                        successors.push((address + offset as u64, None));
+                        break;
+                    }
+                    _ => bail!("Capstone Error: {}", e.code() as u32)
+                }
+            };
+
+            if instructions.count() == 0 {
+                return Err("Capstone failed to disassemble any instruction".into());
+            }
+
+            let instruction = instructions.get(0).unwrap();
+
+            if let capstone::InstrIdArch::ARM64(instruction_id) = instruction.id {
+
+                let mut instruction_graph = ControlFlowGraph::new();
+
+                try!(match instruction_id {
+                    capstone::arm64_insn::ARM64_INS_ADC   => semantics::adc(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_ADCS  => semantics::adc(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_ADD   => semantics::add(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_ADDS  => semantics::add(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_ADR   |
+                    capstone::arm64_insn::ARM64_INS_ADRP  => semantics::adr(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_AND   => semantics::and(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_ANDS  => semantics::and(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_ASR   |
+                    capstone::arm64_insn::ARM64_INS_ASRV  => semantics::asr_(&mut instruction_graph, &instruction),
+                    // branches only emit a brc if the destination is undetermined at
+                    // translation time
+                    capstone::arm64_insn::ARM64_INS_B     => semantics::b(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_BFI   => semantics::bfi(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_BFXIL => semantics::bfxil(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_BIC   => semantics::bic(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_BICS  => semantics::bic(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_BL    |
+                    capstone::arm64_insn::ARM64_INS_BLR   => semantics::bl(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_BR    => semantics::br(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_BRK   => semantics::brk(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CBNZ  |
+                    capstone::arm64_insn::ARM64_INS_CBZ   |
+                    capstone::arm64_insn::ARM64_INS_TBNZ  |
+                    capstone::arm64_insn::ARM64_INS_TBZ   => semantics::cbz(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CCMN  => semantics::ccmn(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CCMP  => semantics::ccmp(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CINC  => semantics::cinc(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CINV  => semantics::cinv(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CLREX |
+                    capstone::arm64_insn::ARM64_INS_DMB   |
+                    capstone::arm64_insn::ARM64_INS_DSB   |
+                    capstone::arm64_insn::ARM64_INS_HINT  |
+                    capstone::arm64_insn::ARM64_INS_ISB   |
+                    capstone::arm64_insn::ARM64_INS_NOP   |
+                    capstone::arm64_insn::ARM64_INS_PRFM  |
+                    capstone::arm64_insn::ARM64_INS_PRFUM |
+                    capstone::arm64_insn::ARM64_INS_YIELD => semantics::nop(&mut instruction_graph),
+                    capstone::arm64_insn::ARM64_INS_CLZ   => semantics::clz(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CMN   => semantics::cmn(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CMP   => semantics::cmp(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CNEG  => semantics::cneg(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CSEL  => semantics::csel(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CSET  => semantics::cset(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CSETM => semantics::csetm(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CSINC => semantics::csinc(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CSINV => semantics::csinv(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_CSNEG => semantics::csneg(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_EON   => semantics::eon(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_EOR   => semantics::eor(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_EXTR  => semantics::extr(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDNP  |
+                    capstone::arm64_insn::ARM64_INS_LDP   |
+                    capstone::arm64_insn::ARM64_INS_LDXP  |
+                    capstone::arm64_insn::ARM64_INS_LDAXP => semantics::ldp(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDPSW => semantics::ldpsw(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDAR  |
+                    capstone::arm64_insn::ARM64_INS_LDAXR |
+                    capstone::arm64_insn::ARM64_INS_LDR   |
+                    capstone::arm64_insn::ARM64_INS_LDUR  |
+                    capstone::arm64_insn::ARM64_INS_LDXR  => semantics::ldr(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDARB  |
+                    capstone::arm64_insn::ARM64_INS_LDAXRB |
+                    capstone::arm64_insn::ARM64_INS_LDRB   |
+                    capstone::arm64_insn::ARM64_INS_LDURB  |
+                    capstone::arm64_insn::ARM64_INS_LDXRB  => semantics::ldrb(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDARH  |
+                    capstone::arm64_insn::ARM64_INS_LDAXRH |
+                    capstone::arm64_insn::ARM64_INS_LDRH   |
+                    capstone::arm64_insn::ARM64_INS_LDURH  |
+                    capstone::arm64_insn::ARM64_INS_LDXRH  => semantics::ldrh(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDRSB  |
+                    capstone::arm64_insn::ARM64_INS_LDURSB => semantics::ldrsb(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDRSH  |
+                    capstone::arm64_insn::ARM64_INS_LDURSH => semantics::ldrsh(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LDRSW  |
+                    capstone::arm64_insn::ARM64_INS_LDURSW => semantics::ldrsw(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LSL   |
+                    capstone::arm64_insn::ARM64_INS_LSLV  => semantics::lsl(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_LSR   |
+                    capstone::arm64_insn::ARM64_INS_LSRV  => semantics::lsr(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MADD  |
+                    capstone::arm64_insn::ARM64_INS_MUL   => semantics::madd(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MNEG  => semantics::mneg(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MOV   |
+                    capstone::arm64_insn::ARM64_INS_MOVZ  => semantics::mov(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MOVK  => semantics::movk(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MOVN  => semantics::movn(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MSUB  => semantics::msub(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_MVN   => semantics::mvn(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_NEG   => semantics::neg(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_NEGS  => semantics::neg(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_ORN   => semantics::orn(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_ORR   => semantics::orr(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_RET   => semantics::ret(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_REV   => semantics::rev(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_REV16 => semantics::rev16(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_REV32 => semantics::rev32(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_ROR   |
+                    capstone::arm64_insn::ARM64_INS_RORV  => semantics::ror_(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SBC   => semantics::sbc(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_SBCS  => semantics::sbc(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_SBFIZ => semantics::sbfiz(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SBFX  => semantics::sbfx(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SDIV  => semantics::sdiv(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SMADDL |
+                    capstone::arm64_insn::ARM64_INS_SMULL  => semantics::smaddl(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SMNEGL |
+                    capstone::arm64_insn::ARM64_INS_SMSUBL => semantics::smsubl(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SMULH  => semantics::smulh(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_STNP  |
+                    capstone::arm64_insn::ARM64_INS_STP   => semantics::stp(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_STLR  |
+                    capstone::arm64_insn::ARM64_INS_STR   |
+                    capstone::arm64_insn::ARM64_INS_STUR  => semantics::str(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_STLRB |
+                    capstone::arm64_insn::ARM64_INS_STRB  |
+                    capstone::arm64_insn::ARM64_INS_STURB => semantics::strb(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_STLRH |
+                    capstone::arm64_insn::ARM64_INS_STRH  |
+                    capstone::arm64_insn::ARM64_INS_STURH => semantics::strh(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_STLXR |
+                    capstone::arm64_insn::ARM64_INS_STXR  => semantics::stxr(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SUB   => semantics::sub(&mut instruction_graph, &instruction, false),
+                    capstone::arm64_insn::ARM64_INS_SUBS  => semantics::sub(&mut instruction_graph, &instruction, true),
+                    capstone::arm64_insn::ARM64_INS_SVC   => semantics::svc(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SXTB  => semantics::sxtb(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SXTH  => semantics::sxth(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_SXTW  => semantics::sxtw(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_TST   => semantics::tst(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UBFIZ => semantics::ubfiz(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UBFX  => semantics::ubfx(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UDIV  => semantics::udiv(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UMADDL |
+                    capstone::arm64_insn::ARM64_INS_UMULL  => semantics::umaddl(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UMNEGL |
+                    capstone::arm64_insn::ARM64_INS_UMSUBL => semantics::umsubl(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UMULH  => semantics::umulh(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UXTB  => semantics::uxtb(&mut instruction_graph, &instruction),
+                    capstone::arm64_insn::ARM64_INS_UXTH  => semantics::uxth(&mut instruction_graph, &instruction),
+
+                    _ => return Err(format!("Unhandled instruction {} at 0x{:x}",
+                        instruction.mnemonic,
+                        instruction.address
+                    ).into())
+                });
+
+                instruction_graph.set_address(Some(instruction.address));
+
+                block_graph.append(&instruction_graph)?;
+
+                length += instruction.size as usize;
+
+                // instructions that terminate blocks
+                match instruction_id {
+                    capstone::arm64_insn::ARM64_INS_B => {
+                        let detail = semantics::details(&instruction)?;
+                        let target = detail.operands[0].imm() as u64;
+                        match semantics::condition(detail.cc)? {
+                            Some(condition) => {
+                                successors.push((address + length as u64, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                                successors.push((target, Some(condition)));
+                            },
+                            None => successors.push((target, None))
+                        }
+                        break;
+                    }
+                    capstone::arm64_insn::ARM64_INS_CBNZ |
+                    capstone::arm64_insn::ARM64_INS_CBZ |
+                    capstone::arm64_insn::ARM64_INS_TBNZ |
+                    capstone::arm64_insn::ARM64_INS_TBZ => {
+                        let detail = semantics::details(&instruction)?;
+                        let condition = semantics::compare_branch_condition(&instruction)?;
+                        let target = detail.operands[(detail.op_count - 1) as usize].imm() as u64;
+                        successors.push((address + length as u64, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                        successors.push((target, Some(condition)));
+                        break;
+                    }
+                    // instructions without successors
+                    capstone::arm64_insn::ARM64_INS_BR |
+                    capstone::arm64_insn::ARM64_INS_RET => break,
+                    _ => ()
+                }
+            }
+            else {
+                bail!("not an aarch64 instruction")
+            }
+
+            offset += instruction.size as usize;
+        }
+
+        Ok(BlockTranslationResult::new(block_graph, address, length, successors))
+    }
+}
@@ -0,0 +1,1566 @@
+use capstone_rust::capstone;
+use capstone_rust::capstone::{cs_arm64, cs_arm64_op};
+use capstone_rust::capstone_sys::{arm64_cc, arm64_extender, arm64_op_type, arm64_reg, arm64_shifter};
+use error::*;
+use il::*;
+use il::Expression as Expr;
+
+
+const MEM_SIZE: u64 = (1 << 48);
+
+
+/// Returns the name of the scalar holding a register, and the width of the
+/// register in bits.
+///
+/// W registers are the low 32 bits of the X registers. The zero registers
+/// have no scalar.
+fn register(reg: arm64_reg) -> Result<(Option<&'static str>, usize)> {
+    Ok(match reg {
+        arm64_reg::ARM64_REG_X0 => (Some("x0"), 64),
+        arm64_reg::ARM64_REG_X1 => (Some("x1"), 64),
+        arm64_reg::ARM64_REG_X2 => (Some("x2"), 64),
+        arm64_reg::ARM64_REG_X3 => (Some("x3"), 64),
+        arm64_reg::ARM64_REG_X4 => (Some("x4"), 64),
+        arm64_reg::ARM64_REG_X5 => (Some("x5"), 64),
+        arm64_reg::ARM64_REG_X6 => (Some("x6"), 64),
+        arm64_reg::ARM64_REG_X7 => (Some("x7"), 64),
+        arm64_reg::ARM64_REG_X8 => (Some("x8"), 64),
+        arm64_reg::ARM64_REG_X9 => (Some("x9"), 64),
+        arm64_reg::ARM64_REG_X10 => (Some("x10"), 64),
+        arm64_reg::ARM64_REG_X11 => (Some("x11"), 64),
+        arm64_reg::ARM64_REG_X12 => (Some("x12"), 64),
+        arm64_reg::ARM64_REG_X13 => (Some("x13"), 64),
+        arm64_reg::ARM64_REG_X14 => (Some("x14"), 64),
+        arm64_reg::ARM64_REG_X15 => (Some("x15"), 64),
+        arm64_reg::ARM64_REG_X16 |
+        arm64_reg::ARM64_REG_IP0 => (Some("x16"), 64),
+        arm64_reg::ARM64_REG_X17 |
+        arm64_reg::ARM64_REG_IP1 => (Some("x17"), 64),
+        arm64_reg::ARM64_REG_X18 => (Some("x18"), 64),
+        arm64_reg::ARM64_REG_X19 => (Some("x19"), 64),
+        arm64_reg::ARM64_REG_X20 => (Some("x20"), 64),
+        arm64_reg::ARM64_REG_X21 => (Some("x21"), 64),
+        arm64_reg::ARM64_REG_X22 => (Some("x22"), 64),
+        arm64_reg::ARM64_REG_X23 => (Some("x23"), 64),
+        arm64_reg::ARM64_REG_X24 => (Some("x24"), 64),
+        arm64_reg::ARM64_REG_X25 => (Some("x25"), 64),
+        arm64_reg::ARM64_REG_X26 => (Some("x26"), 64),
+        arm64_reg::ARM64_REG_X27 => (Some("x27"), 64),
+        arm64_reg::ARM64_REG_X28 => (Some("x28"), 64),
+        arm64_reg::ARM64_REG_X29 |
+        arm64_reg::ARM64_REG_FP => (Some("x29"), 64),
+        arm64_reg::ARM64_REG_X30 |
+        arm64_reg::ARM64_REG_LR => (Some("x30"), 64),
+        arm64_reg::ARM64_REG_SP => (Some("sp"), 64),
+        arm64_reg::ARM64_REG_XZR => (None, 64),
+        arm64_reg::ARM64_REG_W0 => (Some("x0"), 32),
+        arm64_reg::ARM64_REG_W1 => (Some("x1"), 32),
+        arm64_reg::ARM64_REG_W2 => (Some("x2"), 32),
+        arm64_reg::ARM64_REG_W3 => (Some("x3"), 32),
+        arm64_reg::ARM64_REG_W4 => (Some("x4"), 32),
+        arm64_reg::ARM64_REG_W5 => (Some("x5"), 32),
+        arm64_reg::ARM64_REG_W6 => (Some("x6"), 32),
+        arm64_reg::ARM64_REG_W7 => (Some("x7"), 32),
+        arm64_reg::ARM64_REG_W8 => (Some("x8"), 32),
+        arm64_reg::ARM64_REG_W9 => (Some("x9"), 32),
+        arm64_reg::ARM64_REG_W10 => (Some("x10"), 32),
+        arm64_reg::ARM64_REG_W11 => (Some("x11"), 32),
+        arm64_reg::ARM64_REG_W12 => (Some("x12"), 32),
+        arm64_reg::ARM64_REG_W13 => (Some("x13"), 32),
+        arm64_reg::ARM64_REG_W14 => (Some("x14"), 32),
+        arm64_reg::ARM64_REG_W15 => (Some("x15"), 32),
+        arm64_reg::ARM64_REG_W16 => (Some("x16"), 32),
+        arm64_reg::ARM64_REG_W17 => (Some("x17"), 32),
+        arm64_reg::ARM64_REG_W18 => (Some("x18"), 32),
+        arm64_reg::ARM64_REG_W19 => (Some("x19"), 32),
+        arm64_reg::ARM64_REG_W20 => (Some("x20"), 32),
+        arm64_reg::ARM64_REG_W21 => (Some("x21"), 32),
+        arm64_reg::ARM64_REG_W22 => (Some("x22"), 32),
+        arm64_reg::ARM64_REG_W23 => (Some("x23"), 32),
+        arm64_reg::ARM64_REG_W24 => (Some("x24"), 32),
+        arm64_reg::ARM64_REG_W25 => (Some("x25"), 32),
+        arm64_reg::ARM64_REG_W26 => (Some("x26"), 32),
+        arm64_reg::ARM64_REG_W27 => (Some("x27"), 32),
+        arm64_reg::ARM64_REG_W28 => (Some("x28"), 32),
+        arm64_reg::ARM64_REG_W29 => (Some("x29"), 32),
+        arm64_reg::ARM64_REG_W30 => (Some("x30"), 32),
+        arm64_reg::ARM64_REG_WSP => (Some("sp"), 32),
+        arm64_reg::ARM64_REG_WZR => (None, 32),
+        _ => bail!("unsupported register {:?}", reg)
+    })
+}
+
+
+/// Returns the width of a register in bits.
+pub fn register_bits(reg: arm64_reg) -> Result<usize> {
+    Ok(register(reg)?.1)
+}
+
+
+/// Returns the value of a register. The zero registers read as 0.
+pub fn register_value(reg: arm64_reg) -> Result<Expression> {
+    match register(reg)? {
+        (Some(name), 64) => Ok(expr_scalar(name, 64)),
+        (Some(name), bits) => Expr::trun(bits, expr_scalar(name, 64)),
+        (None, bits) => Ok(expr_const(0, bits))
+    }
+}
+
+
+/// Writes a value to a register. Writes to W registers zero the upper 32
+/// bits of the X register, and writes to the zero registers are discarded.
+pub fn write_register(block: &mut Block, reg: arm64_reg, value: Expression) -> Result<()> {
+    match register(reg)? {
+        (Some(name), 64) => block.assign(scalar(name, 64), value),
+        (Some(name), _) => block.assign(scalar(name, 64), Expr::zext(64, value)?),
+        (None, _) => ()
+    }
+    Ok(())
+}
+
+
+/// Returns the details for an AArch64 instruction.
+pub fn details(instruction: &capstone::Instr) -> Result<cs_arm64> {
+    let detail = instruction.detail.as_ref().unwrap();
+    match detail.arch {
+        capstone::DetailsArch::ARM64(x) => Ok(x),
+        _ => Err("Could not get instruction details".into())
+    }
+}
+
+
+/// Returns bit `bit` of `value`.
+fn bit(value: Expression, bit: u64) -> Result<Expression> {
+    let bits = value.bits();
+    Expr::trun(1, Expr::shr(value, expr_const(bit, bits))?)
+}
+
+
+/// Returns the bitwise not of `value`.
+fn not(value: Expression) -> Result<Expression> {
+    let bits = value.bits();
+    Expr::xor(value, expr_const(0xffff_ffff_ffff_ffff, bits))
+}
+
+
+/// Returns `a` if `condition` holds, or `b` otherwise.
+fn select(condition: Expression, a: Expression, b: Expression) -> Result<Expression> {
+    let mask = Expr::sext(a.bits(), condition)?;
+    Expr::or(Expr::and(a, mask.clone())?, Expr::and(b, not(mask)?)?)
+}
+
+
+/// Divides `lhs` by `rhs`, giving 0 when `rhs` is 0 as `sdiv` and `udiv` do.
+/// A zero divisor is bumped to 1 before the division is evaluated.
+fn divide(lhs: Expression, rhs: Expression, signed: bool) -> Result<Expression> {
+    let bits = rhs.bits();
+    let zero = Expr::cmpeq(rhs.clone(), expr_const(0, bits))?;
+    let divisor = Expr::or(rhs, Expr::zext(bits, zero.clone())?)?;
+    let quotient = if signed {
+        Expr::divs(lhs, divisor)?
+    }
+    else {
+        Expr::divu(lhs, divisor)?
+    };
+    select(zero, expr_const(0, bits), quotient)
+}
+
+
+/// Returns `value` shifted right arithmetically by `amount`.
+fn asr(value: Expression, amount: Expression) -> Result<Expression> {
+    let bits = value.bits();
+
+    // The bits shifted in are copies of the sign bit
+    let mask = Expr::shl(expr_const(1, bits), amount.clone())?;
+    let mask = Expr::sub(mask, expr_const(1, bits))?;
+    let mask = Expr::shl(mask, Expr::sub(expr_const(bits as u64, bits), amount.clone())?)?;
+    let sign = Expr::shr(value.clone(), expr_const(bits as u64 - 1, bits))?;
+
+    Expr::or(Expr::mul(mask, sign)?, Expr::shr(value, amount)?)
+}
+
+
+/// Returns `value` rotated right by `amount`.
+fn ror(value: Expression, amount: Expression) -> Result<Expression> {
+    let bits = value.bits();
+    let left = Expr::sub(expr_const(bits as u64, bits), amount.clone())?;
+    Expr::or(Expr::shr(value.clone(), amount)?, Expr::shl(value, left)?)
+}
+
+
+/// Shifts `value` by `amount`.
+fn shift(shifter: arm64_shifter, value: Expression, amount: Expression) -> Result<Expression> {
+    match shifter {
+        arm64_shifter::ARM64_SFT_LSL => Expr::shl(value, amount),
+        arm64_shifter::ARM64_SFT_LSR => Expr::shr(value, amount),
+        arm64_shifter::ARM64_SFT_ASR => asr(value, amount),
+        arm64_shifter::ARM64_SFT_ROR => ror(value, amount),
+        _ => bail!("unsupported shift {:?}", shifter)
+    }
+}
+
+
+/// Applies the extension of an extended register operand, giving a value of
+/// `bits`.
+fn extend(extender: arm64_extender, value: Expression, bits: usize) -> Result<Expression> {
+    let (from, signed) = match extender {
+        arm64_extender::ARM64_EXT_INVALID => return resize(value, bits, false),
+        arm64_extender::ARM64_EXT_UXTB => (8, false),
+        arm64_extender::ARM64_EXT_UXTH => (16, false),
+        arm64_extender::ARM64_EXT_UXTW => (32, false),
+        arm64_extender::ARM64_EXT_UXTX => (64, false),
+        arm64_extender::ARM64_EXT_SXTB => (8, true),
+        arm64_extender::ARM64_EXT_SXTH => (16, true),
+        arm64_extender::ARM64_EXT_SXTW => (32, true),
+        arm64_extender::ARM64_EXT_SXTX => (64, true)
+    };
+    let value = resize(value, from, false)?;
+    resize(value, bits, signed)
+}
+
+
+/// Truncates or extends `value` to `bits`.
+fn resize(value: Expression, bits: usize, signed: bool) -> Result<Expression> {
+    if value.bits() > bits {
+        Expr::trun(bits, value)
+    }
+    else if value.bits() < bits {
+        if signed {
+            Expr::sext(bits, value)
+        }
+        else {
+            Expr::zext(bits, value)
+        }
+    }
+    else {
+        Ok(value)
+    }
+}
+
+
+/// Gets the value of a register or immediate operand as `bits`, after
+/// applying its extension and shift.
+pub fn operand_value(operand: &cs_arm64_op, bits: usize) -> Result<Expression> {
+    let value = match operand.type_ {
+        arm64_op_type::ARM64_OP_REG => extend(operand.ext, register_value(*operand.reg())?, bits)?,
+        arm64_op_type::ARM64_OP_IMM |
+        arm64_op_type::ARM64_OP_CIMM => {
+            let mask = if bits == 64 { 0xffff_ffff_ffff_ffff } else { (1 << bits) - 1 };
+            expr_const(operand.imm() as u64 & mask, bits)
+        },
+        _ => bail!("unsupported operand type {:?}", operand.type_)
+    };
+
+    match operand.shift.type_ {
+        arm64_shifter::ARM64_SFT_INVALID => Ok(value),
+        _ if operand.shift.value == 0 => Ok(value),
+        shifter => shift(shifter, value, expr_const(operand.shift.value as u64, bits))
+    }
+}
+
+
+/// Gets the address a memory operand refers to.
+pub fn memory_address(operand: &cs_arm64_op) -> Result<Expression> {
+    let mem = operand.mem();
+
+    let address = register_value(arm64_reg::from(mem.base))?;
+
+    let address = match arm64_reg::from(mem.index) {
+        arm64_reg::ARM64_REG_INVALID => address,
+        index => {
+            let index = extend(operand.ext, register_value(index)?, 64)?;
+            let index = match operand.shift.type_ {
+                arm64_shifter::ARM64_SFT_LSL => Expr::shl(index, expr_const(operand.shift.value as u64, 64))?,
+                _ => index
+            };
+            Expr::add(address, index)?
+        }
+    };
+
+    if mem.disp == 0 {
+        Ok(address)
+    }
+    else {
+        Expr::add(address, expr_const(mem.disp as i64 as u64, 64))
+    }
+}
+
+
+/// Returns the address accessed by a load or store whose address operand is
+/// `detail.operands[index]`, and writes back the base register for pre- and
+/// post-indexed addressing. Literal loads give their address as an
+/// immediate.
+fn access_address(block: &mut Block, detail: &cs_arm64, index: usize) -> Result<Expression> {
+    let operand = &detail.operands[index];
+
+    if operand.type_ == arm64_op_type::ARM64_OP_IMM {
+        return Ok(expr_const(operand.imm() as u64, 64));
+    }
+
+    let base = arm64_reg::from(operand.mem().base);
+
+    let address = block.temp(64);
+    block.assign(address.clone(), memory_address(operand)?);
+
+    // A post-indexed offset follows the memory operand, and is added to the
+    // base after the access
+    if index + 1 < detail.op_count as usize {
+        let offset = detail.operands[index + 1].imm() as u64;
+        let updated = Expr::add(address.clone().into(), expr_const(offset, 64))?;
+        write_register(block, base, updated)?;
+    }
+    else if detail.writeback {
+        write_register(block, base, address.clone().into())?;
+    }
+
+    Ok(address.into())
+}
+
+
+/// Sets N and Z from a result, and clears C and V, as the logical
+/// instructions do.
+fn set_logical_flags(block: &mut Block, result: Expression) -> Result<()> {
+    let bits = result.bits();
+    block.assign(scalar("N", 1), bit(result.clone(), bits as u64 - 1)?);
+    block.assign(scalar("Z", 1), Expr::cmpeq(result, expr_const(0, bits))?);
+    block.assign(scalar("C", 1), expr_const(0, 1));
+    block.assign(scalar("V", 1), expr_const(0, 1));
+    Ok(())
+}
+
+
+/// Adds `lhs`, `rhs` and `carry`, returning the result and setting N, Z, C
+/// and V if `set_flags` is true.
+///
+/// Subtraction is `AddWithCarry(lhs, NOT(rhs), 1)`.
+fn add_with_carry(block: &mut Block, lhs: Expression, rhs: Expression, carry: Expression, set_flags: bool)
+-> Result<Expression> {
+    let bits = lhs.bits();
+
+    let partial = block.temp(bits);
+    block.assign(partial.clone(), Expr::add(lhs.clone(), rhs.clone())?);
+
+    let result = block.temp(bits);
+    block.assign(result.clone(), Expr::add(partial.clone().into(), Expr::zext(bits, carry)?)?);
+
+    if set_flags {
+        block.assign(scalar("N", 1), bit(result.clone().into(), bits as u64 - 1)?);
+        block.assign(scalar("Z", 1), Expr::cmpeq(result.clone().into(), expr_const(0, bits))?);
+
+        // The sum carries out if either addition wraps
+        let carry = Expr::or(
+            Expr::cmpltu(partial.clone().into(), lhs.clone())?,
+            Expr::cmpltu(result.clone().into(), partial.into())?
+        )?;
+        block.assign(scalar("C", 1), carry);
+
+        let overflow = Expr::and(
+            Expr::xor(lhs, result.clone().into())?,
+            Expr::xor(rhs, result.clone().into())?
+        )?;
+        block.assign(scalar("V", 1), bit(overflow, bits as u64 - 1)?);
+    }
+
+    Ok(result.into())
+}
+
+
+/// Returns the condition under which an instruction with condition code `cc`
+/// executes or branches, or `None` if it always does.
+pub fn condition(cc: arm64_cc) -> Result<Option<Expression>> {
+    let flag = |name| expr_scalar(name, 1);
+    let clear = |name| Expr::cmpeq(expr_scalar(name, 1), expr_const(0, 1));
+
+    Ok(Some(match cc {
+        arm64_cc::ARM64_CC_AL |
+        arm64_cc::ARM64_CC_NV |
+        arm64_cc::ARM64_CC_INVALID => return Ok(None),
+        arm64_cc::ARM64_CC_EQ => flag("Z"),
+        arm64_cc::ARM64_CC_NE => clear("Z")?,
+        arm64_cc::ARM64_CC_HS => flag("C"),
+        arm64_cc::ARM64_CC_LO => clear("C")?,
+        arm64_cc::ARM64_CC_MI => flag("N"),
+        arm64_cc::ARM64_CC_PL => clear("N")?,
+        arm64_cc::ARM64_CC_VS => flag("V"),
+        arm64_cc::ARM64_CC_VC => clear("V")?,
+        arm64_cc::ARM64_CC_HI => Expr::and(flag("C"), clear("Z")?)?,
+        arm64_cc::ARM64_CC_LS => Expr::or(clear("C")?, flag("Z"))?,
+        arm64_cc::ARM64_CC_GE => Expr::cmpeq(flag("N"), flag("V"))?,
+        arm64_cc::ARM64_CC_LT => Expr::cmpneq(flag("N"), flag("V"))?,
+        arm64_cc::ARM64_CC_GT => Expr::and(clear("Z")?, Expr::cmpeq(flag("N"), flag("V"))?)?,
+        arm64_cc::ARM64_CC_LE => Expr::or(flag("Z"), Expr::cmpneq(flag("N"), flag("V"))?)?
+    }))
+}
+
+
+/// Returns the condition under which `cbz`, `cbnz`, `tbz` or `tbnz` branch.
+pub fn compare_branch_condition(instruction: &capstone::Instr) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let value = register_value(*detail.operands[0].reg())?;
+    let bits = value.bits();
+
+    match instruction.id {
+        capstone::InstrIdArch::ARM64(capstone::arm64_insn::ARM64_INS_CBZ) =>
+            Expr::cmpeq(value, expr_const(0, bits)),
+        capstone::InstrIdArch::ARM64(capstone::arm64_insn::ARM64_INS_CBNZ) =>
+            Expr::cmpneq(value, expr_const(0, bits)),
+        capstone::InstrIdArch::ARM64(capstone::arm64_insn::ARM64_INS_TBZ) =>
+            Expr::cmpeq(bit(value, detail.operands[1].imm() as u64)?, expr_const(0, 1)),
+        capstone::InstrIdArch::ARM64(capstone::arm64_insn::ARM64_INS_TBNZ) =>
+            Expr::cmpeq(bit(value, detail.operands[1].imm() as u64)?, expr_const(1, 1)),
+        _ => bail!("not a compare and branch instruction")
+    }
+}
+
+
+/// Returns the destination register, the width of the operation and the
+/// operands of a data processing instruction.
+fn data_operands(detail: &cs_arm64) -> Result<(arm64_reg, usize, &[cs_arm64_op])> {
+    let rd = *detail.operands[0].reg();
+    let bits = register_bits(rd)?;
+    Ok((rd, bits, &detail.operands[1..(detail.op_count as usize)]))
+}
+
+
+/// Lifts an instruction of the form `rd = op(rn, operand2)`, which sets
+/// flags itself.
+fn arithmetic<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool, op: F)
+-> Result<()> where F: Fn(&mut Block, Expression, Expression, bool) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, bits, operands) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(&operands[0], bits)?;
+        let rhs = operand_value(&operands[1], bits)?;
+
+        let result = op(&mut block, lhs, rhs, set_flags)?;
+
+        write_register(&mut block, rd, result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a comparison, `op(rn, operand2)`, which only sets flags.
+fn compare<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(&mut Block, Expression, Expression) -> Result<()> {
+    let detail = details(instruction)?;
+    let bits = register_bits(*detail.operands[0].reg())?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(&detail.operands[0], bits)?;
+        let rhs = operand_value(&detail.operands[1], bits)?;
+
+        op(&mut block, lhs, rhs)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a logical instruction of the form `rd = op(rn, operand2)`.
+fn logical<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool, op: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, bits, operands) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(&operands[0], bits)?;
+        let rhs = operand_value(&operands[1], bits)?;
+
+        let result = block.temp(bits);
+        block.assign(result.clone(), op(lhs, rhs)?);
+
+        if set_flags {
+            set_logical_flags(&mut block, result.clone().into())?;
+        }
+
+        write_register(&mut block, rd, result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an instruction of the form `rd = op(operands)`, where `op` is
+/// given the operands of the instruction and the width of rd.
+fn unary<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(&[cs_arm64_op], usize) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, bits, operands) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let result = op(operands, bits)?;
+
+        write_register(&mut block, rd, result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a conditional select, `rd = cond ? rn : op(rm)`. Aliases which
+/// omit rm use rn for it, and aliases which omit both use the zero register.
+fn conditional_select<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, bits, operands) = data_operands(&detail)?;
+    let condition = condition(detail.cc)?.ok_or("conditional select without a condition")?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (lhs, rhs) = match operands.len() {
+            0 => (expr_const(0, bits), expr_const(0, bits)),
+            1 => (operand_value(&operands[0], bits)?, operand_value(&operands[0], bits)?),
+            _ => (operand_value(&operands[0], bits)?, operand_value(&operands[1], bits)?)
+        };
+
+        write_register(&mut block, rd, select(condition, lhs, op(rhs)?)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a conditional select alias which applies `op` when the condition
+/// holds, `rd = cond ? op(rn) : rn`, as `cinc`, `cinv`, `cneg`, `cset` and
+/// `csetm` do.
+fn conditional_apply<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, bits, operands) = data_operands(&detail)?;
+    let condition = condition(detail.cc)?.ok_or("conditional select without a condition")?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = match operands.len() {
+            0 => expr_const(0, bits),
+            _ => operand_value(&operands[0], bits)?
+        };
+
+        write_register(&mut block, rd, select(condition, op(value.clone())?, value)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a conditional compare, which compares when the condition holds and
+/// otherwise sets the flags from an immediate.
+fn conditional_compare(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, negate: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+    let bits = register_bits(*detail.operands[0].reg())?;
+    let condition = condition(detail.cc)?.ok_or("conditional compare without a condition")?;
+
+    let head_index = control_flow_graph.new_block()?.index();
+
+    let compare_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(&detail.operands[0], bits)?;
+        let rhs = operand_value(&detail.operands[1], bits)?;
+
+        if negate {
+            add_with_carry(&mut block, lhs, rhs, expr_const(0, 1), true)?;
+        }
+        else {
+            add_with_carry(&mut block, lhs, not(rhs)?, expr_const(1, 1), true)?;
+        }
+
+        block.index()
+    };
+
+    let immediate_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let nzcv = detail.operands[2].imm() as u64;
+        block.assign(scalar("N", 1), expr_const((nzcv >> 3) & 1, 1));
+        block.assign(scalar("Z", 1), expr_const((nzcv >> 2) & 1, 1));
+        block.assign(scalar("C", 1), expr_const((nzcv >> 1) & 1, 1));
+        block.assign(scalar("V", 1), expr_const(nzcv & 1, 1));
+
+        block.index()
+    };
+
+    let tail_index = control_flow_graph.new_block()?.index();
+
+    control_flow_graph.conditional_edge(head_index, compare_index, condition.clone())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        immediate_index,
+        Expr::cmpeq(condition, expr_const(0, 1))?
+    )?;
+    control_flow_graph.unconditional_edge(compare_index, tail_index)?;
+    control_flow_graph.unconditional_edge(immediate_index, tail_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a shift instruction, `lsl`, `lsr`, `asr` and `ror`, by an immediate
+/// or by a register modulo the width of the operation.
+fn shift_instruction(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, shifter: arm64_shifter)
+-> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let value = operand_value(&operands[0], bits)?;
+        let amount = match operands[1].type_ {
+            arm64_op_type::ARM64_OP_IMM => {
+                let amount = operands[1].imm() as u64;
+                if amount == 0 {
+                    return Ok(value);
+                }
+                expr_const(amount, bits)
+            },
+            _ => Expr::and(operand_value(&operands[1], bits)?, expr_const(bits as u64 - 1, bits))?
+        };
+        shift(shifter, value, amount)
+    })
+}
+
+
+/// Lifts a multiply-add, `rd = ra + op(rn * rm)`, where the product of the
+/// sources, extended to the width of rd, is negated by `subtract`.
+/// Instructions without ra add zero.
+fn multiply_add(
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    signed: bool,
+    subtract: bool
+) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let lhs = resize(register_value(*operands[0].reg())?, bits, signed)?;
+        let rhs = resize(register_value(*operands[1].reg())?, bits, signed)?;
+        let product = Expr::mul(lhs, rhs)?;
+
+        let accumulator = match operands.len() {
+            3 => register_value(*operands[2].reg())?,
+            _ => expr_const(0, bits)
+        };
+
+        if subtract {
+            Expr::sub(accumulator, product)
+        }
+        else {
+            Expr::add(accumulator, product)
+        }
+    })
+}
+
+
+/// Lifts a multiply returning the high 64 bits of the 128-bit product.
+fn multiply_high(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, signed: bool)
+-> Result<()> {
+    unary(control_flow_graph, instruction, |operands, _| {
+        let lhs = resize(register_value(*operands[0].reg())?, 128, signed)?;
+        let rhs = resize(register_value(*operands[1].reg())?, 128, signed)?;
+        let product = Expr::mul(lhs, rhs)?;
+        Expr::trun(64, Expr::shr(product, expr_const(64, 128))?)
+    })
+}
+
+
+/// Returns the mask of the low `width` bits of a value of `bits`.
+fn low_mask(width: u64) -> u64 {
+    if width >= 64 { 0xffff_ffff_ffff_ffff } else { (1 << width) - 1 }
+}
+
+
+/// Lifts a bitfield extract, `ubfx` and `sbfx`, or insert into zero, `ubfiz`
+/// and `sbfiz`.
+fn bitfield(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, signed: bool, insert: bool)
+-> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let value = resize(register_value(*operands[0].reg())?, bits, false)?;
+        let lsb = operands[1].imm() as u64;
+        let width = operands[2].imm() as usize;
+
+        if insert {
+            let field = resize(Expr::trun(width, value)?, bits, signed)?;
+            Expr::shl(field, expr_const(lsb, bits))
+        }
+        else {
+            let field = Expr::trun(width, Expr::shr(value, expr_const(lsb, bits))?)?;
+            resize(field, bits, signed)
+        }
+    })
+}
+
+
+/// Lifts a bitfield move, `bfi` when `insert`, or `bfxil`, which keep the
+/// bits of rd outside the field.
+fn bitfield_move(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, insert: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+    let rd = *detail.operands[0].reg();
+
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let value = register_value(*operands[0].reg())?;
+        let lsb = operands[1].imm() as u64;
+        let width = operands[2].imm() as u64;
+        let mask = low_mask(width);
+
+        let (field, position) = if insert {
+            (Expr::and(value, expr_const(mask, bits))?, lsb)
+        }
+        else {
+            (Expr::and(Expr::shr(value, expr_const(lsb, bits))?, expr_const(mask, bits))?, 0)
+        };
+
+        let destination = Expr::and(register_value(rd)?, expr_const(!(mask << position), bits))?;
+        Expr::or(destination, Expr::shl(field, expr_const(position, bits))?)
+    })
+}
+
+
+/// Lifts a byte reversal within each `size` bit chunk of a register.
+fn reverse(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, size: usize) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let value = register_value(*operands[0].reg())?;
+        let size = if size > bits { bits } else { size };
+
+        let mut result = expr_const(0, bits);
+        for chunk in 0..(bits / size) {
+            for byte in 0..(size / 8) {
+                let from = (chunk * size + byte * 8) as u64;
+                let to = (chunk * size + (size - 8 - byte * 8)) as u64;
+                let byte = Expr::and(Expr::shr(value.clone(), expr_const(from, bits))?, expr_const(0xff, bits))?;
+                result = Expr::or(result, Expr::shl(byte, expr_const(to, bits))?)?;
+            }
+        }
+        Ok(result)
+    })
+}
+
+
+/// Lifts a load of `bits` from memory into `operands[0]`, extended to the
+/// width of the register.
+fn load(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: Option<usize>, signed: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+    let rt = *detail.operands[0].reg();
+    let register_bits = register_bits(rt)?;
+    let bits = bits.unwrap_or(register_bits);
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let address = access_address(&mut block, &detail, 1)?;
+
+        let temp = block.temp(bits);
+        block.load(temp.clone(), address, array("mem", MEM_SIZE));
+
+        write_register(&mut block, rt, resize(temp.into(), register_bits, signed)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a store of the low `bits` of `operands[index]` to the memory
+/// operand after it.
+fn store(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: Option<usize>, index: usize)
+-> Result<()> {
+    let detail = details(instruction)?;
+    let rt = *detail.operands[index].reg();
+    let bits = bits.unwrap_or(register_bits(rt)?);
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = resize(register_value(rt)?, bits, false)?;
+        let address = access_address(&mut block, &detail, index + 1)?;
+
+        block.store(array("mem", MEM_SIZE), address, value);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a load of a pair of registers. `ldpsw` loads 32-bit values and sign
+/// extends them.
+fn load_pair(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, signed: bool) -> Result<()> {
+    let detail = details(instruction)?;
+    let first = *detail.operands[0].reg();
+    let second = *detail.operands[1].reg();
+    let register_bits = register_bits(first)?;
+    let bits = if signed { 32 } else { register_bits };
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let address = access_address(&mut block, &detail, 2)?;
+
+        let first_value = block.temp(bits);
+        let second_value = block.temp(bits);
+        block.load(first_value.clone(), address.clone(), array("mem", MEM_SIZE));
+        block.load(
+            second_value.clone(),
+            Expr::add(address, expr_const(bits as u64 / 8, 64))?,
+            array("mem", MEM_SIZE)
+        );
+
+        write_register(&mut block, first, resize(first_value.into(), register_bits, signed)?)?;
+        write_register(&mut block, second, resize(second_value.into(), register_bits, signed)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an instruction with no effect on state we model.
+pub fn nop(control_flow_graph: &mut ControlFlowGraph) -> Result<()> {
+    let block_index = control_flow_graph.new_block()?.index();
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn adc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, set_flags, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, rhs, expr_scalar("C", 1), set_flags)
+    })
+}
+
+
+
+pub fn add(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, set_flags, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, rhs, expr_const(0, 1), set_flags)
+    })
+}
+
+
+
+/// Capstone gives the targets of `adr` and `adrp` as absolute addresses.
+pub fn adr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        Ok(expr_const(operands[0].imm() as u64, bits))
+    })
+}
+
+
+
+pub fn and(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    logical(control_flow_graph, instruction, set_flags, |lhs, rhs| Expr::and(lhs, rhs))
+}
+
+
+
+pub fn asr_(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(control_flow_graph, instruction, arm64_shifter::ARM64_SFT_ASR)
+}
+
+
+
+/// `b` only emits a block. Its successors are found at translation time.
+pub fn b(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    nop(control_flow_graph)
+}
+
+
+
+pub fn bfi(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    bitfield_move(control_flow_graph, instruction, true)
+}
+
+
+
+pub fn bfxil(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    bitfield_move(control_flow_graph, instruction, false)
+}
+
+
+
+pub fn bic(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    logical(control_flow_graph, instruction, set_flags, |lhs, rhs| Expr::and(lhs, not(rhs)?))
+}
+
+
+
+pub fn bl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let return_address = instruction.address + instruction.size as u64;
+
+        let target = match detail.operands[0].type_ {
+            arm64_op_type::ARM64_OP_REG => register_value(*detail.operands[0].reg())?,
+            _ => expr_const(detail.operands[0].imm() as u64, 64)
+        };
+
+        block.assign(scalar("x30", 64), expr_const(return_address, 64));
+        block.brc(target, expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn br(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.brc(register_value(*detail.operands[0].reg())?, expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn brk(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.raise(expr_scalar("brk", 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `cbz`, `cbnz`, `tbz` and `tbnz` only emit a block. Their successors are
+/// found at translation time.
+pub fn cbz(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    nop(control_flow_graph)
+}
+
+
+
+pub fn ccmn(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_compare(control_flow_graph, instruction, true)
+}
+
+
+
+pub fn ccmp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_compare(control_flow_graph, instruction, false)
+}
+
+
+
+pub fn cinc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_apply(control_flow_graph, instruction, |value| {
+        let bits = value.bits();
+        Expr::add(value, expr_const(1, bits))
+    })
+}
+
+
+
+pub fn cinv(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_apply(control_flow_graph, instruction, not)
+}
+
+
+
+pub fn clz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let value = register_value(*operands[0].reg())?;
+
+        // The count is the number of prefixes of value which are all zero
+        let mut count = expr_const(0, bits);
+        for i in 1..(bits + 1) {
+            let prefix = Expr::shr(value.clone(), expr_const((bits - i) as u64, bits))?;
+            let zero = Expr::cmpeq(prefix, expr_const(0, bits))?;
+            count = Expr::add(count, Expr::zext(bits, zero)?)?;
+        }
+        Ok(count)
+    })
+}
+
+
+
+pub fn cmn(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(control_flow_graph, instruction, |block, lhs, rhs| {
+        add_with_carry(block, lhs, rhs, expr_const(0, 1), true)?;
+        Ok(())
+    })
+}
+
+
+
+pub fn cmp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(control_flow_graph, instruction, |block, lhs, rhs| {
+        add_with_carry(block, lhs, not(rhs)?, expr_const(1, 1), true)?;
+        Ok(())
+    })
+}
+
+
+
+pub fn cneg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_apply(control_flow_graph, instruction, |value| {
+        let bits = value.bits();
+        Expr::sub(expr_const(0, bits), value)
+    })
+}
+
+
+
+pub fn csel(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_select(control_flow_graph, instruction, |value| Ok(value))
+}
+
+
+
+/// `cset rd, cc` sets rd to 1 when the condition holds.
+pub fn cset(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_apply(control_flow_graph, instruction, |value| {
+        let bits = value.bits();
+        Ok(expr_const(1, bits))
+    })
+}
+
+
+
+/// `csetm rd, cc` sets every bit of rd when the condition holds.
+pub fn csetm(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_apply(control_flow_graph, instruction, not)
+}
+
+
+
+pub fn csinc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_select(control_flow_graph, instruction, |value| {
+        let bits = value.bits();
+        Expr::add(value, expr_const(1, bits))
+    })
+}
+
+
+
+pub fn csinv(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_select(control_flow_graph, instruction, not)
+}
+
+
+
+pub fn csneg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_select(control_flow_graph, instruction, |value| {
+        let bits = value.bits();
+        Expr::sub(expr_const(0, bits), value)
+    })
+}
+
+
+
+pub fn eon(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(control_flow_graph, instruction, false, |lhs, rhs| Expr::xor(lhs, not(rhs)?))
+}
+
+
+
+pub fn eor(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(control_flow_graph, instruction, false, |lhs, rhs| Expr::xor(lhs, rhs))
+}
+
+
+
+pub fn extr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let high = register_value(*operands[0].reg())?;
+        let low = register_value(*operands[1].reg())?;
+        let lsb = operands[2].imm() as u64;
+        if lsb == 0 {
+            return Ok(low);
+        }
+        Expr::or(
+            Expr::shr(low, expr_const(lsb, bits))?,
+            Expr::shl(high, expr_const(bits as u64 - lsb, bits))?
+        )
+    })
+}
+
+
+
+pub fn ldp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_pair(control_flow_graph, instruction, false)
+}
+
+
+
+pub fn ldpsw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_pair(control_flow_graph, instruction, true)
+}
+
+
+
+pub fn ldr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, None, false)
+}
+
+
+
+pub fn ldrb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, Some(8), false)
+}
+
+
+
+pub fn ldrh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, Some(16), false)
+}
+
+
+
+pub fn ldrsb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, Some(8), true)
+}
+
+
+
+pub fn ldrsh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, Some(16), true)
+}
+
+
+
+pub fn ldrsw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, Some(32), true)
+}
+
+
+
+pub fn lsl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(control_flow_graph, instruction, arm64_shifter::ARM64_SFT_LSL)
+}
+
+
+
+pub fn lsr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(control_flow_graph, instruction, arm64_shifter::ARM64_SFT_LSR)
+}
+
+
+
+pub fn madd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn mneg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn mov(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| operand_value(&operands[0], bits))
+}
+
+
+
+pub fn movk(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let rd = *detail.operands[0].reg();
+
+    unary(control_flow_graph, instruction, |operands, bits| {
+        let position = operands[0].shift.value as u64;
+        let value = Expr::and(register_value(rd)?, expr_const(!(0xffff << position), bits))?;
+        Expr::or(value, operand_value(&operands[0], bits)?)
+    })
+}
+
+
+
+pub fn movn(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| not(operand_value(&operands[0], bits)?))
+}
+
+
+
+pub fn msub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn mvn(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| not(operand_value(&operands[0], bits)?))
+}
+
+
+
+pub fn neg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    let detail = details(instruction)?;
+    let (rd, bits, operands) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = operand_value(&operands[0], bits)?;
+        let result = add_with_carry(&mut block, expr_const(0, bits), not(value)?, expr_const(1, 1), set_flags)?;
+
+        write_register(&mut block, rd, result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn orn(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(control_flow_graph, instruction, false, |lhs, rhs| Expr::or(lhs, not(rhs)?))
+}
+
+
+
+pub fn orr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(control_flow_graph, instruction, false, |lhs, rhs| Expr::or(lhs, rhs))
+}
+
+
+
+pub fn ret(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let target = match detail.op_count {
+            0 => expr_scalar("x30", 64),
+            _ => register_value(*detail.operands[0].reg())?
+        };
+        block.brc(target, expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn rev(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    reverse(control_flow_graph, instruction, 64)
+}
+
+
+
+pub fn rev16(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    reverse(control_flow_graph, instruction, 16)
+}
+
+
+
+pub fn rev32(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    reverse(control_flow_graph, instruction, 32)
+}
+
+
+
+pub fn ror_(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(control_flow_graph, instruction, arm64_shifter::ARM64_SFT_ROR)
+}
+
+
+
+pub fn sbc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, set_flags, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, not(rhs)?, expr_scalar("C", 1), set_flags)
+    })
+}
+
+
+
+pub fn sbfiz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    bitfield(control_flow_graph, instruction, true, true)
+}
+
+
+
+pub fn sbfx(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    bitfield(control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn sdiv(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, false, |_, lhs, rhs, _| divide(lhs, rhs, true))
+}
+
+
+
+pub fn smaddl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn smsubl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, true, true)
+}
+
+
+
+pub fn smulh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_high(control_flow_graph, instruction, true)
+}
+
+
+
+pub fn stp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let first = *detail.operands[0].reg();
+    let second = *detail.operands[1].reg();
+    let bits = register_bits(first)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let first_value = register_value(first)?;
+        let second_value = register_value(second)?;
+
+        let address = access_address(&mut block, &detail, 2)?;
+
+        block.store(array("mem", MEM_SIZE), address.clone(), first_value);
+        block.store(
+            array("mem", MEM_SIZE),
+            Expr::add(address, expr_const(bits as u64 / 8, 64))?,
+            second_value
+        );
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn str(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, None, 0)
+}
+
+
+
+pub fn strb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, Some(8), 0)
+}
+
+
+
+pub fn strh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, Some(16), 0)
+}
+
+
+
+/// Exclusive stores always succeed, as we do not model other processors.
+pub fn stxr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, None, 1)?;
+
+    let detail = details(instruction)?;
+    let exit_index = control_flow_graph.exit().ok_or("store has no exit")?;
+    let block = control_flow_graph.block_mut(exit_index).ok_or("Could not find block")?;
+    let bits = register_bits(*detail.operands[0].reg())?;
+    write_register(block, *detail.operands[0].reg(), expr_const(0, bits))
+}
+
+
+
+pub fn sub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, set_flags: bool) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, set_flags, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, not(rhs)?, expr_const(1, 1), set_flags)
+    })
+}
+
+
+
+pub fn svc(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.raise(expr_scalar("svc", 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn sxtb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        resize(Expr::trun(8, register_value(*operands[0].reg())?)?, bits, true)
+    })
+}
+
+
+
+pub fn sxth(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        resize(Expr::trun(16, register_value(*operands[0].reg())?)?, bits, true)
+    })
+}
+
+
+
+pub fn sxtw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        resize(resize(register_value(*operands[0].reg())?, 32, false)?, bits, true)
+    })
+}
+
+
+
+pub fn tst(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(control_flow_graph, instruction, |block, lhs, rhs| {
+        set_logical_flags(block, Expr::and(lhs, rhs)?)
+    })
+}
+
+
+
+pub fn ubfiz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    bitfield(control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn ubfx(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    bitfield(control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn udiv(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, false, |_, lhs, rhs, _| divide(lhs, rhs, false))
+}
+
+
+
+pub fn umaddl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn umsubl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_add(control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn umulh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_high(control_flow_graph, instruction, false)
+}
+
+
+
+pub fn uxtb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        resize(Expr::trun(8, register_value(*operands[0].reg())?)?, bits, false)
+    })
+}
+
+
+
+pub fn uxth(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |operands, bits| {
+        resize(Expr::trun(16, register_value(*operands[0].reg())?)?, bits, false)
+    })
+}
@@ -0,0 +1,283 @@
+//! Capstone-based translator for 32-bit ARM, covering the A32 and Thumb-2
+//! instruction sets.
+//!
+//! The instruction set of a block is given by its address. Thumb blocks have
+//! the lowest bit of their address set, as they do when branched to with
+//! `bx`, and the successors of Thumb blocks are reported with this bit set.
+//!
+//! Conditionally executed instructions are lifted into a conditional edge
+//! around their semantics.
+
+use capstone_rust::{capstone, capstone_sys};
+use error::*;
+use il::*;
+use translator::{Arch, BlockTranslationResult, Endian};
+
+
+mod semantics;
+
+use self::semantics::Mode;
+
+/// The ARM translator.
+pub struct Arm;
+
+
+impl Arm {
+    pub fn new() -> Arm {
+        Arm
+    }
+}
+
+
+impl Arch for Arm {
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+
+    fn address_bits(&self) -> usize {
+        32
+    }
+
+    fn instruction_address(&self, address: u64) -> u64 {
+        address & !1
+    }
+
+    fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        let mode = Mode::from_address(address);
+
+        let cs_mode = match mode {
+            Mode::Arm => capstone::cs_mode::CS_MODE_ARM,
+            Mode::Thumb => capstone::cs_mode::CS_MODE_THUMB
+        };
+
+        let cs = match capstone::Capstone::new(capstone::cs_arch::CS_ARCH_ARM, cs_mode) {
+            Ok(cs) => cs,
+            Err(_) => return Err("Capstone Error".into())
+        };
+
+        cs.option(capstone::cs_opt_type::CS_OPT_DETAIL, capstone::cs_opt_value::CS_OPT_ON).unwrap();
+
+        // the address the instructions of this block begin at
+        let instruction_address = self.instruction_address(address);
+
+        // our graph for the block which we will build iteratively with each instruction
+        let mut block_graph = ControlFlowGraph::new();
+
+        // the length of this block in bytes
+        let mut length: usize = 0;
+
+        let mut successors = Vec::new();
+
+        let mut offset: usize = 0;
+
+        // capstone only carries the state of an it block through a single
+        // call, so instructions in an it block are disassembled together with
+        // their it. This holds the offset of the it, the number of
+        // instructions it covers, and the number of those seen so far.
+        let mut it_block: Option<(usize, usize, usize)> = None;
+
+        loop {
+            let (start, count) = match it_block {
+                Some((it_offset, _, seen)) => (it_offset, seen + 2),
+                None => (offset, 1)
+            };
+            let disassembly_range = (start)..bytes.len();
+            let disassembly_bytes = bytes.get(disassembly_range).unwrap();
+            let instructions = match cs.disasm(disassembly_bytes, instruction_address + start as u64, count) {
+                Ok(instructions) => instructions,
+                Err(e) => match e.code() {
+                    capstone_sys::cs_err::CS_ERR_OK => {
+                        successors.push((mode.tag(instruction_address + offset as u64), None));
+                        break;
+                    }
+                    _ => bail!("Capstone Error: {}", e.code() as u32)
+                }
+            };
+
+            if instructions.count() == 0 {
+                return Err("Capstone failed to disassemble any instruction".into());
+            }
+
+            let instruction = instructions.get(instructions.count() - 1).unwrap();
+
+            if instruction.address != instruction_address + offset as u64 {
+                successors.push((mode.tag(instruction_address + offset as u64), None));
+                break;
+            }
+
+            it_block = match it_block {
+                Some((it_offset, covered, seen)) if seen + 1 < covered =>
+                    Some((it_offset, covered, seen + 1)),
+                _ => None
+            };
+
+            if let capstone::InstrIdArch::ARM(instruction_id) = instruction.id {
+
+                let mut instruction_graph = ControlFlowGraph::new();
+
+                try!(match instruction_id {
+                    capstone::arm_insn::ARM_INS_ADC   => semantics::adc(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_ADD   |
+                    capstone::arm_insn::ARM_INS_ADDW  => semantics::add(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_ADR   => semantics::adr(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_AND   => semantics::and(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_ASR   => semantics::asr_(mode, &mut instruction_graph, &instruction),
+                    // branches only emit a brc if the destination is undetermined at
+                    // translation time
+                    capstone::arm_insn::ARM_INS_B     => semantics::b(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BFC   => semantics::bfc(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BFI   => semantics::bfi(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BIC   => semantics::bic(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BKPT  => semantics::bkpt(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BL    => semantics::bl(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BLX   => semantics::blx(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_BX    => semantics::bx(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_CBNZ  |
+                    capstone::arm_insn::ARM_INS_CBZ   => semantics::cbz(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_CLZ   => semantics::clz(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_CMN   => semantics::cmn(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_CMP   => semantics::cmp(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_DMB   |
+                    capstone::arm_insn::ARM_INS_DSB   |
+                    capstone::arm_insn::ARM_INS_ISB   => semantics::nop(&mut instruction_graph),
+                    capstone::arm_insn::ARM_INS_EOR   => semantics::eor(mode, &mut instruction_graph, &instruction),
+                    // the condition of each instruction in an it block is given by capstone
+                    capstone::arm_insn::ARM_INS_IT    => semantics::nop(&mut instruction_graph),
+                    capstone::arm_insn::ARM_INS_LDM   => semantics::ldm(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDMDA => semantics::ldmda(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDMDB => semantics::ldmdb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDMIB => semantics::ldmib(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDR   |
+                    capstone::arm_insn::ARM_INS_LDREX => semantics::ldr(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDRB  => semantics::ldrb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDRD  => semantics::ldrd(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDRH  => semantics::ldrh(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDRSB => semantics::ldrsb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LDRSH => semantics::ldrsh(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LSL   => semantics::lsl(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_LSR   => semantics::lsr(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_MLA   => semantics::mla(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_MLS   => semantics::mls(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_MOV   |
+                    capstone::arm_insn::ARM_INS_MOVW  => semantics::mov(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_MOVT  => semantics::movt(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_MUL   => semantics::mul(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_MVN   => semantics::mvn(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_NEG   => semantics::neg(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_NOP   => semantics::nop(&mut instruction_graph),
+                    capstone::arm_insn::ARM_INS_ORN   => semantics::orn(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_ORR   => semantics::orr(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_POP   => semantics::pop(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_PUSH  => semantics::push(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_REV   => semantics::rev(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_REV16 => semantics::rev16(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_ROR   => semantics::ror_(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_RRX   => semantics::rrx(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_RSB   => semantics::rsb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_RSC   => semantics::rsc(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SBC   => semantics::sbc(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SBFX  => semantics::sbfx(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SDIV  => semantics::sdiv(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SMLAL => semantics::smlal(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SMULL => semantics::smull(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STM   => semantics::stm(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STMDA => semantics::stmda(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STMDB => semantics::stmdb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STMIB => semantics::stmib(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STR   => semantics::str(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STRB  => semantics::strb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STRD  => semantics::strd(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STREX => semantics::strex(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_STRH  => semantics::strh(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SUB   |
+                    capstone::arm_insn::ARM_INS_SUBW  => semantics::sub(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SVC   => semantics::svc(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SXTB  => semantics::sxtb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_SXTH  => semantics::sxth(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_TBB   |
+                    capstone::arm_insn::ARM_INS_TBH   => semantics::tbb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_TEQ   => semantics::teq(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_TST   => semantics::tst(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_UBFX  => semantics::ubfx(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_UDIV  => semantics::udiv(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_UMLAL => semantics::umlal(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_UMULL => semantics::umull(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_UXTB  => semantics::uxtb(mode, &mut instruction_graph, &instruction),
+                    capstone::arm_insn::ARM_INS_UXTH  => semantics::uxth(mode, &mut instruction_graph, &instruction),
+
+                    _ => return Err(format!("Unhandled instruction {} at 0x{:x}",
+                        instruction.mnemonic,
+                        instruction.address
+                    ).into())
+                });
+
+                let detail = semantics::details(&instruction)?;
+                let condition = semantics::condition(detail.cc)?;
+
+                // Conditional branches report their condition through their
+                // successors. Every other conditional instruction is wrapped
+                // in a conditional edge.
+                if let Some(ref condition) = condition {
+                    if instruction_id != capstone::arm_insn::ARM_INS_B {
+                        instruction_graph = semantics::conditional(&instruction_graph, condition.clone())?;
+                    }
+                }
+
+                instruction_graph.set_address(Some(instruction.address));
+
+                block_graph.append(&instruction_graph)?;
+
+                length += instruction.size as usize;
+
+                let next_address = mode.tag(instruction.address + instruction.size as u64);
+
+                // instructions that terminate blocks
+                match instruction_id {
+                    capstone::arm_insn::ARM_INS_B => {
+                        let operand = detail.operands[0];
+                        let target = mode.tag(operand.imm() as u32 as u64);
+                        match condition {
+                            Some(condition) => {
+                                successors.push((next_address, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                                successors.push((target, Some(condition)));
+                            },
+                            None => successors.push((target, None))
+                        }
+                        break;
+                    }
+                    capstone::arm_insn::ARM_INS_CBNZ |
+                    capstone::arm_insn::ARM_INS_CBZ => {
+                        let condition = semantics::compare_branch_condition(mode, &instruction)?;
+                        let target = mode.tag(detail.operands[1].imm() as u32 as u64);
+                        successors.push((next_address, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                        successors.push((target, Some(condition)));
+                        break;
+                    }
+                    // instructions which write pc branch with a brc, and fall
+                    // through when their condition does not hold
+                    _ => {
+                        if semantics::writes_pc(&instruction)? {
+                            if let Some(condition) = condition {
+                                successors.push((next_address, Some(Expression::cmpeq(condition, expr_const(0, 1))?)));
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+            else {
+                bail!("not an arm instruction")
+            }
+
+            // the mnemonic of an it gives the condition of each instruction
+            // it covers, as in itte
+            if let capstone::InstrIdArch::ARM(capstone::arm_insn::ARM_INS_IT) = instruction.id {
+                it_block = Some((offset, instruction.mnemonic.len() - 1, 0));
+            }
+
+            offset += instruction.size as usize;
+        }
+
+        Ok(BlockTranslationResult::new(block_graph, address, length, successors))
+    }
+}
@@ -0,0 +1,1977 @@
+use capstone_rust::capstone;
+use capstone_rust::capstone::{cs_arm, cs_arm_op};
+use capstone_rust::capstone_sys::{arm_cc, arm_op_type, arm_reg, arm_shifter};
+use error::*;
+use il::*;
+use il::Expression as Expr;
+
+
+const MEM_SIZE: u64 = (1 << 32);
+
+
+/// The instruction set instructions are lifted from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// 32-bit ARM (A32)
+    Arm,
+    /// Thumb and Thumb-2
+    Thumb
+}
+
+
+impl Mode {
+    /// The mode of the code at `address`. Thumb addresses have their lowest
+    /// bit set.
+    pub fn from_address(address: u64) -> Mode {
+        if address & 1 == 1 {
+            Mode::Thumb
+        }
+        else {
+            Mode::Arm
+        }
+    }
+
+    /// Tag `address` as holding code in this mode.
+    pub fn tag(&self, address: u64) -> u64 {
+        match *self {
+            Mode::Arm => address & !1,
+            Mode::Thumb => address | 1
+        }
+    }
+
+    /// The value an instruction at `address` reads from pc.
+    pub fn pc(&self, address: u64) -> u64 {
+        match *self {
+            Mode::Arm => address + 8,
+            Mode::Thumb => address + 4
+        }
+    }
+}
+
+
+/// Returns the scalar for a general-purpose register other than pc.
+pub fn register(reg: arm_reg) -> Result<Scalar> {
+    let name = match reg {
+        arm_reg::ARM_REG_R0 => "r0",
+        arm_reg::ARM_REG_R1 => "r1",
+        arm_reg::ARM_REG_R2 => "r2",
+        arm_reg::ARM_REG_R3 => "r3",
+        arm_reg::ARM_REG_R4 => "r4",
+        arm_reg::ARM_REG_R5 => "r5",
+        arm_reg::ARM_REG_R6 => "r6",
+        arm_reg::ARM_REG_R7 => "r7",
+        arm_reg::ARM_REG_R8 => "r8",
+        arm_reg::ARM_REG_R9 |
+        arm_reg::ARM_REG_SB => "r9",
+        arm_reg::ARM_REG_R10 |
+        arm_reg::ARM_REG_SL => "r10",
+        arm_reg::ARM_REG_R11 |
+        arm_reg::ARM_REG_FP => "r11",
+        arm_reg::ARM_REG_R12 |
+        arm_reg::ARM_REG_IP => "r12",
+        arm_reg::ARM_REG_R13 |
+        arm_reg::ARM_REG_SP => "sp",
+        arm_reg::ARM_REG_R14 |
+        arm_reg::ARM_REG_LR => "lr",
+        _ => bail!("unsupported register {:?}", reg)
+    };
+    Ok(scalar(name, 32))
+}
+
+
+/// Returns true if `reg` is pc.
+pub fn is_pc(reg: arm_reg) -> bool {
+    reg == arm_reg::ARM_REG_PC || reg == arm_reg::ARM_REG_R15
+}
+
+
+/// Returns the details for an ARM instruction.
+pub fn details(instruction: &capstone::Instr) -> Result<cs_arm> {
+    let detail = instruction.detail.as_ref().unwrap();
+    match detail.arch {
+        capstone::DetailsArch::ARM(x) => Ok(x),
+        _ => Err("Could not get instruction details".into())
+    }
+}
+
+
+/// Returns the value of a register. Reads of pc give the address of the
+/// instruction plus 8 in ARM mode, or plus 4 in Thumb mode.
+pub fn register_value(mode: Mode, instruction: &capstone::Instr, reg: arm_reg)
+-> Result<Expression> {
+    if is_pc(reg) {
+        Ok(expr_const(mode.pc(instruction.address), 32))
+    }
+    else {
+        Ok(register(reg)?.into())
+    }
+}
+
+
+/// Writes the result of a data processing instruction to a register.
+///
+/// A write to pc is a branch. In Thumb mode these branches stay in Thumb mode.
+pub fn write_register(mode: Mode, block: &mut Block, reg: arm_reg, value: Expression) -> Result<()> {
+    if is_pc(reg) {
+        let target = match mode {
+            Mode::Arm => value,
+            Mode::Thumb => Expr::or(value, expr_const(1, 32))?
+        };
+        block.brc(target, expr_const(1, 1));
+    }
+    else {
+        block.assign(register(reg)?, value);
+    }
+    Ok(())
+}
+
+
+/// Writes a value loaded from memory to a register.
+///
+/// A load into pc is a branch, and the lowest bit of the value loaded selects
+/// the mode of the target.
+pub fn load_register(block: &mut Block, reg: arm_reg, value: Expression) -> Result<()> {
+    if is_pc(reg) {
+        block.brc(value, expr_const(1, 1));
+    }
+    else {
+        block.assign(register(reg)?, value);
+    }
+    Ok(())
+}
+
+
+/// Returns bit `bit` of `value`.
+fn bit(value: Expression, bit: u64) -> Result<Expression> {
+    let bits = value.bits();
+    Expr::trun(1, Expr::shr(value, expr_const(bit, bits))?)
+}
+
+
+/// Returns `value` shifted right arithmetically by `amount`.
+fn asr(value: Expression, amount: Expression) -> Result<Expression> {
+    let bits = value.bits();
+
+    // The bits shifted in are copies of the sign bit
+    let mask = Expr::shl(expr_const(1, bits), amount.clone())?;
+    let mask = Expr::sub(mask, expr_const(1, bits))?;
+    let mask = Expr::shl(mask, Expr::sub(expr_const(bits as u64, bits), amount.clone())?)?;
+    let sign = Expr::shr(value.clone(), expr_const(bits as u64 - 1, bits))?;
+
+    Expr::or(Expr::mul(mask, sign)?, Expr::shr(value, amount)?)
+}
+
+
+/// Returns `value` rotated right by `amount`.
+fn ror(value: Expression, amount: Expression) -> Result<Expression> {
+    let bits = value.bits();
+    let left = Expr::sub(expr_const(bits as u64, bits), amount.clone())?;
+    Expr::or(Expr::shr(value.clone(), amount)?, Expr::shl(value, left)?)
+}
+
+
+/// Shifts `value` by an immediate `amount`, returning the result and the
+/// carry out of the shifter, if the shifter changes the carry flag.
+fn shift_immediate(shifter: arm_shifter, value: Expression, amount: u64)
+-> Result<(Expression, Option<Expression>)> {
+    Ok(match shifter {
+        arm_shifter::ARM_SFT_INVALID => (value, None),
+        arm_shifter::ARM_SFT_LSL |
+        arm_shifter::ARM_SFT_LSL_REG => {
+            if amount == 0 {
+                (value, None)
+            }
+            else {
+                let carry = bit(value.clone(), 32 - amount)?;
+                (Expr::shl(value, expr_const(amount, 32))?, Some(carry))
+            }
+        },
+        arm_shifter::ARM_SFT_LSR |
+        arm_shifter::ARM_SFT_LSR_REG => {
+            let carry = bit(value.clone(), amount - 1)?;
+            if amount == 32 {
+                (expr_const(0, 32), Some(carry))
+            }
+            else {
+                (Expr::shr(value, expr_const(amount, 32))?, Some(carry))
+            }
+        },
+        arm_shifter::ARM_SFT_ASR |
+        arm_shifter::ARM_SFT_ASR_REG => {
+            let carry = bit(value.clone(), amount - 1)?;
+            // Shifting by 32 fills every bit with the sign bit, as does 31
+            let amount = if amount > 31 { 31 } else { amount };
+            (asr(value, expr_const(amount, 32))?, Some(carry))
+        },
+        arm_shifter::ARM_SFT_ROR |
+        arm_shifter::ARM_SFT_ROR_REG => {
+            let result = ror(value, expr_const(amount, 32))?;
+            let carry = bit(result.clone(), 31)?;
+            (result, Some(carry))
+        },
+        arm_shifter::ARM_SFT_RRX |
+        arm_shifter::ARM_SFT_RRX_REG => {
+            let carry = bit(value.clone(), 0)?;
+            let c = Expr::shl(Expr::zext(32, expr_scalar("C", 1))?, expr_const(31, 32))?;
+            (Expr::or(c, Expr::shr(value, expr_const(1, 32))?)?, Some(carry))
+        }
+    })
+}
+
+
+/// Shifts `value` by `amount`, the bottom byte of a register.
+///
+/// The carry out of the shifter is not modelled for shifts by a register.
+fn shift_register(shifter: arm_shifter, value: Expression, amount: Expression) -> Result<Expression> {
+    let amount = Expr::and(amount, expr_const(0xff, 32))?;
+    match shifter {
+        arm_shifter::ARM_SFT_LSL |
+        arm_shifter::ARM_SFT_LSL_REG => Expr::shl(value, amount),
+        arm_shifter::ARM_SFT_LSR |
+        arm_shifter::ARM_SFT_LSR_REG => Expr::shr(value, amount),
+        arm_shifter::ARM_SFT_ASR |
+        arm_shifter::ARM_SFT_ASR_REG => asr(value, amount),
+        arm_shifter::ARM_SFT_ROR |
+        arm_shifter::ARM_SFT_ROR_REG => ror(value, Expr::and(amount, expr_const(0x1f, 32))?),
+        _ => bail!("invalid register shift {:?}", shifter)
+    }
+}
+
+
+/// Applies the barrel shifter of `operand` to `value`, returning the result
+/// and the carry out of the shifter.
+fn shift(mode: Mode, instruction: &capstone::Instr, operand: &cs_arm_op, value: Expression)
+-> Result<(Expression, Option<Expression>)> {
+    match operand.shift.type_ {
+        arm_shifter::ARM_SFT_ASR_REG |
+        arm_shifter::ARM_SFT_LSL_REG |
+        arm_shifter::ARM_SFT_LSR_REG |
+        arm_shifter::ARM_SFT_ROR_REG => {
+            let amount = register_value(mode, instruction, arm_reg::from(operand.shift.value))?;
+            Ok((shift_register(operand.shift.type_, value, amount)?, None))
+        },
+        shifter => shift_immediate(shifter, value, operand.shift.value as u64)
+    }
+}
+
+
+/// Gets the value of a register or immediate operand after the barrel
+/// shifter, along with the carry out of the shifter.
+pub fn operand_value(mode: Mode, instruction: &capstone::Instr, operand: &cs_arm_op)
+-> Result<(Expression, Option<Expression>)> {
+    match operand.type_ {
+        arm_op_type::ARM_OP_REG => {
+            let value = register_value(mode, instruction, *operand.reg())?;
+            shift(mode, instruction, operand, value)
+        },
+        arm_op_type::ARM_OP_IMM |
+        arm_op_type::ARM_OP_PIMM |
+        arm_op_type::ARM_OP_CIMM => Ok((expr_const(operand.imm() as u32 as u64, 32), None)),
+        _ => bail!("unsupported operand type {:?}", operand.type_)
+    }
+}
+
+
+/// Gets the address a memory operand refers to.
+///
+/// Addresses relative to pc use pc aligned down to a word, as literal loads
+/// do.
+pub fn memory_address(mode: Mode, instruction: &capstone::Instr, operand: &cs_arm_op)
+-> Result<Expression> {
+    let mem = operand.mem();
+
+    let base = arm_reg::from(mem.base);
+    let base = if is_pc(base) {
+        expr_const(mode.pc(instruction.address) & !3, 32)
+    }
+    else {
+        register_value(mode, instruction, base)?
+    };
+
+    let (offset, subtract) = match arm_reg::from(mem.index) {
+        arm_reg::ARM_REG_INVALID => {
+            if mem.disp == 0 {
+                return Ok(base);
+            }
+            (expr_const((mem.disp as i64).abs() as u64, 32), mem.disp < 0 || operand.subtracted)
+        },
+        index => {
+            let index = register_value(mode, instruction, index)?;
+            let index = if mem.lshift > 0 {
+                Expr::shl(index, expr_const(mem.lshift as u64, 32))?
+            }
+            else {
+                shift(mode, instruction, operand, index)?.0
+            };
+            (index, mem.scale == -1 || operand.subtracted)
+        }
+    };
+
+    if subtract {
+        Expr::sub(base, offset)
+    }
+    else {
+        Expr::add(base, offset)
+    }
+}
+
+
+/// Returns the address accessed by a load or store whose memory operand is
+/// `detail.operands[index]`, and writes back the base register for pre- and
+/// post-indexed addressing.
+fn access_address(
+    mode: Mode,
+    block: &mut Block,
+    instruction: &capstone::Instr,
+    detail: &cs_arm,
+    index: usize
+) -> Result<Expression> {
+    let operand = &detail.operands[index];
+    let base = arm_reg::from(operand.mem().base);
+
+    let address = block.temp(32);
+    block.assign(address.clone(), memory_address(mode, instruction, operand)?);
+
+    // A post-indexed offset follows the memory operand, and is added to the
+    // base after the access
+    if index + 1 < detail.op_count as usize {
+        let offset = &detail.operands[index + 1];
+        let (value, subtract) = match offset.type_ {
+            arm_op_type::ARM_OP_IMM => {
+                let imm = offset.imm() as i64;
+                (expr_const(imm.abs() as u64, 32), imm < 0 || offset.subtracted)
+            },
+            _ => (operand_value(mode, instruction, offset)?.0, offset.subtracted)
+        };
+        let updated = if subtract {
+            Expr::sub(address.clone().into(), value)?
+        }
+        else {
+            Expr::add(address.clone().into(), value)?
+        };
+        write_register(mode, block, base, updated)?;
+    }
+    else if detail.writeback {
+        write_register(mode, block, base, address.clone().into())?;
+    }
+
+    Ok(address.into())
+}
+
+
+/// Returns the destination register and the two source operands of a data
+/// processing instruction. Thumb writes `op rd, rm` when rd is also the
+/// first source.
+fn data_operands(detail: &cs_arm) -> Result<(arm_reg, &cs_arm_op, &cs_arm_op)> {
+    match detail.op_count {
+        2 => Ok((*detail.operands[0].reg(), &detail.operands[0], &detail.operands[1])),
+        3 => Ok((*detail.operands[0].reg(), &detail.operands[1], &detail.operands[2])),
+        _ => bail!("invalid number of operands {}", detail.op_count)
+    }
+}
+
+
+/// Sets N and Z from a result.
+pub fn set_nz(block: &mut Block, result: Expression) -> Result<()> {
+    block.assign(scalar("N", 1), bit(result.clone(), 31)?);
+    block.assign(scalar("Z", 1), Expr::cmpeq(result, expr_const(0, 32))?);
+    Ok(())
+}
+
+
+/// Adds `lhs`, `rhs` and `carry`, ARM's `AddWithCarry`, returning the result
+/// and setting N, Z, C and V if `set_flags` is true.
+///
+/// Subtraction is `AddWithCarry(lhs, NOT(rhs), 1)`.
+pub fn add_with_carry(block: &mut Block, lhs: Expression, rhs: Expression, carry: Expression, set_flags: bool)
+-> Result<Expression> {
+    let wide = block.temp(33);
+    let sum = Expr::add(Expr::zext(33, lhs.clone())?, Expr::zext(33, rhs.clone())?)?;
+    block.assign(wide.clone(), Expr::add(sum, Expr::zext(33, carry)?)?);
+
+    let result = block.temp(32);
+    block.assign(result.clone(), Expr::trun(32, wide.clone().into())?);
+
+    if set_flags {
+        set_nz(block, result.clone().into())?;
+        block.assign(scalar("C", 1), bit(wide.into(), 32)?);
+        let overflow = Expr::and(
+            Expr::xor(lhs, result.clone().into())?,
+            Expr::xor(rhs, result.clone().into())?
+        )?;
+        block.assign(scalar("V", 1), bit(overflow, 31)?);
+    }
+
+    Ok(result.into())
+}
+
+
+/// Returns the bitwise not of `value`.
+fn not(value: Expression) -> Result<Expression> {
+    let bits = value.bits();
+    Expr::xor(value, expr_const(0xffff_ffff, bits))
+}
+
+
+/// Returns `a` if `condition` holds, or `b` otherwise.
+fn select(condition: Expression, a: Expression, b: Expression) -> Result<Expression> {
+    let mask = Expr::sext(a.bits(), condition)?;
+    Expr::or(Expr::and(a, mask.clone())?, Expr::and(b, not(mask)?)?)
+}
+
+
+/// Returns `lhs / rhs`, where dividing by zero gives zero.
+///
+/// The divisor is replaced with 1 when it is zero, so the division itself
+/// never divides by zero.
+fn divide(lhs: Expression, rhs: Expression, signed: bool) -> Result<Expression> {
+    let bits = rhs.bits();
+    let zero = Expr::cmpeq(rhs.clone(), expr_const(0, bits))?;
+    let divisor = Expr::or(rhs, Expr::zext(bits, zero.clone())?)?;
+    let quotient = if signed {
+        Expr::divs(lhs, divisor)?
+    }
+    else {
+        Expr::divu(lhs, divisor)?
+    };
+    select(zero, expr_const(0, bits), quotient)
+}
+
+
+/// Returns the condition under which an instruction with condition code `cc`
+/// executes, or `None` if it always executes.
+///
+/// Instructions in Thumb-2 `it` blocks carry the condition of their block.
+pub fn condition(cc: arm_cc) -> Result<Option<Expression>> {
+    let flag = |name| expr_scalar(name, 1);
+    let clear = |name| Expr::cmpeq(expr_scalar(name, 1), expr_const(0, 1));
+
+    Ok(Some(match cc {
+        arm_cc::ARM_CC_AL |
+        arm_cc::ARM_CC_INVALID => return Ok(None),
+        arm_cc::ARM_CC_EQ => flag("Z"),
+        arm_cc::ARM_CC_NE => clear("Z")?,
+        arm_cc::ARM_CC_HS => flag("C"),
+        arm_cc::ARM_CC_LO => clear("C")?,
+        arm_cc::ARM_CC_MI => flag("N"),
+        arm_cc::ARM_CC_PL => clear("N")?,
+        arm_cc::ARM_CC_VS => flag("V"),
+        arm_cc::ARM_CC_VC => clear("V")?,
+        arm_cc::ARM_CC_HI => Expr::and(flag("C"), clear("Z")?)?,
+        arm_cc::ARM_CC_LS => Expr::or(clear("C")?, flag("Z"))?,
+        arm_cc::ARM_CC_GE => Expr::cmpeq(flag("N"), flag("V"))?,
+        arm_cc::ARM_CC_LT => Expr::cmpneq(flag("N"), flag("V"))?,
+        arm_cc::ARM_CC_GT => Expr::and(clear("Z")?, Expr::cmpeq(flag("N"), flag("V"))?)?,
+        arm_cc::ARM_CC_LE => Expr::or(flag("Z"), Expr::cmpneq(flag("N"), flag("V"))?)?
+    }))
+}
+
+
+/// Wraps the semantics of an instruction so they only execute when
+/// `condition` holds.
+pub fn conditional(body: &ControlFlowGraph, condition: Expression) -> Result<ControlFlowGraph> {
+    let mut control_flow_graph = ControlFlowGraph::new();
+
+    let head_index = control_flow_graph.new_block()?.index();
+    let (entry_index, exit_index) = control_flow_graph.insert(body)?;
+    let tail_index = control_flow_graph.new_block()?.index();
+
+    control_flow_graph.conditional_edge(head_index, entry_index, condition.clone())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        tail_index,
+        Expr::cmpeq(condition, expr_const(0, 1))?
+    )?;
+    control_flow_graph.unconditional_edge(exit_index, tail_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
+
+    Ok(control_flow_graph)
+}
+
+
+/// Returns the condition under which `cbz` or `cbnz` branches.
+pub fn compare_branch_condition(mode: Mode, instruction: &capstone::Instr) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let value = register_value(mode, instruction, *detail.operands[0].reg())?;
+    match instruction.id {
+        capstone::InstrIdArch::ARM(capstone::arm_insn::ARM_INS_CBZ) =>
+            Expr::cmpeq(value, expr_const(0, 32)),
+        capstone::InstrIdArch::ARM(capstone::arm_insn::ARM_INS_CBNZ) =>
+            Expr::cmpneq(value, expr_const(0, 32)),
+        _ => bail!("not a compare and branch instruction")
+    }
+}
+
+
+/// Returns true if `instruction` ends a block by writing pc, other than as a
+/// call.
+pub fn writes_pc(instruction: &capstone::Instr) -> Result<bool> {
+    let detail = details(instruction)?;
+    let registers = |first: usize| {
+        detail.operands[first..(detail.op_count as usize)]
+            .iter()
+            .any(|operand| operand.type_ == arm_op_type::ARM_OP_REG && is_pc(*operand.reg()))
+    };
+
+    let instruction_id = match instruction.id {
+        capstone::InstrIdArch::ARM(instruction_id) => instruction_id,
+        _ => bail!("not an arm instruction")
+    };
+
+    Ok(match instruction_id {
+        capstone::arm_insn::ARM_INS_BL |
+        capstone::arm_insn::ARM_INS_BLX |
+        capstone::arm_insn::ARM_INS_CMN |
+        capstone::arm_insn::ARM_INS_CMP |
+        capstone::arm_insn::ARM_INS_STM |
+        capstone::arm_insn::ARM_INS_STMDA |
+        capstone::arm_insn::ARM_INS_STMDB |
+        capstone::arm_insn::ARM_INS_STMIB |
+        capstone::arm_insn::ARM_INS_STR |
+        capstone::arm_insn::ARM_INS_STRB |
+        capstone::arm_insn::ARM_INS_STRD |
+        capstone::arm_insn::ARM_INS_STREX |
+        capstone::arm_insn::ARM_INS_STRH |
+        capstone::arm_insn::ARM_INS_PUSH |
+        capstone::arm_insn::ARM_INS_TEQ |
+        capstone::arm_insn::ARM_INS_TST => false,
+        capstone::arm_insn::ARM_INS_B |
+        capstone::arm_insn::ARM_INS_BX |
+        capstone::arm_insn::ARM_INS_TBB |
+        capstone::arm_insn::ARM_INS_TBH => true,
+        capstone::arm_insn::ARM_INS_LDM |
+        capstone::arm_insn::ARM_INS_LDMDA |
+        capstone::arm_insn::ARM_INS_LDMDB |
+        capstone::arm_insn::ARM_INS_LDMIB => registers(1),
+        capstone::arm_insn::ARM_INS_POP => registers(0),
+        _ => detail.op_count > 0
+             && detail.operands[0].type_ == arm_op_type::ARM_OP_REG
+             && is_pc(*detail.operands[0].reg())
+    })
+}
+
+
+/// Lifts an instruction of the form `rd = op(rn, operand2)`, which sets
+/// flags itself.
+fn arithmetic<F>(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(&mut Block, Expression, Expression, bool) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, lhs, rhs) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(mode, instruction, lhs)?.0;
+        let rhs = operand_value(mode, instruction, rhs)?.0;
+
+        let result = op(&mut block, lhs, rhs, detail.update_flags)?;
+
+        write_register(mode, &mut block, rd, result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a logical instruction of the form `rd = op(rn, operand2)`. When
+/// flags are set, C is the carry out of the barrel shifter.
+fn logical<F>(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, lhs, rhs) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(mode, instruction, lhs)?.0;
+        let (rhs, carry) = operand_value(mode, instruction, rhs)?;
+
+        let result = block.temp(32);
+        block.assign(result.clone(), op(lhs, rhs)?);
+
+        if detail.update_flags {
+            set_nz(&mut block, result.clone().into())?;
+            if let Some(carry) = carry {
+                block.assign(scalar("C", 1), carry);
+            }
+        }
+
+        write_register(mode, &mut block, rd, result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a comparison, `op(rn, operand2)`, which only sets flags.
+fn compare<F>(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(&mut Block, Expression, Expression, Option<Expression>) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(mode, instruction, &detail.operands[0])?.0;
+        let (rhs, carry) = operand_value(mode, instruction, &detail.operands[1])?;
+
+        op(&mut block, lhs, rhs, carry)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a load of `bits` from memory, extended to 32 bits.
+fn load(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize, signed: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let address = access_address(mode, &mut block, instruction, &detail, 1)?;
+
+        let temp = block.temp(bits);
+        block.load(temp.clone(), address, array("mem", MEM_SIZE));
+
+        let value = if bits == 32 {
+            temp.into()
+        }
+        else if signed {
+            Expr::sext(32, temp.into())?
+        }
+        else {
+            Expr::zext(32, temp.into())?
+        };
+
+        load_register(&mut block, *detail.operands[0].reg(), value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a store of the low `bits` of a register to memory.
+fn store(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize)
+-> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = register_value(mode, instruction, *detail.operands[0].reg())?;
+        let value = if bits == 32 {
+            value
+        }
+        else {
+            Expr::trun(bits, value)?
+        };
+
+        let address = access_address(mode, &mut block, instruction, &detail, 1)?;
+
+        block.store(array("mem", MEM_SIZE), address, value);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Returns the base register, the registers transferred and whether the base
+/// is written back, for `ldm`, `stm`, `push` and `pop`.
+fn multiple_operands(instruction: &capstone::Instr, detail: &cs_arm) -> Result<(arm_reg, Vec<arm_reg>, bool)> {
+    let registers = |first: usize| -> Vec<arm_reg> {
+        detail.operands[first..(detail.op_count as usize)]
+            .iter()
+            .map(|operand| *operand.reg())
+            .collect()
+    };
+
+    match instruction.id {
+        capstone::InstrIdArch::ARM(capstone::arm_insn::ARM_INS_PUSH) |
+        capstone::InstrIdArch::ARM(capstone::arm_insn::ARM_INS_POP) =>
+            Ok((arm_reg::ARM_REG_SP, registers(0), true)),
+        _ => Ok((*detail.operands[0].reg(), registers(1), detail.writeback))
+    }
+}
+
+
+/// Returns the lowest address accessed by a transfer of `count` registers,
+/// and the value of the base register after the transfer.
+fn multiple_addresses(base: Expression, count: u64, increment: bool, before: bool)
+-> Result<(Expression, Expression)> {
+    let size = expr_const(count * 4, 32);
+    Ok(match (increment, before) {
+        (true, false) => (base.clone(), Expr::add(base, size)?),
+        (true, true) => (Expr::add(base.clone(), expr_const(4, 32))?, Expr::add(base, size)?),
+        (false, false) => (
+            Expr::add(Expr::sub(base.clone(), size.clone())?, expr_const(4, 32))?,
+            Expr::sub(base, size)?
+        ),
+        (false, true) => (Expr::sub(base.clone(), size.clone())?, Expr::sub(base, size)?)
+    })
+}
+
+
+/// Lifts a load of multiple registers, `ldm` and `pop`. Registers are loaded
+/// from ascending addresses in register order.
+fn load_multiple(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    increment: bool,
+    before: bool
+) -> Result<()> {
+    let detail = details(instruction)?;
+    let (base, registers, writeback) = multiple_operands(instruction, &detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let base_value = register_value(mode, instruction, base)?;
+        let (start, end) = multiple_addresses(base_value, registers.len() as u64, increment, before)?;
+
+        let address = block.temp(32);
+        block.assign(address.clone(), start);
+
+        let mut values = Vec::new();
+        for i in 0..registers.len() {
+            let temp = block.temp(32);
+            let offset = Expr::add(address.clone().into(), expr_const(i as u64 * 4, 32))?;
+            block.load(temp.clone(), offset, array("mem", MEM_SIZE));
+            values.push(temp);
+        }
+
+        // A base which is also loaded takes the loaded value
+        if writeback && !registers.contains(&base) {
+            write_register(mode, &mut block, base, end)?;
+        }
+
+        // Loads into pc branch, and must come last
+        let mut pc = None;
+        for (reg, value) in registers.into_iter().zip(values.into_iter()) {
+            if is_pc(reg) {
+                pc = Some(value);
+            }
+            else {
+                load_register(&mut block, reg, value.into())?;
+            }
+        }
+        if let Some(value) = pc {
+            load_register(&mut block, arm_reg::ARM_REG_PC, value.into())?;
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a store of multiple registers, `stm` and `push`. Registers are
+/// stored to ascending addresses in register order.
+fn store_multiple(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    increment: bool,
+    before: bool
+) -> Result<()> {
+    let detail = details(instruction)?;
+    let (base, registers, writeback) = multiple_operands(instruction, &detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let base_value = register_value(mode, instruction, base)?;
+        let (start, end) = multiple_addresses(base_value, registers.len() as u64, increment, before)?;
+
+        let address = block.temp(32);
+        block.assign(address.clone(), start);
+
+        for (i, reg) in registers.iter().enumerate() {
+            let value = register_value(mode, instruction, *reg)?;
+            let offset = Expr::add(address.clone().into(), expr_const(i as u64 * 4, 32))?;
+            block.store(array("mem", MEM_SIZE), offset, value);
+        }
+
+        if writeback {
+            write_register(mode, &mut block, base, end)?;
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a sign or zero extension of the low `bits` of a register, `sxtb`,
+/// `sxth`, `uxtb` and `uxth`.
+fn extend(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize, signed: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // The source may be rotated first
+        let value = operand_value(mode, instruction, &detail.operands[1])?.0;
+        let value = Expr::trun(bits, value)?;
+        let value = if signed {
+            Expr::sext(32, value)?
+        }
+        else {
+            Expr::zext(32, value)?
+        };
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a shift instruction, `lsl`, `lsr`, `asr` and `ror`. Thumb writes
+/// `op rd, rm` when rd is also the value shifted, while capstone gives the
+/// A32 aliases of `mov` as `op rd, rm` with the shift carried by rm.
+fn shift_instruction(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    shifter: arm_shifter
+) -> Result<()> {
+    let detail = details(instruction)?;
+    let (rd, value, amount) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (result, carry) = match amount.type_ {
+            arm_op_type::ARM_OP_REG if amount.shift.type_ != arm_shifter::ARM_SFT_INVALID =>
+                operand_value(mode, instruction, amount)?,
+            arm_op_type::ARM_OP_IMM => {
+                let value = operand_value(mode, instruction, value)?.0;
+                shift_immediate(shifter, value, amount.imm() as u64)?
+            },
+            _ => {
+                let value = operand_value(mode, instruction, value)?.0;
+                let amount = operand_value(mode, instruction, amount)?.0;
+                (shift_register(shifter, value, amount)?, None)
+            }
+        };
+
+        let temp = block.temp(32);
+        block.assign(temp.clone(), result);
+
+        if detail.update_flags {
+            set_nz(&mut block, temp.clone().into())?;
+            if let Some(carry) = carry {
+                block.assign(scalar("C", 1), carry);
+            }
+        }
+
+        write_register(mode, &mut block, rd, temp.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a long multiply, `umull`, `smull`, `umlal` and `smlal`, of the form
+/// `op rdlo, rdhi, rn, rm`.
+fn multiply_long(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    signed: bool,
+    accumulate: bool
+) -> Result<()> {
+    let detail = details(instruction)?;
+    let rdlo = *detail.operands[0].reg();
+    let rdhi = *detail.operands[1].reg();
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let extend = |value: Expression| if signed {
+            Expr::sext(64, value)
+        }
+        else {
+            Expr::zext(64, value)
+        };
+
+        let lhs = extend(register_value(mode, instruction, *detail.operands[2].reg())?)?;
+        let rhs = extend(register_value(mode, instruction, *detail.operands[3].reg())?)?;
+
+        let mut product = Expr::mul(lhs, rhs)?;
+        if accumulate {
+            let hi = Expr::zext(64, register_value(mode, instruction, rdhi)?)?;
+            let lo = Expr::zext(64, register_value(mode, instruction, rdlo)?)?;
+            let accumulator = Expr::or(Expr::shl(hi, expr_const(32, 64))?, lo)?;
+            product = Expr::add(product, accumulator)?;
+        }
+
+        let result = block.temp(64);
+        block.assign(result.clone(), product);
+
+        if detail.update_flags {
+            block.assign(scalar("N", 1), bit(result.clone().into(), 63)?);
+            block.assign(scalar("Z", 1), Expr::cmpeq(result.clone().into(), expr_const(0, 64))?);
+        }
+
+        let hi = Expr::trun(32, Expr::shr(result.clone().into(), expr_const(32, 64))?)?;
+        write_register(mode, &mut block, rdlo, Expr::trun(32, result.into())?)?;
+        write_register(mode, &mut block, rdhi, hi)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an instruction with no effect on state we model.
+pub fn nop(control_flow_graph: &mut ControlFlowGraph) -> Result<()> {
+    let block_index = control_flow_graph.new_block()?.index();
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn adc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, rhs, expr_scalar("C", 1), set_flags)
+    })
+}
+
+
+
+pub fn add(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, rhs, expr_const(0, 1), set_flags)
+    })
+}
+
+
+
+pub fn adr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let base = mode.pc(instruction.address) & !3;
+        let offset = detail.operands[1].imm() as i64;
+        let value = (base as i64).wrapping_add(offset) as u64;
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), expr_const(value, 32))?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn and(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(mode, control_flow_graph, instruction, |lhs, rhs| Expr::and(lhs, rhs))
+}
+
+
+
+pub fn asr_(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(mode, control_flow_graph, instruction, arm_shifter::ARM_SFT_ASR)
+}
+
+
+
+/// `b` only emits a block. Its successors are found at translation time.
+pub fn b(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    nop(control_flow_graph)
+}
+
+
+
+pub fn bfc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let rd = *detail.operands[0].reg();
+        let lsb = detail.operands[1].imm() as u64;
+        let width = detail.operands[2].imm() as u64;
+        let mask = (((1u64 << width) - 1) << lsb) & 0xffff_ffff;
+
+        let value = register_value(mode, instruction, rd)?;
+        write_register(mode, &mut block, rd, Expr::and(value, expr_const(!mask, 32))?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn bfi(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let rd = *detail.operands[0].reg();
+        let lsb = detail.operands[2].imm() as u64;
+        let width = detail.operands[3].imm() as u64;
+        let mask = (1u64 << width) - 1;
+
+        let value = register_value(mode, instruction, rd)?;
+        let value = Expr::and(value, expr_const(!(mask << lsb), 32))?;
+
+        let field = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let field = Expr::and(field, expr_const(mask, 32))?;
+        let field = Expr::shl(field, expr_const(lsb, 32))?;
+
+        write_register(mode, &mut block, rd, Expr::or(value, field)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn bic(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(mode, control_flow_graph, instruction, |lhs, rhs| Expr::and(lhs, not(rhs)?))
+}
+
+
+
+pub fn bkpt(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.raise(expr_scalar("bkpt", 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn bl(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let return_address = mode.tag(instruction.address + instruction.size as u64);
+        let target = mode.tag(detail.operands[0].imm() as u32 as u64);
+
+        block.assign(scalar("lr", 32), expr_const(return_address, 32));
+        block.brc(expr_const(target, 32), expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `blx` with an immediate always switches modes. With a register, the
+/// lowest bit of the register selects the mode.
+pub fn blx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let return_address = mode.tag(instruction.address + instruction.size as u64);
+
+        let target = match detail.operands[0].type_ {
+            arm_op_type::ARM_OP_REG => register_value(mode, instruction, *detail.operands[0].reg())?,
+            _ => {
+                let target = detail.operands[0].imm() as u32 as u64;
+                match mode {
+                    Mode::Arm => expr_const(Mode::Thumb.tag(target), 32),
+                    Mode::Thumb => expr_const(target & !3, 32)
+                }
+            }
+        };
+
+        block.assign(scalar("lr", 32), expr_const(return_address, 32));
+        block.brc(target, expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn bx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let target = register_value(mode, instruction, *detail.operands[0].reg())?;
+        block.brc(target, expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `cbz` and `cbnz` only emit a block. Their successors are found at
+/// translation time.
+pub fn cbz(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    nop(control_flow_graph)
+}
+
+
+
+pub fn clz(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = block.temp(32);
+        block.assign(value.clone(), register_value(mode, instruction, *detail.operands[1].reg())?);
+
+        // The count is the number of prefixes of value which are all zero
+        let mut count = expr_const(0, 32);
+        for i in 1..33 {
+            let prefix = Expr::shr(value.clone().into(), expr_const(32 - i, 32))?;
+            let zero = Expr::cmpeq(prefix, expr_const(0, 32))?;
+            count = Expr::add(count, Expr::zext(32, zero)?)?;
+        }
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), count)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cmn(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(mode, control_flow_graph, instruction, |block, lhs, rhs, _| {
+        add_with_carry(block, lhs, rhs, expr_const(0, 1), true)?;
+        Ok(())
+    })
+}
+
+
+
+pub fn cmp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(mode, control_flow_graph, instruction, |block, lhs, rhs, _| {
+        add_with_carry(block, lhs, not(rhs)?, expr_const(1, 1), true)?;
+        Ok(())
+    })
+}
+
+
+
+pub fn eor(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(mode, control_flow_graph, instruction, |lhs, rhs| Expr::xor(lhs, rhs))
+}
+
+
+
+pub fn ldm(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_multiple(mode, control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn ldmda(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_multiple(mode, control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn ldmdb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_multiple(mode, control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn ldmib(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_multiple(mode, control_flow_graph, instruction, true, true)
+}
+
+
+
+pub fn ldr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(mode, control_flow_graph, instruction, 32, false)
+}
+
+
+
+pub fn ldrb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(mode, control_flow_graph, instruction, 8, false)
+}
+
+
+
+pub fn ldrd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let address = access_address(mode, &mut block, instruction, &detail, 2)?;
+
+        let first = block.temp(32);
+        let second = block.temp(32);
+        block.load(first.clone(), address.clone(), array("mem", MEM_SIZE));
+        block.load(
+            second.clone(),
+            Expr::add(address, expr_const(4, 32))?,
+            array("mem", MEM_SIZE)
+        );
+
+        load_register(&mut block, *detail.operands[0].reg(), first.into())?;
+        load_register(&mut block, *detail.operands[1].reg(), second.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn ldrh(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(mode, control_flow_graph, instruction, 16, false)
+}
+
+
+
+pub fn ldrsb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(mode, control_flow_graph, instruction, 8, true)
+}
+
+
+
+pub fn ldrsh(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(mode, control_flow_graph, instruction, 16, true)
+}
+
+
+
+pub fn lsl(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(mode, control_flow_graph, instruction, arm_shifter::ARM_SFT_LSL)
+}
+
+
+
+pub fn lsr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(mode, control_flow_graph, instruction, arm_shifter::ARM_SFT_LSR)
+}
+
+
+
+pub fn mla(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let rhs = register_value(mode, instruction, *detail.operands[2].reg())?;
+        let accumulator = register_value(mode, instruction, *detail.operands[3].reg())?;
+
+        let result = block.temp(32);
+        block.assign(result.clone(), Expr::add(Expr::mul(lhs, rhs)?, accumulator)?);
+
+        if detail.update_flags {
+            set_nz(&mut block, result.clone().into())?;
+        }
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn mls(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let rhs = register_value(mode, instruction, *detail.operands[2].reg())?;
+        let accumulator = register_value(mode, instruction, *detail.operands[3].reg())?;
+
+        let result = Expr::sub(accumulator, Expr::mul(lhs, rhs)?)?;
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn mov(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (value, carry) = operand_value(mode, instruction, &detail.operands[1])?;
+
+        let result = block.temp(32);
+        block.assign(result.clone(), value);
+
+        if detail.update_flags {
+            set_nz(&mut block, result.clone().into())?;
+            if let Some(carry) = carry {
+                block.assign(scalar("C", 1), carry);
+            }
+        }
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn movt(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let rd = *detail.operands[0].reg();
+        let top = (detail.operands[1].imm() as u64 & 0xffff) << 16;
+
+        let value = Expr::and(register_value(mode, instruction, rd)?, expr_const(0xffff, 32))?;
+        write_register(mode, &mut block, rd, Expr::or(value, expr_const(top, 32))?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn mul(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        let result = block.temp(32);
+        block.assign(result.clone(), Expr::mul(lhs, rhs)?);
+        if set_flags {
+            set_nz(block, result.clone().into())?;
+        }
+        Ok(result.into())
+    })
+}
+
+
+
+pub fn mvn(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (value, carry) = operand_value(mode, instruction, &detail.operands[1])?;
+
+        let result = block.temp(32);
+        block.assign(result.clone(), not(value)?);
+
+        if detail.update_flags {
+            set_nz(&mut block, result.clone().into())?;
+            if let Some(carry) = carry {
+                block.assign(scalar("C", 1), carry);
+            }
+        }
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `neg rd, rm` is `rsb rd, rm, #0`.
+pub fn neg(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let result = add_with_carry(
+            &mut block,
+            not(value)?,
+            expr_const(0, 32),
+            expr_const(1, 1),
+            detail.update_flags
+        )?;
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn orn(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(mode, control_flow_graph, instruction, |lhs, rhs| Expr::or(lhs, not(rhs)?))
+}
+
+
+
+pub fn orr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    logical(mode, control_flow_graph, instruction, |lhs, rhs| Expr::or(lhs, rhs))
+}
+
+
+
+pub fn pop(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_multiple(mode, control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn push(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_multiple(mode, control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn rev(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = block.temp(32);
+        block.assign(value.clone(), register_value(mode, instruction, *detail.operands[1].reg())?);
+
+        let mut result = expr_const(0, 32);
+        for i in 0..4 {
+            let byte = Expr::and(
+                Expr::shr(value.clone().into(), expr_const(i * 8, 32))?,
+                expr_const(0xff, 32)
+            )?;
+            result = Expr::or(result, Expr::shl(byte, expr_const((3 - i) * 8, 32))?)?;
+        }
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn rev16(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = block.temp(32);
+        block.assign(value.clone(), register_value(mode, instruction, *detail.operands[1].reg())?);
+
+        let high = Expr::and(
+            Expr::shr(value.clone().into(), expr_const(8, 32))?,
+            expr_const(0x00ff_00ff, 32)
+        )?;
+        let low = Expr::and(
+            Expr::shl(value.into(), expr_const(8, 32))?,
+            expr_const(0xff00_ff00, 32)
+        )?;
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), Expr::or(high, low)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn ror_(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift_instruction(mode, control_flow_graph, instruction, arm_shifter::ARM_SFT_ROR)
+}
+
+
+
+pub fn rrx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let (value, carry) = shift_immediate(arm_shifter::ARM_SFT_RRX, value, 1)?;
+
+        let result = block.temp(32);
+        block.assign(result.clone(), value);
+
+        if detail.update_flags {
+            set_nz(&mut block, result.clone().into())?;
+            if let Some(carry) = carry {
+                block.assign(scalar("C", 1), carry);
+            }
+        }
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn rsb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, not(lhs)?, rhs, expr_const(1, 1), set_flags)
+    })
+}
+
+
+
+pub fn rsc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, not(lhs)?, rhs, expr_scalar("C", 1), set_flags)
+    })
+}
+
+
+
+pub fn sbc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, not(rhs)?, expr_scalar("C", 1), set_flags)
+    })
+}
+
+
+
+pub fn sbfx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lsb = detail.operands[2].imm() as u64;
+        let width = detail.operands[3].imm() as usize;
+
+        let value = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let value = Expr::shr(value, expr_const(lsb, 32))?;
+        let value = Expr::sext(32, Expr::trun(width, value)?)?;
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn sdiv(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |_, lhs, rhs, _| divide(lhs, rhs, true))
+}
+
+
+
+pub fn smlal(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_long(mode, control_flow_graph, instruction, true, true)
+}
+
+
+
+pub fn smull(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_long(mode, control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn stm(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_multiple(mode, control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn stmda(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_multiple(mode, control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn stmdb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_multiple(mode, control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn stmib(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_multiple(mode, control_flow_graph, instruction, true, true)
+}
+
+
+
+pub fn str(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(mode, control_flow_graph, instruction, 32)
+}
+
+
+
+pub fn strb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(mode, control_flow_graph, instruction, 8)
+}
+
+
+
+pub fn strd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let first = register_value(mode, instruction, *detail.operands[0].reg())?;
+        let second = register_value(mode, instruction, *detail.operands[1].reg())?;
+
+        let address = access_address(mode, &mut block, instruction, &detail, 2)?;
+
+        block.store(array("mem", MEM_SIZE), address.clone(), first);
+        block.store(array("mem", MEM_SIZE), Expr::add(address, expr_const(4, 32))?, second);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `strex` always succeeds, as we do not model other processors.
+pub fn strex(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let address = access_address(mode, &mut block, instruction, &detail, 2)?;
+
+        block.store(array("mem", MEM_SIZE), address, value);
+        write_register(mode, &mut block, *detail.operands[0].reg(), expr_const(0, 32))?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn strh(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(mode, control_flow_graph, instruction, 16)
+}
+
+
+
+pub fn sub(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |block, lhs, rhs, set_flags| {
+        add_with_carry(block, lhs, not(rhs)?, expr_const(1, 1), set_flags)
+    })
+}
+
+
+
+pub fn svc(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.raise(expr_scalar("svc", 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn sxtb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    extend(mode, control_flow_graph, instruction, 8, true)
+}
+
+
+
+pub fn sxth(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    extend(mode, control_flow_graph, instruction, 16, true)
+}
+
+
+
+/// `tbb` and `tbh` branch forward by twice an entry loaded from a table of
+/// bytes or halfwords.
+pub fn tbb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let bits = match instruction.id {
+        capstone::InstrIdArch::ARM(capstone::arm_insn::ARM_INS_TBH) => 16,
+        _ => 8
+    };
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let address = memory_address(mode, instruction, &detail.operands[0])?;
+
+        let entry = block.temp(bits);
+        block.load(entry.clone(), address, array("mem", MEM_SIZE));
+
+        let offset = Expr::shl(Expr::zext(32, entry.into())?, expr_const(1, 32))?;
+        let target = Expr::add(expr_const(mode.pc(instruction.address), 32), offset)?;
+
+        write_register(mode, &mut block, arm_reg::ARM_REG_PC, target)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn teq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(mode, control_flow_graph, instruction, |block, lhs, rhs, carry| {
+        set_nz(block, Expr::xor(lhs, rhs)?)?;
+        if let Some(carry) = carry {
+            block.assign(scalar("C", 1), carry);
+        }
+        Ok(())
+    })
+}
+
+
+
+pub fn tst(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    compare(mode, control_flow_graph, instruction, |block, lhs, rhs, carry| {
+        set_nz(block, Expr::and(lhs, rhs)?)?;
+        if let Some(carry) = carry {
+            block.assign(scalar("C", 1), carry);
+        }
+        Ok(())
+    })
+}
+
+
+
+pub fn ubfx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lsb = detail.operands[2].imm() as u64;
+        let width = detail.operands[3].imm() as u64;
+
+        let value = register_value(mode, instruction, *detail.operands[1].reg())?;
+        let value = Expr::shr(value, expr_const(lsb, 32))?;
+        let value = Expr::and(value, expr_const((1 << width) - 1, 32))?;
+
+        write_register(mode, &mut block, *detail.operands[0].reg(), value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn udiv(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(mode, control_flow_graph, instruction, |_, lhs, rhs, _| divide(lhs, rhs, false))
+}
+
+
+
+pub fn umlal(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_long(mode, control_flow_graph, instruction, false, true)
+}
+
+
+
+pub fn umull(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply_long(mode, control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn uxtb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    extend(mode, control_flow_graph, instruction, 8, false)
+}
+
+
+
+pub fn uxth(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    extend(mode, control_flow_graph, instruction, 16, false)
+}
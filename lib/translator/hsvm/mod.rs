@@ -1,200 +1,445 @@
-use il;
-use error::Error;
+//! Translator for HSVM, a small 16-bit big endian virtual machine.
+//!
+//! Every instruction is four bytes. The first byte is the opcode, and the
+//! remaining bytes hold operands in one of these encodings:
+//!
+//! ```text
+//! A: [opcode][  0 ][  0 ][  0 ]
+//! B: [opcode][ rd ][  0 ][  0 ]
+//! C: [opcode][ rd ][ rs ][  0 ]
+//! D: [opcode][ rd ][ rs ][ rt ]
+//! E: [opcode][ rd ][   lval   ]
+//! F: [opcode][  0 ][   lval   ]
+//! ```
+//!
+//! `lval` is a big endian 16-bit immediate. Registers are encoded as `r0` to
+//! `r6` (0 to 6), `rip` (7), `rbp` (8), `rsp` (9) and `r7` (10). Reading
+//! `rip` gives the address of the next instruction, and writing it is a jump.
+//!
+//! | Opcode | Encoding | Instruction    | Semantics                               |
+//! |--------|----------|----------------|-----------------------------------------|
+//! | 0x10   | D        | `add`          | `rd = rs + rt`                          |
+//! | 0x11   | E        | `add`          | `rd = rd + lval`                        |
+//! | 0x12   | D        | `sub`          | `rd = rs - rt`                          |
+//! | 0x13   | E        | `sub`          | `rd = rd - lval`                        |
+//! | 0x14   | D        | `mul`          | `rd = rs * rt`                          |
+//! | 0x15   | E        | `mul`          | `rd = rd * lval`                        |
+//! | 0x16   | D        | `div`          | `rd = rs / rt`, unsigned                |
+//! | 0x17   | E        | `div`          | `rd = rd / lval`, unsigned              |
+//! | 0x18   | D        | `mod`          | `rd = rs % rt`, unsigned                |
+//! | 0x19   | E        | `mod`          | `rd = rd % lval`, unsigned              |
+//! | 0x1a   | D        | `and`          | `rd = rs & rt`                          |
+//! | 0x1b   | E        | `and`          | `rd = rd & lval`                        |
+//! | 0x1c   | D        | `or`           | `rd = rs \| rt`                         |
+//! | 0x1d   | E        | `or`           | `rd = rd \| lval`                       |
+//! | 0x1e   | D        | `xor`          | `rd = rs ^ rt`                          |
+//! | 0x1f   | E        | `xor`          | `rd = rd ^ lval`                        |
+//! | 0x20   | F        | `jmp`          | `rip = rip + lval`                      |
+//! | 0x21   | F        | `je`           | jump if `flags == 0`                    |
+//! | 0x22   | F        | `jne`          | jump if `flags != 0`                    |
+//! | 0x23   | F        | `jl`           | jump if `flags < 0`, signed             |
+//! | 0x24   | F        | `jle`          | jump if `flags <= 0`, signed            |
+//! | 0x25   | F        | `jg`           | jump if `flags > 0`, signed             |
+//! | 0x26   | F        | `jge`          | jump if `flags >= 0`, signed            |
+//! | 0x27   | F        | `call`         | push `rip`, `rip = rip + lval`          |
+//! | 0x28   | B        | `call`         | push `rip`, `rip = rd`                  |
+//! | 0x29   | A        | `ret`          | pop `rip`                               |
+//! | 0x30   | E        | `load`         | `rd = mem16[lval]`                      |
+//! | 0x31   | C        | `load`         | `rd = mem16[rs]`                        |
+//! | 0x32   | E        | `loadb`        | `rd = mem8[lval]`                       |
+//! | 0x33   | C        | `loadb`        | `rd = mem8[rs]`                         |
+//! | 0x34   | E        | `stor`         | `mem16[lval] = rd`                      |
+//! | 0x35   | C        | `stor`         | `mem16[rd] = rs`                        |
+//! | 0x36   | E        | `storb`        | `mem8[lval] = rd`                       |
+//! | 0x37   | C        | `storb`        | `mem8[rd] = rs`                         |
+//! | 0x40   | B        | `in`           | read a byte of input into `rd`          |
+//! | 0x41   | B        | `out`          | write the low byte of `rd` to output    |
+//! | 0x42   | B        | `push`         | `rsp = rsp - 2`, `mem16[rsp] = rd`      |
+//! | 0x43   | F        | `push`         | `rsp = rsp - 2`, `mem16[rsp] = lval`    |
+//! | 0x44   | B        | `pop`          | `rd = mem16[rsp]`, `rsp = rsp + 2`      |
+//! | 0x51   | C        | `mov`          | `rd = rs`                               |
+//! | 0x52   | E        | `mov`          | `rd = lval`                             |
+//! | 0x53   | C        | `cmp`          | `flags = compare(rd, rs)`               |
+//! | 0x54   | E        | `cmp`          | `flags = compare(rd, lval)`             |
+//! | 0x60   | A        | `hlt`          | stop execution                          |
+//! | 0x61   | A        | `syscall`      | call into the platform                  |
+//! | 0x90   | A        | `nop`          |                                         |
+//!
+//! `cmp` compares unsigned, and sets `flags` to 0 if its operands are equal,
+//! 1 if the first is greater, and -1 if the first is less.
+//!
+//! `in`, `out`, `hlt` and `syscall` raise `in`, `out`, `hlt` and `syscall`.
+//! The byte read or written by `in` and `out` passes through the 8-bit scalar
+//! `io`, which `in` reads after its raise and `out` writes before its raise.
+
+use error::*;
+use il::*;
+use il::Expression as Expr;
+use translator::{Arch, BlockTranslationResult, Endian};
+
+
+const MEM_SIZE: u64 = (1 << 16);
+
+/// The size of every HSVM instruction in bytes.
+const INSTRUCTION_SIZE: usize = 4;
+
+
+/// The HSVM translator.
+pub struct Hsvm;
+
+
+impl Hsvm {
+    pub fn new() -> Hsvm {
+        Hsvm
+    }
+}
 
-struct Register {
-    name: &'static str,
-    encoding: u8
+
+/// A register operand.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Register {
+    Scalar(&'static str),
+    Rip
 }
 
 
-const REGISTERS : &'static [Register] = &[
-    Register {name: "r0",  encoding: 0x0},
-    Register {name: "r1",  encoding: 0x1},
-    Register {name: "r2",  encoding: 0x2},
-    Register {name: "r3",  encoding: 0x3},
-    Register {name: "r4",  encoding: 0x4},
-    Register {name: "r5",  encoding: 0x5},
-    Register {name: "r6",  encoding: 0x6},
-    Register {name: "rip", encoding: 0x7},
-    Register {name: "rbp", encoding: 0x8},
-    Register {name: "rsp", encoding: 0x9},
-    Register {name: "r7",  encoding: 0xA},
-];
+impl Register {
+    /// The value of this register in an instruction followed by
+    /// `next_address`.
+    fn value(&self, next_address: u64) -> Expression {
+        match *self {
+            Register::Scalar(name) => expr_scalar(name, 16),
+            Register::Rip => expr_const(next_address, 16)
+        }
+    }
+
+    /// Writes `value` to this register. Writing `rip` is a jump.
+    fn write(&self, block: &mut Block, value: Expression) {
+        match *self {
+            Register::Scalar(name) => block.assign(scalar(name, 16), value),
+            Register::Rip => block.brc(value, expr_const(1, 1))
+        }
+    }
+}
 
 
 
-fn decode_register(byte: u8) -> Result<il::Variable, Error> {
+fn decode_register(byte: u8) -> Result<Register> {
     match byte {
-        0 => Ok(il::Variable::new("r0", 16)),
-        1 => Ok(il::Variable::new("r1", 16)),
-        2 => Ok(il::Variable::new("r2", 16)),
-        3 => Ok(il::Variable::new("r3", 16)),
-        4 => Ok(il::Variable::new("r4", 16)),
-        5 => Ok(il::Variable::new("r5", 16)),
-        6 => Ok(il::Variable::new("r6", 16)),
-        7 => Ok(il::Variable::new("rip", 16)),
-        8 => Ok(il::Variable::new("rbp", 16)),
-        9 => Ok(il::Variable::new("rsp", 16)),
-        10 => Ok(il::Variable::new("r7", 16)),
+        0 => Ok(Register::Scalar("r0")),
+        1 => Ok(Register::Scalar("r1")),
+        2 => Ok(Register::Scalar("r2")),
+        3 => Ok(Register::Scalar("r3")),
+        4 => Ok(Register::Scalar("r4")),
+        5 => Ok(Register::Scalar("r5")),
+        6 => Ok(Register::Scalar("r6")),
+        7 => Ok(Register::Rip),
+        8 => Ok(Register::Scalar("rbp")),
+        9 => Ok(Register::Scalar("rsp")),
+        10 => Ok(Register::Scalar("r7")),
         _ => Err("Invalid register encoding".into())
     }
 }
 
 
 
-fn decode_lval(bytes: &[u8]) -> Result<il::Constant, Error> {
-    let lval = ((bytes[2] as u64) << 8) | (bytes[3] as u64);
-    Ok(il::Constant::new(lval, 16))
+fn decode_lval(bytes: &[u8]) -> u64 {
+    ((bytes[2] as u64) << 8) | (bytes[3] as u64)
 }
 
 
 
-fn decode_b(bytes: &[u8]) -> Result<il::Variable, Error> {
+fn decode_b(bytes: &[u8]) -> Result<Register> {
     decode_register(bytes[1])
 }
 
 
-fn decode_c(bytes: &[u8]) -> Result<(il::Variable, il::Variable), Error> {
-    let var_a = try!(decode_register(bytes[1]));
-    let var_b = try!(decode_register(bytes[2]));
-    Ok((var_a, var_b))
+fn decode_c(bytes: &[u8]) -> Result<(Register, Register)> {
+    let rd = decode_register(bytes[1])?;
+    let rs = decode_register(bytes[2])?;
+    Ok((rd, rs))
 }
 
 
-fn decode_d(bytes: &[u8])
-    ->
-Result<(il::Variable, il::Variable, il::Variable), Error> {
-    let var_a = try!(decode_register(bytes[1]));
-    let var_b = try!(decode_register(bytes[2]));
-    let var_c = try!(decode_register(bytes[3]));
-    Ok((var_a, var_b, var_c))
+fn decode_d(bytes: &[u8]) -> Result<(Register, Register, Register)> {
+    let rd = decode_register(bytes[1])?;
+    let rs = decode_register(bytes[2])?;
+    let rt = decode_register(bytes[3])?;
+    Ok((rd, rs, rt))
 }
 
 
-fn decode_e(bytes: &[u8]) -> Result<(il::Variable, il::Constant), Error> {
-    let var_a = try!(decode_register(bytes[1]));
-    let lval = try!(decode_lval(bytes));
-    Ok((var_a, lval))
+fn decode_e(bytes: &[u8]) -> Result<(Register, Expression)> {
+    let rd = decode_register(bytes[1])?;
+    Ok((rd, expr_const(decode_lval(bytes), 16)))
 }
 
 
-fn decode_f(bytes: &[u8]) -> Result<il::Constant, Error> {
-    decode_lval(bytes)
+fn decode_f(bytes: &[u8]) -> Expression {
+    expr_const(decode_lval(bytes), 16)
 }
 
 
-fn decode_operation(bytes: &[u8]) -> Result<il::Operation, Error> {
-    if bytes.len() < 4 {
-        return Err("instruction too short".into());
+/// Returns true if the instruction with `opcode` writes to its `rd` operand.
+fn writes_destination(opcode: u8) -> bool {
+    match opcode {
+        0x10...0x1f |
+        0x30...0x33 |
+        0x40 | 0x44 | 0x51 | 0x52 => true,
+        _ => false
     }
+}
+
+
+/// Returns the target of a relative jump or call at `address`.
+fn relative_target(bytes: &[u8], address: u64) -> u64 {
+    (address + INSTRUCTION_SIZE as u64 + decode_lval(bytes)) & 0xffff
+}
+
 
-    /*
-    // Arithmetic operations
-    if bytes[0] >= 0x10 && bytes[0] < 0x20 {
-        let dst = decode_register(bytes[1])?;
-        let (lhs, rhs) = match bytes[0] & 0x1 {
-            0 => {
-                (
-                    Expression::variable(decode_register(bytes[2])?),
-                    Expression::variable(decode_register(bytes[3])?)
-                )
+/// Returns the condition of a conditional jump.
+fn jump_condition(opcode: u8) -> Result<Expression> {
+    let flags = expr_scalar("flags", 16);
+    Ok(match opcode {
+        0x21 => Expr::cmpeq(flags, expr_const(0, 16))?,
+        0x22 => Expr::cmpneq(flags, expr_const(0, 16))?,
+        0x23 => Expr::cmplts(flags, expr_const(0, 16))?,
+        0x24 => Expr::cmplts(flags, expr_const(1, 16))?,
+        0x25 => Expr::cmplts(expr_const(0, 16), flags)?,
+        0x26 => Expr::cmplts(expr_const(0xffff, 16), flags)?,
+        _ => bail!("0x{:02x} is not a conditional jump", opcode)
+    })
+}
+
+
+/// Pushes `value` onto the stack.
+fn push(block: &mut Block, value: Expression) -> Result<()> {
+    let temp = block.temp(16);
+    block.assign(temp.clone(), value);
+    let rsp = Expr::sub(expr_scalar("rsp", 16), expr_const(2, 16))?;
+    block.assign(scalar("rsp", 16), rsp);
+    block.store(array("mem", MEM_SIZE), expr_scalar("rsp", 16), temp.into());
+    Ok(())
+}
+
+
+/// Pops a value from the stack.
+fn pop(block: &mut Block) -> Result<Expression> {
+    let temp = block.temp(16);
+    block.load(temp.clone(), expr_scalar("rsp", 16), array("mem", MEM_SIZE));
+    let rsp = Expr::add(expr_scalar("rsp", 16), expr_const(2, 16))?;
+    block.assign(scalar("rsp", 16), rsp);
+    Ok(temp.into())
+}
+
+
+/// Returns `flags` for a comparison of `lhs` and `rhs`.
+fn compare(lhs: Expression, rhs: Expression) -> Result<Expression> {
+    let less = Expr::sext(16, Expr::cmpltu(lhs.clone(), rhs.clone())?)?;
+    let greater = Expr::zext(16, Expr::cmpltu(rhs, lhs)?)?;
+    Expr::or(less, greater)
+}
+
+
+/// Lifts the instruction in `bytes` at `address` into a single block.
+fn translate_instruction(control_flow_graph: &mut ControlFlowGraph, bytes: &[u8], address: u64) -> Result<()> {
+    let next_address = address + INSTRUCTION_SIZE as u64;
+    let opcode = bytes[0];
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        match opcode {
+            // arithmetic
+            0x10...0x1f => {
+                let (rd, lhs, rhs) = if opcode & 1 == 0 {
+                    let (rd, rs, rt) = decode_d(bytes)?;
+                    (rd, rs.value(next_address), rt.value(next_address))
+                }
+                else {
+                    let (rd, lval) = decode_e(bytes)?;
+                    (rd, rd.value(next_address), lval)
+                };
+                let result = match opcode & 0xe {
+                    0x0 => Expr::add(lhs, rhs)?,
+                    0x2 => Expr::sub(lhs, rhs)?,
+                    0x4 => Expr::mul(lhs, rhs)?,
+                    0x6 => Expr::divu(lhs, rhs)?,
+                    0x8 => Expr::modu(lhs, rhs)?,
+                    0xa => Expr::and(lhs, rhs)?,
+                    0xc => Expr::or(lhs, rhs)?,
+                    _ => Expr::xor(lhs, rhs)?
+                };
+                rd.write(&mut block, result);
             },
-            1 => {
-                (
-                    Expression::variable(decode_register(bytes[1])?),
-                    Expression::constant(decode_lval(bytes)?)
-                )
-            }
-            _ => return Err("Error decoding arithmetic operands".into())
-        };
-
-        return match bytes[0] & 0xe {
-            0 => Ok(Operation::Assign {
-                dst: dst,
-                src: Expression::Add(Box::new(lhs), Box::new(rhs))
-            }),
-            2 => Ok(Operation::Assign {
-                dst: dst,
-                src: Expression::Sub(Box::new(lhs), Box::new(rhs))
-            }),
-            4 => Ok(il::Instruction::Assign {
-                dst: dst,
-                src: il::Expression::Mul(Box::new(lhs), Box::new(rhs))
-            }),
-            6 => Ok(il::Instruction::Assign {
-                dst: dst,
-                src: il::Expression::Divu(Box::new(lhs), Box::new(rhs)),
-            }),
-            8 => Ok(il::Instruction::Assign {
-                dst: dst,
-                src: il::Expression::Modu(Box::new(lhs), Box::new(rhs))
-            }),
-            10 => Ok(il::Instruction::Assign {
-                dst: dst,
-                src: il::Expression::And(Box::new(lhs), Box::new(rhs))
-            }),
-            12 => Ok(il::Instruction::Assign {
-                dst: dst,
-                src: il::Expression::Or(Box::new(lhs), Box::new(rhs))
-            }),
-            14 => Ok(il::Instruction::Assign {
-                dst: dst,
-                src: il::Expression::Xor(Box::new(lhs), Box::new(rhs))
-            }),
-            _ => Err("Error decoding instruction".into())
+            // jumps only emit a block. Their successors are found at
+            // translation time
+            0x20...0x26 => {},
+            0x27 => {
+                push(&mut block, expr_const(next_address, 16))?;
+                block.brc(expr_const(relative_target(bytes, address), 16), expr_const(1, 1));
+            },
+            0x28 => {
+                let target = decode_b(bytes)?.value(next_address);
+                let temp = block.temp(16);
+                block.assign(temp.clone(), target);
+                push(&mut block, expr_const(next_address, 16))?;
+                block.brc(temp.into(), expr_const(1, 1));
+            },
+            0x29 => {
+                let target = pop(&mut block)?;
+                block.brc(target, expr_const(1, 1));
+            },
+            // loads
+            0x30...0x33 => {
+                let (rd, index) = if opcode & 1 == 0 {
+                    decode_e(bytes)?
+                }
+                else {
+                    let (rd, rs) = decode_c(bytes)?;
+                    (rd, rs.value(next_address))
+                };
+                let value = if opcode & 2 == 0 {
+                    let temp = block.temp(16);
+                    block.load(temp.clone(), index, array("mem", MEM_SIZE));
+                    temp.into()
+                }
+                else {
+                    let temp = block.temp(8);
+                    block.load(temp.clone(), index, array("mem", MEM_SIZE));
+                    Expr::zext(16, temp.into())?
+                };
+                rd.write(&mut block, value);
+            },
+            // stores
+            0x34...0x37 => {
+                let (index, value) = if opcode & 1 == 0 {
+                    let (rd, lval) = decode_e(bytes)?;
+                    (lval, rd.value(next_address))
+                }
+                else {
+                    let (rd, rs) = decode_c(bytes)?;
+                    (rd.value(next_address), rs.value(next_address))
+                };
+                let value = if opcode & 2 == 0 { value } else { Expr::trun(8, value)? };
+                block.store(array("mem", MEM_SIZE), index, value);
+            },
+            0x40 => {
+                block.raise(expr_scalar("in", 1));
+                decode_b(bytes)?.write(&mut block, Expr::zext(16, expr_scalar("io", 8))?);
+            },
+            0x41 => {
+                let value = decode_b(bytes)?.value(next_address);
+                block.assign(scalar("io", 8), Expr::trun(8, value)?);
+                block.raise(expr_scalar("out", 1));
+            },
+            0x42 => push(&mut block, decode_b(bytes)?.value(next_address))?,
+            0x43 => push(&mut block, decode_f(bytes))?,
+            0x44 => {
+                let value = pop(&mut block)?;
+                decode_b(bytes)?.write(&mut block, value);
+            },
+            0x51 => {
+                let (rd, rs) = decode_c(bytes)?;
+                rd.write(&mut block, rs.value(next_address));
+            },
+            0x52 => {
+                let (rd, lval) = decode_e(bytes)?;
+                rd.write(&mut block, lval);
+            },
+            0x53 => {
+                let (rd, rs) = decode_c(bytes)?;
+                block.assign(scalar("flags", 16), compare(rd.value(next_address), rs.value(next_address))?);
+            },
+            0x54 => {
+                let (rd, lval) = decode_e(bytes)?;
+                block.assign(scalar("flags", 16), compare(rd.value(next_address), lval)?);
+            },
+            0x60 => block.raise(expr_scalar("hlt", 1)),
+            0x61 => block.raise(expr_scalar("syscall", 1)),
+            0x90 => {},
+            _ => bail!("Invalid opcode 0x{:02x} at 0x{:x}", opcode, address)
         }
-    }
 
-    let branch = Expression::add(
-        Expression::variable(Variable::new("rip", 16)),
-        Expression::constant(decode_lval(bytes)?));
-
-    match bytes[0] {
-        // JMP
-        0x20 => {
-            Ok(il::Instruction::Brc {
-                dst: branch,
-                condition: il::Expression::constant(il::Constant::new(1, 1))
-            })
-        },
-
-        // JE
-        0x21 => {
-            Ok(Instruction::Brc {
-                dst: branch,
-                condition: Expression::cmpeq(
-                    Expression::variable(Variable::new("flags", 16)),
-                    Expression::constant(Constant::new(0, 16))
-                )
-            })
-        },
-
-        // JNE
-        0x22 => {
-            Ok(Instruction::Brc {
-                dst: branch,
-                condition: Expression::cmpltu(
-                    Expression::constant(Constant::new(0, 16)),
-                    Expression::variable(Variable::new("flags", 16))
-                )
-            })
-        }
+        block.index()
+    };
 
-        // JL
-        0x23 => {
-            Ok(Instruction::Brc {
-                dst: branch,
-                condition: Expression::cmplts(
-                    Expression::variable(Variable::new("flags", 16)),
-                    Expression::constant(Constant::new(0, 16))
-                )
-            })
-        }
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
+    control_flow_graph.set_address(Some(address));
+
+    Ok(())
+}
 
-        _ => Err("Error decoding instruction".into())
+
+
+impl Arch for Hsvm {
+    fn endian(&self) -> Endian {
+        Endian::Big
     }
-    */
 
-    Err("".into())
-}
\ No newline at end of file
+    fn address_bits(&self) -> usize {
+        16
+    }
+
+    fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        // our graph for the block which we will build iteratively with each instruction
+        let mut block_graph = ControlFlowGraph::new();
+
+        // the length of this block in bytes
+        let mut length: usize = 0;
+
+        let mut successors = Vec::new();
+
+        let mut offset: usize = 0;
+
+        loop {
+            let instruction_bytes = match bytes.get(offset..(offset + INSTRUCTION_SIZE)) {
+                Some(instruction_bytes) => instruction_bytes,
+                None => {
+                    successors.push((address + offset as u64, None));
+                    break;
+                }
+            };
+
+            let instruction_address = address + offset as u64;
+
+            let mut instruction_graph = ControlFlowGraph::new();
+            translate_instruction(&mut instruction_graph, instruction_bytes, instruction_address)?;
+
+            block_graph.append(&instruction_graph)?;
+
+            length += INSTRUCTION_SIZE;
+            offset += INSTRUCTION_SIZE;
+
+            let opcode = instruction_bytes[0];
+
+            // instructions that terminate blocks
+            match opcode {
+                0x20 => {
+                    successors.push((relative_target(instruction_bytes, instruction_address), None));
+                    break;
+                },
+                0x21...0x26 => {
+                    let condition = jump_condition(opcode)?;
+                    successors.push((
+                        address + offset as u64,
+                        Some(Expr::cmpeq(condition.clone(), expr_const(0, 1))?)
+                    ));
+                    successors.push((relative_target(instruction_bytes, instruction_address), Some(condition)));
+                    break;
+                },
+                0x29 | 0x60 => break,
+                _ => {
+                    // writes to rip jump with a brc
+                    if writes_destination(opcode) && decode_b(instruction_bytes)? == Register::Rip {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(BlockTranslationResult::new(block_graph, address, length, successors))
+    }
+}
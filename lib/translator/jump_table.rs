@@ -0,0 +1,423 @@
+//! Resolution of indirect jumps through jump tables.
+//!
+//! Blocks which end in a `brc` with no known successors, such as
+//! `jmp [eax*4 + table]`, are sliced backwards, through the block which
+//! guards them, until the target of the jump and the guarding condition are
+//! expressed over one scalar, the index into the table. Every value of the
+//! index which satisfies the guarding condition is enumerated, entries are
+//! read from `TranslationMemory`, and the resulting targets are added as
+//! conditional successors to the block.
+
+use error::*;
+use executor;
+use il::*;
+use std::collections::{BTreeMap, BTreeSet};
+use translator::{BlockTranslationResult, Endian, TranslationMemory};
+
+
+/// The largest index into a jump table we will consider.
+const MAX_JUMP_TABLE_INDEX: u64 = 1024;
+
+
+/// The name of the scalar which stands in for the entry loaded from a table.
+const ENTRY: &'static str = "jump_table_entry";
+
+
+/// The name of the scalar which stands in for an index loaded from memory.
+const INDEX: &'static str = "jump_table_index";
+
+
+/// Replace `scalar` with `replacement` everywhere in `expression`.
+pub(crate) fn substitute(expression: &mut Expression, scalar: &Scalar, replacement: &Expression) {
+    let replace = match *expression {
+        Expression::Scalar(ref s) => s == scalar,
+        Expression::Constant(_) => false,
+        Expression::Add(ref mut lhs, ref mut rhs) |
+        Expression::Sub(ref mut lhs, ref mut rhs) |
+        Expression::Mul(ref mut lhs, ref mut rhs) |
+        Expression::Divu(ref mut lhs, ref mut rhs) |
+        Expression::Modu(ref mut lhs, ref mut rhs) |
+        Expression::Divs(ref mut lhs, ref mut rhs) |
+        Expression::Mods(ref mut lhs, ref mut rhs) |
+        Expression::And(ref mut lhs, ref mut rhs) |
+        Expression::Or(ref mut lhs, ref mut rhs) |
+        Expression::Xor(ref mut lhs, ref mut rhs) |
+        Expression::Shl(ref mut lhs, ref mut rhs) |
+        Expression::Shr(ref mut lhs, ref mut rhs) |
+        Expression::Cmpeq(ref mut lhs, ref mut rhs) |
+        Expression::Cmpneq(ref mut lhs, ref mut rhs) |
+        Expression::Cmplts(ref mut lhs, ref mut rhs) |
+        Expression::Cmpltu(ref mut lhs, ref mut rhs) |
+        Expression::Fadd(ref mut lhs, ref mut rhs) |
+        Expression::Fsub(ref mut lhs, ref mut rhs) |
+        Expression::Fmul(ref mut lhs, ref mut rhs) |
+        Expression::Fdiv(ref mut lhs, ref mut rhs) |
+        Expression::Fcmpeq(ref mut lhs, ref mut rhs) |
+        Expression::Fcmplt(ref mut lhs, ref mut rhs) |
+        Expression::Fcmpuo(ref mut lhs, ref mut rhs) => {
+            substitute(lhs, scalar, replacement);
+            substitute(rhs, scalar, replacement);
+            false
+        },
+        Expression::Zext(_, ref mut src) |
+        Expression::Sext(_, ref mut src) |
+        Expression::Trun(_, ref mut src) |
+        Expression::Fext(_, ref mut src) |
+        Expression::Itof(_, ref mut src) |
+        Expression::Ftoi(_, ref mut src) => {
+            substitute(src, scalar, replacement);
+            false
+        }
+    };
+    if replace {
+        *expression = replacement.clone();
+    }
+}
+
+
+fn contains(expression: &Expression, scalar: &Scalar) -> bool {
+    expression.collect_scalars().into_iter().any(|s| s == scalar)
+}
+
+
+/// Evaluate an expression once all of its scalars have been given values.
+fn evaluate(expression: &Expression, values: &[(&Scalar, u64)]) -> Result<u64> {
+    let mut expression = expression.clone();
+    for &(scalar, value) in values {
+        substitute(&mut expression, scalar, &expr_const(value, scalar.bits()));
+    }
+    Ok(executor::constants_expression(&expression)?.value())
+}
+
+
+/// The target of an indirect jump, and the condition which guards it,
+/// expressed over the scalars on entry to the slice.
+struct Slice {
+    target: Expression,
+    condition: Expression,
+    /// The address of the table entry loaded by the jump, if any.
+    entry_address: Option<Expression>,
+    /// The address the index was loaded from, if it was loaded.
+    index_address: Option<Expression>
+}
+
+
+impl Slice {
+    /// Move this slice backwards over an instruction. Returns false if the
+    /// instruction can not be sliced through.
+    fn instruction(&mut self, instruction: &Instruction) -> bool {
+        match *instruction.operation() {
+            Operation::Assign { ref dst, ref src } => {
+                self.substitute(dst, src);
+                true
+            },
+            Operation::Load { ref dst, ref index, .. } => {
+                if self.index_address.as_ref().map(|a| contains(a, dst)).unwrap_or(false) {
+                    return false;
+                }
+                let in_condition = contains(&self.condition, dst);
+                let in_entry_address = self.entry_address
+                    .as_ref()
+                    .map(|a| contains(a, dst))
+                    .unwrap_or(false);
+                if    contains(&self.target, dst)
+                   && self.entry_address.is_none()
+                   && !in_condition {
+                    let entry = scalar(ENTRY, dst.bits());
+                    substitute(&mut self.target, dst, &entry.into());
+                    self.entry_address = Some(index.clone());
+                }
+                else if in_condition || in_entry_address || contains(&self.target, dst) {
+                    // The index itself was loaded, as when a switch compiled
+                    // at -O0 reloads it from the stack in both the guarding
+                    // block and the jump. Every load of the index must read
+                    // the same address.
+                    if self.index_address.as_ref().map(|a| a != index).unwrap_or(false) {
+                        return false;
+                    }
+                    self.substitute(dst, &scalar(INDEX, dst.bits()).into());
+                    self.index_address = Some(index.clone());
+                }
+                true
+            },
+            // We assume stores do not write to the jump table
+            Operation::Store { .. } => true,
+            Operation::Brc { .. } |
+            Operation::Phi { .. } |
+            Operation::Raise { .. } => false
+        }
+    }
+
+    /// Replace `scalar` with `replacement` everywhere in this slice.
+    fn substitute(&mut self, scalar: &Scalar, replacement: &Expression) {
+        substitute(&mut self.target, scalar, replacement);
+        substitute(&mut self.condition, scalar, replacement);
+        if let Some(ref mut entry_address) = self.entry_address {
+            substitute(entry_address, scalar, replacement);
+        }
+        if let Some(ref mut index_address) = self.index_address {
+            substitute(index_address, scalar, replacement);
+        }
+    }
+
+    /// Move this slice backwards over all instructions in a translated
+    /// block. Returns false if the block can not be sliced through.
+    fn block(&mut self, instructions: &[&Instruction]) -> bool {
+        instructions.iter().rev().all(|instruction| self.instruction(instruction))
+    }
+
+    /// The single scalar this slice is expressed over, or `None` if it holds
+    /// no scalars. Returns an error if it holds more than one.
+    fn index(&self) -> Result<Option<Scalar>> {
+        let mut scalars: BTreeSet<&Scalar> = BTreeSet::new();
+        scalars.extend(self.target.collect_scalars());
+        scalars.extend(self.condition.collect_scalars());
+        if let Some(ref entry_address) = self.entry_address {
+            scalars.extend(entry_address.collect_scalars());
+        }
+        let mut scalars = scalars.into_iter().filter(|scalar| scalar.name() != ENTRY);
+        let index = scalars.next().cloned();
+        if scalars.next().is_some() {
+            bail!("Jump table slice depends on more than one scalar");
+        }
+        Ok(index)
+    }
+}
+
+
+/// All instructions of a linear translated block, in order, or `None` if the
+/// translation of this block branches internally.
+fn linear_instructions(control_flow_graph: &ControlFlowGraph)
+-> Option<Vec<&Instruction>> {
+    let mut instructions = Vec::new();
+    let mut block_index = match control_flow_graph.entry() {
+        Some(entry) => entry,
+        None => return None
+    };
+    loop {
+        let block = match control_flow_graph.block(block_index) {
+            Some(block) => block,
+            None => return None
+        };
+        instructions.extend(block.instructions().iter());
+        if Some(block_index) == control_flow_graph.exit() {
+            return Some(instructions);
+        }
+        match control_flow_graph.graph().edges_out(block_index) {
+            Some(edges) if edges.len() == 1 && edges[0].condition().is_none() =>
+                block_index = edges[0].tail(),
+            _ => return None
+        }
+    }
+}
+
+
+fn read_entry(
+    memory: &TranslationMemory,
+    endian: &Endian,
+    address: u64,
+    bits: usize
+) -> Option<u64> {
+    let length = (bits + 7) / 8;
+    let bytes = memory.get_bytes(address, length);
+    if bytes.len() != length {
+        return None;
+    }
+    let mut value = 0;
+    for i in 0..length {
+        let byte = bytes[i] as u64;
+        value |= match *endian {
+            Endian::Big => byte << ((length - i - 1) * 8),
+            Endian::Little => byte << (i * 8)
+        };
+    }
+    Some(value)
+}
+
+
+/// Find the targets of the jump ending the block at `address`, if it jumps
+/// through a table.
+fn jump_targets(
+    translation_results: &BTreeMap<u64, BlockTranslationResult>,
+    address: u64,
+    memory: &TranslationMemory,
+    endian: &Endian
+) -> Result<Option<BTreeSet<u64>>> {
+    let result = &translation_results[&address];
+
+    // The jump must end the block. A block may also end in an instruction
+    // without successors, such as a `hlt` following a call.
+    let control_flow_graph = result.control_flow_graph();
+    let last = control_flow_graph.exit()
+                                 .and_then(|exit| control_flow_graph.block(exit))
+                                 .and_then(|block| block.instructions().last());
+    match last {
+        Some(instruction) if instruction.is_brc() => {},
+        _ => return Ok(None)
+    }
+
+    let instructions = match linear_instructions(result.control_flow_graph()) {
+        Some(instructions) => instructions,
+        None => return Ok(None)
+    };
+    let (jump, instructions) = match instructions.split_last() {
+        Some((jump, instructions)) => (jump, instructions),
+        None => return Ok(None)
+    };
+    let target = match *jump.operation() {
+        Operation::Brc { target: Expression::Constant(_), .. } => return Ok(None),
+        Operation::Brc { ref target, .. } => target.clone(),
+        _ => return Ok(None)
+    };
+
+    let mut slice = Slice {
+        target: target,
+        condition: expr_const(1, 1),
+        entry_address: None,
+        index_address: None
+    };
+    if !slice.block(instructions) {
+        return Ok(None);
+    }
+
+    // Slice through the block guarding this one, if there is only one
+    let predecessors = translation_results
+        .values()
+        .filter_map(|predecessor| {
+            predecessor.successors()
+                .iter()
+                .find(|successor| successor.0 == address)
+                .map(|successor| (predecessor, &successor.1))
+        })
+        .collect::<Vec<(&BlockTranslationResult, &Option<Expression>)>>();
+    if predecessors.len() == 1 {
+        let (predecessor, condition) = predecessors[0];
+        if let Some(ref condition) = *condition {
+            slice.condition = condition.clone();
+        }
+        let instructions = match linear_instructions(predecessor.control_flow_graph()) {
+            Some(instructions) => instructions,
+            None => return Ok(None)
+        };
+        if !slice.block(&instructions) {
+            return Ok(None);
+        }
+    }
+
+    let index = match slice.index() {
+        Ok(index) => index,
+        Err(_) => return Ok(None)
+    };
+
+    // The values of the index we need to consider
+    let (indices, bounded) = match index {
+        Some(ref index) if index.bits() < 64
+                        && (1 << index.bits()) <= MAX_JUMP_TABLE_INDEX =>
+            (1 << index.bits(), true),
+        Some(_) => (MAX_JUMP_TABLE_INDEX, false),
+        None => (1, true)
+    };
+
+    // If the condition holds past the largest index, it does not bound the
+    // index. A signed comparison also holds for negative indices, so we check
+    // the sign bit and the largest value of the index as well.
+    if let (false, &Some(ref index)) = (bounded, &index) {
+        let bits = index.bits();
+        let mask = if bits == 64 { !0 } else { (1 << bits) - 1 };
+        for &value in [indices, 1 << (bits - 1), mask].iter() {
+            if evaluate(&slice.condition, &[(index, value)])? == 1 {
+                return Ok(None);
+            }
+        }
+    }
+
+    let mut targets = BTreeSet::new();
+    for i in 0..indices {
+        let mut values = match index {
+            Some(ref index) => vec![(index, i)],
+            None => Vec::new()
+        };
+        if evaluate(&slice.condition, &values)? == 0 {
+            continue;
+        }
+        let entry = match slice.entry_address {
+            Some(ref entry_address) => {
+                let bits = slice.target
+                    .collect_scalars()
+                    .into_iter()
+                    .find(|scalar| scalar.name() == ENTRY)
+                    .map(|scalar| scalar.bits())
+                    .unwrap_or(0);
+                let entry_address = evaluate(entry_address, &values)?;
+                match read_entry(memory, endian, entry_address, bits) {
+                    Some(value) => Some((scalar(ENTRY, bits), value)),
+                    None => return Ok(None)
+                }
+            },
+            None => None
+        };
+        if let Some((ref entry, value)) = entry {
+            values.push((entry, value));
+        }
+        let target = evaluate(&slice.target, &values)?;
+        // Every target must be somewhere we can translate
+        if memory.get_u8(target).is_none() {
+            return Ok(None);
+        }
+        targets.insert(target);
+    }
+
+    Ok(if targets.is_empty() { None } else { Some(targets) })
+}
+
+
+/// Resolve every unresolved indirect jump through a jump table in
+/// `translation_results`.
+///
+/// Resolved jumps have their `brc` replaced with conditional successors to
+/// every target in the table. Returns the targets found.
+pub fn resolve_jump_tables(
+    translation_results: &mut BTreeMap<u64, BlockTranslationResult>,
+    memory: &TranslationMemory,
+    endian: &Endian
+) -> Result<Vec<u64>> {
+    let mut all_targets = Vec::new();
+
+    let unresolved = translation_results
+        .iter()
+        .filter(|&(_, result)| result.successors().is_empty())
+        .map(|(address, _)| *address)
+        .collect::<Vec<u64>>();
+
+    for address in unresolved {
+        let targets = match jump_targets(translation_results, address, memory, endian)? {
+            Some(targets) => targets,
+            None => continue
+        };
+
+        let result = translation_results.get_mut(&address).unwrap();
+        let exit = result.control_flow_graph.exit().ok_or("No exit for block")?;
+        let block = result.control_flow_graph
+            .block_mut(exit)
+            .ok_or("Could not find exit block")?;
+        let (jump_index, jump_target) = match block.instructions().last() {
+            Some(instruction) => match *instruction.operation() {
+                Operation::Brc { ref target, .. } => (instruction.index(), target.clone()),
+                _ => bail!("Jump table block does not end in brc")
+            },
+            None => bail!("Jump table block is empty")
+        };
+        block.remove_instruction(jump_index)?;
+
+        for target in targets {
+            let condition = Expression::cmpeq(
+                jump_target.clone(),
+                expr_const(target, jump_target.bits())
+            )?;
+            result.successors.push((target, Some(condition)));
+            all_targets.push(target);
+        }
+    }
+
+    Ok(all_targets)
+}
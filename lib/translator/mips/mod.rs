@@ -0,0 +1,340 @@
+//! Capstone-based translators for 32-bit MIPS, in big endian (`Mips`) and
+//! little endian (`Mipsel`).
+//!
+//! The instruction after a branch, in its delay slot, executes before the
+//! branch transfers control. A branch first saves its condition or register
+//! target, as its delay slot may overwrite the registers they are read from.
+//! The delay slot is then lifted into the same block, followed by the
+//! transfer of control of the branch. The delay slot of a branch likely only
+//! executes if the branch is taken.
+
+use capstone_rust::{capstone, capstone_sys};
+use error::*;
+use il::*;
+use translator::{Arch, BlockTranslationResult, Endian};
+
+
+mod semantics;
+
+/// The big endian MIPS translator.
+pub struct Mips;
+
+
+impl Mips {
+    pub fn new() -> Mips {
+        Mips
+    }
+}
+
+
+/// The little endian MIPS translator.
+pub struct Mipsel;
+
+
+impl Mipsel {
+    pub fn new() -> Mipsel {
+        Mipsel
+    }
+}
+
+
+impl Arch for Mips {
+    fn endian(&self) -> Endian {
+        Endian::Big
+    }
+
+    fn address_bits(&self) -> usize {
+        32
+    }
+
+    fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        translate_block(Endian::Big, bytes, address)
+    }
+}
+
+
+impl Arch for Mipsel {
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
+
+    fn address_bits(&self) -> usize {
+        32
+    }
+
+    fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        translate_block(Endian::Little, bytes, address)
+    }
+}
+
+
+/// Disassembles the instruction at `offset` into `bytes`, or returns `None`
+/// if there are no more instructions.
+fn disassemble(cs: &capstone::Capstone, bytes: &[u8], address: u64, offset: usize)
+-> Result<Option<capstone::Instr>> {
+    let disassembly_bytes = match bytes.get(offset..bytes.len()) {
+        Some(disassembly_bytes) => disassembly_bytes,
+        None => return Ok(None)
+    };
+    let instructions = match cs.disasm(disassembly_bytes, address + offset as u64, 1) {
+        Ok(instructions) => instructions,
+        Err(e) => match e.code() {
+            capstone_sys::cs_err::CS_ERR_OK => return Ok(None),
+            _ => bail!("Capstone Error: {}", e.code() as u32)
+        }
+    };
+
+    if instructions.count() == 0 {
+        return Err("Capstone failed to disassemble any instruction".into());
+    }
+
+    Ok(instructions.get(0))
+}
+
+
+/// Lifts a single instruction.
+fn translate_instruction(endian: &Endian, instruction: &capstone::Instr) -> Result<ControlFlowGraph> {
+    let instruction_id = match instruction.id {
+        capstone::InstrIdArch::MIPS(instruction_id) => instruction_id,
+        _ => bail!("not a mips instruction")
+    };
+
+    let mut instruction_graph = ControlFlowGraph::new();
+
+    try!(match instruction_id {
+        capstone::mips_insn::MIPS_INS_ADD    |
+        capstone::mips_insn::MIPS_INS_ADDI   |
+        capstone::mips_insn::MIPS_INS_ADDIU  |
+        capstone::mips_insn::MIPS_INS_ADDU   => semantics::add(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_AND    |
+        capstone::mips_insn::MIPS_INS_ANDI   => semantics::and(&mut instruction_graph, &instruction),
+        // branches only emit a brc if they are calls, or their destination is
+        // undetermined at translation time
+        capstone::mips_insn::MIPS_INS_B      |
+        capstone::mips_insn::MIPS_INS_J      => semantics::b(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BAL    => semantics::bal(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BEQ    |
+        capstone::mips_insn::MIPS_INS_BEQL   => semantics::beq(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BEQZ   => semantics::beqz(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BGEZ   |
+        capstone::mips_insn::MIPS_INS_BGEZL  => semantics::bgez(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BGEZAL => semantics::bgezal(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BGTZ   |
+        capstone::mips_insn::MIPS_INS_BGTZL  => semantics::bgtz(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BLEZ   |
+        capstone::mips_insn::MIPS_INS_BLEZL  => semantics::blez(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BLTZ   |
+        capstone::mips_insn::MIPS_INS_BLTZL  => semantics::bltz(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BLTZAL => semantics::bltzal(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BNE    |
+        capstone::mips_insn::MIPS_INS_BNEL   => semantics::bne(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BNEZ   => semantics::bnez(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_BREAK  => semantics::break_(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_CACHE  |
+        capstone::mips_insn::MIPS_INS_EHB    |
+        capstone::mips_insn::MIPS_INS_NOP    |
+        capstone::mips_insn::MIPS_INS_PREF   |
+        capstone::mips_insn::MIPS_INS_SSNOP  |
+        capstone::mips_insn::MIPS_INS_SYNC   => semantics::nop(&mut instruction_graph),
+        capstone::mips_insn::MIPS_INS_CLO    => semantics::clo(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_CLZ    => semantics::clz(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_DIV    => semantics::div(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_DIVU   => semantics::divu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_EXT    => semantics::ext(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_INS    => semantics::ins(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_JAL    => semantics::jal(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_JALR   => semantics::jalr(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_JR     => semantics::jr(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LB     => semantics::lb(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LBU    => semantics::lbu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LH     => semantics::lh(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LHU    => semantics::lhu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LI     => semantics::li(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LUI    => semantics::lui(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LL     |
+        capstone::mips_insn::MIPS_INS_LW     => semantics::lw(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LWL    => semantics::lwl(endian, &mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_LWR    => semantics::lwr(endian, &mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MADD   => semantics::madd(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MADDU  => semantics::maddu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MFHI   => semantics::mfhi(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MFLO   => semantics::mflo(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MOVE   => semantics::move_(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MOVN   => semantics::movn(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MOVZ   => semantics::movz(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MSUB   => semantics::msub(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MSUBU  => semantics::msubu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MTHI   => semantics::mthi(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MTLO   => semantics::mtlo(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MUL    => semantics::mul(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MULT   => semantics::mult(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_MULTU  => semantics::multu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_NEG    |
+        capstone::mips_insn::MIPS_INS_NEGU   => semantics::neg(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_NOR    => semantics::nor(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_NOT    => semantics::not_(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_OR     |
+        capstone::mips_insn::MIPS_INS_ORI    => semantics::or(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_RDHWR  => semantics::rdhwr(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SB     => semantics::sb(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SC     => semantics::sc(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SEB    => semantics::seb(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SEH    => semantics::seh(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SH     => semantics::sh(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SLL    |
+        capstone::mips_insn::MIPS_INS_SLLV   => semantics::sll(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SLT    |
+        capstone::mips_insn::MIPS_INS_SLTI   => semantics::slt(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SLTIU  |
+        capstone::mips_insn::MIPS_INS_SLTU   => semantics::sltu(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SRA    |
+        capstone::mips_insn::MIPS_INS_SRAV   => semantics::sra(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SRL    |
+        capstone::mips_insn::MIPS_INS_SRLV   => semantics::srl(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SUB    |
+        capstone::mips_insn::MIPS_INS_SUBU   => semantics::sub(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SW     => semantics::sw(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SWL    => semantics::swl(endian, &mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SWR    => semantics::swr(endian, &mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_SYSCALL => semantics::syscall(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_TEQ    => semantics::teq(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_WSBH   => semantics::wsbh(&mut instruction_graph, &instruction),
+        capstone::mips_insn::MIPS_INS_XOR    |
+        capstone::mips_insn::MIPS_INS_XORI   => semantics::xor(&mut instruction_graph, &instruction),
+
+        _ => return Err(format!("Unhandled instruction {} at 0x{:x}",
+            instruction.mnemonic,
+            instruction.address
+        ).into())
+    });
+
+    instruction_graph.set_address(Some(instruction.address));
+
+    Ok(instruction_graph)
+}
+
+
+fn translate_block(endian: Endian, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+    let cs_mode = match endian {
+        Endian::Big => capstone::cs_mode::CS_MODE_MIPS32 | capstone::cs_mode::CS_MODE_BIG_ENDIAN,
+        Endian::Little => capstone::cs_mode::CS_MODE_MIPS32
+    };
+
+    let cs = match capstone::Capstone::new(capstone::cs_arch::CS_ARCH_MIPS, cs_mode) {
+        Ok(cs) => cs,
+        Err(_) => return Err("Capstone Error".into())
+    };
+
+    cs.option(capstone::cs_opt_type::CS_OPT_DETAIL, capstone::cs_opt_value::CS_OPT_ON).unwrap();
+
+    // our graph for the block which we will build iteratively with each instruction
+    let mut block_graph = ControlFlowGraph::new();
+
+    // the length of this block in bytes
+    let mut length: usize = 0;
+
+    let mut successors = Vec::new();
+
+    let mut offset: usize = 0;
+
+    loop {
+        let instruction = match disassemble(&cs, bytes, address, offset)? {
+            Some(instruction) => instruction,
+            None => {
+                successors.push((address + offset as u64, None));
+                break;
+            }
+        };
+
+        let instruction_id = match instruction.id {
+            capstone::InstrIdArch::MIPS(instruction_id) => instruction_id,
+            _ => bail!("not a mips instruction")
+        };
+
+        // A branch whose delay slot is past the end of bytes begins the next
+        // block instead
+        if    semantics::has_delay_slot(instruction_id)
+           && offset + instruction.size as usize + 4 > bytes.len()
+           && length > 0 {
+            successors.push((instruction.address, None));
+            break;
+        }
+
+        let instruction_graph = translate_instruction(&endian, &instruction)?;
+
+        block_graph.append(&instruction_graph)?;
+
+        length += instruction.size as usize;
+        offset += instruction.size as usize;
+
+        if !semantics::has_delay_slot(instruction_id) {
+            continue;
+        }
+
+        // lift the delay slot
+        let delay_slot = match disassemble(&cs, bytes, address, offset)? {
+            Some(delay_slot) => delay_slot,
+            None => bail!("Delay slot of branch at 0x{:x} could not be disassembled", instruction.address)
+        };
+
+        if let capstone::InstrIdArch::MIPS(delay_slot_id) = delay_slot.id {
+            if semantics::has_delay_slot(delay_slot_id) {
+                bail!("Branch in delay slot at 0x{:x}", delay_slot.address);
+            }
+        }
+
+        let mut delay_slot_graph = translate_instruction(&endian, &delay_slot)?;
+
+        if semantics::is_likely(instruction_id) {
+            delay_slot_graph = semantics::conditional(&delay_slot_graph, semantics::branch_condition())?;
+            delay_slot_graph.set_address(Some(delay_slot.address));
+        }
+
+        block_graph.append(&delay_slot_graph)?;
+
+        length += delay_slot.size as usize;
+        offset += delay_slot.size as usize;
+
+        // the transfer of control of the branch, after its delay slot
+        let mut jump_graph = ControlFlowGraph::new();
+        semantics::jump(&mut jump_graph, &instruction)?;
+        jump_graph.set_address(Some(instruction.address));
+
+        block_graph.append(&jump_graph)?;
+
+        let next_address = delay_slot.address + delay_slot.size as u64;
+
+        // instructions that terminate blocks
+        match instruction_id {
+            capstone::mips_insn::MIPS_INS_B |
+            capstone::mips_insn::MIPS_INS_J => {
+                successors.push((semantics::branch_target(&instruction)?, None));
+                break;
+            }
+            capstone::mips_insn::MIPS_INS_BEQ |
+            capstone::mips_insn::MIPS_INS_BEQL |
+            capstone::mips_insn::MIPS_INS_BEQZ |
+            capstone::mips_insn::MIPS_INS_BGEZ |
+            capstone::mips_insn::MIPS_INS_BGEZL |
+            capstone::mips_insn::MIPS_INS_BGTZ |
+            capstone::mips_insn::MIPS_INS_BGTZL |
+            capstone::mips_insn::MIPS_INS_BLEZ |
+            capstone::mips_insn::MIPS_INS_BLEZL |
+            capstone::mips_insn::MIPS_INS_BLTZ |
+            capstone::mips_insn::MIPS_INS_BLTZL |
+            capstone::mips_insn::MIPS_INS_BNE |
+            capstone::mips_insn::MIPS_INS_BNEL |
+            capstone::mips_insn::MIPS_INS_BNEZ => {
+                let condition = semantics::branch_condition();
+                successors.push((next_address, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                successors.push((semantics::branch_target(&instruction)?, Some(condition)));
+                break;
+            }
+            capstone::mips_insn::MIPS_INS_JR => break,
+            // calls continue after their delay slot
+            _ => {}
+        }
+    }
+
+    Ok(BlockTranslationResult::new(block_graph, address, length, successors))
+}
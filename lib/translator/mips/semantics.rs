@@ -0,0 +1,1354 @@
+use capstone_rust::capstone;
+use capstone_rust::capstone::{cs_mips, cs_mips_op};
+use capstone_rust::capstone_sys::{mips_op_type, mips_reg};
+use error::*;
+use il::*;
+use il::Expression as Expr;
+use translator::Endian;
+
+
+const MEM_SIZE: u64 = (1 << 32);
+
+/// The scalar a conditional branch saves its condition in before its delay
+/// slot executes.
+const BRANCH_CONDITION: &'static str = "branch_condition";
+
+/// The scalar a register jump saves its target in before its delay slot
+/// executes.
+const BRANCH_TARGET: &'static str = "branch_target";
+
+
+/// Returns the name of the scalar holding a register. `$zero` has no scalar.
+fn register(reg: mips_reg) -> Result<Option<&'static str>> {
+    Ok(match reg {
+        mips_reg::MIPS_REG_0 => None,
+        mips_reg::MIPS_REG_1 => Some("at"),
+        mips_reg::MIPS_REG_2 => Some("v0"),
+        mips_reg::MIPS_REG_3 => Some("v1"),
+        mips_reg::MIPS_REG_4 => Some("a0"),
+        mips_reg::MIPS_REG_5 => Some("a1"),
+        mips_reg::MIPS_REG_6 => Some("a2"),
+        mips_reg::MIPS_REG_7 => Some("a3"),
+        mips_reg::MIPS_REG_8 => Some("t0"),
+        mips_reg::MIPS_REG_9 => Some("t1"),
+        mips_reg::MIPS_REG_10 => Some("t2"),
+        mips_reg::MIPS_REG_11 => Some("t3"),
+        mips_reg::MIPS_REG_12 => Some("t4"),
+        mips_reg::MIPS_REG_13 => Some("t5"),
+        mips_reg::MIPS_REG_14 => Some("t6"),
+        mips_reg::MIPS_REG_15 => Some("t7"),
+        mips_reg::MIPS_REG_16 => Some("s0"),
+        mips_reg::MIPS_REG_17 => Some("s1"),
+        mips_reg::MIPS_REG_18 => Some("s2"),
+        mips_reg::MIPS_REG_19 => Some("s3"),
+        mips_reg::MIPS_REG_20 => Some("s4"),
+        mips_reg::MIPS_REG_21 => Some("s5"),
+        mips_reg::MIPS_REG_22 => Some("s6"),
+        mips_reg::MIPS_REG_23 => Some("s7"),
+        mips_reg::MIPS_REG_24 => Some("t8"),
+        mips_reg::MIPS_REG_25 => Some("t9"),
+        mips_reg::MIPS_REG_26 => Some("k0"),
+        mips_reg::MIPS_REG_27 => Some("k1"),
+        mips_reg::MIPS_REG_28 => Some("gp"),
+        mips_reg::MIPS_REG_29 => Some("sp"),
+        mips_reg::MIPS_REG_30 => Some("fp"),
+        mips_reg::MIPS_REG_31 => Some("ra"),
+        _ => bail!("unsupported register {:?}", reg)
+    })
+}
+
+
+/// Returns the value of a register. `$zero` reads as 0.
+pub fn register_value(reg: mips_reg) -> Result<Expression> {
+    match register(reg)? {
+        Some(name) => Ok(expr_scalar(name, 32)),
+        None => Ok(expr_const(0, 32))
+    }
+}
+
+
+/// Writes a value to a register. Writes to `$zero` are discarded.
+pub fn write_register(block: &mut Block, reg: mips_reg, value: Expression) -> Result<()> {
+    if let Some(name) = register(reg)? {
+        block.assign(scalar(name, 32), value);
+    }
+    Ok(())
+}
+
+
+/// Returns the details for a MIPS instruction.
+pub fn details(instruction: &capstone::Instr) -> Result<cs_mips> {
+    let detail = instruction.detail.as_ref().unwrap();
+    match detail.arch {
+        capstone::DetailsArch::MIPS(x) => Ok(x),
+        _ => Err("Could not get instruction details".into())
+    }
+}
+
+
+/// Gets the value of a register or immediate operand.
+pub fn operand_value(operand: &cs_mips_op) -> Result<Expression> {
+    match operand.type_ {
+        mips_op_type::MIPS_OP_REG => register_value(*operand.reg()),
+        mips_op_type::MIPS_OP_IMM => Ok(expr_const(operand.imm() as u64 & 0xffff_ffff, 32)),
+        _ => bail!("unsupported operand type {:?}", operand.type_)
+    }
+}
+
+
+/// Gets the address a memory operand refers to.
+pub fn memory_address(operand: &cs_mips_op) -> Result<Expression> {
+    let mem = operand.mem();
+
+    let base = register_value(mips_reg::from(mem.base))?;
+
+    if mem.disp == 0 {
+        Ok(base)
+    }
+    else {
+        Expr::add(base, expr_const(mem.disp as u64 & 0xffff_ffff, 32))
+    }
+}
+
+
+/// Returns the bitwise not of `value`.
+fn not(value: Expression) -> Result<Expression> {
+    let bits = value.bits();
+    Expr::xor(value, expr_const(0xffff_ffff_ffff_ffff, bits))
+}
+
+
+/// Returns `a` if `condition` holds, or `b` otherwise.
+fn select(condition: Expression, a: Expression, b: Expression) -> Result<Expression> {
+    let mask = Expr::sext(a.bits(), condition)?;
+    Expr::or(Expr::and(a, mask.clone())?, Expr::and(b, not(mask)?)?)
+}
+
+
+/// Returns `value` shifted right arithmetically by `amount`.
+fn asr(value: Expression, amount: Expression) -> Result<Expression> {
+    let bits = value.bits();
+
+    // The bits shifted in are copies of the sign bit
+    let mask = Expr::shl(expr_const(1, bits), amount.clone())?;
+    let mask = Expr::sub(mask, expr_const(1, bits))?;
+    let mask = Expr::shl(mask, Expr::sub(expr_const(bits as u64, bits), amount.clone())?)?;
+    let sign = Expr::shr(value.clone(), expr_const(bits as u64 - 1, bits))?;
+
+    Expr::or(Expr::mul(mask, sign)?, Expr::shr(value, amount)?)
+}
+
+
+/// Returns a mask of the low `width` bits.
+fn low_mask(width: u64) -> u64 {
+    if width >= 64 { 0xffff_ffff_ffff_ffff } else { (1 << width) - 1 }
+}
+
+
+/// Returns the number of leading zero bits of `value`.
+fn count_leading_zeros(block: &mut Block, value: Expression) -> Result<Expression> {
+    let temp = block.temp(32);
+    block.assign(temp.clone(), value);
+
+    // The count is the number of prefixes of value which are all zero
+    let mut count = expr_const(0, 32);
+    for i in 1..33 {
+        let prefix = Expr::shr(temp.clone().into(), expr_const(32 - i, 32))?;
+        let zero = Expr::cmpeq(prefix, expr_const(0, 32))?;
+        count = Expr::add(count, Expr::zext(32, zero)?)?;
+    }
+
+    Ok(count)
+}
+
+
+/// Returns the value of the 64-bit accumulator formed by hi and lo.
+fn accumulator() -> Result<Expression> {
+    let hi = Expr::shl(Expr::zext(64, expr_scalar("hi", 32))?, expr_const(32, 64))?;
+    Expr::or(hi, Expr::zext(64, expr_scalar("lo", 32))?)
+}
+
+
+/// Writes a 64-bit value to hi and lo.
+fn write_accumulator(block: &mut Block, value: Expression) -> Result<()> {
+    let temp = block.temp(64);
+    block.assign(temp.clone(), value);
+    block.assign(scalar("hi", 32), Expr::trun(32, Expr::shr(temp.clone().into(), expr_const(32, 64))?)?);
+    block.assign(scalar("lo", 32), Expr::trun(32, temp.into())?);
+    Ok(())
+}
+
+
+/// Wraps the semantics of an instruction so they only execute when
+/// `condition` holds.
+pub fn conditional(body: &ControlFlowGraph, condition: Expression) -> Result<ControlFlowGraph> {
+    let mut control_flow_graph = ControlFlowGraph::new();
+
+    let head_index = control_flow_graph.new_block()?.index();
+    let (entry_index, exit_index) = control_flow_graph.insert(body)?;
+    let tail_index = control_flow_graph.new_block()?.index();
+
+    control_flow_graph.conditional_edge(head_index, entry_index, condition.clone())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        tail_index,
+        Expr::cmpeq(condition, expr_const(0, 1))?
+    )?;
+    control_flow_graph.unconditional_edge(exit_index, tail_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
+
+    Ok(control_flow_graph)
+}
+
+
+/// Returns true if `instruction_id` is a branch or jump with a delay slot.
+pub fn has_delay_slot(instruction_id: capstone::mips_insn) -> bool {
+    match instruction_id {
+        capstone::mips_insn::MIPS_INS_B |
+        capstone::mips_insn::MIPS_INS_BAL |
+        capstone::mips_insn::MIPS_INS_BEQ |
+        capstone::mips_insn::MIPS_INS_BEQL |
+        capstone::mips_insn::MIPS_INS_BEQZ |
+        capstone::mips_insn::MIPS_INS_BGEZ |
+        capstone::mips_insn::MIPS_INS_BGEZAL |
+        capstone::mips_insn::MIPS_INS_BGEZL |
+        capstone::mips_insn::MIPS_INS_BGTZ |
+        capstone::mips_insn::MIPS_INS_BGTZL |
+        capstone::mips_insn::MIPS_INS_BLEZ |
+        capstone::mips_insn::MIPS_INS_BLEZL |
+        capstone::mips_insn::MIPS_INS_BLTZ |
+        capstone::mips_insn::MIPS_INS_BLTZAL |
+        capstone::mips_insn::MIPS_INS_BLTZL |
+        capstone::mips_insn::MIPS_INS_BNE |
+        capstone::mips_insn::MIPS_INS_BNEL |
+        capstone::mips_insn::MIPS_INS_BNEZ |
+        capstone::mips_insn::MIPS_INS_J |
+        capstone::mips_insn::MIPS_INS_JAL |
+        capstone::mips_insn::MIPS_INS_JALR |
+        capstone::mips_insn::MIPS_INS_JR => true,
+        _ => false
+    }
+}
+
+
+/// Returns true if `instruction_id` is a branch likely, whose delay slot
+/// only executes if the branch is taken.
+pub fn is_likely(instruction_id: capstone::mips_insn) -> bool {
+    match instruction_id {
+        capstone::mips_insn::MIPS_INS_BEQL |
+        capstone::mips_insn::MIPS_INS_BGEZL |
+        capstone::mips_insn::MIPS_INS_BGTZL |
+        capstone::mips_insn::MIPS_INS_BLEZL |
+        capstone::mips_insn::MIPS_INS_BLTZL |
+        capstone::mips_insn::MIPS_INS_BNEL => true,
+        _ => false
+    }
+}
+
+
+/// The condition of a conditional branch, as saved before its delay slot.
+pub fn branch_condition() -> Expression {
+    expr_scalar(BRANCH_CONDITION, 1)
+}
+
+
+/// Returns the target of a branch or jump with an immediate target, which
+/// capstone gives as an absolute address in the last operand.
+pub fn branch_target(instruction: &capstone::Instr) -> Result<u64> {
+    let detail = details(instruction)?;
+    let operand = &detail.operands[detail.op_count as usize - 1];
+    Ok(operand.imm() as u64 & 0xffff_ffff)
+}
+
+
+/// Lifts the transfer of control of a call or register jump, which happens
+/// after its delay slot. Other branches report their targets as successors
+/// at translation time, and emit an empty block.
+pub fn jump(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        match instruction.id {
+            capstone::InstrIdArch::MIPS(capstone::mips_insn::MIPS_INS_BAL) |
+            capstone::InstrIdArch::MIPS(capstone::mips_insn::MIPS_INS_JAL) =>
+                block.brc(expr_const(branch_target(instruction)?, 32), expr_const(1, 1)),
+            capstone::InstrIdArch::MIPS(capstone::mips_insn::MIPS_INS_BGEZAL) |
+            capstone::InstrIdArch::MIPS(capstone::mips_insn::MIPS_INS_BLTZAL) =>
+                block.brc(expr_const(branch_target(instruction)?, 32), branch_condition()),
+            capstone::InstrIdArch::MIPS(capstone::mips_insn::MIPS_INS_JALR) |
+            capstone::InstrIdArch::MIPS(capstone::mips_insn::MIPS_INS_JR) =>
+                block.brc(expr_scalar(BRANCH_TARGET, 32), expr_const(1, 1)),
+            _ => ()
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts the part of a branch which happens before its delay slot. The
+/// condition is saved, as the delay slot may overwrite the registers it
+/// reads, and then the link register is written.
+fn branch(
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    condition: Option<Expression>,
+    link: bool
+) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        if let Some(condition) = condition {
+            block.assign(scalar(BRANCH_CONDITION, 1), condition);
+        }
+
+        if link {
+            block.assign(scalar("ra", 32), expr_const(instruction.address + 8, 32));
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a branch which compares its first operand against zero with `op`.
+fn branch_zero<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, link: bool, op: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let condition = op(operand_value(&detail.operands[0])?, expr_const(0, 32))?;
+    branch(control_flow_graph, instruction, Some(condition), link)
+}
+
+
+/// Lifts a register jump, saving its target before the delay slot, and
+/// writing the return address to `link` if given.
+fn jump_register(
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    target: mips_reg,
+    link: Option<mips_reg>
+) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar(BRANCH_TARGET, 32), register_value(target)?);
+
+        if let Some(link) = link {
+            write_register(&mut block, link, expr_const(instruction.address + 8, 32))?;
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Returns the destination register and the two source operands of an
+/// instruction of the form `rd = op(rs, rt)`. Two operand aliases use rd as
+/// rs.
+fn data_operands(detail: &cs_mips) -> Result<(mips_reg, Expression, Expression)> {
+    let rd = *detail.operands[0].reg();
+    if detail.op_count == 2 {
+        Ok((rd, register_value(rd)?, operand_value(&detail.operands[1])?))
+    }
+    else {
+        Ok((rd, operand_value(&detail.operands[1])?, operand_value(&detail.operands[2])?))
+    }
+}
+
+
+/// Lifts an instruction of the form `rd = op(rs, rt)`.
+fn arithmetic<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let (rd, lhs, rhs) = data_operands(&detail)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        write_register(&mut block, rd, op(lhs, rhs)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a set on less than, `rd = cmp(rs, rt) ? 1 : 0`.
+fn set<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, cmp: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::zext(32, cmp(lhs, rhs)?))
+}
+
+
+/// Lifts a shift, `rd = op(rt, sa)`. Only the low five bits of a shift
+/// amount in a register are used.
+fn shift<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    arithmetic(control_flow_graph, instruction, |value, amount| {
+        op(value, Expr::and(amount, expr_const(0x1f, 32))?)
+    })
+}
+
+
+/// Lifts an instruction of the form `rd = op(rs)`.
+fn unary<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+-> Result<()> where F: Fn(&mut Block, Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let rd = *detail.operands[0].reg();
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = operand_value(&detail.operands[1])?;
+        let result = op(&mut block, value)?;
+
+        write_register(&mut block, rd, result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a conditional move, `if cmp(rt, 0) { rd = rs }`.
+fn conditional_move<F>(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, cmp: F)
+-> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    let detail = details(instruction)?;
+    let rd = *detail.operands[0].reg();
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let condition = cmp(operand_value(&detail.operands[2])?, expr_const(0, 32))?;
+        let value = select(condition, operand_value(&detail.operands[1])?, register_value(rd)?)?;
+
+        write_register(&mut block, rd, value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a multiply of rs and rt into hi and lo. The product is added to, or
+/// subtracted from, hi and lo when `accumulate` is given.
+fn multiply(
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    signed: bool,
+    accumulate: Option<bool>
+) -> Result<()> {
+    let detail = details(instruction)?;
+    let op_count = detail.op_count as usize;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_value(&detail.operands[op_count - 2])?;
+        let rhs = operand_value(&detail.operands[op_count - 1])?;
+
+        let (lhs, rhs) = if signed {
+            (Expr::sext(64, lhs)?, Expr::sext(64, rhs)?)
+        }
+        else {
+            (Expr::zext(64, lhs)?, Expr::zext(64, rhs)?)
+        };
+
+        let product = Expr::mul(lhs, rhs)?;
+
+        let result = match accumulate {
+            None => product,
+            Some(false) => Expr::add(accumulator()?, product)?,
+            Some(true) => Expr::sub(accumulator()?, product)?
+        };
+
+        write_accumulator(&mut block, result)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a divide of rs by rt, with the quotient in lo and the remainder in
+/// hi. The result of dividing by zero is unpredictable, and hi and lo are
+/// left unchanged.
+fn divide(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, signed: bool) -> Result<()> {
+    let detail = details(instruction)?;
+    let op_count = detail.op_count as usize;
+
+    let lhs = operand_value(&detail.operands[op_count - 2])?;
+    let rhs = operand_value(&detail.operands[op_count - 1])?;
+
+    let mut body = ControlFlowGraph::new();
+
+    let block_index = {
+        let mut block = body.new_block()?;
+
+        let (quotient, remainder) = if signed {
+            (Expr::divs(lhs.clone(), rhs.clone())?, Expr::mods(lhs, rhs.clone())?)
+        }
+        else {
+            (Expr::divu(lhs.clone(), rhs.clone())?, Expr::modu(lhs, rhs.clone())?)
+        };
+
+        let quotient_temp = block.temp(32);
+        let remainder_temp = block.temp(32);
+        block.assign(quotient_temp.clone(), quotient);
+        block.assign(remainder_temp.clone(), remainder);
+        block.assign(scalar("lo", 32), quotient_temp.into());
+        block.assign(scalar("hi", 32), remainder_temp.into());
+
+        block.index()
+    };
+
+    body.set_entry(block_index)?;
+    body.set_exit(block_index)?;
+
+    *control_flow_graph = conditional(&body, Expr::cmpneq(rhs, expr_const(0, 32))?)?;
+
+    Ok(())
+}
+
+
+/// Lifts a load of `bits` from memory into `operands[0]`, extended to 32
+/// bits.
+fn load(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize, signed: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let temp = block.temp(bits);
+        block.load(temp.clone(), memory_address(&detail.operands[1])?, array("mem", MEM_SIZE));
+
+        let value = if bits == 32 {
+            temp.into()
+        }
+        else if signed {
+            Expr::sext(32, temp.into())?
+        }
+        else {
+            Expr::zext(32, temp.into())?
+        };
+
+        write_register(&mut block, *detail.operands[0].reg(), value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a store of the low `bits` of `operands[0]` to memory.
+fn store(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = register_value(*detail.operands[0].reg())?;
+        let value = if bits == 32 { value } else { Expr::trun(bits, value)? };
+
+        block.store(array("mem", MEM_SIZE), memory_address(&detail.operands[1])?, value);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Returns the aligned word an unaligned access falls in, and the shift, in
+/// bits, between that word and the bytes `left` or right of the access.
+///
+/// The bytes of a word are numbered from its most significant byte on big
+/// endian targets, and from its least significant byte on little endian
+/// targets.
+fn unaligned(block: &mut Block, operand: &cs_mips_op, endian: &Endian, left: bool)
+-> Result<(Expression, Expression)> {
+    let address = block.temp(32);
+    block.assign(address.clone(), memory_address(operand)?);
+
+    let aligned = Expr::and(address.clone().into(), expr_const(!3 & 0xffff_ffff, 32))?;
+    let offset = Expr::and(address.into(), expr_const(3, 32))?;
+
+    // the number of bytes of the word which are not accessed
+    let skipped = match (endian, left) {
+        (&Endian::Big, true) | (&Endian::Little, false) => offset,
+        (&Endian::Big, false) | (&Endian::Little, true) => Expr::sub(expr_const(3, 32), offset)?
+    };
+
+    Ok((aligned, Expr::mul(skipped, expr_const(8, 32))?))
+}
+
+
+/// Lifts `lwl` or `lwr`, which merge the bytes of an unaligned word into the
+/// left or right of rt.
+fn load_unaligned(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, endian: &Endian, left: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+    let rt = *detail.operands[0].reg();
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (aligned, shift) = unaligned(&mut block, &detail.operands[1], endian, left)?;
+
+        let word = block.temp(32);
+        block.load(word.clone(), aligned, array("mem", MEM_SIZE));
+
+        let value = if left {
+            // the loaded bytes fill rt from its most significant byte
+            let kept = Expr::sub(Expr::shl(expr_const(1, 32), shift.clone())?, expr_const(1, 32))?;
+            Expr::or(
+                Expr::shl(word.into(), shift)?,
+                Expr::and(register_value(rt)?, kept)?
+            )?
+        }
+        else {
+            // the loaded bytes fill rt from its least significant byte
+            let kept = not(Expr::shr(expr_const(0xffff_ffff, 32), shift.clone())?)?;
+            Expr::or(
+                Expr::shr(word.into(), shift)?,
+                Expr::and(register_value(rt)?, kept)?
+            )?
+        };
+
+        write_register(&mut block, rt, value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts `swl` or `swr`, which store the left or right of rt to the bytes of
+/// an unaligned word.
+fn store_unaligned(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, endian: &Endian, left: bool)
+-> Result<()> {
+    let detail = details(instruction)?;
+    let rt = *detail.operands[0].reg();
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (aligned, shift) = unaligned(&mut block, &detail.operands[1], endian, left)?;
+
+        let address = block.temp(32);
+        block.assign(address.clone(), aligned);
+
+        let word = block.temp(32);
+        block.load(word.clone(), address.clone().into(), array("mem", MEM_SIZE));
+
+        let value = if left {
+            let kept = not(Expr::shr(expr_const(0xffff_ffff, 32), shift.clone())?)?;
+            Expr::or(
+                Expr::shr(register_value(rt)?, shift)?,
+                Expr::and(word.into(), kept)?
+            )?
+        }
+        else {
+            let kept = Expr::sub(Expr::shl(expr_const(1, 32), shift.clone())?, expr_const(1, 32))?;
+            Expr::or(
+                Expr::shl(register_value(rt)?, shift)?,
+                Expr::and(word.into(), kept)?
+            )?
+        };
+
+        block.store(array("mem", MEM_SIZE), address.into(), value);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an instruction which raises `name`.
+fn raise(control_flow_graph: &mut ControlFlowGraph, name: &str) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.raise(expr_scalar(name, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an instruction with no effect on state we model.
+pub fn nop(control_flow_graph: &mut ControlFlowGraph) -> Result<()> {
+    let block_index = control_flow_graph.new_block()?.index();
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `add` and `addi` trap on signed overflow, which is not modelled.
+pub fn add(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::add(lhs, rhs))
+}
+
+
+
+pub fn and(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::and(lhs, rhs))
+}
+
+
+
+/// `b` and `j` only emit a block. Their successors are found at translation
+/// time.
+pub fn b(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch(control_flow_graph, instruction, None, false)
+}
+
+
+
+pub fn bal(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch(control_flow_graph, instruction, None, true)
+}
+
+
+
+pub fn beq(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let condition = Expr::cmpeq(
+        operand_value(&detail.operands[0])?,
+        operand_value(&detail.operands[1])?
+    )?;
+    branch(control_flow_graph, instruction, Some(condition), false)
+}
+
+
+
+pub fn beqz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, false, |value, zero| Expr::cmpeq(value, zero))
+}
+
+
+
+pub fn bgez(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, false, |value, zero| {
+        Expr::cmpeq(Expr::cmplts(value, zero)?, expr_const(0, 1))
+    })
+}
+
+
+
+pub fn bgezal(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, true, |value, zero| {
+        Expr::cmpeq(Expr::cmplts(value, zero)?, expr_const(0, 1))
+    })
+}
+
+
+
+pub fn bgtz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, false, |value, zero| Expr::cmplts(zero, value))
+}
+
+
+
+pub fn blez(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, false, |value, zero| {
+        Expr::cmpeq(Expr::cmplts(zero, value)?, expr_const(0, 1))
+    })
+}
+
+
+
+pub fn bltz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, false, |value, zero| Expr::cmplts(value, zero))
+}
+
+
+
+pub fn bltzal(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, true, |value, zero| Expr::cmplts(value, zero))
+}
+
+
+
+pub fn bne(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let condition = Expr::cmpneq(
+        operand_value(&detail.operands[0])?,
+        operand_value(&detail.operands[1])?
+    )?;
+    branch(control_flow_graph, instruction, Some(condition), false)
+}
+
+
+
+pub fn bnez(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch_zero(control_flow_graph, instruction, false, |value, zero| Expr::cmpneq(value, zero))
+}
+
+
+
+pub fn break_(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    raise(control_flow_graph, "break")
+}
+
+
+
+pub fn clo(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |block, value| count_leading_zeros(block, not(value)?))
+}
+
+
+
+pub fn clz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |block, value| count_leading_zeros(block, value))
+}
+
+
+
+pub fn div(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    divide(control_flow_graph, instruction, true)
+}
+
+
+
+pub fn divu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    divide(control_flow_graph, instruction, false)
+}
+
+
+
+/// `ext rt, rs, pos, size`
+pub fn ext(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let position = detail.operands[2].imm() as u64;
+    let size = detail.operands[3].imm() as u64;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = Expr::shr(operand_value(&detail.operands[1])?, expr_const(position, 32))?;
+        let value = Expr::and(value, expr_const(low_mask(size), 32))?;
+
+        write_register(&mut block, *detail.operands[0].reg(), value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `ins rt, rs, pos, size`
+pub fn ins(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let rt = *detail.operands[0].reg();
+    let position = detail.operands[2].imm() as u64;
+    let size = detail.operands[3].imm() as u64;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let mask = (low_mask(size) << position) & 0xffff_ffff;
+
+        let field = Expr::shl(operand_value(&detail.operands[1])?, expr_const(position, 32))?;
+        let field = Expr::and(field, expr_const(mask, 32))?;
+
+        let kept = Expr::and(register_value(rt)?, expr_const(!mask & 0xffff_ffff, 32))?;
+
+        write_register(&mut block, rt, Expr::or(kept, field)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn jal(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    branch(control_flow_graph, instruction, None, true)
+}
+
+
+
+/// `jalr rs` links through ra, and `jalr rd, rs` through rd.
+pub fn jalr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let (link, target) = if detail.op_count == 1 {
+        (mips_reg::MIPS_REG_31, *detail.operands[0].reg())
+    }
+    else {
+        (*detail.operands[0].reg(), *detail.operands[1].reg())
+    };
+
+    jump_register(control_flow_graph, instruction, target, Some(link))
+}
+
+
+
+pub fn jr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    jump_register(control_flow_graph, instruction, *detail.operands[0].reg(), None)
+}
+
+
+
+pub fn lb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, 8, true)
+}
+
+
+
+pub fn lbu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, 8, false)
+}
+
+
+
+pub fn lh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, 16, true)
+}
+
+
+
+pub fn lhu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, 16, false)
+}
+
+
+
+pub fn li(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| Ok(value))
+}
+
+
+
+/// Capstone gives the immediate of `lui` unshifted.
+pub fn lui(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| Expr::shl(value, expr_const(16, 32)))
+}
+
+
+
+/// `ll` is lifted as `lw`, as there is only one thread of execution.
+pub fn lw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load(control_flow_graph, instruction, 32, false)
+}
+
+
+
+pub fn lwl(endian: &Endian, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_unaligned(control_flow_graph, instruction, endian, true)
+}
+
+
+
+pub fn lwr(endian: &Endian, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    load_unaligned(control_flow_graph, instruction, endian, false)
+}
+
+
+
+pub fn madd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply(control_flow_graph, instruction, true, Some(false))
+}
+
+
+
+pub fn maddu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply(control_flow_graph, instruction, false, Some(false))
+}
+
+
+
+pub fn mfhi(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        write_register(&mut block, *detail.operands[0].reg(), expr_scalar("hi", 32))?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn mflo(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        write_register(&mut block, *detail.operands[0].reg(), expr_scalar("lo", 32))?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn move_(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| Ok(value))
+}
+
+
+
+pub fn movn(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_move(control_flow_graph, instruction, |value, zero| Expr::cmpneq(value, zero))
+}
+
+
+
+pub fn movz(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    conditional_move(control_flow_graph, instruction, |value, zero| Expr::cmpeq(value, zero))
+}
+
+
+
+pub fn msub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply(control_flow_graph, instruction, true, Some(true))
+}
+
+
+
+pub fn msubu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply(control_flow_graph, instruction, false, Some(true))
+}
+
+
+
+pub fn mthi(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("hi", 32), operand_value(&detail.operands[0])?);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn mtlo(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("lo", 32), operand_value(&detail.operands[0])?);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/// `mul` writes the low 32 bits of the product to rd. hi and lo are
+/// unpredictable afterwards, and are left unchanged.
+pub fn mul(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::mul(lhs, rhs))
+}
+
+
+
+pub fn mult(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply(control_flow_graph, instruction, true, None)
+}
+
+
+
+pub fn multu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    multiply(control_flow_graph, instruction, false, None)
+}
+
+
+
+pub fn neg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| Expr::sub(expr_const(0, 32), value))
+}
+
+
+
+pub fn nor(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| not(Expr::or(lhs, rhs)?))
+}
+
+
+
+pub fn not_(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| not(value))
+}
+
+
+
+pub fn or(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::or(lhs, rhs))
+}
+
+
+
+/// Only hardware register 29, the thread pointer Linux emulates for `rdhwr`,
+/// is supported.
+pub fn rdhwr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+
+    if *detail.operands[1].reg() != mips_reg::MIPS_REG_29 {
+        bail!("unsupported hardware register {:?}", detail.operands[1].reg());
+    }
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        write_register(&mut block, *detail.operands[0].reg(), expr_scalar("userlocal", 32))?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn sb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, 8)
+}
+
+
+
+/// `sc` always succeeds, as there is only one thread of execution.
+pub fn sc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let rt = *detail.operands[0].reg();
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.store(array("mem", MEM_SIZE), memory_address(&detail.operands[1])?, register_value(rt)?);
+        write_register(&mut block, rt, expr_const(1, 32))?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn seb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| Expr::sext(32, Expr::trun(8, value)?))
+}
+
+
+
+pub fn seh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |_, value| Expr::sext(32, Expr::trun(16, value)?))
+}
+
+
+
+pub fn sh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, 16)
+}
+
+
+
+pub fn sll(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift(control_flow_graph, instruction, |value, amount| Expr::shl(value, amount))
+}
+
+
+
+pub fn slt(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    set(control_flow_graph, instruction, |lhs, rhs| Expr::cmplts(lhs, rhs))
+}
+
+
+
+/// The immediate of `sltiu` is sign extended, and then compared unsigned.
+pub fn sltu(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    set(control_flow_graph, instruction, |lhs, rhs| Expr::cmpltu(lhs, rhs))
+}
+
+
+
+pub fn sra(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift(control_flow_graph, instruction, |value, amount| asr(value, amount))
+}
+
+
+
+pub fn srl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    shift(control_flow_graph, instruction, |value, amount| Expr::shr(value, amount))
+}
+
+
+
+/// `sub` traps on signed overflow, which is not modelled.
+pub fn sub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::sub(lhs, rhs))
+}
+
+
+
+pub fn sw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store(control_flow_graph, instruction, 32)
+}
+
+
+
+pub fn swl(endian: &Endian, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_unaligned(control_flow_graph, instruction, endian, true)
+}
+
+
+
+pub fn swr(endian: &Endian, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    store_unaligned(control_flow_graph, instruction, endian, false)
+}
+
+
+
+pub fn syscall(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    raise(control_flow_graph, "syscall")
+}
+
+
+
+/// `teq rs, rt` traps if rs and rt are equal.
+pub fn teq(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = details(instruction)?;
+    let condition = Expr::cmpeq(
+        operand_value(&detail.operands[0])?,
+        operand_value(&detail.operands[1])?
+    )?;
+
+    let mut body = ControlFlowGraph::new();
+    raise(&mut body, "trap")?;
+
+    *control_flow_graph = conditional(&body, condition)?;
+
+    Ok(())
+}
+
+
+
+/// `wsbh` swaps the bytes of each halfword.
+pub fn wsbh(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    unary(control_flow_graph, instruction, |block, value| {
+        let temp = block.temp(32);
+        block.assign(temp.clone(), value);
+
+        let high = Expr::and(
+            Expr::shl(temp.clone().into(), expr_const(8, 32))?,
+            expr_const(0xff00_ff00, 32)
+        )?;
+        let low = Expr::and(
+            Expr::shr(temp.into(), expr_const(8, 32))?,
+            expr_const(0x00ff_00ff, 32)
+        )?;
+
+        Expr::or(high, low)
+    })
+}
+
+
+
+pub fn xor(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    arithmetic(control_flow_graph, instruction, |lhs, rhs| Expr::xor(lhs, rhs))
+}
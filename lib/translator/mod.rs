@@ -3,8 +3,13 @@
 use error::*;
 use il::*;
 use std::boxed::Box;
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
+pub mod aarch64;
+pub mod arm;
+pub mod hsvm;
+mod jump_table;
+pub mod mips;
 pub mod x86;
 
 /// The endianness of the native architecture.
@@ -111,36 +116,79 @@ pub trait Arch {
     /// Get the endianness of this `Arch`.
     fn endian(&self) -> Endian;
 
+    /// Get the size of an address on this `Arch` in bits.
+    fn address_bits(&self) -> usize;
+
+    /// Get the address the instructions of the block at `address` begin at.
+    ///
+    /// Architectures which encode an instruction set in the low bits of block
+    /// addresses, such as Thumb on ARM, clear those bits here.
+    fn instruction_address(&self, address: u64) -> u64 {
+        address
+    }
+
     /// Translates a function
     fn translate_function(
         &self,
         memory: &TranslationMemory,
         function_address: u64)
     -> Result<Function> {
+        self.translate_function_with_entries(memory, function_address, &BTreeSet::new())
+    }
+
+    /// Translates a function, given the entries of other known functions.
+    ///
+    /// Jumps to any of `function_entries` are tail calls, and are translated
+    /// as a `brc` to that function instead of becoming part of this function.
+    fn translate_function_with_entries(
+        &self,
+        memory: &TranslationMemory,
+        function_address: u64,
+        function_entries: &BTreeSet<u64>)
+    -> Result<Function> {
+        let is_tail_call = |address: u64| {
+            address != function_address && function_entries.contains(&address)
+        };
+
         let mut translation_queue = VecDeque::new();
         let mut translation_results = BTreeMap::new();
 
         translation_queue.push_front(function_address);
 
-        // translate all blocks in the function
-        while !translation_queue.is_empty() {
-            let block_address = translation_queue.pop_front().unwrap();
+        loop {
+            // translate all blocks in the function
+            while !translation_queue.is_empty() {
+                let block_address = translation_queue.pop_front().unwrap();
 
-            if translation_results.contains_key(&block_address) {
-                continue;
-            }
+                if translation_results.contains_key(&block_address) {
+                    continue;
+                }
 
-            let block_bytes = memory.get_bytes(block_address, DEFAULT_TRANSLATION_BLOCK_BYTES);
+                let block_bytes = memory.get_bytes(self.instruction_address(block_address), DEFAULT_TRANSLATION_BLOCK_BYTES);
 
-            // translate this block
-            let block_translation_result = self.translate_block(&block_bytes, block_address)?;
+                // translate this block
+                let block_translation_result = self.translate_block(&block_bytes, block_address)?;
 
-            // enqueue all successors
-            for successor in block_translation_result.successors().iter() {
-                translation_queue.push_back(successor.0);
+                // enqueue all successors
+                for successor in block_translation_result.successors().iter() {
+                    if !is_tail_call(successor.0) {
+                        translation_queue.push_back(successor.0);
+                    }
+                }
+
+                translation_results.insert(block_address, block_translation_result);
             }
 
-            translation_results.insert(block_address, block_translation_result);
+            // resolve jump tables, and translate the blocks they jump to
+            let targets = jump_table::resolve_jump_tables(
+                &mut translation_results,
+                memory,
+                &self.endian()
+            )?;
+            if targets.is_empty() {
+                break;
+            }
+            translation_queue.extend(targets);
         }
 
         // We now insert all of these blocks into a new control flow graph,
@@ -156,6 +204,16 @@ pub trait Arch {
         for result in translation_results {
             let (_, this_exit) = indices[&result.0];
             for successor in result.1.successors().iter() {
+                if is_tail_call(successor.0) {
+                    let condition = match successor.1 {
+                        Some(ref condition) => condition.clone(),
+                        None => expr_const(1, 1)
+                    };
+                    control_flow_graph.block_mut(this_exit)
+                                      .ok_or("Could not find block")?
+                                      .brc(expr_const(successor.0, self.address_bits()), condition);
+                    continue;
+                }
                 let (that_entry, _) = indices[&successor.0];
                 match successor.1 {
                     Some(ref condition) => control_flow_graph.conditional_edge(this_exit, that_entry, condition.clone())?,
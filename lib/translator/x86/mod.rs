@@ -1,13 +1,22 @@
-//! Capstone-based translator for 32-bit x86.
+//! Capstone-based translators for 32-bit x86 and AMD64.
+//!
+//! Both translators share the same semantics, parameterized by a `Mode` which
+//! selects the width of addresses and the stack, string and count registers.
+//! RIP-relative operands are lifted as reads of the scalar `rip`, which is
+//! replaced with the address of the following instruction once the
+//! instruction has been lifted.
 
 use capstone_rust::{capstone, capstone_sys};
 use error::*;
 use il::*;
 use translator::{Arch, BlockTranslationResult, Endian};
+use translator::jump_table::substitute;
 
 
 mod semantics;
 
+use self::semantics::Mode;
+
 /// The X86 translator.
 pub struct X86;
 
@@ -19,6 +28,17 @@ impl X86 {
 }
 
 
+/// The AMD64 translator.
+pub struct Amd64;
+
+
+impl Amd64 {
+    pub fn new() -> Amd64 {
+        Amd64
+    }
+}
+
+
 fn raise_fp(control_flow_graph: &mut ControlFlowGraph) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
@@ -35,291 +55,507 @@ fn raise_fp(control_flow_graph: &mut ControlFlowGraph) -> Result<()> {
 }
 
 
+/// Replace reads of `rip` in `control_flow_graph` with `next_address`, the
+/// address of the instruction following the one lifted.
+fn resolve_rip(control_flow_graph: &mut ControlFlowGraph, next_address: u64) {
+    let rip = scalar("rip", 64);
+    let replacement = expr_const(next_address, 64);
+
+    for block in control_flow_graph.blocks_mut() {
+        for instruction in block.instructions_mut() {
+            match *instruction.operation_mut() {
+                Operation::Assign { ref mut src, .. } => substitute(src, &rip, &replacement),
+                Operation::Store { ref mut index, ref mut src, .. } => {
+                    substitute(index, &rip, &replacement);
+                    substitute(src, &rip, &replacement);
+                },
+                Operation::Load { ref mut index, .. } => substitute(index, &rip, &replacement),
+                Operation::Brc { ref mut target, ref mut condition } => {
+                    substitute(target, &rip, &replacement);
+                    substitute(condition, &rip, &replacement);
+                },
+                Operation::Raise { ref mut expr } => substitute(expr, &rip, &replacement),
+                Operation::Phi { .. } => ()
+            }
+        }
+    }
+
+    for edge in control_flow_graph.edges_mut() {
+        if let Some(ref mut condition) = *edge.condition_mut() {
+            substitute(condition, &rip, &replacement);
+        }
+    }
+}
+
+
 impl Arch for X86 {
     fn endian(&self) -> Endian {
         Endian::Little
     }
 
+    fn address_bits(&self) -> usize {
+        32
+    }
+
     fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
-        let cs = match capstone::Capstone::new(capstone::cs_arch::CS_ARCH_X86, capstone::cs_mode::CS_MODE_32) {
-            Ok(cs) => cs,
-            Err(_) => return Err("Capstone Error".into())
-        };
+        translate_block(Mode::X86, bytes, address)
+    }
+}
+
+
+impl Arch for Amd64 {
+    fn endian(&self) -> Endian {
+        Endian::Little
+    }
 
-        cs.option(capstone::cs_opt_type::CS_OPT_DETAIL, capstone::cs_opt_value::CS_OPT_ON).unwrap();
+    fn address_bits(&self) -> usize {
+        64
+    }
 
-        // our graph for the block which we will build iteratively with each instruction
-        let mut block_graph = ControlFlowGraph::new();
+    fn translate_block(&self, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+        translate_block(Mode::Amd64, bytes, address)
+    }
+}
 
-        // the length of this block in bytes
-        let mut length: usize = 0;
 
-        let mut successors = Vec::new();
+fn translate_block(mode: Mode, bytes: &[u8], address: u64) -> Result<BlockTranslationResult> {
+    let cs_mode = match mode {
+        Mode::X86 => capstone::cs_mode::CS_MODE_32,
+        Mode::Amd64 => capstone::cs_mode::CS_MODE_64
+    };
 
-        let mut offset: usize = 0;
+    let cs = match capstone::Capstone::new(capstone::cs_arch::CS_ARCH_X86, cs_mode) {
+        Ok(cs) => cs,
+        Err(_) => return Err("Capstone Error".into())
+    };
 
-        loop {
-            /* We must have at least 16 bytes left in the buffer. */
-            // if bytes.len() - offset < 16 {
-            //     successors.push((address + offset as u64, None));
-            //     break;
-            // }
-            let disassembly_range = (offset)..bytes.len();
-            let disassembly_bytes = bytes.get(disassembly_range).unwrap();
-            let instructions = match cs.disasm(disassembly_bytes, address + offset as u64, 1) {
-                Ok(instructions) => instructions,
-                Err(e) => match e.code() {
-                    capstone_sys::cs_err::CS_ERR_OK => {
-                        successors.push((address + offset as u64, None));
-                        break;
-                    }
-                    _ => bail!("Capstone Error: {}", e.code() as u32)
+    cs.option(capstone::cs_opt_type::CS_OPT_DETAIL, capstone::cs_opt_value::CS_OPT_ON).unwrap();
+
+    // our graph for the block which we will build iteratively with each instruction
+    let mut block_graph = ControlFlowGraph::new();
+
+    // the length of this block in bytes
+    let mut length: usize = 0;
+
+    let mut successors = Vec::new();
+
+    let mut offset: usize = 0;
+
+    loop {
+        /* We must have at least 16 bytes left in the buffer. */
+        // if bytes.len() - offset < 16 {
+        //     successors.push((address + offset as u64, None));
+        //     break;
+        // }
+        let disassembly_range = (offset)..bytes.len();
+        let disassembly_bytes = bytes.get(disassembly_range).unwrap();
+        let instructions = match cs.disasm(disassembly_bytes, address + offset as u64, 1) {
+            Ok(instructions) => instructions,
+            Err(e) => match e.code() {
+                capstone_sys::cs_err::CS_ERR_OK => {
+                    successors.push((address + offset as u64, None));
+                    break;
                 }
-            };
+                _ => bail!("Capstone Error: {}", e.code() as u32)
+            }
+        };
+
+        if instructions.count() == 0 {
+            return Err("Capstone failed to disassemble any instruction".into());
+        }
 
-            if instructions.count() == 0 {
-                return Err("Capstone failed to disassemble any instruction".into());
+        let instruction = instructions.get(0).unwrap();
+
+        if let capstone::InstrIdArch::X86(instruction_id) = instruction.id {
+            
+            let mut instruction_graph = ControlFlowGraph::new();
+
+            try!(match instruction_id {
+                capstone::x86_insn::X86_INS_AAA => semantics::aaa(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_AAD => semantics::aad(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_AAM => semantics::aam(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_AAS => semantics::aas(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ADC  => semantics::adc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ADD  => semantics::add(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_AND  => semantics::and(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BSF  => semantics::bsf(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BSR  => semantics::bsr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BSWAP => semantics::bswap(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BT   => semantics::bt(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BTC  => semantics::btc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BTR  => semantics::btr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_BTS  => semantics::bts(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CALL => semantics::call(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CBW  => semantics::cbw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CDQ  => semantics::cdq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CDQE => semantics::cdqe(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CLC  => semantics::clc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CLD  => semantics::cld(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CLI  => semantics::cli(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CMC  => semantics::cmc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CMOVA  |
+                capstone::x86_insn::X86_INS_CMOVAE |
+                capstone::x86_insn::X86_INS_CMOVB  |
+                capstone::x86_insn::X86_INS_CMOVBE |
+                capstone::x86_insn::X86_INS_CMOVE  |
+                capstone::x86_insn::X86_INS_CMOVG  |
+                capstone::x86_insn::X86_INS_CMOVGE |
+                capstone::x86_insn::X86_INS_CMOVL  |
+                capstone::x86_insn::X86_INS_CMOVLE |
+                capstone::x86_insn::X86_INS_CMOVNE |
+                capstone::x86_insn::X86_INS_CMOVNO |
+                capstone::x86_insn::X86_INS_CMOVNP |
+                capstone::x86_insn::X86_INS_CMOVNS |
+                capstone::x86_insn::X86_INS_CMOVO  |
+                capstone::x86_insn::X86_INS_CMOVP  |
+                capstone::x86_insn::X86_INS_CMOVS  => semantics::cmovcc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CMP    => semantics::cmp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CMPSB  |
+                capstone::x86_insn::X86_INS_CMPSD  |
+                capstone::x86_insn::X86_INS_CMPSQ  |
+                capstone::x86_insn::X86_INS_CMPSW  => semantics::cmps(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CMPXCHG => semantics::cmpxchg(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CMPXCHG16B |
+                capstone::x86_insn::X86_INS_CMPXCHG8B => semantics::cmpxchg8b(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CPUID => semantics::cpuid(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CQO    => semantics::cqo(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CWD    => semantics::cwd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_CWDE   => semantics::cwde(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_DAA => semantics::daa(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_DAS => semantics::das(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_DEC    => semantics::dec(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_DIV    => semantics::div(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ENTER => semantics::enter(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FABS   => semantics::fabs(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FADD   => semantics::fadd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCHS   => semantics::fchs(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCMOVB   |
+                capstone::x86_insn::X86_INS_FCMOVBE  |
+                capstone::x86_insn::X86_INS_FCMOVE   |
+                capstone::x86_insn::X86_INS_FCMOVNB  |
+                capstone::x86_insn::X86_INS_FCMOVNBE |
+                capstone::x86_insn::X86_INS_FCMOVNE  |
+                capstone::x86_insn::X86_INS_FCMOVNU  |
+                capstone::x86_insn::X86_INS_FCMOVU   => semantics::fcmovcc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCOM   => semantics::fcom(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCOMI  => semantics::fcomi(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCOMP  => semantics::fcomp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCOMPI => semantics::fcompi(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FCOMPP => semantics::fcompp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FDIV   => semantics::fdiv(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FDIVP  => semantics::fdivp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FDIVR  => semantics::fdivr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FDIVRP => semantics::fdivrp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FIADD  => semantics::fiadd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FICOM  => semantics::ficom(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FICOMP => semantics::ficomp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FIDIV  => semantics::fidiv(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FIDIVR => semantics::fidivr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FILD   => semantics::fild(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FIMUL  => semantics::fimul(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FIST   => semantics::fist(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FISTP  => semantics::fistp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FISTTP => semantics::fisttp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FISUB  => semantics::fisub(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FISUBR => semantics::fisubr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLD    => semantics::fld(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLD1   => semantics::fld1(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDCW  => semantics::fldcw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDENV => raise_fp(&mut instruction_graph),
+                capstone::x86_insn::X86_INS_FLDL2E => semantics::fldl2e(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDL2T => semantics::fldl2t(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDLG2 => semantics::fldlg2(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDLN2 => semantics::fldln2(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDPI  => semantics::fldpi(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FLDZ   => semantics::fldz(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FMUL   => semantics::fmul(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FMULP  => semantics::fmulp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FNCLEX => semantics::nop(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FNINIT => semantics::fninit(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FNOP   => semantics::nop(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FNSTCW => semantics::fnstcw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FNSTENV => raise_fp(&mut instruction_graph),
+                capstone::x86_insn::X86_INS_FNSTSW => semantics::fnstsw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FSCALE => raise_fp(&mut instruction_graph),
+                capstone::x86_insn::X86_INS_FST    => semantics::fst(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FSTP   => semantics::fstp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FSUB   => semantics::fsub(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FSUBP  => semantics::fsubp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FSUBR  => semantics::fsubr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FSUBRP => semantics::fsubrp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FTST   => semantics::ftst(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FUCOM  => semantics::fucom(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FUCOMI => semantics::fucomi(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FUCOMP => semantics::fucomp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FUCOMPI => semantics::fucompi(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FUCOMPP => semantics::fucompp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FXAM   => semantics::fxam(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_FXCH   => semantics::fxch(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_HLT    => semantics::nop(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_IDIV   => semantics::idiv(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_IMUL   => semantics::imul(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_INC    => semantics::inc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_INT    => semantics::int(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_INT3 => semantics::int3(mode, &mut instruction_graph, &instruction),
+                // conditional jumps will only emit a brc if the destination is undetermined at
+                // translation time
+                capstone::x86_insn::X86_INS_JA   |
+                capstone::x86_insn::X86_INS_JAE  |
+                capstone::x86_insn::X86_INS_JB   |
+                capstone::x86_insn::X86_INS_JBE  |
+                capstone::x86_insn::X86_INS_JCXZ |
+                capstone::x86_insn::X86_INS_JECXZ |
+                capstone::x86_insn::X86_INS_JRCXZ |
+                capstone::x86_insn::X86_INS_JE   |
+                capstone::x86_insn::X86_INS_JG   |
+                capstone::x86_insn::X86_INS_JGE  |
+                capstone::x86_insn::X86_INS_JL   |
+                capstone::x86_insn::X86_INS_JLE  |
+                capstone::x86_insn::X86_INS_JNE  |
+                capstone::x86_insn::X86_INS_JNO  |
+                capstone::x86_insn::X86_INS_JNP  |
+                capstone::x86_insn::X86_INS_JNS  |
+                capstone::x86_insn::X86_INS_JO   |
+                capstone::x86_insn::X86_INS_JP   |
+                capstone::x86_insn::X86_INS_JS   => semantics::jcc(mode, &mut instruction_graph, &instruction),
+                // unconditional jumps will only emit a brc if the destination is undetermined at
+                // translation time
+                capstone::x86_insn::X86_INS_JMP   => semantics::jmp(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LAHF => semantics::lahf(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LEA   => semantics::lea(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LEAVE => semantics::leave(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LODSB |
+                capstone::x86_insn::X86_INS_LODSD |
+                capstone::x86_insn::X86_INS_LODSQ |
+                capstone::x86_insn::X86_INS_LODSW => semantics::lods(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LOOP  => semantics::loop_(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LOOPE => semantics::loop_(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LOOPNE => semantics::loop_(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_LZCNT => semantics::lzcnt(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOV   => semantics::mov(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVAPD  |
+                capstone::x86_insn::X86_INS_MOVAPS  |
+                capstone::x86_insn::X86_INS_MOVDQA  |
+                capstone::x86_insn::X86_INS_MOVDQU  |
+                capstone::x86_insn::X86_INS_MOVNTDQ |
+                capstone::x86_insn::X86_INS_MOVUPD  |
+                capstone::x86_insn::X86_INS_MOVUPS  => semantics::mov(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVBE => semantics::movbe(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVD  => semantics::movd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVQ  => semantics::movq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVSB |
+                capstone::x86_insn::X86_INS_MOVSW |
+                capstone::x86_insn::X86_INS_MOVSQ => semantics::movs(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVSD => semantics::movsd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVSS => semantics::movss(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVSX |
+                capstone::x86_insn::X86_INS_MOVSXD => semantics::movsx(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MOVZX => semantics::movzx(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_MUL  => semantics::mul(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_NEG  => semantics::neg(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_NOP  => semantics::nop(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_NOT  => semantics::not(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_OR   => semantics::or(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PADDB    => semantics::paddb(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PADDD    => semantics::paddd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PADDQ    => semantics::paddq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PADDW    => semantics::paddw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ANDPD |
+                capstone::x86_insn::X86_INS_ANDPS |
+                capstone::x86_insn::X86_INS_PAND  => semantics::pand(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ANDNPD |
+                capstone::x86_insn::X86_INS_ANDNPS |
+                capstone::x86_insn::X86_INS_PANDN  => semantics::pandn(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PCMPEQB  => semantics::pcmpeqb(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PCMPEQD  => semantics::pcmpeqd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PCMPEQW  => semantics::pcmpeqw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PCMPGTB  => semantics::pcmpgtb(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PCMPGTD  => semantics::pcmpgtd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PCMPGTW  => semantics::pcmpgtw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PMAXSW   => semantics::pmaxsw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PMAXUB   => semantics::pmaxub(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PMINSW   => semantics::pminsw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PMINUB   => semantics::pminub(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PMOVMSKB => semantics::pmovmskb(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_POP  => semantics::pop(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_POPAL |
+                capstone::x86_insn::X86_INS_POPAW => semantics::popad(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_POPCNT => semantics::popcnt(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_POPF |
+                capstone::x86_insn::X86_INS_POPFD |
+                capstone::x86_insn::X86_INS_POPFQ => semantics::popfd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ORPD |
+                capstone::x86_insn::X86_INS_ORPS |
+                capstone::x86_insn::X86_INS_POR  => semantics::por(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSHUFD     => semantics::pshufd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSLLD      => semantics::pslld(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSLLDQ     => semantics::pslldq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSLLQ      => semantics::psllq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSLLW      => semantics::psllw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSRAD      => semantics::psrad(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSRAW      => semantics::psraw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSRLD      => semantics::psrld(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSRLDQ     => semantics::psrldq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSRLQ      => semantics::psrlq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSRLW      => semantics::psrlw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSUBB      => semantics::psubb(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSUBD      => semantics::psubd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSUBQ      => semantics::psubq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PSUBW      => semantics::psubw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKHBW  => semantics::punpckhbw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKHDQ  => semantics::punpckhdq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKHQDQ => semantics::punpckhqdq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKHWD  => semantics::punpckhwd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKLBW  => semantics::punpcklbw(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKLDQ  => semantics::punpckldq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKLQDQ => semantics::punpcklqdq(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUNPCKLWD  => semantics::punpcklwd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUSH => semantics::push(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUSHAL |
+                capstone::x86_insn::X86_INS_PUSHAW => semantics::pushad(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PUSHF |
+                capstone::x86_insn::X86_INS_PUSHFD |
+                capstone::x86_insn::X86_INS_PUSHFQ => semantics::pushfd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_PXOR  |
+                capstone::x86_insn::X86_INS_XORPD |
+                capstone::x86_insn::X86_INS_XORPS => semantics::pxor(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_RCL => semantics::rcl(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_RCR => semantics::rcr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_RDTSC => semantics::rdtsc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_RET  => semantics::ret(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ROL  => semantics::rol(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_ROR  => semantics::ror(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SAHF => semantics::sahf(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SAR  => semantics::sar(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SBB  => semantics::sbb(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SCASB |
+                capstone::x86_insn::X86_INS_SCASD |
+                capstone::x86_insn::X86_INS_SCASQ |
+                capstone::x86_insn::X86_INS_SCASW => semantics::scas(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SETAE |
+                capstone::x86_insn::X86_INS_SETA  |
+                capstone::x86_insn::X86_INS_SETBE |
+                capstone::x86_insn::X86_INS_SETB  |
+                capstone::x86_insn::X86_INS_SETE  |
+                capstone::x86_insn::X86_INS_SETGE |
+                capstone::x86_insn::X86_INS_SETG  |
+                capstone::x86_insn::X86_INS_SETLE |
+                capstone::x86_insn::X86_INS_SETL  |
+                capstone::x86_insn::X86_INS_SETNE |
+                capstone::x86_insn::X86_INS_SETNO |
+                capstone::x86_insn::X86_INS_SETNP |
+                capstone::x86_insn::X86_INS_SETNS |
+                capstone::x86_insn::X86_INS_SETO |
+                capstone::x86_insn::X86_INS_SETP |
+                capstone::x86_insn::X86_INS_SETS => semantics::setcc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SHL  => semantics::shl(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SHR  => semantics::shr(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SHLD => semantics::shld(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SHRD => semantics::shrd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STC  => semantics::stc(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STD  => semantics::std(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STI  => semantics::sti(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STOSB => semantics::stos(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STOSW => semantics::stos(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STOSD => semantics::stos(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_STOSQ => semantics::stos(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SUB  => semantics::sub(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SYSCALL => semantics::syscall(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_SYSENTER => semantics::sysenter(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_TEST => semantics::test(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_TZCNT => semantics::tzcnt(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_UD2 => semantics::ud2(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_WAIT => semantics::nop(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_XADD => semantics::xadd(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_XCHG => semantics::xchg(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_XLATB => semantics::xlat(mode, &mut instruction_graph, &instruction),
+                capstone::x86_insn::X86_INS_XOR  => semantics::xor(mode, &mut instruction_graph, &instruction),
+
+                _ => return Err(format!("Unhandled instruction {} at 0x{:x}",
+                    instruction.mnemonic,
+                    instruction.address
+                ).into())
+            });
+
+            let detail = semantics::details(&instruction)?;
+            let repeated = detail.prefix.contains(&(capstone_sys::x86_prefix::X86_PREFIX_REP as u8))
+                || detail.prefix.contains(&(capstone_sys::x86_prefix::X86_PREFIX_REPNE as u8));
+            if repeated && !semantics::is_sse(&instruction)? {
+                semantics::rep_prefix(mode, &mut instruction_graph, &instruction)?;
             }
 
-            let instruction = instructions.get(0).unwrap();
-
-            if let capstone::InstrIdArch::X86(instruction_id) = instruction.id {
-                
-                let mut instruction_graph = ControlFlowGraph::new();
-
-                try!(match instruction_id {
-                    capstone::x86_insn::X86_INS_ADC  => semantics::adc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_ADD  => semantics::add(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_AND  => semantics::and(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BSF  => semantics::bsf(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BSR  => semantics::bsr(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BSWAP => semantics::bswap(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BT   => semantics::bt(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BTC  => semantics::btc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BTR  => semantics::bts(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_BTS  => semantics::btr(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CALL => semantics::call(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CBW  => semantics::cbw(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CDQ  => semantics::cdq(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CLC  => semantics::clc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CLD  => semantics::cld(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CLI  => semantics::cli(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CMC  => semantics::cmc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CMOVA  |
-                    capstone::x86_insn::X86_INS_CMOVAE |
-                    capstone::x86_insn::X86_INS_CMOVB  |
-                    capstone::x86_insn::X86_INS_CMOVBE |
-                    capstone::x86_insn::X86_INS_CMOVE  |
-                    capstone::x86_insn::X86_INS_CMOVG  |
-                    capstone::x86_insn::X86_INS_CMOVGE |
-                    capstone::x86_insn::X86_INS_CMOVL  |
-                    capstone::x86_insn::X86_INS_CMOVLE |
-                    capstone::x86_insn::X86_INS_CMOVNE |
-                    capstone::x86_insn::X86_INS_CMOVNO |
-                    capstone::x86_insn::X86_INS_CMOVNP |
-                    capstone::x86_insn::X86_INS_CMOVNS |
-                    capstone::x86_insn::X86_INS_CMOVO  |
-                    capstone::x86_insn::X86_INS_CMOVP  |
-                    capstone::x86_insn::X86_INS_CMOVS  => semantics::cmovcc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CMP    => semantics::cmp(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CMPSB  => semantics::cmpsb(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CMPXCHG => semantics::cmpxchg(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CWD    => semantics::cwd(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_CWDE   => semantics::cwde(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_DEC    => semantics::dec(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_DIV    => semantics::div(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_FABS   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FCHS   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FDIV   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FILD   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FISTP  => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FLDCW  => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FLD    => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FLD1   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FLDENV => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FLDZ   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FDIVP  => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FMUL   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FMULP  => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FNSTENV => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FNSTCW => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FNSTSW => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FSCALE => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FST    => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FSTP   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FSUBR  => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FSUB   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FSUBP  => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FUCOMI => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FUCOMPI => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FXAM   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_FXCH   => raise_fp(&mut instruction_graph),
-                    capstone::x86_insn::X86_INS_HLT    => semantics::nop(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_IDIV   => semantics::idiv(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_IMUL   => semantics::imul(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_INC    => semantics::inc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_INT    => semantics::int(&mut instruction_graph, &instruction),
-                    // conditional jumps will only emit a brc if the destination is undetermined at
-                    // translation time
-                    capstone::x86_insn::X86_INS_JA   |
-                    capstone::x86_insn::X86_INS_JAE  |
-                    capstone::x86_insn::X86_INS_JB   |
-                    capstone::x86_insn::X86_INS_JBE  |
-                    capstone::x86_insn::X86_INS_JCXZ |
-                    capstone::x86_insn::X86_INS_JECXZ |
-                    capstone::x86_insn::X86_INS_JE   |
-                    capstone::x86_insn::X86_INS_JG   |
-                    capstone::x86_insn::X86_INS_JGE  |
-                    capstone::x86_insn::X86_INS_JL   |
-                    capstone::x86_insn::X86_INS_JLE  |
-                    capstone::x86_insn::X86_INS_JNE  |
-                    capstone::x86_insn::X86_INS_JNO  |
-                    capstone::x86_insn::X86_INS_JNP  |
-                    capstone::x86_insn::X86_INS_JNS  |
-                    capstone::x86_insn::X86_INS_JO   |
-                    capstone::x86_insn::X86_INS_JP   |
-                    capstone::x86_insn::X86_INS_JS   => semantics::jcc(&mut instruction_graph, &instruction),
-                    // unconditional jumps will only emit a brc if the destination is undetermined at
-                    // translation time
-                    capstone::x86_insn::X86_INS_JMP   => semantics::jmp(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_LEA   => semantics::lea(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_LEAVE => semantics::leave(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_LODSB => semantics::lodsb(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_LOOP  => semantics::loop_(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_LOOPE => semantics::loop_(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_LOOPNE => semantics::loop_(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_MOV   => semantics::mov(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_MOVSB |
-                    capstone::x86_insn::X86_INS_MOVSW |
-                    capstone::x86_insn::X86_INS_MOVSD => semantics::movs(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_MOVSX => semantics::movsx(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_MOVZX => semantics::movzx(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_MUL  => semantics::mul(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_NEG  => semantics::neg(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_NOP  => semantics::nop(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_NOT  => semantics::not(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_OR   => semantics::or(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_POP  => semantics::pop(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_PUSH => semantics::push(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_RDTSC => semantics::nop(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_RET  => semantics::ret(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_ROL  => semantics::rol(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_ROR  => semantics::ror(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SAR  => semantics::sar(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SBB  => semantics::sbb(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SCASB => semantics::scasb(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SETAE |
-                    capstone::x86_insn::X86_INS_SETA  |
-                    capstone::x86_insn::X86_INS_SETBE |
-                    capstone::x86_insn::X86_INS_SETB  |
-                    capstone::x86_insn::X86_INS_SETE  |
-                    capstone::x86_insn::X86_INS_SETGE |
-                    capstone::x86_insn::X86_INS_SETG  |
-                    capstone::x86_insn::X86_INS_SETLE |
-                    capstone::x86_insn::X86_INS_SETL  |
-                    capstone::x86_insn::X86_INS_SETNE |
-                    capstone::x86_insn::X86_INS_SETNO |
-                    capstone::x86_insn::X86_INS_SETNP |
-                    capstone::x86_insn::X86_INS_SETNS |
-                    capstone::x86_insn::X86_INS_SETO |
-                    capstone::x86_insn::X86_INS_SETP |
-                    capstone::x86_insn::X86_INS_SETS => semantics::setcc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SHL  => semantics::shl(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SHR  => semantics::shr(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SHLD => semantics::shld(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SHRD => semantics::shrd(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_STC  => semantics::stc(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_STD  => semantics::std(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_STI  => semantics::sti(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_STOSB => semantics::stos(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_STOSW => semantics::stos(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_STOSD => semantics::stos(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SUB  => semantics::sub(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_SYSENTER => semantics::sysenter(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_TEST => semantics::test(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_WAIT => semantics::nop(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_XADD => semantics::xadd(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_XCHG => semantics::xchg(&mut instruction_graph, &instruction),
-                    capstone::x86_insn::X86_INS_XOR  => semantics::xor(&mut instruction_graph, &instruction),
-
-                    _ => return Err(format!("Unhandled instruction {} at 0x{:x}",
-                        instruction.mnemonic,
-                        instruction.address
-                    ).into())
-                });
-
-                let detail = semantics::details(&instruction)?;
-                if detail.prefix.contains(&(capstone_sys::x86_prefix::X86_PREFIX_REP as u8)) {
-                    semantics::rep_prefix(&mut instruction_graph, &instruction)?;
-                }
+            if mode == Mode::Amd64 {
+                resolve_rip(&mut instruction_graph, instruction.address + instruction.size as u64);
+            }
 
-                instruction_graph.set_address(Some(instruction.address));
-
-                block_graph.append(&instruction_graph)?;
-
-                length += instruction.size as usize;
-
-                // instructions that terminate blocks
-                match instruction_id {
-                    // conditional branching instructions
-                    capstone::x86_insn::X86_INS_JA |
-                    capstone::x86_insn::X86_INS_JAE |
-                    capstone::x86_insn::X86_INS_JB |
-                    capstone::x86_insn::X86_INS_JBE |
-                    capstone::x86_insn::X86_INS_JCXZ |
-                    capstone::x86_insn::X86_INS_JECXZ |
-                    capstone::x86_insn::X86_INS_JE |
-                    capstone::x86_insn::X86_INS_JG |
-                    capstone::x86_insn::X86_INS_JGE |
-                    capstone::x86_insn::X86_INS_JL |
-                    capstone::x86_insn::X86_INS_JLE |
-                    capstone::x86_insn::X86_INS_JNO |
-                    capstone::x86_insn::X86_INS_JNE |
-                    capstone::x86_insn::X86_INS_JNP |
-                    capstone::x86_insn::X86_INS_JNS |
-                    capstone::x86_insn::X86_INS_JO |
-                    capstone::x86_insn::X86_INS_JP |
-                    capstone::x86_insn::X86_INS_JS => {
-                        let condition = semantics::cc_condition(&instruction)?;
-                        successors.push((address + length as u64, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
-                        let operand = semantics::details(&instruction)?.operands[0];
-                        if operand.type_ == capstone_sys::x86_op_type::X86_OP_IMM {
-                            successors.push((operand.imm() as u64, Some(condition)));
-                        }
-                        break;
+            instruction_graph.set_address(Some(instruction.address));
+
+            block_graph.append(&instruction_graph)?;
+
+            length += instruction.size as usize;
+
+            // instructions that terminate blocks
+            match instruction_id {
+                // conditional branching instructions
+                capstone::x86_insn::X86_INS_JA |
+                capstone::x86_insn::X86_INS_JAE |
+                capstone::x86_insn::X86_INS_JB |
+                capstone::x86_insn::X86_INS_JBE |
+                capstone::x86_insn::X86_INS_JCXZ |
+                capstone::x86_insn::X86_INS_JECXZ |
+                capstone::x86_insn::X86_INS_JRCXZ |
+                capstone::x86_insn::X86_INS_JE |
+                capstone::x86_insn::X86_INS_JG |
+                capstone::x86_insn::X86_INS_JGE |
+                capstone::x86_insn::X86_INS_JL |
+                capstone::x86_insn::X86_INS_JLE |
+                capstone::x86_insn::X86_INS_JNO |
+                capstone::x86_insn::X86_INS_JNE |
+                capstone::x86_insn::X86_INS_JNP |
+                capstone::x86_insn::X86_INS_JNS |
+                capstone::x86_insn::X86_INS_JO |
+                capstone::x86_insn::X86_INS_JP |
+                capstone::x86_insn::X86_INS_JS => {
+                    let condition = semantics::cc_condition(mode, &instruction)?;
+                    successors.push((address + length as u64, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                    let operand = semantics::details(&instruction)?.operands[0];
+                    if operand.type_ == capstone_sys::x86_op_type::X86_OP_IMM {
+                        successors.push((operand.imm() as u64, Some(condition)));
                     }
-                    capstone::x86_insn::X86_INS_LOOP |
-                    capstone::x86_insn::X86_INS_LOOPE |
-                    capstone::x86_insn::X86_INS_LOOPNE => {
-                        let condition = semantics::loop_condition(&instruction)?;
-                        successors.push((address + length as u64, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
-                        let operand = semantics::details(&instruction)?.operands[0];
-                        if operand.type_ == capstone_sys::x86_op_type::X86_OP_IMM {
-                            successors.push((operand.imm() as u64, Some(condition)));
-                        }
-                        break;
+                    break;
+                }
+                capstone::x86_insn::X86_INS_LOOP |
+                capstone::x86_insn::X86_INS_LOOPE |
+                capstone::x86_insn::X86_INS_LOOPNE => {
+                    let condition = semantics::loop_condition(mode, &instruction)?;
+                    successors.push((address + length as u64, Some(Expression::cmpeq(condition.clone(), expr_const(0, 1))?)));
+                    let operand = semantics::details(&instruction)?.operands[0];
+                    if operand.type_ == capstone_sys::x86_op_type::X86_OP_IMM {
+                        successors.push((operand.imm() as u64, Some(condition)));
                     }
-                    // non-conditional branching instructions
-                    capstone::x86_insn::X86_INS_JMP => {
-                        let operand = semantics::details(&instruction)?.operands[0];
-                        if operand.type_ == capstone_sys::x86_op_type::X86_OP_IMM {
-                            successors.push((operand.imm() as u64, None));
-                        }
-                        break;
+                    break;
+                }
+                // non-conditional branching instructions
+                capstone::x86_insn::X86_INS_JMP => {
+                    let operand = semantics::details(&instruction)?.operands[0];
+                    if operand.type_ == capstone_sys::x86_op_type::X86_OP_IMM {
+                        successors.push((operand.imm() as u64, None));
                     }
-                    // instructions without successors
-                    capstone::x86_insn::X86_INS_HLT => break,
-                    capstone::x86_insn::X86_INS_RET => break,
-                    _ => ()
+                    break;
                 }
+                // instructions without successors
+                capstone::x86_insn::X86_INS_HLT => break,
+                capstone::x86_insn::X86_INS_RET => break,
+                capstone::x86_insn::X86_INS_UD2 => break,
+                _ => ()
             }
-            else {
-                bail!("not an x86 instruction")
-            }
-
-            offset += instruction.size as usize;
+        }
+        else {
+            bail!("not an x86 instruction")
         }
 
-        Ok(BlockTranslationResult::new(block_graph, address, length, successors))
+        offset += instruction.size as usize;
     }
+
+    Ok(BlockTranslationResult::new(block_graph, address, length, successors))
 }
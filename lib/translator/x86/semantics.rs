@@ -1,13 +1,82 @@
 use capstone_rust::capstone;
 use capstone_rust::capstone::{cs_x86_op};
-use capstone_rust::capstone_sys::{x86_op_type, x86_reg};
+use capstone_rust::capstone_sys::{x86_op_type, x86_prefix, x86_reg};
 use error::*;
 use il::*;
 use il::Expression as Expr;
+use std::cmp;
 
 
 const MEM_SIZE: u64 = (1 << 48);
 
+// The flags we model, and their bits in eflags. TF, AC and the other system
+// flags are not modelled.
+const EFLAGS: &'static [(&'static str, u64)] = &[
+    ("CF", 0), ("PF", 2), ("AF", 4), ("ZF", 6), ("SF", 7),
+    ("IF", 9), ("DF", 10), ("OF", 11), ("ID", 21)
+];
+
+
+/// The mode instructions are lifted in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// 32-bit x86
+    X86,
+    /// 64-bit x86
+    Amd64
+}
+
+
+impl Mode {
+    /// The size of addresses, and of the stack pointer, in bits.
+    pub fn bits(&self) -> usize {
+        match *self {
+            Mode::X86 => 32,
+            Mode::Amd64 => 64
+        }
+    }
+
+    fn registers(&self) -> &'static [X86Register] {
+        match *self {
+            Mode::X86 => X86REGISTERS,
+            Mode::Amd64 => AMD64REGISTERS
+        }
+    }
+
+    fn full_register(&self, x86: x86_reg, amd64: x86_reg) -> &'static X86Register {
+        let capstone_reg = match *self {
+            Mode::X86 => x86,
+            Mode::Amd64 => amd64
+        };
+        get_register(*self, capstone_reg).unwrap()
+    }
+
+    /// The full-width counter, ecx or rcx.
+    pub fn cx(&self) -> &'static X86Register {
+        self.full_register(x86_reg::X86_REG_ECX, x86_reg::X86_REG_RCX)
+    }
+
+    /// The full-width source index, esi or rsi.
+    pub fn si(&self) -> &'static X86Register {
+        self.full_register(x86_reg::X86_REG_ESI, x86_reg::X86_REG_RSI)
+    }
+
+    /// The full-width destination index, edi or rdi.
+    pub fn di(&self) -> &'static X86Register {
+        self.full_register(x86_reg::X86_REG_EDI, x86_reg::X86_REG_RDI)
+    }
+
+    /// The stack pointer, esp or rsp.
+    pub fn sp(&self) -> &'static X86Register {
+        self.full_register(x86_reg::X86_REG_ESP, x86_reg::X86_REG_RSP)
+    }
+
+    /// The frame pointer, ebp or rbp.
+    pub fn bp(&self) -> &'static X86Register {
+        self.full_register(x86_reg::X86_REG_EBP, x86_reg::X86_REG_RBP)
+    }
+}
+
 
 /// Struct for dealing with x86 registers
 pub struct X86Register {
@@ -40,7 +109,10 @@ impl X86Register {
 
     /// Returns the full-width register for this register
     pub fn get_full(&self) -> Result<&'static X86Register> {
-        get_register(self.full_reg)
+        X86REGISTERS.iter()
+            .chain(AMD64REGISTERS.iter())
+            .find(|register| register.is_full() && register.capstone_reg == self.full_reg)
+            .ok_or("Could not find full register".into())
     }
 
     /// Returns an expression which evaluates to the value of the register.
@@ -62,12 +134,24 @@ impl X86Register {
 
     /// Sets the value of this register.
     ///
-    /// This handles things like al/ah/ax/eax
+    /// This handles things like al/ah/ax/eax. Writes to the 32-bit half of a
+    /// 64-bit register zero-extend into the full register. Loads of fs and gs
+    /// raise `load_fs` or `load_gs`, so the platform can set the base of the
+    /// segment selected.
     pub fn set(&self, block: &mut Block, value: Expression) -> Result<()> {
         if self.is_full() {
             block.assign(scalar(self.name, self.bits), value);
+            match self.capstone_reg {
+                x86_reg::X86_REG_FS => block.raise(expr_scalar("load_fs", 1)),
+                x86_reg::X86_REG_GS => block.raise(expr_scalar("load_gs", 1)),
+                _ => ()
+            }
             Ok(())
         }
+        else if self.bits == 32 && self.get_full()?.bits == 64 {
+            let full_reg = self.get_full()?;
+            full_reg.set(block, Expr::zext(64, value)?)
+        }
         else if self.offset == 0 {
             let full_reg = self.get_full()?;
             let mask = !0 << self.bits;
@@ -77,7 +161,7 @@ impl X86Register {
         }
         else {
             let full_reg = self.get_full()?;
-            let mask = ((1 << self.bits) - 1) << self.offset;
+            let mask = !(((1 << self.bits) - 1) << self.offset);
             let expr = Expr::and(full_reg.get()?, expr_const(mask, full_reg.bits))?;
             let value = Expr::zext(full_reg.bits, value)?;
             let expr = Expr::or(expr, Expr::shl(value, expr_const(self.offset as u64, full_reg.bits))?)?;
@@ -114,17 +198,120 @@ const X86REGISTERS : &'static [X86Register] = &[
     X86Register { name: "bp", capstone_reg: x86_reg::X86_REG_BP, full_reg: x86_reg::X86_REG_EBP, offset: 0, bits: 16 },
     X86Register { name: "ebp", capstone_reg: x86_reg::X86_REG_EBP, full_reg: x86_reg::X86_REG_EBP, offset: 0, bits: 32 },
     X86Register { name: "fs", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_FS, offset: 0, bits: 16 },
-    X86Register { name: "gs", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_GS, offset: 0, bits: 16 },
-    X86Register { name: "ds", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_DS, offset: 0, bits: 16 },
-    X86Register { name: "es", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_ES, offset: 0, bits: 16 },
-    X86Register { name: "cs", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_CS, offset: 0, bits: 16 },
-    X86Register { name: "ss", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_SS, offset: 0, bits: 16 },
+    X86Register { name: "gs", capstone_reg: x86_reg::X86_REG_GS, full_reg: x86_reg::X86_REG_GS, offset: 0, bits: 16 },
+    X86Register { name: "ds", capstone_reg: x86_reg::X86_REG_DS, full_reg: x86_reg::X86_REG_DS, offset: 0, bits: 16 },
+    X86Register { name: "es", capstone_reg: x86_reg::X86_REG_ES, full_reg: x86_reg::X86_REG_ES, offset: 0, bits: 16 },
+    X86Register { name: "cs", capstone_reg: x86_reg::X86_REG_CS, full_reg: x86_reg::X86_REG_CS, offset: 0, bits: 16 },
+    X86Register { name: "ss", capstone_reg: x86_reg::X86_REG_SS, full_reg: x86_reg::X86_REG_SS, offset: 0, bits: 16 },
+    X86Register { name: "xmm0", capstone_reg: x86_reg::X86_REG_XMM0, full_reg: x86_reg::X86_REG_XMM0, offset: 0, bits: 128 },
+    X86Register { name: "xmm1", capstone_reg: x86_reg::X86_REG_XMM1, full_reg: x86_reg::X86_REG_XMM1, offset: 0, bits: 128 },
+    X86Register { name: "xmm2", capstone_reg: x86_reg::X86_REG_XMM2, full_reg: x86_reg::X86_REG_XMM2, offset: 0, bits: 128 },
+    X86Register { name: "xmm3", capstone_reg: x86_reg::X86_REG_XMM3, full_reg: x86_reg::X86_REG_XMM3, offset: 0, bits: 128 },
+    X86Register { name: "xmm4", capstone_reg: x86_reg::X86_REG_XMM4, full_reg: x86_reg::X86_REG_XMM4, offset: 0, bits: 128 },
+    X86Register { name: "xmm5", capstone_reg: x86_reg::X86_REG_XMM5, full_reg: x86_reg::X86_REG_XMM5, offset: 0, bits: 128 },
+    X86Register { name: "xmm6", capstone_reg: x86_reg::X86_REG_XMM6, full_reg: x86_reg::X86_REG_XMM6, offset: 0, bits: 128 },
+    X86Register { name: "xmm7", capstone_reg: x86_reg::X86_REG_XMM7, full_reg: x86_reg::X86_REG_XMM7, offset: 0, bits: 128 },
+];
+
+
+const AMD64REGISTERS : &'static [X86Register] = &[
+    X86Register { name: "ah", capstone_reg: x86_reg::X86_REG_AH, full_reg: x86_reg::X86_REG_RAX, offset: 8, bits: 8 },
+    X86Register { name: "al", capstone_reg: x86_reg::X86_REG_AL, full_reg: x86_reg::X86_REG_RAX, offset: 0, bits: 8 },
+    X86Register { name: "ax", capstone_reg: x86_reg::X86_REG_AX, full_reg: x86_reg::X86_REG_RAX, offset: 0, bits: 16 },
+    X86Register { name: "eax", capstone_reg: x86_reg::X86_REG_EAX, full_reg: x86_reg::X86_REG_RAX, offset: 0, bits: 32 },
+    X86Register { name: "rax", capstone_reg: x86_reg::X86_REG_RAX, full_reg: x86_reg::X86_REG_RAX, offset: 0, bits: 64 },
+    X86Register { name: "bh", capstone_reg: x86_reg::X86_REG_BH, full_reg: x86_reg::X86_REG_RBX, offset: 8, bits: 8 },
+    X86Register { name: "bl", capstone_reg: x86_reg::X86_REG_BL, full_reg: x86_reg::X86_REG_RBX, offset: 0, bits: 8 },
+    X86Register { name: "bx", capstone_reg: x86_reg::X86_REG_BX, full_reg: x86_reg::X86_REG_RBX, offset: 0, bits: 16 },
+    X86Register { name: "ebx", capstone_reg: x86_reg::X86_REG_EBX, full_reg: x86_reg::X86_REG_RBX, offset: 0, bits: 32 },
+    X86Register { name: "rbx", capstone_reg: x86_reg::X86_REG_RBX, full_reg: x86_reg::X86_REG_RBX, offset: 0, bits: 64 },
+    X86Register { name: "ch", capstone_reg: x86_reg::X86_REG_CH, full_reg: x86_reg::X86_REG_RCX, offset: 8, bits: 8 },
+    X86Register { name: "cl", capstone_reg: x86_reg::X86_REG_CL, full_reg: x86_reg::X86_REG_RCX, offset: 0, bits: 8 },
+    X86Register { name: "cx", capstone_reg: x86_reg::X86_REG_CX, full_reg: x86_reg::X86_REG_RCX, offset: 0, bits: 16 },
+    X86Register { name: "ecx", capstone_reg: x86_reg::X86_REG_ECX, full_reg: x86_reg::X86_REG_RCX, offset: 0, bits: 32 },
+    X86Register { name: "rcx", capstone_reg: x86_reg::X86_REG_RCX, full_reg: x86_reg::X86_REG_RCX, offset: 0, bits: 64 },
+    X86Register { name: "dh", capstone_reg: x86_reg::X86_REG_DH, full_reg: x86_reg::X86_REG_RDX, offset: 8, bits: 8 },
+    X86Register { name: "dl", capstone_reg: x86_reg::X86_REG_DL, full_reg: x86_reg::X86_REG_RDX, offset: 0, bits: 8 },
+    X86Register { name: "dx", capstone_reg: x86_reg::X86_REG_DX, full_reg: x86_reg::X86_REG_RDX, offset: 0, bits: 16 },
+    X86Register { name: "edx", capstone_reg: x86_reg::X86_REG_EDX, full_reg: x86_reg::X86_REG_RDX, offset: 0, bits: 32 },
+    X86Register { name: "rdx", capstone_reg: x86_reg::X86_REG_RDX, full_reg: x86_reg::X86_REG_RDX, offset: 0, bits: 64 },
+    X86Register { name: "sil", capstone_reg: x86_reg::X86_REG_SIL, full_reg: x86_reg::X86_REG_RSI, offset: 0, bits: 8 },
+    X86Register { name: "si", capstone_reg: x86_reg::X86_REG_SI, full_reg: x86_reg::X86_REG_RSI, offset: 0, bits: 16 },
+    X86Register { name: "esi", capstone_reg: x86_reg::X86_REG_ESI, full_reg: x86_reg::X86_REG_RSI, offset: 0, bits: 32 },
+    X86Register { name: "rsi", capstone_reg: x86_reg::X86_REG_RSI, full_reg: x86_reg::X86_REG_RSI, offset: 0, bits: 64 },
+    X86Register { name: "dil", capstone_reg: x86_reg::X86_REG_DIL, full_reg: x86_reg::X86_REG_RDI, offset: 0, bits: 8 },
+    X86Register { name: "di", capstone_reg: x86_reg::X86_REG_DI, full_reg: x86_reg::X86_REG_RDI, offset: 0, bits: 16 },
+    X86Register { name: "edi", capstone_reg: x86_reg::X86_REG_EDI, full_reg: x86_reg::X86_REG_RDI, offset: 0, bits: 32 },
+    X86Register { name: "rdi", capstone_reg: x86_reg::X86_REG_RDI, full_reg: x86_reg::X86_REG_RDI, offset: 0, bits: 64 },
+    X86Register { name: "spl", capstone_reg: x86_reg::X86_REG_SPL, full_reg: x86_reg::X86_REG_RSP, offset: 0, bits: 8 },
+    X86Register { name: "sp", capstone_reg: x86_reg::X86_REG_SP, full_reg: x86_reg::X86_REG_RSP, offset: 0, bits: 16 },
+    X86Register { name: "esp", capstone_reg: x86_reg::X86_REG_ESP, full_reg: x86_reg::X86_REG_RSP, offset: 0, bits: 32 },
+    X86Register { name: "rsp", capstone_reg: x86_reg::X86_REG_RSP, full_reg: x86_reg::X86_REG_RSP, offset: 0, bits: 64 },
+    X86Register { name: "bpl", capstone_reg: x86_reg::X86_REG_BPL, full_reg: x86_reg::X86_REG_RBP, offset: 0, bits: 8 },
+    X86Register { name: "bp", capstone_reg: x86_reg::X86_REG_BP, full_reg: x86_reg::X86_REG_RBP, offset: 0, bits: 16 },
+    X86Register { name: "ebp", capstone_reg: x86_reg::X86_REG_EBP, full_reg: x86_reg::X86_REG_RBP, offset: 0, bits: 32 },
+    X86Register { name: "rbp", capstone_reg: x86_reg::X86_REG_RBP, full_reg: x86_reg::X86_REG_RBP, offset: 0, bits: 64 },
+    X86Register { name: "r8b", capstone_reg: x86_reg::X86_REG_R8B, full_reg: x86_reg::X86_REG_R8, offset: 0, bits: 8 },
+    X86Register { name: "r8w", capstone_reg: x86_reg::X86_REG_R8W, full_reg: x86_reg::X86_REG_R8, offset: 0, bits: 16 },
+    X86Register { name: "r8d", capstone_reg: x86_reg::X86_REG_R8D, full_reg: x86_reg::X86_REG_R8, offset: 0, bits: 32 },
+    X86Register { name: "r8", capstone_reg: x86_reg::X86_REG_R8, full_reg: x86_reg::X86_REG_R8, offset: 0, bits: 64 },
+    X86Register { name: "r9b", capstone_reg: x86_reg::X86_REG_R9B, full_reg: x86_reg::X86_REG_R9, offset: 0, bits: 8 },
+    X86Register { name: "r9w", capstone_reg: x86_reg::X86_REG_R9W, full_reg: x86_reg::X86_REG_R9, offset: 0, bits: 16 },
+    X86Register { name: "r9d", capstone_reg: x86_reg::X86_REG_R9D, full_reg: x86_reg::X86_REG_R9, offset: 0, bits: 32 },
+    X86Register { name: "r9", capstone_reg: x86_reg::X86_REG_R9, full_reg: x86_reg::X86_REG_R9, offset: 0, bits: 64 },
+    X86Register { name: "r10b", capstone_reg: x86_reg::X86_REG_R10B, full_reg: x86_reg::X86_REG_R10, offset: 0, bits: 8 },
+    X86Register { name: "r10w", capstone_reg: x86_reg::X86_REG_R10W, full_reg: x86_reg::X86_REG_R10, offset: 0, bits: 16 },
+    X86Register { name: "r10d", capstone_reg: x86_reg::X86_REG_R10D, full_reg: x86_reg::X86_REG_R10, offset: 0, bits: 32 },
+    X86Register { name: "r10", capstone_reg: x86_reg::X86_REG_R10, full_reg: x86_reg::X86_REG_R10, offset: 0, bits: 64 },
+    X86Register { name: "r11b", capstone_reg: x86_reg::X86_REG_R11B, full_reg: x86_reg::X86_REG_R11, offset: 0, bits: 8 },
+    X86Register { name: "r11w", capstone_reg: x86_reg::X86_REG_R11W, full_reg: x86_reg::X86_REG_R11, offset: 0, bits: 16 },
+    X86Register { name: "r11d", capstone_reg: x86_reg::X86_REG_R11D, full_reg: x86_reg::X86_REG_R11, offset: 0, bits: 32 },
+    X86Register { name: "r11", capstone_reg: x86_reg::X86_REG_R11, full_reg: x86_reg::X86_REG_R11, offset: 0, bits: 64 },
+    X86Register { name: "r12b", capstone_reg: x86_reg::X86_REG_R12B, full_reg: x86_reg::X86_REG_R12, offset: 0, bits: 8 },
+    X86Register { name: "r12w", capstone_reg: x86_reg::X86_REG_R12W, full_reg: x86_reg::X86_REG_R12, offset: 0, bits: 16 },
+    X86Register { name: "r12d", capstone_reg: x86_reg::X86_REG_R12D, full_reg: x86_reg::X86_REG_R12, offset: 0, bits: 32 },
+    X86Register { name: "r12", capstone_reg: x86_reg::X86_REG_R12, full_reg: x86_reg::X86_REG_R12, offset: 0, bits: 64 },
+    X86Register { name: "r13b", capstone_reg: x86_reg::X86_REG_R13B, full_reg: x86_reg::X86_REG_R13, offset: 0, bits: 8 },
+    X86Register { name: "r13w", capstone_reg: x86_reg::X86_REG_R13W, full_reg: x86_reg::X86_REG_R13, offset: 0, bits: 16 },
+    X86Register { name: "r13d", capstone_reg: x86_reg::X86_REG_R13D, full_reg: x86_reg::X86_REG_R13, offset: 0, bits: 32 },
+    X86Register { name: "r13", capstone_reg: x86_reg::X86_REG_R13, full_reg: x86_reg::X86_REG_R13, offset: 0, bits: 64 },
+    X86Register { name: "r14b", capstone_reg: x86_reg::X86_REG_R14B, full_reg: x86_reg::X86_REG_R14, offset: 0, bits: 8 },
+    X86Register { name: "r14w", capstone_reg: x86_reg::X86_REG_R14W, full_reg: x86_reg::X86_REG_R14, offset: 0, bits: 16 },
+    X86Register { name: "r14d", capstone_reg: x86_reg::X86_REG_R14D, full_reg: x86_reg::X86_REG_R14, offset: 0, bits: 32 },
+    X86Register { name: "r14", capstone_reg: x86_reg::X86_REG_R14, full_reg: x86_reg::X86_REG_R14, offset: 0, bits: 64 },
+    X86Register { name: "r15b", capstone_reg: x86_reg::X86_REG_R15B, full_reg: x86_reg::X86_REG_R15, offset: 0, bits: 8 },
+    X86Register { name: "r15w", capstone_reg: x86_reg::X86_REG_R15W, full_reg: x86_reg::X86_REG_R15, offset: 0, bits: 16 },
+    X86Register { name: "r15d", capstone_reg: x86_reg::X86_REG_R15D, full_reg: x86_reg::X86_REG_R15, offset: 0, bits: 32 },
+    X86Register { name: "r15", capstone_reg: x86_reg::X86_REG_R15, full_reg: x86_reg::X86_REG_R15, offset: 0, bits: 64 },
+    X86Register { name: "rip", capstone_reg: x86_reg::X86_REG_RIP, full_reg: x86_reg::X86_REG_RIP, offset: 0, bits: 64 },
+    X86Register { name: "fs", capstone_reg: x86_reg::X86_REG_FS, full_reg: x86_reg::X86_REG_FS, offset: 0, bits: 16 },
+    X86Register { name: "gs", capstone_reg: x86_reg::X86_REG_GS, full_reg: x86_reg::X86_REG_GS, offset: 0, bits: 16 },
+    X86Register { name: "ds", capstone_reg: x86_reg::X86_REG_DS, full_reg: x86_reg::X86_REG_DS, offset: 0, bits: 16 },
+    X86Register { name: "es", capstone_reg: x86_reg::X86_REG_ES, full_reg: x86_reg::X86_REG_ES, offset: 0, bits: 16 },
+    X86Register { name: "cs", capstone_reg: x86_reg::X86_REG_CS, full_reg: x86_reg::X86_REG_CS, offset: 0, bits: 16 },
+    X86Register { name: "ss", capstone_reg: x86_reg::X86_REG_SS, full_reg: x86_reg::X86_REG_SS, offset: 0, bits: 16 },
+    X86Register { name: "xmm0", capstone_reg: x86_reg::X86_REG_XMM0, full_reg: x86_reg::X86_REG_XMM0, offset: 0, bits: 128 },
+    X86Register { name: "xmm1", capstone_reg: x86_reg::X86_REG_XMM1, full_reg: x86_reg::X86_REG_XMM1, offset: 0, bits: 128 },
+    X86Register { name: "xmm2", capstone_reg: x86_reg::X86_REG_XMM2, full_reg: x86_reg::X86_REG_XMM2, offset: 0, bits: 128 },
+    X86Register { name: "xmm3", capstone_reg: x86_reg::X86_REG_XMM3, full_reg: x86_reg::X86_REG_XMM3, offset: 0, bits: 128 },
+    X86Register { name: "xmm4", capstone_reg: x86_reg::X86_REG_XMM4, full_reg: x86_reg::X86_REG_XMM4, offset: 0, bits: 128 },
+    X86Register { name: "xmm5", capstone_reg: x86_reg::X86_REG_XMM5, full_reg: x86_reg::X86_REG_XMM5, offset: 0, bits: 128 },
+    X86Register { name: "xmm6", capstone_reg: x86_reg::X86_REG_XMM6, full_reg: x86_reg::X86_REG_XMM6, offset: 0, bits: 128 },
+    X86Register { name: "xmm7", capstone_reg: x86_reg::X86_REG_XMM7, full_reg: x86_reg::X86_REG_XMM7, offset: 0, bits: 128 },
+    X86Register { name: "xmm8", capstone_reg: x86_reg::X86_REG_XMM8, full_reg: x86_reg::X86_REG_XMM8, offset: 0, bits: 128 },
+    X86Register { name: "xmm9", capstone_reg: x86_reg::X86_REG_XMM9, full_reg: x86_reg::X86_REG_XMM9, offset: 0, bits: 128 },
+    X86Register { name: "xmm10", capstone_reg: x86_reg::X86_REG_XMM10, full_reg: x86_reg::X86_REG_XMM10, offset: 0, bits: 128 },
+    X86Register { name: "xmm11", capstone_reg: x86_reg::X86_REG_XMM11, full_reg: x86_reg::X86_REG_XMM11, offset: 0, bits: 128 },
+    X86Register { name: "xmm12", capstone_reg: x86_reg::X86_REG_XMM12, full_reg: x86_reg::X86_REG_XMM12, offset: 0, bits: 128 },
+    X86Register { name: "xmm13", capstone_reg: x86_reg::X86_REG_XMM13, full_reg: x86_reg::X86_REG_XMM13, offset: 0, bits: 128 },
+    X86Register { name: "xmm14", capstone_reg: x86_reg::X86_REG_XMM14, full_reg: x86_reg::X86_REG_XMM14, offset: 0, bits: 128 },
+    X86Register { name: "xmm15", capstone_reg: x86_reg::X86_REG_XMM15, full_reg: x86_reg::X86_REG_XMM15, offset: 0, bits: 128 },
 ];
 
 
 /// Takes a capstone register enum and returns an `X86Register`
-pub fn get_register(capstone_id: x86_reg) -> Result<&'static X86Register> {
-    for register in X86REGISTERS.iter() {
+pub fn get_register(mode: Mode, capstone_id: x86_reg) -> Result<&'static X86Register> {
+    for register in mode.registers().iter() {
         if register.capstone_reg == capstone_id {
             return Ok(&register);
         }
@@ -145,96 +332,123 @@ pub fn details(instruction: &capstone::Instr) -> Result<capstone::cs_x86> {
 
 
 /// Gets the value of an operand as an IL expression
-pub fn operand_value(operand: &cs_x86_op) -> Result<Expression> {
+///
+/// Addresses are the width of addresses in `mode`, and include the base of an
+/// fs or gs segment override. RIP-relative addresses read the scalar `rip`,
+/// which the translator replaces with the address of the next instruction.
+pub fn operand_value(mode: Mode, operand: &cs_x86_op) -> Result<Expression> {
     match operand.type_ {
         x86_op_type::X86_OP_INVALID => Err("Invalid operand".into()),
         x86_op_type::X86_OP_REG => {
             // Get the register value
-            get_register(*operand.reg())?.get()
+            get_register(mode, *operand.reg())?.get()
         }
         x86_op_type::X86_OP_MEM => {
-            let mem = operand.mem();
-            let base_capstone_reg = capstone::x86_reg::from(mem.base);
-            let index_capstone_reg = capstone::x86_reg::from(mem.index);
+            let address = effective_address(mode, operand)?;
+
+            // Linux, like every 64-bit mode, uses a flat memory model where
+            // only fs and gs have a base, which the platform sets.
+            match x86_reg::from(operand.mem().segment) {
+                x86_reg::X86_REG_FS =>
+                    Ok(Expr::add(expr_scalar("fs_base", mode.bits()), address)?),
+                x86_reg::X86_REG_GS =>
+                    Ok(Expr::add(expr_scalar("gs_base", mode.bits()), address)?),
+                x86_reg::X86_REG_INVALID |
+                x86_reg::X86_REG_CS |
+                x86_reg::X86_REG_DS |
+                x86_reg::X86_REG_ES |
+                x86_reg::X86_REG_SS => Ok(address),
+                _ => bail!("invalid segment register")
+            }
+        },
+        x86_op_type::X86_OP_IMM => {
+            Ok(expr_const(operand.imm() as u64, operand.size as usize * 8))
+        }
+        x86_op_type::X86_OP_FP => Err("Unhandled operand".into()),
+    }
+}
 
-            let base = match base_capstone_reg {
-                x86_reg::X86_REG_INVALID => None,
-                reg => Some(get_register(reg)?.get()?)
-            };
 
-            let index = match index_capstone_reg {
-                x86_reg::X86_REG_INVALID => None,
-                reg => Some(get_register(reg)?.get()?)
-            };
+/// Gets the offset of a memory operand into its segment, which is the
+/// address computed from its base, index, scale and displacement.
+pub fn effective_address(mode: Mode, operand: &cs_x86_op) -> Result<Expression> {
+    let bits = mode.bits();
 
-            let scale = Expr::constant(Constant::new(mem.scale as i64 as u64, 32));
+    // address registers narrower than an address are zero-extended
+    let address_register = |reg: x86_reg| -> Result<Expression> {
+        let value = get_register(mode, reg)?.get()?;
+        if value.bits() < bits {
+            Expr::zext(bits, value)
+        }
+        else {
+            Ok(value)
+        }
+    };
 
-            let si = match index {
-                Some(index) => Some(Expr::mul(index, scale).unwrap()),
-                None => None
-            };
+    if operand.type_ != x86_op_type::X86_OP_MEM {
+        bail!("effective_address called on non-memory operand");
+    }
 
-            // Handle base and scale/index
-            let op : Option<Expression> = if base.is_some() {
-                if si.is_some() {
-                    Some(Expr::add(base.unwrap(), si.unwrap()).unwrap())
-                }
-                else {
-                    base
-                }
-            }
-            else if si.is_some() {
-                si
-            }
-            else {
-                None
-            };
+    let mem = operand.mem();
+    let base_capstone_reg = capstone::x86_reg::from(mem.base);
+    let index_capstone_reg = capstone::x86_reg::from(mem.index);
 
-            // handle disp
-            let op = if op.is_some() {
-                if mem.disp > 0 {
-                    Expr::add(op.unwrap(), expr_const(mem.disp as u64, 32))?
-                }
-                else if mem.disp < 0 {
-                    Expr::sub(op.unwrap(), expr_const(mem.disp.abs() as u64, 32))?
-                }
-                else {
-                    op.unwrap()
-                }
-            }
-            else {
-                expr_const(mem.disp as u64, 32)
-            };
+    let base = match base_capstone_reg {
+        x86_reg::X86_REG_INVALID => None,
+        reg => Some(address_register(reg)?)
+    };
 
-            match x86_reg::from(mem.segment) {
-                x86_reg::X86_REG_INVALID =>
-                    Ok(op),
-                x86_reg::X86_REG_CS => 
-                    Ok(Expr::add(expr_scalar("cs_base", 32), op)?),
-                x86_reg::X86_REG_DS => 
-                    Ok(Expr::add(expr_scalar("ds_base", 32), op)?),
-                x86_reg::X86_REG_ES => 
-                    Ok(Expr::add(expr_scalar("es_base", 32), op)?),
-                x86_reg::X86_REG_FS => 
-                    Ok(Expr::add(expr_scalar("fs_base", 32), op)?),
-                x86_reg::X86_REG_GS => 
-                    Ok(Expr::add(expr_scalar("gs_base", 32), op)?),
-                x86_reg::X86_REG_SS => 
-                    Ok(Expr::add(expr_scalar("ss_base", 32), op)?),
-                _ => bail!("invalid segment register")
-            }
-        },
-        x86_op_type::X86_OP_IMM => {
-            Ok(expr_const(operand.imm() as u64, operand.size as usize * 8))
+    let index = match index_capstone_reg {
+        x86_reg::X86_REG_INVALID => None,
+        reg => Some(address_register(reg)?)
+    };
+
+    let scale = Expr::constant(Constant::new(mem.scale as i64 as u64, bits));
+
+    let si = match index {
+        Some(index) => Some(Expr::mul(index, scale).unwrap()),
+        None => None
+    };
+
+    // Handle base and scale/index
+    let op : Option<Expression> = if base.is_some() {
+        if si.is_some() {
+            Some(Expr::add(base.unwrap(), si.unwrap()).unwrap())
+        }
+        else {
+            base
         }
-        x86_op_type::X86_OP_FP => Err("Unhandled operand".into()),
     }
+    else if si.is_some() {
+        si
+    }
+    else {
+        None
+    };
+
+    // handle disp
+    let address = if op.is_some() {
+        if mem.disp > 0 {
+            Expr::add(op.unwrap(), expr_const(mem.disp as u64, bits))?
+        }
+        else if mem.disp < 0 {
+            Expr::sub(op.unwrap(), expr_const(mem.disp.abs() as u64, bits))?
+        }
+        else {
+            op.unwrap()
+        }
+    }
+    else {
+        expr_const(mem.disp as u64, bits)
+    };
+
+    Ok(address)
 }
 
 
 /// Gets the value of an operand as an IL expression, performing any required loads as needed.
-pub fn operand_load(block: &mut Block, operand: &cs_x86_op) -> Result<Expression> {
-    let op = try!(operand_value(operand));
+pub fn operand_load(mode: Mode, block: &mut Block, operand: &cs_x86_op) -> Result<Expression> {
+    let op = try!(operand_value(mode, operand));
 
     if operand.type_ == x86_op_type::X86_OP_MEM {
         let temp = block.temp(operand.size as usize * 8);
@@ -246,16 +460,16 @@ pub fn operand_load(block: &mut Block, operand: &cs_x86_op) -> Result<Expression
 
 
 /// Stores a value in an operand, performing any stores as necessary.
-pub fn operand_store(mut block: &mut Block, operand: &cs_x86_op, value: Expression) -> Result<()> {
+pub fn operand_store(mode: Mode, mut block: &mut Block, operand: &cs_x86_op, value: Expression) -> Result<()> {
     match operand.type_ {
         x86_op_type::X86_OP_INVALID => Err("operand_store called on invalid operand".into()),
         x86_op_type::X86_OP_IMM => Err("operand_store called on immediate operand".into()),
         x86_op_type::X86_OP_REG => {
-            let dst_register = get_register(*operand.reg())?;
+            let dst_register = get_register(mode, *operand.reg())?;
             dst_register.set(&mut block, value)
         },
         x86_op_type::X86_OP_MEM => {
-            let address = operand_value(operand)?;
+            let address = operand_value(mode, operand)?;
             block.store(array("mem", MEM_SIZE), address, value);
             Ok(())
         },
@@ -267,20 +481,22 @@ pub fn operand_store(mut block: &mut Block, operand: &cs_x86_op, value: Expressi
 
 
 /// Convenience function to pop a value off the stack
-pub fn pop_value(block: &mut Block, bits: usize) -> Result<Expression> {
+pub fn pop_value(mode: Mode, block: &mut Block, bits: usize) -> Result<Expression> {
     let temp = block.temp(bits);
+    let sp = mode.sp();
 
-    block.load(temp.clone(), expr_scalar("esp", 32), array("mem", MEM_SIZE));
-    block.assign(scalar("esp", 32), Expr::add(expr_scalar("esp", 32), expr_const(bits as u64 / 8, 32))?);
+    block.load(temp.clone(), sp.get()?, array("mem", MEM_SIZE));
+    sp.set(block, Expr::add(sp.get()?, expr_const(bits as u64 / 8, sp.bits()))?)?;
 
     Ok(temp.into())
 }
 
 
 /// Convenience function to push a value onto the stack
-pub fn push_value(block: &mut Block, value: Expression) -> Result<()> {
-    block.assign(scalar("esp", 32), Expr::sub(expr_scalar("esp", 32), expr_const(4, 32))?);
-    block.store(array("mem", MEM_SIZE), expr_scalar("esp", 32), value);
+pub fn push_value(mode: Mode, block: &mut Block, value: Expression) -> Result<()> {
+    let sp = mode.sp();
+    sp.set(block, Expr::sub(sp.get()?, expr_const(value.bits() as u64 / 8, sp.bits()))?)?;
+    block.store(array("mem", MEM_SIZE), sp.get()?, value);
     Ok(())
 }
 
@@ -321,9 +537,31 @@ pub fn set_cf(block: &mut Block, result: Expression, lhs: Expression) -> Result<
 }
 
 
+/// Convenience function to set the af based on result and both operands
+pub fn set_af(block: &mut Block, result: Expression, lhs: Expression, rhs: Expression) -> Result<()> {
+    let expr = Expr::xor(Expr::xor(lhs, rhs)?, result)?;
+    let expr = Expr::shr(expr.clone(), expr_const(4, expr.bits()))?;
+    block.assign(scalar("AF", 1), Expr::trun(1, expr)?);
+    Ok(())
+}
+
+
+/// Convenience function to set the pf based on the low byte of result
+pub fn set_pf(block: &mut Block, result: Expression) -> Result<()> {
+    let byte = resize(result, 8)?;
+    let mut parity = expr_const(1, 1);
+    for i in 0..8 {
+        let bit = Expr::trun(1, Expr::shr(byte.clone(), expr_const(i, 8))?)?;
+        parity = Expr::xor(parity, bit)?;
+    }
+    block.assign(scalar("PF", 1), parity);
+    Ok(())
+}
+
+
 /// Returns a condition which is true if a conditional instruction should be
 /// executed. Used for setcc, jcc and cmovcc.
-pub fn cc_condition(instruction: &capstone::Instr) -> Result<Expression> {
+pub fn cc_condition(mode: Mode, instruction: &capstone::Instr) -> Result<Expression> {
     if let capstone::InstrIdArch::X86(instruction_id) = instruction.id {
         match instruction_id {
             capstone::x86_insn::X86_INS_CMOVA |
@@ -349,13 +587,17 @@ pub fn cc_condition(instruction: &capstone::Instr) -> Result<Expression> {
                 Expr::or(cf, zf)
             },
             capstone::x86_insn::X86_INS_JCXZ => {
-                let cx = get_register(x86_reg::X86_REG_CX)?.get()?;
+                let cx = get_register(mode, x86_reg::X86_REG_CX)?.get()?;
                 Expr::cmpeq(cx, expr_const(0, 16))
             },
             capstone::x86_insn::X86_INS_JECXZ => {
-                let cx = get_register(x86_reg::X86_REG_ECX)?.get()?;
+                let cx = get_register(mode, x86_reg::X86_REG_ECX)?.get()?;
                 Expr::cmpeq(cx, expr_const(0, 32))
             },
+            capstone::x86_insn::X86_INS_JRCXZ => {
+                let cx = get_register(mode, x86_reg::X86_REG_RCX)?.get()?;
+                Expr::cmpeq(cx, expr_const(0, 64))
+            },
             capstone::x86_insn::X86_INS_CMOVE |
             capstone::x86_insn::X86_INS_JE |
             capstone::x86_insn::X86_INS_SETE =>
@@ -420,19 +662,19 @@ pub fn cc_condition(instruction: &capstone::Instr) -> Result<Expression> {
 
 
 /// Returns a condition which is true if a loop should be taken
-pub fn loop_condition(instruction: &capstone::Instr) -> Result<Expression> {
-    let ecx = scalar("ecx", 32);
+pub fn loop_condition(mode: Mode, instruction: &capstone::Instr) -> Result<Expression> {
+    let ecx = mode.cx().get()?;
 
     if let capstone::InstrIdArch::X86(instruction_id) = instruction.id {
         match instruction_id {
             capstone::x86_insn::X86_INS_LOOP =>
-                Expr::cmpneq(ecx.clone().into(), expr_const(0, ecx.bits())),
+                Expr::cmpneq(ecx.clone(), expr_const(0, ecx.bits())),
             capstone::x86_insn::X86_INS_LOOPE => {
-                let expr = Expr::cmpneq(ecx.clone().into(), expr_const(0, ecx.bits()))?;
+                let expr = Expr::cmpneq(ecx.clone(), expr_const(0, ecx.bits()))?;
                 Expr::and(expr, Expr::cmpeq(expr_scalar("ZF", 1), expr_const(1, 1))?)
             }
             capstone::x86_insn::X86_INS_LOOPNE => {
-                let expr = Expr::cmpneq(ecx.clone().into(), expr_const(0, ecx.bits()))?;
+                let expr = Expr::cmpneq(ecx.clone(), expr_const(0, ecx.bits()))?;
                 Expr::and(expr, Expr::cmpeq(expr_scalar("ZF", 1), expr_const(0, 1))?)
             }
             _ => bail!("unhandled loop")
@@ -444,24 +686,65 @@ pub fn loop_condition(instruction: &capstone::Instr) -> Result<Expression> {
 }
 
 
-/// Wraps the given instruction graph with the rep prefix inplace
+/// Wraps the given instruction graph with a rep, repe or repne prefix
+/// inplace.
+///
+/// The instruction repeats until cx is zero, and cmps and scas also stop once
+/// ZF is clear for repe, or set for repne. The prefix has no effect on any
+/// other instruction, such as `rep ret`.
 pub fn rep_prefix(
+    mode: Mode,
     control_flow_graph: &mut ControlFlowGraph,
     instruction: &capstone::Instr
 ) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // The value of ZF which ends a repeated compare, if any
+    let terminating_zf = match instruction.id {
+        capstone::InstrIdArch::X86(instruction_id) => match instruction_id {
+            capstone::x86_insn::X86_INS_CMPSB |
+            capstone::x86_insn::X86_INS_CMPSW |
+            capstone::x86_insn::X86_INS_CMPSD |
+            capstone::x86_insn::X86_INS_CMPSQ |
+            capstone::x86_insn::X86_INS_SCASB |
+            capstone::x86_insn::X86_INS_SCASW |
+            capstone::x86_insn::X86_INS_SCASD |
+            capstone::x86_insn::X86_INS_SCASQ => {
+                if detail.prefix.contains(&(x86_prefix::X86_PREFIX_REPNE as u8)) {
+                    Some(1)
+                }
+                else {
+                    Some(0)
+                }
+            },
+            capstone::x86_insn::X86_INS_LODSB |
+            capstone::x86_insn::X86_INS_LODSW |
+            capstone::x86_insn::X86_INS_LODSD |
+            capstone::x86_insn::X86_INS_LODSQ |
+            capstone::x86_insn::X86_INS_MOVSB |
+            capstone::x86_insn::X86_INS_MOVSW |
+            capstone::x86_insn::X86_INS_MOVSD |
+            capstone::x86_insn::X86_INS_MOVSQ |
+            capstone::x86_insn::X86_INS_STOSB |
+            capstone::x86_insn::X86_INS_STOSW |
+            capstone::x86_insn::X86_INS_STOSD |
+            capstone::x86_insn::X86_INS_STOSQ => None,
+            _ => return Ok(())
+        },
+        _ => bail!("not an x86 instruction")
+    };
 
     if control_flow_graph.entry().is_none() || control_flow_graph.exit().is_none() {
         bail!("control_flow_graph entry/exit was none");
     }
 
+    let cx = mode.cx();
+
     let head_index = control_flow_graph.new_block()?.index();
 
     let loop_index = {
         let mut loop_block = control_flow_graph.new_block()?;
-        loop_block.assign(
-            scalar("ecx", 32),
-            Expr::sub(expr_scalar("ecx", 32), expr_const(1, 32))?
-        );
+        cx.set(&mut loop_block, Expr::sub(cx.get()?, expr_const(1, cx.bits()))?)?;
         loop_block.index()
     };
 
@@ -475,48 +758,35 @@ pub fn rep_prefix(
     control_flow_graph.conditional_edge(
         head_index,
         entry,
-        Expr::cmpneq(expr_scalar("ecx", 32), expr_const(0, 32))?
+        Expr::cmpneq(cx.get()?, expr_const(0, cx.bits()))?
     )?;
     control_flow_graph.conditional_edge(
         head_index,
         terminating_index,
-        Expr::cmpeq(expr_scalar("ecx", 32), expr_const(0, 32))?
+        Expr::cmpeq(cx.get()?, expr_const(0, cx.bits()))?
     )?;
 
     // exit -> loop
     control_flow_graph.unconditional_edge(exit, loop_index)?;
 
-    if let capstone::InstrIdArch::X86(instruction_id) = instruction.id {
-        match instruction_id {
-            capstone::x86_insn::X86_INS_CMPSB |
-            capstone::x86_insn::X86_INS_CMPSW |
-            capstone::x86_insn::X86_INS_CMPSD |
-            capstone::x86_insn::X86_INS_SCASB |
-            capstone::x86_insn::X86_INS_SCASW |
-            capstone::x86_insn::X86_INS_SCASD => {
-                // loop -> head
-                control_flow_graph.conditional_edge(
-                    loop_index,
-                    head_index,
-                    Expr::cmpneq(expr_scalar("ZF", 1), expr_const(0, 1))?
-                )?;
-                // loop -> terminating
-                control_flow_graph.conditional_edge(
-                    loop_index,
-                    terminating_index,
-                    Expr::cmpneq(expr_scalar("ZF", 1), expr_const(1, 1))?
-                )?;
-            },
-            capstone::x86_insn::X86_INS_STOSB |
-            capstone::x86_insn::X86_INS_STOSW |
-            capstone::x86_insn::X86_INS_STOSD |
-            capstone::x86_insn::X86_INS_MOVSB |
-            capstone::x86_insn::X86_INS_MOVSW |
-            capstone::x86_insn::X86_INS_MOVSD => {
-                // loop -> head
-                control_flow_graph.unconditional_edge(loop_index, head_index)?;
-            },
-            _ => bail!("unsupported instruction for rep prefix, 0x{:x}", instruction.address)
+    match terminating_zf {
+        Some(terminating_zf) => {
+            // loop -> head
+            control_flow_graph.conditional_edge(
+                loop_index,
+                head_index,
+                Expr::cmpneq(expr_scalar("ZF", 1), expr_const(terminating_zf, 1))?
+            )?;
+            // loop -> terminating
+            control_flow_graph.conditional_edge(
+                loop_index,
+                terminating_index,
+                Expr::cmpeq(expr_scalar("ZF", 1), expr_const(terminating_zf, 1))?
+            )?;
+        },
+        None => {
+            // loop -> head
+            control_flow_graph.unconditional_edge(loop_index, head_index)?;
         }
     }
 
@@ -527,401 +797,2894 @@ pub fn rep_prefix(
 }
 
 
-
-pub fn adc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-    // create a block for this instruction
-    let block_index = {
+/// Completes a string instruction whose work is done in the block at
+/// `head_index`, by moving each of `registers` forwards by `bytes`, or
+/// backwards if the direction flag is set.
+fn string_step(
+    control_flow_graph: &mut ControlFlowGraph,
+    head_index: u64,
+    bytes: u64,
+    registers: &[&'static X86Register]
+) -> Result<()> {
+    let inc_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
-
-        let result = block.temp(lhs.bits());
-
-        // perform addition
-        let addition = Expr::add(lhs.clone(), rhs.clone())?;
-        let zext_cf = Expr::zext(lhs.bits(), expr_scalar("CF", 1))?;
-        block.assign(result.clone(), Expr::add(addition, zext_cf)?);
-
-        // calculate flags
-        set_zf(&mut block, result.clone().into())?;
-        set_sf(&mut block, result.clone().into())?;
-        set_of(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
-        set_cf(&mut block, result.clone().into(), lhs.clone())?;
-
-        // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+        for register in registers {
+            register.set(&mut block, Expr::add(register.get()?, expr_const(bytes, register.bits()))?)?;
+        }
 
         block.index()
     };
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
-
-    Ok(())
-}
-
-pub fn add(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-    let block_index = {
+    let dec_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
+        for register in registers {
+            register.set(&mut block, Expr::sub(register.get()?, expr_const(bytes, register.bits()))?)?;
+        }
 
-        let result = block.temp(lhs.bits());
+        block.index()
+    };
 
-        // perform addition
-        block.assign(result.clone(), Expr::add(lhs.clone(), rhs.clone())?);
+    let tail_index = {
+        control_flow_graph.new_block()?.index()
+    };
 
-        // calculate flags
-        set_zf(&mut block, result.clone().into())?;
-        set_sf(&mut block, result.clone().into())?;
-        set_of(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
-        set_cf(&mut block, result.clone().into(), lhs.clone())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        inc_index,
+        Expr::cmpeq(expr_scalar("DF", 1), expr_const(0, 1))?
+    )?;
 
-        // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        dec_index,
+        Expr::cmpeq(expr_scalar("DF", 1), expr_const(1, 1))?
+    )?;
 
-        block.index()
-    };
+    control_flow_graph.unconditional_edge(inc_index, tail_index)?;
+    control_flow_graph.unconditional_edge(dec_index, tail_index)?;
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
 
     Ok(())
 }
 
 
+/// The number of registers in the x87 register stack.
+const FPU_STACK_SIZE: usize = 8;
 
-pub fn and(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+/// The x87 register st(i).
+///
+/// The register stack is held relative to its top, so `st0` is always the top
+/// of the stack, pushes and pops move values between registers, and the
+/// 3-bit scalar `TOP` follows the top of the stack for the status word.
+/// Registers hold IEEE 754 binary64 values, not 80-bit extended precision.
+pub fn fpu_st(index: usize) -> Scalar {
+    scalar(format!("st{}", index), 64)
+}
 
-        if rhs.bits() != lhs.bits() {
-            rhs = Expr::sext(lhs.bits(), rhs)?;
-        }
 
-        let result = block.temp(lhs.bits());
+/// Returns the index i of the x87 register st(i).
+fn fpu_register_index(reg: x86_reg) -> Result<usize> {
+    Ok(match reg {
+        x86_reg::X86_REG_ST0 => 0,
+        x86_reg::X86_REG_ST1 => 1,
+        x86_reg::X86_REG_ST2 => 2,
+        x86_reg::X86_REG_ST3 => 3,
+        x86_reg::X86_REG_ST4 => 4,
+        x86_reg::X86_REG_ST5 => 5,
+        x86_reg::X86_REG_ST6 => 6,
+        x86_reg::X86_REG_ST7 => 7,
+        _ => bail!("{:?} is not an x87 register", reg)
+    })
+}
 
-        // perform addition
-        block.assign(result.clone(), Expr::and(lhs.clone(), rhs.clone())?);
 
-        // calculate flags
-        set_zf(&mut block, result.clone().into())?;
-        set_sf(&mut block, result.clone().into())?;
-        block.assign(scalar("CF", 1), expr_const(0, 1));
-        block.assign(scalar("OF", 1), expr_const(0, 1));
+/// A constant of `bits` bits with every bit set.
+fn ones(bits: usize) -> Expression {
+    Expr::constant(Constant::new_u128(!0, bits))
+}
 
-        // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
 
-        block.index()
-    };
+/// Returns `then` if the 1-bit `condition` is set, and `else_` otherwise.
+fn select(condition: Expression, then: Expression, else_: Expression) -> Result<Expression> {
+    let bits = then.bits();
+    let mask = if bits == 1 { condition } else { Expr::sext(bits, condition)? };
+    let not_mask = Expr::xor(mask.clone(), ones(bits))?;
+    Expr::or(Expr::and(then, mask)?, Expr::and(else_, not_mask)?)
+}
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
 
+/// Push a value onto the x87 register stack.
+pub fn fpu_push(block: &mut Block, value: Expression) -> Result<()> {
+    let temp = block.temp(64);
+    block.assign(temp.clone(), value);
+    for index in (1..FPU_STACK_SIZE).rev() {
+        block.assign(fpu_st(index), fpu_st(index - 1).into());
+    }
+    block.assign(fpu_st(0), temp.into());
+    block.assign(scalar("TOP", 3), Expr::sub(expr_scalar("TOP", 3), expr_const(1, 3))?);
     Ok(())
 }
 
 
+/// Pop the x87 register stack. The popped value moves to the bottom of the
+/// stack, as it stays in its physical register.
+pub fn fpu_pop(block: &mut Block) -> Result<()> {
+    let temp = block.temp(64);
+    block.assign(temp.clone(), fpu_st(0).into());
+    for index in 0..(FPU_STACK_SIZE - 1) {
+        block.assign(fpu_st(index), fpu_st(index + 1).into());
+    }
+    block.assign(fpu_st(FPU_STACK_SIZE - 1), temp.into());
+    block.assign(scalar("TOP", 3), Expr::add(expr_scalar("TOP", 3), expr_const(1, 3))?);
+    Ok(())
+}
 
-pub fn bswap(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        // get operands
-        let src = operand_load(&mut block, &detail.operands[0])?;
 
-        if src.bits() != 32 {
-            bail!("Invalid bit-width for bswap arg {} at 0x{:x}", src, instruction.address);
-        }
+/// Load an 80-bit extended precision value from memory as a binary64 value.
+///
+/// The significand is truncated. Denormals become zero, and exponents outside
+/// the range of binary64 are not handled.
+fn fpu_load_extended(block: &mut Block, address: Expression) -> Result<Expression> {
+    let significand = block.temp(64);
+    block.load(significand.clone(), address.clone(), array("mem", MEM_SIZE));
+
+    let sign_exponent = block.temp(16);
+    let exponent_address = Expr::add(address.clone(), expr_const(8, address.bits()))?;
+    block.load(sign_exponent.clone(), exponent_address, array("mem", MEM_SIZE));
+
+    let sign = Expr::zext(64, Expr::shr(sign_exponent.clone().into(), expr_const(15, 16))?)?;
+    let exponent = Expr::zext(64, Expr::and(sign_exponent.into(), expr_const(0x7fff, 16))?)?;
+    let is_zero = Expr::cmpeq(exponent.clone(), expr_const(0, 64))?;
+    let is_max = Expr::cmpeq(exponent.clone(), expr_const(0x7fff, 64))?;
+
+    let exponent = Expr::sub(exponent, expr_const(16383 - 1023, 64))?;
+    let exponent = Expr::and(exponent, expr_const(0x7ff, 64))?;
+    let exponent = select(is_max, expr_const(0x7ff, 64), exponent)?;
+    let exponent = select(is_zero.clone(), expr_const(0, 64), exponent)?;
+
+    let fraction = Expr::shr(significand.into(), expr_const(11, 64))?;
+    let fraction = Expr::and(fraction, expr_const(0x000f_ffff_ffff_ffff, 64))?;
+    let fraction = select(is_zero, expr_const(0, 64), fraction)?;
+
+    Expr::or(
+        Expr::or(
+            Expr::shl(sign, expr_const(63, 64))?,
+            Expr::shl(exponent, expr_const(52, 64))?
+        )?,
+        fraction
+    )
+}
 
-        let expr = Expr::or(
-            Expr::or(
-                Expr::and(Expr::shl(src.clone(), expr_const(24, 32))?, expr_const(0xff00_0000, 32))?,
-                Expr::and(Expr::shl(src.clone(), expr_const(8, 32))?, expr_const(0x00ff_0000, 32))?
-            )?,
-            Expr::or(
-                Expr::and(Expr::shr(src.clone(), expr_const(8, 32))?, expr_const(0x0000_ff00, 32))?,
-                Expr::and(Expr::shr(src, expr_const(24, 32))?, expr_const(0x0000_00ff, 32))?
-            )?
-        )?;
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+/// Store a binary64 value to memory as an 80-bit extended precision value.
+fn fpu_store_extended(block: &mut Block, address: Expression, value: Expression) -> Result<()> {
+    let sign = Expr::trun(16, Expr::shr(value.clone(), expr_const(48, 64))?)?;
+    let sign = Expr::and(sign, expr_const(0x8000, 16))?;
 
-        block.index()
-    };
+    let exponent = Expr::shr(value.clone(), expr_const(52, 64))?;
+    let exponent = Expr::and(exponent, expr_const(0x7ff, 64))?;
+    let is_zero = Expr::cmpeq(exponent.clone(), expr_const(0, 64))?;
+    let is_max = Expr::cmpeq(exponent.clone(), expr_const(0x7ff, 64))?;
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
+    let exponent = Expr::add(exponent, expr_const(16383 - 1023, 64))?;
+    let exponent = select(is_max, expr_const(0x7fff, 64), exponent)?;
+    let exponent = select(is_zero.clone(), expr_const(0, 64), exponent)?;
 
+    // The integer bit of the significand is explicit
+    let fraction = Expr::and(value, expr_const(0x000f_ffff_ffff_ffff, 64))?;
+    let significand = Expr::or(
+        Expr::shl(fraction, expr_const(11, 64))?,
+        expr_const(0x8000_0000_0000_0000, 64)
+    )?;
+    let significand = select(is_zero, expr_const(0, 64), significand)?;
+
+    block.store(array("mem", MEM_SIZE), address.clone(), significand);
+    block.store(
+        array("mem", MEM_SIZE),
+        Expr::add(address.clone(), expr_const(8, address.bits()))?,
+        Expr::or(sign, Expr::trun(16, exponent)?)?
+    );
     Ok(())
 }
 
 
-/*
-    BSF scans the bits in the second word or doubleword operand starting with
-    bit 0. The ZF flag is cleared if the bits are all 0; otherwise, the ZF flag
-    is set and the destination register is loaded with the bit index of the
-    first set bit.
-*/
-pub fn bsf(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+/// Gets the value of an x87 operand as a binary64 value, performing any
+/// required loads. Memory operands are integers if `integer` is true.
+pub fn fpu_operand_load(
+    mode: Mode,
+    block: &mut Block,
+    operand: &cs_x86_op,
+    integer: bool
+) -> Result<Expression> {
+    match operand.type_ {
+        x86_op_type::X86_OP_REG => Ok(fpu_st(fpu_register_index(*operand.reg())?).into()),
+        x86_op_type::X86_OP_MEM => {
+            let address = operand_value(mode, operand)?;
+            if operand.size == 10 && !integer {
+                return fpu_load_extended(block, address);
+            }
+            let temp = block.temp(operand.size as usize * 8);
+            block.load(temp.clone(), address, array("mem", MEM_SIZE));
+            match (integer, operand.size) {
+                (true, _) => Expr::itof(64, temp.into()),
+                (false, 4) => Expr::fext(64, temp.into()),
+                (false, 8) => Ok(temp.into()),
+                _ => bail!("invalid x87 operand size {}", operand.size)
+            }
+        },
+        _ => Err("invalid x87 operand".into())
+    }
+}
+
+
+/// Stores a binary64 value in an x87 operand, performing any stores as
+/// necessary.
+pub fn fpu_operand_store(
+    mode: Mode,
+    block: &mut Block,
+    operand: &cs_x86_op,
+    value: Expression
+) -> Result<()> {
+    match operand.type_ {
+        x86_op_type::X86_OP_REG => {
+            block.assign(fpu_st(fpu_register_index(*operand.reg())?), value);
+            Ok(())
+        },
+        x86_op_type::X86_OP_MEM => {
+            let address = operand_value(mode, operand)?;
+            match operand.size {
+                4 => block.store(array("mem", MEM_SIZE), address, Expr::fext(32, value)?),
+                8 => block.store(array("mem", MEM_SIZE), address, value),
+                10 => fpu_store_extended(block, address, value)?,
+                _ => bail!("invalid x87 operand size {}", operand.size)
+            }
+            Ok(())
+        },
+        _ => Err("invalid x87 operand".into())
+    }
+}
+
+
+/// Convert a binary64 value to a signed integer, rounding as selected by the
+/// rounding control field of the x87 control word.
+fn fpu_round_integer(block: &mut Block, value: Expression, bits: usize) -> Result<Expression> {
+    let truncated = block.temp(bits);
+    block.assign(truncated.clone(), Expr::ftoi(bits, value.clone())?);
+
+    // The part of value removed by truncation
+    let fraction = block.temp(64);
+    block.assign(
+        fraction.clone(),
+        Expr::fsub(value, Expr::itof(64, truncated.clone().into())?)?
+    );
+    let fraction: Expression = fraction.into();
+
+    let zero = expr_const(0, 64);
+    let half = expr_const(0x3fe0_0000_0000_0000, 64);
+    let negative_half = expr_const(0xbfe0_0000_0000_0000, 64);
+    let odd = Expr::trun(1, truncated.clone().into())?;
+
+    let rounding = Expr::shr(expr_scalar("fpu_cw", 16), expr_const(10, 16))?;
+    let rounding = Expr::trun(2, rounding)?;
+    let nearest = Expr::cmpeq(rounding.clone(), expr_const(0, 2))?;
+    let down = Expr::cmpeq(rounding.clone(), expr_const(1, 2))?;
+    let up = Expr::cmpeq(rounding, expr_const(2, 2))?;
+
+    // Round to nearest, with ties to even
+    let nearest_increment = Expr::or(
+        Expr::fcmplt(half.clone(), fraction.clone())?,
+        Expr::and(Expr::fcmpeq(fraction.clone(), half)?, odd.clone())?
+    )?;
+    let nearest_decrement = Expr::or(
+        Expr::fcmplt(fraction.clone(), negative_half.clone())?,
+        Expr::and(Expr::fcmpeq(fraction.clone(), negative_half)?, odd)?
+    )?;
+
+    let increment = Expr::or(
+        Expr::and(nearest.clone(), nearest_increment)?,
+        Expr::and(up, Expr::fcmplt(zero.clone(), fraction.clone())?)?
+    )?;
+    let decrement = Expr::or(
+        Expr::and(nearest, nearest_decrement)?,
+        Expr::and(down, Expr::fcmplt(fraction, zero)?)?
+    )?;
+
+    Expr::sub(
+        Expr::add(truncated.into(), Expr::zext(bits, increment)?)?,
+        Expr::zext(bits, decrement)?
+    )
+}
+
+
+/// Returns the less-than, equal and unordered results of comparing two
+/// binary64 values.
+fn fpu_compare(lhs: Expression, rhs: Expression)
+-> Result<(Expression, Expression, Expression)> {
+    Ok((
+        Expr::fcmplt(lhs.clone(), rhs.clone())?,
+        Expr::fcmpeq(lhs.clone(), rhs.clone())?,
+        Expr::fcmpuo(lhs, rhs)?
+    ))
+}
+
+
+/// Returns the x87 status word, with the condition codes and `TOP`.
+fn fpu_status_word() -> Result<Expression> {
+    let field = |name: &str, bits: usize, offset: u64| -> Result<Expression> {
+        Expr::shl(Expr::zext(16, expr_scalar(name, bits))?, expr_const(offset, 16))
+    };
+    Expr::or(
+        Expr::or(field("C0", 1, 8)?, field("C1", 1, 9)?)?,
+        Expr::or(
+            Expr::or(field("C2", 1, 10)?, field("TOP", 3, 11)?)?,
+            field("C3", 1, 14)?
+        )?
+    )
+}
+
+
+/// Lifts an x87 arithmetic instruction.
+///
+/// The operation is applied to the destination and source operands, in that
+/// order unless `reverse` is set, and the register stack is popped afterwards
+/// if `pop` is set. Memory operands are integers if `integer` is set.
+fn fpu_arithmetic<F>(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    op: F,
+    reverse: bool,
+    pop: bool,
+    integer: bool
+) -> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
     let detail = try!(details(instruction));
 
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
 
-    // create our head block
-    let (head_index, rhs, counter) = {
-        let (head_index, rhs) = {
-            let mut head_block = control_flow_graph.new_block()?;
+        let operands = &detail.operands;
+        let (dst, src) = match detail.op_count {
+            0 => (fpu_st(1), fpu_st(0).into()),
+            1 if operands[0].type_ == x86_op_type::X86_OP_MEM =>
+                (fpu_st(0), fpu_operand_load(mode, &mut block, &operands[0], integer)?),
+            1 if pop =>
+                (fpu_st(fpu_register_index(*operands[0].reg())?), fpu_st(0).into()),
+            1 => (fpu_st(0), fpu_st(fpu_register_index(*operands[0].reg())?).into()),
+            _ => (
+                fpu_st(fpu_register_index(*operands[0].reg())?),
+                fpu_st(fpu_register_index(*operands[1].reg())?).into()
+            )
+        };
 
-            // get started
-            let rhs = operand_load(&mut head_block, &detail.operands[1])?;
+        let result = if reverse {
+            op(src, dst.clone().into())?
+        }
+        else {
+            op(dst.clone().into(), src)?
+        };
+        block.assign(dst, result);
 
-            (head_block.index(), rhs)
+        if pop {
+            fpu_pop(&mut block)?;
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an x87 comparison which sets the condition codes, and then pops the
+/// register stack `pops` times. Memory operands are integers if `integer` is
+/// set.
+fn fpu_compare_condition_codes(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    pops: usize,
+    integer: bool
+) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let rhs = match detail.op_count {
+            0 => fpu_st(1).into(),
+            count => fpu_operand_load(
+                mode,
+                &mut block,
+                &detail.operands[count as usize - 1],
+                integer
+            )?
         };
 
-        let counter = {
-            control_flow_graph.temp(rhs.bits())
+        let (less, equal, unordered) = fpu_compare(fpu_st(0).into(), rhs)?;
+        block.assign(scalar("C0", 1), Expr::or(less, unordered.clone())?);
+        block.assign(scalar("C1", 1), expr_const(0, 1));
+        block.assign(scalar("C2", 1), unordered.clone());
+        block.assign(scalar("C3", 1), Expr::or(equal, unordered)?);
+
+        for _ in 0..pops {
+            fpu_pop(&mut block)?;
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an x87 comparison which sets `ZF`, `PF` and `CF`, and then pops the
+/// register stack if `pop` is set.
+fn fpu_compare_flags(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    pop: bool
+) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let rhs = match detail.op_count {
+            0 => fpu_st(1).into(),
+            count => fpu_operand_load(
+                mode,
+                &mut block,
+                &detail.operands[count as usize - 1],
+                false
+            )?
         };
 
-        let mut head_block = control_flow_graph.block_mut(head_index)
-                                               .ok_or("Could not find block")?;
+        let (less, equal, unordered) = fpu_compare(fpu_st(0).into(), rhs)?;
+        block.assign(scalar("ZF", 1), Expr::or(equal, unordered.clone())?);
+        block.assign(scalar("PF", 1), unordered.clone());
+        block.assign(scalar("CF", 1), Expr::or(less, unordered)?);
+        block.assign(scalar("OF", 1), expr_const(0, 1));
+        block.assign(scalar("SF", 1), expr_const(0, 1));
 
-        // This is the loop preamble, and we'll always execute it
-        head_block.assign(scalar("ZF", 1), expr_const(0, 1));
-        head_block.assign(counter.clone(), expr_const(0, rhs.bits()));
+        if pop {
+            fpu_pop(&mut block)?;
+        }
 
-        (head_index, rhs, counter)
+        block.index()
     };
 
-    // if rhs == 0 then ZF = 1 and we are done.
-    let zero_index = {
-        let mut zero_block = control_flow_graph.new_block()?;
-        zero_block.assign(scalar("ZF", 1), expr_const(1, 1));
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-        zero_block.index()
+    Ok(())
+}
+
+
+/// Lifts an instruction which pushes a binary64 constant onto the x87
+/// register stack.
+fn fpu_load_constant(control_flow_graph: &mut ControlFlowGraph, value: u64) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        fpu_push(&mut block, expr_const(value, 64))?;
+        block.index()
     };
 
-    // The loop body checks if the bits for our counter is set.
-    let (bitfield, loop_index) = {
-        let bitfield = control_flow_graph.temp(rhs.bits());
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-        let mut loop_block = control_flow_graph.new_block()?;
-        loop_block.assign(bitfield.clone(),
-            Expr::and(
-                Expr::shr(rhs.clone(), counter.clone().into())?,
-                expr_const(1, rhs.bits())
-            )?
-        );
+    Ok(())
+}
 
-        (bitfield, loop_block.index())
+
+/// Lifts an x87 instruction which stores st0 to an integer in memory, and
+/// pops the register stack if `pop` is set. The value is truncated if
+/// `truncate` is set, and otherwise rounded by the control word.
+fn fpu_store_integer(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    pop: bool,
+    truncate: bool
+) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let bits = detail.operands[0].size as usize * 8;
+        let value = if truncate {
+            Expr::ftoi(bits, fpu_st(0).into())?
+        }
+        else {
+            fpu_round_integer(&mut block, fpu_st(0).into(), bits)?
+        };
+        operand_store(mode, &mut block, &detail.operands[0], value)?;
+
+        if pop {
+            fpu_pop(&mut block)?;
+        }
+
+        block.index()
     };
 
-    // While our bitfield == 0, we increment counter and keep looping
-    let iterate_index = {
-        let mut iterate_block = control_flow_graph.new_block()?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-        iterate_block.assign(counter.clone(), Expr::add(counter.clone().into(), expr_const(1, counter.bits()))?);
+    Ok(())
+}
 
-        iterate_block.index()
+
+/// Lifts an x87 instruction which replaces st0 with `op(st0)`.
+fn fpu_unary<F>(control_flow_graph: &mut ControlFlowGraph, op: F) -> Result<()>
+where F: Fn(Expression) -> Result<Expression> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        block.assign(fpu_st(0), op(fpu_st(0).into())?);
+        block.index()
     };
 
-    // In our terminating block, we set the result to counter
-    let terminating_index = {
-        let mut terminating_block = control_flow_graph.new_block()?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-        operand_store(&mut terminating_block, &detail.operands[0], counter.into())?;
+    Ok(())
+}
 
-        terminating_block.index()
+
+
+/// Returns true if any operand of this instruction is an xmm register.
+///
+/// SSE instructions use 0xf2 and 0xf3 as mandatory prefixes, which capstone
+/// reports as rep prefixes, so these instructions must not be repeated.
+pub fn is_sse(instruction: &capstone::Instr) -> Result<bool> {
+    let detail = try!(details(instruction));
+    Ok(detail.operands[0..detail.op_count as usize].iter().any(|operand| {
+        operand.type_ == x86_op_type::X86_OP_REG &&
+        get_register(Mode::Amd64, *operand.reg())
+            .map(|register| register.bits() == 128)
+            .unwrap_or(false)
+    }))
+}
+
+
+/// Truncates or zero-extends `value` to `bits` bits.
+fn resize(value: Expression, bits: usize) -> Result<Expression> {
+    if value.bits() > bits {
+        Expr::trun(bits, value)
+    }
+    else if value.bits() < bits {
+        Expr::zext(bits, value)
+    }
+    else {
+        Ok(value)
+    }
+}
+
+
+/// Splits a vector into lanes of `bits` bits, lowest lane first.
+fn vector_lanes(vector: Expression, bits: usize) -> Result<Vec<Expression>> {
+    let mut lanes = Vec::new();
+    for i in 0..(vector.bits() / bits) {
+        let lane = if i == 0 {
+            vector.clone()
+        }
+        else {
+            Expr::shr(vector.clone(), expr_const((i * bits) as u64, vector.bits()))?
+        };
+        lanes.push(Expr::trun(bits, lane)?);
+    }
+    Ok(lanes)
+}
+
+
+/// Joins lanes, lowest lane first, into one vector.
+fn vector_join(lanes: Vec<Expression>) -> Result<Expression> {
+    let bits: usize = lanes.iter().map(|lane| lane.bits()).sum();
+    let mut vector: Option<Expression> = None;
+    let mut offset = 0;
+    for lane in lanes {
+        let lane_bits = lane.bits();
+        let lane = Expr::zext(bits, lane)?;
+        let lane = if offset > 0 {
+            Expr::shl(lane, expr_const(offset as u64, bits))?
+        }
+        else {
+            lane
+        };
+        vector = Some(match vector {
+            Some(vector) => Expr::or(vector, lane)?,
+            None => lane
+        });
+        offset += lane_bits;
+    }
+    vector.ok_or("Cannot join a vector with no lanes".into())
+}
+
+
+/// Lifts an SSE instruction which replaces its first operand with `op` of
+/// both operands, applied to the whole register.
+fn sse_bitwise<F>(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, op: F)
+    -> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        operand_store(mode, &mut block, &detail.operands[0], op(lhs, rhs)?)?;
+
+        block.index()
     };
 
-    control_flow_graph.conditional_edge(
-        head_index,
-        zero_index,
-        Expr::cmpeq(rhs.clone(), expr_const(0, rhs.bits()))?
-    )?;
-    control_flow_graph.conditional_edge(
-        head_index,
-        loop_index,
-        Expr::cmpneq(rhs.clone(), expr_const(0, rhs.bits()))?
-    )?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an SSE instruction which replaces each `bits`-bit lane of its first
+/// operand with `op` of the corresponding lanes of both operands.
+fn sse_lanewise<F>(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    bits: usize,
+    op: F
+) -> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let mut lanes = Vec::new();
+        for (lhs, rhs) in vector_lanes(lhs, bits)?.into_iter().zip(vector_lanes(rhs, bits)?) {
+            lanes.push(op(lhs, rhs)?);
+        }
+
+        operand_store(mode, &mut block, &detail.operands[0], vector_join(lanes)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an SSE shift of each `bits`-bit lane. The count is an immediate or
+/// the low 64 bits of an operand, and counts of `bits` or more clear each
+/// lane, or fill it with its sign bit when `arithmetic` is set.
+fn sse_shift(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    bits: usize,
+    left: bool,
+    arithmetic: bool
+) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let vector = operand_load(mode, &mut block, &detail.operands[0])?;
+        let count = if detail.operands[1].type_ == x86_op_type::X86_OP_IMM {
+            expr_const(detail.operands[1].imm() as u8 as u64, 64)
+        }
+        else {
+            resize(operand_load(mode, &mut block, &detail.operands[1])?, 64)?
+        };
+
+        let limit = if arithmetic { bits - 1 } else { bits };
+        let count = select(
+            Expr::cmpltu(count.clone(), expr_const(bits as u64, 64))?,
+            Expr::trun(bits, count)?,
+            expr_const(limit as u64, bits)
+        )?;
+
+        let mut lanes = Vec::new();
+        for lane in vector_lanes(vector, bits)? {
+            lanes.push(if left {
+                Expr::shl(lane, count.clone())?
+            }
+            else if arithmetic {
+                let sign = Expr::trun(1, Expr::shr(lane.clone(), expr_const(bits as u64 - 1, bits))?)?;
+                let fill = Expr::xor(Expr::shr(ones(bits), count.clone())?, ones(bits))?;
+                Expr::or(
+                    Expr::shr(lane, count.clone())?,
+                    Expr::and(Expr::sext(bits, sign)?, fill)?
+                )?
+            }
+            else {
+                Expr::shr(lane, count.clone())?
+            });
+        }
+
+        operand_store(mode, &mut block, &detail.operands[0], vector_join(lanes)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts a move of the low `bits` bits of a register or memory operand.
+/// Moves into an xmm register zero its upper bits.
+fn sse_move_low(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize)
+    -> Result<()> {
+
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let value = resize(src, bits)?;
+
+        let dst = &detail.operands[0];
+        let value = if dst.type_ == x86_op_type::X86_OP_REG {
+            resize(value, get_register(mode, *dst.reg())?.bits())?
+        }
+        else {
+            value
+        };
+
+        operand_store(mode, &mut block, dst, value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts movss and the SSE form of movsd, which move a `bits`-bit scalar
+/// float. Moves between xmm registers keep the upper bits of the destination,
+/// and loads from memory zero them.
+fn sse_move_scalar(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, bits: usize)
+    -> Result<()> {
+
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let dst = &detail.operands[0];
+        let src = &detail.operands[1];
+
+        let value = resize(operand_load(mode, &mut block, src)?, bits)?;
+
+        if dst.type_ == x86_op_type::X86_OP_REG && src.type_ == x86_op_type::X86_OP_REG {
+            let register = get_register(mode, *dst.reg())?;
+            let mask = Expr::shl(ones(128), expr_const(bits as u64, 128))?;
+            let upper = Expr::and(register.get()?, mask)?;
+            register.set(&mut block, Expr::or(upper, Expr::zext(128, value)?)?)?;
+        }
+        else if dst.type_ == x86_op_type::X86_OP_REG {
+            operand_store(mode, &mut block, dst, Expr::zext(128, value)?)?;
+        }
+        else {
+            operand_store(mode, &mut block, dst, value)?;
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Lifts an SSE unpack, which interleaves the `bits`-bit lanes from the low,
+/// or `high`, halves of both operands.
+fn sse_unpack(
+    mode: Mode,
+    control_flow_graph: &mut ControlFlowGraph,
+    instruction: &capstone::Instr,
+    bits: usize,
+    high: bool
+) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = vector_lanes(operand_load(mode, &mut block, &detail.operands[0])?, bits)?;
+        let rhs = vector_lanes(operand_load(mode, &mut block, &detail.operands[1])?, bits)?;
+
+        let half = lhs.len() / 2;
+        let start = if high { half } else { 0 };
+
+        let mut lanes = Vec::new();
+        for i in start..(start + half) {
+            lanes.push(lhs[i].clone());
+            lanes.push(rhs[i].clone());
+        }
+
+        operand_store(mode, &mut block, &detail.operands[0], vector_join(lanes)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Loads the base and bit offset of a bt, btc, btr or bts instruction.
+///
+/// A register offset into a memory operand addresses a bit string, and can
+/// select a bit outside of the operand, so we move the address to the word
+/// holding the bit. The address of that word is returned for the store of a
+/// btc, btr or bts. Other offsets are taken modulo the operand size.
+fn bit_operand(mode: Mode, block: &mut Block, base: &cs_x86_op, offset: &cs_x86_op)
+    -> Result<(Expression, Expression, Option<Expression>)> {
+
+    let bits = base.size as usize * 8;
+    let offset_value = operand_load(mode, block, offset)?;
+
+    if base.type_ == x86_op_type::X86_OP_MEM && offset.type_ == x86_op_type::X86_OP_REG {
+        let offset_value = if offset_value.bits() < mode.bits() {
+            Expr::sext(mode.bits(), offset_value)?
+        }
+        else {
+            offset_value
+        };
+        let bit = Expr::and(offset_value.clone(), expr_const(bits as u64 - 1, mode.bits()))?;
+        let displacement = Expr::divs(
+            Expr::sub(offset_value, bit.clone())?,
+            expr_const(8, mode.bits())
+        )?;
+        let address = Expr::add(operand_value(mode, base)?, displacement)?;
+
+        let temp = block.temp(bits);
+        block.load(temp.clone(), address.clone(), array("mem", MEM_SIZE));
+
+        return Ok((temp.into(), resize(bit, bits)?, Some(address)));
+    }
+
+    let base_value = operand_load(mode, block, base)?;
+
+    let bit = Expr::and(resize(offset_value, bits)?, expr_const(bits as u64 - 1, bits))?;
+
+    Ok((base_value, bit, None))
+}
+
+
+/// Stores the result of a btc, btr or bts instruction, at the address found by
+/// `bit_operand` when there is one.
+fn bit_store(mode: Mode, block: &mut Block, base: &cs_x86_op, address: Option<Expression>, value: Expression)
+    -> Result<()> {
+
+    match address {
+        Some(address) => {
+            block.store(array("mem", MEM_SIZE), address, value);
+            Ok(())
+        },
+        None => operand_store(mode, block, base, value)
+    }
+}
+
+
+/// Reverses the order of the bytes in `value`.
+fn byte_swap(value: Expression) -> Result<Expression> {
+    let bits = value.bits();
+
+    // move each byte to the opposite end of the value
+    let bytes = bits as u64 / 8;
+    let mut expr = expr_const(0, bits);
+    for i in 0..bytes {
+        let byte = Expr::and(
+            Expr::shr(value.clone(), expr_const(i * 8, bits))?,
+            expr_const(0xff, bits)
+        )?;
+        let byte = Expr::shl(byte, expr_const((bytes - 1 - i) * 8, bits))?;
+        expr = Expr::or(expr, byte)?;
+    }
+
+    Ok(expr)
+}
+
+
+/// Builds the low `bits` bits of eflags from the flags we model.
+fn flags_value(bits: usize) -> Result<Expression> {
+    // bit 1 of eflags is always set
+    let mut value = expr_const(2, bits);
+    for &(flag, bit) in EFLAGS.iter() {
+        if bit < bits as u64 {
+            let flag = Expr::zext(bits, expr_scalar(flag, 1))?;
+            value = Expr::or(value, Expr::shl(flag, expr_const(bit, bits))?)?;
+        }
+    }
+    Ok(value)
+}
+
+
+/// Sets the flags we model from `value`, which holds the low bits of eflags.
+/// IF is left alone, as it can't be changed by user code.
+fn set_flags(block: &mut Block, value: Expression) -> Result<()> {
+    let bits = value.bits();
+    for &(flag, bit) in EFLAGS.iter() {
+        if flag != "IF" && bit < bits as u64 {
+            let expr = Expr::shr(value.clone(), expr_const(bit, bits))?;
+            block.assign(scalar(flag, 1), Expr::trun(1, expr)?);
+        }
+    }
+    Ok(())
+}
+
+
+/// The size of eflags pushed or popped by pushf or popf.
+fn flags_bits(instruction: &capstone::Instr) -> usize {
+    match instruction.id {
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_PUSHF) |
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_POPF) => 16,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_PUSHFQ) |
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_POPFQ) => 64,
+        _ => 32
+    }
+}
+
+
+/// The registers pushed by pusha or pushad, in the order they are pushed.
+fn push_all_registers(instruction: &capstone::Instr) -> [x86_reg; 8] {
+    match instruction.id {
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_PUSHAW) |
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_POPAW) => [
+            x86_reg::X86_REG_AX, x86_reg::X86_REG_CX, x86_reg::X86_REG_DX, x86_reg::X86_REG_BX,
+            x86_reg::X86_REG_SP, x86_reg::X86_REG_BP, x86_reg::X86_REG_SI, x86_reg::X86_REG_DI
+        ],
+        _ => [
+            x86_reg::X86_REG_EAX, x86_reg::X86_REG_ECX, x86_reg::X86_REG_EDX, x86_reg::X86_REG_EBX,
+            x86_reg::X86_REG_ESP, x86_reg::X86_REG_EBP, x86_reg::X86_REG_ESI, x86_reg::X86_REG_EDI
+        ]
+    }
+}
+
+
+/// Raises `expr` to the platform.
+fn raise(control_flow_graph: &mut ControlFlowGraph, expr: Expression) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.raise(expr);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Adjusts ax after an addition or subtraction of unpacked BCD values, for aaa
+/// and aas.
+fn ascii_adjust<F>(mode: Mode, control_flow_graph: &mut ControlFlowGraph, op: F)
+    -> Result<()> where F: Fn(Expression, Expression) -> Result<Expression> {
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let al = get_register(mode, x86_reg::X86_REG_AL)?;
+        let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+
+        // adjust if the low digit is not a decimal digit, or carried
+        let adjust = block.temp(1);
+        block.assign(adjust.clone(), Expr::or(
+            Expr::cmpltu(expr_const(9, 8), Expr::and(al.get()?, expr_const(0xf, 8))?)?,
+            expr_scalar("AF", 1)
+        )?);
+
+        let result = select(adjust.clone().into(), op(ax.get()?, expr_const(0x106, 16))?, ax.get()?)?;
+        ax.set(&mut block, Expr::and(result, expr_const(0xff0f, 16))?)?;
+
+        block.assign(scalar("AF", 1), adjust.clone().into());
+        block.assign(scalar("CF", 1), adjust.into());
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Counts the leading, or trailing, zero bits of the source for lzcnt and
+/// tzcnt.
+fn zero_count(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, leading: bool)
+    -> Result<()> {
+
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let bits = src.bits();
+
+        // We visit the bits towards the end we count from, so the last set
+        // bit we visit gives the count.
+        let count = block.temp(bits);
+        block.assign(count.clone(), expr_const(bits as u64, bits));
+        for i in 0..bits {
+            let (bit, zeros) = if leading { (i, bits - 1 - i) } else { (bits - 1 - i, bits - 1 - i) };
+            let set = Expr::trun(1, Expr::shr(src.clone(), expr_const(bit as u64, bits))?)?;
+            block.assign(count.clone(), select(set, expr_const(zeros as u64, bits), count.clone().into())?);
+        }
+
+        block.assign(scalar("CF", 1), Expr::cmpeq(src, expr_const(0, bits))?);
+        set_zf(&mut block, count.clone().into())?;
+
+        operand_store(mode, &mut block, &detail.operands[0], count.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/// Rotates through the carry flag for rcl and rcr.
+fn rotate_carry(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr, left: bool)
+    -> Result<()> {
+
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let count = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        // We rotate CF:lhs, which is one bit wider than lhs
+        let bits = lhs.bits();
+        let wide = bits + 1;
+
+        let mask = if bits == 64 { 0x3f } else { 0x1f };
+        let count = Expr::and(count.clone(), expr_const(mask, count.bits()))?;
+        let count = Expr::modu(resize(count, wide)?, expr_const(wide as u64, wide))?;
+        let shift = block.temp(wide);
+        block.assign(shift.clone(), count);
+
+        let value = block.temp(wide);
+        block.assign(value.clone(), Expr::or(
+            Expr::shl(Expr::zext(wide, expr_scalar("CF", 1))?, expr_const(bits as u64, wide))?,
+            Expr::zext(wide, lhs)?
+        )?);
+
+        let complement = Expr::sub(expr_const(wide as u64, wide), shift.clone().into())?;
+        let rotated = if left {
+            Expr::or(
+                Expr::shl(value.clone().into(), shift.clone().into())?,
+                Expr::shr(value.into(), complement)?
+            )?
+        }
+        else {
+            Expr::or(
+                Expr::shr(value.clone().into(), shift.clone().into())?,
+                Expr::shl(value.into(), complement)?
+            )?
+        };
+        let result = block.temp(wide);
+        block.assign(result.clone(), rotated);
+
+        let bit = |bit: usize| -> Result<Expression> {
+            Expr::trun(1, Expr::shr(result.clone().into(), expr_const(bit as u64, wide))?)
+        };
+
+        // OF is only defined for a count of 1, where it is set if the sign
+        // changed
+        let carry = bit(bits)?;
+        let overflow = if left {
+            Expr::xor(bit(bits - 1)?, carry.clone())?
+        }
+        else {
+            Expr::xor(bit(bits - 1)?, bit(bits - 2)?)?
+        };
+
+        // flags are unaffected when the count is 0
+        let unchanged = block.temp(1);
+        block.assign(unchanged.clone(), Expr::cmpeq(shift.into(), expr_const(0, wide))?);
+        block.assign(scalar("OF", 1), select(unchanged.clone().into(), expr_scalar("OF", 1), overflow)?);
+        block.assign(scalar("CF", 1), select(unchanged.into(), expr_scalar("CF", 1), carry)?);
+
+        operand_store(mode, &mut block, &detail.operands[0], Expr::trun(bits, result.into())?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/*
+    AAA adjusts the sum of two unpacked BCD values in al, carrying into ah.
+*/
+pub fn aaa(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    ascii_adjust(mode, control_flow_graph, Expr::add)
+}
+
+
+
+/*
+    AAD converts the unpacked BCD value in ax to binary in al, before a
+    division. The base is 10 unless another is given.
+*/
+pub fn aad(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let base = if detail.op_count > 0 {
+            resize(operand_load(mode, &mut block, &detail.operands[0])?, 8)?
+        }
+        else {
+            expr_const(10, 8)
+        };
+
+        let al = get_register(mode, x86_reg::X86_REG_AL)?;
+        let ah = get_register(mode, x86_reg::X86_REG_AH)?;
+
+        let result = block.temp(8);
+        block.assign(result.clone(), Expr::add(al.get()?, Expr::mul(ah.get()?, base)?)?);
+
+        let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+        ax.set(&mut block, Expr::zext(16, result.clone().into())?)?;
+
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_pf(&mut block, result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    AAM converts the product in al to unpacked BCD in ax, after a
+    multiplication. The base is 10 unless another is given.
+*/
+pub fn aam(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let base = if detail.op_count > 0 {
+            resize(operand_load(mode, &mut block, &detail.operands[0])?, 8)?
+        }
+        else {
+            expr_const(10, 8)
+        };
+
+        let al = get_register(mode, x86_reg::X86_REG_AL)?;
+        let ah = get_register(mode, x86_reg::X86_REG_AH)?;
+
+        let value = block.temp(8);
+        block.assign(value.clone(), al.get()?);
+
+        let result = block.temp(8);
+        block.assign(result.clone(), Expr::modu(value.clone().into(), base.clone())?);
+
+        ah.set(&mut block, Expr::divu(value.into(), base)?)?;
+        al.set(&mut block, result.clone().into())?;
+
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_pf(&mut block, result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    AAS adjusts the difference of two unpacked BCD values in al, borrowing
+    from ah.
+*/
+pub fn aas(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    ascii_adjust(mode, control_flow_graph, Expr::sub)
+}
+
+
+
+pub fn adc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // create a block for this instruction
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // get operands
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let result = block.temp(lhs.bits());
+
+        // perform addition
+        let addition = Expr::add(lhs.clone(), rhs.clone())?;
+        let zext_cf = Expr::zext(lhs.bits(), expr_scalar("CF", 1))?;
+        block.assign(result.clone(), Expr::add(addition, zext_cf)?);
+
+        // calculate flags
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_of(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
+        set_cf(&mut block, result.clone().into(), lhs.clone())?;
+
+        // store result
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+pub fn add(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // get operands
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let result = block.temp(lhs.bits());
+
+        // perform addition
+        block.assign(result.clone(), Expr::add(lhs.clone(), rhs.clone())?);
+
+        // calculate flags
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_of(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
+        set_cf(&mut block, result.clone().into(), lhs.clone())?;
+
+        // store result
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn and(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // get operands
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        if rhs.bits() != lhs.bits() {
+            rhs = Expr::sext(lhs.bits(), rhs)?;
+        }
+
+        let result = block.temp(lhs.bits());
+
+        // perform addition
+        block.assign(result.clone(), Expr::and(lhs.clone(), rhs.clone())?);
+
+        // calculate flags
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        block.assign(scalar("CF", 1), expr_const(0, 1));
+        block.assign(scalar("OF", 1), expr_const(0, 1));
+
+        // store result
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn bswap(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // get operands
+        let src = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        let bits = src.bits();
+        if bits != 32 && bits != 64 {
+            bail!("Invalid bit-width for bswap arg {} at 0x{:x}", src, instruction.address);
+        }
+
+        operand_store(mode, &mut block, &detail.operands[0], byte_swap(src)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/*
+    BSF scans the bits in the second word or doubleword operand starting with
+    bit 0. The ZF flag is cleared if the bits are all 0; otherwise, the ZF flag
+    is set and the destination register is loaded with the bit index of the
+    first set bit.
+*/
+pub fn bsf(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+
+    // create our head block
+    let (head_index, rhs, counter) = {
+        let (head_index, rhs) = {
+            let mut head_block = control_flow_graph.new_block()?;
+
+            // get started
+            let rhs = operand_load(mode, &mut head_block, &detail.operands[1])?;
+
+            (head_block.index(), rhs)
+        };
+
+        let counter = {
+            control_flow_graph.temp(rhs.bits())
+        };
+
+        let mut head_block = control_flow_graph.block_mut(head_index)
+                                               .ok_or("Could not find block")?;
+
+        // This is the loop preamble, and we'll always execute it
+        head_block.assign(scalar("ZF", 1), expr_const(0, 1));
+        head_block.assign(counter.clone(), expr_const(0, rhs.bits()));
+
+        (head_index, rhs, counter)
+    };
+
+    // if rhs == 0 then ZF = 1 and we are done.
+    let zero_index = {
+        let mut zero_block = control_flow_graph.new_block()?;
+        zero_block.assign(scalar("ZF", 1), expr_const(1, 1));
+
+        zero_block.index()
+    };
+
+    // The loop body checks if the bits for our counter is set.
+    let (bitfield, loop_index) = {
+        let bitfield = control_flow_graph.temp(rhs.bits());
+
+        let mut loop_block = control_flow_graph.new_block()?;
+        loop_block.assign(bitfield.clone(),
+            Expr::and(
+                Expr::shr(rhs.clone(), counter.clone().into())?,
+                expr_const(1, rhs.bits())
+            )?
+        );
+
+        (bitfield, loop_block.index())
+    };
+
+    // While our bitfield == 0, we increment counter and keep looping
+    let iterate_index = {
+        let mut iterate_block = control_flow_graph.new_block()?;
+
+        iterate_block.assign(counter.clone(), Expr::add(counter.clone().into(), expr_const(1, counter.bits()))?);
+
+        iterate_block.index()
+    };
+
+    // In our terminating block, we set the result to counter
+    let terminating_index = {
+        let mut terminating_block = control_flow_graph.new_block()?;
+
+        operand_store(mode, &mut terminating_block, &detail.operands[0], counter.into())?;
+
+        terminating_block.index()
+    };
+
+    control_flow_graph.conditional_edge(
+        head_index,
+        zero_index,
+        Expr::cmpeq(rhs.clone(), expr_const(0, rhs.bits()))?
+    )?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        loop_index,
+        Expr::cmpneq(rhs.clone(), expr_const(0, rhs.bits()))?
+    )?;
+
+    control_flow_graph.unconditional_edge(zero_index, terminating_index)?;
+
+    control_flow_graph.conditional_edge(
+        loop_index,
+        iterate_index,
+        Expr::cmpeq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
+    )?;
+    control_flow_graph.conditional_edge(
+        loop_index,
+        terminating_index,
+        Expr::cmpneq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
+    )?;
+    control_flow_graph.unconditional_edge(iterate_index, loop_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(terminating_index)?;
+
+    Ok(())
+}
+
+
+/*
+    BSR scans the bits in the second word or doubleword operand from the most
+    significant bit to the least significant bit. The ZF flag is cleared if the
+    bits are all 0; otherwise, ZF is set and the destination register is loaded
+    with the bit index of the first set bit found when scanning in the reverse
+    direction.
+*/
+pub fn bsr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let (head_index, rhs, counter) = {
+        let (head_index, rhs) = {
+            let mut head_block = control_flow_graph.new_block()?;
+
+            // get started
+            let rhs = operand_load(mode, &mut head_block, &detail.operands[1])?;
+
+            (head_block.index(), rhs)
+        };
+
+        let counter = {
+            control_flow_graph.temp(rhs.bits())
+        };
+
+        let mut head_block = control_flow_graph.block_mut(head_index)
+                                               .ok_or("Could not find block")?;
+
+        // This is the loop preamble, and we'll always execute it
+        head_block.assign(scalar("ZF", 1), expr_const(0, 1));
+        head_block.assign(counter.clone(), expr_const((rhs.bits() - 1) as u64, rhs.bits()));
+
+        (head_index, rhs, counter)
+    };
+
+
+    // if rhs == 0 then ZF = 1 and we are done.
+    let zero_index = {
+        let mut zero_block = control_flow_graph.new_block()?;
+        zero_block.assign(scalar("ZF", 1), expr_const(1, 1));
+
+        zero_block.index()
+    };
+
+    // The loop body checks if the bits for our counter is set
+    let (bitfield, loop_index) = {
+        let bitfield = control_flow_graph.temp(rhs.bits());
+
+        let mut loop_block = control_flow_graph.new_block()?;
+        loop_block.assign(bitfield.clone(),
+            Expr::and(
+                Expr::shr(rhs.clone(), counter.clone().into())?,
+                expr_const(1, rhs.bits())
+            )?
+        );
+
+        (bitfield, loop_block.index())
+    };
+
+    // While our bitfield == 0, we decrement counter and keep looping
+    let iterate_index = {
+        let mut iterate_block = control_flow_graph.new_block()?;
+
+        iterate_block.assign(counter.clone(), Expr::sub(counter.clone().into(), expr_const(1, counter.bits()))?);
+
+        iterate_block.index()
+    };
+
+    // In our terminating block, we set the result to counter
+    let terminating_index = {
+        let mut terminating_block = control_flow_graph.new_block()?;
+
+        operand_store(mode, &mut terminating_block, &detail.operands[0], counter.into())?;
+
+        terminating_block.index()
+    };
+
+    control_flow_graph.conditional_edge(
+        head_index,
+        zero_index,
+        Expr::cmpeq(rhs.clone(), expr_const(0, rhs.bits()))?
+    )?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        loop_index,
+        Expr::cmpneq(rhs.clone(), expr_const(0, rhs.bits()))?
+    )?;
+
+    control_flow_graph.unconditional_edge(zero_index, terminating_index)?;
+
+    control_flow_graph.conditional_edge(
+        loop_index,
+        iterate_index,
+        Expr::cmpeq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
+    )?;
+    control_flow_graph.conditional_edge(
+        loop_index,
+        terminating_index,
+        Expr::cmpneq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
+    )?;
+    control_flow_graph.unconditional_edge(iterate_index, loop_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(terminating_index)?;
+
+    Ok(())
+}
+
+
+/*
+    BT saves the value of the bit indicated by the base (first operand) and the
+    bit offset (second operand) into the carry flag.
+
+    CF ← BIT[LeftSRC, RightSRC];
+
+    0F A3 BT r/m16,r16 3/12 Save bit in carry flag
+    0F A3 BT r/m32,r32 3/12 Save bit in carry flag
+    0F BA /4 ib BT r/m16,imm8 3/6 Save bit in carry flag
+    0F BA /4 ib BT r/m32,imm8 3/6 Save bit in carry flag
+*/
+pub fn bt(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // create our head block
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (base, bit, _) = bit_operand(mode, &mut block, &detail.operands[0], &detail.operands[1])?;
+
+        // this handles the assign to CF
+        let mask = Expr::shl(expr_const(1, base.bits()), bit)?;
+        let expr = Expr::cmpneq(Expr::and(base.clone(), mask.clone())?, expr_const(0, base.bits()))?;
+        block.assign(scalar("CF", 1), expr);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/*
+    BTC saves the value of the bit indicated by the base (first operand) and the
+    bit offset (second operand) into the carry flag and then complements the
+    bit.
+
+    CF ← BIT[LeftSRC, RightSRC];
+    BIT[LeftSRC, RightSRC] ← NOT BIT[LeftSRC, RightSRC];
+
+    0F BB BTC r/m16,r16 6/13 Save bit in carry flag and complement
+    0F BB BTC r/m32,r32 6/13 Save bit in carry flag and complement
+    0F BA /7 ib BTC r/m16,imm8 6/8 Save bit in carry flag and complement
+    0F BA /7 ib BTC r/m32,imm8 6/8 Save bit in carry flag and complement
+*/
+pub fn btc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // create our head block
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (base, bit, address) = bit_operand(mode, &mut block, &detail.operands[0], &detail.operands[1])?;
+
+        // this handles the assign to CF
+        let mask = Expr::shl(expr_const(1, base.bits()), bit)?;
+        let expr = Expr::cmpneq(Expr::and(base.clone(), mask.clone())?, expr_const(0, base.bits()))?;
+        block.assign(scalar("CF", 1), expr);
+
+        let expr = Expr::xor(base, mask)?;
+        bit_store(mode, &mut block, &detail.operands[0], address, expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/*
+    BTR saves the value of the bit indicated by the base (first operand) and the
+    bit offset (second operand) into the carry flag and then stores 0 in the
+    bit.
+
+    CF ← BIT[LeftSRC, RightSRC];
+    BIT[LeftSRC, RightSRC] ← 0;
+
+    0F B3 BTR r/m16,r16 6/13 Save bit in carry flag and reset
+    0F B3 BTR r/m32,r32 6/13 Save bit in carry flag and reset
+    0F BA /6 ib BTR r/m16,imm8 6/8 Save bit in carry flag and reset
+    0F BA /6 ib BTR r/m32,imm8 6/8 Save bit in carry flag and reset
+*/
+pub fn btr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // create our head block
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (base, bit, address) = bit_operand(mode, &mut block, &detail.operands[0], &detail.operands[1])?;
+
+        // this handles the assign to CF
+        let mask = Expr::shl(expr_const(1, base.bits()), bit)?;
+        let expr = Expr::cmpneq(Expr::and(base.clone(), mask.clone())?, expr_const(0, base.bits()))?;
+        block.assign(scalar("CF", 1), expr);
+
+        let expr = Expr::and(base.clone(), Expr::xor(mask, ones(base.bits()))?)?;
+        bit_store(mode, &mut block, &detail.operands[0], address, expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/*
+    BTS saves the value of the bit indicated by the base (first operand) and the
+    bit offset (second operand) into the carry flag and then stores 1 in the
+    bit.
+
+    CF ← BIT[LeftSRC, RightSRC];
+    BIT[LeftSRC, RightSRC] ← 1;
+
+    0F AB BTS r/m16,r16 6/13 Save bit in carry flag and set
+    0F AB BTS r/m32,r32 6/13 Save bit in carry flag and set
+    0F BA /5 ib BTS r/m16,imm8 6/8 Save bit in carry flag and set
+    0F BA /5 ib BTS r/m32,imm8 6/8 Save bit in carry flag and set
+*/
+pub fn bts(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // create our head block
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let (base, bit, address) = bit_operand(mode, &mut block, &detail.operands[0], &detail.operands[1])?;
+
+        // this handles the assign to CF
+        let mask = Expr::shl(expr_const(1, base.bits()), bit)?;
+        let expr = Expr::cmpneq(Expr::and(base.clone(), mask.clone())?, expr_const(0, base.bits()))?;
+        block.assign(scalar("CF", 1), expr);
+
+        let expr = Expr::or(base, mask)?;
+        bit_store(mode, &mut block, &detail.operands[0], address, expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn call(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // get started
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        let ret_addr = instruction.address + instruction.size as u64;
+
+        push_value(mode, &mut block, expr_const(ret_addr, mode.bits()))?;
+
+        block.brc(dst, expr_const(1, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cbw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let expr = Expr::sext(dst.bits(), src.into())?;
+
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cdq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // isolate the sign bits of ax
+        let eax = get_register(mode, x86_reg::X86_REG_EAX)?;
+        let expr = Expr::shr(eax.get()?, expr_const(31, 32))?;
+        let expr = Expr::trun(1, expr)?;
+        let expr = Expr::sext(32, expr)?;
+
+        get_register(mode, x86_reg::X86_REG_EDX)?.set(&mut block, expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cdqe(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let eax = get_register(mode, x86_reg::X86_REG_EAX)?;
+        let rax = get_register(mode, x86_reg::X86_REG_RAX)?;
+
+        rax.set(&mut block, Expr::sext(64, eax.get()?)?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn clc(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("CF", 1), expr_const(0, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cld(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("DF", 1), expr_const(0, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cli(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("IF", 1), expr_const(0, 1));
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cmc(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let expr = Expr::xor(expr_scalar("CF", 1), expr_const(1, 1))?;
+        block.assign(scalar("CF", 1), expr);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cmovcc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        // The destination is written even if the condition is false, which
+        // zero extends a 32-bit destination in 64-bit mode.
+        let condition = cc_condition(mode, &instruction)?;
+        let value = select(condition, src, dst)?;
+
+        operand_store(mode, &mut block, &detail.operands[0], value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cmp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        if rhs.bits() != lhs.bits() {
+            rhs = Expr::sext(lhs.bits(), rhs.into())?;
+        }
+
+        let expr = Expr::sub(lhs.clone(), rhs.clone())?;
+
+        set_zf(&mut block, expr.clone())?;
+        set_sf(&mut block, expr.clone())?;
+        set_of(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
+        set_cf(&mut block, expr.clone(), lhs.clone())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cmps(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    // The SSE cmpsd shares its instruction id with the string instruction
+    if is_sse(instruction)? {
+        bail!("Unhandled SSE instruction {} at 0x{:x}", instruction.mnemonic, instruction.address);
+    }
+
+    let (head_index, bits) = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let expr = Expr::sub(lhs.clone(), rhs.clone())?;
+
+        set_zf(&mut block, expr.clone())?;
+        set_sf(&mut block, expr.clone())?;
+        set_of(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
+        set_cf(&mut block, expr.clone(), lhs.clone())?;
+
+        (block.index(), lhs.bits())
+    };
+
+    string_step(control_flow_graph, head_index, (bits / 8) as u64, &[mode.si(), mode.di()])
+}
+
+
+
+pub fn cmpxchg(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let (head_index, dest, lhs, rhs) = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let dest = match rhs.bits() {
+            8 => get_register(mode, x86_reg::X86_REG_AL)?,
+            16 => get_register(mode, x86_reg::X86_REG_AX)?,
+            32 => get_register(mode, x86_reg::X86_REG_EAX)?,
+            64 => get_register(mode, x86_reg::X86_REG_RAX)?,
+            _ => bail!("can't figure out dest for xmpxchg, rhs.bits()={}", rhs.bits())
+        };
+
+        (block.index(), dest, lhs, rhs)
+    };
+
+    let taken_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("ZF", 1), expr_const(1, 1));
+        operand_store(mode, &mut block, &detail.operands[0], rhs.clone())?;
+
+        block.index()
+    };
+
+    let not_taken_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("ZF", 1), expr_const(0, 1));
+        dest.set(&mut block, lhs.clone())?;
+
+        block.index()
+    };
+
+    let tail_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let result = Expr::sub(lhs.clone(), rhs.clone())?;
+        set_sf(&mut block, result.clone())?;
+        set_of(&mut block, result.clone(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, result.clone(), lhs.clone(), rhs.clone())?;
+        set_cf(&mut block, result.clone(), lhs.clone())?;
+
+        block.index()
+    };
+
+    let condition = Expr::cmpeq(dest.get()?, lhs.clone())?;
+
+    control_flow_graph.conditional_edge(head_index, taken_index, condition.clone())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        not_taken_index,
+        Expr::cmpeq(condition.clone(), expr_const(0, 1))?
+    )?;
+    control_flow_graph.unconditional_edge(taken_index, tail_index)?;
+    control_flow_graph.unconditional_edge(not_taken_index, tail_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    CMPXCHG8B compares edx:eax with a 64-bit memory operand. If they are equal
+    ecx:ebx is stored in the operand, otherwise the operand is loaded into
+    edx:eax. CMPXCHG16B does the same with rdx:rax, rcx:rbx and 128 bits.
+*/
+pub fn cmpxchg8b(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let bits = detail.operands[0].size as usize * 8;
+    let (high, low, new_high, new_low) = match bits {
+        64 => (x86_reg::X86_REG_EDX, x86_reg::X86_REG_EAX, x86_reg::X86_REG_ECX, x86_reg::X86_REG_EBX),
+        128 => (x86_reg::X86_REG_RDX, x86_reg::X86_REG_RAX, x86_reg::X86_REG_RCX, x86_reg::X86_REG_RBX),
+        _ => bail!("can't figure out registers for cmpxchg8b, bits={}", bits)
+    };
+    let high = get_register(mode, high)?;
+    let low = get_register(mode, low)?;
+    let new_high = get_register(mode, new_high)?;
+    let new_low = get_register(mode, new_low)?;
+
+    let join = |high: &X86Register, low: &X86Register| -> Result<Expression> {
+        Expr::or(
+            Expr::shl(Expr::zext(bits, high.get()?)?, expr_const(bits as u64 / 2, bits))?,
+            Expr::zext(bits, low.get()?)?
+        )
+    };
+
+    let (head_index, dest) = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let dest = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        (block.index(), dest)
+    };
+
+    let taken_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("ZF", 1), expr_const(1, 1));
+        operand_store(mode, &mut block, &detail.operands[0], join(new_high, new_low)?)?;
+
+        block.index()
+    };
+
+    let not_taken_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        block.assign(scalar("ZF", 1), expr_const(0, 1));
+        let value = Expr::shr(dest.clone(), expr_const(bits as u64 / 2, bits))?;
+        high.set(&mut block, Expr::trun(bits / 2, value)?)?;
+        low.set(&mut block, Expr::trun(bits / 2, dest.clone())?)?;
+
+        block.index()
+    };
+
+    let tail_index = {
+        control_flow_graph.new_block()?.index()
+    };
+
+    let condition = Expr::cmpeq(dest, join(high, low)?)?;
+
+    control_flow_graph.conditional_edge(head_index, taken_index, condition.clone())?;
+    control_flow_graph.conditional_edge(
+        head_index,
+        not_taken_index,
+        Expr::cmpeq(condition, expr_const(0, 1))?
+    )?;
+    control_flow_graph.unconditional_edge(taken_index, tail_index)?;
+    control_flow_graph.unconditional_edge(not_taken_index, tail_index)?;
+
+    control_flow_graph.set_entry(head_index)?;
+    control_flow_graph.set_exit(tail_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    CPUID returns processor identification and features in eax, ebx, ecx and
+    edx. These depend on the processor we model, so we raise to the platform.
+*/
+pub fn cpuid(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    raise(control_flow_graph, expr_scalar("cpuid", 1))
+}
+
+
+
+pub fn cqo(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // isolate the sign bits of rax
+        let rax = get_register(mode, x86_reg::X86_REG_RAX)?;
+        let expr = Expr::shr(rax.get()?, expr_const(63, 64))?;
+        let expr = Expr::trun(1, expr)?;
+        let expr = Expr::sext(64, expr)?;
+
+        get_register(mode, x86_reg::X86_REG_RDX)?.set(&mut block, expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cwd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // isolate the sign bits of ax
+        let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+        let expr = Expr::shr(ax.get()?, expr_const(15, 16))?;
+        let expr = Expr::trun(1, expr)?;
+        let expr = Expr::sext(16, expr)?;
+
+        get_register(mode, x86_reg::X86_REG_DX)?.set(&mut block, expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn cwde(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let expr = Expr::sext(dst.bits(), src.into())?;
+
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    DAA adjusts the sum of two packed BCD values in al.
+*/
+pub fn daa(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let al = get_register(mode, x86_reg::X86_REG_AL)?;
+
+        // adjust the low digit if it is not a decimal digit, or carried
+        let low = block.temp(1);
+        block.assign(low.clone(), Expr::or(
+            Expr::cmpltu(expr_const(9, 8), Expr::and(al.get()?, expr_const(0xf, 8))?)?,
+            expr_scalar("AF", 1)
+        )?);
+
+        // adjust the high digit if the value is not decimal, or carried
+        let high = block.temp(1);
+        block.assign(high.clone(), Expr::or(
+            Expr::cmpltu(expr_const(0x99, 8), al.get()?)?,
+            expr_scalar("CF", 1)
+        )?);
+
+        let result = block.temp(8);
+        let expr = Expr::add(al.get()?, select(low.clone().into(), expr_const(0x06, 8), expr_const(0, 8))?)?;
+        let expr = Expr::add(expr, select(high.clone().into(), expr_const(0x60, 8), expr_const(0, 8))?)?;
+        block.assign(result.clone(), expr);
+        al.set(&mut block, result.clone().into())?;
+
+        block.assign(scalar("AF", 1), low.into());
+        block.assign(scalar("CF", 1), high.into());
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_pf(&mut block, result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    DAS adjusts the difference of two packed BCD values in al.
+*/
+pub fn das(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let al = get_register(mode, x86_reg::X86_REG_AL)?;
+
+        // adjust the low digit if it is not a decimal digit, or borrowed
+        let low = block.temp(1);
+        block.assign(low.clone(), Expr::or(
+            Expr::cmpltu(expr_const(9, 8), Expr::and(al.get()?, expr_const(0xf, 8))?)?,
+            expr_scalar("AF", 1)
+        )?);
+
+        // adjust the high digit if the value is not decimal, or borrowed
+        let high = block.temp(1);
+        block.assign(high.clone(), Expr::or(
+            Expr::cmpltu(expr_const(0x99, 8), al.get()?)?,
+            expr_scalar("CF", 1)
+        )?);
+
+        // adjusting the low digit can borrow as well
+        let borrow = Expr::and(low.clone().into(), Expr::cmpltu(al.get()?, expr_const(6, 8))?)?;
+        let carry = Expr::or(high.clone().into(), borrow)?;
+
+        let result = block.temp(8);
+        let expr = Expr::sub(al.get()?, select(low.clone().into(), expr_const(0x06, 8), expr_const(0, 8))?)?;
+        let expr = Expr::sub(expr, select(high.into(), expr_const(0x60, 8), expr_const(0, 8))?)?;
+        block.assign(result.clone(), expr);
+
+        block.assign(scalar("CF", 1), carry);
+        block.assign(scalar("AF", 1), low.into());
+        al.set(&mut block, result.clone().into())?;
+
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_pf(&mut block, result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn dec(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        let expr = Expr::sub(dst.clone().into(), expr_const(1, dst.bits()))?;
+
+        set_zf(&mut block, expr.clone())?;
+        set_sf(&mut block, expr.clone())?;
+        set_of(&mut block, expr.clone(), dst.clone(), expr_const(1, dst.bits()))?;
+        set_af(&mut block, expr.clone(), dst.clone(), expr_const(1, dst.bits()))?;
+        set_cf(&mut block, expr.clone(), dst.clone())?;
+
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn div(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let divisor = operand_load(mode, &mut block, &detail.operands[0])?;
+        let divisor = Expr::zext(divisor.bits() * 2, divisor)?;
+
+        let dividend: Expr = match divisor.bits() {
+            16 => get_register(mode, x86_reg::X86_REG_AX)?.get()?,
+            32 => {
+                let expr_dx = Expr::zext(32, get_register(mode, x86_reg::X86_REG_DX)?.get()?)?;
+                let expr_dx = Expr::shl(expr_dx, expr_const(16, 32))?;
+                Expr::or(expr_dx, Expr::zext(32, get_register(mode, x86_reg::X86_REG_AX)?.get()?)?)?
+            },
+            64 => {
+                let expr_edx = Expr::zext(64, get_register(mode, x86_reg::X86_REG_EDX)?.get()?)?;
+                let expr_edx = Expr::shl(expr_edx, expr_const(32, 64))?;
+                Expr::or(expr_edx, Expr::zext(64, get_register(mode, x86_reg::X86_REG_EAX)?.get()?)?)?
+            },
+            128 => {
+                let expr_rdx = Expr::zext(128, get_register(mode, x86_reg::X86_REG_RDX)?.get()?)?;
+                let expr_rdx = Expr::shl(expr_rdx, expr_const(64, 128))?;
+                Expr::or(expr_rdx, Expr::zext(128, get_register(mode, x86_reg::X86_REG_RAX)?.get()?)?)?
+            },
+            _ => return Err("invalid bit-width in x86 div".into())
+        };
+
+        let quotient  = block.temp(divisor.bits());
+        let remainder = block.temp(divisor.bits());
+
+        block.assign(quotient.clone(), Expr::divu(dividend.clone(), divisor.clone())?);
+        block.assign(remainder.clone(), Expr::modu(dividend, divisor.clone())?);
+
+        match divisor.bits() {
+            16 => {
+                let al = get_register(mode, x86_reg::X86_REG_AL)?;
+                let ah = get_register(mode, x86_reg::X86_REG_AH)?;
+                al.set(&mut block, Expr::trun(8, quotient.into())?)?;
+                ah.set(&mut block, Expr::trun(8, remainder.into())?)?;
+            },
+            32 => {
+                let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+                let dx = get_register(mode, x86_reg::X86_REG_DX)?;
+                ax.set(&mut block, Expr::trun(16, quotient.into())?)?;
+                dx.set(&mut block, Expr::trun(16, remainder.into())?)?;
+            },
+            64 => {
+                let eax = get_register(mode, x86_reg::X86_REG_EAX)?;
+                let edx = get_register(mode, x86_reg::X86_REG_EDX)?;
+                eax.set(&mut block, Expr::trun(32, quotient.into())?)?;
+                edx.set(&mut block, Expr::trun(32, remainder.into())?)?;
+            },
+            128 => {
+                let rax = get_register(mode, x86_reg::X86_REG_RAX)?;
+                let rdx = get_register(mode, x86_reg::X86_REG_RDX)?;
+                rax.set(&mut block, Expr::trun(64, quotient.into())?)?;
+                rdx.set(&mut block, Expr::trun(64, remainder.into())?)?;
+            },
+            _ => return Err("invalid bit-width in x86 div".into())
+        }
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+/*
+    ENTER creates a stack frame of the given size. A nesting level above 0
+    copies that many frame pointers from the enclosing frame, the last being
+    the new frame pointer.
+*/
+pub fn enter(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let size = detail.operands[0].imm() as u64 & 0xffff;
+    let level = detail.operands[1].imm() as u64 % 32;
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let sp = mode.sp();
+        let bp = mode.bp();
+        let bytes = expr_const(mode.bits() as u64 / 8, mode.bits());
+
+        push_value(mode, &mut block, bp.get()?)?;
+
+        let frame = block.temp(mode.bits());
+        block.assign(frame.clone(), sp.get()?);
+
+        if level > 0 {
+            for _ in 1..level {
+                bp.set(&mut block, Expr::sub(bp.get()?, bytes.clone())?)?;
+                let temp = block.temp(mode.bits());
+                block.load(temp.clone(), bp.get()?, array("mem", MEM_SIZE));
+                push_value(mode, &mut block, temp.into())?;
+            }
+            push_value(mode, &mut block, frame.clone().into())?;
+        }
+
+        bp.set(&mut block, frame.into())?;
+        sp.set(&mut block, Expr::sub(sp.get()?, expr_const(size, mode.bits()))?)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn fabs(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_unary(control_flow_graph, |value| Expr::and(value, expr_const(0x7fff_ffff_ffff_ffff, 64)))
+}
+
+
+
+pub fn fadd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    // faddp shares its instruction id with fadd
+    let pop = instruction.mnemonic.starts_with("faddp");
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fadd, false, pop, false)
+}
+
+
+
+pub fn fchs(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_unary(control_flow_graph, |value| Expr::xor(value, expr_const(0x8000_0000_0000_0000, 64)))
+}
+
+
+
+pub fn fcmovcc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let flag = |name: &str, value: u64| Expr::cmpeq(expr_scalar(name, 1), expr_const(value, 1));
+
+    let condition = match instruction.id {
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVB) => flag("CF", 1)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVBE) =>
+            Expr::or(flag("CF", 1)?, flag("ZF", 1)?)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVE) => flag("ZF", 1)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVU) => flag("PF", 1)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVNB) => flag("CF", 0)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVNBE) =>
+            Expr::and(flag("CF", 0)?, flag("ZF", 0)?)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVNE) => flag("ZF", 0)?,
+        capstone::InstrIdArch::X86(capstone::x86_insn::X86_INS_FCMOVNU) => flag("PF", 0)?,
+        _ => bail!("unhandled fcmovcc instruction")
+    };
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let src = fpu_operand_load(
+            mode,
+            &mut block,
+            &detail.operands[detail.op_count as usize - 1],
+            false
+        )?;
+        block.assign(fpu_st(0), select(condition, src, fpu_st(0).into())?);
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn fcom(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 0, false)
+}
+
+
+
+pub fn fcomi(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_flags(mode, control_flow_graph, instruction, false)
+}
+
+
+
+pub fn fcomp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 1, false)
+}
+
+
+
+pub fn fcompi(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_flags(mode, control_flow_graph, instruction, true)
+}
+
+
+
+pub fn fcompp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 2, false)
+}
+
+
+
+pub fn fdiv(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fdiv, false, false, false)
+}
+
+
+
+pub fn fdivp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fdiv, false, true, false)
+}
+
+
+
+pub fn fdivr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fdiv, true, false, false)
+}
+
+
+
+pub fn fdivrp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fdiv, true, true, false)
+}
+
+
+
+pub fn fiadd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fadd, false, false, true)
+}
+
+
+
+pub fn ficom(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 0, true)
+}
+
+
+
+pub fn ficomp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 1, true)
+}
+
+
+
+pub fn fidiv(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fdiv, false, false, true)
+}
+
+
+
+pub fn fidivr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fdiv, true, false, true)
+}
+
+
+
+pub fn fild(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        let value = fpu_operand_load(mode, &mut block, &detail.operands[0], true)?;
+        fpu_push(&mut block, value)?;
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn fimul(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fmul, false, false, true)
+}
+
+
+
+pub fn fist(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_store_integer(mode, control_flow_graph, instruction, false, false)
+}
+
+
+
+pub fn fistp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_store_integer(mode, control_flow_graph, instruction, true, false)
+}
+
+
+
+pub fn fisttp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_store_integer(mode, control_flow_graph, instruction, true, true)
+}
+
+
+
+pub fn fisub(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fsub, false, false, true)
+}
+
+
+
+pub fn fisubr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fsub, true, false, true)
+}
+
+
+
+pub fn fld(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        let value = fpu_operand_load(mode, &mut block, &detail.operands[0], false)?;
+        fpu_push(&mut block, value)?;
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn fld1(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0x3ff0_0000_0000_0000)
+}
+
+
+
+pub fn fldcw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        let value = operand_load(mode, &mut block, &detail.operands[0])?;
+        block.assign(scalar("fpu_cw", 16), value);
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn fldl2e(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0x3ff7_1547_652b_82fe)
+}
+
+
+
+pub fn fldl2t(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0x400a_934f_0979_a371)
+}
+
+
+
+pub fn fldlg2(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0x3fd3_4413_509f_79ff)
+}
+
+
+
+pub fn fldln2(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0x3fe6_2e42_fefa_39ef)
+}
+
+
+
+pub fn fldpi(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0x4009_21fb_5444_2d18)
+}
+
+
+
+pub fn fldz(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    fpu_load_constant(control_flow_graph, 0)
+}
+
+
+
+pub fn fmul(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fmul, false, false, false)
+}
+
+
+
+pub fn fmulp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fmul, false, true, false)
+}
+
+
+
+pub fn fninit(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        block.assign(scalar("fpu_cw", 16), expr_const(0x37f, 16));
+        block.assign(scalar("TOP", 3), expr_const(0, 3));
+        for name in &["C0", "C1", "C2", "C3"] {
+            block.assign(scalar(*name, 1), expr_const(0, 1));
+        }
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
 
-    control_flow_graph.unconditional_edge(zero_index, terminating_index)?;
 
-    control_flow_graph.conditional_edge(
-        loop_index,
-        iterate_index,
-        Expr::cmpeq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
-    )?;
-    control_flow_graph.conditional_edge(
-        loop_index,
-        terminating_index,
-        Expr::cmpneq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
-    )?;
-    control_flow_graph.unconditional_edge(iterate_index, loop_index)?;
 
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(terminating_index)?;
+pub fn fnstcw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        operand_store(mode, &mut block, &detail.operands[0], expr_scalar("fpu_cw", 16))?;
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
     Ok(())
 }
 
 
-/*
-    BSR scans the bits in the second word or doubleword operand from the most
-    significant bit to the least significant bit. The ZF flag is cleared if the
-    bits are all 0; otherwise, ZF is set and the destination register is loaded
-    with the bit index of the first set bit found when scanning in the reverse
-    direction.
-*/
-pub fn bsr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+
+pub fn fnstsw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    let (head_index, rhs, counter) = {
-        let (head_index, rhs) = {
-            let mut head_block = control_flow_graph.new_block()?;
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        operand_store(mode, &mut block, &detail.operands[0], fpu_status_word()?)?;
+        block.index()
+    };
 
-            // get started
-            let rhs = operand_load(&mut head_block, &detail.operands[1])?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-            (head_block.index(), rhs)
-        };
+    Ok(())
+}
 
-        let counter = {
-            control_flow_graph.temp(rhs.bits())
-        };
 
-        let mut head_block = control_flow_graph.block_mut(head_index)
-                                               .ok_or("Could not find block")?;
 
-        // This is the loop preamble, and we'll always execute it
-        head_block.assign(scalar("ZF", 1), expr_const(0, 1));
-        head_block.assign(counter.clone(), expr_const((rhs.bits() - 1) as u64, rhs.bits()));
+pub fn fst(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
 
-        (head_index, rhs, counter)
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        fpu_operand_store(mode, &mut block, &detail.operands[0], fpu_st(0).into())?;
+        block.index()
     };
 
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-    // if rhs == 0 then ZF = 1 and we are done.
-    let zero_index = {
-        let mut zero_block = control_flow_graph.new_block()?;
-        zero_block.assign(scalar("ZF", 1), expr_const(1, 1));
+    Ok(())
+}
 
-        zero_block.index()
-    };
 
-    // The loop body checks if the bits for our counter is set
-    let (bitfield, loop_index) = {
-        let bitfield = control_flow_graph.temp(rhs.bits());
 
-        let mut loop_block = control_flow_graph.new_block()?;
-        loop_block.assign(bitfield.clone(),
-            Expr::and(
-                Expr::shr(rhs.clone(), counter.clone().into())?,
-                expr_const(1, rhs.bits())
-            )?
-        );
+pub fn fstp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
 
-        (bitfield, loop_block.index())
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+        fpu_operand_store(mode, &mut block, &detail.operands[0], fpu_st(0).into())?;
+        fpu_pop(&mut block)?;
+        block.index()
     };
 
-    // While our bitfield == 0, we decrement counter and keep looping
-    let iterate_index = {
-        let mut iterate_block = control_flow_graph.new_block()?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-        iterate_block.assign(counter.clone(), Expr::sub(counter.clone().into(), expr_const(1, counter.bits()))?);
+    Ok(())
+}
 
-        iterate_block.index()
-    };
 
-    // In our terminating block, we set the result to counter
-    let terminating_index = {
-        let mut terminating_block = control_flow_graph.new_block()?;
 
-        operand_store(&mut terminating_block, &detail.operands[0], counter.into())?;
+pub fn fsub(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fsub, false, false, false)
+}
 
-        terminating_block.index()
-    };
 
-    control_flow_graph.conditional_edge(
-        head_index,
-        zero_index,
-        Expr::cmpeq(rhs.clone(), expr_const(0, rhs.bits()))?
-    )?;
-    control_flow_graph.conditional_edge(
-        head_index,
-        loop_index,
-        Expr::cmpneq(rhs.clone(), expr_const(0, rhs.bits()))?
-    )?;
 
-    control_flow_graph.unconditional_edge(zero_index, terminating_index)?;
+pub fn fsubp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fsub, false, true, false)
+}
 
-    control_flow_graph.conditional_edge(
-        loop_index,
-        iterate_index,
-        Expr::cmpeq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
-    )?;
-    control_flow_graph.conditional_edge(
-        loop_index,
-        terminating_index,
-        Expr::cmpneq(bitfield.clone().into(), expr_const(0, bitfield.bits()))?
-    )?;
-    control_flow_graph.unconditional_edge(iterate_index, loop_index)?;
 
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(terminating_index)?;
 
-    Ok(())
+pub fn fsubr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fsub, true, false, false)
 }
 
 
-/*
-    BT saves the value of the bit indicated by the base (first operand) and the
-    bit offset (second operand) into the carry flag.
 
-    CF ← BIT[LeftSRC, RightSRC];
+pub fn fsubrp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_arithmetic(mode, control_flow_graph, instruction, Expr::fsub, true, true, false)
+}
 
-    0F A3 BT r/m16,r16 3/12 Save bit in carry flag
-    0F A3 BT r/m32,r32 3/12 Save bit in carry flag
-    0F BA /4 ib BT r/m16,imm8 3/6 Save bit in carry flag
-    0F BA /4 ib BT r/m32,imm8 3/6 Save bit in carry flag
-*/
-pub fn bt(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
 
-    // create our head block
+
+pub fn ftst(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // get started
-        let base = operand_load(&mut block, &detail.operands[0])?;
-        let mut offset = operand_load(&mut block, &detail.operands[1])?;
-
-        // let's ensure we have equal sorts
-        if offset.bits() != base.bits() {
-            let temp = block.temp(base.bits());
-            block.assign(temp.clone(), Expr::zext(base.bits(), offset.clone().into())?);
-            offset = temp.into();
-        }
-
-        let temp = block.temp(base.bits());
-        block.assign(temp.clone(), Expr::shr(base.into(), offset.into())?);
-        block.assign(scalar("CF", 1), Expr::trun(1, temp.into())?);
+        let (less, equal, unordered) = fpu_compare(fpu_st(0).into(), expr_const(0, 64))?;
+        block.assign(scalar("C0", 1), Expr::or(less, unordered.clone())?);
+        block.assign(scalar("C1", 1), expr_const(0, 1));
+        block.assign(scalar("C2", 1), unordered.clone());
+        block.assign(scalar("C3", 1), Expr::or(equal, unordered)?);
 
         block.index()
     };
@@ -933,45 +3696,72 @@ pub fn bt(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Ins
 }
 
 
-/*
-    BTC saves the value of the bit indicated by the base (first operand) and the
-    bit offset (second operand) into the carry flag and then complements the
-    bit.
 
-    CF ← BIT[LeftSRC, RightSRC];
-    BIT[LeftSRC, RightSRC] ← NOT BIT[LeftSRC, RightSRC];
+pub fn fucom(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 0, false)
+}
 
-    0F BB BTC r/m16,r16 6/13 Save bit in carry flag and complement
-    0F BB BTC r/m32,r32 6/13 Save bit in carry flag and complement
-    0F BA /7 ib BTC r/m16,imm8 6/8 Save bit in carry flag and complement
-    0F BA /7 ib BTC r/m32,imm8 6/8 Save bit in carry flag and complement
-*/
-pub fn btc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
 
-    // create our head block
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        // get started
-        let base = operand_load(&mut block, &detail.operands[0])?;
-        let mut offset = operand_load(&mut block, &detail.operands[1])?;
+pub fn fucomi(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_flags(mode, control_flow_graph, instruction, false)
+}
 
-        // let's ensure we have equal sorts
-        if offset.bits() != base.bits() {
-            let temp = block.temp(base.bits());
-            block.assign(temp.clone(), Expr::zext(base.bits(), offset.clone().into())?);
-            offset = temp.into();
-        }
 
-        // this handles the assign to CF
-        let temp = block.temp(base.bits());
-        block.assign(temp.clone(), Expr::shr(base.into(), offset.clone().into())?);
-        block.assign(scalar("CF", 1), Expr::trun(1, temp.clone().into())?);
 
-        let expr = Expr::xor(temp.clone().into(), expr_const(1, temp.clone().bits()))?;
-        let expr = Expr::shl(expr, offset.into())?;
-        operand_store(&mut block, &detail.operands[0], expr)?;
+pub fn fucomp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 1, false)
+}
+
+
+
+pub fn fucompi(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_flags(mode, control_flow_graph, instruction, true)
+}
+
+
+
+pub fn fucompp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    fpu_compare_condition_codes(mode, control_flow_graph, instruction, 2, false)
+}
+
+
+
+pub fn fxam(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value: Expression = fpu_st(0).into();
+        let exponent = Expr::and(
+            Expr::shr(value.clone(), expr_const(52, 64))?,
+            expr_const(0x7ff, 64)
+        )?;
+        let fraction = Expr::and(value.clone(), expr_const(0x000f_ffff_ffff_ffff, 64))?;
+
+        let is_max = Expr::cmpeq(exponent.clone(), expr_const(0x7ff, 64))?;
+        let is_zero_exponent = Expr::cmpeq(exponent, expr_const(0, 64))?;
+        let is_zero_fraction = Expr::cmpeq(fraction, expr_const(0, 64))?;
+        let is_nan = Expr::and(
+            is_max.clone(),
+            Expr::cmpeq(is_zero_fraction.clone(), expr_const(0, 1))?
+        )?;
+        let is_zero = Expr::and(is_zero_exponent.clone(), is_zero_fraction)?;
+
+        // Registers are never tagged empty, so every value is a NaN (C0),
+        // normal (C2), infinity (C2, C0), zero (C3) or denormal (C3, C2).
+        block.assign(scalar("C0", 1), is_max);
+        block.assign(
+            scalar("C1", 1),
+            Expr::trun(1, Expr::shr(value, expr_const(63, 64))?)?
+        );
+        block.assign(
+            scalar("C2", 1),
+            Expr::and(
+                Expr::cmpeq(is_nan, expr_const(0, 1))?,
+                Expr::cmpeq(is_zero, expr_const(0, 1))?
+            )?
+        );
+        block.assign(scalar("C3", 1), is_zero_exponent);
 
         block.index()
     };
@@ -983,47 +3773,22 @@ pub fn btc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 }
 
 
-/*
-    BTR saves the value of the bit indicated by the base (first operand) and the
-    bit offset (second operand) into the carry flag and then stores 0 in the
-    bit.
-
-    CF ← BIT[LeftSRC, RightSRC];
-    BIT[LeftSRC, RightSRC] ← 0;
 
-    0F B3 BTR r/m16,r16 6/13 Save bit in carry flag and reset
-    0F B3 BTR r/m32,r32 6/13 Save bit in carry flag and reset
-    0F BA /6 ib BTR r/m16,imm8 6/8 Save bit in carry flag and reset
-    0F BA /6 ib BTR r/m32,imm8 6/8 Save bit in carry flag and reset
-*/
-pub fn btr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn fxch(_: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    // create our head block
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // get started
-        let base = operand_load(&mut block, &detail.operands[0])?;
-        let mut offset = operand_load(&mut block, &detail.operands[1])?;
-
-        // let's ensure we have equal sorts
-        if offset.bits() != base.bits() {
-            let temp = block.temp(base.bits());
-            block.assign(temp.clone(), Expr::zext(base.bits(), offset.clone().into())?);
-            offset = temp.into();
-        }
-
-        // this handles the assign to CF
-        let temp = block.temp(base.bits());
-        block.assign(temp.clone(), Expr::shr(base.clone().into(), offset.clone().into())?);
-        block.assign(scalar("CF", 1), Expr::trun(1, temp.clone().into())?);
-
-        let expr = Expr::shl(expr_const(1, base.bits()), offset.into())?;
-        let expr = Expr::xor(expr, expr_const(0xffff_ffff_ffff_ffff, base.bits()))?;
-        let expr = Expr::and(base.into(), expr)?;
+        let other = match detail.op_count {
+            0 => fpu_st(1),
+            count => fpu_st(fpu_register_index(*detail.operands[count as usize - 1].reg())?)
+        };
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+        let temp = block.temp(64);
+        block.assign(temp.clone(), fpu_st(0).into());
+        block.assign(fpu_st(0), other.clone().into());
+        block.assign(other, temp.into());
 
         block.index()
     };
@@ -1035,47 +3800,72 @@ pub fn btr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 }
 
 
-/*
-    BTS saves the value of the bit indicated by the base (first operand) and the
-    bit offset (second operand) into the carry flag and then stores 1 in the
-    bit.
-
-    CF ← BIT[LeftSRC, RightSRC];
-    BIT[LeftSRC, RightSRC] ← 1;
 
-    0F AB BTS r/m16,r16 6/13 Save bit in carry flag and set
-    0F AB BTS r/m32,r32 6/13 Save bit in carry flag and set
-    0F BA /5 ib BTS r/m16,imm8 6/8 Save bit in carry flag and set
-    0F BA /5 ib BTS r/m32,imm8 6/8 Save bit in carry flag and set
-*/
-pub fn bts(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+// This is essentially the exact same as div with the signs of the arith ops
+// reversed.
+pub fn idiv(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    // create our head block
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // get started
-        let base = operand_load(&mut block, &detail.operands[0])?;
-        let mut offset = operand_load(&mut block, &detail.operands[1])?;
+        let divisor = operand_load(mode, &mut block, &detail.operands[0])?;
+        let divisor = Expr::zext(divisor.bits() * 2, divisor)?;
 
-        // let's ensure we have equal sorts
-        if offset.bits() != base.bits() {
-            let temp = block.temp(base.bits());
-            block.assign(temp.clone(), Expr::zext(base.bits(), offset.clone().into())?);
-            offset = temp.into();
+        let dividend: Expr = match divisor.bits() {
+            16 => get_register(mode, x86_reg::X86_REG_AX)?.get()?,
+            32 => {
+                let expr_dx = Expr::zext(32, get_register(mode, x86_reg::X86_REG_DX)?.get()?)?;
+                let expr_dx = Expr::shl(expr_dx, expr_const(16, 32))?;
+                Expr::or(expr_dx, Expr::zext(32, get_register(mode, x86_reg::X86_REG_AX)?.get()?)?)?
+            },
+            64 => {
+                let expr_edx = Expr::zext(64, get_register(mode, x86_reg::X86_REG_EDX)?.get()?)?;
+                let expr_edx = Expr::shl(expr_edx, expr_const(32, 64))?;
+                Expr::or(expr_edx, Expr::zext(64, get_register(mode, x86_reg::X86_REG_EAX)?.get()?)?)?
+            },
+            128 => {
+                let expr_rdx = Expr::zext(128, get_register(mode, x86_reg::X86_REG_RDX)?.get()?)?;
+                let expr_rdx = Expr::shl(expr_rdx, expr_const(64, 128))?;
+                Expr::or(expr_rdx, Expr::zext(128, get_register(mode, x86_reg::X86_REG_RAX)?.get()?)?)?
+            },
+            _ => return Err("invalid bit-width in x86 div".into())
+        };
+
+        let quotient  = block.temp(divisor.bits());
+        let remainder = block.temp(divisor.bits());
+
+        block.assign(quotient.clone(), Expr::divs(dividend.clone(), divisor.clone())?);
+        block.assign(remainder.clone(), Expr::mods(dividend, divisor.clone())?);
+
+        match divisor.bits() {
+            16 => {
+                let al = get_register(mode, x86_reg::X86_REG_AL)?;
+                let ah = get_register(mode, x86_reg::X86_REG_AH)?;
+                al.set(&mut block, Expr::trun(8, quotient.into())?)?;
+                ah.set(&mut block, Expr::trun(8, remainder.into())?)?;
+            },
+            32 => {
+                let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+                let dx = get_register(mode, x86_reg::X86_REG_DX)?;
+                ax.set(&mut block, Expr::trun(16, quotient.into())?)?;
+                dx.set(&mut block, Expr::trun(16, remainder.into())?)?;
+            },
+            64 => {
+                let eax = get_register(mode, x86_reg::X86_REG_EAX)?;
+                let edx = get_register(mode, x86_reg::X86_REG_EDX)?;
+                eax.set(&mut block, Expr::trun(32, quotient.into())?)?;
+                edx.set(&mut block, Expr::trun(32, remainder.into())?)?;
+            },
+            128 => {
+                let rax = get_register(mode, x86_reg::X86_REG_RAX)?;
+                let rdx = get_register(mode, x86_reg::X86_REG_RDX)?;
+                rax.set(&mut block, Expr::trun(64, quotient.into())?)?;
+                rdx.set(&mut block, Expr::trun(64, remainder.into())?)?;
+            },
+            _ => return Err("invalid bit-width in x86 div".into())
         }
 
-        // this handles the assign to CF
-        let temp = block.temp(base.bits());
-        block.assign(temp.clone(), Expr::shr(base.clone().into(), offset.clone().into())?);
-        block.assign(scalar("CF", 1), Expr::trun(1, temp.clone().into())?);
-
-        let expr = Expr::shl(expr_const(1, base.bits()), offset.into())?;
-        let expr = Expr::or(base.into(), expr)?;
-
-        operand_store(&mut block, &detail.operands[0], expr)?;
-
         block.index()
     };
 
@@ -1086,21 +3876,123 @@ pub fn bts(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 }
 
 
-
-pub fn call(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+// If we have one operand, we go in AX, DX:AX, EDX:EAX
+// If we have two operands, sign-extend rhs if required, go in 0 operand
+// If we have three operands, sign-extend rhs if required, go in 0 operand
+pub fn imul(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // get started
-        let dst = operand_load(&mut block, &detail.operands[0])?;
+        // Get multiplicand
+        let multiplicand = match detail.op_count {
+            1 => match detail.operands[0].size {
+                // AL
+                1 => get_register(mode, x86_reg::X86_REG_AL)?.get()?,
+                // AX
+                2 => get_register(mode, x86_reg::X86_REG_AX)?.get()?,
+                // EAX
+                4 => get_register(mode, x86_reg::X86_REG_EAX)?.get()?,
+                // RAX
+                8 => get_register(mode, x86_reg::X86_REG_RAX)?.get()?,
+                _ => bail!("invalid operand size for imul")
+            },
+            2 => operand_load(mode, &mut block, &detail.operands[0])?,
+            3 => operand_load(mode, &mut block, &detail.operands[1])?,
+            _ => bail!("invalid number of operands for imul {} at 0x{:x}",
+                    detail.op_count,
+                    instruction.address)
+        };
 
-        let ret_addr = instruction.address + instruction.size as u64;
+        // Get multiplier
+        let multiplier = match detail.op_count {
+            1 => operand_load(mode, &mut block, &detail.operands[0])?,
+            2 => {
+                let multiplier = operand_load(mode, &mut block, &detail.operands[1])?;
+                if multiplier.bits() < multiplicand.bits() {
+                    Expr::sext(multiplicand.bits(), multiplier)?
+                }
+                else {
+                    multiplier
+                }
+            },
+            3 => {
+                let multiplier = operand_load(mode, &mut block, &detail.operands[2])?;
+                if multiplier.bits() < multiplicand.bits() {
+                    Expr::sext(multiplicand.bits(), multiplier)?
+                }
+                else {
+                    multiplier
+                }
+            }
+            _ => bail!("invalid number of operands for imul")
+        };
 
-        push_value(&mut block, expr_const(ret_addr, 32))?;
+        // Perform multiplication
+        let bit_width = multiplicand.bits() * 2;
 
-        block.brc(dst, expr_const(1, 1));
+        let result = block.temp(bit_width);
+        block.assign(result.clone(), Expr::mul(
+            Expr::zext(bit_width, multiplicand)?,
+            Expr::zext(bit_width, multiplier)?
+        )?);
+
+        // Set the result
+        match detail.op_count {
+            1 => {
+                match detail.operands[0].size {
+                    1 => get_register(mode, x86_reg::X86_REG_AX)?.set(&mut block, result.clone().into())?,
+                    2 => {
+                        let dx = get_register(mode, x86_reg::X86_REG_DX)?;
+                        let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+                        let expr = Expr::shr(result.clone().into(), expr_const(16, 32))?;
+                        dx.set(&mut block, Expr::trun(16, expr)?)?;
+                        ax.set(&mut block, Expr::trun(16, result.clone().into())?)?;
+                    },
+                    4 => {
+                        let edx = get_register(mode, x86_reg::X86_REG_EDX)?;
+                        let eax = get_register(mode, x86_reg::X86_REG_EAX)?;
+                        let expr = Expr::shr(result.clone().into(), expr_const(32, 64))?;
+                        edx.set(&mut block, Expr::trun(32, expr)?)?;
+                        eax.set(&mut block, Expr::trun(32, result.clone().into())?)?;
+                    },
+                    8 => {
+                        let rdx = get_register(mode, x86_reg::X86_REG_RDX)?;
+                        let rax = get_register(mode, x86_reg::X86_REG_RAX)?;
+                        let expr = Expr::shr(result.clone().into(), expr_const(64, 128))?;
+                        rdx.set(&mut block, Expr::trun(64, expr)?)?;
+                        rax.set(&mut block, Expr::trun(64, result.clone().into())?)?;
+                    },
+                    _ => bail!("Invalid operand size for imul")
+                }
+            },
+            2 => {
+                let expr = Expr::trun(bit_width / 2, result.clone().into())?;
+                operand_store(mode, &mut block, &detail.operands[0], expr)?;
+            }
+            3 => {
+                let expr = Expr::trun(bit_width / 2, result.clone().into())?;
+                operand_store(mode, &mut block, &detail.operands[0], expr)?;
+            }
+            _ => bail!("invalid number of operands for imul")
+        }
+
+
+        // Set flags
+        block.assign(scalar("OF", 1),
+            Expr::cmpneq(
+                Expr::trun(
+                    bit_width / 2,
+                    Expr::shr(
+                        result.clone().into(),
+                        expr_const((bit_width / 2) as u64, bit_width)
+                    )?
+                )?,
+                expr_const(0, bit_width / 2)
+            )?
+        );
+        block.assign(scalar("CF", 1), expr_scalar("OF", 1));
 
         block.index()
     };
@@ -1113,18 +4005,23 @@ pub fn call(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn cbw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn inc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let dst = operand_load(&mut block, &detail.operands[0])?;
-        let src = operand_load(&mut block, &detail.operands[1])?;
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
 
-        let expr = Expr::sext(dst.bits(), src.into())?;
+        let expr = Expr::add(dst.clone().into(), expr_const(1, dst.bits()))?;
+
+        set_zf(&mut block, expr.clone())?;
+        set_sf(&mut block, expr.clone())?;
+        set_of(&mut block, expr.clone(), dst.clone(), expr_const(1, dst.bits()))?;
+        set_af(&mut block, expr.clone(), dst.clone(), expr_const(1, dst.bits()))?;
+        set_cf(&mut block, expr.clone(), dst.clone())?;
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
 
         block.index()
     };
@@ -1137,16 +4034,15 @@ pub fn cbw(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn cdq(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn int(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // isolate the sign bits of ax
-        let expr = Expr::shr(expr_scalar("eax", 32), expr_const(31, 32))?;
-        let expr = Expr::trun(1, expr)?;
-        let expr = Expr::sext(32, expr)?;
+        let expr = operand_load(mode, &mut block, &detail.operands[0])?;
 
-        block.assign(scalar("edx", 32), expr);
+        block.raise(expr);
 
         block.index()
     };
@@ -1159,28 +4055,28 @@ pub fn cdq(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn clc(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        block.assign(scalar("CF", 1), expr_const(0, 1));
-
-        block.index()
-    };
-
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
-
-    Ok(())
+/*
+    INT3 is the one byte form of int 3, a breakpoint.
+*/
+pub fn int3(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    raise(control_flow_graph, expr_const(3, 8))
 }
 
 
 
-pub fn cld(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn jcc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        block.assign(scalar("DF", 1), expr_const(0, 1));
+        // we only need to emit a brc here if the destination cannot be determined
+        // at translation time
+        if detail.operands[0].type_ != x86_op_type::X86_OP_IMM {
+            let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+            let expr = cc_condition(mode, &instruction)?;
+            block.brc(dst, expr);
+        }
 
         block.index()
     };
@@ -1193,11 +4089,18 @@ pub fn cld(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn cli(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn jmp(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        block.assign(scalar("IF", 1), expr_const(0, 1));
+        // we only need to emit a brc here if the destination cannot be determined
+        // at translation time
+        if detail.operands[0].type_ != x86_op_type::X86_OP_IMM {
+            let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+            block.brc(dst, expr_const(1, 1));
+        }
 
         block.index()
     };
@@ -1210,12 +4113,14 @@ pub fn cli(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn cmc(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+/*
+    LAHF loads SF, ZF, AF, PF and CF into ah.
+*/
+pub fn lahf(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let expr = Expr::xor(expr_scalar("CF", 1), expr_const(1, 1))?;
-        block.assign(scalar("CF", 1), expr);
+        get_register(mode, x86_reg::X86_REG_AH)?.set(&mut block, flags_value(8)?)?;
 
         block.index()
     };
@@ -1228,68 +4133,34 @@ pub fn cmc(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn cmovcc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn lea(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    let head_index = {
-        let block = control_flow_graph.new_block()?;
-
-        block.index()
-    };
-
-    let tail_index = {
-        let block = control_flow_graph.new_block()?;
-
-        block.index()
-    };
-
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_load(&mut block, &detail.operands[1])?;
+        let src = effective_address(mode, &detail.operands[1])?;
 
-        operand_store(&mut block, &detail.operands[0], src)?;
+        operand_store(mode, &mut block, &detail.operands[0], src)?;
 
         block.index()
     };
 
-    let condition = cc_condition(&instruction)?;
-
-    control_flow_graph.conditional_edge(head_index, block_index, condition.clone())?;
-    control_flow_graph.conditional_edge(
-        head_index,
-        tail_index,
-        Expr::cmpeq(condition, expr_const(0, 1))?
-    )?;
-    control_flow_graph.unconditional_edge(block_index, tail_index)?;
-
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(tail_index)?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
     Ok(())
 }
 
 
 
-pub fn cmp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
+pub fn leave(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
-
-        if rhs.bits() != lhs.bits() {
-            rhs = Expr::sext(lhs.bits(), rhs.into())?;
-        }
-
-        let expr = Expr::sub(lhs.clone(), rhs.clone())?;
-
-        set_zf(&mut block, expr.clone())?;
-        set_sf(&mut block, expr.clone())?;
-        set_of(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
-        set_cf(&mut block, expr.clone(), lhs.clone())?;
+        mode.sp().set(&mut block, mode.bp().get()?)?;
+        let bp = pop_value(mode, &mut block, mode.bits())?;
+        mode.bp().set(&mut block, bp)?;
 
         block.index()
     };
@@ -1302,167 +4173,63 @@ pub fn cmp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn cmpsb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-
-    let head_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
-
-        if rhs.bits() != lhs.bits() {
-            rhs = Expr::sext(lhs.bits(), rhs.into())?;
-        }
-
-        let expr = Expr::sub(lhs.clone(), rhs.clone())?;
-
-        set_zf(&mut block, expr.clone())?;
-        set_sf(&mut block, expr.clone())?;
-        set_of(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
-        set_cf(&mut block, expr.clone(), lhs.clone())?;
-
-        block.index()
-    };
-
-    let inc_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        block.assign(
-            scalar("esi", 32),
-            Expr::add(expr_scalar("esi", 32), expr_const(1, 32))?
-        );
-
-        block.assign(
-            scalar("edi", 32),
-            Expr::add(expr_scalar("edi", 32), expr_const(1, 32))?
-        );
-
-        block.index()
-    };
-
-    let dec_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        block.assign(
-            scalar("esi", 32),
-            Expr::sub(expr_scalar("esi", 32), expr_const(1, 32))?
-        );
-
-        block.assign(
-            scalar("edi", 32),
-            Expr::sub(expr_scalar("edi", 32), expr_const(1, 32))?
-        );
-
-        block.index()
-    };
-
-    let tail_index = {
-        control_flow_graph.new_block()?.index()
-    };
-
-
-    control_flow_graph.conditional_edge(
-        head_index,
-        inc_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(0, 1))?
-    )?;
-
-    control_flow_graph.conditional_edge(
-        head_index,
-        dec_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(1, 1))?
-    )?;
-
-    control_flow_graph.unconditional_edge(inc_index, tail_index)?;
-    control_flow_graph.unconditional_edge(dec_index, tail_index)?;
-
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(tail_index)?;
-
-    Ok(())
-}
-
-
-
-pub fn cmpxchg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-    let (head_index, dest, lhs, rhs) = {
-        let mut block = control_flow_graph.new_block()?;
-
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
-
-        let dest = match rhs.bits() {
-            8 => get_register(x86_reg::X86_REG_AL)?,
-            16 => get_register(x86_reg::X86_REG_AX)?,
-            32 => get_register(x86_reg::X86_REG_EAX)?,
-            _ => bail!("can't figure out dest for xmpxchg, rhs.bits()={}", rhs.bits())
-        };
-
-        (block.index(), dest, lhs, rhs)
-    };
+pub fn lods(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
 
-    let taken_index = {
+    let (head_index, bits) = {
         let mut block = control_flow_graph.new_block()?;
 
-        block.assign(scalar("ZF", 1), expr_const(1, 1));
-        operand_store(&mut block, &detail.operands[0], rhs.clone())?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let bits = src.bits();
 
-        block.index()
+        operand_store(mode, &mut block, &detail.operands[0], src)?;
+
+        (block.index(), bits)
     };
 
-    let not_taken_index = {
-        let mut block = control_flow_graph.new_block()?;
+    string_step(control_flow_graph, head_index, (bits / 8) as u64, &[mode.si()])
+}
 
-        block.assign(scalar("ZF", 1), expr_const(0, 1));
-        dest.set(&mut block, lhs.clone())?;
 
-        block.index()
-    };
 
-    let tail_index = {
+pub fn loop_(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let result = Expr::sub(lhs.clone(), rhs.clone())?;
-        set_sf(&mut block, result.clone())?;
-        set_of(&mut block, result.clone(), lhs.clone(), rhs.clone())?;
-        set_cf(&mut block, result.clone(), lhs.clone())?;
+        let cx = mode.cx();
+
+        cx.set(&mut block, Expr::sub(cx.get()?, expr_const(1, mode.bits()))?)?;
 
         block.index()
     };
 
-    let condition = Expr::cmpeq(dest.get()?, lhs.clone())?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-    control_flow_graph.conditional_edge(head_index, taken_index, condition.clone())?;
-    control_flow_graph.conditional_edge(
-        head_index,
-        not_taken_index,
-        Expr::cmpeq(condition.clone(), expr_const(0, 1))?
-    )?;
-    control_flow_graph.unconditional_edge(taken_index, tail_index)?;
-    control_flow_graph.unconditional_edge(not_taken_index, tail_index)?;
+    Ok(())
+}
 
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(tail_index)?;
 
-    Ok(())
+
+/*
+    LZCNT counts the leading zero bits of the source. CF is set if the source
+    is 0, and ZF is set if the count is 0.
+*/
+pub fn lzcnt(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    zero_count(mode, control_flow_graph, instruction, true)
 }
 
 
 
-pub fn cwd(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn mov(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // isolate the sign bits of ax
-        let expr = Expr::shr(expr_scalar("ax", 16), expr_const(15, 16))?;
-        let expr = Expr::trun(1, expr)?;
-        let expr = Expr::sext(16, expr)?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
 
-        block.assign(scalar("dx", 16), expr);
+        operand_store(mode, &mut block, &detail.operands[0], src)?;
 
         block.index()
     };
@@ -1475,18 +4242,19 @@ pub fn cwd(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn cwde(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+/*
+    MOVBE moves a value between a register and memory, reversing the order of
+    its bytes.
+*/
+pub fn movbe(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let dst = operand_load(&mut block, &detail.operands[0])?;
-        let src = operand_load(&mut block, &detail.operands[1])?;
-
-        let expr = Expr::sext(dst.bits(), src.into())?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+        operand_store(mode, &mut block, &detail.operands[0], byte_swap(src)?)?;
 
         block.index()
     };
@@ -1499,22 +4267,65 @@ pub fn cwde(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn dec(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn movd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_move_low(mode, control_flow_graph, instruction, 32)
+}
+
+
+
+pub fn movq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_move_low(mode, control_flow_graph, instruction, 64)
+}
+
+
+
+pub fn movs(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    let block_index = {
+    let (head_index, bits) = {
         let mut block = control_flow_graph.new_block()?;
 
-        let dst = operand_load(&mut block, &detail.operands[0])?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let bits = src.bits();
 
-        let expr = Expr::sub(dst.clone().into(), expr_const(1, dst.bits()))?;
+        operand_store(mode, &mut block, &detail.operands[0], src)?;
 
-        set_zf(&mut block, expr.clone())?;
-        set_sf(&mut block, expr.clone())?;
-        set_of(&mut block, expr.clone(), dst.clone(), expr_const(1, dst.bits()))?;
-        set_cf(&mut block, expr.clone(), dst.clone())?;
+        (block.index(), bits)
+    };
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+    string_step(control_flow_graph, head_index, (bits / 8) as u64, &[mode.si(), mode.di()])
+}
+
+
+
+pub fn movsd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    // The SSE movsd shares its instruction id with the string instruction
+    if is_sse(instruction)? {
+        sse_move_scalar(mode, control_flow_graph, instruction, 64)
+    }
+    else {
+        movs(mode, control_flow_graph, instruction)
+    }
+}
+
+
+
+pub fn movss(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_move_scalar(mode, control_flow_graph, instruction, 32)
+}
+
+
+
+pub fn movsx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let value = Expr::sext((detail.operands[0].size as usize) * 8, src)?;
+
+        operand_store(mode, &mut block, &detail.operands[0], value)?;
 
         block.index()
     };
@@ -1527,57 +4338,16 @@ pub fn dec(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn div(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn movzx(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let divisor = operand_load(&mut block, &detail.operands[0])?;
-        let divisor = Expr::zext(divisor.bits() * 2, divisor)?;
-
-        let dividend: Expr = match divisor.bits() {
-            16 => get_register(x86_reg::X86_REG_AX)?.get()?,
-            32 => {
-                let expr_dx = Expr::zext(32, get_register(x86_reg::X86_REG_DX)?.get()?)?;
-                let expr_dx = Expr::shl(expr_dx, expr_const(16, 32))?;
-                Expr::or(expr_dx, Expr::zext(32, get_register(x86_reg::X86_REG_AX)?.get()?)?)?
-            },
-            64 => {
-                let expr_edx = Expr::zext(64, get_register(x86_reg::X86_REG_EDX)?.get()?)?;
-                let expr_edx = Expr::shl(expr_edx, expr_const(32, 64))?;
-                Expr::or(expr_edx, Expr::zext(64, get_register(x86_reg::X86_REG_EAX)?.get()?)?)?
-            },
-            _ => return Err("invalid bit-width in x86 div".into())
-        };
-
-        let quotient  = block.temp(divisor.bits());
-        let remainder = block.temp(divisor.bits());
-
-        block.assign(quotient.clone(), Expr::divu(dividend.clone(), divisor.clone())?);
-        block.assign(remainder.clone(), Expr::modu(dividend, divisor.clone())?);
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let value = Expr::zext((detail.operands[0].size as usize) * 8, src)?;
 
-        match divisor.bits() {
-            16 => {
-                let al = get_register(x86_reg::X86_REG_AL)?;
-                let ah = get_register(x86_reg::X86_REG_AH)?;
-                al.set(&mut block, Expr::trun(8, quotient.into())?)?;
-                ah.set(&mut block, Expr::trun(8, remainder.into())?)?;
-            },
-            32 => {
-                let ax = get_register(x86_reg::X86_REG_AX)?;
-                let dx = get_register(x86_reg::X86_REG_DX)?;
-                ax.set(&mut block, Expr::trun(16, quotient.into())?)?;
-                dx.set(&mut block, Expr::trun(16, remainder.into())?)?;
-            },
-            64 => {
-                let eax = get_register(x86_reg::X86_REG_EAX)?;
-                let edx = get_register(x86_reg::X86_REG_EDX)?;
-                eax.set(&mut block, Expr::trun(32, quotient.into())?)?;
-                edx.set(&mut block, Expr::trun(32, remainder.into())?)?;
-            },
-            _ => return Err("invalid bit-width in x86 div".into())
-        }
+        operand_store(mode, &mut block, &detail.operands[0], value)?;
 
         block.index()
     };
@@ -1589,58 +4359,61 @@ pub fn div(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 }
 
 
-// This is essentially the exact same as div with the signs of the arith ops
-// reversed.
-pub fn idiv(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+
+pub fn mul(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let divisor = operand_load(&mut block, &detail.operands[0])?;
-        let divisor = Expr::zext(divisor.bits() * 2, divisor)?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[0])?;
 
-        let dividend: Expr = match divisor.bits() {
-            16 => get_register(x86_reg::X86_REG_AX)?.get()?,
-            32 => {
-                let expr_dx = Expr::zext(32, get_register(x86_reg::X86_REG_DX)?.get()?)?;
-                let expr_dx = Expr::shl(expr_dx, expr_const(16, 32))?;
-                Expr::or(expr_dx, Expr::zext(32, get_register(x86_reg::X86_REG_AX)?.get()?)?)?
-            },
-            64 => {
-                let expr_edx = Expr::zext(64, get_register(x86_reg::X86_REG_EDX)?.get()?)?;
-                let expr_edx = Expr::shl(expr_edx, expr_const(32, 64))?;
-                Expr::or(expr_edx, Expr::zext(64, get_register(x86_reg::X86_REG_EAX)?.get()?)?)?
-            },
-            _ => return Err("invalid bit-width in x86 div".into())
+        let lhs = match rhs.bits() {
+            8 => get_register(mode, x86_reg::X86_REG_AL)?.get()?,
+            16 => get_register(mode, x86_reg::X86_REG_AX)?.get()?,
+            32 => get_register(mode, x86_reg::X86_REG_EAX)?.get()?,
+            64 => get_register(mode, x86_reg::X86_REG_RAX)?.get()?,
+            _ => bail!("invalid bit-width for mul")
         };
 
-        let quotient  = block.temp(divisor.bits());
-        let remainder = block.temp(divisor.bits());
-
-        block.assign(quotient.clone(), Expr::divs(dividend.clone(), divisor.clone())?);
-        block.assign(remainder.clone(), Expr::mods(dividend, divisor.clone())?);
+        let bit_width = rhs.bits() * 2;
+        let result = block.temp(bit_width);
+        let expr = Expr::mul(Expr::zext(bit_width, lhs)?, Expr::zext(bit_width, rhs.clone())?)?;
+        block.assign(result.clone(), expr);
 
-        match divisor.bits() {
+        match rhs.bits() {
+            8 => {
+                let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+                ax.set(&mut block, result.into())?;
+                let expr = Expr::cmpeq(get_register(mode, x86_reg::X86_REG_AH)?.get()?, expr_const(0, 8))?;
+                block.assign(scalar("ZF", 1), expr);
+                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
+            },
             16 => {
-                let al = get_register(x86_reg::X86_REG_AL)?;
-                let ah = get_register(x86_reg::X86_REG_AH)?;
-                al.set(&mut block, Expr::trun(8, quotient.into())?)?;
-                ah.set(&mut block, Expr::trun(8, remainder.into())?)?;
+                let dx = get_register(mode, x86_reg::X86_REG_DX)?;
+                let ax = get_register(mode, x86_reg::X86_REG_AX)?;
+                dx.set(&mut block, Expr::trun(16, Expr::shr(result.clone().into(), expr_const(16, 32))?)?)?;
+                ax.set(&mut block, Expr::trun(16, result.into())?)?;
+                block.assign(scalar("ZF", 1), Expr::cmpeq(dx.get()?, expr_const(0, 16))?);
+                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
             },
             32 => {
-                let ax = get_register(x86_reg::X86_REG_AX)?;
-                let dx = get_register(x86_reg::X86_REG_DX)?;
-                ax.set(&mut block, Expr::trun(16, quotient.into())?)?;
-                dx.set(&mut block, Expr::trun(16, remainder.into())?)?;
+                let edx = get_register(mode, x86_reg::X86_REG_EDX)?;
+                let eax = get_register(mode, x86_reg::X86_REG_EAX)?;
+                edx.set(&mut block, Expr::trun(32, Expr::shr(result.clone().into(), expr_const(32, 64))?)?)?;
+                eax.set(&mut block, Expr::trun(32, result.into())?)?;
+                block.assign(scalar("ZF", 1), Expr::cmpeq(edx.get()?, expr_const(0, 32))?);
+                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
             },
             64 => {
-                let eax = get_register(x86_reg::X86_REG_EAX)?;
-                let edx = get_register(x86_reg::X86_REG_EDX)?;
-                eax.set(&mut block, Expr::trun(32, quotient.into())?)?;
-                edx.set(&mut block, Expr::trun(32, remainder.into())?)?;
+                let rdx = get_register(mode, x86_reg::X86_REG_RDX)?;
+                let rax = get_register(mode, x86_reg::X86_REG_RAX)?;
+                rdx.set(&mut block, Expr::trun(64, Expr::shr(result.clone().into(), expr_const(64, 128))?)?)?;
+                rax.set(&mut block, Expr::trun(64, result.into())?)?;
+                block.assign(scalar("ZF", 1), Expr::cmpeq(rdx.get()?, expr_const(0, 64))?);
+                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
             },
-            _ => return Err("invalid bit-width in x86 div".into())
+            _ => bail!("invalid bit-width for mul")
         }
 
         block.index()
@@ -1653,114 +4426,26 @@ pub fn idiv(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 }
 
 
-// If we have one operand, we go in AX, DX:AX, EDX:EAX
-// If we have two operands, sign-extend rhs if required, go in 0 operand
-// If we have three operands, sign-extend rhs if required, go in 0 operand
-pub fn imul(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+
+pub fn neg(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // Get multiplicand
-        let multiplicand = match detail.op_count {
-            1 => match detail.operands[0].size {
-                // AL
-                1 => get_register(x86_reg::X86_REG_AL)?.get()?,
-                // AX
-                2 => get_register(x86_reg::X86_REG_AX)?.get()?,
-                // EAX
-                4 => get_register(x86_reg::X86_REG_EAX)?.get()?,
-                _ => bail!("invalid operand size for imul")
-            },
-            2 => operand_load(&mut block, &detail.operands[0])?,
-            3 => operand_load(&mut block, &detail.operands[1])?,
-            _ => bail!("invalid number of operands for imul {} at 0x{:x}",
-                    detail.op_count,
-                    instruction.address)
-        };
-
-        // Get multiplier
-        let multiplier = match detail.op_count {
-            1 => operand_load(&mut block, &detail.operands[0])?,
-            2 => {
-                let multiplier = operand_load(&mut block, &detail.operands[1])?;
-                if multiplier.bits() < multiplicand.bits() {
-                    Expr::sext(multiplicand.bits(), multiplier)?
-                }
-                else {
-                    multiplier
-                }
-            },
-            3 => {
-                let multiplier = operand_load(&mut block, &detail.operands[2])?;
-                if multiplier.bits() < multiplicand.bits() {
-                    Expr::sext(multiplicand.bits(), multiplier)?
-                }
-                else {
-                    multiplier
-                }
-            }
-            _ => bail!("invalid number of operands for imul")
-        };
-
-        // Perform multiplication
-        let bit_width = multiplicand.bits() * 2;
-
-        let result = block.temp(bit_width);
-        block.assign(result.clone(), Expr::mul(
-            Expr::zext(bit_width, multiplicand)?,
-            Expr::zext(bit_width, multiplier)?
-        )?);
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
 
-        // Set the result
-        match detail.op_count {
-            1 => {
-                match detail.operands[0].size {
-                    1 => get_register(x86_reg::X86_REG_AX)?.set(&mut block, result.clone().into())?,
-                    2 => {
-                        let dx = get_register(x86_reg::X86_REG_DX)?;
-                        let ax = get_register(x86_reg::X86_REG_AX)?;
-                        let expr = Expr::shr(result.clone().into(), expr_const(16, 32))?;
-                        dx.set(&mut block, Expr::trun(16, expr)?)?;
-                        ax.set(&mut block, Expr::trun(16, result.clone().into())?)?;
-                    },
-                    4 => {
-                        let edx = get_register(x86_reg::X86_REG_EDX)?;
-                        let eax = get_register(x86_reg::X86_REG_EAX)?;
-                        let expr = Expr::shr(result.clone().into(), expr_const(32, 64))?;
-                        edx.set(&mut block, Expr::trun(32, expr)?)?;
-                        eax.set(&mut block, Expr::trun(32, result.clone().into())?)?;
-                    },
-                    _ => bail!("Invalid operand size for imul")
-                }
-            },
-            2 => {
-                let expr = Expr::trun(bit_width / 2, result.clone().into())?;
-                operand_store(&mut block, &detail.operands[0], expr)?;
-            }
-            3 => {
-                let expr = Expr::trun(bit_width / 2, result.clone().into())?;
-                operand_store(&mut block, &detail.operands[0], expr)?;
-            }
-            _ => bail!("invalid number of operands for imul")
-        }
+        let result = block.temp(dst.bits());
 
+        block.assign(scalar("CF", 1), Expr::cmpneq(dst.clone().into(), expr_const(0, dst.bits()))?);
+        block.assign(result.clone(), Expr::sub(expr_const(0, dst.bits()), dst.clone().into())?);
 
-        // Set flags
-        block.assign(scalar("OF", 1),
-            Expr::cmpneq(
-                Expr::trun(
-                    bit_width / 2,
-                    Expr::shr(
-                        result.clone().into(),
-                        expr_const((bit_width / 2) as u64, bit_width)
-                    )?
-                )?,
-                expr_const(0, bit_width / 2)
-            )?
-        );
-        block.assign(scalar("CF", 1), expr_scalar("OF", 1));
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        set_of(&mut block, result.clone().into(), expr_const(0, dst.bits()), dst.clone().into())?;
+        set_af(&mut block, result.clone().into(), expr_const(0, dst.bits()), dst.clone().into())?;
+
+        operand_store(mode, &mut block, &detail.operands[0], result.clone().into())?;
 
         block.index()
     };
@@ -1773,24 +4458,9 @@ pub fn imul(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn inc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
+pub fn nop(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        let dst = operand_load(&mut block, &detail.operands[0])?;
-
-        let expr = Expr::add(dst.clone().into(), expr_const(1, dst.bits()))?;
-
-        set_zf(&mut block, expr.clone())?;
-        set_sf(&mut block, expr.clone())?;
-        set_of(&mut block, expr.clone(), dst.clone(), expr_const(1, dst.bits()))?;
-        set_cf(&mut block, expr.clone(), dst.clone())?;
-
-        operand_store(&mut block, &detail.operands[0], expr)?;
-
-        block.index()
+        control_flow_graph.new_block()?.index()
     };
 
     control_flow_graph.set_entry(block_index)?;
@@ -1801,15 +4471,17 @@ pub fn inc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn int(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn not(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let expr = operand_load(&mut block, &detail.operands[0])?;
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
 
-        block.raise(expr);
+        let expr = Expr::xor(dst.clone(), expr_const(!0, dst.bits()))?;
+
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
 
         block.index()
     };
@@ -1822,20 +4494,34 @@ pub fn int(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn jcc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn or(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        // we only need to emit a brc here if the destination cannot be determined
-        // at translation time
-        if detail.operands[0].type_ != x86_op_type::X86_OP_IMM {
-            let dst = operand_load(&mut block, &detail.operands[0])?;
-            let expr = cc_condition(&instruction)?;
-            block.brc(dst, expr);
+        // get operands
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+
+        let result = block.temp(lhs.bits());
+
+        if lhs.bits() != rhs.bits() {
+            rhs = Expr::sext(lhs.bits(), rhs)?;
         }
 
+        // perform addition
+        block.assign(result.clone(), Expr::or(lhs.clone(), rhs.clone())?);
+
+        // calculate flags
+        set_zf(&mut block, result.clone().into())?;
+        set_sf(&mut block, result.clone().into())?;
+        block.assign(scalar("CF", 1), expr_const(0, 1));
+        block.assign(scalar("OF", 1), expr_const(0, 1));
+
+        // store result
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+
         block.index()
     };
 
@@ -1847,140 +4533,131 @@ pub fn jcc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn jmp(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn paddb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 8, Expr::add)
+}
 
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        // we only need to emit a brc here if the destination cannot be determined
-        // at translation time
-        if detail.operands[0].type_ != x86_op_type::X86_OP_IMM {
-            let dst = operand_load(&mut block, &detail.operands[0])?;
-            block.brc(dst, expr_const(1, 1));
-        }
 
-        block.index()
-    };
+pub fn paddd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 32, Expr::add)
+}
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
 
-    Ok(())
+
+pub fn paddq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 64, Expr::add)
 }
 
 
 
-pub fn lea(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn paddw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 16, Expr::add)
+}
 
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_value(&detail.operands[1])?;
 
-        operand_store(&mut block, &detail.operands[0], src)?;
+pub fn pand(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_bitwise(mode, control_flow_graph, instruction, Expr::and)
+}
 
-        block.index()
-    };
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
 
-    Ok(())
+pub fn pandn(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_bitwise(mode, control_flow_graph, instruction, |lhs, rhs|
+        Expr::and(Expr::xor(lhs, ones(128))?, rhs))
 }
 
 
 
-pub fn leave(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
+pub fn pcmpeqb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 8, |lhs, rhs|
+        Expr::sext(8, Expr::cmpeq(lhs, rhs)?))
+}
 
-        block.assign(scalar("esp", 32), expr_scalar("ebp", 32));
-        let ebp = pop_value(&mut block, 32)?;
-        block.assign(scalar("ebp", 32), ebp);
 
-        block.index()
-    };
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
+pub fn pcmpeqd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 32, |lhs, rhs|
+        Expr::sext(32, Expr::cmpeq(lhs, rhs)?))
+}
 
-    Ok(())
+
+
+pub fn pcmpeqw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 16, |lhs, rhs|
+        Expr::sext(16, Expr::cmpeq(lhs, rhs)?))
 }
 
 
 
-pub fn lodsb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn pcmpgtb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 8, |lhs, rhs|
+        Expr::sext(8, Expr::cmplts(rhs, lhs)?))
+}
 
-    let head_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
 
-        get_register(x86_reg::X86_REG_AL)?.set(&mut block, rhs)?;
+pub fn pcmpgtd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 32, |lhs, rhs|
+        Expr::sext(32, Expr::cmplts(rhs, lhs)?))
+}
 
-        block.index()
-    };
 
-    let inc_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        block.assign(
-            scalar("esi", 32),
-            Expr::add(expr_scalar("esi", 32), expr_const(1, 32))?
-        );
+pub fn pcmpgtw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 16, |lhs, rhs|
+        Expr::sext(16, Expr::cmplts(rhs, lhs)?))
+}
 
-        block.index()
-    };
 
-    let dec_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        block.assign(
-            scalar("esi", 32),
-            Expr::sub(expr_scalar("esi", 32), expr_const(1, 32))?
-        );
+pub fn pmaxsw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 16, |lhs, rhs|
+        select(Expr::cmplts(lhs.clone(), rhs.clone())?, rhs, lhs))
+}
 
-        block.index()
-    };
 
-    let tail_index = {
-        control_flow_graph.new_block()?.index()
-    };
 
+pub fn pmaxub(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 8, |lhs, rhs|
+        select(Expr::cmpltu(lhs.clone(), rhs.clone())?, rhs, lhs))
+}
 
-    control_flow_graph.conditional_edge(
-        head_index,
-        inc_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(0, 1))?
-    )?;
 
-    control_flow_graph.conditional_edge(
-        head_index,
-        dec_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(1, 1))?
-    )?;
 
-    control_flow_graph.unconditional_edge(inc_index, tail_index)?;
-    control_flow_graph.unconditional_edge(dec_index, tail_index)?;
+pub fn pminsw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 16, |lhs, rhs|
+        select(Expr::cmplts(lhs.clone(), rhs.clone())?, lhs, rhs))
+}
 
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(tail_index)?;
 
-    Ok(())
+
+pub fn pminub(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 8, |lhs, rhs|
+        select(Expr::cmpltu(lhs.clone(), rhs.clone())?, lhs, rhs))
 }
 
 
 
-pub fn loop_(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn pmovmskb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let ecx = scalar("ecx", 32);
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let bits = detail.operands[0].size as usize * 8;
+
+        // gather the sign bit of each byte
+        let mut mask = expr_const(0, bits);
+        for (i, byte) in vector_lanes(src, 8)?.into_iter().enumerate() {
+            let sign = Expr::trun(1, Expr::shr(byte, expr_const(7, 8))?)?;
+            let sign = Expr::shl(Expr::zext(bits, sign)?, expr_const(i as u64, bits))?;
+            mask = Expr::or(mask, sign)?;
+        }
 
-        block.assign(ecx.clone(), Expr::sub(ecx.clone().into(), expr_const(1, ecx.bits()))?);
+        operand_store(mode, &mut block, &detail.operands[0], mask)?;
 
         block.index()
     };
@@ -1993,15 +4670,49 @@ pub fn loop_(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) ->
 
 
 
-pub fn mov(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn pop(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
+    // create a block for this instruction
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_load(&mut block, &detail.operands[1])?;
+        let value = match detail.operands[0].type_ {
+            x86_op_type::X86_OP_MEM => pop_value(mode, &mut block, detail.operands[0].size as usize * 8)?,
+            x86_op_type::X86_OP_REG => 
+                pop_value(mode, &mut block, get_register(mode, *detail.operands[0].reg())?.bits())?,
+            _ => bail!("invalid op type for `pop` instruction")
+        };
+
+        operand_store(mode, &mut block, &detail.operands[0], value)?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    POPAD pops edi, esi, ebp, ebx, edx, ecx and eax, discarding the esp saved
+    by pushad. POPA does the same with the 16-bit registers.
+*/
+pub fn popad(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
 
-        operand_store(&mut block, &detail.operands[0], src)?;
+        for register in push_all_registers(instruction).iter().rev() {
+            let register = get_register(mode, *register)?;
+            let value = pop_value(mode, &mut block, register.bits())?;
+            match register.capstone_reg {
+                x86_reg::X86_REG_SP | x86_reg::X86_REG_ESP => {},
+                _ => register.set(&mut block, value)?
+            }
+        }
 
         block.index()
     };
@@ -2014,88 +4725,86 @@ pub fn mov(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn movs(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+/*
+    POPCNT counts the set bits of the source. ZF is set if the source is 0,
+    and the other flags are cleared.
+*/
+pub fn popcnt(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    let head_index = {
+    let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_load(&mut block, &detail.operands[1])?;
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
+        let bits = src.bits();
+
+        let mut count = expr_const(0, bits);
+        for i in 0..bits {
+            let bit = Expr::and(
+                Expr::shr(src.clone(), expr_const(i as u64, bits))?,
+                expr_const(1, bits)
+            )?;
+            count = Expr::add(count, bit)?;
+        }
 
-        operand_store(&mut block, &detail.operands[0], src.clone())?;
+        set_zf(&mut block, src)?;
+        for flag in ["AF", "CF", "OF", "PF", "SF"].iter() {
+            block.assign(scalar(*flag, 1), expr_const(0, 1));
+        }
 
-        block.assign(
-            scalar("esi", 32),
-            Expr::add(expr_scalar("esi", 32), expr_const((src.bits() / 8) as u64, 32))?
-        );
-        block.assign(
-            scalar("edi", 32),
-            Expr::add(expr_scalar("edi", 32), expr_const((src.bits() / 8) as u64, 32))?
-        );
+        operand_store(mode, &mut block, &detail.operands[0], count)?;
 
         block.index()
     };
 
-    let inc_index = {
-        let mut block = control_flow_graph.new_block()?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-        block.assign(
-            scalar("esi", 32),
-            Expr::add(expr_scalar("esi", 32), expr_const(1, 32))?
-        );
+    Ok(())
+}
 
-        block.index()
-    };
 
-    let dec_index = {
+
+/*
+    POPFD pops eflags off the stack. POPF pops the low 16 bits, and POPFQ all
+    64 bits.
+*/
+pub fn popfd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        block.assign(
-            scalar("esi", 32),
-            Expr::sub(expr_scalar("esi", 32), expr_const(1, 32))?
-        );
+        let value = pop_value(mode, &mut block, flags_bits(instruction))?;
+        set_flags(&mut block, value)?;
 
         block.index()
     };
 
-    let tail_index = {
-        control_flow_graph.new_block()?.index()
-    };
-
-
-    control_flow_graph.conditional_edge(
-        head_index,
-        inc_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(0, 1))?
-    )?;
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
 
-    control_flow_graph.conditional_edge(
-        head_index,
-        dec_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(1, 1))?
-    )?;
+    Ok(())
+}
 
-    control_flow_graph.unconditional_edge(inc_index, tail_index)?;
-    control_flow_graph.unconditional_edge(dec_index, tail_index)?;
 
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(tail_index)?;
 
-    Ok(())
+pub fn por(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_bitwise(mode, control_flow_graph, instruction, Expr::or)
 }
 
 
 
-pub fn movsx(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn pshufd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_load(&mut block, &detail.operands[1])?;
-        let value = Expr::sext((detail.operands[0].size as usize) * 8, src)?;
+        let src = vector_lanes(operand_load(mode, &mut block, &detail.operands[1])?, 32)?;
+        let order = detail.operands[2].imm() as usize;
 
-        operand_store(&mut block, &detail.operands[0], value)?;
+        let lanes = (0..4).map(|i| src[(order >> (i * 2)) & 3].clone()).collect();
+
+        operand_store(mode, &mut block, &detail.operands[0], vector_join(lanes)?)?;
 
         block.index()
     };
@@ -2108,16 +4817,25 @@ pub fn movsx(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::
 
 
 
-pub fn movzx(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn pslld(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 32, true, false)
+}
+
+
+
+pub fn pslldq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_load(&mut block, &detail.operands[1])?;
-        let value = Expr::zext((detail.operands[0].size as usize) * 8, src)?;
+        let vector = operand_load(mode, &mut block, &detail.operands[0])?;
 
-        operand_store(&mut block, &detail.operands[0], value)?;
+        // the count is in bytes, and counts above 15 clear the register
+        let count = cmp::min(detail.operands[1].imm() as u64, 16) * 8;
+        let vector = Expr::shl(vector, expr_const(count, 128))?;
+
+        operand_store(mode, &mut block, &detail.operands[0], vector)?;
 
         block.index()
     };
@@ -2130,52 +4848,49 @@ pub fn movzx(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::
 
 
 
-pub fn mul(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn psllq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 64, true, false)
+}
 
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        let rhs = operand_load(&mut block, &detail.operands[0])?;
 
-        let lhs = match rhs.bits() {
-            8 => get_register(x86_reg::X86_REG_AL)?.get()?,
-            16 => get_register(x86_reg::X86_REG_AX)?.get()?,
-            32 => get_register(x86_reg::X86_REG_EAX)?.get()?,
-            _ => bail!("invalid bit-width for mul")
-        };
+pub fn psllw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 16, true, false)
+}
 
-        let bit_width = rhs.bits() * 2;
-        let result = block.temp(bit_width);
-        let expr = Expr::mul(Expr::zext(bit_width, lhs)?, Expr::zext(bit_width, rhs.clone())?)?;
-        block.assign(result.clone(), expr);
 
-        match rhs.bits() {
-            8 => {
-                let ax = get_register(x86_reg::X86_REG_AX)?;
-                ax.set(&mut block, result.into())?;
-                let expr = Expr::cmpeq(get_register(x86_reg::X86_REG_AH)?.get()?, expr_const(0, 8))?;
-                block.assign(scalar("ZF", 1), expr);
-                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
-            },
-            16 => {
-                let dx = get_register(x86_reg::X86_REG_DX)?;
-                let ax = get_register(x86_reg::X86_REG_AX)?;
-                dx.set(&mut block, Expr::trun(16, Expr::shr(result.clone().into(), expr_const(16, 32))?)?)?;
-                ax.set(&mut block, Expr::trun(16, result.into())?)?;
-                block.assign(scalar("ZF", 1), Expr::cmpeq(dx.get()?, expr_const(0, 16))?);
-                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
-            },
-            32 => {
-                let edx = get_register(x86_reg::X86_REG_EDX)?;
-                let eax = get_register(x86_reg::X86_REG_EAX)?;
-                edx.set(&mut block, Expr::trun(32, Expr::shr(result.clone().into(), expr_const(32, 64))?)?)?;
-                eax.set(&mut block, Expr::trun(32, result.into())?)?;
-                block.assign(scalar("ZF", 1), Expr::cmpeq(edx.get()?, expr_const(0, 32))?);
-                block.assign(scalar("CF", 1), expr_scalar("ZF", 1));
-            },
-            _ => bail!("invalid bit-width for mul")
-        }
+
+pub fn psrad(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 32, false, true)
+}
+
+
+
+pub fn psraw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 16, false, true)
+}
+
+
+
+pub fn psrld(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 32, false, false)
+}
+
+
+
+pub fn psrldq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let vector = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        // the count is in bytes, and counts above 15 clear the register
+        let count = cmp::min(detail.operands[1].imm() as u64, 16) * 8;
+        let vector = Expr::shr(vector, expr_const(count, 128))?;
+
+        operand_store(mode, &mut block, &detail.operands[0], vector)?;
 
         block.index()
     };
@@ -2188,99 +4903,99 @@ pub fn mul(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn neg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn psrlq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 64, false, false)
+}
 
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        let dst = operand_load(&mut block, &detail.operands[0])?;
 
-        let result = block.temp(dst.bits());
+pub fn psrlw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_shift(mode, control_flow_graph, instruction, 16, false, false)
+}
 
-        block.assign(scalar("CF", 1), Expr::cmpneq(dst.clone().into(), expr_const(0, dst.bits()))?);
-        block.assign(result.clone(), Expr::sub(expr_const(0, dst.bits()), dst.clone().into())?);
 
-        set_zf(&mut block, result.clone().into())?;
-        set_sf(&mut block, result.clone().into())?;
-        set_of(&mut block, result.clone().into(), expr_const(0, dst.bits()), dst.clone().into())?;
 
-        operand_store(&mut block, &detail.operands[0], result.clone().into())?;
+pub fn psubb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 8, Expr::sub)
+}
 
-        block.index()
-    };
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
 
-    Ok(())
+pub fn psubd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 32, Expr::sub)
 }
 
 
 
-pub fn nop(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
-    let block_index = {
-        control_flow_graph.new_block()?.index()
-    };
+pub fn psubq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 64, Expr::sub)
+}
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
 
-    Ok(())
+
+pub fn psubw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_lanewise(mode, control_flow_graph, instruction, 16, Expr::sub)
 }
 
 
 
-pub fn not(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn punpckhbw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 8, true)
+}
 
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        let dst = operand_load(&mut block, &detail.operands[0])?;
 
-        let expr = Expr::xor(dst.clone(), expr_const(!0, dst.bits()))?;
+pub fn punpckhdq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 32, true)
+}
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
 
-        block.index()
-    };
 
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(block_index)?;
+pub fn punpckhqdq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 64, true)
+}
 
-    Ok(())
+
+
+pub fn punpckhwd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 16, true)
 }
 
 
 
-pub fn or(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
+pub fn punpcklbw(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 8, false)
+}
 
-    let block_index = {
-        let mut block = control_flow_graph.new_block()?;
 
-        // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
 
-        let result = block.temp(lhs.bits());
+pub fn punpckldq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 32, false)
+}
 
-        if lhs.bits() != rhs.bits() {
-            rhs = Expr::sext(lhs.bits(), rhs)?;
-        }
 
-        // perform addition
-        block.assign(result.clone(), Expr::or(lhs.clone(), rhs.clone())?);
 
-        // calculate flags
-        set_zf(&mut block, result.clone().into())?;
-        set_sf(&mut block, result.clone().into())?;
-        block.assign(scalar("CF", 1), expr_const(0, 1));
-        block.assign(scalar("OF", 1), expr_const(0, 1));
+pub fn punpcklqdq(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 64, false)
+}
 
-        // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+
+
+pub fn punpcklwd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_unpack(mode, control_flow_graph, instruction, 16, false)
+}
+
+
+
+pub fn push(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let detail = try!(details(instruction));
+
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let value = operand_load(mode, &mut block, &detail.operands[0])?;
+
+        push_value(mode, &mut block, value)?;
 
         block.index()
     };
@@ -2293,21 +5008,28 @@ pub fn or(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Ins
 
 
 
-pub fn pop(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
-    // create a block for this instruction
+/*
+    PUSHAD pushes eax, ecx, edx, ebx, the original esp, ebp, esi and edi.
+    PUSHA does the same with the 16-bit registers.
+*/
+pub fn pushad(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let value = match detail.operands[0].type_ {
-            x86_op_type::X86_OP_MEM => pop_value(&mut block, detail.operands[0].size as usize * 8)?,
-            x86_op_type::X86_OP_REG => 
-                pop_value(&mut block, get_register(*detail.operands[0].reg())?.bits())?,
-            _ => bail!("invalid op type for `pop` instruction")
-        };
+        let registers = push_all_registers(instruction);
 
-        operand_store(&mut block, &detail.operands[0], value)?;
+        // the stack pointer is pushed as it was before the first push
+        let sp = get_register(mode, registers[4])?;
+        let original_sp = block.temp(sp.bits());
+        block.assign(original_sp.clone(), sp.get()?);
+
+        for register in registers.iter() {
+            let value = match *register {
+                x86_reg::X86_REG_SP | x86_reg::X86_REG_ESP => original_sp.clone().into(),
+                _ => get_register(mode, *register)?.get()?
+            };
+            push_value(mode, &mut block, value)?;
+        }
 
         block.index()
     };
@@ -2320,15 +5042,15 @@ pub fn pop(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn push(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
-    let detail = try!(details(instruction));
-
+/*
+    PUSHFD pushes eflags onto the stack. PUSHF pushes the low 16 bits, and
+    PUSHFQ all 64 bits.
+*/
+pub fn pushfd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let value = operand_load(&mut block, &detail.operands[0])?;
-
-        push_value(&mut block, value)?;
+        push_value(mode, &mut block, flags_value(flags_bits(instruction))?)?;
 
         block.index()
     };
@@ -2341,22 +5063,69 @@ pub fn push(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn ret(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn pxor(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    sse_bitwise(mode, control_flow_graph, instruction, Expr::xor)
+}
+
+
+
+/*
+    RCL rotates the operand and CF together to the left.
+*/
+pub fn rcl(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    rotate_carry(mode, control_flow_graph, instruction, true)
+}
+
+
+
+/*
+    RCR rotates the operand and CF together to the right.
+*/
+pub fn rcr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    rotate_carry(mode, control_flow_graph, instruction, false)
+}
+
+
+
+/*
+    RDTSC loads the time-stamp counter into edx:eax. The platform decides how
+    time passes, so we raise to it.
+*/
+pub fn rdtsc(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    raise(control_flow_graph, expr_scalar("rdtsc", 1))
+}
+
+
+
+pub fn ret(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let value = pop_value(&mut block, 32)?;
+        let value = pop_value(mode, &mut block, mode.bits())?;
 
         if detail.op_count == 1 {
-            let imm = operand_load(&mut block, &detail.operands[0])?;
-            block.assign(scalar("esp", 32), Expr::add(expr_scalar("esp", 32), imm)?);
+            let imm = operand_load(mode, &mut block, &detail.operands[0])?;
+            let imm = if imm.bits() < mode.bits() {
+                Expr::zext(mode.bits(), imm)?
+            }
+            else {
+                imm
+            };
+            let sp = mode.sp();
+            sp.set(&mut block, Expr::add(sp.get()?, imm)?)?;
         }
 
-        block.assign(scalar("eip", 32), value);
-
-        block.brc(expr_scalar("eip", 32), expr_const(1, 1));
+        match mode {
+            Mode::X86 => {
+                block.assign(scalar("eip", 32), value);
+                block.brc(expr_scalar("eip", 32), expr_const(1, 1));
+            },
+            // rip is replaced with the address of the next instruction once
+            // lifting completes, so branch on the popped value directly
+            Mode::Amd64 => block.brc(value, expr_const(1, 1))
+        }
 
         block.index()
     };
@@ -2369,15 +5138,15 @@ pub fn ret(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn rol(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn rol(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let count = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let count = operand_load(mode, &mut block, &detail.operands[1])?;
 
         let mut count = Expr::and(count.clone(), expr_const(0x1f, count.bits()))?;
 
@@ -2411,7 +5180,7 @@ pub fn rol(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         // SF/ZF are unaffected
 
         // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
 
         block.index()
     };
@@ -2424,15 +5193,15 @@ pub fn rol(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn ror(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn ror(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let count = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let count = operand_load(mode, &mut block, &detail.operands[1])?;
 
         let mut count = Expr::and(count.clone(), expr_const(0x1f, count.bits()))?;
 
@@ -2466,7 +5235,29 @@ pub fn ror(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         // SF/ZF are unaffected
 
         // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    SAHF stores ah into SF, ZF, AF, PF and CF.
+*/
+pub fn sahf(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let ah = block.temp(8);
+        block.assign(ah.clone(), get_register(mode, x86_reg::X86_REG_AH)?.get()?);
+        set_flags(&mut block, ah.into())?;
 
         block.index()
     };
@@ -2479,15 +5270,15 @@ pub fn ror(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn sar(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn sar(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         if lhs.bits() != rhs.bits() {
             rhs = Expr::zext(lhs.bits(), rhs)?;
@@ -2519,7 +5310,7 @@ pub fn sar(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         set_sf(&mut block, temp.clone().into())?;
         set_cf(&mut block, temp.clone().into(), lhs.clone())?;
 
-        operand_store(&mut block, &detail.operands[0], temp.into())?;
+        operand_store(mode, &mut block, &detail.operands[0], temp.into())?;
 
         block.index()
     };
@@ -2532,15 +5323,15 @@ pub fn sar(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn sbb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn sbb(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         if lhs.bits() != rhs.bits() {
             rhs = Expr::sext(lhs.bits(), rhs)?;
@@ -2554,10 +5345,11 @@ pub fn sbb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         set_zf(&mut block, expr.clone())?;
         set_sf(&mut block, expr.clone())?;
         set_of(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
         set_cf(&mut block, expr.clone(), lhs.clone())?;
 
         // store result
-        operand_store(&mut block, &detail.operands[0], expr)?;
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
 
         block.index()
     };
@@ -2570,15 +5362,15 @@ pub fn sbb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn scasb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn scas(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    let head_index = {
+    let (head_index, bits) = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         let expr = Expr::sub(lhs.clone(), rhs.clone())?;
 
@@ -2586,70 +5378,26 @@ pub fn scasb(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::
         set_zf(&mut block, expr.clone())?;
         set_sf(&mut block, expr.clone())?;
         set_of(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, expr.clone(), lhs.clone(), rhs.clone())?;
         set_cf(&mut block, expr.clone(), lhs.clone())?;
 
-        block.index()
-    };
-
-    let inc_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        block.assign(
-            scalar("edi", 32),
-            Expr::add(expr_scalar("edi", 32), expr_const(1, 32))?
-        );
-
-        block.index()
-    };
-
-    let dec_index = {
-        let mut block = control_flow_graph.new_block()?;
-
-        block.assign(
-            scalar("edi", 32),
-            Expr::sub(expr_scalar("edi", 32), expr_const(1, 32))?
-        );
-
-        block.index()
-    };
-
-    let tail_index = {
-        control_flow_graph.new_block()?.index()
+        (block.index(), lhs.bits())
     };
 
-    control_flow_graph.conditional_edge(
-        head_index,
-        inc_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(0, 1))?
-    )?;
-
-    control_flow_graph.conditional_edge(
-        head_index,
-        dec_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(1, 1))?
-    )?;
-
-    control_flow_graph.unconditional_edge(inc_index, tail_index)?;
-    control_flow_graph.unconditional_edge(dec_index, tail_index)?;
-
-    control_flow_graph.set_entry(head_index)?;
-    control_flow_graph.set_exit(tail_index)?;
-
-
-    Ok(())
+    string_step(control_flow_graph, head_index, (bits / 8) as u64, &[mode.di()])
 }
 
 
 
-pub fn setcc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn setcc(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
-        let expr = cc_condition(instruction)?;
+        let expr = cc_condition(mode, instruction)?;
 
-        operand_store(&mut block, &detail.operands[0], Expr::zext(8, expr)?)?;
+        operand_store(mode, &mut block, &detail.operands[0], Expr::zext(8, expr)?)?;
 
         block.index()
     };
@@ -2662,15 +5410,15 @@ pub fn setcc(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::
 
 
 
-pub fn shl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn shl(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         if lhs.bits() != rhs.bits() {
             rhs = Expr::zext(lhs.bits(), rhs)?;
@@ -2707,7 +5455,7 @@ pub fn shl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         set_zf(&mut block, expr.clone())?;
         set_sf(&mut block, expr.clone())?;
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
 
         block.index()
     };
@@ -2720,15 +5468,15 @@ pub fn shl(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn shr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn shr(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         if lhs.bits() != rhs.bits() {
             rhs = Expr::zext(lhs.bits(), rhs)?;
@@ -2758,7 +5506,7 @@ pub fn shr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         set_zf(&mut block, expr.clone())?;
         set_sf(&mut block, expr.clone())?;
 
-        operand_store(&mut block, &detail.operands[0], expr)?;
+        operand_store(mode, &mut block, &detail.operands[0], expr)?;
 
         block.index()
     };
@@ -2771,16 +5519,16 @@ pub fn shr(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn shld(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn shld(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let dst = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
-        let count = operand_load(&mut block, &detail.operands[2])?;
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+        let count = operand_load(mode, &mut block, &detail.operands[2])?;
 
         let tmp = Expr::or(
             Expr::shl(Expr::zext(dst.bits() * 2, dst.clone())?, expr_const(dst.bits() as u64, dst.bits() * 2))?,
@@ -2805,7 +5553,7 @@ pub fn shld(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
         set_zf(&mut block, result.clone())?;
         set_sf(&mut block, result.clone())?;
 
-        operand_store(&mut block, &detail.operands[0], Expr::trun(dst.bits(), result)?)?;
+        operand_store(mode, &mut block, &detail.operands[0], Expr::trun(dst.bits(), result)?)?;
 
         block.index()
     };
@@ -2818,16 +5566,16 @@ pub fn shld(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn shrd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn shrd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let dst = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
-        let count = operand_load(&mut block, &detail.operands[2])?;
+        let dst = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
+        let count = operand_load(mode, &mut block, &detail.operands[2])?;
 
         let tmp = Expr::or(
             Expr::zext(dst.bits() * 2, dst.clone())?,
@@ -2852,7 +5600,7 @@ pub fn shrd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
         set_zf(&mut block, result.clone())?;
         set_sf(&mut block, result.clone())?;
 
-        operand_store(&mut block, &detail.operands[0], Expr::trun(dst.bits(), result)?)?;
+        operand_store(mode, &mut block, &detail.operands[0], Expr::trun(dst.bits(), result)?)?;
 
         block.index()
     };
@@ -2865,7 +5613,7 @@ pub fn shrd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn stc(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn stc(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
@@ -2882,7 +5630,7 @@ pub fn stc(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn std(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn std(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
@@ -2899,7 +5647,7 @@ pub fn std(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn sti(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn sti(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
@@ -2916,70 +5664,26 @@ pub fn sti(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Re
 
 
 
-pub fn stos(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn stos(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
-    // create a block for this instruction
-    let (block_index, bits) = {
+    let (head_index, bits) = {
         let mut block = control_flow_graph.new_block()?;
 
-        let src = operand_load(&mut block, &detail.operands[1])?;
-
+        let src = operand_load(mode, &mut block, &detail.operands[1])?;
         let bits = src.bits();
 
-        operand_store(&mut block, &detail.operands[0], src)?;
-
-        (block.index(), bits as u64)
-    };
-
-    let inc_index = {
-        let mut inc_block = control_flow_graph.new_block()?;;
-
-        inc_block.assign(
-            scalar("edi", 32),
-            Expr::add(expr_scalar("edi", 32), expr_const(bits / 8, 32))?
-        );
-
-        inc_block.index()
-    };
-
-    let dec_index = {
-        let mut dec_block = control_flow_graph.new_block()?;;
-
-        dec_block.assign(
-            scalar("edi", 32),
-            Expr::sub(expr_scalar("edi", 32), expr_const(bits / 8, 32))?
-        );
-
-        dec_block.index()
-    };
+        operand_store(mode, &mut block, &detail.operands[0], src)?;
 
-    let terminating_index = {
-        control_flow_graph.new_block()?.index()
+        (block.index(), bits)
     };
 
-    control_flow_graph.conditional_edge(
-        block_index,
-        inc_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(0, 1))?
-    )?;
-    control_flow_graph.conditional_edge(
-        block_index,
-        dec_index,
-        Expr::cmpeq(expr_scalar("DF", 1), expr_const(1, 1))?
-    )?;
-    control_flow_graph.unconditional_edge(inc_index, terminating_index)?;
-    control_flow_graph.unconditional_edge(dec_index, terminating_index)?;
-
-    control_flow_graph.set_entry(block_index)?;
-    control_flow_graph.set_exit(terminating_index)?;
-
-    Ok(())
+    string_step(control_flow_graph, head_index, (bits / 8) as u64, &[mode.di()])
 }
 
 
 
-pub fn sub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn sub(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     // create a block for this instruction
@@ -2987,8 +5691,8 @@ pub fn sub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         if lhs.bits() != rhs.bits() {
             rhs = Expr::sext(lhs.bits(), rhs)?;
@@ -3001,10 +5705,36 @@ pub fn sub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         set_zf(&mut block, result.clone().into())?;
         set_sf(&mut block, result.clone().into())?;
         set_of(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
         set_cf(&mut block, result.clone().into(), lhs.clone())?;
 
         // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+pub fn syscall(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        // In 64-bit mode, syscall saves the return address in rcx and rflags
+        // in r11
+        if mode == Mode::Amd64 {
+            let next_address = instruction.address + instruction.size as u64;
+            get_register(mode, x86_reg::X86_REG_RCX)?.set(&mut block, expr_const(next_address, 64))?;
+            get_register(mode, x86_reg::X86_REG_R11)?.set(&mut block, flags_value(64)?)?;
+        }
+
+        block.raise(expr_scalar("syscall", 1));
 
         block.index()
     };
@@ -3017,7 +5747,7 @@ pub fn sub(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
 
 
 
-pub fn sysenter(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+pub fn sysenter(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
     // create a block for this instruction
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
@@ -3036,15 +5766,15 @@ pub fn sysenter(control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr)
 
 
 
-pub fn test(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn test(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         let expr = Expr::and(lhs.clone(), rhs.clone())?;
 
@@ -3065,15 +5795,34 @@ pub fn test(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn xadd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+/*
+    TZCNT counts the trailing zero bits of the source. CF is set if the source
+    is 0, and ZF is set if the count is 0.
+*/
+pub fn tzcnt(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+    zero_count(mode, control_flow_graph, instruction, false)
+}
+
+
+
+/*
+    UD2 raises an invalid opcode exception.
+*/
+pub fn ud2(_: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    raise(control_flow_graph, expr_scalar("ud2", 1))
+}
+
+
+
+pub fn xadd(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         let result = block.temp(lhs.bits());
 
@@ -3084,11 +5833,12 @@ pub fn xadd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
         set_zf(&mut block, result.clone().into())?;
         set_sf(&mut block, result.clone().into())?;
         set_of(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
+        set_af(&mut block, result.clone().into(), lhs.clone(), rhs.clone())?;
         set_cf(&mut block, result.clone().into(), lhs.clone())?;
 
         // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
-        operand_store(&mut block, &detail.operands[1], rhs)?;
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
+        operand_store(mode, &mut block, &detail.operands[1], rhs)?;
 
         block.index()
     };
@@ -3101,21 +5851,47 @@ pub fn xadd(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn xchg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn xchg(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     let block_index = {
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         let tmp = block.temp(lhs.bits());
         block.assign(tmp.clone(), lhs.clone());
 
-        operand_store(&mut block, &detail.operands[0], rhs)?;
-        operand_store(&mut block, &detail.operands[1], tmp.into())?;
+        operand_store(mode, &mut block, &detail.operands[0], rhs)?;
+        operand_store(mode, &mut block, &detail.operands[1], tmp.into())?;
+
+        block.index()
+    };
+
+    control_flow_graph.set_entry(block_index)?;
+    control_flow_graph.set_exit(block_index)?;
+
+    Ok(())
+}
+
+
+
+/*
+    XLAT loads al with the byte at ebx + al, from a table of up to 256 bytes.
+*/
+pub fn xlat(mode: Mode, control_flow_graph: &mut ControlFlowGraph, _: &capstone::Instr) -> Result<()> {
+    let block_index = {
+        let mut block = control_flow_graph.new_block()?;
+
+        let al = get_register(mode, x86_reg::X86_REG_AL)?;
+        let bx = mode.full_register(x86_reg::X86_REG_EBX, x86_reg::X86_REG_RBX);
+
+        let address = Expr::add(bx.get()?, Expr::zext(mode.bits(), al.get()?)?)?;
+        let temp = block.temp(8);
+        block.load(temp.clone(), address, array("mem", MEM_SIZE));
+        al.set(&mut block, temp.into())?;
 
         block.index()
     };
@@ -3128,7 +5904,7 @@ pub fn xchg(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::I
 
 
 
-pub fn xor(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
+pub fn xor(mode: Mode, control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::Instr) -> Result<()> {
     let detail = try!(details(instruction));
 
     // create a block for this instruction
@@ -3136,8 +5912,8 @@ pub fn xor(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         let mut block = control_flow_graph.new_block()?;
 
         // get operands
-        let lhs = operand_load(&mut block, &detail.operands[0])?;
-        let mut rhs = operand_load(&mut block, &detail.operands[1])?;
+        let lhs = operand_load(mode, &mut block, &detail.operands[0])?;
+        let mut rhs = operand_load(mode, &mut block, &detail.operands[1])?;
 
         if lhs.bits() != rhs.bits() {
             rhs = Expr::sext(lhs.bits(), rhs)?;
@@ -3153,7 +5929,7 @@ pub fn xor(control_flow_graph: &mut ControlFlowGraph, instruction: &capstone::In
         block.assign(scalar("OF", 1), expr_const(0, 1));;
 
         // store result
-        operand_store(&mut block, &detail.operands[0], result.into())?;
+        operand_store(mode, &mut block, &detail.operands[0], result.into())?;
 
         block.index()
     };